@@ -4,5 +4,7 @@ mod benchmarks;
 
 criterion_main!(
     benchmarks::randomwalk::benches,
-    benchmarks::manybits::benches
+    benchmarks::manybits::benches,
+    benchmarks::parallel::benches,
+    benchmarks::permcache::benches
 );
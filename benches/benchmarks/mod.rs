@@ -1,2 +1,4 @@
 pub mod manybits;
+pub mod parallel;
+pub mod permcache;
 pub mod randomwalk;
@@ -0,0 +1,48 @@
+use criterion::{criterion_group, Criterion};
+use q1tsim::circuit::Circuit;
+
+fn build_parallel_circuit(nr_bits: usize, nr_layers: usize) -> q1tsim::error::Result<Circuit>
+{
+    let mut circuit = Circuit::new(nr_bits, nr_bits);
+
+    for i in 0..nr_layers
+    {
+        for bit in 0..nr_bits
+        {
+            circuit.ry(0.1 * (i + bit) as f64, bit)?;
+        }
+        for bit in 0..nr_bits-1
+        {
+            circuit.cx(bit, bit+1)?;
+        }
+    }
+
+    let bits: Vec<usize> = (0..nr_bits).collect();
+    circuit.measure_all(&bits)?;
+
+    Ok(circuit)
+}
+
+fn sequential(nr_bits: usize, nr_layers: usize, nr_shots: usize)
+{
+    let mut circuit = build_parallel_circuit(nr_bits, nr_layers).expect("Failed to build circuit");
+    circuit.with_seed(0x1f67a51423cd2615);
+    circuit.execute(nr_shots).expect("Failed to execute circuit");
+}
+
+#[cfg(feature = "parallel")]
+fn parallel(nr_bits: usize, nr_layers: usize, nr_shots: usize)
+{
+    let mut circuit = build_parallel_circuit(nr_bits, nr_layers).expect("Failed to build circuit");
+    circuit.with_seed(0x1f67a51423cd2615);
+    circuit.run_shots_parallel(nr_shots).expect("Failed to execute circuit");
+}
+
+fn bench_parallel(c: &mut Criterion)
+{
+    c.bench_function("par sequential 10", |b| b.iter(|| sequential(10, 10, 10_000)));
+    #[cfg(feature = "parallel")]
+    c.bench_function("par parallel 10", |b| b.iter(|| parallel(10, 10, 10_000)));
+}
+
+criterion_group!(benches, bench_parallel);
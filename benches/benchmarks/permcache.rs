@@ -0,0 +1,43 @@
+use criterion::{criterion_group, Criterion};
+use q1tsim::circuit::{QuStateRepr, Circuit};
+
+use rand_core::SeedableRng;
+
+fn build_permcache_circuit(nr_bits: usize, nr_layers: usize) -> q1tsim::error::Result<Circuit>
+{
+    let mut circuit = Circuit::new(nr_bits, 3);
+
+    for _ in 0..nr_layers
+    {
+        circuit.h(0)?;
+        circuit.add_gate(q1tsim::gates::CCX::new(), &[0, 3, 5])?;
+        circuit.add_gate(q1tsim::gates::CCX::new(), &[5, 1, 7])?;
+        circuit.add_gate(q1tsim::gates::CSwap::new(), &[7, 2, 4])?;
+    }
+
+    circuit.measure(0, 0)?;
+    circuit.measure(5, 1)?;
+    circuit.measure(7, 2)?;
+
+    Ok(circuit)
+}
+
+fn permcache(nr_bits: usize, nr_layers: usize, nr_shots: usize, cache_size: usize)
+{
+    let mut rng = rand_hc::Hc128Rng::seed_from_u64(0x1f67a51423cd2615);
+
+    q1tsim::gates::set_bit_permutation_cache_size(cache_size);
+    let mut circuit = build_permcache_circuit(nr_bits, nr_layers).expect("Failed to build circuit");
+    let q_state = QuStateRepr::vector(nr_bits, nr_shots);
+    circuit.execute_with(nr_shots, &mut rng, q_state).expect("Failed to execute circuit");
+}
+
+fn bench_permcache(c: &mut Criterion)
+{
+    c.bench_function("pc uncached 10", |b| b.iter(|| permcache(10, 100, 100, 0)));
+    c.bench_function("pc cached 10", |b| b.iter(|| permcache(10, 100, 100, 16)));
+    c.bench_function("pc uncached 14", |b| b.iter(|| permcache(14, 100, 10, 0)));
+    c.bench_function("pc cached 14", |b| b.iter(|| permcache(14, 100, 10, 16)));
+}
+
+criterion_group!(benches, bench_permcache);
@@ -40,6 +40,8 @@ macro_rules! gen_derive
 gen_derive!(q1tsim::export, OpenQasm, open_qasm_derive);
 gen_derive!(q1tsim::export, CQasm, c_qasm_derive);
 gen_derive!(q1tsim::export, Latex, latex_derive);
+gen_derive!(q1tsim::export, TketJson, tket_json_derive);
+gen_derive!(q1tsim::instruction, ToInstruction, to_instruction_derive);
 gen_derive!(q1tsim::arithmetic, Square, square_derive);
 
 #[proc_macro_derive(ExportGate)]
@@ -52,6 +54,8 @@ pub fn export_gate_derive(input: proc_macro::TokenStream) -> proc_macro::TokenSt
         impl q1tsim::export::OpenQasm for #name {}
         impl q1tsim::export::CQasm for #name {}
         impl q1tsim::export::Latex for #name {}
+        impl q1tsim::export::TketJson for #name {}
+        impl q1tsim::instruction::ToInstruction for #name {}
     };
     gen.into()
 }
@@ -0,0 +1,788 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Quantum algorithms
+//!
+//! This module contains building blocks for common quantum algorithms, that
+//! can be added to an existing circuit.
+
+/// Quantum Fourier transform with options.
+///
+/// Add the quantum Fourier transform over the qubits in `qbits` to `circuit`,
+/// where the most significant qubit comes first. The transform is built from
+/// a QFT layer (see `Circuit::qft_layer()`) for every qubit, i.e. a Hadamard
+/// gate followed by controlled phase rotations `CR`<sub>`k`</sub> from the
+/// less significant qubits. Rotations with an angle smaller than `threshold`
+/// are left out; a threshold of zero results in the exact transform. When
+/// `swap` is `true`, the bit order is reversed at the end of the transform
+/// using swap gates, otherwise the result is left in reversed bit order. When
+/// `inverse` is `true`, the inverse of this transform is added instead. An
+/// `InvalidQBit` error is returned if any of the qubits is not in the circuit.
+pub fn qft_general(circuit: &mut crate::circuit::Circuit, qbits: &[usize], inverse: bool,
+    swap: bool, threshold: f64) -> crate::error::Result<()>
+{
+    if inverse
+    {
+        let mut forward = crate::circuit::Circuit::new(circuit.nr_qbits(), 0);
+        qft_general(&mut forward, qbits, false, swap, threshold)?;
+        let qubit_map: Vec<usize> = (0..circuit.nr_qbits()).collect();
+        return circuit.append_on_qubits(&forward.inverse()?, &qubit_map, &[]);
+    }
+
+    let n = qbits.len();
+    for j in 0..n
+    {
+        // The rotation controlled by the qubit at distance d is over π/2^d.
+        // All further rotations on this qubit are even smaller.
+        let nr_rotations = (1..n-j)
+            .take_while(|&d| ::std::f64::consts::PI / 2.0f64.powi(d as i32) >= threshold)
+            .count();
+        circuit.qft_layer(qbits[j], &qbits[j+1..j+1+nr_rotations])?;
+    }
+    if swap
+    {
+        for j in 0..n/2
+        {
+            circuit.swap(qbits[j], qbits[n-j-1])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Approximate quantum Fourier transform.
+///
+/// Add an approximate quantum Fourier transform for integers modulo
+/// `dimension` to `circuit`. The transform is performed on the `n =
+/// log₂(dimension)` least significant qubits of `bits`, i.e. on its last
+/// `n` elements, where the most significant qubit comes first. Only
+/// transforms over a power of two are supported; for other dimensions, an
+/// `InvalidDimension` error is returned. For e.g. Shor's algorithm, the
+/// transform over `N` can be replaced by the transform over the next power
+/// of two, in a register large enough to hold all values modulo `N`.
+///
+/// Controlled phase rotations with an angle smaller than `threshold` in
+/// absolute value are left out. Since the angles decrease exponentially, this
+/// leaves at most `O(log(1/threshold))` rotations per qubit, reducing the
+/// number of gates from `O(n²)` to `O(n log n)`. The error introduced by the
+/// approximation is less than `n·threshold` in operator norm. A threshold of
+/// zero results in the exact transform. The bit order is reversed at the end
+/// of the transform using swap gates. See `qft_general()`.
+///
+/// An `InvalidNrBits` error is returned if `bits` holds fewer than `n`
+/// qubits.
+pub fn qft_approx(circuit: &mut crate::circuit::Circuit, bits: &[usize], dimension: usize,
+    threshold: f64) -> crate::error::Result<()>
+{
+    if !dimension.is_power_of_two()
+    {
+        return Err(crate::error::Error::InvalidDimension(dimension));
+    }
+
+    let n = dimension.trailing_zeros() as usize;
+    if n > bits.len()
+    {
+        return Err(crate::error::Error::InvalidNrBits(bits.len(), n, String::from("QFT")));
+    }
+
+    qft_general(circuit, &bits[bits.len()-n..], false, true, threshold)
+}
+
+/// Quantum Fourier transform.
+///
+/// Add the quantum Fourier transform over the qubits in `qbits` to `circuit`,
+/// where the most significant qubit comes first. The transform is built from
+/// the standard decomposition into Hadamard gates and controlled phase
+/// rotations, after which the bit order is reversed using swap gates. When
+/// `inverse` is `true`, the inverse transform is added instead. An
+/// `InvalidQBit` error is returned if any of the qubits is not in the circuit.
+/// See `qft_general()`.
+pub fn qft(circuit: &mut crate::circuit::Circuit, qbits: &[usize], inverse: bool)
+    -> crate::error::Result<()>
+{
+    qft_general(circuit, qbits, inverse, true, 0.0)
+}
+
+/// Quantum Fourier transform circuit.
+///
+/// Create a new circuit on `n` qubits and no classical bits, performing the
+/// quantum Fourier transform over all its qubits. See `qft()` for details.
+pub fn qft_circuit(n: usize) -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(n, 0);
+    let qbits: Vec<usize> = (0..n).collect();
+    // All qubits are in the circuit, so this cannot fail
+    qft(&mut circuit, &qbits, false).unwrap();
+    circuit
+}
+
+/// Multi-controlled Z gate.
+///
+/// Flip the sign of the state in which all qubits in `qbits` are |1⟩. Up to
+/// three qubits, the `Z`, `CZ` and `CCZ` gates are used. For more qubits, a
+/// multi-controlled `X` gate on the last qubit is conjugated by Hadamard
+/// gates.
+fn multi_controlled_z(circuit: &mut crate::circuit::Circuit, qbits: &[usize])
+    -> crate::error::Result<()>
+{
+    match qbits.len()
+    {
+        0 => Ok(()),
+        1 => circuit.add_gate(crate::gates::Z::new(), qbits),
+        2 => circuit.add_gate(crate::gates::CZ::new(), qbits),
+        3 => circuit.add_gate(crate::gates::CCZ::new(), qbits),
+        _ => {
+            let (&target, controls) = qbits.split_last().unwrap();
+            circuit.h(target)?;
+            circuit.mcx(controls, target)?;
+            circuit.h(target)
+        }
+    }
+}
+
+/// Grover diffusion operator.
+///
+/// Add the inversion about the average `H`<sup>`⊗n`</sup>`(2|0⟩⟨0| -
+/// I)H`<sup>`⊗n`</sup> on the qubits in `qbits` to `circuit`, up to a global
+/// phase of `-1`. The reflection is implemented using `X` gates and a
+/// multi-controlled `Z` gate.
+pub fn grover_diffusion(circuit: &mut crate::circuit::Circuit, qbits: &[usize])
+    -> crate::error::Result<()>
+{
+    for &bit in qbits
+    {
+        circuit.h(bit)?;
+        circuit.x(bit)?;
+    }
+    multi_controlled_z(circuit, qbits)?;
+    for &bit in qbits
+    {
+        circuit.x(bit)?;
+        circuit.h(bit)?;
+    }
+
+    Ok(())
+}
+
+/// Phase flip oracle.
+///
+/// Add an oracle to `circuit` that flips the sign of the single basis state
+/// in which the qubits in `qbits` hold the value `target`, and leaves all
+/// other states unchanged. The first qubit in `qbits` corresponds to the most
+/// significant bit of `target`.
+pub fn grover_oracle_phase_flip(circuit: &mut crate::circuit::Circuit, qbits: &[usize],
+    target: u64) -> crate::error::Result<()>
+{
+    let n = qbits.len();
+    let zeros: Vec<usize> = (0..n).filter(|&i| (target >> (n - 1 - i)) & 1 == 0)
+        .map(|i| qbits[i])
+        .collect();
+    for &bit in zeros.iter()
+    {
+        circuit.x(bit)?;
+    }
+    multi_controlled_z(circuit, qbits)?;
+    for &bit in zeros.iter()
+    {
+        circuit.x(bit)?;
+    }
+
+    Ok(())
+}
+
+/// Amplitude estimation circuit.
+///
+/// Build a circuit estimating the amplitude `a = sin(θ)` of the good states
+/// in the state `A|0⟩`, where `A` is the unitary performed by circuit
+/// `state_prep`, and the good states are marked by circuit `oracle`, which
+/// should flip the sign of good states and leave the others unchanged. This
+/// is done by quantum phase estimation on the Grover operator `Q = A S₀ A†
+/// S`<sub>`χ`</sub>, where `S`<sub>`χ`</sub> is the oracle and `S₀ = 2|0⟩⟨0|
+/// - I`, which has eigenvalues `exp(±2iθ)` on the subspace containing `A|0⟩`.
+///
+/// The first `precision_bits` qubits of the resulting circuit make up the
+/// phase register, and the remaining qubits are the qubits of `state_prep`.
+/// The phase register is measured into the classical register, which holds
+/// `precision_bits` bits, such that the measured value is directly the phase
+/// estimate `y`. Use `extract_amplitude_estimate()` to decode the
+/// measurement results.
+///
+/// The controlled powers `Q`<sup>`2`<sup>`j`</sup></sup> are implemented as
+/// loops repeating a controlled Grover operator `2`<sup>`j`</sup> times, so
+/// the circuit grows linearly with the number of Grover iterations.
+/// Circuits `state_prep` and `oracle` should be purely unitary circuits on the
+/// same number of qubits. For other circuits, a `NotAGate` error is returned,
+/// while an `InvalidNrBits` error is returned when the number of qubits in
+/// `oracle` differs from that in `state_prep`.
+pub fn amplitude_estimation_circuit(state_prep: &crate::circuit::Circuit,
+    oracle: &crate::circuit::Circuit, precision_bits: usize)
+    -> crate::error::Result<crate::circuit::Circuit>
+{
+    use crate::gates::Gate;
+
+    let n = state_prep.nr_qbits();
+    if oracle.nr_qbits() != n
+    {
+        return Err(crate::error::Error::InvalidNrBits(oracle.nr_qbits(), n,
+            String::from("amplitude estimation oracle")));
+    }
+
+    // S₀ = 2|0⟩⟨0| - I = -X^⊗n C^(n-1)Z X^⊗n. The global phase matters, since
+    // the Grover operator is controlled.
+    let qbits: Vec<usize> = (0..n).collect();
+    let mut s_0 = crate::circuit::Circuit::new(n, 0);
+    for &bit in qbits.iter()
+    {
+        s_0.x(bit)?;
+    }
+    multi_controlled_z(&mut s_0, &qbits)?;
+    for &bit in qbits.iter()
+    {
+        s_0.x(bit)?;
+    }
+    s_0.add_gate(crate::gates::GlobalPhase::new(::std::f64::consts::PI), &[])?;
+
+    let a = crate::gates::Composite::from_circuit("A", state_prep)?;
+    let mut q = crate::gates::Composite::new("Q", n);
+    q.add_gate(crate::gates::Composite::from_circuit("S_chi", oracle)?, &qbits);
+    q.add_gate(a.adjoint(), &qbits);
+    q.add_gate(crate::gates::Composite::from_circuit("S_0", &s_0)?, &qbits);
+    q.add_gate(a.clone(), &qbits);
+
+    let mut cq = crate::gates::Composite::new("CQ", n + 1);
+    cq.add_gate(crate::gates::C::new(q), &(0..=n).collect::<Vec<usize>>());
+
+    let mut circuit = crate::circuit::Circuit::new(precision_bits + n, precision_bits);
+    let system: Vec<usize> = (precision_bits..precision_bits+n).collect();
+    circuit.add_gate(a, &system)?;
+
+    // Qubit 0 is the most significant bit of the phase register, so the
+    // last qubit controls Q, the one before that Q², etc.
+    let mut bits = vec![0; n + 1];
+    bits[1..].copy_from_slice(&system);
+    for j in 0..precision_bits
+    {
+        let control = precision_bits - j - 1;
+        circuit.h(control)?;
+        bits[0] = control;
+        let label = format!("cq{}", j);
+        circuit.add_gate(crate::gates::Loop::new(&label, 1 << j, cq.clone()), &bits)?;
+    }
+
+    // Since the eigenvalues of Q come in conjugate pairs, and the estimates
+    // for phases y and -y are the same, we can use the forward transform
+    // instead of the inverse.
+    let phase_bits: Vec<usize> = (0..precision_bits).collect();
+    qft_approx(&mut circuit, &phase_bits, 1 << precision_bits, 0.0)?;
+    for bit in 0..precision_bits
+    {
+        circuit.measure(bit, precision_bits - bit - 1)?;
+    }
+
+    Ok(circuit)
+}
+
+/// Quantum phase estimation.
+///
+/// Add the quantum phase estimation circuit for gate `unitary`, operating on
+/// the qubits in `target_bits`, to `circuit`. The phase is estimated in the
+/// register `counting_bits`, where the most significant qubit comes first.
+/// After Hadamard gates on the counting qubits, the `k`-th counting qubit
+/// from the end controls `U`<sup>`2`<sup>`k`</sup></sup>, after which the
+/// inverse quantum Fourier transform is applied to the counting register.
+/// When the target qubits are in an eigenstate of `unitary` with eigenvalue
+/// `exp(2πiφ)`, measuring the counting register then yields an estimate of
+/// `2`<sup>`n`</sup>`φ`, for `n` counting qubits.
+///
+/// The controlled powers of `unitary` are implemented as loops repeating the
+/// controlled gate, so the circuit holds `2`<sup>`n`</sup>` - 1` controlled
+/// applications of `unitary` in total. An `InvalidNrBits` error is returned
+/// if the number of qubits in `target_bits` does not match the number of
+/// qubits `unitary` operates on.
+pub fn quantum_phase_estimation(circuit: &mut crate::circuit::Circuit, counting_bits: &[usize],
+    target_bits: &[usize], unitary: &dyn crate::export::CircuitGate) -> crate::error::Result<()>
+{
+    let m = target_bits.len();
+    if unitary.nr_affected_bits() != m
+    {
+        return Err(crate::error::Error::InvalidNrBits(m, unitary.nr_affected_bits(),
+            String::from(unitary.description())));
+    }
+
+    for &bit in counting_bits
+    {
+        circuit.h(bit)?;
+    }
+
+    let mut cu = crate::gates::Composite::new(&format!("C{}", unitary.description()), m + 1);
+    cu.add_gate(crate::gates::C::new(unitary.clone_box()), &(0..=m).collect::<Vec<usize>>());
+
+    let n = counting_bits.len();
+    let mut bits = vec![0; m + 1];
+    bits[1..].copy_from_slice(target_bits);
+    for k in 0..n
+    {
+        bits[0] = counting_bits[n - k - 1];
+        let label = format!("cu{}", k);
+        circuit.add_gate(crate::gates::Loop::new(&label, 1 << k, cu.clone()), &bits)?;
+    }
+
+    qft(circuit, counting_bits, true)
+}
+
+/// Quantum teleportation circuit.
+///
+/// Create a circuit teleporting the state of qubit `data_qubit` to qubit
+/// `bob_qubit`. The circuit first prepares a Bell pair on qubits
+/// `alice_qubit` and `bob_qubit`, then measures `data_qubit` and
+/// `alice_qubit` in the Bell basis, into classical bits 0 and 1
+/// respectively. Depending on the outcomes, an `X` and `Z` gate are applied
+/// to `bob_qubit`, after which it holds the original state of
+/// `data_qubit`. The circuit holds enough qubits for the largest index
+/// given, and `nr_cbits` classical bits. The input state can be prepared
+/// by composing a preparation circuit of the same size with this circuit.
+///
+/// An `InvalidQBit` error is returned when the same qubit is used for more
+/// than one role, and an `InvalidNrBits` error when `nr_cbits` is less
+/// than two.
+pub fn teleportation_circuit(data_qubit: usize, alice_qubit: usize, bob_qubit: usize,
+    nr_cbits: usize) -> crate::error::Result<crate::circuit::Circuit>
+{
+    if alice_qubit == data_qubit
+    {
+        return Err(crate::error::Error::InvalidQBit(alice_qubit));
+    }
+    if bob_qubit == data_qubit || bob_qubit == alice_qubit
+    {
+        return Err(crate::error::Error::InvalidQBit(bob_qubit));
+    }
+    if nr_cbits < 2
+    {
+        return Err(crate::error::Error::InvalidNrBits(nr_cbits, 2,
+            String::from("teleportation")));
+    }
+
+    let nr_qbits = data_qubit.max(alice_qubit).max(bob_qubit) + 1;
+    let mut circuit = crate::circuit::Circuit::new(nr_qbits, nr_cbits);
+
+    circuit.h(alice_qubit)?;
+    circuit.cx(alice_qubit, bob_qubit)?;
+
+    circuit.cx(data_qubit, alice_qubit)?;
+    circuit.h(data_qubit)?;
+    circuit.measure(data_qubit, 0)?;
+    circuit.measure(alice_qubit, 1)?;
+
+    circuit.add_conditional_gate(&[1], 1, crate::gates::X::new(), &[bob_qubit])?;
+    circuit.add_conditional_gate(&[0], 1, crate::gates::Z::new(), &[bob_qubit])?;
+
+    Ok(circuit)
+}
+
+/// Decode an amplitude estimate.
+///
+/// Decode the results of running the amplitude estimation circuit built by
+/// `amplitude_estimation_circuit()` with `precision_bits` bits in the phase
+/// register, for `nr_shots` shots. The most likely phase estimate `y` out of
+/// the measurements in `histogram` is converted to the amplitude estimate
+/// `sin(πy/2`<sup>`m`</sup>`)`, where `m` is the number of precision bits.
+/// Phases `y` and `2`<sup>`m`</sup>` - y` lead to the same estimate, and their
+/// counts are combined. When no shots were made, zero is returned.
+pub fn extract_amplitude_estimate<S>(histogram: &::std::collections::HashMap<u64, usize, S>,
+    precision_bits: usize, nr_shots: usize) -> f64
+where S: ::std::hash::BuildHasher
+{
+    if nr_shots == 0
+    {
+        return 0.0;
+    }
+
+    let size = 1u64 << precision_bits;
+    let count = |y: u64| histogram.get(&y).cloned().unwrap_or(0);
+    (0..=size/2).map(|y| {
+            let total = if y == 0 || y == size - y { count(y) } else { count(y) + count(size - y) };
+            (total, y)
+        })
+        .max()
+        .map(|(_, y)| (::std::f64::consts::PI * y as f64 / size as f64).sin())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{amplitude_estimation_circuit, extract_amplitude_estimate, grover_diffusion,
+        grover_oracle_phase_flip, qft, qft_approx, qft_circuit, qft_general, quantum_phase_estimation,
+        teleportation_circuit};
+    use crate::circuit::Circuit;
+    use crate::gates::Gate;
+
+    /// The unitary matrix of the gates in circuit `circuit`
+    fn unitary(circuit: &Circuit) -> crate::cmatrix::CMatrix
+    {
+        crate::gates::Composite::from_circuit("U", circuit).unwrap().matrix()
+    }
+
+    /// The matrix of the exact quantum Fourier transform over `n` bits
+    fn qft_matrix(n: usize) -> crate::cmatrix::CMatrix
+    {
+        let size = 1 << n;
+        let norm = 1.0 / (size as f64).sqrt();
+        crate::cmatrix::CMatrix::from_shape_fn((size, size), |(j, k)| {
+            let angle = 2.0 * ::std::f64::consts::PI * ((j * k) % size) as f64 / size as f64;
+            crate::cmatrix::CNumber::from_polar(&norm, &angle)
+        })
+    }
+
+    /// The Frobenius norm of the difference of `a` and `b`, divided by the
+    /// square root of the dimension, so that it is bounded by the operator
+    /// norm of the difference
+    fn normalized_distance(a: &crate::cmatrix::CMatrix, b: &crate::cmatrix::CMatrix) -> f64
+    {
+        let sum: f64 = (a - b).iter().map(|x| x.norm_sqr()).sum();
+        (sum / a.rows() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_qft_approx_exact()
+    {
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(qft_approx(&mut circuit, &[0], 2, 0.0), Ok(()));
+        assert_complex_matrix_eq!(&unitary(&circuit), &crate::gates::H::new().matrix());
+
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(qft_approx(&mut circuit, &[0, 1, 2, 3], 16, 0.0), Ok(()));
+        assert_complex_matrix_eq!(&unitary(&circuit), &qft_matrix(4));
+
+        // Transform on the last three bits
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(qft_approx(&mut circuit, &[0, 1, 2, 3], 8, 0.0), Ok(()));
+        let expected = crate::cmatrix::kron_mat(&crate::cmatrix::CMatrix::eye(2), &qft_matrix(3));
+        assert_complex_matrix_eq!(&unitary(&circuit), &expected);
+
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(qft_approx(&mut circuit, &[0, 1, 2, 3], 5, 0.0),
+            Err(crate::error::Error::InvalidDimension(5)));
+        assert_eq!(qft_approx(&mut circuit, &[0, 1, 2, 3], 0, 0.0),
+            Err(crate::error::Error::InvalidDimension(0)));
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(qft_approx(&mut circuit, &[0, 1, 2], 16, 0.0),
+            Err(crate::error::Error::InvalidNrBits(3, 4, String::from("QFT"))));
+    }
+
+    #[test]
+    fn test_qft_approx_many_bits()
+    {
+        // The circuit is only built, not run
+        let n = 63;
+        let bits: Vec<usize> = (0..n).collect();
+        let mut circuit = Circuit::new(n, 0);
+        assert_eq!(qft_approx(&mut circuit, &bits, 1 << n, 0.0), Ok(()));
+        assert_eq!(circuit.to_instructions().len(), n + n * (n - 1) / 2 + n / 2);
+    }
+
+    #[test]
+    fn test_qft_approx_error()
+    {
+        let n = 5;
+        let bits: Vec<usize> = (0..n).collect();
+        let exact = qft_matrix(n);
+        for &threshold in [0.1, 0.2, 0.5, 1.0].iter()
+        {
+            let mut circuit = Circuit::new(n, 0);
+            assert_eq!(qft_approx(&mut circuit, &bits, 1 << n, threshold), Ok(()));
+            let distance = normalized_distance(&unitary(&circuit), &exact);
+            assert!(distance > 0.0);
+            assert!(distance <= n as f64 * threshold);
+        }
+    }
+
+    #[test]
+    fn test_qft_approx_size()
+    {
+        let n = 10;
+        let threshold = 0.01;
+        let bits: Vec<usize> = (0..n).collect();
+
+        let mut exact = Circuit::new(n, 0);
+        assert_eq!(qft_approx(&mut exact, &bits, 1 << n, 0.0), Ok(()));
+        let mut approx = Circuit::new(n, 0);
+        assert_eq!(qft_approx(&mut approx, &bits, 1 << n, threshold), Ok(()));
+
+        let nr_rotations = |circuit: &Circuit| circuit.to_instructions().iter()
+            .filter(|instr| format!("{:?}", instr).starts_with("CU1"))
+            .count();
+        assert_eq!(nr_rotations(&exact), n * (n - 1) / 2);
+        // Only rotations over π/2^d with d ≤ ⌊log₂(π/threshold)⌋ = 8 remain
+        assert_eq!(nr_rotations(&approx), n * (n - 1) / 2 - 1);
+        assert!(approx.total_cost() < exact.total_cost());
+        // The critical path runs through the Hadamard gates and the
+        // rotations between neighbouring qubits, which are all kept
+        assert_eq!(approx.critical_path_cost(), exact.critical_path_cost());
+
+        let threshold = 0.1;
+        let mut approx = Circuit::new(n, 0);
+        assert_eq!(qft_approx(&mut approx, &bits, 1 << n, threshold), Ok(()));
+        // With d ≤ 4, every qubit is the target of at most four rotations
+        assert_eq!(nr_rotations(&approx), 4 * n - (1 + 2 + 3 + 4));
+        assert!(approx.total_cost() < exact.total_cost());
+        assert_eq!(approx.critical_path_cost(), exact.critical_path_cost());
+    }
+
+    #[test]
+    fn test_qft()
+    {
+        use rand::Rng;
+
+        let circuit = qft_circuit(1);
+        assert_complex_matrix_eq!(&unitary(&circuit), &crate::gates::H::new().matrix());
+
+        let circuit = qft_circuit(4);
+        assert_complex_matrix_eq!(&unitary(&circuit), &qft_matrix(4));
+
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(qft(&mut circuit, &[0, 1, 2], true), Ok(()));
+        assert_complex_matrix_eq!(&unitary(&circuit), &qft_matrix(3).t().mapv(|x| x.conj()));
+
+        // QFT on qubits in reversed order, followed by its inverse
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(qft(&mut circuit, &[3, 1, 2, 0], false), Ok(()));
+        assert_eq!(qft(&mut circuit, &[3, 1, 2, 0], true), Ok(()));
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0x5eed);
+        let mut state = crate::cmatrix::CVector::from_shape_fn(16, |_| {
+            crate::cmatrix::CNumber::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0))
+        });
+        let norm = state.iter().map(|x| x.norm_sqr()).sum::<f64>().sqrt();
+        state /= crate::cmatrix::CNumber::new(norm, 0.0);
+        assert_complex_vector_eq!(&unitary(&circuit).dot(&state), &state);
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(qft(&mut circuit, &[0, 2], false), Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_qft_general()
+    {
+        // Without the swaps, the transform is the QFT followed by a reversal
+        // of the bit order
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(qft_general(&mut circuit, &[0, 1, 2], false, false, 0.0), Ok(()));
+        assert_eq!(circuit.swap(0, 2), Ok(()));
+        assert_complex_matrix_eq!(&unitary(&circuit), &qft_matrix(3), 1.0e-14);
+
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.swap(0, 2), Ok(()));
+        assert_eq!(qft_general(&mut circuit, &[0, 1, 2], true, false, 0.0), Ok(()));
+        assert_complex_matrix_eq!(&unitary(&circuit), &qft_matrix(3).t().mapv(|x| x.conj()),
+            1.0e-14);
+
+        // The threshold removes rotations from the forward and inverse
+        // transforms alike
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(qft_general(&mut circuit, &[0, 1, 2, 3], false, true, 0.5), Ok(()));
+        assert_eq!(qft_general(&mut circuit, &[0, 1, 2, 3], true, true, 0.5), Ok(()));
+        assert_eq!(circuit.to_instructions().len(), 2 * (4 + 5 + 2));
+        assert_complex_matrix_eq!(&unitary(&circuit), &crate::cmatrix::CMatrix::eye(16), 1.0e-14);
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(qft_general(&mut circuit, &[0, 2], true, false, 0.0),
+            Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_grover_oracle_phase_flip()
+    {
+        for n in 1..6
+        {
+            let bits: Vec<usize> = (0..n).rev().collect();
+            for &target in [0, 1, (1 << n) - 1, 5 % (1 << n)].iter()
+            {
+                let mut circuit = Circuit::new(n, 0);
+                assert_eq!(grover_oracle_phase_flip(&mut circuit, &bits, target), Ok(()));
+                // Bits are reversed, so the index of the marked state is reversed too
+                let idx = (0..n).fold(0, |acc, i| (acc << 1) | ((target >> i) & 1)) as usize;
+                let mut expected = crate::cmatrix::CMatrix::eye(1 << n);
+                expected[[idx, idx]] = -crate::cmatrix::COMPLEX_ONE;
+                assert_complex_matrix_eq!(&unitary(&circuit), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grover_diffusion()
+    {
+        let n = 3;
+        let size = 1 << n;
+        let bits = [0, 1, 2];
+
+        let mut circuit = Circuit::new(n, 0);
+        assert_eq!(grover_diffusion(&mut circuit, &bits), Ok(()));
+        let expected = crate::cmatrix::CMatrix::from_shape_fn((size, size), |(j, k)| {
+            let x = 2.0 / size as f64 - if j == k { 1.0 } else { 0.0 };
+            -crate::cmatrix::CNumber::new(x, 0.0)
+        });
+        assert_complex_matrix_eq!(&unitary(&circuit), &expected);
+
+        // One Grover iteration on the uniform superposition
+        let target = 5;
+        let mut circuit = Circuit::new(n, 0);
+        for &bit in bits.iter()
+        {
+            assert_eq!(circuit.h(bit), Ok(()));
+        }
+        let p_before = unitary(&circuit)[[target, 0]].norm_sqr();
+        assert_eq!(grover_oracle_phase_flip(&mut circuit, &bits, target as u64), Ok(()));
+        assert_eq!(grover_diffusion(&mut circuit, &bits), Ok(()));
+        let p_after = unitary(&circuit)[[target, 0]].norm_sqr();
+        assert!((p_before - 0.125).abs() < 1.0e-12);
+        assert!(p_after >= 2.0 * p_before);
+        assert!((p_after - 25.0 / 32.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_amplitude_estimation()
+    {
+        let nr_shots = 1024;
+        let precision_bits = 5;
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(439);
+
+        let mut oracle = Circuit::new(1, 0);
+        assert_eq!(oracle.z(0), Ok(()));
+
+        // a = sin(π/8) is exactly representable with five precision bits
+        let mut state_prep = Circuit::new(1, 0);
+        assert_eq!(state_prep.ry(::std::f64::consts::FRAC_PI_4, 0), Ok(()));
+        let mut circuit = amplitude_estimation_circuit(&state_prep, &oracle, precision_bits)
+            .unwrap();
+        assert_eq!(circuit.nr_qbits(), precision_bits + 1);
+        assert_eq!(circuit.nr_cbits(), precision_bits);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        let histogram = circuit.histogram().unwrap();
+        // Only y = 4 and y = 28 can be measured
+        assert_eq!(histogram.values().sum::<usize>(), nr_shots);
+        assert_eq!(histogram.get(&4).cloned().unwrap_or(0) + histogram.get(&28).cloned().unwrap_or(0),
+            nr_shots);
+        let estimate = extract_amplitude_estimate(&histogram, precision_bits, nr_shots);
+        assert!((estimate - (::std::f64::consts::PI / 8.0).sin()).abs() < 1.0e-10);
+
+        let a = 0.6f64;
+        let mut state_prep = Circuit::new(1, 0);
+        assert_eq!(state_prep.ry(2.0 * a.asin(), 0), Ok(()));
+        let mut circuit = amplitude_estimation_circuit(&state_prep, &oracle, precision_bits)
+            .unwrap();
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        let histogram = circuit.histogram().unwrap();
+        let estimate = extract_amplitude_estimate(&histogram, precision_bits, nr_shots);
+        assert!((estimate - a).abs() < ::std::f64::consts::PI / (1 << precision_bits) as f64);
+
+        assert_eq!(extract_amplitude_estimate(&histogram, precision_bits, 0), 0.0);
+        let oracle = Circuit::new(2, 0);
+        assert!(amplitude_estimation_circuit(&state_prep, &oracle, precision_bits).is_err());
+    }
+
+    #[test]
+    fn test_quantum_phase_estimation()
+    {
+        let nr_shots = 256;
+        let n = 3;
+        let counting_bits: Vec<usize> = (0..n).collect();
+
+        // Z has eigenvalue 1 for |0⟩, -1 = exp(2πi·0.5) for |1⟩, and T has
+        // eigenvalue exp(2πi/8) for |1⟩.
+        let cases: Vec<(Box<dyn crate::export::CircuitGate>, bool, u64)> = vec![
+            (Box::new(crate::gates::Z::new()), false, 0),
+            (Box::new(crate::gates::Z::new()), true, 4),
+            (Box::new(crate::gates::T::new()), true, 1)
+        ];
+        for (gate, excited, expected) in cases
+        {
+            let mut circuit = Circuit::new(n + 1, n);
+            if excited
+            {
+                assert_eq!(circuit.x(n), Ok(()));
+            }
+            assert_eq!(quantum_phase_estimation(&mut circuit, &counting_bits, &[n], gate.as_ref()),
+                Ok(()));
+            for bit in 0..n
+            {
+                assert_eq!(circuit.measure(bit, n - bit - 1), Ok(()));
+            }
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+            let histogram = circuit.histogram().unwrap();
+            assert_eq!(histogram.get(&expected), Some(&nr_shots));
+        }
+
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(quantum_phase_estimation(&mut circuit, &[0], &[1, 2], &crate::gates::Z::new()),
+            Err(crate::error::Error::InvalidNrBits(2, 1, String::from("Z"))));
+    }
+
+    #[test]
+    fn test_teleportation_circuit()
+    {
+        let nr_shots = 1024;
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(531);
+
+        let circuit = teleportation_circuit(2, 0, 3, 3).unwrap();
+        assert_eq!(circuit.nr_qbits(), 4);
+        assert_eq!(circuit.nr_cbits(), 3);
+
+        // |+⟩, |-⟩, and |i⟩ are eigenstates of X, X, and Y respectively, so
+        // measuring the teleported state in the right basis gives a definite
+        // result.
+        let cases = [
+            (false, false, crate::circuit::Basis::X, 0),
+            (true, false, crate::circuit::Basis::X, 1),
+            (false, true, crate::circuit::Basis::Y, 0)
+        ];
+        for &(flip, phase, basis, expected) in cases.iter()
+        {
+            let mut prep = Circuit::new(4, 3);
+            if flip
+            {
+                assert_eq!(prep.x(2), Ok(()));
+            }
+            assert_eq!(prep.h(2), Ok(()));
+            if phase
+            {
+                assert_eq!(prep.s(2), Ok(()));
+            }
+            assert_eq!(prep.compose(&circuit), Ok(()));
+            assert_eq!(prep.measure_basis(3, 2, basis), Ok(()));
+            assert_eq!(prep.execute_with_rng(nr_shots, &mut rng), Ok(()));
+            let hist = prep.histogram_vec().unwrap();
+            let count: usize = hist.iter().enumerate()
+                .filter(|&(key, _)| (key >> 2) & 1 == expected)
+                .map(|(_, &count)| count)
+                .sum();
+            assert_eq!(count, nr_shots);
+        }
+
+        // A state with 1/4 probability of measuring 1
+        let theta = ::std::f64::consts::FRAC_PI_3;
+        let mut prep = Circuit::new(4, 3);
+        assert_eq!(prep.ry(theta, 2), Ok(()));
+        assert_eq!(prep.compose(&circuit), Ok(()));
+        assert_eq!(prep.measure(3, 2), Ok(()));
+        assert_eq!(prep.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        let hist = prep.histogram_vec().unwrap();
+        let count: usize = hist[4..].iter().sum();
+        assert!(crate::stats::measurement_ok(count, nr_shots, 0.25, 1.0e-5));
+
+        assert!(matches!(teleportation_circuit(0, 0, 1, 2), Err(crate::error::Error::InvalidQBit(0))));
+        assert!(matches!(teleportation_circuit(0, 1, 1, 2), Err(crate::error::Error::InvalidQBit(1))));
+        assert!(matches!(teleportation_circuit(0, 1, 2, 1),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Digital quantum annealing
+//!
+//! In quantum annealing, or the quantum adiabatic algorithm, a system is
+//! prepared in the ground state of a simple Hamiltonian `H`<sub>`0`</sub>,
+//! which is then slowly deformed into a target Hamiltonian
+//! `H`<sub>`1`</sub> along the path `H(s) = (1-s)H`<sub>`0`</sub>` +
+//! sH`<sub>`1`</sub>. If this is done slowly enough compared to the minimum
+//! spectral gap along the path, the system ends up in the ground state of the
+//! target Hamiltonian. On a gate based computer, the continuous evolution is
+//! replaced by a sequence of Trotterized steps at fixed values of `s`.
+
+use crate::hamiltonian::Hamiltonian;
+
+/// Add digital annealing steps.
+///
+/// Add the evolution `exp(-i·time_step·H(s))`, with `H(s) = (1-s)·initial +
+/// s·target`, for every value `s` in `schedule` in turn, to `circuit`. Each
+/// step is approximated by a single first order Trotter step, in which the
+/// terms of `initial` are applied first, followed by those of `target`. The
+/// evolution acts on the first qubits of `circuit`, which should be prepared
+/// in the ground state of `initial`. An `InvalidQBit` error is returned if
+/// the Hamiltonians act on more qubits than `circuit` holds.
+pub fn add_annealing_steps(circuit: &mut crate::circuit::Circuit, initial: &Hamiltonian,
+    target: &Hamiltonian, schedule: &[f64], time_step: f64) -> crate::error::Result<()>
+{
+    let nr_bits = initial.nr_bits().max(target.nr_bits());
+    if nr_bits > circuit.nr_qbits()
+    {
+        return Err(crate::error::Error::InvalidQBit(nr_bits - 1));
+    }
+
+    for &s in schedule
+    {
+        let weighted = [(1.0 - s, initial), (s, target)];
+        for &(weight, hamiltonian) in weighted.iter()
+        {
+            if weight == 0.0
+            {
+                continue;
+            }
+            for (coef, ps) in hamiltonian.to_pauli_strings()
+            {
+                crate::walk::add_pauli_rotation(circuit, ps, weight * coef * time_step)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Digital quantum annealing circuit.
+///
+/// Create a circuit implementing the Trotterized annealing from Hamiltonian
+/// `initial` to Hamiltonian `target` following `schedule`, where each value
+/// `s` in `schedule` is applied for a time `time_step`. The circuit acts on
+/// as many qubits as the largest Pauli string in either Hamiltonian, and has
+/// no classical bits. As the circuit starts from the state |0...0⟩, it only
+/// performs the intended annealing when this is the ground state of
+/// `initial`; otherwise use `add_annealing_steps()` on a circuit preparing
+/// the ground state. Since the angles of the gates depend on the schedule, a
+/// different schedule is swept by creating a new circuit.
+pub fn quantum_annealing_circuit(initial: &Hamiltonian, target: &Hamiltonian, schedule: &[f64],
+    time_step: f64) -> crate::circuit::Circuit
+{
+    let nr_bits = initial.nr_bits().max(target.nr_bits());
+    let mut circuit = crate::circuit::Circuit::new(nr_bits, 0);
+    // The circuit is large enough, so this cannot fail
+    add_annealing_steps(&mut circuit, initial, target, schedule, time_step).unwrap();
+    circuit
+}
+
+/// Estimate the minimum spectral gap along an annealing path.
+///
+/// Estimate the minimum gap between the two lowest eigenvalues of `H(s) =
+/// (1-s)·initial + s·target` along `schedule`, by annealing two states in
+/// parallel. Circuit `ground` should prepare the ground state of `initial`,
+/// and circuit `excited` its first excited state. Both are evolved as in
+/// `add_annealing_steps()`, and after the step for each `s` in `schedule`,
+/// the energies ⟨H(s)⟩ of the two evolved states are computed from the
+/// expectation values of the terms of the Hamiltonians. Provided the
+/// annealing is slow enough, the two states stay close to the two lowest
+/// eigenstates of `H(s)`, and the minimum difference between their energies
+/// estimates the gap. As the path is only sampled at the points in
+/// `schedule`, the true minimum may be somewhat smaller when the gap closes
+/// sharply between sample points. For an empty schedule, infinity is returned.
+/// An `InvalidNrBits` error is returned when the two circuits have a
+/// different number of qubits, and an `InvalidQBit` error if the Hamiltonians
+/// act on more qubits than the circuits hold.
+pub fn adiabatic_gap_estimation(initial: &Hamiltonian, target: &Hamiltonian,
+    ground: &crate::circuit::Circuit, excited: &crate::circuit::Circuit, schedule: &[f64],
+    time_step: f64) -> crate::error::Result<f64>
+{
+    if ground.nr_qbits() != excited.nr_qbits()
+    {
+        return Err(crate::error::Error::InvalidNrBits(excited.nr_qbits(), ground.nr_qbits(),
+            String::from("excited state preparation")));
+    }
+
+    let obs0 = crate::observable::SparsePauliOp::from(initial);
+    let obs1 = crate::observable::SparsePauliOp::from(target);
+    let nr_qbits = ground.nr_qbits();
+    let qubit_map: Vec<usize> = (0..nr_qbits).collect();
+    let mut circuits = vec![];
+    for preparation in [ground, excited].iter()
+    {
+        let nr_cbits = preparation.nr_cbits();
+        let mut circuit = crate::circuit::Circuit::new(nr_qbits, nr_cbits);
+        circuit.append_on_qubits(preparation, &qubit_map, &(0..nr_cbits).collect::<Vec<_>>())?;
+        circuits.push(circuit);
+    }
+    let mut gap = f64::INFINITY;
+    for &s in schedule
+    {
+        let mut energies = [0.0; 2];
+        for (circuit, energy) in circuits.iter_mut().zip(energies.iter_mut())
+        {
+            add_annealing_steps(circuit, initial, target, &[s], time_step)?;
+            circuit.execute(1)?;
+            *energy = (1.0 - s) * circuit.expectation_value(&obs0)?
+                + s * circuit.expectation_value(&obs1)?;
+        }
+        gap = gap.min(energies[1] - energies[0]);
+    }
+
+    Ok(gap)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{add_annealing_steps, adiabatic_gap_estimation, quantum_annealing_circuit};
+    use crate::circuit::Circuit;
+    use crate::hamiltonian::Hamiltonian;
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    fn hamiltonian(terms: &[(f64, &[PauliOp])]) -> Hamiltonian
+    {
+        let mut h = Hamiltonian::new();
+        for &(coef, ops) in terms
+        {
+            h.add_term(coef, PauliString::new(ops));
+        }
+        h
+    }
+
+    #[test]
+    fn test_quantum_annealing_circuit()
+    {
+        let initial = hamiltonian(&[(-1.0, &[PauliOp::X, PauliOp::I]), (-1.0, &[PauliOp::I, PauliOp::X])]);
+        let target = hamiltonian(&[(-1.0, &[PauliOp::Z, PauliOp::Z])]);
+
+        let circuit = quantum_annealing_circuit(&initial, &target, &[0.0, 0.5, 1.0], 0.1);
+        assert_eq!(circuit.nr_qbits(), 2);
+        assert_eq!(circuit.nr_cbits(), 0);
+
+        let circuit = quantum_annealing_circuit(&initial, &target, &[], 0.1);
+        assert!(circuit.to_instructions().is_empty());
+
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(add_annealing_steps(&mut circuit, &initial, &target, &[0.5], 0.1),
+            Err(crate::error::Error::InvalidQBit(1)));
+    }
+
+    #[test]
+    fn test_annealing()
+    {
+        // Anneal from the transverse field -X to -Z, whose ground state is |0⟩
+        let initial = hamiltonian(&[(-1.0, &[PauliOp::X])]);
+        let target = hamiltonian(&[(-1.0, &[PauliOp::Z])]);
+        let nr_steps = 200;
+        let schedule: Vec<f64> = (1..=nr_steps).map(|k| k as f64 / nr_steps as f64).collect();
+
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(add_annealing_steps(&mut circuit, &initial, &target, &schedule, 0.1), Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = circuit.expectation_value(PauliString::new(&[PauliOp::Z])).unwrap();
+        assert!(energy > 0.98);
+
+        // Too fast: the state hardly changes
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(add_annealing_steps(&mut circuit, &initial, &target, &[0.5, 1.0], 0.01),
+            Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = circuit.expectation_value(PauliString::new(&[PauliOp::Z])).unwrap();
+        assert!(energy < 0.1);
+    }
+
+    #[test]
+    fn test_adiabatic_gap_estimation()
+    {
+        // H(s) = -(1-s)X - sZ has eigenvalues ±√((1-s)² + s²), so the gap is
+        // smallest at s = 1/2, where it is √2.
+        let initial = hamiltonian(&[(-1.0, &[PauliOp::X])]);
+        let target = hamiltonian(&[(-1.0, &[PauliOp::Z])]);
+        let mut ground = Circuit::new(1, 0);
+        assert_eq!(ground.h(0), Ok(()));
+        let mut excited = Circuit::new(1, 0);
+        assert_eq!(excited.x(0), Ok(()));
+        assert_eq!(excited.h(0), Ok(()));
+
+        let nr_steps = 200;
+        let schedule: Vec<f64> = (1..=nr_steps).map(|k| k as f64 / nr_steps as f64).collect();
+        let gap = adiabatic_gap_estimation(&initial, &target, &ground, &excited, &schedule, 0.1)
+            .unwrap();
+        assert!((gap - 2.0f64.sqrt()).abs() < 0.05);
+
+        // At s = 0, the states are eigenstates, and the gap is exact
+        let gap = adiabatic_gap_estimation(&initial, &target, &ground, &excited, &[0.0], 0.1)
+            .unwrap();
+        assert!((gap - 2.0).abs() < 1.0e-10);
+
+        assert_eq!(adiabatic_gap_estimation(&initial, &target, &ground, &excited, &[], 0.1),
+            Ok(f64::INFINITY));
+
+        let excited = Circuit::new(2, 0);
+        assert!(matches!(
+            adiabatic_gap_estimation(&initial, &target, &ground, &excited, &schedule, 0.1),
+            Err(crate::error::Error::InvalidNrBits(2, 1, _))
+        ));
+        let ground = Circuit::new(2, 0);
+        let target = hamiltonian(&[(-1.0, &[PauliOp::Z, PauliOp::Z, PauliOp::Z])]);
+        assert_eq!(
+            adiabatic_gap_estimation(&initial, &target, &ground, &excited, &schedule, 0.1),
+            Err(crate::error::Error::InvalidQBit(2))
+        );
+    }
+}
@@ -0,0 +1,765 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of bosonic modes
+//!
+//! This module provides a simple simulator for circuits that combine qubits
+//! with bosonic modes, such as the modes of a microwave cavity or the phonon
+//! modes in an ion trap. Each mode is described in a Fock space that is
+//! truncated at a maximum number of photons. Operations that would take the
+//! state outside this space are simply cut off, so the maximum should be
+//! chosen large enough for the states of interest.
+
+/// Structure describing a single bosonic mode
+///
+/// Struct `BosonicMode` describes a bosonic mode in a Fock space truncated to
+/// at most `max_photons` photons, i.e. in a space of dimension
+/// `max_photons+1`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BosonicMode
+{
+    /// The maximum number of photons in this mode
+    max_photons: usize
+}
+
+impl BosonicMode
+{
+    /// Create a new mode, truncated to at most `max_photons` photons.
+    pub fn new(max_photons: usize) -> Self
+    {
+        BosonicMode { max_photons: max_photons }
+    }
+
+    /// The maximum number of photons in this mode
+    pub fn max_photons(&self) -> usize
+    {
+        self.max_photons
+    }
+
+    /// The dimension of the truncated Fock space of this mode
+    pub fn dimension(&self) -> usize
+    {
+        self.max_photons + 1
+    }
+
+    /// The matrix of the annihilation operator `a` in the truncated Fock
+    /// space of this mode.
+    pub fn annihilation_matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let n = self.dimension();
+        crate::cmatrix::CMatrix::from_shape_fn((n, n), |(i, j)| {
+            if j == i + 1
+            {
+                crate::cmatrix::CNumber::new((j as f64).sqrt(), 0.0)
+            }
+            else
+            {
+                crate::cmatrix::COMPLEX_ZERO
+            }
+        })
+    }
+
+    /// The matrix of the creation operator `a`<sup>`†`</sup> in the
+    /// truncated Fock space of this mode.
+    pub fn creation_matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        self.annihilation_matrix().t().to_owned()
+    }
+
+    /// The matrix of the number operator `a`<sup>`†`</sup>`a` in the
+    /// truncated Fock space of this mode.
+    pub fn number_matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let n = self.dimension();
+        let mut res = crate::cmatrix::CMatrix::zeros((n, n));
+        for i in 0..n
+        {
+            res[[i, i]] = crate::cmatrix::CNumber::new(i as f64, 0.0);
+        }
+        res
+    }
+}
+
+/// Trait for operations on bosonic modes
+pub trait BosonicGate
+{
+    /// Return a short description of the gate.
+    fn description(&self) -> &str;
+    /// Return the number of modes this gate operates on.
+    fn nr_affected_modes(&self) -> usize;
+    /// Return the matrix of this gate, acting on modes `modes`.
+    ///
+    /// For gates on multiple modes, the first mode corresponds to the most
+    /// significant part of the index in the product space.
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix;
+    /// Return whether this operation is unitary.
+    ///
+    /// Non-unitary operations, like the creation and annihilation operators,
+    /// act as heralded operations: the state is renormalized after they are
+    /// applied.
+    fn is_unitary(&self) -> bool { true }
+}
+
+/// The annihilation operator `a`
+pub struct Annihilation
+{
+}
+
+impl Annihilation
+{
+    /// Create a new annihilation operator
+    pub fn new() -> Self
+    {
+        Annihilation { }
+    }
+}
+
+impl BosonicGate for Annihilation
+{
+    fn description(&self) -> &str { "a" }
+    fn nr_affected_modes(&self) -> usize { 1 }
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix
+    {
+        modes[0].annihilation_matrix()
+    }
+    fn is_unitary(&self) -> bool { false }
+}
+
+/// The creation operator `a`<sup>`†`</sup>
+pub struct Creation
+{
+}
+
+impl Creation
+{
+    /// Create a new creation operator
+    pub fn new() -> Self
+    {
+        Creation { }
+    }
+}
+
+impl BosonicGate for Creation
+{
+    fn description(&self) -> &str { "a†" }
+    fn nr_affected_modes(&self) -> usize { 1 }
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix
+    {
+        modes[0].creation_matrix()
+    }
+    fn is_unitary(&self) -> bool { false }
+}
+
+/// The displacement operator
+///
+/// The displacement operator `D(α) = exp(αa`<sup>`†`</sup>` - α`<sup>`*`</sup>`a)`
+/// transforms the vacuum into the coherent state `|α⟩`.
+pub struct Displacement
+{
+    alpha: crate::cmatrix::CNumber
+}
+
+impl Displacement
+{
+    /// Create a new displacement operator `D(α)`.
+    pub fn new(alpha: crate::cmatrix::CNumber) -> Self
+    {
+        Displacement { alpha: alpha }
+    }
+}
+
+impl BosonicGate for Displacement
+{
+    fn description(&self) -> &str { "D" }
+    fn nr_affected_modes(&self) -> usize { 1 }
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix
+    {
+        let a = modes[0].annihilation_matrix();
+        let ad = modes[0].creation_matrix();
+        crate::linalg::expm(&(ad * self.alpha - a * self.alpha.conj()))
+    }
+}
+
+/// The squeezing operator
+///
+/// The single mode squeezing operator is defined as
+/// `S(z) = exp((z`<sup>`*`</sup>`a`<sup>`2`</sup>` - za`<sup>`†2`</sup>`)/2)`.
+pub struct Squeezing
+{
+    z: crate::cmatrix::CNumber
+}
+
+impl Squeezing
+{
+    /// Create a new squeezing operator `S(z)`.
+    pub fn new(z: crate::cmatrix::CNumber) -> Self
+    {
+        Squeezing { z: z }
+    }
+}
+
+impl BosonicGate for Squeezing
+{
+    fn description(&self) -> &str { "S" }
+    fn nr_affected_modes(&self) -> usize { 1 }
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix
+    {
+        let a = modes[0].annihilation_matrix();
+        let ad = modes[0].creation_matrix();
+        let a2 = a.dot(&a);
+        let ad2 = ad.dot(&ad);
+        crate::linalg::expm(&((a2 * self.z.conj() - ad2 * self.z) * 0.5))
+    }
+}
+
+/// The beam splitter
+///
+/// The beam splitter acting on two modes with annihilation operators `a` and
+/// `b` is defined as `B(θ, φ) = exp(θ(e`<sup>`iφ`</sup>`a`<sup>`†`</sup>`b -
+/// e`<sup>`-iφ`</sup>`ab`<sup>`†`</sup>`))`. For `θ = π/4` this is a balanced
+/// beam splitter.
+pub struct BeamSplitter
+{
+    theta: f64,
+    phi: f64
+}
+
+impl BeamSplitter
+{
+    /// Create a new beam splitter `B(θ, φ)`.
+    pub fn new(theta: f64, phi: f64) -> Self
+    {
+        BeamSplitter { theta: theta, phi: phi }
+    }
+}
+
+impl BosonicGate for BeamSplitter
+{
+    fn description(&self) -> &str { "BS" }
+    fn nr_affected_modes(&self) -> usize { 2 }
+    fn matrix(&self, modes: &[BosonicMode]) -> crate::cmatrix::CMatrix
+    {
+        let i0 = crate::cmatrix::CMatrix::eye(modes[0].dimension());
+        let i1 = crate::cmatrix::CMatrix::eye(modes[1].dimension());
+        let a = crate::cmatrix::kron_mat(&modes[0].annihilation_matrix(), &i1);
+        let ad = crate::cmatrix::kron_mat(&modes[0].creation_matrix(), &i1);
+        let b = crate::cmatrix::kron_mat(&i0, &modes[1].annihilation_matrix());
+        let bd = crate::cmatrix::kron_mat(&i0, &modes[1].creation_matrix());
+
+        let e = crate::cmatrix::CNumber::from_polar(&self.theta, &self.phi);
+        let g = ad.dot(&b) * e - a.dot(&bd) * e.conj();
+        crate::linalg::expm(&g)
+    }
+}
+
+/// The Jaynes-Cummings interaction between a qubit and a bosonic mode
+///
+/// The Jaynes-Cummings interaction with coupling strength `g` is described
+/// by the Hamiltonian `H = g(σ`<sub>`+`</sub>`a + σ`<sub>`-`</sub>`a`<sup>`†`</sup>`)`,
+/// where `σ`<sub>`+`</sub>` = |1⟩⟨0|` excites the qubit. Struct
+/// `JaynesCummings` describes the evolution `exp(-iHt)` under this
+/// Hamiltonian during a time `t`. For `gt = π/2`, an excitation is swapped
+/// between the qubit and the mode.
+pub struct JaynesCummings
+{
+    g: f64,
+    t: f64
+}
+
+impl JaynesCummings
+{
+    /// Create a new Jaynes-Cummings evolution operator for coupling strength
+    /// `g` and time `t`.
+    pub fn new(g: f64, t: f64) -> Self
+    {
+        JaynesCummings { g: g, t: t }
+    }
+
+    /// Return a short description of the gate.
+    pub fn description(&self) -> &str
+    {
+        "JC"
+    }
+
+    /// Return the matrix of this operator, acting on a qubit and mode `mode`.
+    /// The qubit corresponds to the most significant part of the index.
+    pub fn matrix(&self, mode: &BosonicMode) -> crate::cmatrix::CMatrix
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let sigma_p = array![[z, z], [o, z]];
+        let sigma_m = array![[z, o], [z, z]];
+
+        let h = crate::cmatrix::kron_mat(&sigma_p, &mode.annihilation_matrix())
+            + crate::cmatrix::kron_mat(&sigma_m, &mode.creation_matrix());
+        crate::linalg::expm(&(h * crate::cmatrix::CNumber::new(0.0, -self.g * self.t)))
+    }
+}
+
+/// Operations in a hybrid circuit
+enum HybridOp
+{
+    /// Gate on qubits
+    Gate(Box<dyn crate::gates::Gate>, Vec<usize>),
+    /// Operation on bosonic modes
+    Bosonic(Box<dyn BosonicGate>, Vec<usize>),
+    /// Coupling between a qubit and a mode
+    Coupling(JaynesCummings, usize, usize)
+}
+
+/// A circuit combining qubits and bosonic modes
+///
+/// Struct `HybridCircuit` describes a circuit acting on a register of qubits
+/// and a number of bosonic modes. The state of the system is described by a
+/// single state vector in the product space of the qubits and the truncated
+/// Fock spaces of the modes, with the qubits in the most significant part of
+/// the index. Execution starts from the state with all qubits zero and all
+/// modes in the vacuum state.
+pub struct HybridCircuit
+{
+    /// The number of qubits in the circuit
+    nr_qbits: usize,
+    /// The bosonic modes in the circuit
+    modes: Vec<BosonicMode>,
+    /// The operations in the circuit
+    ops: Vec<HybridOp>,
+    /// The state after execution of the circuit
+    state: Option<crate::cmatrix::CVector>
+}
+
+impl HybridCircuit
+{
+    /// Create a new hybrid circuit, acting on `nr_qbits` qubits and bosonic
+    /// modes `modes`.
+    pub fn new(nr_qbits: usize, modes: &[BosonicMode]) -> Self
+    {
+        HybridCircuit
+        {
+            nr_qbits: nr_qbits,
+            modes: modes.to_vec(),
+            ops: vec![],
+            state: None
+        }
+    }
+
+    /// The number of qubits in this circuit
+    pub fn nr_qbits(&self) -> usize
+    {
+        self.nr_qbits
+    }
+
+    /// The bosonic modes in this circuit
+    pub fn modes(&self) -> &[BosonicMode]
+    {
+        &self.modes
+    }
+
+    fn check_qbit(&self, bit: usize) -> crate::error::Result<()>
+    {
+        if bit >= self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    fn check_mode(&self, mode: usize) -> crate::error::Result<()>
+    {
+        if mode >= self.modes.len()
+        {
+            Err(crate::error::Error::InvalidMode(mode))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    /// Add a gate.
+    ///
+    /// Append the gate `gate`, operating on qubits `bits`, to this circuit.
+    pub fn add_gate<G>(&mut self, gate: G, bits: &[usize]) -> crate::error::Result<()>
+    where G: 'static + crate::gates::Gate
+    {
+        if gate.nr_affected_bits() != bits.len()
+        {
+            return Err(crate::error::Error::InvalidNrBits(bits.len(),
+                gate.nr_affected_bits(), String::from(gate.description())));
+        }
+        for &bit in bits
+        {
+            self.check_qbit(bit)?;
+        }
+
+        self.ops.push(HybridOp::Gate(Box::new(gate), bits.to_vec()));
+        Ok(())
+    }
+
+    /// Add a bosonic operation.
+    ///
+    /// Append the operation `gate`, operating on modes `modes`, to this
+    /// circuit.
+    pub fn add_bosonic_gate<G>(&mut self, gate: G, modes: &[usize])
+        -> crate::error::Result<()>
+    where G: 'static + BosonicGate
+    {
+        if gate.nr_affected_modes() != modes.len()
+        {
+            return Err(crate::error::Error::InvalidNrBits(modes.len(),
+                gate.nr_affected_modes(), String::from(gate.description())));
+        }
+        for &mode in modes
+        {
+            self.check_mode(mode)?;
+        }
+
+        self.ops.push(HybridOp::Bosonic(Box::new(gate), modes.to_vec()));
+        Ok(())
+    }
+
+    /// Add a qubit-mode coupling.
+    ///
+    /// Append the Jaynes-Cummings interaction `gate` between qubit `bit`
+    /// and mode `mode` to this circuit.
+    pub fn add_coupling(&mut self, gate: JaynesCummings, bit: usize, mode: usize)
+        -> crate::error::Result<()>
+    {
+        self.check_qbit(bit)?;
+        self.check_mode(mode)?;
+
+        self.ops.push(HybridOp::Coupling(gate, bit, mode));
+        Ok(())
+    }
+
+    /// The dimensions of the subsystems, qubits first
+    fn dimensions(&self) -> Vec<usize>
+    {
+        let mut dims = vec![2; self.nr_qbits];
+        dims.extend(self.modes.iter().map(|m| m.dimension()));
+        dims
+    }
+
+    /// Execute this circuit.
+    ///
+    /// Execute the circuit, starting from the state where all qubits are zero,
+    /// and all modes are in the vacuum state.
+    pub fn execute(&mut self) -> crate::error::Result<()>
+    {
+        let dims = self.dimensions();
+        let mut state = crate::cmatrix::CVector::zeros(dims.iter().product::<usize>());
+        state[0] = crate::cmatrix::COMPLEX_ONE;
+
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                HybridOp::Gate(ref gate, ref bits) => {
+                    apply_local(&mut state, &dims, bits, &gate.matrix());
+                },
+                HybridOp::Bosonic(ref gate, ref modes) => {
+                    let sub: Vec<usize> = modes.iter().map(|&m| self.nr_qbits + m).collect();
+                    let mode_list: Vec<BosonicMode> = modes.iter().map(|&m| self.modes[m]).collect();
+                    apply_local(&mut state, &dims, &sub, &gate.matrix(&mode_list));
+                    if !gate.is_unitary()
+                    {
+                        let norm = state.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+                        if norm < 1.0e-12
+                        {
+                            return Err(crate::error::Error::ZeroProbability(
+                                String::from(gate.description())));
+                        }
+                        state /= crate::cmatrix::CNumber::new(norm, 0.0);
+                    }
+                },
+                HybridOp::Coupling(ref gate, bit, mode) => {
+                    apply_local(&mut state, &dims, &[bit, self.nr_qbits + mode],
+                        &gate.matrix(&self.modes[mode]));
+                }
+            }
+        }
+
+        self.state = Some(state);
+        Ok(())
+    }
+
+    /// The state vector after execution of the circuit
+    pub fn state(&self) -> crate::error::Result<&crate::cmatrix::CVector>
+    {
+        self.state.as_ref().ok_or(crate::error::Error::NotExecuted)
+    }
+
+    /// Compute the marginal probability distribution of subsystem `sub`.
+    fn marginal(&self, sub: usize) -> crate::error::Result<Vec<f64>>
+    {
+        let state = self.state()?;
+        let dims = self.dimensions();
+        let stride = dims[sub+1..].iter().product::<usize>();
+        let mut probs = vec![0.0; dims[sub]];
+        for (idx, c) in state.iter().enumerate()
+        {
+            probs[(idx / stride) % dims[sub]] += c.norm_sqr();
+        }
+        Ok(probs)
+    }
+
+    /// The probability of finding qubit `bit` in state `|1⟩` after execution
+    /// of the circuit.
+    pub fn qbit_probability(&self, bit: usize) -> crate::error::Result<f64>
+    {
+        self.check_qbit(bit)?;
+        Ok(self.marginal(bit)?[1])
+    }
+
+    /// The probabilities of finding `n` photons in mode `mode`, for
+    /// `n = 0, ..., max_photons`, after execution of the circuit.
+    pub fn photon_number_distribution(&self, mode: usize) -> crate::error::Result<Vec<f64>>
+    {
+        self.check_mode(mode)?;
+        self.marginal(self.nr_qbits + mode)
+    }
+
+    /// The expected number of photons in mode `mode` after execution of the
+    /// circuit.
+    pub fn mean_photon_number(&self, mode: usize) -> crate::error::Result<f64>
+    {
+        let probs = self.photon_number_distribution(mode)?;
+        Ok(probs.iter().enumerate().map(|(n, p)| n as f64 * p).sum())
+    }
+}
+
+/// Apply matrix `mat` to subsystems `sub` of `state`.
+///
+/// Apply the operator with matrix `mat` to the subsystems with indices in
+/// `sub` of the state vector `state`, in a product space of subsystems with
+/// dimensions `dims`. The first subsystem in `sub` is the most significant
+/// in the index of `mat`.
+fn apply_local(state: &mut crate::cmatrix::CVector, dims: &[usize], sub: &[usize],
+    mat: &crate::cmatrix::CMatrix)
+{
+    let mut strides = vec![1; dims.len()];
+    for i in (0..dims.len().saturating_sub(1)).rev()
+    {
+        strides[i] = strides[i+1] * dims[i+1];
+    }
+
+    // Offset in the full index for index l in subsystems subs
+    let offset = |subs: &[usize], mut l: usize| {
+        let mut offset = 0;
+        for &s in subs.iter().rev()
+        {
+            offset += (l % dims[s]) * strides[s];
+            l /= dims[s];
+        }
+        offset
+    };
+
+    let others: Vec<usize> = (0..dims.len()).filter(|i| !sub.contains(i)).collect();
+    let nr_bases = others.iter().map(|&i| dims[i]).product::<usize>();
+
+    let sub_dim = mat.rows();
+    let offsets: Vec<usize> = (0..sub_dim).map(|l| offset(sub, l)).collect();
+
+    let mut v = crate::cmatrix::CVector::zeros(sub_dim);
+    for k in 0..nr_bases
+    {
+        let base = offset(&others, k);
+        for (l, &offset) in offsets.iter().enumerate()
+        {
+            v[l] = state[base + offset];
+        }
+        let w = mat.dot(&v);
+        for (l, &offset) in offsets.iter().enumerate()
+        {
+            state[base + offset] = w[l];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Annihilation, BeamSplitter, BosonicGate, BosonicMode, Creation,
+        Displacement, HybridCircuit, JaynesCummings, Squeezing};
+    use crate::gates::{CX, H, X};
+
+    fn factorial(n: usize) -> f64
+    {
+        (1..=n).map(|k| k as f64).product()
+    }
+
+    #[test]
+    fn test_mode()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let s2 = 2.0f64.sqrt() * o;
+
+        let mode = BosonicMode::new(2);
+        assert_eq!(mode.max_photons(), 2);
+        assert_eq!(mode.dimension(), 3);
+        assert_complex_matrix_eq!(&mode.annihilation_matrix(),
+            &array![[z, o, z], [z, z, s2], [z, z, z]]);
+        assert_complex_matrix_eq!(&mode.creation_matrix(),
+            &array![[z, z, z], [o, z, z], [z, s2, z]]);
+        assert_complex_matrix_eq!(&mode.number_matrix(),
+            &array![[z, z, z], [z, o, z], [z, z, 2.0*o]]);
+    }
+
+    #[test]
+    fn test_creation_annihilation()
+    {
+        let modes = [BosonicMode::new(4)];
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_bosonic_gate(Annihilation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        assert_eq!(circuit.photon_number_distribution(0), Ok(vec![0.0, 1.0, 0.0, 0.0, 0.0]));
+
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Annihilation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.execute(),
+            Err(crate::error::Error::ZeroProbability(String::from("a"))));
+    }
+
+    #[test]
+    fn test_displacement()
+    {
+        // Coherent state |α⟩ has a Poisson photon number distribution
+        let alpha = crate::cmatrix::CNumber::new(0.6, 0.8);
+        let modes = [BosonicMode::new(30)];
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Displacement::new(alpha), &[0]), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+
+        let probs = circuit.photon_number_distribution(0).unwrap();
+        let mean = alpha.norm_sqr();
+        for n in 0..10
+        {
+            let expected = (-mean).exp() * mean.powi(n as i32) / factorial(n);
+            assert!((probs[n] - expected).abs() < 1.0e-10);
+        }
+        assert!((circuit.mean_photon_number(0).unwrap() - mean).abs() < 1.0e-10);
+
+        // D(-α)D(α) = I
+        let d = Displacement::new(alpha).matrix(&modes);
+        let dm = Displacement::new(-alpha).matrix(&modes);
+        let prod = dm.dot(&d);
+        assert!((prod[[0, 0]] - crate::cmatrix::COMPLEX_ONE).norm() < 1.0e-10);
+    }
+
+    #[test]
+    fn test_squeezing()
+    {
+        // Squeezed vacuum only contains even photon numbers, with mean
+        // photon number sinh²(r)
+        let r = 0.5;
+        let modes = [BosonicMode::new(40)];
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Squeezing::new(crate::cmatrix::CNumber::new(r, 0.0)), &[0]), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+
+        let probs = circuit.photon_number_distribution(0).unwrap();
+        for n in (1..40).step_by(2)
+        {
+            assert!(probs[n] < 1.0e-15);
+        }
+        assert!((probs[0] - 1.0 / r.cosh()).abs() < 1.0e-10);
+        assert!((circuit.mean_photon_number(0).unwrap() - r.sinh().powi(2)).abs() < 1.0e-8);
+    }
+
+    #[test]
+    fn test_beam_splitter()
+    {
+        let modes = [BosonicMode::new(2), BosonicMode::new(3)];
+
+        // Single photon on balanced beam splitter
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_bosonic_gate(BeamSplitter::new(::std::f64::consts::FRAC_PI_4, 0.0), &[0, 1]), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        assert!((circuit.mean_photon_number(0).unwrap() - 0.5).abs() < 1.0e-12);
+        assert!((circuit.mean_photon_number(1).unwrap() - 0.5).abs() < 1.0e-12);
+
+        // Hong-Ou-Mandel: two photons always leave through the same port
+        let mut circuit = HybridCircuit::new(0, &modes);
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[1]), Ok(()));
+        assert_eq!(circuit.add_bosonic_gate(BeamSplitter::new(::std::f64::consts::FRAC_PI_4, 0.3), &[0, 1]), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        let state = circuit.state().unwrap();
+        // Index of |1,1⟩ is 1*4 + 1
+        assert!(state[5].norm() < 1.0e-12);
+        assert!((circuit.photon_number_distribution(0).unwrap()[2] - 0.5).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_jaynes_cummings()
+    {
+        let g = 2.0;
+        let modes = [BosonicMode::new(3)];
+
+        // Swap an excitation from the qubit into the mode
+        let mut circuit = HybridCircuit::new(2, &modes);
+        assert_eq!(circuit.add_gate(X::new(), &[1]), Ok(()));
+        assert_eq!(circuit.add_coupling(JaynesCummings::new(g, 0.25 * ::std::f64::consts::PI), 1, 0), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        assert!(circuit.qbit_probability(0).unwrap() < 1.0e-12);
+        assert!(circuit.qbit_probability(1).unwrap() < 1.0e-12);
+        assert!((circuit.photon_number_distribution(0).unwrap()[1] - 1.0).abs() < 1.0e-12);
+
+        // Rabi oscillation
+        let t = 0.3;
+        let mut circuit = HybridCircuit::new(1, &modes);
+        assert_eq!(circuit.add_gate(X::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_coupling(JaynesCummings::new(g, t), 0, 0), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        assert!((circuit.qbit_probability(0).unwrap() - (g*t).cos().powi(2)).abs() < 1.0e-12);
+
+        // Entangle two qubits, and map one of them onto the mode
+        let mut circuit = HybridCircuit::new(2, &modes);
+        assert_eq!(circuit.add_gate(H::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_gate(CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(circuit.add_coupling(JaynesCummings::new(g, 0.25 * ::std::f64::consts::PI), 1, 0), Ok(()));
+        assert_eq!(circuit.execute(), Ok(()));
+        let state = circuit.state().unwrap();
+        let x = ::std::f64::consts::FRAC_1_SQRT_2;
+        // (|00⟩|0⟩ - i|10⟩|1⟩)/√2, the latter with index 1*8 + 0*4 + 1
+        assert!((state[0].re - x).abs() < 1.0e-12);
+        assert!((state[9].im + x).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        let modes = [BosonicMode::new(3)];
+        let mut circuit = HybridCircuit::new(1, &modes);
+        assert_eq!(circuit.nr_qbits(), 1);
+        assert_eq!(circuit.modes(), &modes);
+        assert_eq!(circuit.add_gate(X::new(), &[1]), Err(crate::error::Error::InvalidQBit(1)));
+        assert_eq!(circuit.add_gate(CX::new(), &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("CX"))));
+        assert_eq!(circuit.add_bosonic_gate(Creation::new(), &[1]),
+            Err(crate::error::Error::InvalidMode(1)));
+        assert_eq!(circuit.add_bosonic_gate(BeamSplitter::new(0.1, 0.0), &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("BS"))));
+        assert_eq!(circuit.add_coupling(JaynesCummings::new(1.0, 1.0), 0, 1),
+            Err(crate::error::Error::InvalidMode(1)));
+        assert_eq!(circuit.state(), Err(crate::error::Error::NotExecuted));
+        assert_eq!(circuit.qbit_probability(0), Err(crate::error::Error::NotExecuted));
+    }
+}
@@ -0,0 +1,407 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blind quantum computation
+//!
+//! This module implements the circuit side of measurement-based blind quantum
+//! computation, in the style of the Broadbent-Fitzsimons-Kashefi protocol. The
+//! client translates a gate circuit into a sequence of measurement angles on a
+//! brickwork-like graph state, which the server prepares and measures. Each
+//! measurement of a qubit `j` at angle `φ` teleports the logical state in its
+//! row to the next qubit `k`, while applying `J(-φ) = H·U`<sub>`1`</sub>`(-φ)` to
+//! it. Edges between adjacent rows apply a `CZ` gate to the logical qubits in
+//! these rows. The cryptographic aspects of the protocol, like hiding the
+//! angles and the graph layout from the server, are not implemented.
+
+use crate::circuit::Circuit;
+
+/// Function decoding the logical output of a BQC computation from the
+/// measurement results in the classical register of the server circuit
+pub type BqcDecoder = Box<dyn Fn(&[u64]) -> Vec<bool>>;
+
+/// Graph state for measurement-based computation
+///
+/// Struct `BrickworkState` describes a graph state on a grid of `nr_rows`
+/// rows and `nr_cols` columns of qubits. The qubits in each row form a chain,
+/// carrying a single logical qubit, while vertical edges between qubits in the
+/// same column of adjacent rows entangle the logical qubits. In the protocol
+/// of Broadbent, Fitzsimons and Kashefi, the vertical edges form a fixed brick
+/// pattern; here, they are placed where the computation needs them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrickworkState
+{
+    /// The number of rows, i.e. the number of logical qubits
+    nr_rows: usize,
+    /// The number of columns
+    nr_cols: usize,
+    /// Vertical edges, as `(row, col)`, connecting the qubit on `row` with
+    /// that on `row+1` in column `col`
+    vertical_edges: Vec<(usize, usize)>
+}
+
+impl BrickworkState
+{
+    /// Create a new graph state.
+    ///
+    /// Create a graph state on `nr_rows` rows of `nr_cols` qubits, with
+    /// vertical edges between rows `row` and `row+1` in column `col` for each
+    /// `(row, col)` in `vertical_edges`. An error is returned when there are
+    /// no columns, or when an edge lies outside the grid.
+    pub fn new(nr_rows: usize, nr_cols: usize, vertical_edges: &[(usize, usize)])
+        -> crate::error::Result<Self>
+    {
+        if nr_cols == 0
+        {
+            return Err(crate::error::Error::InvalidNrBits(0, 1, String::from("brickwork columns")));
+        }
+        for &(row, col) in vertical_edges
+        {
+            if row + 1 >= nr_rows
+            {
+                return Err(crate::error::Error::InvalidQBit(row + 1));
+            }
+            if col >= nr_cols
+            {
+                return Err(crate::error::Error::InvalidQBit(col));
+            }
+        }
+
+        let mut edges = vertical_edges.to_vec();
+        edges.sort_unstable();
+        edges.dedup();
+        Ok(BrickworkState { nr_rows: nr_rows, nr_cols: nr_cols, vertical_edges: edges })
+    }
+
+    /// The number of rows in this state
+    pub fn nr_rows(&self) -> usize
+    {
+        self.nr_rows
+    }
+
+    /// The number of columns in this state
+    pub fn nr_cols(&self) -> usize
+    {
+        self.nr_cols
+    }
+
+    /// The vertical edges in this state, as `(row, col)` pairs
+    pub fn vertical_edges(&self) -> &[(usize, usize)]
+    {
+        &self.vertical_edges
+    }
+
+    /// Return whether the qubits on rows `row` and `row+1` in column `col`
+    /// are connected.
+    fn has_vertical_edge(&self, row: usize, col: usize) -> bool
+    {
+        self.vertical_edges.binary_search(&(row, col)).is_ok()
+    }
+
+    /// The rows of the qubits connected vertically to the qubit on row `row`
+    /// in column `col`.
+    fn vertical_neighbours(&self, row: usize, col: usize) -> Vec<usize>
+    {
+        let mut res = vec![];
+        if row > 0 && self.has_vertical_edge(row - 1, col)
+        {
+            res.push(row - 1);
+        }
+        if self.has_vertical_edge(row, col)
+        {
+            res.push(row + 1);
+        }
+        res
+    }
+}
+
+/// Index of the physical qubit, and of the classical bit, holding the graph
+/// state qubit on row `row` in column `col`. Only two columns are alive at any
+/// time, so qubits and classical bits are reused for every other column.
+fn slot(nr_rows: usize, row: usize, col: usize) -> usize
+{
+    (col % 2) * nr_rows + row
+}
+
+/// BQC server circuit.
+///
+/// Create the circuit with which the server measures the graph state
+/// `brickwork_state`, using measurement angles `angles` for all qubits except
+/// those in the last column, ordered by column first, and by row within a
+/// column. Qubit `j` is measured in the basis `(|0⟩ ± exp(iφ)|1⟩)/√2`, with
+/// outcome 0 for the `+` state. Rather than adapting the measurement angles to
+/// earlier outcomes, the byproduct operators on the remaining qubits are
+/// corrected by classically controlled Pauli gates. The qubits in the last
+/// column, which hold the output, are measured in the computational basis
+/// without correction, so the results should be decoded with the decoder
+/// from `bqc_client_protocol()`. The graph state is prepared one column
+/// ahead of the measurements, so that the circuit requires only
+/// `2·nr_rows` qubits and classical bits. An error is returned when the
+/// number of angles does not match the graph state.
+pub fn bqc_server_circuit(brickwork_state: &BrickworkState, angles: &[f64])
+    -> crate::error::Result<Circuit>
+{
+    let nr_rows = brickwork_state.nr_rows();
+    let nr_cols = brickwork_state.nr_cols();
+    if angles.len() != nr_rows * (nr_cols - 1)
+    {
+        return Err(crate::error::Error::InvalidNrBits(angles.len(), nr_rows * (nr_cols - 1),
+            String::from("BQC angles")));
+    }
+
+    let bit = |row: usize, col: usize| slot(nr_rows, row, col);
+    let mut circuit = Circuit::new(2 * nr_rows, 2 * nr_rows);
+    for col in 0..nr_cols
+    {
+        // Prepare this column, and entangle it with the previous one
+        for row in 0..nr_rows
+        {
+            if col >= 2
+            {
+                circuit.reset(bit(row, col))?;
+            }
+            circuit.h(bit(row, col))?;
+            if col > 0
+            {
+                circuit.add_gate(crate::gates::CZ::new(), &[bit(row, col - 1), bit(row, col)])?;
+            }
+        }
+        for row in 0..nr_rows
+        {
+            if brickwork_state.has_vertical_edge(row, col)
+            {
+                circuit.add_gate(crate::gates::CZ::new(), &[bit(row, col), bit(row + 1, col)])?;
+            }
+        }
+
+        if col == 0
+        {
+            continue;
+        }
+
+        // Measure the previous column, and correct the byproducts
+        let prev = col - 1;
+        for row in 0..nr_rows
+        {
+            let qbit = bit(row, prev);
+            circuit.u1(-angles[prev * nr_rows + row], qbit)?;
+            circuit.h(qbit)?;
+            circuit.measure(qbit, qbit)?;
+            if col < nr_cols - 1
+            {
+                circuit.add_conditional_gate(&[qbit], 1, crate::gates::X::new(), &[bit(row, col)])?;
+                for nb in brickwork_state.vertical_neighbours(row, col)
+                {
+                    circuit.add_conditional_gate(&[qbit], 1, crate::gates::Z::new(),
+                        &[bit(nb, col)])?;
+                }
+            }
+        }
+    }
+
+    for row in 0..nr_rows
+    {
+        let qbit = bit(row, nr_cols - 1);
+        circuit.measure(qbit, qbit)?;
+    }
+
+    Ok(circuit)
+}
+
+/// Add the measurement angles for a single-qubit gate with matrix `matrix` on
+/// row `row` to `columns`.
+fn add_single_qubit_columns(columns: &mut Vec<Vec<f64>>, nr_rows: usize, row: usize,
+    matrix: &crate::cmatrix::CMatrix)
+{
+    // U = R_z(φ)R_y(θ)R_z(λ) = R_z(φ+π/2)R_x(θ)R_z(λ-π/2), and up to a global
+    // phase R_z(a)R_x(b)R_z(c) = J(0)J(a)J(b)J(c), with J(α) = H·U1(α)
+    // implemented by measuring at angle -α.
+    let (theta, phi, lambda) = crate::gates::u3_angles(matrix);
+    let half_pi = ::std::f64::consts::FRAC_PI_2;
+    for &alpha in [lambda - half_pi, theta, phi + half_pi, 0.0].iter()
+    {
+        let mut column = vec![0.0; nr_rows];
+        column[row] = -alpha;
+        columns.push(column);
+    }
+}
+
+/// Add a `CZ` gate between rows `row` and `row+1` to the graph state, and
+/// the corresponding measurement angles to `columns`.
+fn add_cz_columns(columns: &mut Vec<Vec<f64>>, edges: &mut Vec<(usize, usize)>,
+    nr_rows: usize, row: usize)
+{
+    edges.push((row, columns.len()));
+    columns.push(vec![0.0; nr_rows]);
+    columns.push(vec![0.0; nr_rows]);
+}
+
+/// BQC client protocol.
+///
+/// Translate the gate circuit `computation` into the parameters of a blind
+/// computation: the graph state the server should prepare, the measurement
+/// angles for use in `bqc_server_circuit()`, and a decoder. The decoder takes
+/// the values of the classical bits of the server circuit, one value per bit,
+/// and returns the result of measuring the qubits of `computation` in the
+/// computational basis after the computation. The first column of the graph
+/// state maps its initial state |+⟩ to |0⟩. Every single-qubit gate is
+/// implemented in four columns, a `CZ` gate on adjacent qubits through an edge
+/// between their rows, and a `CX` gate on adjacent qubits as a `CZ` gate
+/// between Hadamard gates. An error is returned when the computation contains
+/// operations other than gates, or gates on more than one qubit that are not of
+/// this form.
+pub fn bqc_client_protocol(computation: &Circuit)
+    -> crate::error::Result<(BrickworkState, Vec<f64>, BqcDecoder)>
+{
+    use crate::instruction::Instruction;
+
+    let nr_rows = computation.nr_qbits();
+    let hadamard = crate::gates::Gate::matrix(&crate::gates::H::new());
+    let mut columns = vec![vec![0.0; nr_rows]];
+    let mut edges = vec![];
+    for (gate, bits) in computation.gate_ops()?
+    {
+        let instrs = if bits.len() == 2 { gate.instructions(bits) } else { vec![] };
+        match (bits.len(), instrs.as_slice())
+        {
+            (1, _) => {
+                add_single_qubit_columns(&mut columns, nr_rows, bits[0], &gate.matrix());
+            },
+            (2, &[Instruction::CZ(b0, b1)]) if b0 + 1 == b1 || b1 + 1 == b0 => {
+                add_cz_columns(&mut columns, &mut edges, nr_rows, b0.min(b1));
+            },
+            (2, &[Instruction::CX(c, t)]) if c + 1 == t || t + 1 == c => {
+                add_single_qubit_columns(&mut columns, nr_rows, t, &hadamard);
+                add_cz_columns(&mut columns, &mut edges, nr_rows, c.min(t));
+                add_single_qubit_columns(&mut columns, nr_rows, t, &hadamard);
+            },
+            _ => {
+                return Err(crate::error::Error::OpNotImplemented(
+                    String::from("bqc_client_protocol"), String::from(gate.description())));
+            }
+        }
+    }
+
+    let nr_cols = columns.len() + 1;
+    let angles = columns.concat();
+    let state = BrickworkState::new(nr_rows, nr_cols, &edges)?;
+    let decoder = move |outcomes: &[u64]| {
+        (0..nr_rows).map(|row| {
+                let output = outcomes[slot(nr_rows, row, nr_cols - 1)];
+                let byproduct = outcomes[slot(nr_rows, row, nr_cols - 2)];
+                (output ^ byproduct) & 1 == 1
+            })
+            .collect()
+    };
+
+    Ok((state, angles, Box::new(decoder)))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{bqc_client_protocol, bqc_server_circuit, BqcDecoder, BrickworkState};
+    use crate::circuit::Circuit;
+    use rand::SeedableRng;
+
+    /// Run the blind version of `computation`, and return the histogram of
+    /// decoded results, with qubit `i` in bit `i` of the key.
+    fn run_blind(computation: &Circuit, nr_shots: usize, seed: u64)
+        -> ::std::collections::HashMap<u64, usize>
+    {
+        let (state, angles, decoder): (BrickworkState, Vec<f64>, BqcDecoder)
+            = bqc_client_protocol(computation).unwrap();
+        let mut circuit = bqc_server_circuit(&state, &angles).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+
+        let mut res = ::std::collections::HashMap::new();
+        for (&key, &count) in circuit.histogram().unwrap().iter()
+        {
+            let outcomes: Vec<u64> = (0..circuit.nr_cbits()).map(|i| (key >> i) & 1).collect();
+            let value = decoder(&outcomes).iter().enumerate()
+                .fold(0, |v, (i, &b)| v | ((b as u64) << i));
+            *res.entry(value).or_insert(0) += count;
+        }
+        res
+    }
+
+    #[test]
+    fn test_brickwork_state()
+    {
+        let state = BrickworkState::new(3, 5, &[(1, 3), (0, 2), (1, 3)]).unwrap();
+        assert_eq!(state.nr_rows(), 3);
+        assert_eq!(state.nr_cols(), 5);
+        assert_eq!(state.vertical_edges(), &[(0, 2), (1, 3)]);
+        assert_eq!(state.vertical_neighbours(1, 3), vec![2]);
+        assert_eq!(state.vertical_neighbours(2, 3), vec![1]);
+        assert_eq!(state.vertical_neighbours(1, 2), vec![0]);
+
+        assert_eq!(BrickworkState::new(3, 5, &[(2, 1)]), Err(crate::error::Error::InvalidQBit(3)));
+        assert_eq!(BrickworkState::new(3, 5, &[(0, 5)]), Err(crate::error::Error::InvalidQBit(5)));
+        assert!(bqc_server_circuit(&state, &[0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_single_qubit_gates()
+    {
+        let mut computation = Circuit::new(2, 0);
+        assert_eq!(computation.x(0), Ok(()));
+        assert_eq!(computation.h(1), Ok(()));
+        assert_eq!(computation.rz(0.7, 1), Ok(()));
+        assert_eq!(computation.h(1), Ok(()));
+        assert_eq!(computation.rx(::std::f64::consts::PI, 1), Ok(()));
+
+        let hist = run_blind(&computation, 256, 1234);
+        let p01: f64 = 0.35f64.cos().powi(2);
+        let count = *hist.get(&0b01).unwrap_or(&0) as f64;
+        assert_eq!(hist.keys().filter(|&&k| k & 1 != 1).count(), 0);
+        assert!((count / 256.0 - (1.0 - p01)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_entangling_gates()
+    {
+        // Bell state
+        let mut computation = Circuit::new(2, 0);
+        assert_eq!(computation.h(0), Ok(()));
+        assert_eq!(computation.cx(0, 1), Ok(()));
+        let hist = run_blind(&computation, 256, 2345);
+        assert_eq!(hist.keys().filter(|&&k| k != 0b00 && k != 0b11).count(), 0);
+        assert!(*hist.get(&0b11).unwrap_or(&0) > 96);
+        assert!(*hist.get(&0b00).unwrap_or(&0) > 96);
+
+        // Deterministic three-qubit computation
+        let mut computation = Circuit::new(3, 0);
+        assert_eq!(computation.x(0), Ok(()));
+        assert_eq!(computation.cx(0, 1), Ok(()));
+        assert_eq!(computation.add_gate(crate::gates::CZ::new(), &[2, 1]), Ok(()));
+        assert_eq!(computation.cx(2, 1), Ok(()));
+        let hist = run_blind(&computation, 64, 3456);
+        assert_eq!(hist.get(&0b011), Some(&64));
+
+        // Phase kickback through CZ
+        let mut computation = Circuit::new(2, 0);
+        assert_eq!(computation.x(0), Ok(()));
+        assert_eq!(computation.h(1), Ok(()));
+        assert_eq!(computation.add_gate(crate::gates::CZ::new(), &[0, 1]), Ok(()));
+        assert_eq!(computation.h(1), Ok(()));
+        let hist = run_blind(&computation, 64, 4567);
+        assert_eq!(hist.get(&0b11), Some(&64));
+
+        // Non-adjacent qubits are not supported
+        let mut computation = Circuit::new(3, 0);
+        assert_eq!(computation.cx(0, 2), Ok(()));
+        assert!(bqc_client_protocol(&computation).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Trait for the state of a single branch in a `BranchedState`
+///
+/// Trait `BranchState` describes the operations a quantum state
+/// representation should support in order to be used as a simulation backend
+/// through `BranchedState`.
+pub trait BranchState: Clone
+{
+    /// The number of qubits in this state
+    fn nr_bits(&self) -> usize;
+    /// Apply gate `gate` on the qubits in `bits`.
+    fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: crate::gates::Gate + ?Sized;
+    /// Apply the noise channel with Kraus operators `kraus` on the qubits in
+    /// `bits`. Representations that cannot describe mixed states ignore
+    /// noise.
+    fn apply_channel(&mut self, _kraus: &[crate::cmatrix::CMatrix], _bits: &[usize])
+        -> crate::error::Result<()>
+    {
+        Ok(())
+    }
+    /// The probability of finding qubit `bit` in state |1⟩
+    fn probability_one(&self, bit: usize) -> f64;
+    /// Project qubit `bit` onto |0⟩ (`value = false`) or |1⟩ (`value = true`),
+    /// where `prob` is the probability of this outcome, and renormalize the
+    /// state.
+    fn project(&mut self, bit: usize, value: bool, prob: f64);
+    /// The expectation value of Pauli string `obs` in this state
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString) -> f64;
+    /// The normalized coefficient vector of this state, or `None` for
+    /// representations of mixed states, which is the default.
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>
+    {
+        None
+    }
+    /// Reset the state to |00...0⟩
+    fn reset_all(&mut self);
+}
+
+/// Quantum state consisting of separately evolving branches
+///
+/// Struct `BranchedState` tracks the quantum state over a number of runs of
+/// a circuit. Runs that have the same history of measurement outcomes share
+/// a single branch in the state, so that the state only needs to be copied
+/// when a measurement result differs between runs.
+#[derive(Clone)]
+pub struct BranchedState<S: BranchState>
+{
+    /// The number of qubits in this state
+    nr_bits: usize,
+    /// The number of separate runs for evolving this state
+    nr_shots: usize,
+    /// Run counts for each branch
+    counts: Vec<usize>,
+    /// The quantum states for each branch
+    states: Vec<S>
+}
+
+impl<S: BranchState> BranchedState<S>
+{
+    /// Create a new state, starting from `state` for all of the `nr_shots`
+    /// runs.
+    pub fn new(state: S, nr_shots: usize) -> Self
+    {
+        BranchedState
+        {
+            nr_bits: state.nr_bits(),
+            nr_shots: nr_shots,
+            counts: vec![nr_shots],
+            states: vec![state]
+        }
+    }
+
+    /// The states of the separate branches, along with the number of runs
+    /// in each branch.
+    pub fn branches(&self) -> impl Iterator<Item=(&S, usize)>
+    {
+        self.states.iter().zip(self.counts.iter().cloned())
+    }
+
+    /// Measure qubit `qbit` in every branch, splitting branches where both
+    /// outcomes occur. Return the outcome for every new branch.
+    fn collapse<R: rand::Rng>(&mut self, qbit: usize, rng: &mut R)
+        -> crate::error::Result<Vec<bool>>
+    {
+        if qbit >= self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidQBit(qbit));
+        }
+
+        let mut new_states = vec![];
+        let mut new_counts = vec![];
+        let mut outcomes = vec![];
+        for (mut state, &count) in self.states.drain(..).zip(self.counts.iter())
+        {
+            // Guard against round-off errors taking the probability out of
+            // range
+            let p1 = state.probability_one(qbit).min(1.0);
+            let p1 = p1.max(0.0);
+            let distribution = rand_distr::Binomial::new(count as u64, 1.0 - p1).unwrap();
+            let n0 = rng.sample(distribution) as usize;
+
+            if n0 > 0 && n0 < count
+            {
+                let mut s0 = state.clone();
+                s0.project(qbit, false, 1.0 - p1);
+                new_states.push(s0);
+                new_counts.push(n0);
+                outcomes.push(false);
+            }
+            if n0 == count
+            {
+                state.project(qbit, false, 1.0 - p1);
+                new_states.push(state);
+                new_counts.push(count);
+                outcomes.push(false);
+            }
+            else
+            {
+                state.project(qbit, true, p1);
+                new_states.push(state);
+                new_counts.push(count - n0);
+                outcomes.push(true);
+            }
+        }
+
+        self.states = new_states;
+        self.counts = new_counts;
+
+        Ok(outcomes)
+    }
+}
+
+impl<S: BranchState> crate::qustate::QuState for BranchedState<S>
+{
+    fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: crate::gates::Gate + ?Sized
+    {
+        for state in self.states.iter_mut()
+        {
+            state.apply_gate(gate, bits)?;
+        }
+        Ok(())
+    }
+
+    fn apply_unary_gate_all<G>(&mut self, gate: &G) -> crate::error::Result<()>
+    where G: crate::gates::Gate + ?Sized
+    {
+        for bit in 0..self.nr_bits
+        {
+            self.apply_gate(gate, &[bit])?;
+        }
+        Ok(())
+    }
+
+    fn apply_conditional_gate<G>(&mut self, control: &[bool], gate: &G,
+        bits: &[usize]) -> crate::error::Result<()>
+    where G: crate::gates::Gate + ?Sized
+    {
+        if control.len() != self.nr_shots
+        {
+            return Err(crate::error::Error::InvalidNrControlBits(control.len(),
+                self.nr_shots, String::from(gate.description())));
+        }
+
+        let ranges = crate::qustate::collect_conditional_ranges(&self.counts,
+            control);
+        let mut new_states = Vec::with_capacity(ranges.len());
+        for &(idx, _, apply) in ranges.iter()
+        {
+            let mut state = self.states[idx].clone();
+            if apply
+            {
+                state.apply_gate(gate, bits)?;
+            }
+            new_states.push(state);
+        }
+
+        self.states = new_states;
+        self.counts = ranges.iter().map(|t| t.1).collect();
+
+        Ok(())
+    }
+
+    fn apply_channel(&mut self, kraus: &[crate::cmatrix::CMatrix], bits: &[usize])
+        -> crate::error::Result<()>
+    {
+        for state in self.states.iter_mut()
+        {
+            state.apply_channel(kraus, bits)?;
+        }
+        Ok(())
+    }
+
+    fn measure<R: rand::Rng>(&mut self, qbit: usize, rng: &mut R)
+        -> crate::error::Result<ndarray::Array1<u64>>
+    {
+        let mut res = ndarray::Array1::zeros(self.nr_shots);
+        self.measure_into(qbit, 0, &mut res, rng)?;
+        Ok(res)
+    }
+
+    fn measure_into<R: rand::Rng>(&mut self, qbit: usize, cbit: usize,
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let outcomes = self.collapse(qbit, rng)?;
+
+        let one_mask = 1 << cbit;
+        let zero_mask = !one_mask;
+        let mut res_start = 0;
+        for (&value, &count) in outcomes.iter().zip(self.counts.iter())
+        {
+            let mut slice = res.slice_mut(s![res_start..res_start+count]);
+            if value
+            {
+                slice.map_inplace(|b| *b |= one_mask);
+            }
+            else
+            {
+                slice.map_inplace(|b| *b &= zero_mask);
+            }
+            res_start += count;
+        }
+
+        Ok(())
+    }
+
+    fn measure_all<R: rand::Rng>(&mut self, rng: &mut R)
+        -> crate::error::Result<ndarray::Array1<u64>>
+    {
+        let mut res = ndarray::Array1::zeros(self.nr_shots);
+        let cbits: Vec<usize> = (0..self.nr_bits).collect();
+        self.measure_all_into(&cbits, &mut res, rng)?;
+        Ok(res)
+    }
+
+    fn measure_all_into<R: rand::Rng>(&mut self, cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        if cbits.len() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), self.nr_bits));
+        }
+
+        for (qbit, &cbit) in cbits.iter().enumerate()
+        {
+            self.measure_into(qbit, cbit, res, rng)?;
+        }
+
+        Ok(())
+    }
+
+    fn peek_into<R: rand::Rng>(&self, qbit: usize, cbit: usize,
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        // The results for the runs are the same as when measuring a copy of
+        // the state, since splitting a branch preserves the order of the runs.
+        self.clone().measure_into(qbit, cbit, res, rng)
+    }
+
+    fn peek_all_into<R: rand::Rng>(&mut self, cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        self.clone().measure_all_into(cbits, res, rng)
+    }
+
+    fn partial_measure_peek<R: rand::Rng>(&self, qbits: &[usize], cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        if qbits.len() != cbits.len()
+        {
+            return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), qbits.len()));
+        }
+
+        let mut copy = self.clone();
+        for (&qbit, &cbit) in qbits.iter().zip(cbits)
+        {
+            copy.measure_into(qbit, cbit, res, rng)?;
+        }
+
+        Ok(())
+    }
+
+    fn expectation_into(&self, obs: &crate::stabilizer::PauliString, cbit: usize,
+        res: &mut ndarray::Array1<u64>) -> crate::error::Result<()>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let one_mask = 1 << cbit;
+        let zero_mask = !one_mask;
+
+        let mut res_start = 0;
+        for (state, &count) in self.states.iter().zip(self.counts.iter())
+        {
+            let mut slice = res.slice_mut(s![res_start..res_start+count]);
+            if state.expectation_value(obs) > 0.0
+            {
+                slice.map_inplace(|b| *b |= one_mask);
+            }
+            else
+            {
+                slice.map_inplace(|b| *b &= zero_mask);
+            }
+            res_start += count;
+        }
+
+        Ok(())
+    }
+
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString)
+        -> crate::error::Result<f64>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+
+        let sum: f64 = self.states.iter().zip(self.counts.iter())
+            .map(|(state, &count)| count as f64 * state.expectation_value(obs))
+            .sum();
+        Ok(sum / self.nr_shots as f64)
+    }
+
+    fn reset<R: rand::Rng>(&mut self, bit: usize, rng: &mut R)
+        -> crate::error::Result<()>
+    {
+        let outcomes = self.collapse(bit, rng)?;
+        for (state, &value) in self.states.iter_mut().zip(outcomes.iter())
+        {
+            if value
+            {
+                state.apply_gate(&crate::gates::X::new(), &[bit])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn reset_all(&mut self)
+    {
+        self.states.truncate(1);
+        self.states[0].reset_all();
+        self.counts = vec![self.nr_shots];
+    }
+
+    fn are_shots_entangled(&self) -> bool
+    {
+        self.counts.len() > 1
+    }
+
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>
+    {
+        if self.nr_shots == 1
+        {
+            self.states[0].statevector()
+        }
+        else
+        {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::BranchedState;
+    use crate::density::DensityMatrix;
+    use crate::gates::{CX, H, X};
+    use crate::qustate::QuState;
+    use crate::stabilizer::{PauliOp, PauliString};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_measure()
+    {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1234);
+        let nr_shots = 1024;
+
+        let mut s = BranchedState::new(DensityMatrix::new(2), nr_shots);
+        assert_eq!(s.apply_gate(&X::new(), &[1]), Ok(()));
+        let res = s.measure_all(&mut rng).unwrap();
+        assert!(res.iter().all(|&b| b == 0b10));
+        assert_eq!(s.branches().count(), 1);
+
+        let mut s = BranchedState::new(DensityMatrix::new(2), nr_shots);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        let res = s.measure_all(&mut rng).unwrap();
+        assert!(res.iter().all(|&b| b == 0b00 || b == 0b11));
+        let nr_ones = res.iter().filter(|&&b| b == 0b11).count();
+        assert!(nr_ones > 400 && nr_ones < 624);
+        assert_eq!(s.branches().count(), 2);
+        assert_eq!(s.branches().map(|(_, count)| count).sum::<usize>(), nr_shots);
+
+        assert_eq!(s.measure(2, &mut rng), Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_peek()
+    {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4321);
+        let nr_shots = 512;
+
+        let mut s = BranchedState::new(DensityMatrix::new(2), nr_shots);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        let mut res = ndarray::Array1::zeros(nr_shots);
+        assert_eq!(s.peek_all_into(&[0, 1], &mut res, &mut rng), Ok(()));
+        assert!(res.iter().all(|&b| b == 0b00 || b == 0b11));
+        assert_eq!(s.branches().count(), 1);
+
+        let mut res = ndarray::Array1::zeros(nr_shots);
+        assert_eq!(s.partial_measure_peek(&[1], &[2], &mut res, &mut rng), Ok(()));
+        assert!(res.iter().all(|&b| b == 0 || b == 0b100));
+        assert_eq!(s.branches().count(), 1);
+    }
+
+    #[test]
+    fn test_conditional_reset()
+    {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5678);
+        let nr_shots = 100;
+
+        let mut s = BranchedState::new(DensityMatrix::new(2), nr_shots);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        let res = s.measure(0, &mut rng).unwrap();
+        let control: Vec<bool> = res.iter().map(|&b| b == 1).collect();
+        assert_eq!(s.apply_conditional_gate(&control, &X::new(), &[1]), Ok(()));
+        let res2 = s.measure(1, &mut rng).unwrap();
+        assert_eq!(res, res2);
+
+        assert_eq!(s.reset(0, &mut rng), Ok(()));
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::I]);
+        let mut res = ndarray::Array1::zeros(nr_shots);
+        assert_eq!(s.expectation_into(&obs, 0, &mut res), Ok(()));
+        assert!(res.iter().all(|&b| b == 1));
+
+        s.reset_all();
+        assert_eq!(s.branches().count(), 1);
+        let res = s.measure_all(&mut rng).unwrap();
+        assert!(res.iter().all(|&b| b == 0));
+    }
+}
@@ -12,11 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::export::{CircuitGate, CQasm, OpenQasm};
+use crate::export::{CircuitGate, CQasm, OpenQasm, TketJson};
 use crate::qustate::QuState;
 
+/// The maximum number of qubits for which to compute the unitary matrix when
+/// searching for identities
+const MAX_IDENTITY_BITS: usize = 10;
+/// The maximum number of gates in a sequence when searching for local
+/// identities during aggressive optimization
+const MAX_AGGRESSIVE_WINDOW: usize = 8;
+/// The maximum number of gates in a sequence when searching for identities in
+/// non-Clifford circuits that are too large to compute the full unitary
+const MAX_IDENTITY_WINDOW: usize = 8;
+
 /// Basis in which to perform measurements
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Basis
 {
     /// Pauli `X` basis
@@ -28,7 +39,8 @@ pub enum Basis
 }
 
 /// A single operation in a circuit
-enum CircuitOp
+#[derive(Clone)]
+pub(crate) enum CircuitOp
 {
     /// Apply a gate to the state
     Gate(Box<dyn CircuitGate>, Vec<usize>),
@@ -36,6 +48,8 @@ enum CircuitOp
     ConditionalGate(Vec<usize>, u64, Box<dyn CircuitGate>, Vec<usize>),
     /// Reset a qubit to |0⟩
     Reset(usize),
+    /// Reset a qubit to |0⟩ (`false`) or |1⟩ (`true`)
+    ResetTo(usize, bool),
     /// Reset the quantum state to |00...0⟩
     ResetAll,
     /// Measure a qubit in a certain basis
@@ -46,12 +60,39 @@ enum CircuitOp
     Peek(usize, usize, Basis),
     /// Measure all qubits in a certain basis without affecting state
     PeekAll(Vec<usize>, Basis),
+    /// Measure a subset of qubits without affecting state
+    PeekPartial(Vec<usize>, Vec<usize>),
     /// Prevent gate reordering on the associated bits across the barrier
-    Barrier(Vec<usize>)
+    Barrier(Vec<usize>),
+    /// Apply a noise channel, described by its Kraus operators
+    Noise(Vec<crate::cmatrix::CMatrix>, Vec<usize>),
+    /// Compute the sign of an expectation value without affecting state
+    ExpectationMeasure(crate::stabilizer::PauliString, usize)
 }
 
 impl CircuitOp
 {
+    /// Return the qubits this operation acts on, or `None` if it acts on all
+    /// qubits
+    pub(crate) fn qbits(&self) -> Option<&[usize]>
+    {
+        match *self
+        {
+            CircuitOp::Gate(_, ref bits) => Some(bits),
+            CircuitOp::ConditionalGate(_, _, _, ref bits) => Some(bits),
+            CircuitOp::Reset(ref bit) => Some(::std::slice::from_ref(bit)),
+            CircuitOp::ResetTo(ref bit, _) => Some(::std::slice::from_ref(bit)),
+            CircuitOp::Measure(ref bit, _, _) => Some(::std::slice::from_ref(bit)),
+            CircuitOp::Peek(ref bit, _, _) => Some(::std::slice::from_ref(bit)),
+            CircuitOp::PeekPartial(ref bits, _) => Some(bits),
+            CircuitOp::Barrier(ref bits) => Some(bits),
+            CircuitOp::Noise(_, ref bits) => Some(bits),
+            CircuitOp::ResetAll | CircuitOp::MeasureAll(_, _)
+                | CircuitOp::PeekAll(_, _)
+                | CircuitOp::ExpectationMeasure(_, _) => None
+        }
+    }
+
     /// Return whether a circuit operation is a stabilizer operation
     fn is_stabilizer(&self) -> bool
     {
@@ -59,18 +100,39 @@ impl CircuitOp
         {
             CircuitOp::Gate(ref gate, _) => gate.is_stabilizer(),
             CircuitOp::ConditionalGate(_, _, ref gate, _) => gate.is_stabilizer(),
+            // Computing expectation values is done in vector representation
+            CircuitOp::ExpectationMeasure(_, _) => false,
             _ => true
         }
     }
 }
 
+/// The gate type of `gate`, i.e. its description without the parameter values
+pub(crate) fn gate_type<G>(gate: &G) -> &str
+where G: crate::gates::Gate + ?Sized
+{
+    let desc = gate.description();
+    if gate.parameters().is_empty()
+    {
+        desc
+    }
+    else
+    {
+        desc.split('(').next().unwrap_or(desc)
+    }
+}
+
 /// Enumeration for the possible representations of the quantum state
 pub enum QuStateRepr
 {
     /// Stabilizer tableau
     Stabilizer(crate::stabilizer::StabilizerState),
     /// Coefficient vector
-    Vector(crate::vectorstate::VectorState)
+    Vector(crate::vectorstate::VectorState),
+    /// Density matrix
+    Density(crate::branched::BranchedState<crate::density::DensityMatrix>),
+    /// Matrix product state
+    MPS(crate::branched::BranchedState<crate::mps::MPSState>)
 }
 
 impl QuStateRepr
@@ -88,6 +150,161 @@ impl QuStateRepr
     {
         QuStateRepr::Stabilizer(crate::stabilizer::StabilizerState::new(nr_qbits, nr_shots))
     }
+
+    /// Create a new density matrix backend for `nr_qbits` qbits, averaging
+    /// measurements over `nr_shots` runs.
+    pub fn density(nr_qbits: usize, nr_shots: usize) -> Self
+    {
+        QuStateRepr::Density(crate::branched::BranchedState::new(
+            crate::density::DensityMatrix::new(nr_qbits), nr_shots))
+    }
+
+    /// Create a new matrix product state backend for `nr_qbits` qbits, with
+    /// bond dimension at most `max_bond_dim`, averaging measurements over
+    /// `nr_shots` runs.
+    pub fn mps(nr_qbits: usize, nr_shots: usize, max_bond_dim: usize) -> Self
+    {
+        QuStateRepr::MPS(crate::branched::BranchedState::new(
+            crate::mps::MPSState::with_max_bond_dimension(nr_qbits, max_bond_dim), nr_shots))
+    }
+}
+
+/// Enumeration for the simulation backends a circuit can be executed with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimulationMode
+{
+    /// Use a stabilizer tableau when the circuit is a stabilizer circuit,
+    /// and a coefficient vector otherwise
+    Automatic,
+    /// Describe the state by its coefficient vector
+    Statevector,
+    /// Describe the state by its density matrix. This is the only mode in
+    /// which noise operations are taken into account.
+    DensityMatrix,
+    /// Describe the state by a stabilizer tableau. Only circuits consisting of
+    /// Clifford operations can be simulated in this mode.
+    Stabilizer,
+    /// Describe the state as a matrix product state, with the given maximum
+    /// bond dimension. Only gates on one or two qubits are supported.
+    MPS(usize)
+}
+
+/// Configuration of the circuit optimization pipeline
+///
+/// Struct `OptimizationConfig` selects which passes are run by
+/// `Circuit::optimize()`. Use `OptimizationConfig::level()` to obtain the
+/// default configuration for an optimization level, and adjust individual
+/// fields where needed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizationConfig
+{
+    /// Remove sequences of gates that implement the identity
+    pub cancel_adjacent_gates: bool,
+    /// Merge subsequent rotations around the same axis on the same qubit
+    pub merge_rotations: bool,
+    /// Decompose multi-qubit gates into single-qubit gates and `CX` gates
+    pub decompose_multi_qubit: bool,
+    /// Allow gates to be cancelled or merged across barriers
+    pub commute_past_barriers: bool,
+    /// The optimization level: 0 for none, 1 for light, 2 for aggressive
+    /// optimization. At level 1, only pairs of adjacent gates are cancelled,
+    /// at level 2 and higher, longer sequences are searched as well.
+    pub optimization_level: u8
+}
+
+impl OptimizationConfig
+{
+    /// Create a new optimization configuration.
+    ///
+    /// Create the default configuration for optimization level `level`. At
+    /// level 0, no optimizations are performed. At level 1, gate cancellation
+    /// and rotation merging are enabled, and at level 2 and higher, multi-qubit
+    /// gates are decomposed as well and barriers are ignored.
+    pub fn level(level: u8) -> Self
+    {
+        OptimizationConfig
+        {
+            cancel_adjacent_gates: level >= 1,
+            merge_rotations: level >= 1,
+            decompose_multi_qubit: level >= 2,
+            commute_past_barriers: level >= 2,
+            optimization_level: level
+        }
+    }
+}
+
+/// Timing statistics of a circuit execution
+///
+/// Struct `CircuitTimingStats` holds the timing of a circuit, as scheduled by
+/// `Circuit::execute_with_timing()`, together with estimates of the errors
+/// due to relaxation of the qubits while they are idle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitTimingStats
+{
+    /// The sum of the durations of all operations in the circuit
+    pub total_time: f64,
+    /// The duration of the circuit when operations on different qubits are
+    /// run in parallel
+    pub critical_path_time: f64,
+    /// For each qubit, the time within the critical path it is not operated on
+    pub idle_times: Vec<f64>,
+    /// For each qubit, the probability `1 - exp(-t/T1)` of energy relaxation
+    /// during its idle time `t`
+    pub t1_errors: Vec<f64>,
+    /// For each qubit, the probability `1 - exp(-t/T2)` of dephasing during its
+    /// idle time `t`
+    pub t2_errors: Vec<f64>
+}
+
+/// Resource estimate of a circuit
+///
+/// Struct `ResourceEstimate` holds the number of gates of various types in a
+/// circuit, along with its depth, as computed by `Circuit::resource_estimate()`.
+/// Conditional gates are counted in the same way as unconditional gates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResourceEstimate
+{
+    /// The total number of gates
+    pub total_gates: usize,
+    /// The number of gates operating on a single qubit
+    pub single_qubit_gates: usize,
+    /// The number of gates operating on two qubits
+    pub two_qubit_gates: usize,
+    /// The number of `T` and `T`<sup>`†`</sup> gates
+    pub t_gates: usize,
+    /// The number of Clifford gates
+    pub clifford_gates: usize,
+    /// The number of qubit measurements
+    pub measurements: usize,
+    /// The number of qubit resets
+    pub resets: usize,
+    /// The depth of the circuit, as returned by `Circuit::depth()`
+    pub depth: usize,
+    /// The number of layers of `T` and `T`<sup>`†`</sup> gates
+    pub t_depth: usize
+}
+
+impl ::std::fmt::Display for ResourceEstimate
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        let rows = [
+            ("Total gates", self.total_gates),
+            ("Single-qubit gates", self.single_qubit_gates),
+            ("Two-qubit gates", self.two_qubit_gates),
+            ("T gates", self.t_gates),
+            ("Clifford gates", self.clifford_gates),
+            ("Measurements", self.measurements),
+            ("Resets", self.resets),
+            ("Depth", self.depth),
+            ("T depth", self.t_depth)
+        ];
+        for (name, value) in rows.iter()
+        {
+            writeln!(f, "{:<20}{:>8}", name, value)?;
+        }
+        Ok(())
+    }
 }
 
 /// A quantum circuit
@@ -105,7 +322,19 @@ pub struct Circuit
     /// The classial state of the system
     c_state: Option<ndarray::Array1<u64>>,
     /// The operations to perform on the state
-    ops: Vec<CircuitOp>
+    ops: Vec<CircuitOp>,
+    /// Whether to precompute gate matrices when executing the circuit
+    cache_gates: bool,
+    /// The backend to use when executing the circuit
+    simulation_mode: SimulationMode,
+    /// Warnings emitted during the last execution of the circuit
+    warnings: Vec<crate::error::Warning>,
+    /// Whether transformations of this circuit should keep track of the
+    /// global phase
+    phase_tracking: bool,
+    /// Random number generator for sampling measurements, when a seed has
+    /// been set with `with_seed()`
+    rng: Option<rand::rngs::StdRng>
 }
 
 impl Circuit
@@ -122,7 +351,12 @@ impl Circuit
             nr_cbits: nr_cbits,
             q_state: None,
             c_state: None,
-            ops: vec![]
+            ops: vec![],
+            cache_gates: false,
+            simulation_mode: SimulationMode::Automatic,
+            warnings: vec![],
+            phase_tracking: false,
+            rng: None
         }
     }
 
@@ -132,6 +366,58 @@ impl Circuit
         self.nr_qbits
     }
 
+    /// The operations in this circuit
+    pub(crate) fn ops(&self) -> &[CircuitOp]
+    {
+        &self.ops
+    }
+
+    /// Create a new circuit with the same registers and phase tracking
+    /// setting as this circuit, holding operations `ops`.
+    pub(crate) fn with_ops(&self, ops: Vec<CircuitOp>) -> Self
+    {
+        let mut res = Circuit::new(self.nr_qbits, self.nr_cbits);
+        res.ops = ops;
+        res.phase_tracking = self.phase_tracking;
+        res
+    }
+
+    /// Return the gates in this circuit, along with the bits they operate on.
+    /// Barriers are ignored. If the circuit is not purely unitary, i.e. when
+    /// it contains measurements, resets, conditional gates or noise,
+    /// `Error::NotAGate` is returned.
+    pub(crate) fn gate_ops(&self) -> crate::error::Result<Vec<(&dyn CircuitGate, &[usize])>>
+    {
+        let mut res = vec![];
+        for op in self.ops.iter()
+        {
+            let desc = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => {
+                        res.push((gate.as_ref(), bits.as_slice()));
+                        continue;
+                    },
+                    CircuitOp::Barrier(_) => { continue; },
+                    CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                        format!("conditional {}", gate.description())
+                    },
+                    CircuitOp::Reset(_)
+                    | CircuitOp::ResetTo(_, _)
+                    | CircuitOp::ResetAll => String::from("reset"),
+                    CircuitOp::Measure(_, _, _)
+                    | CircuitOp::MeasureAll(_, _)
+                    | CircuitOp::Peek(_, _, _)
+                    | CircuitOp::PeekAll(_, _)
+                    | CircuitOp::PeekPartial(_, _)
+                    | CircuitOp::ExpectationMeasure(_, _) => String::from("measurement"),
+                    CircuitOp::Noise(_, _) => String::from("noise")
+                };
+            return Err(crate::error::Error::NotAGate(desc));
+        }
+
+        Ok(res)
+    }
+
     /// The number of classical bits in this circuit
     pub fn nr_cbits(&self) -> usize
     {
@@ -144,6 +430,362 @@ impl Circuit
         self.ops.iter().all(|op| op.is_stabilizer())
     }
 
+    /// Convert to a stabilizer circuit
+    ///
+    /// Convert this circuit into a `StabilizerCircuit`, which can only hold
+    /// Clifford gates and measurements. If this circuit contains a gate that
+    /// is not a Clifford gate, a `NotAStabilizer` error with the description
+    /// of the first such gate is returned. Other operations that cannot be
+    /// represented, like resets or conditional gates, result in a
+    /// `NotACliffordCircuit` error.
+    pub fn to_stabilizer_circuit(&self) -> crate::error::Result<crate::stabilizer::StabilizerCircuit>
+    {
+        crate::stabilizer::StabilizerCircuit::from_circuit(self)
+    }
+
+    /// Total cost of this circuit.
+    ///
+    /// Return the sum of the costs of all gates in this circuit, as estimated
+    /// by the gates' `cost()` method.
+    pub fn total_cost(&self) -> f64
+    {
+        self.ops.iter().map(|op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _) => gate.cost(),
+                _ => 0.0
+            }
+        }).sum()
+    }
+
+    /// Cost per gate type.
+    ///
+    /// Return the total cost of the gates in this circuit, summed over gates
+    /// with the same description.
+    pub fn cost_breakdown(&self) -> ::std::collections::HashMap<&str, f64>
+    {
+        let mut res = ::std::collections::HashMap::new();
+        for op in self.ops.iter()
+        {
+            if let CircuitOp::Gate(ref gate, _) = *op
+            {
+                *res.entry(gate.description()).or_insert(0.0) += gate.cost();
+            }
+        }
+        res
+    }
+
+    /// Cost of the critical path.
+    ///
+    /// Return the cost of the most expensive path through this circuit, where
+    /// each gate contributes its cost to the path. Gates acting on multiple
+    /// qubits, as well as barriers and operations on the full quantum state,
+    /// synchronize the paths through the qubits they operate on.
+    pub fn critical_path_cost(&self) -> f64
+    {
+        let mut costs = vec![0.0; self.nr_qbits];
+        let all_bits: Vec<usize> = (0..self.nr_qbits).collect();
+        for op in self.ops.iter()
+        {
+            let (bits, cost) = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => (bits.as_slice(), gate.cost()),
+                    CircuitOp::Barrier(ref bits) => (bits.as_slice(), 0.0),
+                    CircuitOp::ResetAll
+                    | CircuitOp::MeasureAll(_, _)
+                    | CircuitOp::PeekAll(_, _)
+                    | CircuitOp::ExpectationMeasure(_, _) => (all_bits.as_slice(), 0.0),
+                    _ => continue
+                };
+
+            let end = bits.iter().map(|&b| costs[b]).fold(0.0, f64::max) + cost;
+            for &bit in bits
+            {
+                costs[bit] = end;
+            }
+        }
+
+        costs.into_iter().fold(0.0, f64::max)
+    }
+
+    /// Depth of this circuit.
+    ///
+    /// Return the number of layers of gates in this circuit, where each gate
+    /// is placed in the layer following the last layer in which any of its
+    /// qubits was used. Conditional gates count in the same way as
+    /// unconditional gates. Barriers and operations on the full quantum
+    /// state do not add a layer themselves, but synchronize the qubits they
+    /// operate on.
+    pub fn depth(&self) -> usize
+    {
+        let mut layers = vec![0; self.nr_qbits];
+        let all_bits: Vec<usize> = (0..self.nr_qbits).collect();
+        for op in self.ops.iter()
+        {
+            let (bits, count) = match *op
+                {
+                    CircuitOp::Gate(_, ref bits)
+                    | CircuitOp::ConditionalGate(_, _, _, ref bits) => (bits.as_slice(), 1),
+                    CircuitOp::Barrier(ref bits) => (bits.as_slice(), 0),
+                    CircuitOp::ResetAll
+                    | CircuitOp::MeasureAll(_, _)
+                    | CircuitOp::PeekAll(_, _)
+                    | CircuitOp::ExpectationMeasure(_, _) => (all_bits.as_slice(), 0),
+                    _ => continue
+                };
+
+            let layer = bits.iter().map(|&b| layers[b]).max().unwrap_or(0) + count;
+            for &bit in bits
+            {
+                layers[bit] = layer;
+            }
+        }
+
+        layers.into_iter().max().unwrap_or(0)
+    }
+
+    /// The number of unconditional gates in this circuit
+    pub fn gate_count(&self) -> usize
+    {
+        self.ops.iter().filter(|op| matches!(op, CircuitOp::Gate(_, _))).count()
+    }
+
+    /// Estimate the resources needed for this circuit
+    ///
+    /// Count the number of gates in this circuit, classified by the number
+    /// of qubits they operate on and whether they are Clifford gates, as
+    /// well as the number of `T` and `T`<sup>`†`</sup> gates, measurements,
+    /// and resets. The `T` depth of the circuit is computed in the same way
+    /// as the depth in `depth()`, where only `T` and `T`<sup>`†`</sup>
+    /// gates add a layer. Other gates only synchronize the qubits they
+    /// operate on.
+    pub fn resource_estimate(&self) -> ResourceEstimate
+    {
+        let mut res = ResourceEstimate
+        {
+            total_gates: 0,
+            single_qubit_gates: 0,
+            two_qubit_gates: 0,
+            t_gates: 0,
+            clifford_gates: 0,
+            measurements: 0,
+            resets: 0,
+            depth: self.depth(),
+            t_depth: 0
+        };
+
+        let mut t_layers = vec![0; self.nr_qbits];
+        let all_bits: Vec<usize> = (0..self.nr_qbits).collect();
+        for op in self.ops.iter()
+        {
+            let (bits, count) = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits)
+                    | CircuitOp::ConditionalGate(_, _, ref gate, ref bits) => {
+                        res.total_gates += 1;
+                        match gate.nr_affected_bits()
+                        {
+                            1 => { res.single_qubit_gates += 1; },
+                            2 => { res.two_qubit_gates += 1; },
+                            _ => { /* larger gates only count in the total */ }
+                        }
+                        if gate.is_stabilizer()
+                        {
+                            res.clifford_gates += 1;
+                        }
+                        let is_t = matches!(gate.description(), "T" | "T†");
+                        if is_t
+                        {
+                            res.t_gates += 1;
+                        }
+                        (bits.as_slice(), is_t as usize)
+                    },
+                    CircuitOp::Measure(_, _, _) => {
+                        res.measurements += 1;
+                        continue;
+                    },
+                    CircuitOp::MeasureAll(ref cbits, _) => {
+                        res.measurements += cbits.len();
+                        (all_bits.as_slice(), 0)
+                    },
+                    CircuitOp::Reset(_) | CircuitOp::ResetTo(_, _) => {
+                        res.resets += 1;
+                        continue;
+                    },
+                    CircuitOp::ResetAll => {
+                        res.resets += self.nr_qbits;
+                        (all_bits.as_slice(), 0)
+                    },
+                    CircuitOp::Barrier(ref bits) => (bits.as_slice(), 0),
+                    CircuitOp::PeekAll(_, _)
+                    | CircuitOp::ExpectationMeasure(_, _) => (all_bits.as_slice(), 0),
+                    _ => continue
+                };
+
+            let layer = bits.iter().map(|&b| t_layers[b]).max().unwrap_or(0) + count;
+            for &bit in bits
+            {
+                t_layers[bit] = layer;
+            }
+        }
+        res.t_depth = t_layers.into_iter().max().unwrap_or(0);
+
+        res
+    }
+
+    /// Check the connectivity of gates.
+    ///
+    /// Check that all gates in this circuit acting on two or more qubits,
+    /// operate on pairs of qubits in `topology`. Pairs are undirected, so a
+    /// gate on qubits `a` and `b` is allowed when either `(a, b)` or `(b, a)`
+    /// occurs in `topology`. For gates on more than two qubits, every pair of
+    /// their qubits is checked. Gates are checked as a whole, so e.g. a
+    /// composite gate is rejected even if its constituent gates would be
+    /// allowed. If any gate violates the topology, `Err` is returned with a
+    /// list of `(i, a, b)` triplets, where `i` is the index of the
+    /// operation in the circuit, and `a` and `b` are the qubits that are not
+    /// connected.
+    pub fn satisfies_topology(&self, topology: &::std::collections::HashSet<(usize, usize)>)
+        -> ::std::result::Result<(), Vec<(usize, usize, usize)>>
+    {
+        let mut violations = vec![];
+        for (idx, op) in self.ops.iter().enumerate()
+        {
+            let bits = match *op
+                {
+                    CircuitOp::Gate(_, ref bits)
+                    | CircuitOp::ConditionalGate(_, _, _, ref bits) => bits,
+                    _ => continue
+                };
+
+            for (i, &a) in bits.iter().enumerate()
+            {
+                for &b in bits[i+1..].iter()
+                {
+                    if !topology.contains(&(a, b)) && !topology.contains(&(b, a))
+                    {
+                        violations.push((idx, a, b));
+                    }
+                }
+            }
+        }
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    /// Check whether this circuit can run natively on a device.
+    ///
+    /// Return `true` if this circuit fits on a device with connectivity
+    /// `topology`, supporting the gates in `basis_gates`, and `false`
+    /// otherwise. The circuit should not use more qubits than the device
+    /// has, its gates should satisfy the topology as in
+    /// `satisfies_topology()`, and every gate should occur in `basis_gates`.
+    /// Gates are identified by their description without parameters, so that
+    /// e.g. `"RZ"` allows `R`<sub>`Z`</sub> gates with any angle. Names are
+    /// compared case insensitively. Operations other than gates, like
+    /// measurements and barriers, are always allowed.
+    pub fn is_native_for(&self, topology: &crate::topology::Topology, basis_gates: &[&str])
+        -> bool
+    {
+        if self.nr_qbits > topology.nr_qbits() || self.satisfies_topology(topology.edges()).is_err()
+        {
+            return false;
+        }
+
+        self.ops.iter().all(|op| match *op
+            {
+                CircuitOp::Gate(ref gate, _) | CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                    let name = gate_type(gate.as_ref());
+                    basis_gates.iter().any(|b| b.eq_ignore_ascii_case(name))
+                },
+                _ => true
+            })
+    }
+
+    /// Compute a structural hash of this circuit.
+    ///
+    /// Compute a hash value of the structure of this circuit, i.e. of the
+    /// number of qubits and classical bits, and of the sequence of
+    /// operations and the bits they operate on. For gates, only the type of
+    /// the gate is taken into account, and not the values of its parameters,
+    /// so that e.g. two circuits that only differ in the angle of an
+    /// `R`<sub>`X`</sub> gate have the same hash. Likewise, the probabilities
+    /// in noise channels are ignored. The hash can be used to find results
+    /// of computations on a circuit that depend only on its structure, like
+    /// those in a `CircuitCache`. Note that different circuits may have the
+    /// same structural hash, and that the hash value may change between
+    /// versions of this library.
+    pub fn structural_hash(&self) -> u64
+    {
+        use ::std::hash::{Hash, Hasher};
+
+        let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+        self.nr_qbits.hash(&mut hasher);
+        self.nr_cbits.hash(&mut hasher);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    0u8.hash(&mut hasher);
+                    gate_type(gate.as_ref()).hash(&mut hasher);
+                    bits.hash(&mut hasher);
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    1u8.hash(&mut hasher);
+                    control.hash(&mut hasher);
+                    target.hash(&mut hasher);
+                    gate_type(gate.as_ref()).hash(&mut hasher);
+                    bits.hash(&mut hasher);
+                },
+                CircuitOp::Reset(bit) => {
+                    2u8.hash(&mut hasher);
+                    bit.hash(&mut hasher);
+                },
+                CircuitOp::ResetTo(bit, value) => {
+                    3u8.hash(&mut hasher);
+                    bit.hash(&mut hasher);
+                    value.hash(&mut hasher);
+                },
+                CircuitOp::ResetAll => {
+                    4u8.hash(&mut hasher);
+                },
+                CircuitOp::Measure(qbit, cbit, basis) | CircuitOp::Peek(qbit, cbit, basis) => {
+                    let tag = if let CircuitOp::Measure(..) = *op { 5u8 } else { 6u8 };
+                    tag.hash(&mut hasher);
+                    qbit.hash(&mut hasher);
+                    cbit.hash(&mut hasher);
+                    format!("{:?}", basis).hash(&mut hasher);
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) | CircuitOp::PeekAll(ref cbits, basis) => {
+                    let tag = if let CircuitOp::MeasureAll(..) = *op { 7u8 } else { 8u8 };
+                    tag.hash(&mut hasher);
+                    cbits.hash(&mut hasher);
+                    format!("{:?}", basis).hash(&mut hasher);
+                },
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    9u8.hash(&mut hasher);
+                    qbits.hash(&mut hasher);
+                    cbits.hash(&mut hasher);
+                },
+                CircuitOp::Barrier(ref bits) => {
+                    10u8.hash(&mut hasher);
+                    bits.hash(&mut hasher);
+                },
+                CircuitOp::Noise(_, ref bits) => {
+                    11u8.hash(&mut hasher);
+                    bits.hash(&mut hasher);
+                },
+                CircuitOp::ExpectationMeasure(ref observable, cbit) => {
+                    12u8.hash(&mut hasher);
+                    format!("{}", observable).hash(&mut hasher);
+                    cbit.hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
     /// The classical register.
     ///
     /// Return a reference to the classical bit register, containing the results
@@ -172,6 +814,52 @@ impl Circuit
         }
     }
 
+    /// Apply a gate to all qubits.
+    ///
+    /// Append a single operation to this circuit, that applies a copy of the
+    /// single-qubit gate `gate` to every qubit in the circuit. The operation is
+    /// stored as the Kronecker product of these copies.
+    pub fn apply_all<G: 'static>(&mut self, gate: G) -> crate::error::Result<()>
+    where G: CircuitGate + Clone
+    {
+        gate.check_nr_bits(1)?;
+        if self.nr_qbits > 0
+        {
+            let mut kron: Box<dyn CircuitGate> = Box::new(gate.clone());
+            for _ in 1..self.nr_qbits
+            {
+                let g0: Box<dyn CircuitGate> = Box::new(gate.clone());
+                kron = Box::new(crate::gates::Kron::new(g0, kron));
+            }
+            let bits: Vec<usize> = (0..self.nr_qbits).collect();
+            self.ops.push(CircuitOp::Gate(kron, bits));
+        }
+        Ok(())
+    }
+
+    /// Apply a gate to a range of qubits.
+    ///
+    /// Append a copy of the single-qubit gate `gate` for every qubit in `bits`
+    /// to this circuit.
+    pub fn apply_range<G: 'static>(&mut self, gate: G, bits: &[usize])
+        -> crate::error::Result<()>
+    where G: CircuitGate + Clone
+    {
+        gate.check_nr_bits(1)?;
+        if let Some(&bit) = bits.iter().find(|&&b| b >= self.nr_qbits)
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else
+        {
+            for &bit in bits
+            {
+                self.ops.push(CircuitOp::Gate(Box::new(gate.clone()), vec![bit]));
+            }
+            Ok(())
+        }
+    }
+
     /// Add a conditional gate.
     ///
     /// Append a `n`-ary gate `gate`, that will operate on the `n` qubits in
@@ -397,56 +1085,253 @@ impl Circuit
         self.peek_all_basis(cbits, Basis::Z)
     }
 
-    /// Reset a qubit
+    /// Add a measurement.
     ///
-    /// Reset the qubit `qbit` to |0⟩. This is done by measuring the bit, and
-    /// flipping it if the result is `1`, so this is potentially an expensive
-    /// operation.
-    pub fn reset(&mut self, qbit: usize) -> crate::error::Result<()>
+    /// Add the measurement of the qubits in `qbits` into the classical bits
+    /// `cbits`, without collapsing the quantum state. The outcome for qubit
+    /// `qbits[i]` is stored in classical bit `cbits[i]`. The outcomes are
+    /// sampled from the joint distribution of the measured qubits, so unlike
+    /// separate calls to `peek()`, correlations between the qubits are
+    /// retained. Measurement is done in the Pauli `Z` basis.
+    /// NOTE: this is not a physical process, and cannot be reproduced on a real
+    /// quantum computer.
+    pub fn peek_partial(&mut self, qbits: &[usize], cbits: &[usize])
+        -> crate::error::Result<()>
     {
-        if qbit >= self.nr_qbits
+        if cbits.len() != qbits.len()
         {
-            Err(crate::error::Error::InvalidQBit(qbit))
+            Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), qbits.len()))
+        }
+        else if let Some(&bit) = qbits.iter().find(|&&b| b >= self.nr_qbits)
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else if let Some(&bit) = cbits.iter().find(|&&b| b >= self.nr_cbits)
+        {
+            Err(crate::error::Error::InvalidCBit(bit))
         }
         else
         {
-            self.ops.push(CircuitOp::Reset(qbit));
+            self.ops.push(CircuitOp::PeekPartial(qbits.to_vec(), cbits.to_vec()));
             Ok(())
         }
     }
 
-    /// Reset all qubits
-    ///
-    /// Reset the entire quantum state of the circuit to |00...0⟩. The classical
-    /// register is not affected.
-    pub fn reset_all(&mut self)
-    {
-        self.ops.push(CircuitOp::ResetAll);
-    }
-
-    /// Add a Hadamard gate.
-    ///
-    /// Add a Hadamard gate operating on qubit `qbit`, to this circuit.
-    pub fn h(&mut self, qbit: usize) -> crate::error::Result<()>
-    {
-        self.add_gate(crate::gates::H::new(), &[qbit])
-    }
-
-    /// Add a Pauli X gate.
+    /// Add an expectation value measurement.
     ///
-    /// Add a Pauli X gate operating on qubit `bit`, to this circuit.
-    pub fn x(&mut self, bit: usize) -> crate::error::Result<()>
+    /// Add the computation of the expectation value of Pauli string
+    /// `observable` on the full quantum state, storing its sign in classical
+    /// bit `cbit`: 1 if the expectation value is positive, 0 otherwise. In
+    /// simulation, this does not collapse the quantum state; when computing
+    /// the density matrix of the circuit, a physical measurement of the
+    /// observable is modeled instead.
+    /// NOTE: this is not a physical process, and cannot be reproduced on a real
+    /// quantum computer.
+    pub fn measure_expectation(&mut self, observable: &crate::stabilizer::PauliString,
+        cbit: usize) -> crate::error::Result<()>
     {
-        self.add_gate(crate::gates::X::new(), &[bit])
+        if observable.nr_bits() != self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidNrBits(observable.nr_bits(),
+                self.nr_qbits, format!("{}", observable)))
+        }
+        else if cbit >= self.nr_cbits
+        {
+            Err(crate::error::Error::InvalidCBit(cbit))
+        }
+        else
+        {
+            self.ops.push(CircuitOp::ExpectationMeasure(observable.clone(), cbit));
+            Ok(())
+        }
     }
 
-    /// Add a Pauli Y gate.
+    /// Add a Pauli measurement.
     ///
-    /// Add a Pauli Y gate operating on qubit `bit`, to this circuit.
-    pub fn y(&mut self, bit: usize) -> crate::error::Result<()>
+    /// Add measurement of the product of the Pauli operators in `pauli_string`,
+    /// acting on the corresponding qubits in `qbits`, into classical bit
+    /// `cbit`. The result is 0 for eigenvalue +1, and 1 for eigenvalue -1. The
+    /// measurement is built from standard gates: after a change of basis on
+    /// each qubit (`H` for `X`, `S`<sup>`†`</sup> followed by `H` for `Y`), the
+    /// parity of the qubits is computed in the last qubit using `CX` gates and
+    /// measured, after which the parity computation and changes of basis are
+    /// undone. An `InvalidPauliMeasurement` error is returned when the Pauli
+    /// string contains an identity operator.
+    pub fn measure_pauli(&mut self, pauli_string: &[crate::stabilizer::PauliOp],
+        qbits: &[usize], cbit: usize) -> crate::error::Result<()>
     {
-        self.add_gate(crate::gates::Y::new(), &[bit])
-    }
+        use crate::stabilizer::PauliOp;
+
+        let desc: String = pauli_string.iter().map(|op| format!("{}", op)).collect();
+        if pauli_string.is_empty() || pauli_string.len() != qbits.len()
+        {
+            return Err(crate::error::Error::InvalidNrBits(qbits.len(), pauli_string.len(), desc));
+        }
+        if pauli_string.contains(&PauliOp::I)
+        {
+            return Err(crate::error::Error::InvalidPauliMeasurement(desc));
+        }
+        for (i, &qbit) in qbits.iter().enumerate()
+        {
+            if qbit >= self.nr_qbits || qbits[..i].contains(&qbit)
+            {
+                return Err(crate::error::Error::InvalidQBit(qbit));
+            }
+        }
+        if cbit >= self.nr_cbits
+        {
+            return Err(crate::error::Error::InvalidCBit(cbit));
+        }
+
+        for (&op, &qbit) in pauli_string.iter().zip(qbits)
+        {
+            match op
+            {
+                PauliOp::X => { self.h(qbit)?; },
+                PauliOp::Y => { self.sdg(qbit)?; self.h(qbit)?; },
+                _ => { /* already in the Z basis */ }
+            }
+        }
+        let (&last, rest) = qbits.split_last().unwrap();
+        for &qbit in rest
+        {
+            self.cx(qbit, last)?;
+        }
+        self.measure(last, cbit)?;
+        for &qbit in rest.iter().rev()
+        {
+            self.cx(qbit, last)?;
+        }
+        for (&op, &qbit) in pauli_string.iter().zip(qbits)
+        {
+            match op
+            {
+                PauliOp::X => { self.h(qbit)?; },
+                PauliOp::Y => { self.h(qbit)?; self.s(qbit)?; },
+                _ => { /* already in the Z basis */ }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reset a qubit
+    ///
+    /// Reset the qubit `qbit` to |0⟩. This is done by measuring the bit, and
+    /// flipping it if the result is `1`, so this is potentially an expensive
+    /// operation.
+    pub fn reset(&mut self, qbit: usize) -> crate::error::Result<()>
+    {
+        if qbit >= self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidQBit(qbit))
+        }
+        else
+        {
+            self.ops.push(CircuitOp::Reset(qbit));
+            Ok(())
+        }
+    }
+
+    /// Reset a qubit to a given value
+    ///
+    /// Reset the qubit `qbit` to |0⟩ if `value` is `false`, or to |1⟩ if
+    /// `value` is `true`. Like `reset()`, this is done by measuring the bit,
+    /// and flipping it if the result differs from `value`.
+    pub fn reset_to(&mut self, qbit: usize, value: bool) -> crate::error::Result<()>
+    {
+        if qbit >= self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidQBit(qbit))
+        }
+        else
+        {
+            self.ops.push(CircuitOp::ResetTo(qbit, value));
+            Ok(())
+        }
+    }
+
+    /// Recycle an ancilla qubit
+    ///
+    /// Measure qubit `qbit` in the Z basis into classical bit `cbit`, and
+    /// reset it to |0⟩ immediately afterwards, so that it can be reused in the
+    /// remainder of the circuit. Since the qubit has just been measured, the
+    /// reset is deterministic and does not collapse the state any further.
+    ///
+    /// Note that the recycling is added to the circuit as a sequence of two
+    /// operations, a measurement followed by a reset, rather than as a single
+    /// combined operation. Simulation, optimization and export therefore treat
+    /// them like any other measurement and reset, and e.g. OpenQasm export
+    /// emits `measure q[i] -> b[j];` followed by `reset q[i];`.
+    pub fn recycle_ancilla(&mut self, qbit: usize, cbit: usize) -> crate::error::Result<()>
+    {
+        self.recycle_and_reuse(qbit, qbit, cbit)
+    }
+
+    /// Recycle a qubit for reuse
+    ///
+    /// Measure qubit `measure_bit` in the Z basis into classical bit `cbit`, and
+    /// reset qubit `reset_bit` to |0⟩. This is meant for circuits where
+    /// `measure_bit` and `reset_bit` are different logical identifiers for the
+    /// same physical qubit slot, so that after the measurement the slot is
+    /// cleared for reuse. As in `recycle_ancilla()`, this adds a measurement
+    /// and a reset as two separate operations. If any of the bits is invalid,
+    /// no operations are added to the circuit.
+    pub fn recycle_and_reuse(&mut self, measure_bit: usize, reset_bit: usize, cbit: usize)
+        -> crate::error::Result<()>
+    {
+        if measure_bit >= self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidQBit(measure_bit))
+        }
+        else if reset_bit >= self.nr_qbits
+        {
+            Err(crate::error::Error::InvalidQBit(reset_bit))
+        }
+        else if cbit >= self.nr_cbits
+        {
+            Err(crate::error::Error::InvalidCBit(cbit))
+        }
+        else
+        {
+            self.ops.push(CircuitOp::Measure(measure_bit, cbit, Basis::Z));
+            self.ops.push(CircuitOp::Reset(reset_bit));
+            Ok(())
+        }
+    }
+
+    /// Reset all qubits
+    ///
+    /// Reset the entire quantum state of the circuit to |00...0⟩. The classical
+    /// register is not affected.
+    pub fn reset_all(&mut self)
+    {
+        self.ops.push(CircuitOp::ResetAll);
+    }
+
+    /// Add a Hadamard gate.
+    ///
+    /// Add a Hadamard gate operating on qubit `qbit`, to this circuit.
+    pub fn h(&mut self, qbit: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::H::new(), &[qbit])
+    }
+
+    /// Add a Pauli X gate.
+    ///
+    /// Add a Pauli X gate operating on qubit `bit`, to this circuit.
+    pub fn x(&mut self, bit: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::X::new(), &[bit])
+    }
+
+    /// Add a Pauli Y gate.
+    ///
+    /// Add a Pauli Y gate operating on qubit `bit`, to this circuit.
+    pub fn y(&mut self, bit: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::Y::new(), &[bit])
+    }
 
     /// Add a Pauli Z gate.
     ///
@@ -472,6 +1357,24 @@ impl Circuit
         self.add_gate(crate::gates::Sdg::new(), &[bit])
     }
 
+    /// Add a square root of X gate
+    ///
+    /// Add a `V` gate, the square root of the Pauli `X` gate, operating on
+    /// qubit `bit`, to this circuit.
+    pub fn v(&mut self, bit: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::V::new(), &[bit])
+    }
+
+    /// Add an inverse square root of X gate
+    ///
+    /// Add a `V`† gate, the inverse of the `V` gate,
+    /// operating on qubit `bit`, to this circuit.
+    pub fn vdg(&mut self, bit: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::Vdg::new(), &[bit])
+    }
+
     /// Add a R<sub>X</sub> gate.
     ///
     /// Add a `R`<sub>`X`</sub>`(θ)` gate operating on qubit `bit`, to this circuit.
@@ -536,6 +1439,57 @@ impl Circuit
         self.add_gate(crate::gates::CX::new(), &[control, target])
     }
 
+    /// Add a Swap gate.
+    ///
+    /// Add a gate swapping the states of qubits `bit0` and `bit1` to this
+    /// circuit.
+    pub fn swap(&mut self, bit0: usize, bit1: usize) -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::Swap::new(), &[bit0, bit1])
+    }
+
+    /// Add a multi-controlled X gate.
+    ///
+    /// Add a gate flipping qubit `target` if and only if all qubits in
+    /// `controls` are |1⟩ to this circuit. See `gates::n_controlled_x()` for
+    /// the decomposition that is used.
+    pub fn mcx(&mut self, controls: &[usize], target: usize) -> crate::error::Result<()>
+    {
+        let mut bits = controls.to_vec();
+        bits.push(target);
+        self.add_gate(crate::gates::n_controlled_x(controls.len()), &bits)
+    }
+
+    /// Add a controlled phase rotation.
+    ///
+    /// Add a `CR`<sub>`k`</sub> gate, rotating the phase of qubit `target`
+    /// over `2π/2`<sup>`k`</sup> when qubit `control` is set, to this
+    /// circuit.
+    pub fn controlled_phase_rotation(&mut self, k: u32, control: usize, target: usize)
+        -> crate::error::Result<()>
+    {
+        self.add_gate(crate::gates::ControlledPhase::new(k), &[control, target])
+    }
+
+    /// Add a layer of the quantum Fourier transform.
+    ///
+    /// Add a Hadamard gate on qubit `qubit`, followed by a cascade of
+    /// controlled phase rotations on `qubit`, where the `i`th qubit in
+    /// `subsequent_qubits` (counting from zero) controls a `CR`<sub>`i+2`</sub>
+    /// rotation. Applying this for every qubit in a register, each time with
+    /// the remaining less significant qubits as `subsequent_qubits`, results
+    /// in the quantum Fourier transform up to a reversal of the bit order.
+    pub fn qft_layer(&mut self, qubit: usize, subsequent_qubits: &[usize])
+        -> crate::error::Result<()>
+    {
+        self.h(qubit)?;
+        for (i, &control) in subsequent_qubits.iter().enumerate()
+        {
+            self.controlled_phase_rotation(i as u32 + 2, control, qubit)?;
+        }
+        Ok(())
+    }
+
     /// Add a barrier
     ///
     /// Add a barrier on the bits in `bits`. No transformations on these bits
@@ -553,1656 +1507,6664 @@ impl Circuit
         }
     }
 
-    /// Execute this circuit
+    /// Add noise
     ///
-    /// Execute this circuit, performing its operations and measurements.
-    /// Measurements are made over `nr_shots` executions of the circuit. This
-    /// function clears any previous states of the system (quantum or classical).
-    #[inline(always)]
-    pub fn execute(&mut self, nr_shots: usize) -> crate::error::Result<()>
+    /// Add noise channel `channel`, operating on the qubits in `qbits`, to this
+    /// circuit. Noise is only taken into account when computing the density
+    /// matrix of the final state, or when executing the circuit in
+    /// `SimulationMode::DensityMatrix`. In other simulation modes it is
+    /// ignored.
+    pub fn add_noise<N>(&mut self, channel: N, qbits: &[usize]) -> crate::error::Result<()>
+    where N: crate::gates::NoiseChannel
     {
-        self.execute_with_rng(nr_shots, &mut rand::thread_rng())
+        if qbits.len() != channel.nr_affected_bits()
+        {
+            Err(crate::error::Error::InvalidNrBits(qbits.len(),
+                channel.nr_affected_bits(), String::from(channel.description())))
+        }
+        else if let Some(&bit) = qbits.iter().find(|&&b| b >= self.nr_qbits)
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else
+        {
+            self.ops.push(CircuitOp::Noise(channel.kraus_operators(), qbits.to_vec()));
+            Ok(())
+        }
     }
 
-    /// Execute this circuit
+    /// Add noise after a gate.
     ///
-    /// Execute this circuit, performing its operations and measurements.
-    /// Measurements are made over `nr_shots` executions of the circuit, using
-    /// random number generator `rng` for sampling. This function clears any
-    /// previous states of the system (quantum or classical).
-    pub fn execute_with_rng<R: rand::RngCore>(&mut self, nr_shots: usize, rng: &mut R)
+    /// Insert noise channel `channel` directly after the gate at index
+    /// `gate_idx` in the list of operations of this circuit. When the channel
+    /// operates on as many qubits as the gate, it acts on the qubits of the
+    /// gate; a single qubit channel after a multi-qubit gate is applied to
+    /// each of the gate's qubits separately. Note that inserting noise shifts
+    /// the indices of the subsequent operations. As with `add_noise()`, noise
+    /// is ignored in pure state simulations.
+    ///
+    /// An `InvalidOpIndex` error is returned when there is no operation at
+    /// `gate_idx`, a `NotAGate` error when the operation is not a
+    /// (conditional) gate, and an `InvalidNrBits` error when the number of
+    /// qubits of the channel does not fit the gate.
+    pub fn add_noise_after_gate<N>(&mut self, gate_idx: usize, channel: N)
         -> crate::error::Result<()>
+    where N: crate::gates::NoiseChannel
     {
-        let q_state = if self.is_stabilizer_circuit()
+        let bits = match self.ops.get(gate_idx)
+            {
+                Some(CircuitOp::Gate(_, ref bits))
+                | Some(CircuitOp::ConditionalGate(_, _, _, ref bits)) => bits.clone(),
+                Some(_) => {
+                    return Err(crate::error::Error::NotAGate(format!("at index {}", gate_idx)));
+                },
+                None => {
+                    return Err(crate::error::Error::InvalidOpIndex(gate_idx));
+                }
+            };
+
+        let nr_bits = channel.nr_affected_bits();
+        let kraus = channel.kraus_operators();
+        let noise = if nr_bits == bits.len()
+            {
+                vec![CircuitOp::Noise(kraus, bits)]
+            }
+            else if nr_bits == 1
             {
-                QuStateRepr::stabilizer(self.nr_qbits, nr_shots)
+                bits.iter().map(|&bit| CircuitOp::Noise(kraus.clone(), vec![bit])).collect()
             }
             else
             {
-                QuStateRepr::vector(self.nr_qbits, nr_shots)
+                return Err(crate::error::Error::InvalidNrBits(bits.len(), nr_bits,
+                    String::from(channel.description())));
             };
-        self.execute_with(nr_shots, rng, q_state)
-    }
 
-    /// Execute this circuit
-    ///
-    /// Execute this circuit, performing its operations and measurements.
-    /// Measurements are made over `nr_shots` executions of the circuit, using
-    /// random number generator `rng` for sampling. The initial quantum state of
-    /// the system is set to `q_state`. The classical state is cleared before
-    /// execution.
-    pub fn execute_with<R: rand::RngCore>(&mut self, nr_shots: usize, rng: &mut R,
-        q_state: QuStateRepr) -> crate::error::Result<()>
-    {
-        self.q_state = Some(q_state);
-        self.c_state = Some(ndarray::Array1::zeros(nr_shots));
-        self.reexecute_with_rng(rng)
+        let tail = self.ops.split_off(gate_idx + 1);
+        self.ops.extend(noise);
+        self.ops.extend(tail);
+        Ok(())
     }
 
-    /// Execute a circuit again.
+    /// Convert this circuit to a list of instructions.
     ///
-    /// Run this circuit again, starting with the state from the previous
-    /// execution. If this circuit has not been run before, a `NotExecuted`
-    /// error is returned.
-    #[inline(always)]
-    pub fn reexecute(&mut self) -> crate::error::Result<()>
+    /// Return the operations in this circuit as a list of instructions, that
+    /// only use plain data types. Gates for which no dedicated instruction
+    /// exists are converted to `Instruction::Custom`, holding their matrix.
+    /// Composite gates and Kronecker products are expanded into the gates they
+    /// consist of, and reference parameters are replaced by their current
+    /// value.
+    pub fn to_instructions(&self) -> Vec<crate::instruction::Instruction>
     {
-        self.reexecute_with_rng(&mut rand::thread_rng())
-    }
+        use crate::instruction::{Instruction, ToInstruction};
 
-    /// Execute a circuit again.
-    ///
-    /// Run this circuit again, starting with the state from the previous
-    /// execution, using random number generator `rng` for sampling. If this
-    /// circuit has not been run before, a `NotExecuted` error is returned.
-    pub fn reexecute_with_rng<R: rand::Rng>(&mut self, rng: &mut R)
-        -> crate::error::Result<()>
-    {
-        if let Some(c_state) = self.c_state.as_mut()
+        let mut res = vec![];
+        for op in self.ops.iter()
         {
-            let ops = &self.ops;
-            match self.q_state
+            match *op
             {
-                Some(QuStateRepr::Stabilizer(ref mut state)) => {
-                    Self::do_execute_with(state, c_state, ops, rng)
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    res.extend(gate.instructions(bits));
                 },
-                Some(QuStateRepr::Vector(ref mut state)) => {
-                    Self::do_execute_with(state, c_state, ops, rng)
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    res.extend(gate.instructions(bits).into_iter().map(|instruction| {
+                        Instruction::ConditionalGate(control.clone(), target,
+                            Box::new(instruction))
+                    }));
                 },
-                _ => {
-                    Err(crate::error::Error::NotExecuted)
+                CircuitOp::Reset(bit) => {
+                    res.push(Instruction::Reset(bit));
+                },
+                CircuitOp::ResetTo(bit, value) => {
+                    res.push(Instruction::ResetTo(bit, value));
+                },
+                CircuitOp::ResetAll => {
+                    res.push(Instruction::ResetAll);
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    res.push(Instruction::Measure(qbit, cbit, basis));
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    res.push(Instruction::MeasureAll(cbits.clone(), basis));
+                },
+                CircuitOp::Peek(qbit, cbit, basis) => {
+                    res.push(Instruction::Peek(qbit, cbit, basis));
+                },
+                CircuitOp::PeekAll(ref cbits, basis) => {
+                    res.push(Instruction::PeekAll(cbits.clone(), basis));
+                },
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    res.push(Instruction::PeekPartial(qbits.clone(), cbits.clone()));
+                },
+                CircuitOp::Barrier(ref qbits) => {
+                    res.push(Instruction::Barrier(qbits.clone()));
+                },
+                CircuitOp::Noise(ref ops, ref qbits) => {
+                    res.push(Instruction::noise(ops, qbits));
+                },
+                CircuitOp::ExpectationMeasure(ref observable, cbit) => {
+                    res.push(Instruction::ExpectationMeasure(observable.ops().to_vec(), cbit));
                 }
             }
         }
-        else
+
+        res
+    }
+
+    /// Create a circuit from a list of instructions.
+    ///
+    /// Create a new circuit with `nr_qbits` quantum bits and `nr_cbits`
+    /// classical bits, holding the operations described by `instrs`. An error
+    /// is returned when an instruction refers to an invalid qubit or classical
+    /// bit, when the matrix of a custom gate or noise channel does not match
+    /// the number of qubits it operates on, or when a conditional instruction
+    /// holds an operation that is not a gate.
+    pub fn from_instructions(instrs: &[crate::instruction::Instruction], nr_qbits: usize,
+        nr_cbits: usize) -> crate::error::Result<Self>
+    {
+        let mut circuit = Circuit::new(nr_qbits, nr_cbits);
+        for instruction in instrs.iter()
         {
-            Err(crate::error::Error::NotExecuted)
+            instruction.add_to_circuit(&mut circuit, None)?;
         }
+        Ok(circuit)
     }
 
-    fn do_execute_with<Q: QuState, R: rand::Rng>(q_state: &mut Q,
-        c_state: &mut ndarray::Array1<u64>, ops: &[CircuitOp], rng: &mut R)
-        -> crate::error::Result<()>
+    /// Import a circuit from Cirq.
+    ///
+    /// Create a new circuit from the JSON description `json` of a Cirq
+    /// circuit, as produced by `cirq.to_json()`. The qubits in the Cirq
+    /// circuit, which may be line qubits, grid qubits or named qubits, are
+    /// sorted in the same order as Cirq sorts them, and numbered
+    /// consecutively. Every qubit measured in the circuit writes its result
+    /// to a new classical bit, in the order in which the measurements occur.
+    /// Measurement keys are not retained. Gates are imported up to a global
+    /// phase; the supported gates are integer powers of `H`, `CNOT`, `SWAP`,
+    /// `CCX` and `CCZ`, arbitrary powers of `X`, `Y`, `Z` and `CZ`, the
+    /// rotations `rx`, `ry` and `rz`, `CSWAP` and the identity. For other
+    /// gates, and for parameterized exponents, an `UnsupportedCirqGate` error
+    /// is returned.
+    pub fn from_cirq_json(json: &str) -> crate::error::ParseResult<Self>
     {
-        for op in ops
+        crate::cirq::circuit_from_cirq_json(json)
+    }
+
+    /// Import a circuit from OpenQasm.
+    ///
+    /// Create a new circuit from the OpenQasm 2.0 program `src`. All quantum
+    /// registers declared in the program are numbered consecutively in the
+    /// order in which they are declared, and the same goes for the classical
+    /// registers. The gates from the standard `qelib1.inc` header are
+    /// supported, as well as measurements, resets, barriers and conditional
+    /// gates. Operations on full registers are applied to each bit in the
+    /// register. Include statements are ignored. Gate definitions without
+    /// parameters are imported as composite gates, such as those written by
+    /// `open_qasm()` for composite and custom gates. Parameterized and opaque
+    /// gate definitions are not supported, and result in an
+    /// `UnsupportedOpenQasm` error.
+    pub fn from_open_qasm(src: &str) -> crate::error::ParseResult<Self>
+    {
+        crate::parse::circuit_from_open_qasm(src)
+    }
+
+    /// The unitary matrix of this circuit.
+    ///
+    /// Compute the matrix of the unitary transformation performed by this
+    /// circuit, without executing it. The matrix holds `2`<sup>`n`</sup> rows
+    /// and columns, where `n` is the number of qubits in the circuit, so this
+    /// is only feasible for a modest number of qubits. Barriers are ignored.
+    /// If the circuit contains measurements, resets, conditional gates or
+    /// noise, `Error::NotAGate` is returned.
+    pub fn get_unitary(&self) -> crate::error::Result<crate::cmatrix::CMatrix>
+    {
+        let mut res = crate::cmatrix::CMatrix::eye(1 << self.nr_qbits);
+        for (gate, bits) in self.gate_ops()?
         {
-            match *op
-            {
-                CircuitOp::Gate(ref gate, ref bits) => {
-                    q_state.apply_gate(gate.as_gate(), bits.as_slice())?;
-                },
-                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
-                    let mut cbits = vec![0; c_state.len()];
-                    for (idst, &isrc) in control.iter().enumerate()
-                    {
-                        for (db, &sb) in cbits.iter_mut().zip(c_state.iter())
-                        {
-                            *db |= ((sb >> isrc) & 1) << idst;
-                        }
-                    }
-                    let apply_gate: Vec<bool> = cbits.iter()
-                        .map(|&b| b == target)
-                        .collect();
-                    q_state.apply_conditional_gate(&apply_gate, gate.as_gate(),
-                        bits.as_slice())?;
-                },
-                CircuitOp::Measure(qbit, cbit, basis) => {
-                    match basis
-                    {
-                        Basis::X => {
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.measure_into(qbit, cbit, c_state, rng)?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                        },
-                        Basis::Y => {
-                            q_state.apply_gate(&crate::gates::Sdg::new(), &[qbit])?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.measure_into(qbit, cbit, c_state, rng)?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.apply_gate(&crate::gates::S::new(), &[qbit])?;
-                        },
-                        Basis::Z => {
-                            q_state.measure_into(qbit, cbit, c_state, rng)?;
-                        }
-                    }
-                }
-                CircuitOp::MeasureAll(ref cbits, basis) => {
-                    match basis
-                    {
-                        Basis::X => {
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.measure_all_into(cbits, c_state, rng)?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                        },
-                        Basis::Y => {
-                            q_state.apply_unary_gate_all(&crate::gates::Sdg::new())?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.measure_all_into(cbits, c_state, rng)?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.apply_unary_gate_all(&crate::gates::S::new())?;
-                        },
-                        Basis::Z => {
-                            q_state.measure_all_into(cbits, c_state, rng)?;
-                        }
-                    }
-                },
-                CircuitOp::Peek(qbit, cbit, basis) => {
-                    match basis
-                    {
-                        Basis::X => {
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.peek_into(qbit, cbit, c_state, rng)?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                        },
-                        Basis::Y => {
-                            q_state.apply_gate(&crate::gates::Sdg::new(), &[qbit])?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.peek_into(qbit, cbit, c_state, rng)?;
-                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
-                            q_state.apply_gate(&crate::gates::S::new(), &[qbit])?;
-                        },
-                        Basis::Z => {
-                            q_state.peek_into(qbit, cbit, c_state, rng)?;
-                        }
-                    }
-                },
-                CircuitOp::PeekAll(ref cbits, basis) => {
-                    match basis
-                    {
-                        Basis::X => {
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.peek_all_into(cbits, c_state, rng)?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                        },
-                        Basis::Y => {
-                            q_state.apply_unary_gate_all(&crate::gates::Sdg::new())?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.peek_all_into(cbits, c_state, rng)?;
-                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
-                            q_state.apply_unary_gate_all(&crate::gates::S::new())?;
-                        },
-                        Basis::Z => {
-                            q_state.peek_all_into(cbits, c_state, rng)?;
-                        }
-                    }
-                },
-                CircuitOp::Reset(bit) => {
-                    q_state.reset(bit, rng)?;
-                },
-                CircuitOp::ResetAll => {
-                    q_state.reset_all();
-                },
-                CircuitOp::Barrier(_) => {
-                    /* Nothing to be done */
-                }
-            }
+            crate::gates::apply_gate_mat_slice(res.view_mut(), gate.as_gate(), bits,
+                self.nr_qbits);
         }
+        Ok(res)
+    }
 
-        Ok(())
+    /// Invert this circuit.
+    ///
+    /// Return the adjoint of this circuit, i.e. a new circuit containing the
+    /// Hermitian conjugates of the gates in this circuit, in reverse order.
+    /// Barriers are retained. Since measurements, resets, conditional gates
+    /// and noise cannot be inverted, `Error::NotAGate` is returned when the
+    /// circuit contains any of these.
+    pub fn inverse(&self) -> crate::error::Result<Self>
+    {
+        // Check that all operations can be inverted
+        self.gate_ops()?;
+
+        let ops = self.ops.iter().rev().map(|op| {
+                match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => CircuitOp::Gate(gate.adjoint(), bits.clone()),
+                    _ => op.clone()
+                }
+            }).collect();
+        Ok(self.with_ops(ops))
     }
 
-    /// Create a histogram of measurements.
+    /// Reverse this circuit.
     ///
-    /// Create a histogram of the measured classical bits. The `n` bits in the
-    /// classical register are collected in a single `u64` integer value. The
-    /// first bit in the classical register (at index 0) corresponds to the
-    /// least significant bit in the key; the last classical bit (at index `n-1`)
-    /// to the most significant bit in the key. This function of course only works
-    /// when there are at most 64 bits in the register. If there are more, use
-    /// `histogram_string()`.
-    pub fn histogram(&self) -> crate::error::Result<crate::idhash::U64HashMap<usize>>
+    /// Return a new circuit, containing the operations of this circuit in
+    /// reverse order. Note that the gates themselves are not inverted, so this
+    /// is in general not the inverse of the circuit.
+    pub fn reversed(&self) -> Self
     {
-        if let Some(ref c_state) = self.c_state
-        {
-            let mut res = crate::idhash::new_u64_hash_map();
-            for &key in c_state
-            {
-                let count = res.entry(key).or_insert(0);
-                *count += 1;
-            }
-            Ok(res)
-        }
-        else
-        {
-            Err(crate::error::Error::NotExecuted)
-        }
+        self.with_ops(self.ops.iter().rev().cloned().collect())
     }
 
-    /// Create a histogram of measurements.
+    /// Repeat this circuit.
     ///
-    /// Create a histogram of the measured classical bits. The `n` bits in the
-    /// classical register are collected in a single `usize` integer value,
-    /// which is used as an index in a vector. The bit order of the indices
-    /// is the same as in the `histogram()` function. The vector is of length
-    /// `2`<sub>`n`</sub>, so use this function only for reasonably small
-    /// numbers of `n`. For sparse collections, using `histogram()` or
-    /// `histogram_string` may be better.
-    pub fn histogram_vec(&self) -> crate::error::Result<Vec<usize>>
+    /// Return a new circuit on the same quantum and classical bits, containing
+    /// `n` copies of the operations in this circuit in sequence. Classical
+    /// bits are not renumbered, so measurements in a later repetition
+    /// overwrite the results of earlier ones.
+    pub fn repeat(&self, n: usize) -> Self
     {
-        if let Some(ref c_state) = self.c_state
-        {
-            let mut res = vec![0; 1 << self.nr_cbits];
-            for &key in c_state
-            {
-                res[key as usize] += 1;
-            }
-            Ok(res)
-        }
-        else
-        {
-            Err(crate::error::Error::NotExecuted)
-        }
+        let ops = (0..n).flat_map(|_| self.ops.iter().cloned()).collect();
+        self.with_ops(ops)
     }
 
-    /// Create a histogram of measurements.
+    /// Repeat this circuit, with resets.
     ///
-    /// Create a histogram of the measured classical bits. The `n` bits in the
-    /// classical register are collected in a string key, with the last character
-    /// in the key corresponding to the first bit (at index 0) in the classical
-    /// register and vice versa.
-    pub fn histogram_string(&self)
-        -> crate::error::Result<::std::collections::HashMap<String, usize>>
+    /// Return a new circuit containing `n` copies of the operations in this
+    /// circuit, like `repeat()`, but with the quantum state reset to |00...0⟩
+    /// in between repetitions.
+    pub fn repeat_with_reset(&self, n: usize) -> Self
     {
-        if let Some(ref c_state) = self.c_state
+        let mut ops = vec![];
+        for i in 0..n
         {
-            let mut res = ::std::collections::HashMap::new();
-            for &key in c_state
+            if i > 0
             {
-                let skey = format!("{:0width$b}", key, width=self.nr_cbits);
-                let count = res.entry(skey).or_insert(0);
-                *count += 1;
+                ops.push(CircuitOp::ResetAll);
             }
-            Ok(res)
-        }
-        else
-        {
-            Err(crate::error::Error::NotExecuted)
+            ops.extend(self.ops.iter().cloned());
         }
+        self.with_ops(ops)
     }
 
-    fn is_full_register(&self, control: &[usize]) -> bool
+    /// Add an echo sequence.
+    ///
+    /// Append the operations in circuit `other` to this circuit, followed by
+    /// the same operations in reverse order. When `other` has more quantum or
+    /// classical bits than this circuit, an `InvalidQBit` or `InvalidCBit`
+    /// error is returned.
+    pub fn echo_sequence(&mut self, other: &Circuit) -> crate::error::Result<()>
     {
-        let n = control.len();
-        if n != self.nr_cbits
+        if other.nr_qbits > self.nr_qbits
         {
-            return false;
+            Err(crate::error::Error::InvalidQBit(other.nr_qbits - 1))
         }
-
-        let mut scontrol = vec![0; n];
-        scontrol.copy_from_slice(control);
-        scontrol.sort();
-        for i in 0..n
+        else if other.nr_cbits > self.nr_cbits
         {
-            if scontrol[i] != i
-            {
-                return false;
-            }
+            Err(crate::error::Error::InvalidCBit(other.nr_cbits - 1))
+        }
+        else
+        {
+            self.ops.extend(other.ops.iter().cloned());
+            self.ops.extend(other.ops.iter().rev().cloned());
+            Ok(())
         }
-
-        true
     }
 
-    fn check_open_qasm_condition_bits(&self, control: &[usize])
-        -> crate::error::ExportResult<()>
+    /// Compose two circuits.
+    ///
+    /// Append all operations in circuit `other` to this circuit. Both circuits
+    /// should have the same number of quantum and classical bits, otherwise an
+    /// `InvalidNrBits` error is returned.
+    pub fn compose(&mut self, other: &Circuit) -> crate::error::Result<()>
     {
-        if !self.is_full_register(control)
+        if other.nr_qbits != self.nr_qbits
         {
-            Err(crate::error::ExportError::IncompleteConditionRegister)
+            Err(crate::error::Error::InvalidNrBits(other.nr_qbits, self.nr_qbits,
+                String::from("quantum register")))
+        }
+        else if other.nr_cbits != self.nr_cbits
+        {
+            Err(crate::error::Error::InvalidNrBits(other.nr_cbits, self.nr_cbits,
+                String::from("classical register")))
         }
         else
         {
+            self.ops.extend(other.ops.iter().cloned());
             Ok(())
         }
     }
 
-    /// Export to OpenQasm
+    /// Append a circuit on a subset of bits.
     ///
-    /// Export this circuit to a program in OpenQasm format. On a successful
-    /// conversion, the result is `Ok` with the program text. When the conversion
-    /// to OpenQasm fails, `Err` with an error message is returned.
-    pub fn open_qasm(&self) -> crate::error::Result<String>
+    /// Append all operations in circuit `other` to this circuit, where qubit
+    /// `i` in `other` is mapped to qubit `qubit_map[i]` in this circuit, and
+    /// classical bit `j` to classical bit `cbit_map[j]`. Operations acting on
+    /// all qubits in `other` act on the mapped qubits only. An `InvalidNrBits`
+    /// error is returned when the lengths of the maps do not match the sizes
+    /// of the registers in `other`, and an `InvalidQBit` or `InvalidCBit`
+    /// error when a bit is mapped outside the registers of this circuit, or
+    /// when two qubits are mapped onto the same qubit.
+    pub fn append_on_qubits(&mut self, other: &Circuit, qubit_map: &[usize],
+        cbit_map: &[usize]) -> crate::error::Result<()>
     {
-        let mut res = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
-
-        let mut qbit_names = vec![];
-        if self.nr_qbits > 0
+        if qubit_map.len() != other.nr_qbits
         {
-            res += &format!("qreg q[{}];\n", self.nr_qbits);
-            for i in 0..self.nr_qbits
-            {
-                qbit_names.push(format!("q[{}]", i));
-            }
+            return Err(crate::error::Error::InvalidNrBits(qubit_map.len(), other.nr_qbits,
+                String::from("qubit map")));
         }
-        let mut cbit_names = vec![];
-        if self.nr_cbits > 0
+        if cbit_map.len() != other.nr_cbits
         {
-            res += &format!("creg b[{}];\n", self.nr_cbits);
-            for i in 0..self.nr_cbits
+            return Err(crate::error::Error::InvalidNrBits(cbit_map.len(), other.nr_cbits,
+                String::from("classical bit map")));
+        }
+        for (i, &bit) in qubit_map.iter().enumerate()
+        {
+            if bit >= self.nr_qbits || qubit_map[..i].contains(&bit)
             {
-                cbit_names.push(format!("b[{}]", i));
+                return Err(crate::error::Error::InvalidQBit(bit));
             }
         }
+        if let Some(&bit) = cbit_map.iter().find(|&&bit| bit >= self.nr_cbits)
+        {
+            return Err(crate::error::Error::InvalidCBit(bit));
+        }
 
-        for op in self.ops.iter()
+        // When all qubits are mapped, operations on all qubits can be kept
+        let nr_qbits = self.nr_qbits;
+        let full = other.nr_qbits == nr_qbits;
+        let new_qbits = |bits: &[usize]| bits.iter().map(|&b| qubit_map[b]).collect::<Vec<usize>>();
+        let new_cbits = |bits: &[usize]| bits.iter().map(|&b| cbit_map[b]).collect::<Vec<usize>>();
+        // Classical bits for an operation on all qubits of this circuit,
+        // indexed by the qubit they are associated with
+        let all_cbits = |cbits: &[usize]| {
+            let mut res = vec![0; nr_qbits];
+            for (&qbit, &cbit) in qubit_map.iter().zip(cbits)
+            {
+                res[qbit] = cbit_map[cbit];
+            }
+            res
+        };
+        for op in other.ops.iter()
         {
             match *op
             {
                 CircuitOp::Gate(ref gate, ref bits) => {
-                    res += &format!("{};\n", gate.open_qasm(&qbit_names, bits)?);
+                    self.ops.push(CircuitOp::Gate(gate.clone(), new_qbits(bits)));
                 },
-                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
-                    if control.is_empty()
-                    {
-                        res += &format!("{};\n", gate.open_qasm(&qbit_names, bits)?);
-                    }
-                    else
-                    {
-                        // We do require that the control bits span the entire classical
-                        // register, but not necessarily in the order 0..#bits.
-                        self.check_open_qasm_condition_bits(control)?;
-                        let mut starget = 0;
-                        for (tshift, sshift) in control.iter().enumerate()
-                        {
-                            starget |= ((target >> tshift) & 0x01) << sshift;
-                        }
-                        let condition = format!("b == {}", starget);
-                        let gate_qasm = gate.conditional_open_qasm(&condition, &qbit_names, bits)?;
-                        res += &format!("{};\n", gate_qasm);
-                    }
+                CircuitOp::ConditionalGate(ref cbits, target, ref gate, ref bits) => {
+                    self.ops.push(CircuitOp::ConditionalGate(new_cbits(cbits), target,
+                        gate.clone(), new_qbits(bits)));
+                },
+                CircuitOp::Reset(bit) => self.ops.push(CircuitOp::Reset(qubit_map[bit])),
+                CircuitOp::ResetTo(bit, value) => {
+                    self.ops.push(CircuitOp::ResetTo(qubit_map[bit], value));
+                },
+                CircuitOp::ResetAll if full => self.ops.push(CircuitOp::ResetAll),
+                CircuitOp::ResetAll => {
+                    self.ops.extend(qubit_map.iter().map(|&bit| CircuitOp::Reset(bit)));
                 },
                 CircuitOp::Measure(qbit, cbit, basis) => {
-                    match basis
-                    {
-                        Basis::X => {
-                            res += &format!("{};\n",
-                                crate::gates::H::new().open_qasm(&qbit_names, &[qbit])?);
-                        },
-                        Basis::Y => {
-                            res += &format!("{};\n",
-                                crate::gates::Sdg::new().open_qasm(&qbit_names, &[qbit])?);
-                            res += &format!("{};\n",
-                                crate::gates::H::new().open_qasm(&qbit_names, &[qbit])?);
-                        }
-                        _ => {}
-                    }
-                    res += &format!("measure {} -> {};\n", qbit_names[qbit], cbit_names[cbit]);
-                }
+                    self.ops.push(CircuitOp::Measure(qubit_map[qbit], cbit_map[cbit], basis));
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) if full => {
+                    self.ops.push(CircuitOp::MeasureAll(all_cbits(cbits), basis));
+                },
                 CircuitOp::MeasureAll(ref cbits, basis) => {
-                    match basis
+                    self.ops.extend(qubit_map.iter().zip(cbits).map(|(&qbit, &cbit)| {
+                        CircuitOp::Measure(qbit, cbit_map[cbit], basis)
+                    }));
+                },
+                CircuitOp::Peek(qbit, cbit, basis) => {
+                    self.ops.push(CircuitOp::Peek(qubit_map[qbit], cbit_map[cbit], basis));
+                },
+                CircuitOp::PeekAll(ref cbits, basis) if full => {
+                    self.ops.push(CircuitOp::PeekAll(all_cbits(cbits), basis));
+                },
+                CircuitOp::PeekAll(ref cbits, basis) => {
+                    self.ops.extend(qubit_map.iter().zip(cbits).map(|(&qbit, &cbit)| {
+                        CircuitOp::Peek(qbit, cbit_map[cbit], basis)
+                    }));
+                },
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    self.ops.push(CircuitOp::PeekPartial(new_qbits(qbits), new_cbits(cbits)));
+                },
+                CircuitOp::Barrier(ref bits) => {
+                    self.ops.push(CircuitOp::Barrier(new_qbits(bits)));
+                },
+                CircuitOp::Noise(ref kraus, ref bits) => {
+                    self.ops.push(CircuitOp::Noise(kraus.clone(), new_qbits(bits)));
+                },
+                CircuitOp::ExpectationMeasure(ref obs, cbit) => {
+                    let mut ops = vec![crate::stabilizer::PauliOp::I; nr_qbits];
+                    for (&qbit, &pop) in qubit_map.iter().zip(obs.ops())
                     {
-                        Basis::X => {
-                            let names = [String::from("q")];
-                            res += &format!("{};\n",
-                                crate::gates::H::new().open_qasm(&names, &[0])?);
-                        },
-                        Basis::Y => {
-                            let names = [String::from("q")];
-                            res += &format!("{};\n",
-                                crate::gates::Sdg::new().open_qasm(&names, &[0])?);
-                            res += &format!("{};\n",
-                                crate::gates::H::new().open_qasm(&names, &[0])?);
-                        }
-                        _ => {}
+                        ops[qbit] = pop;
                     }
+                    self.ops.push(CircuitOp::ExpectationMeasure(
+                        crate::stabilizer::PauliString::new(&ops), cbit_map[cbit]));
+                }
+            }
+        }
 
-                    if cbits.len() == self.nr_cbits
-                        && cbits.iter().enumerate().all(|(i, &b)| i==b)
-                    {
-                        res += &format!("measure q -> b;\n");
+        Ok(())
+    }
+
+    /// Extract a sub-circuit.
+    ///
+    /// Create a new circuit on the qubits in `qbits`, renumbered as
+    /// `0..qbits.len()`, containing the operations with index `from_op` up to
+    /// and including `to_op` in this circuit that act on any of these qubits.
+    /// Operations acting on all qubits are always included, and barriers are
+    /// restricted to the qubits in the sub-circuit. When an operation acts on
+    /// both listed and unlisted qubits, it is included as is, and the unlisted
+    /// qubits it acts on are added to the sub-circuit, after the listed qubits
+    /// and in ascending order of their original index. The classical register
+    /// of the sub-circuit is the same as that of this circuit.
+    ///
+    /// Note that the unlisted qubits of such an operation are renumbered as
+    /// well, rather than keeping their original index. Since the listed qubits
+    /// occupy indices `0..qbits.len()`, an original index may clash with one
+    /// of them, or lie far beyond the end of the sub-circuit. Appending the
+    /// unlisted qubits keeps the operation intact, while all other operations
+    /// in the sub-circuit act as the identity on these extra qubits.
+    pub fn subcircuit(&self, qbits: &[usize], from_op: usize, to_op: usize) -> Self
+    {
+        let ops: Vec<&CircuitOp> = self.ops.iter().enumerate()
+            .filter(|&(i, op)| {
+                (from_op..=to_op).contains(&i)
+                    && op.qbits().map_or(true, |bits| bits.iter().any(|b| qbits.contains(b)))
+            })
+            .map(|(_, op)| op)
+            .collect();
+
+        let mut new_to_old = qbits.to_vec();
+        let mut extra: Vec<usize> = ops.iter()
+            .filter(|op| !matches!(op, CircuitOp::Barrier(_)))
+            .filter_map(|op| op.qbits())
+            .flat_map(|bits| bits.iter().cloned())
+            .filter(|b| !qbits.contains(b))
+            .collect();
+        extra.sort_unstable();
+        extra.dedup();
+        new_to_old.extend(extra);
+
+        let new_bit = |bit: usize| new_to_old.iter().position(|&b| b == bit).unwrap();
+        let new_bits = |bits: &[usize]| bits.iter().map(|&b| new_bit(b)).collect::<Vec<usize>>();
+        let mut res = Circuit::new(new_to_old.len(), self.nr_cbits);
+        for op in ops
+        {
+            let new_op = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => {
+                        CircuitOp::Gate(gate.clone(), new_bits(bits))
+                    },
+                    CircuitOp::ConditionalGate(ref cbits, target, ref gate, ref bits) => {
+                        CircuitOp::ConditionalGate(cbits.clone(), target, gate.clone(),
+                            new_bits(bits))
+                    },
+                    CircuitOp::Reset(bit) => CircuitOp::Reset(new_bit(bit)),
+                    CircuitOp::ResetTo(bit, value) => CircuitOp::ResetTo(new_bit(bit), value),
+                    CircuitOp::ResetAll => CircuitOp::ResetAll,
+                    CircuitOp::Measure(qbit, cbit, basis) => {
+                        CircuitOp::Measure(new_bit(qbit), cbit, basis)
+                    },
+                    CircuitOp::MeasureAll(ref cbits, basis) => {
+                        CircuitOp::MeasureAll(new_to_old.iter().map(|&b| cbits[b]).collect(),
+                            basis)
+                    },
+                    CircuitOp::Peek(qbit, cbit, basis) => {
+                        CircuitOp::Peek(new_bit(qbit), cbit, basis)
+                    },
+                    CircuitOp::PeekAll(ref cbits, basis) => {
+                        CircuitOp::PeekAll(new_to_old.iter().map(|&b| cbits[b]).collect(),
+                            basis)
+                    },
+                    CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                        CircuitOp::PeekPartial(new_bits(qbits), cbits.clone())
+                    },
+                    CircuitOp::Barrier(ref bits) => {
+                        CircuitOp::Barrier(bits.iter()
+                            .filter(|&b| new_to_old.contains(b))
+                            .map(|&b| new_bit(b))
+                            .collect())
+                    },
+                    CircuitOp::Noise(ref kraus, ref bits) => {
+                        CircuitOp::Noise(kraus.clone(), new_bits(bits))
+                    },
+                    CircuitOp::ExpectationMeasure(ref obs, cbit) => {
+                        let ops: Vec<crate::stabilizer::PauliOp> = new_to_old.iter()
+                            .map(|&b| obs.ops()[b])
+                            .collect();
+                        CircuitOp::ExpectationMeasure(crate::stabilizer::PauliString::new(&ops),
+                            cbit)
                     }
-                    else
+                };
+            res.ops.push(new_op);
+        }
+
+        res
+    }
+
+    /// Find a sequence of gates implementing the identity.
+    ///
+    /// Starting at the gate at index `start` in `ops`, collect subsequent gates
+    /// that act on at least one of the qubits acted upon by the gates collected
+    /// so far. Operations acting only on other qubits are skipped; since they
+    /// commute with all preceding collected gates, they can be moved in front
+    /// of the collected sequence. The search stops at an operation acting on
+    /// the collected qubits that is not a gate (barriers are skipped when
+    /// `skip_barriers` is `true`), when more than `window` gates
+    /// would be collected, or, when computing with matrices, when the gates
+    /// would act on more than `MAX_IDENTITY_BITS` qubits. When `tableau` is
+    /// `true`, the product of the collected gates is tracked in a Clifford
+    /// tableau on all `nr_qbits` qubits, otherwise the unitary matrix on the
+    /// collected qubits is computed. Gates acting on no qubits, like global
+    /// phases, are passed over. Return the indices of the collected gates as
+    /// soon as their product equals the identity up to a global phase,
+    /// together with that phase factor, or `None` if no such sequence is
+    /// found. The phase factor is not computed in a tableau, and returned as 1.
+    fn find_identity(ops: &[CircuitOp], start: usize, nr_qbits: usize,
+        window: Option<usize>, tableau: bool, skip_barriers: bool)
+        -> Option<(Vec<usize>, crate::cmatrix::CNumber)>
+    {
+        let identity = if tableau { Some(crate::stabilizer::CliffordTableau::new(nr_qbits)) } else { None };
+        let mut product = identity.clone();
+        let mut support: Vec<usize> = vec![];
+        let mut matrix = crate::cmatrix::CMatrix::eye(1);
+        let mut selected = vec![];
+        for (idx, op) in ops.iter().enumerate().skip(start)
+        {
+            if matches!(op, CircuitOp::Gate(ref gate, _) if gate.nr_affected_bits() == 0)
+            {
+                continue;
+            }
+            let bits = op.qbits()?;
+            if !selected.is_empty() && !bits.iter().any(|b| support.contains(b))
+            {
+                continue;
+            }
+            if skip_barriers && !selected.is_empty() && matches!(op, CircuitOp::Barrier(_))
+            {
+                continue;
+            }
+            let gate = match *op
+                {
+                    CircuitOp::Gate(ref gate, _) => gate,
+                    _ => return None
+                };
+            if window.map_or(false, |w| selected.len() >= w)
+            {
+                return None;
+            }
+
+            selected.push(idx);
+            for &bit in bits
+            {
+                if !support.contains(&bit)
+                {
+                    support.push(bit);
+                    if !tableau
                     {
-                        for (qbit, &cbit) in cbits.iter().enumerate()
+                        if support.len() > MAX_IDENTITY_BITS
                         {
-                            res += &format!("measure {} -> {};\n", qbit_names[qbit],
-                                cbit_names[cbit]);
+                            return None;
                         }
+                        matrix = crate::cmatrix::kron_mat(&matrix, &crate::cmatrix::CMatrix::eye(2));
                     }
-                },
-                CircuitOp::Peek(_, _, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::ExportPeekInvalid("OpenQasm")
-                    ));
-                },
-                CircuitOp::PeekAll(_, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::ExportPeekInvalid("OpenQasm")
-                    ));
-                },
-                CircuitOp::Reset(qbit) => {
-                    res += &format!("reset {};\n", qbit_names[qbit]);
-                },
-                CircuitOp::ResetAll => {
-                    res += "reset q;\n";
-                },
-                CircuitOp::Barrier(ref qbits) => {
-                    if qbits.len() == self.nr_qbits
-                        && qbits.iter().enumerate().all(|(i, &b)| i==b)
-                    {
-                        res += "barrier q;\n";
-                    }
-                    else
+                }
+            }
+
+            let is_identity = if let Some(ref mut t) = product
+                {
+                    t.apply_gate(gate.as_gate(), bits).ok()?;
+                    product == identity
+                }
+                else
+                {
+                    let local: Vec<usize> = bits.iter()
+                        .map(|b| support.iter().position(|s| s == b).unwrap())
+                        .collect();
+                    crate::gates::apply_gate_mat_slice(matrix.view_mut(), gate.as_gate(),
+                        &local, support.len());
+                    let phase = matrix[[0, 0]];
+                    (phase.norm() - 1.0).abs() < 1.0e-10
+                        && matrix.indexed_iter().all(|((i, j), &x)| {
+                            let expected = if i == j { phase } else { crate::cmatrix::COMPLEX_ZERO };
+                            (x - expected).norm() < 1.0e-10
+                        })
+                };
+            if is_identity
+            {
+                let phase = if tableau { crate::cmatrix::COMPLEX_ONE } else { matrix[[0, 0]] };
+                return Some((selected, phase));
+            }
+        }
+
+        None
+    }
+
+    /// Check whether `op` is an unconditional gate acting on no qubits, with
+    /// fixed parameters, i.e. a fixed global phase.
+    fn is_global_phase(op: &CircuitOp) -> bool
+    {
+        match *op
+        {
+            CircuitOp::Gate(ref gate, _) => {
+                gate.nr_affected_bits() == 0
+                    && gate.parameters().iter()
+                        .all(|p| matches!(p, crate::gates::Parameter::Direct(_)))
+            },
+            _ => false
+        }
+    }
+
+    /// Multiply the global phase gates in `ops` and the additional phase factor
+    /// `factor` together, and replace them by a single `Phase` gate at the end
+    /// of `ops`. No gate is added when the total phase is trivial.
+    fn collect_global_phase(ops: &mut Vec<CircuitOp>, factor: crate::cmatrix::CNumber)
+    {
+        let mut total = factor;
+        ops.retain(|op| {
+            if Self::is_global_phase(op)
+            {
+                if let CircuitOp::Gate(ref gate, _) = *op
+                {
+                    total *= gate.matrix()[[0, 0]];
+                }
+                false
+            }
+            else
+            {
+                true
+            }
+        });
+
+        let phi = total.arg();
+        if phi.abs() > 1.0e-12
+        {
+            ops.push(CircuitOp::Gate(Box::new(crate::gates::Phase::new(phi)), vec![]));
+        }
+    }
+
+    /// Remove gate sequences implementing the identity, searching at most
+    /// `window` gates per sequence. See `find_identity()`.
+    fn remove_identities_with(&self, window: Option<usize>, tableau: bool,
+        skip_barriers: bool) -> Self
+    {
+        let mut ops = self.ops.clone();
+        let mut dropped_phase = crate::cmatrix::COMPLEX_ONE;
+        let mut changed = true;
+        while changed
+        {
+            changed = false;
+            let mut start = 0;
+            while start < ops.len()
+            {
+                if let Some((indices, phase)) = Self::find_identity(&ops, start, self.nr_qbits,
+                    window, tableau, skip_barriers)
+                {
+                    for &idx in indices.iter().rev()
                     {
-                        res += &format!("barrier {};\n",
-                            qbits.iter()
-                            .map(|&b| qbit_names[b].as_str())
-                            .collect::<Vec<&str>>()
-                            .join(", "));
+                        ops.remove(idx);
                     }
+                    dropped_phase *= phase;
+                    changed = true;
+                }
+                else
+                {
+                    start += 1;
                 }
             }
         }
 
-        Ok(res)
+        if self.phase_tracking
+        {
+            Self::collect_global_phase(&mut ops, dropped_phase);
+        }
+        self.with_ops(ops)
     }
 
-    fn check_c_qasm_measurement(qbit: usize, cbit: usize) -> crate::error::ExportResult<()>
+    /// Remove identities.
+    ///
+    /// Return a new circuit, in which sequences of gates that together
+    /// implement the identity (up to a global phase) are removed. A sequence
+    /// need not be contiguous: gates acting on other qubits may be interleaved,
+    /// but the sequence cannot extend past measurements, resets, barriers or
+    /// other non-unitary operations. For Clifford circuits, the products of
+    /// the gates are computed in the stabilizer formalism, otherwise the full
+    /// unitary is computed, which is only done for circuits of at most 10
+    /// qubits. In larger non-Clifford circuits, only sequences of at most 8
+    /// gates are removed, as in `remove_local_identities()`; use that function
+    /// directly for a different window size. When phase tracking is enabled,
+    /// the phases of the removed sequences are kept in a single `Phase` gate,
+    /// and Clifford circuits are treated like other circuits, since the
+    /// stabilizer formalism does not give the global phase.
+    pub fn remove_identities(&self) -> Self
     {
-        if qbit != cbit
+        self.remove_all_identities(false)
+    }
+
+    /// Remove identities, optionally skipping barriers. See
+    /// `remove_identities()`.
+    fn remove_all_identities(&self, skip_barriers: bool) -> Self
+    {
+        let clifford = self.ops.iter().all(|op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _) => gate.is_stabilizer(),
+                _ => true
+            }
+        });
+
+        if clifford && !self.phase_tracking
         {
-            Err(crate::error::ExportError::NoClassicalRegister)
+            self.remove_identities_with(None, true, skip_barriers)
+        }
+        else if self.nr_qbits <= MAX_IDENTITY_BITS
+        {
+            self.remove_identities_with(None, false, skip_barriers)
         }
         else
         {
-            Ok(())
+            self.remove_identities_with(Some(MAX_IDENTITY_WINDOW), false, skip_barriers)
         }
     }
 
-    /// Export to c-Qasm
+    /// Remove local identities.
     ///
-    /// Export this circuit to a program in c-Qasm format. On a successful
-    /// conversion, the result is `Ok` with the program text. When the conversion
-    /// to c-Qasm fails, `Err` with an error message is returned.
-    pub fn c_qasm(&self) -> crate::error::Result<String>
+    /// Return a new circuit, in which sequences of at most `window` gates
+    /// that together implement the identity (up to a global phase) are
+    /// removed. As in `remove_identities()`, the gates in a sequence may be
+    /// interleaved with gates on other qubits. Since only the unitary of the
+    /// qubits acted on by the gates in a sequence is computed, this can be
+    /// used on circuits of any size.
+    pub fn remove_local_identities(&self, window: usize) -> Self
     {
-        let mut res = String::from("version 1.0\n");
+        self.remove_identities_with(Some(window), false, false)
+    }
 
-        let mut qbit_names = vec![];
-        let mut cbit_names = vec![];
-        if self.nr_qbits > 0
+    /// Cancel gates according to the settings in `config`. At optimization
+    /// level 1, pairs of gates are cancelled, at higher levels, identities of
+    /// any length are searched for, followed by a local search for larger
+    /// circuits. See `find_identity()`.
+    fn cancel_gates(&self, config: &OptimizationConfig) -> Self
+    {
+        let skip_barriers = config.commute_past_barriers;
+        if config.optimization_level >= 2
         {
-            res += &format!("qubits {}\n", self.nr_qbits);
-            for i in 0..self.nr_qbits
-            {
-                qbit_names.push(format!("q[{}]", i));
-                cbit_names.push(format!("b[{}]", i));
-            }
+            self.remove_all_identities(skip_barriers)
+                .remove_identities_with(Some(MAX_AGGRESSIVE_WINDOW), false, skip_barriers)
+        }
+        else
+        {
+            self.remove_identities_with(Some(2), false, skip_barriers)
         }
+    }
 
-        for op in self.ops.iter()
+    /// Convert instructions to circuit operations.
+    ///
+    /// Convert the gate instructions in `instrs` to operations on this
+    /// circuit's qubits. Returns `None` if any of the instructions cannot be
+    /// converted.
+    fn instruction_ops(&self, instrs: &[crate::instruction::Instruction])
+        -> Option<Vec<CircuitOp>>
+    {
+        let mut circuit = Circuit::new(self.nr_qbits, self.nr_cbits);
+        for instruction in instrs.iter()
         {
-            match *op
+            instruction.add_to_circuit(&mut circuit, None).ok()?;
+        }
+        Some(circuit.ops)
+    }
+
+    /// Return the rotation instruction for `gate` operating on `bits`, if
+    /// `gate` is an `R`<sub>`X`</sub>, `R`<sub>`Y`</sub>, `R`<sub>`Z`</sub> or
+    /// `U`<sub>`1`</sub> gate with a fixed angle.
+    fn rotation_instruction(gate: &dyn CircuitGate, bits: &[usize])
+        -> Option<crate::instruction::Instruction>
+    {
+        use crate::instruction::Instruction;
+
+        if !gate.parameters().iter().all(|p| matches!(p, crate::gates::Parameter::Direct(_)))
+        {
+            return None;
+        }
+
+        let mut instrs = gate.instructions(bits);
+        if instrs.len() != 1
+        {
+            return None;
+        }
+        match instrs.pop()
+        {
+            Some(instr @ Instruction::RX(_, _)) | Some(instr @ Instruction::RY(_, _))
+                | Some(instr @ Instruction::RZ(_, _)) | Some(instr @ Instruction::U1(_, _)) => {
+                Some(instr)
+            },
+            _ => None
+        }
+    }
+
+    /// Merge rotations.
+    ///
+    /// Return a new circuit, in which subsequent rotations around the same
+    /// axis on the same qubit are replaced by a single rotation over the sum
+    /// of their angles. Operations on other qubits between the rotations are
+    /// allowed, as are barriers when `skip_barriers` is `true`. Only gates
+    /// with fixed angles are merged, reference parameters are left as is.
+    fn merge_rotations(&self, skip_barriers: bool) -> Self
+    {
+        use crate::instruction::Instruction;
+
+        let mut ops = self.ops.clone();
+        let mut idx = 0;
+        while idx < ops.len()
+        {
+            let mut rotation = match ops[idx]
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => {
+                        Self::rotation_instruction(gate.as_ref(), bits)
+                    },
+                    _ => None
+                };
+
+            let mut next = idx + 1;
+            while let Some(instr) = rotation.clone()
             {
-                CircuitOp::Gate(ref gate, ref bits) => {
-                    res += &format!("{}\n", gate.c_qasm(&qbit_names, bits)?);
-                },
-                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
-                    if control.is_empty()
+                if next >= ops.len()
+                {
+                    break;
+                }
+
+                let bit = match instr
                     {
-                        res += &format!("{}\n", gate.c_qasm(&qbit_names, bits)?);
-                    }
-                    else
+                        Instruction::RX(_, q) | Instruction::RY(_, q)
+                            | Instruction::RZ(_, q) | Instruction::U1(_, q) => q,
+                        _ => unreachable!()
+                    };
+                let other = match ops[next]
                     {
-                        let mut conditions = vec![];
-                        for (shift, &idx) in control.iter().enumerate()
-                        {
-                            if target & (1 << shift) == 0
+                        CircuitOp::Barrier(_) if skip_barriers => {
+                            next += 1;
+                            continue;
+                        },
+                        CircuitOp::Gate(ref gate, ref bits) => {
+                            if !bits.contains(&bit)
                             {
-                                res += &format!("not {}\n", cbit_names[idx]);
+                                next += 1;
+                                continue;
                             }
-                            conditions.push(cbit_names[idx].as_str());
-                        }
-                        let condition = conditions.join(", ");
-                        let gate_qasm = gate.conditional_c_qasm(&condition,
-                            &qbit_names, bits)?;
-                        res += &format!("{}\n", gate_qasm);
-                        for (shift, &idx) in control.iter().enumerate()
-                        {
-                            if target & (1 << shift) == 0
+                            Self::rotation_instruction(gate.as_ref(), bits)
+                        },
+                        ref op => {
+                            if op.qbits().map_or(false, |bits| !bits.contains(&bit))
                             {
-                                res += &format!("not {}\n", cbit_names[idx]);
+                                next += 1;
+                                continue;
                             }
+                            None
                         }
-                    }
-                },
-                CircuitOp::Measure(qbit, cbit, basis) => {
-                    Self::check_c_qasm_measurement(qbit, cbit)?;
-                    let op = match basis
+                    };
+
+                let merged = match (instr, other)
                     {
-                        Basis::X => "measure_x",
-                        Basis::Y => "measure_y",
-                        _        => "measure"
+                        (Instruction::RX(a, q), Some(Instruction::RX(b, _))) => Instruction::RX(a + b, q),
+                        (Instruction::RY(a, q), Some(Instruction::RY(b, _))) => Instruction::RY(a + b, q),
+                        (Instruction::RZ(a, q), Some(Instruction::RZ(b, _))) => Instruction::RZ(a + b, q),
+                        (Instruction::U1(a, q), Some(Instruction::U1(b, _))) => Instruction::U1(a + b, q),
+                        _ => break
                     };
-                    res += &format!("{} q[{}]\n", op, qbit);
+                match self.instruction_ops(::std::slice::from_ref(&merged))
+                {
+                    Some(mut new_ops) if new_ops.len() == 1 => {
+                        ops[idx] = new_ops.remove(0);
+                        ops.remove(next);
+                        rotation = Some(merged);
+                    },
+                    _ => { break; }
                 }
-                CircuitOp::MeasureAll(ref cbits, basis) => {
-                    for (qbit, &cbit) in cbits.iter().enumerate()
-                    {
-                        Self::check_c_qasm_measurement(qbit, cbit)?;
-                    }
-                    match basis
-                    {
-                        Basis::X => {
-                            for bit in 0..self.nr_qbits
+            }
+
+            idx += 1;
+        }
+
+        self.with_ops(ops)
+    }
+
+    /// Decompose multi-qubit gates.
+    ///
+    /// Return a new circuit, in which composite gates and Kronecker products
+    /// are expanded, and swap gates, controlled `Y` gates and controlled `Z`
+    /// gates are replaced by equivalent sequences of single-qubit gates and
+    /// `CX` gates. Gates that have no known decomposition, conditional
+    /// gates, and gates with reference parameters are left as is.
+    fn decompose_multi_qubit(&self) -> Self
+    {
+        use crate::instruction::Instruction;
+
+        let mut ops = vec![];
+        for op in self.ops.iter()
+        {
+            let decomposed = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits)
+                        if bits.len() > 1
+                        && gate.parameters().iter()
+                            .all(|p| matches!(p, crate::gates::Parameter::Direct(_))) => {
+                        let mut instrs = vec![];
+                        for instr in gate.instructions(bits)
+                        {
+                            match instr
                             {
-                                res += &format!("{}\n",
-                                    crate::gates::H::new().c_qasm(&qbit_names, &[bit])?);
+                                Instruction::Swap(b0, b1) => {
+                                    instrs.push(Instruction::CX(b0, b1));
+                                    instrs.push(Instruction::CX(b1, b0));
+                                    instrs.push(Instruction::CX(b0, b1));
+                                },
+                                Instruction::CY(c, t) => {
+                                    instrs.push(Instruction::Sdg(t));
+                                    instrs.push(Instruction::CX(c, t));
+                                    instrs.push(Instruction::S(t));
+                                },
+                                Instruction::CZ(c, t) => {
+                                    instrs.push(Instruction::H(t));
+                                    instrs.push(Instruction::CX(c, t));
+                                    instrs.push(Instruction::H(t));
+                                },
+                                instr => { instrs.push(instr); }
                             }
-                        },
-                        Basis::Y => {
-                            for bit in 0..self.nr_qbits
-                            {
-                                res += &format!("{}\n",
-                                    crate::gates::Sdg::new().c_qasm(&qbit_names, &[bit])?);
-                                res += &format!("{}\n",
-                                    crate::gates::H::new().c_qasm(&qbit_names, &[bit])?);
-                            }
-                        },
-                        _ => {
-                            /* do nothing */
                         }
-                    }
-                    res += &format!("measure_all\n");
-                },
-                CircuitOp::Peek(_, _, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::ExportPeekInvalid("c-Qasm")
-                    ));
-                },
-                CircuitOp::PeekAll(_, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::ExportPeekInvalid("c-Qasm")
-                    ));
-                },
-                CircuitOp::Reset(qbit) => {
-                    res += &format!("prep_z {}\n", qbit_names[qbit]);
+                        if instrs.len() > 1 { self.instruction_ops(&instrs) } else { None }
+                    },
+                    _ => None
+                };
+
+            match decomposed
+            {
+                Some(new_ops) => { ops.extend(new_ops); },
+                None => { ops.push(op.clone()); }
+            }
+        }
+
+        self.with_ops(ops)
+    }
+
+    /// Run a single round of the optimization passes enabled in `config`.
+    fn optimization_round(&self, config: &OptimizationConfig) -> Self
+    {
+        let mut res = if config.cancel_adjacent_gates
+            {
+                self.cancel_gates(config)
+            }
+            else
+            {
+                self.with_ops(self.ops.clone())
+            };
+        if config.merge_rotations
+        {
+            res = res.merge_rotations(config.commute_past_barriers);
+        }
+        if config.decompose_multi_qubit
+        {
+            res = res.decompose_multi_qubit();
+        }
+        if config.cancel_adjacent_gates && (config.merge_rotations || config.decompose_multi_qubit)
+        {
+            res = res.cancel_gates(config);
+        }
+        res
+    }
+
+    /// Optimize this circuit.
+    ///
+    /// Return a new circuit, equivalent to this circuit up to a global phase
+    /// (or exactly equivalent when phase tracking is enabled, see
+    /// `set_phase_tracking()`), that is optimized using the passes enabled in
+    /// `config`. The passes are
+    /// run in a fixed order: gate cancellation, rotation merging, decomposition
+    /// of multi-qubit gates, and cancellation of the gates thus created. This
+    /// is repeated until the number of operations in the circuit no longer
+    /// decreases.
+    pub fn optimize(&self, config: &OptimizationConfig) -> Self
+    {
+        let mut res = self.optimization_round(config);
+        loop
+        {
+            let next = res.optimization_round(config);
+            if next.ops.len() < res.ops.len()
+            {
+                res = next;
+            }
+            else
+            {
+                break;
+            }
+        }
+        res
+    }
+
+    /// Bind parameters.
+    ///
+    /// Bind the parameters of all gates in this circuit, replacing the free and
+    /// reference parameters whose names occur in `store` by their values in
+    /// the store. When a free parameter has no value in `store`, an
+    /// `UnboundParameter` error is returned; the gates before it will have
+    /// been bound.
+    pub fn bind_parameters(&mut self, store: &crate::gates::ParameterStore)
+        -> crate::error::Result<()>
+    {
+        for op in self.ops.iter_mut()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref mut gate, _)
+                | CircuitOp::ConditionalGate(_, _, ref mut gate, _) => {
+                    gate.bind(store)?;
                 },
-                CircuitOp::ResetAll => {
-                    for i in 0..self.nr_qbits
+                _ => { /* No parameters */ }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that all parameters of the gates in this circuit are bound to a
+    /// value, and return an `UnboundParameter` error if not.
+    fn check_parameters_bound(&self) -> crate::error::Result<()>
+    {
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _)
+                | CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                    if let Some(param) = gate.parameters().iter().find(|p| !p.is_bound())
                     {
-                        res += &format!("prep_z {}\n", qbit_names[i]);
+                        return Err(crate::error::Error::UnboundParameter(format!("{}", param)));
                     }
                 },
-                CircuitOp::Barrier(_) => {
-                    /* Not available */
-                }
+                _ => { /* No parameters */ }
             }
         }
-
-        Ok(res)
+        Ok(())
     }
 
-    /// Export to LaTeX
+    /// Seed the random number generator.
     ///
-    /// Export this circuit to LaTeX using the qcircuit package. On a successful
-    /// conversion, the result is `Ok` with the LaTeX code. When the conversion
-    /// to LaTeX fails, `Err` with an error message is returned.
-    pub fn latex(&self) -> crate::error::Result<String>
+    /// Use a random number generator seeded with `seed` for sampling the
+    /// outcomes of measurements in the methods of this circuit that do not
+    /// take a random number generator as argument, like `execute()` and
+    /// `reexecute()`. The generator is shared between subsequent calls, so
+    /// two circuits with the same operations and the same seed produce
+    /// identical results for the same sequence of calls. Without a seed, the
+    /// thread-local random number generator is used.
+    pub fn with_seed(&mut self, seed: u64) -> &mut Self
     {
-        let mut state = crate::export::LatexExportState::new(self.nr_qbits, self.nr_cbits);
-        for op in self.ops.iter()
+        self.rng = Some(<rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed));
+        self
+    }
+
+    /// Call `f` with this circuit and its default random number generator:
+    /// the seeded generator if a seed was set with `with_seed()`, or the
+    /// thread-local generator otherwise.
+    fn with_default_rng<T, F>(&mut self, f: F) -> T
+    where F: FnOnce(&mut Self, &mut dyn rand::RngCore) -> T
+    {
+        match self.rng.take()
         {
-            match *op
+            Some(mut rng) => {
+                let res = f(self, &mut rng);
+                self.rng = Some(rng);
+                res
+            },
+            None => f(self, &mut rand::thread_rng())
+        }
+    }
+
+    /// Execute this circuit
+    ///
+    /// Execute this circuit, performing its operations and measurements.
+    /// Measurements are made over `nr_shots` executions of the circuit. This
+    /// function clears any previous states of the system (quantum or classical).
+    #[inline(always)]
+    pub fn execute(&mut self, nr_shots: usize) -> crate::error::Result<()>
+    {
+        self.with_default_rng(|circuit, mut rng| circuit.execute_with_rng(nr_shots, &mut rng))
+    }
+
+    /// Execute this circuit
+    ///
+    /// Execute this circuit, performing its operations and measurements.
+    /// Measurements are made over `nr_shots` executions of the circuit, using
+    /// random number generator `rng` for sampling. This function clears any
+    /// previous states of the system (quantum or classical).
+    pub fn execute_with_rng<R: rand::RngCore>(&mut self, nr_shots: usize, rng: &mut R)
+        -> crate::error::Result<()>
+    {
+        let q_state = match self.simulation_mode
             {
-                CircuitOp::Gate(ref gate, ref bits) => {
-                    gate.latex(bits, &mut state)?;
+                SimulationMode::Automatic if self.is_stabilizer_circuit() => {
+                    QuStateRepr::stabilizer(self.nr_qbits, nr_shots)
                 },
-                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
-                    state.start_range_op(bits, Some(control))?;
-                    let controlled = state.set_controlled(true);
-                    gate.latex(bits, &mut state)?;
-                    state.set_controlled(controlled);
-                    state.set_condition(control, target, bits)?;
-                    state.end_range_op();
+                SimulationMode::Automatic | SimulationMode::Statevector => {
+                    QuStateRepr::vector(self.nr_qbits, nr_shots)
                 },
-                CircuitOp::Measure(qbit, cbit, basis) => {
-                    let basis_lbl = match basis
-                    {
-                        Basis::X => Some("X"),
-                        Basis::Y => Some("Y"),
-                        _        => None
-                    };
-                    state.set_measurement(qbit, cbit, basis_lbl)?;
-                }
-                CircuitOp::MeasureAll(ref cbits, basis) => {
-                    let basis_lbl = match basis
-                    {
-                        Basis::X => Some("X"),
-                        Basis::Y => Some("Y"),
-                        _        => None
-                    };
-                    for (qbit, &cbit) in cbits.iter().enumerate()
+                SimulationMode::DensityMatrix => {
+                    QuStateRepr::density(self.nr_qbits, nr_shots)
+                },
+                SimulationMode::Stabilizer => {
+                    if !self.is_stabilizer_circuit()
                     {
-                        state.set_measurement(qbit, cbit, basis_lbl)?;
+                        return Err(crate::error::Error::NotACliffordCircuit);
                     }
+                    QuStateRepr::stabilizer(self.nr_qbits, nr_shots)
                 },
-                CircuitOp::Peek(_, _, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::NotImplemented("LaTeX",
-                            String::from("peek")
-                        )
-                    ));
+                SimulationMode::MPS(max_bond_dim) => {
+                    QuStateRepr::mps(self.nr_qbits, nr_shots, max_bond_dim)
+                }
+            };
+        self.execute_with(nr_shots, rng, q_state)
+    }
+
+    /// Execute this circuit in density matrix mode.
+    ///
+    /// Execute this circuit, performing its operations and measurements, like
+    /// `execute()`, but simulating the quantum state using a density matrix,
+    /// regardless of the simulation mode set for this circuit. This allows
+    /// for taking noise operations into account.
+    pub fn execute_density(&mut self, nr_shots: usize) -> crate::error::Result<()>
+    {
+        let q_state = QuStateRepr::density(self.nr_qbits, nr_shots);
+        self.with_default_rng(|circuit, mut rng| circuit.execute_with(nr_shots, &mut rng, q_state))
+    }
+
+    /// Execute this circuit
+    ///
+    /// Execute this circuit, performing its operations and measurements.
+    /// Measurements are made over `nr_shots` executions of the circuit, using
+    /// random number generator `rng` for sampling. The initial quantum state of
+    /// the system is set to `q_state`. The classical state is cleared before
+    /// execution.
+    pub fn execute_with<R: rand::RngCore>(&mut self, nr_shots: usize, rng: &mut R,
+        q_state: QuStateRepr) -> crate::error::Result<()>
+    {
+        self.q_state = Some(q_state);
+        self.c_state = Some(ndarray::Array1::zeros(nr_shots));
+        self.reexecute_with_rng(rng)
+    }
+
+    /// Execute a circuit again.
+    ///
+    /// Run this circuit again, starting with the state from the previous
+    /// execution. If this circuit has not been run before, a `NotExecuted`
+    /// error is returned.
+    #[inline(always)]
+    pub fn reexecute(&mut self) -> crate::error::Result<()>
+    {
+        self.with_default_rng(|circuit, mut rng| circuit.reexecute_with_rng(&mut rng))
+    }
+
+    /// Execute a circuit again.
+    ///
+    /// Run this circuit again, starting with the state from the previous
+    /// execution, using random number generator `rng` for sampling. If this
+    /// circuit has not been run before, a `NotExecuted` error is returned.
+    /// When the circuit contains free parameters that have not been bound,
+    /// an `UnboundParameter` error is returned.
+    pub fn reexecute_with_rng<R: rand::Rng>(&mut self, rng: &mut R)
+        -> crate::error::Result<()>
+    {
+        self.check_parameters_bound()?;
+        let cached_ops = if self.cache_gates { Some(self.cached_ops()) } else { None };
+        self.warnings.clear();
+        if let Some(c_state) = self.c_state.as_mut()
+        {
+            let ops = cached_ops.as_ref().unwrap_or(&self.ops);
+            match self.q_state
+            {
+                Some(QuStateRepr::Stabilizer(ref mut state)) => {
+                    Self::do_execute_with(state, c_state, ops, rng)
                 },
-                CircuitOp::PeekAll(_, _) => {
-                    return Err(crate::error::Error::from(
-                        crate::error::ExportError::NotImplemented("LaTeX",
-                            String::from("peek all")
-                        )
-                    ));
+                Some(QuStateRepr::Vector(ref mut state)) => {
+                    Self::do_execute_with(state, c_state, ops, rng)
                 },
-                CircuitOp::Reset(qbit) => {
-                    state.set_reset(qbit)?;
+                Some(QuStateRepr::Density(ref mut state)) => {
+                    Self::do_execute_with(state, c_state, ops, rng)
                 },
-                CircuitOp::ResetAll => {
-                    state.start_range_op(&[0, self.nr_qbits-1], None)?;
-                    for qbit in 0..self.nr_qbits
+                Some(QuStateRepr::MPS(ref mut state)) => {
+                    Self::do_execute_with(state, c_state, ops, rng)?;
+                    if state.branches().any(|(mps, _)| mps.truncation_error() > 0.0)
                     {
-                        state.set_reset(qbit)?;
+                        self.warnings.push(crate::error::Warning::TruncationOccurred);
                     }
-                    state.end_range_op();
+                    Ok(())
                 },
-                CircuitOp::Barrier(ref qbits) => {
-                    state.set_barrier(qbits)?;
+                _ => {
+                    Err(crate::error::Error::NotExecuted)
                 }
             }
         }
+        else
+        {
+            Err(crate::error::Error::NotExecuted)
+        }
+    }
 
-        Ok(state.code())
+    /// Duration of an operation.
+    ///
+    /// Return the duration of operation `op`, as given in `gate_times`. Gates
+    /// are looked up by their description, or, when not found, by the part of
+    /// their description before any parameters (e.g. `"RX"` for `"RX(0.5000)"`).
+    /// Measurements are looked up as `"measure"`, resets as `"reset"`.
+    /// Operations that are not found take no time.
+    fn op_duration(op: &CircuitOp, gate_times: &::std::collections::HashMap<String, f64>) -> f64
+    {
+        let lookup = |name: &str| {
+            gate_times.get(name)
+                .or_else(|| name.split('(').next().and_then(|n| gate_times.get(n)))
+                .cloned()
+                .unwrap_or(0.0)
+        };
+
+        match *op
+        {
+            CircuitOp::Gate(ref gate, _) | CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                lookup(gate.description())
+            },
+            CircuitOp::Measure(_, _, _) | CircuitOp::MeasureAll(_, _)
+            | CircuitOp::Peek(_, _, _) | CircuitOp::PeekAll(_, _)
+            | CircuitOp::PeekPartial(_, _) | CircuitOp::ExpectationMeasure(_, _) => {
+                lookup("measure")
+            },
+            CircuitOp::Reset(_) | CircuitOp::ResetTo(_, _) | CircuitOp::ResetAll => {
+                lookup("reset")
+            },
+            CircuitOp::Barrier(_) | CircuitOp::Noise(_, _) => 0.0
+        }
     }
-}
 
-#[macro_export]
-macro_rules! circuit_method_check
-{
-    ( add_conditional_gate $res:expr ) => { $res? };
-    ( add_gate $res:expr ) => { $res? };
-    ( barrier $res:expr ) => { $res? };
-    ( cx $res:expr ) => { $res? };
-    ( h $res:expr ) => { $res? };
-    ( measure $res:expr ) => { $res? };
-    ( measure_all $res:expr ) => { $res? };
-    ( measure_all_basis $res:expr ) => { $res? };
-    ( measure_x $res:expr ) => { $res? };
-    ( measure_y $res:expr ) => { $res? };
-    ( measure_z $res:expr ) => { $res? };
-    ( peek $res:expr ) => { $res? };
-    ( peek_x $res:expr ) => { $res? };
-    ( peek_y $res:expr ) => { $res? };
-    ( peek_z $res:expr ) => { $res? };
-    ( peek_all $res:expr ) => { $res? };
-    ( peek_all_basis $res:expr ) => { $res? };
-    ( reset $res:expr ) => { $res? };
-    ( s $res:expr ) => { $res? };
-    ( sdg $res:expr ) => { $res? };
-    ( x $res:expr ) => { $res? };
-    ( y $res:expr ) => { $res? };
-    ( z $res:expr ) => { $res? };
-    ( $name:ident $res:expr ) => { $res };
-}
-
-#[macro_export]
-macro_rules! circuit
-{
-    ($nr_qbits:expr, $nr_cbits:expr, { $( $method_name:ident ( $( $arg:expr ),* ) );* ; } ) => {
-        {
-            let generator = || {
-                let mut circuit = $crate::circuit::Circuit::new($nr_qbits, $nr_cbits);
-                $(
-                    circuit_method_check!(
-                        $method_name
-                        circuit.$method_name($($arg),*)
-                    );
-                );*
-                Ok(circuit) as $crate::error::Result<$crate::circuit::Circuit>
-            };
-            generator()
-        }
+    /// Execute with a timing model.
+    ///
+    /// Execute this circuit for `nr_shots` shots, taking into account the
+    /// relaxation of qubits while they are idle. See
+    /// `execute_with_timing_and_rng()`.
+    pub fn execute_with_timing(&mut self, nr_shots: usize,
+        gate_times: &::std::collections::HashMap<String, f64>, t1: &[f64], t2: &[f64])
+        -> crate::error::Result<(crate::idhash::U64HashMap<usize>, CircuitTimingStats)>
+    {
+        self.with_default_rng(|circuit, mut rng| {
+            circuit.execute_with_timing_and_rng(nr_shots, gate_times, t1, t2, &mut rng)
+        })
     }
-}
-
-#[cfg(test)]
-mod tests
-{
-    use super::{Basis, Circuit, CircuitOp, QuStateRepr};
-    use crate::gates::{CX, CY, H, S, X};
 
-    #[test]
-    fn test_gate_methods()
+    /// Execute with a timing model.
+    ///
+    /// Schedule the operations in this circuit as soon as possible, with the
+    /// durations given in `gate_times` (see `op_duration()`), where operations
+    /// acting on multiple qubits, barriers, and operations on the full state
+    /// synchronize the qubits they act on. For every period in which a qubit
+    /// waits for an operation, a `ThermalRelaxation` channel with the qubit's
+    /// relaxation times `t1` and `t2` is applied. The circuit is then executed
+    /// in density matrix mode for `nr_shots` shots, using random number
+    /// generator `rng`. Note that idle periods after the last operation on a
+    /// qubit are not simulated, since they cannot affect the measurement
+    /// results. Returns the histogram of the measurement results, and the
+    /// timing statistics of the circuit. An error is returned when the
+    /// number of relaxation times does not match the number of qubits, or when
+    /// the relaxation times of a qubit do not satisfy `0 < T2 ≤ 2T1`.
+    pub fn execute_with_timing_and_rng<R: rand::RngCore>(&mut self, nr_shots: usize,
+        gate_times: &::std::collections::HashMap<String, f64>, t1: &[f64], t2: &[f64],
+        rng: &mut R)
+        -> crate::error::Result<(crate::idhash::U64HashMap<usize>, CircuitTimingStats)>
     {
-        let z = crate::cmatrix::COMPLEX_ZERO;
-        let o = crate::cmatrix::COMPLEX_ONE;
-        let x = crate::cmatrix::COMPLEX_HSQRT2;
-        let i = crate::cmatrix::COMPLEX_I;
+        use crate::gates::NoiseChannel;
 
-        let mut circuit = Circuit::new(2, 0);
-        assert_eq!(circuit.h(0), Ok(()));
-        match circuit.ops.last()
+        if t1.len() != self.nr_qbits
         {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), &array![[x, x], [x, -x]]);
-                assert_eq!(bits, &vec![0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not an H gate"),
-            None => panic!("H gate was not added")
-            // LCOV_EXCL_STOP
+            return Err(crate::error::Error::InvalidNrBits(t1.len(), self.nr_qbits,
+                String::from("T1 times")));
         }
-
-        assert_eq!(circuit.x(1), Ok(()));
-        match circuit.ops.last()
+        if t2.len() != self.nr_qbits
         {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[z, o], [o, z]]);
-                assert_eq!(bits, &vec![1]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not an X gate"),
-            None => panic!("X gate was not added")
-            // LCOV_EXCL_STOP
+            return Err(crate::error::Error::InvalidNrBits(t2.len(), self.nr_qbits,
+                String::from("T2 times")));
         }
-
-        assert_eq!(circuit.y(0), Ok(()));
-        match circuit.ops.last()
+        if let Some(bit) = (0..self.nr_qbits)
+            .find(|&b| !(t1[b] > 0.0 && t2[b] > 0.0 && t2[b] <= 2.0 * t1[b]))
         {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[z, -i], [i, z]]);
-                assert_eq!(bits, &vec![0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a Y gate"),
-            None => panic!("Y gate was not added")
-            // LCOV_EXCL_STOP
+            return Err(crate::error::Error::InvalidRelaxationTimes(bit));
         }
 
-        assert_eq!(circuit.z(1), Ok(()));
-        match circuit.ops.last()
+        let all_bits: Vec<usize> = (0..self.nr_qbits).collect();
+        let mut available = vec![0.0; self.nr_qbits];
+        let mut busy = vec![0.0; self.nr_qbits];
+        let mut total_time = 0.0;
+        let mut ops = vec![];
+        for op in self.ops.iter()
         {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, -o]]);
-                assert_eq!(bits, &vec![1]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a Z gate"),
-            None => panic!("Z gate was not added")
-            // LCOV_EXCL_STOP
-        }
+            if let CircuitOp::Noise(_, _) = *op
+            {
+                ops.push(op.clone());
+                continue;
+            }
 
-        assert_eq!(circuit.rx(::std::f64::consts::PI, 1), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[z, -i], [-i, z]]);
-                assert_eq!(bits, &vec![1]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not an RX gate"),
-            None => panic!("RX gate was not added")
-            // LCOV_EXCL_STOP
+            let bits = op.qbits().unwrap_or(&all_bits);
+            let duration = Self::op_duration(op, gate_times);
+            let start = bits.iter().map(|&b| available[b]).fold(0.0, f64::max);
+            for &bit in bits
+            {
+                let idle = start - available[bit];
+                if idle > 0.0
+                {
+                    let channel = crate::gates::ThermalRelaxation::new(idle, t1[bit], t2[bit]);
+                    ops.push(CircuitOp::Noise(channel.kraus_operators(), vec![bit]));
+                }
+                available[bit] = start + duration;
+                busy[bit] += duration;
+            }
+            total_time += duration;
+            ops.push(op.clone());
         }
 
-        assert_eq!(circuit.ry(::std::f64::consts::PI, 0), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[z, -o], [o, z]]);
-                assert_eq!(bits, &vec![0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not an RY gate"),
-            None => panic!("RY gate was not added")
-            // LCOV_EXCL_STOP
-        }
+        let critical_path_time = available.iter().cloned().fold(0.0, f64::max);
+        let idle_times: Vec<f64> = busy.iter().map(|&b| critical_path_time - b).collect();
+        let stats = CircuitTimingStats {
+            total_time: total_time,
+            critical_path_time: critical_path_time,
+            t1_errors: idle_times.iter().zip(t1).map(|(&t, &t1)| 1.0 - (-t / t1).exp()).collect(),
+            t2_errors: idle_times.iter().zip(t2).map(|(&t, &t2)| 1.0 - (-t / t2).exp()).collect(),
+            idle_times: idle_times
+        };
+
+        let mut timed = Circuit::new(self.nr_qbits, self.nr_cbits);
+        timed.ops = ops;
+        timed.cache_gates = self.cache_gates;
+        timed.execute_with(nr_shots, rng, QuStateRepr::density(self.nr_qbits, nr_shots))?;
+        self.q_state = timed.q_state.take();
+        self.c_state = timed.c_state.take();
+        self.warnings = timed.warnings;
+
+        Ok((self.histogram()?, stats))
+    }
 
-        assert_eq!(circuit.rz(::std::f64::consts::PI, 1), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[-i, z], [z, i]]);
-                assert_eq!(bits, &vec![1]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not an RZ gate"),
-            None => panic!("RZ gate was not added")
-            // LCOV_EXCL_STOP
-        }
+    /// Compile this circuit.
+    ///
+    /// Create a `CompiledCircuit` from this circuit, which can be evaluated
+    /// efficiently for different values of the reference parameters in its
+    /// gates. The unitary part of the circuit, up to the first operation that
+    /// is not a gate or barrier, is stored such that upon reevaluation only the
+    /// gates affected by changed parameters need to be applied again.
+    pub fn compile(&self) -> crate::compiled::CompiledCircuit
+    {
+        let split = self.ops.iter()
+            .position(|op| !matches!(op, CircuitOp::Gate(_, _) | CircuitOp::Barrier(_)))
+            .unwrap_or(self.ops.len());
+        let gates = self.ops[..split].iter().filter_map(|op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => Some((gate.clone(), bits.clone())),
+                _ => None
+            }
+        }).collect();
 
-        assert_eq!(circuit.u1(::std::f64::consts::FRAC_PI_4, 1), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, x*(o+i)]]);
-                assert_eq!(bits, &vec![1]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a U1 gate"),
-            None => panic!("U1 gate was not added")
-            // LCOV_EXCL_STOP
-        }
+        let mut tail = Circuit::new(self.nr_qbits, self.nr_cbits);
+        tail.ops = self.ops[split..].to_vec();
+        tail.cache_gates = self.cache_gates;
 
-        assert_eq!(circuit.u2(::std::f64::consts::FRAC_PI_4,
-            ::std::f64::consts::FRAC_PI_2, 0), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![
-                    [x, -x*i],
-                    [0.5*(o+i), 0.5*(-o+i)]
-                ]);
-                assert_eq!(bits, &vec![0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a U2 gate"),
-            None => panic!("U2 gate was not added")
-            // LCOV_EXCL_STOP
-        }
+        crate::compiled::CompiledCircuit::new(self.nr_qbits, gates, tail)
+    }
 
-        assert_eq!(circuit.u3(::std::f64::consts::PI, ::std::f64::consts::FRAC_PI_4,
-            ::std::f64::consts::FRAC_PI_2, 0), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![
-                    [z, -i],
-                    [x*(o+i), z]
-                ]);
-                assert_eq!(bits, &vec![0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a U3 gate"),
-            None => panic!("U3 gate was not added")
-            // LCOV_EXCL_STOP
-        }
+    /// Set the simulation mode.
+    ///
+    /// Set the backend used for simulating the quantum state when this
+    /// circuit is executed to `mode`. The default is
+    /// `SimulationMode::Automatic`.
+    pub fn set_simulation_mode(&mut self, mode: SimulationMode)
+    {
+        self.simulation_mode = mode;
+    }
 
-        assert_eq!(circuit.cx(1, 0), Ok(()));
-        match circuit.ops.last()
-        {
-            Some(CircuitOp::Gate(gate, bits)) => {
-                assert_complex_matrix_eq!(gate.matrix(), array![
-                    [o, z, z, z],
-                    [z, o, z, z],
-                    [z, z, z, o],
-                    [z, z, o, z]
-                ]);
-                assert_eq!(bits, &vec![1, 0]);
-            },
-            // LCOV_EXCL_START
-            Some(_) => panic!("Value added was not a CX gate"),
-            None => panic!("CX gate was not added")
-            // LCOV_EXCL_STOP
-        }
+    /// The backend used for simulating the quantum state when this circuit is
+    /// executed
+    pub fn simulation_mode(&self) -> &SimulationMode
+    {
+        &self.simulation_mode
     }
 
-    #[test]
-    fn test_execute()
+    /// Warnings from the last execution.
+    ///
+    /// Return the warnings emitted during the last (re)execution of this
+    /// circuit, e.g. when a matrix product state simulation had to discard
+    /// part of the state because its bond dimension was too small.
+    pub fn warnings(&self) -> &[crate::error::Warning]
     {
-        let nr_shots = 5;
-        let mut circuit = circuit!(2, 2, {
-            add_gate(X::new(), &[0]);
-            add_gate(X::new(), &[1]);
-            add_gate(CX::new(), &[0, 1]);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        assert_eq!(circuit.cstate(), Some(&array![0b01, 0b01, 0b01, 0b01, 0b01]));
+        &self.warnings
     }
 
-    #[test]
-    fn test_measure()
+    /// Set whether to cache gate matrices.
+    ///
+    /// When `cache_gates` is `true`, the matrices of all gates in this
+    /// circuit are computed once at the start of every (re)execution of
+    /// the circuit, and these precomputed matrices are used when applying the
+    /// gates. This may speed up the simulation of circuits with many
+    /// parametrized gates.
+    pub fn set_cache_gates(&mut self, cache_gates: bool)
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
+        self.cache_gates = cache_gates;
+    }
+
+    /// Set whether to track the global phase.
+    ///
+    /// When `phase_tracking` is `true`, transformations of this circuit that
+    /// would otherwise only be correct up to a global phase, like
+    /// `remove_identities()` and `optimize()`, keep the exact global phase by
+    /// inserting a `Phase` gate. The resulting circuits inherit this setting.
+    /// Phase tracking is disabled by default.
+    pub fn set_phase_tracking(&mut self, phase_tracking: bool)
+    {
+        self.phase_tracking = phase_tracking;
+    }
+
+    /// Whether the global phase is tracked in transformations of this circuit
+    pub fn phase_tracking(&self) -> bool
+    {
+        self.phase_tracking
+    }
+
+    /// The global phase of this circuit.
+    ///
+    /// Return the product of the phase factors of all unconditional gates in
+    /// this circuit that act on no qubits, like `Phase` gates. This is the
+    /// global phase picked up by the circuit on top of the phases in the
+    /// matrices of its other gates. Note that this is only exact for circuits
+    /// derived with phase tracking enabled, as otherwise transformations may
+    /// have dropped global phases.
+    pub fn global_phase_factor(&self) -> crate::cmatrix::CNumber
+    {
+        self.ops.iter().fold(crate::cmatrix::COMPLEX_ONE, |factor, op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _) if gate.nr_affected_bits() == 0 => {
+                    factor * gate.matrix()[[0, 0]]
+                },
+                _ => factor
+            }
+        })
+    }
+
+    /// Return a copy of the operations in this circuit, with all gates
+    /// replaced by cached versions
+    fn cached_ops(&self) -> Vec<CircuitOp>
+    {
+        self.ops.iter().map(|op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    CircuitOp::Gate(Box::new(crate::gates::CachedGate::new(gate.clone())), bits.clone())
+                },
+                CircuitOp::ConditionalGate(ref cbits, target, ref gate, ref bits) => {
+                    CircuitOp::ConditionalGate(cbits.clone(), target,
+                        Box::new(crate::gates::CachedGate::new(gate.clone())), bits.clone())
+                },
+                _ => op.clone()
+            }
+        }).collect()
+    }
+
+    fn do_execute_with<Q: QuState, R: rand::Rng>(q_state: &mut Q,
+        c_state: &mut ndarray::Array1<u64>, ops: &[CircuitOp], rng: &mut R)
+        -> crate::error::Result<()>
+    {
+        for op in ops
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    q_state.apply_gate(gate.as_gate(), bits.as_slice())?;
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    let mut cbits = vec![0; c_state.len()];
+                    for (idst, &isrc) in control.iter().enumerate()
+                    {
+                        for (db, &sb) in cbits.iter_mut().zip(c_state.iter())
+                        {
+                            *db |= ((sb >> isrc) & 1) << idst;
+                        }
+                    }
+                    let apply_gate: Vec<bool> = cbits.iter()
+                        .map(|&b| b == target)
+                        .collect();
+                    q_state.apply_conditional_gate(&apply_gate, gate.as_gate(),
+                        bits.as_slice())?;
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.measure_into(qbit, cbit, c_state, rng)?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                        },
+                        Basis::Y => {
+                            q_state.apply_gate(&crate::gates::Sdg::new(), &[qbit])?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.measure_into(qbit, cbit, c_state, rng)?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.apply_gate(&crate::gates::S::new(), &[qbit])?;
+                        },
+                        Basis::Z => {
+                            q_state.measure_into(qbit, cbit, c_state, rng)?;
+                        }
+                    }
+                }
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.measure_all_into(cbits, c_state, rng)?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                        },
+                        Basis::Y => {
+                            q_state.apply_unary_gate_all(&crate::gates::Sdg::new())?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.measure_all_into(cbits, c_state, rng)?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.apply_unary_gate_all(&crate::gates::S::new())?;
+                        },
+                        Basis::Z => {
+                            q_state.measure_all_into(cbits, c_state, rng)?;
+                        }
+                    }
+                },
+                CircuitOp::Peek(qbit, cbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.peek_into(qbit, cbit, c_state, rng)?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                        },
+                        Basis::Y => {
+                            q_state.apply_gate(&crate::gates::Sdg::new(), &[qbit])?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.peek_into(qbit, cbit, c_state, rng)?;
+                            q_state.apply_gate(&crate::gates::H::new(), &[qbit])?;
+                            q_state.apply_gate(&crate::gates::S::new(), &[qbit])?;
+                        },
+                        Basis::Z => {
+                            q_state.peek_into(qbit, cbit, c_state, rng)?;
+                        }
+                    }
+                },
+                CircuitOp::PeekAll(ref cbits, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.peek_all_into(cbits, c_state, rng)?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                        },
+                        Basis::Y => {
+                            q_state.apply_unary_gate_all(&crate::gates::Sdg::new())?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.peek_all_into(cbits, c_state, rng)?;
+                            q_state.apply_unary_gate_all(&crate::gates::H::new())?;
+                            q_state.apply_unary_gate_all(&crate::gates::S::new())?;
+                        },
+                        Basis::Z => {
+                            q_state.peek_all_into(cbits, c_state, rng)?;
+                        }
+                    }
+                },
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    q_state.partial_measure_peek(qbits, cbits, c_state, rng)?;
+                },
+                CircuitOp::Reset(bit) => {
+                    q_state.reset(bit, rng)?;
+                },
+                CircuitOp::ResetTo(bit, value) => {
+                    q_state.reset(bit, rng)?;
+                    if value
+                    {
+                        q_state.apply_gate(&crate::gates::X::new(), &[bit])?;
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    q_state.reset_all();
+                },
+                CircuitOp::Barrier(_) => {
+                    /* Nothing to be done */
+                },
+                CircuitOp::Noise(ref kraus, ref bits) => {
+                    q_state.apply_channel(kraus, bits)?;
+                },
+                CircuitOp::ExpectationMeasure(ref obs, cbit) => {
+                    q_state.expectation_into(obs, cbit, c_state)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compute the density matrix.
+    ///
+    /// Compute the density matrix of the final state of this circuit, starting
+    /// from |00...0⟩, taking any noise operations into account. Since
+    /// measurements would require branching on the classical register, only
+    /// circuits containing gates, noise, resets and barriers are supported.
+    pub fn density_matrix(&self) -> crate::error::Result<crate::density::DensityMatrix>
+    {
+        let mut rho = crate::density::DensityMatrix::new(self.nr_qbits);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    rho.apply_gate(gate.as_gate(), bits)?;
+                },
+                CircuitOp::Noise(ref kraus, ref bits) => {
+                    rho.apply_channel(kraus, bits)?;
+                },
+                CircuitOp::Reset(bit) => {
+                    rho.reset(bit)?;
+                },
+                CircuitOp::ResetTo(bit, value) => {
+                    rho.reset(bit)?;
+                    if value
+                    {
+                        rho.apply_gate(&crate::gates::X::new(), &[bit])?;
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    rho.reset_all();
+                },
+                CircuitOp::Barrier(_) => {
+                    /* Nothing to be done */
+                },
+                CircuitOp::ExpectationMeasure(ref obs, _) => {
+                    // Physically, the observable is measured, projecting the
+                    // state onto its ±1 eigenspaces
+                    let id = crate::cmatrix::CMatrix::eye(1 << self.nr_qbits);
+                    let p = obs.matrix();
+                    let bits: Vec<usize> = (0..self.nr_qbits).collect();
+                    rho.apply_channel(&[(&id + &p) * 0.5, (&id - &p) * 0.5], &bits)?;
+                },
+                _ => {
+                    return Err(crate::error::Error::OpNotImplemented(
+                        String::from("density_matrix"),
+                        String::from("measurement or conditional")
+                    ));
+                }
+            }
+        }
+
+        Ok(rho)
+    }
+
+    /// Compute the state vector.
+    ///
+    /// Compute the coefficient vector of the final state of this circuit,
+    /// starting from |00...0⟩, by running it once in the coefficient vector
+    /// representation. As in other pure state simulations, noise is ignored.
+    /// Since the outcomes of measurements and single qubit resets are random,
+    /// only circuits containing gates, noise, barriers and resets of the full
+    /// state are supported; for other operations, an `OpNotImplemented` error
+    /// is returned.
+    pub fn statevector(&self) -> crate::error::Result<crate::cmatrix::CVector>
+    {
+        self.check_parameters_bound()?;
+        let mut state = crate::vectorstate::VectorState::new(self.nr_qbits, 1);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    state.apply_gate(gate.as_gate(), bits)?;
+                },
+                CircuitOp::ResetAll => {
+                    state.reset_all();
+                },
+                CircuitOp::Noise(_, _) | CircuitOp::Barrier(_) => {
+                    /* Nothing to be done */
+                },
+                _ => {
+                    return Err(crate::error::Error::OpNotImplemented(
+                        String::from("statevector"),
+                        String::from("measurement or conditional")
+                    ));
+                }
+            }
+        }
+
+        state.statevector().ok_or_else(|| {
+            crate::error::Error::InternalError(String::from("No state vector for a single run"))
+        })
+    }
+
+    /// Sample from the state vector.
+    ///
+    /// Compute the state vector of this circuit as in `statevector()`, and
+    /// draw `nr_samples` independent samples from the probability
+    /// distribution of the outcomes of measuring all qubits. Each sample is
+    /// returned as an integer in which bit `i` holds the outcome for qubit
+    /// `i`, matching the keys in `histogram()` when all qubits are measured
+    /// into the classical bits of the same index. Since the state is only
+    /// computed once, this is much faster than executing a deep circuit for
+    /// many shots. The same restrictions on the operations in the circuit as
+    /// for `statevector()` apply, and the circuit can hold at most 64 qubits.
+    pub fn sample_statevector(&mut self, nr_samples: usize) -> crate::error::Result<Vec<u64>>
+    {
+        self.with_default_rng(|circuit, mut rng| {
+            circuit.sample_statevector_with_rng(nr_samples, &mut rng)
+        })
+    }
+
+    /// Sample from the state vector.
+    ///
+    /// Draw `nr_samples` samples from the final state of this circuit as in
+    /// `sample_statevector()`, using random number generator `rng`.
+    pub fn sample_statevector_with_rng<R: rand::Rng>(&self, nr_samples: usize, rng: &mut R)
+        -> crate::error::Result<Vec<u64>>
+    {
+        use rand::distributions::Distribution;
+
+        if self.nr_qbits > 64
+        {
+            return Err(crate::error::Error::InvalidNrBits(self.nr_qbits, 64,
+                String::from("state vector sampling")));
+        }
+
+        let probs: Vec<f64> = self.statevector()?.iter().map(|c| c.norm_sqr()).collect();
+        let dist = rand::distributions::WeightedIndex::new(&probs).map_err(|err| {
+                crate::error::Error::InternalError(err.to_string())
+            })?;
+
+        // The first qubit is the most significant bit in the state index
+        let shift = 64 - self.nr_qbits as u32;
+        Ok(dist.sample_iter(rng)
+            .take(nr_samples)
+            .map(|idx| (idx as u64).reverse_bits().checked_shr(shift).unwrap_or(0))
+            .collect())
+    }
+
+    /// Compute the fidelity with a pure state.
+    ///
+    /// Compute the fidelity `|⟨ψ|ϕ⟩|²` between the final state `ψ` of this
+    /// circuit, as computed by `statevector()`, and the normalized state
+    /// vector `target`. The same restrictions on the operations in the
+    /// circuit as for `statevector()` apply, so circuits with measurements
+    /// return an error. A `DimensionMismatch` error is returned when the
+    /// length of `target` does not match the number of qubits.
+    pub fn statevector_fidelity(&self, target: &crate::cmatrix::CVector)
+        -> crate::error::Result<f64>
+    {
+        crate::measures::statevector_fidelity(&self.statevector()?, target)
+    }
+
+    /// Compute the purity.
+    ///
+    /// Compute the purity `Tr(ρ²)` of the density matrix `ρ` of the final
+    /// state of this circuit, as computed by `density_matrix()`. The purity is
+    /// less than one when noise operations have mixed the state.
+    pub fn purity(&self) -> crate::error::Result<f64>
+    {
+        let rho = self.density_matrix()?;
+        Ok(crate::measures::purity(rho.matrix()))
+    }
+
+    /// Compute the fidelity with the output of another circuit.
+    ///
+    /// Compute the Uhlmann fidelity between the final states of this circuit
+    /// and of circuit `other`, both starting from |00...0⟩. The states are
+    /// computed as in `density_matrix()`, so this can be used to compare e.g.
+    /// an ideal circuit to one with noise operations. A `DimensionMismatch`
+    /// error is returned when the circuits act on different numbers of qubits.
+    pub fn fidelity_to(&self, other: &Circuit) -> crate::error::Result<f64>
+    {
+        if self.nr_qbits != other.nr_qbits
+        {
+            return Err(crate::error::Error::DimensionMismatch(1 << self.nr_qbits,
+                1 << other.nr_qbits));
+        }
+
+        let rho = self.density_matrix()?;
+        let sigma = other.density_matrix()?;
+        crate::measures::density_matrix_fidelity(rho.matrix(), sigma.matrix())
+    }
+
+    /// Compute an expectation value in the current state.
+    ///
+    /// Compute the expectation value ⟨ψ|H|ψ⟩ of observable `observable` in
+    /// the current quantum state |ψ⟩ of this circuit, averaged over all runs,
+    /// so the circuit should have been executed first. The observable can be
+    /// either a single Pauli string, or a weighted sum of Pauli strings in a
+    /// `SparsePauliOp`. The effect of any measurements in the circuit is
+    /// included, and noise is taken into account when the circuit was run in
+    /// density matrix mode. A `NotExecuted` error is returned when the circuit
+    /// has not been run yet.
+    pub fn expectation_value<O>(&self, observable: O) -> crate::error::Result<f64>
+    where O: Into<crate::observable::SparsePauliOp>
+    {
+        let observable = observable.into();
+        let q_state = self.q_state.as_ref().ok_or(crate::error::Error::NotExecuted)?;
+        let mut res = 0.0;
+        for (weight, pauli) in observable.pauli_strings(self.nr_qbits)?
+        {
+            let value = match *q_state
+                {
+                    QuStateRepr::Stabilizer(ref state) => state.expectation_value(&pauli)?,
+                    QuStateRepr::Vector(ref state) => state.expectation_value(&pauli)?,
+                    QuStateRepr::Density(ref state) => state.expectation_value(&pauli)?,
+                    QuStateRepr::MPS(ref state) => state.expectation_value(&pauli)?
+                };
+            res += weight * value;
+        }
+        Ok(res)
+    }
+
+    /// Compute the Clifford tableau of this circuit.
+    ///
+    /// Compute the tableau describing the Clifford operation implemented by
+    /// this circuit. Barriers are ignored, any other non-gate operation
+    /// results in an `OpNotImplemented` error, and non-Clifford gates in a
+    /// `NotAStabilizer` error.
+    pub fn clifford_tableau(&self) -> crate::error::Result<crate::stabilizer::CliffordTableau>
+    {
+        let mut tableau = crate::stabilizer::CliffordTableau::new(self.nr_qbits);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    tableau.apply_gate(gate.as_gate(), bits)?;
+                },
+                CircuitOp::Barrier(_) => {
+                    /* Nothing to be done */
+                },
+                _ => {
+                    return Err(crate::error::Error::OpNotImplemented(
+                        String::from("clifford_tableau"),
+                        String::from("non-unitary operation")
+                    ));
+                }
+            }
+        }
+
+        Ok(tableau)
+    }
+
+    /// Fold C<sub>X</sub> gates.
+    ///
+    /// Create a copy of this circuit, in which every `C`<sub>`X`</sub> gate is
+    /// followed by `nr_pairs` pairs of `C`<sub>`X`</sub> gates on the same
+    /// qubits. In the ideal case these pairs cancel, but any noise operations
+    /// on the same qubits directly following the original gate are repeated
+    /// after every inserted gate, scaling the noise of the two-qubit gates by
+    /// a factor `2nr_pairs+1`.
+    pub(crate) fn fold_cx(&self, nr_pairs: usize) -> Self
+    {
+        let mut res = Circuit::new(self.nr_qbits, self.nr_cbits);
+        let mut i = 0;
+        while i < self.ops.len()
+        {
+            res.ops.push(self.ops[i].clone());
+            let mut j = i + 1;
+            if let CircuitOp::Gate(ref gate, ref bits) = self.ops[i]
+            {
+                while let Some(CircuitOp::Noise(_, ref nbits)) = self.ops.get(j)
+                {
+                    if !nbits.iter().all(|b| bits.contains(b))
+                    {
+                        break;
+                    }
+                    res.ops.push(self.ops[j].clone());
+                    j += 1;
+                }
+
+                if gate.description() == "CX"
+                {
+                    for _ in 0..2*nr_pairs
+                    {
+                        res.ops.push(CircuitOp::Gate(gate.clone(), bits.clone()));
+                        res.ops.extend_from_slice(&self.ops[i+1..j]);
+                    }
+                }
+            }
+            i = j;
+        }
+
+        res
+    }
+
+    /// Create a copy of this circuit in which every Clifford gate operating
+    /// on multiple qubits, together with the noise operations directly
+    /// following it, is preceded by a random Pauli operator `P` on the qubits
+    /// of the gate, and followed by the Pauli operator `GPG`<sup>`†`</sup>
+    /// that undoes its effect. Other operations are copied as is.
+    pub(crate) fn pauli_dressed<R: rand::Rng>(&self, rng: &mut R) -> Self
+    {
+        const PAULIS: [crate::stabilizer::PauliOp; 4] = [
+            crate::stabilizer::PauliOp::I, crate::stabilizer::PauliOp::X,
+            crate::stabilizer::PauliOp::Y, crate::stabilizer::PauliOp::Z
+        ];
+
+        let mut res = Circuit::new(self.nr_qbits, self.nr_cbits);
+        let mut i = 0;
+        while i < self.ops.len()
+        {
+            let mut j = i + 1;
+            if let CircuitOp::Gate(ref gate, ref bits) = self.ops[i]
+            {
+                while let Some(CircuitOp::Noise(_, ref nbits)) = self.ops.get(j)
+                {
+                    if !nbits.iter().all(|b| bits.contains(b))
+                    {
+                        break;
+                    }
+                    j += 1;
+                }
+
+                let frame: Vec<crate::stabilizer::PauliOp> = bits.iter()
+                    .map(|_| PAULIS[rng.gen_range(0, 4)])
+                    .collect();
+                let mut correction = frame.clone();
+                if bits.len() > 1 && gate.conjugate(&mut correction).is_ok()
+                {
+                    res.add_pauli_ops(&frame, bits);
+                    res.ops.extend_from_slice(&self.ops[i..j]);
+                    res.add_pauli_ops(&correction, bits);
+                    i = j;
+                    continue;
+                }
+            }
+
+            res.ops.extend_from_slice(&self.ops[i..j]);
+            i = j;
+        }
+
+        res
+    }
+
+    /// Add the single-qubit Pauli gates in `ops`, operating on the
+    /// corresponding qubits in `bits`
+    fn add_pauli_ops(&mut self, ops: &[crate::stabilizer::PauliOp], bits: &[usize])
+    {
+        for (&op, &bit) in ops.iter().zip(bits)
+        {
+            let gate: Box<dyn CircuitGate> = match op
+                {
+                    crate::stabilizer::PauliOp::I => continue,
+                    crate::stabilizer::PauliOp::X => Box::new(crate::gates::X::new()),
+                    crate::stabilizer::PauliOp::Y => Box::new(crate::gates::Y::new()),
+                    crate::stabilizer::PauliOp::Z => Box::new(crate::gates::Z::new())
+                };
+            self.ops.push(CircuitOp::Gate(gate, vec![bit]));
+        }
+    }
+
+    /// Create a histogram of measurements.
+    ///
+    /// Create a histogram of the measured classical bits. The `n` bits in the
+    /// classical register are collected in a single `u64` integer value. The
+    /// first bit in the classical register (at index 0) corresponds to the
+    /// least significant bit in the key; the last classical bit (at index `n-1`)
+    /// to the most significant bit in the key. This function of course only works
+    /// when there are at most 64 bits in the register. If there are more, use
+    /// `histogram_string()`.
+    pub fn histogram(&self) -> crate::error::Result<crate::idhash::U64HashMap<usize>>
+    {
+        if let Some(ref c_state) = self.c_state
+        {
+            let mut res = crate::idhash::new_u64_hash_map();
+            for &key in c_state
+            {
+                let count = res.entry(key).or_insert(0);
+                *count += 1;
+            }
+            Ok(res)
+        }
+        else
+        {
+            Err(crate::error::Error::NotExecuted)
+        }
+    }
+
+    /// Create a histogram of measurements.
+    ///
+    /// Create a histogram of the measured classical bits. The `n` bits in the
+    /// classical register are collected in a single `usize` integer value,
+    /// which is used as an index in a vector. The bit order of the indices
+    /// is the same as in the `histogram()` function. The vector is of length
+    /// `2`<sub>`n`</sub>, so use this function only for reasonably small
+    /// numbers of `n`. For sparse collections, using `histogram()` or
+    /// `histogram_string` may be better.
+    pub fn histogram_vec(&self) -> crate::error::Result<Vec<usize>>
+    {
+        if let Some(ref c_state) = self.c_state
+        {
+            let mut res = vec![0; 1 << self.nr_cbits];
+            for &key in c_state
+            {
+                res[key as usize] += 1;
+            }
+            Ok(res)
+        }
+        else
+        {
+            Err(crate::error::Error::NotExecuted)
+        }
+    }
+
+    /// Create a histogram of measurements.
+    ///
+    /// Create a histogram of the measured classical bits. The `n` bits in the
+    /// classical register are collected in a string key, with the last character
+    /// in the key corresponding to the first bit (at index 0) in the classical
+    /// register and vice versa.
+    pub fn histogram_string(&self)
+        -> crate::error::Result<::std::collections::HashMap<String, usize>>
+    {
+        if let Some(ref c_state) = self.c_state
+        {
+            let mut res = ::std::collections::HashMap::new();
+            for &key in c_state
+            {
+                let skey = format!("{:0width$b}", key, width=self.nr_cbits);
+                let count = res.entry(skey).or_insert(0);
+                *count += 1;
+            }
+            Ok(res)
+        }
+        else
+        {
+            Err(crate::error::Error::NotExecuted)
+        }
+    }
+
+    /// The number of shots in the last execution of this circuit, as a
+    /// floating point number for normalizing histograms. When no shots were
+    /// made, one is returned, so that all probabilities are zero.
+    fn nr_shots_f64(&self) -> crate::error::Result<f64>
+    {
+        self.c_state.as_ref()
+            .map(|c_state| c_state.len().max(1) as f64)
+            .ok_or(crate::error::Error::NotExecuted)
+    }
+
+    /// Create a histogram of measurement probabilities.
+    ///
+    /// Create a histogram of the measured classical bits like `histogram()`,
+    /// but with the counts divided by the number of shots, such that the
+    /// values are the relative frequencies of the measurement results.
+    pub fn histogram_probabilities(&self) -> crate::error::Result<crate::idhash::U64HashMap<f64>>
+    {
+        let nr_shots = self.nr_shots_f64()?;
+        let mut res = crate::idhash::new_u64_hash_map();
+        for (key, count) in self.histogram()?
+        {
+            res.insert(key, count as f64 / nr_shots);
+        }
+        Ok(res)
+    }
+
+    /// Create a histogram of measurement probabilities.
+    ///
+    /// Create a histogram of the measured classical bits like
+    /// `histogram_vec()`, but with the counts divided by the number of shots,
+    /// such that the values are the relative frequencies of the measurement
+    /// results.
+    pub fn histogram_vec_probabilities(&self) -> crate::error::Result<Vec<f64>>
+    {
+        let nr_shots = self.nr_shots_f64()?;
+        Ok(self.histogram_vec()?.into_iter().map(|count| count as f64 / nr_shots).collect())
+    }
+
+    /// Create a histogram of measurement probabilities.
+    ///
+    /// Create a histogram of the measured classical bits like
+    /// `histogram_string()`, but with the counts divided by the number of
+    /// shots, such that the values are the relative frequencies of the
+    /// measurement results.
+    pub fn histogram_string_probabilities(&self)
+        -> crate::error::Result<::std::collections::HashMap<String, f64>>
+    {
+        let nr_shots = self.nr_shots_f64()?;
+        Ok(self.histogram_string()?.into_iter()
+            .map(|(key, count)| (key, count as f64 / nr_shots))
+            .collect())
+    }
+
+    fn is_full_register(&self, control: &[usize]) -> bool
+    {
+        let n = control.len();
+        if n != self.nr_cbits
+        {
+            return false;
+        }
+
+        let mut scontrol = vec![0; n];
+        scontrol.copy_from_slice(control);
+        scontrol.sort();
+        for i in 0..n
+        {
+            if scontrol[i] != i
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn check_open_qasm_condition_bits(&self, control: &[usize])
+        -> crate::error::ExportResult<()>
+    {
+        if !self.is_full_register(control)
+        {
+            Err(crate::error::ExportError::IncompleteConditionRegister)
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    /// Export to OpenQasm
+    ///
+    /// Export this circuit to a program in OpenQasm format. Gates that are
+    /// not part of the OpenQasm standard library, like single-qubit custom gates
+    /// or composite gates with a valid OpenQasm name, are defined once at the
+    /// top of the program, and referred to by name in the body. On a successful
+    /// conversion, the result is `Ok` with the program text. When the conversion
+    /// to OpenQasm fails, `Err` with an error message is returned.
+    pub fn open_qasm(&self) -> crate::error::Result<String>
+    {
+        let mut res = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+
+        let mut definitions = vec![];
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _)
+                | CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                    crate::export::add_open_qasm_definitions(&mut definitions,
+                        gate.open_qasm_definition());
+                },
+                _ => { /* No definition needed */ }
+            }
+        }
+        for definition in definitions
+        {
+            res += &definition;
+            res += "\n";
+        }
+
+        let mut qbit_names = vec![];
+        if self.nr_qbits > 0
+        {
+            res += &format!("qreg q[{}];\n", self.nr_qbits);
+            for i in 0..self.nr_qbits
+            {
+                qbit_names.push(format!("q[{}]", i));
+            }
+        }
+        let mut cbit_names = vec![];
+        if self.nr_cbits > 0
+        {
+            res += &format!("creg b[{}];\n", self.nr_cbits);
+            for i in 0..self.nr_cbits
+            {
+                cbit_names.push(format!("b[{}]", i));
+            }
+        }
+
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    let gate_qasm = gate.open_qasm(&qbit_names, bits)?;
+                    // Gates without an OpenQasm 2.0 representation, like a
+                    // global phase, return an empty instruction
+                    if !gate_qasm.is_empty()
+                    {
+                        res += &format!("{};\n", gate_qasm);
+                    }
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    if control.is_empty()
+                    {
+                        let gate_qasm = gate.open_qasm(&qbit_names, bits)?;
+                        if !gate_qasm.is_empty()
+                        {
+                            res += &format!("{};\n", gate_qasm);
+                        }
+                    }
+                    else
+                    {
+                        // We do require that the control bits span the entire classical
+                        // register, but not necessarily in the order 0..#bits.
+                        self.check_open_qasm_condition_bits(control)?;
+                        let mut starget = 0;
+                        for (tshift, sshift) in control.iter().enumerate()
+                        {
+                            starget |= ((target >> tshift) & 0x01) << sshift;
+                        }
+                        let condition = format!("b == {}", starget);
+                        let gate_qasm = gate.conditional_open_qasm(&condition, &qbit_names, bits)?;
+                        if !gate_qasm.is_empty()
+                        {
+                            res += &format!("{};\n", gate_qasm);
+                        }
+                    }
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm(&qbit_names, &[qbit])?);
+                        },
+                        Basis::Y => {
+                            res += &format!("{};\n",
+                                crate::gates::Sdg::new().open_qasm(&qbit_names, &[qbit])?);
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm(&qbit_names, &[qbit])?);
+                        }
+                        _ => {}
+                    }
+                    res += &format!("measure {} -> {};\n", qbit_names[qbit], cbit_names[cbit]);
+                }
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            let names = [String::from("q")];
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm(&names, &[0])?);
+                        },
+                        Basis::Y => {
+                            let names = [String::from("q")];
+                            res += &format!("{};\n",
+                                crate::gates::Sdg::new().open_qasm(&names, &[0])?);
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm(&names, &[0])?);
+                        }
+                        _ => {}
+                    }
+
+                    if cbits.len() == self.nr_cbits
+                        && cbits.iter().enumerate().all(|(i, &b)| i==b)
+                    {
+                        res += &format!("measure q -> b;\n");
+                    }
+                    else
+                    {
+                        for (qbit, &cbit) in cbits.iter().enumerate()
+                        {
+                            res += &format!("measure {} -> {};\n", qbit_names[qbit],
+                                cbit_names[cbit]);
+                        }
+                    }
+                },
+                CircuitOp::Peek(_, _, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("OpenQasm")
+                    ));
+                },
+                CircuitOp::PeekAll(_, _) | CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("OpenQasm")
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    res += &format!("reset {};\n", qbit_names[qbit]);
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    res += &format!("reset {};\n", qbit_names[qbit]);
+                    if value
+                    {
+                        res += &format!("x {};\n", qbit_names[qbit]);
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    res += "reset q;\n";
+                },
+                CircuitOp::Barrier(ref qbits) => {
+                    if qbits.len() == self.nr_qbits
+                        && qbits.iter().enumerate().all(|(i, &b)| i==b)
+                    {
+                        res += "barrier q;\n";
+                    }
+                    else
+                    {
+                        res += &format!("barrier {};\n",
+                            qbits.iter()
+                            .map(|&b| qbit_names[b].as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", "));
+                    }
+                },
+                CircuitOp::Noise(_, _) => {
+                    /* Noise is not exported */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("OpenQasm",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Build the OpenQasm 3.0 condition that the classical bits `control`
+    /// hold the value `target`.
+    fn open_qasm3_condition(&self, control: &[usize], target: u64) -> String
+    {
+        if self.is_full_register(control)
+        {
+            let mut starget = 0;
+            for (tshift, sshift) in control.iter().enumerate()
+            {
+                starget |= ((target >> tshift) & 0x01) << sshift;
+            }
+            format!("c == {}", starget)
+        }
+        else
+        {
+            control.iter().enumerate()
+                .map(|(tshift, &cbit)| {
+                    if (target >> tshift) & 0x01 == 1
+                    {
+                        format!("c[{}]", cbit)
+                    }
+                    else
+                    {
+                        format!("!c[{}]", cbit)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" && ")
+        }
+    }
+
+    /// Export to OpenQasm 3.0
+    ///
+    /// Export this circuit to a program in OpenQasm 3.0 format. Qubits are
+    /// declared in register `q`, classical bits in register `c`. Gate
+    /// definitions are the same as for OpenQasm 2.0 export. Conditional gates
+    /// may depend on any subset of the classical bits, not just the full
+    /// register. On a successful conversion, the result is `Ok` with the program
+    /// text. When the conversion to OpenQasm fails, `Err` with an error message
+    /// is returned.
+    pub fn open_qasm3(&self) -> crate::error::Result<String>
+    {
+        let mut res = String::from("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+
+        let mut definitions = vec![];
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _)
+                | CircuitOp::ConditionalGate(_, _, ref gate, _) => {
+                    crate::export::add_open_qasm_definitions(&mut definitions,
+                        gate.open_qasm_definition());
+                },
+                _ => { /* No definition needed */ }
+            }
+        }
+        for definition in definitions
+        {
+            res += &definition;
+            res += "\n";
+        }
+
+        let mut qbit_names = vec![];
+        if self.nr_qbits > 0
+        {
+            res += &format!("qubit[{}] q;\n", self.nr_qbits);
+            for i in 0..self.nr_qbits
+            {
+                qbit_names.push(format!("q[{}]", i));
+            }
+        }
+        let mut cbit_names = vec![];
+        if self.nr_cbits > 0
+        {
+            res += &format!("bit[{}] c;\n", self.nr_cbits);
+            for i in 0..self.nr_cbits
+            {
+                cbit_names.push(format!("c[{}]", i));
+            }
+        }
+
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    res += &format!("{};\n", gate.open_qasm3(&qbit_names, bits)?);
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    let gate_qasm = gate.open_qasm3(&qbit_names, bits)?;
+                    if control.is_empty()
+                    {
+                        res += &format!("{};\n", gate_qasm);
+                    }
+                    else
+                    {
+                        let condition = self.open_qasm3_condition(control, target);
+                        res += &format!("if ({}) {{ {}; }}\n", condition, gate_qasm);
+                    }
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm3(&qbit_names, &[qbit])?);
+                        },
+                        Basis::Y => {
+                            res += &format!("{};\n",
+                                crate::gates::Sdg::new().open_qasm3(&qbit_names, &[qbit])?);
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm3(&qbit_names, &[qbit])?);
+                        }
+                        _ => {}
+                    }
+                    res += &format!("{} = measure {};\n", cbit_names[cbit], qbit_names[qbit]);
+                }
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            let names = [String::from("q")];
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm3(&names, &[0])?);
+                        },
+                        Basis::Y => {
+                            let names = [String::from("q")];
+                            res += &format!("{};\n",
+                                crate::gates::Sdg::new().open_qasm3(&names, &[0])?);
+                            res += &format!("{};\n",
+                                crate::gates::H::new().open_qasm3(&names, &[0])?);
+                        }
+                        _ => {}
+                    }
+
+                    if cbits.len() == self.nr_cbits
+                        && cbits.iter().enumerate().all(|(i, &b)| i==b)
+                    {
+                        res += "c = measure q;\n";
+                    }
+                    else
+                    {
+                        for (qbit, &cbit) in cbits.iter().enumerate()
+                        {
+                            res += &format!("{} = measure {};\n", cbit_names[cbit],
+                                qbit_names[qbit]);
+                        }
+                    }
+                },
+                CircuitOp::Peek(_, _, _)
+                | CircuitOp::PeekAll(_, _)
+                | CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("OpenQasm")
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    res += &format!("reset {};\n", qbit_names[qbit]);
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    res += &format!("reset {};\n", qbit_names[qbit]);
+                    if value
+                    {
+                        res += &format!("x {};\n", qbit_names[qbit]);
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    res += "reset q;\n";
+                },
+                CircuitOp::Barrier(ref qbits) => {
+                    if qbits.len() == self.nr_qbits
+                        && qbits.iter().enumerate().all(|(i, &b)| i==b)
+                    {
+                        res += "barrier q;\n";
+                    }
+                    else
+                    {
+                        res += &format!("barrier {};\n",
+                            qbits.iter()
+                            .map(|&b| qbit_names[b].as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", "));
+                    }
+                },
+                CircuitOp::Noise(_, _) => {
+                    /* Noise is not exported */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("OpenQasm",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Add the Q# statements in `stmts`, separated by `";\n"`, to the
+    /// operation body `res`.
+    fn add_q_sharp_statements(res: &mut String, stmts: &str)
+    {
+        for stmt in stmts.split(";\n")
+        {
+            *res += &format!("    {};\n", stmt);
+        }
+    }
+
+    /// Export to Q#
+    ///
+    /// Export this circuit to a Q# operation `CircuitOp`, which takes the
+    /// array of qubits to operate on, and returns the array of classical bits.
+    /// Gates are mapped to the operations in the Q# standard library, using
+    /// the `Controlled` functor for controlled gates. Reference parameters are
+    /// exported with their current value, barriers and noise are not exported.
+    /// On success, returns the Q# code. An error is returned when the circuit
+    /// contains a gate or operation that cannot be represented in Q#, like
+    /// custom gates or peek operations.
+    pub fn to_qsharp(&self) -> crate::error::Result<String>
+    {
+        use crate::export::QSharp;
+
+        let qbit_names: Vec<String> = (0..self.nr_qbits).map(|i| format!("q[{}]", i)).collect();
+
+        let mut res = String::from("operation CircuitOp(q : Qubit[]) : Result[] {\n");
+        res += &format!("    mutable b = [Zero, size = {}];\n", self.nr_cbits);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    Self::add_q_sharp_statements(&mut res, &gate.q_sharp(&qbit_names, bits)?);
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    let stmts = gate.q_sharp(&qbit_names, bits)?;
+                    if control.is_empty()
+                    {
+                        Self::add_q_sharp_statements(&mut res, &stmts);
+                    }
+                    else
+                    {
+                        let condition: Vec<String> = control.iter().enumerate()
+                            .map(|(i, &cbit)| {
+                                let value = if (target >> i) & 1 == 1 { "One" } else { "Zero" };
+                                format!("b[{}] == {}", cbit, value)
+                            })
+                            .collect();
+                        res += &format!("    if ({}) {{ {}; }}\n", condition.join(" and "),
+                            stmts.replace(";\n", "; "));
+                    }
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            res += &format!("    H({});\n", qbit_names[qbit]);
+                        },
+                        Basis::Y => {
+                            res += &format!("    Adjoint S({});\n", qbit_names[qbit]);
+                            res += &format!("    H({});\n", qbit_names[qbit]);
+                        },
+                        _ => {}
+                    }
+                    res += &format!("    set b w/= {} <- M({});\n", cbit, qbit_names[qbit]);
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    for (qbit, &cbit) in cbits.iter().enumerate()
+                    {
+                        match basis
+                        {
+                            Basis::X => {
+                                res += &format!("    H({});\n", qbit_names[qbit]);
+                            },
+                            Basis::Y => {
+                                res += &format!("    Adjoint S({});\n", qbit_names[qbit]);
+                                res += &format!("    H({});\n", qbit_names[qbit]);
+                            },
+                            _ => {}
+                        }
+                        res += &format!("    set b w/= {} <- M({});\n", cbit, qbit_names[qbit]);
+                    }
+                },
+                CircuitOp::Peek(_, _, _) | CircuitOp::PeekAll(_, _)
+                | CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("Q#")
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    res += &format!("    Reset({});\n", qbit_names[qbit]);
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    res += &format!("    Reset({});\n", qbit_names[qbit]);
+                    if value
+                    {
+                        res += &format!("    X({});\n", qbit_names[qbit]);
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    res += "    ResetAll(q);\n";
+                },
+                CircuitOp::Barrier(_) | CircuitOp::Noise(_, _) => {
+                    /* Barriers and noise are not exported */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("Q#",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+        res += "    return b;\n}\n";
+
+        Ok(res)
+    }
+
+    fn check_c_qasm_measurement(qbit: usize, cbit: usize) -> crate::error::ExportResult<()>
+    {
+        if qbit != cbit
+        {
+            Err(crate::error::ExportError::NoClassicalRegister)
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    /// Export to OpenQasm with decomposed custom gates
+    ///
+    /// Export this circuit to a program in OpenQasm format, like `open_qasm()`,
+    /// but with custom gates replaced by equivalent sequences of standard
+    /// gates. Single-qubit custom gates are written as a `U`<sub>`3`</sub> gate
+    /// using `euler_decompose()`, two-qubit custom gates are decomposed into
+    /// single-qubit gates and `CX` gates using `kak_decompose()`. Both
+    /// decompositions are exact up to a global phase. Custom gates on more than
+    /// two qubits cannot be decomposed, and are exported as a comment listing
+    /// their matrix. In that case, an `UndecomposedGate` error is returned,
+    /// which holds the program text.
+    pub fn open_qasm_with_decomposition(&self) -> crate::error::Result<String>
+    {
+        use crate::instruction::Instruction;
+
+        let mut ops = vec![];
+        let mut undecomposed = vec![];
+        for op in self.ops.iter()
+        {
+            let (gate, bits, condition) = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => (gate, bits, None),
+                    CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                        (gate, bits, Some((control, target)))
+                    },
+                    _ => {
+                        ops.push(op.clone());
+                        continue;
+                    }
+                };
+
+            let instrs = gate.instructions(bits);
+            if !instrs.iter().any(|instr| matches!(instr, Instruction::Custom(_, _)))
+            {
+                ops.push(op.clone());
+                continue;
+            }
+
+            let mut new_ops = Some(vec![]);
+            for instr in instrs
+            {
+                let expanded = match instr
+                    {
+                        Instruction::Custom(ref rows, ref cbits) => {
+                            let n = rows.len();
+                            let m = crate::cmatrix::CMatrix::from_shape_fn((n, n),
+                                |(i, j)| rows[i][j]);
+                            match crate::decompose::decompose_instructions(&m, cbits)
+                            {
+                                Some(decomposed) => self.instruction_ops(&decomposed),
+                                None => {
+                                    undecomposed.push(gate.description().to_owned());
+                                    let comment = crate::decompose::UndecomposedGate::new(
+                                        gate.description(), m);
+                                    Some(vec![CircuitOp::Gate(Box::new(comment), cbits.clone())])
+                                }
+                            }
+                        },
+                        instr => self.instruction_ops(&[instr])
+                    };
+                new_ops = new_ops.and_then(|mut v| { v.extend(expanded?); Some(v) });
+            }
+
+            match (new_ops, condition)
+            {
+                (Some(new_ops), None) => { ops.extend(new_ops); },
+                (Some(new_ops), Some((control, target))) => {
+                    for new_op in new_ops
+                    {
+                        if let CircuitOp::Gate(gate, bits) = new_op
+                        {
+                            ops.push(CircuitOp::ConditionalGate(control.clone(), target, gate, bits));
+                        }
+                    }
+                },
+                (None, _) => { ops.push(op.clone()); }
+            }
+        }
+
+        let qasm = self.with_ops(ops).open_qasm()?;
+        match undecomposed.into_iter().next()
+        {
+            Some(desc) => {
+                Err(crate::error::Error::from(crate::error::ExportError::UndecomposedGate(desc, qasm)))
+            },
+            None => Ok(qasm)
+        }
+    }
+
+    /// Export to c-Qasm
+    ///
+    /// Export this circuit to a program in c-Qasm format. On a successful
+    /// conversion, the result is `Ok` with the program text. When the conversion
+    /// to c-Qasm fails, `Err` with an error message is returned.
+    pub fn c_qasm(&self) -> crate::error::Result<String>
+    {
+        let mut res = String::from("version 1.0\n");
+
+        let mut qbit_names = vec![];
+        let mut cbit_names = vec![];
+        if self.nr_qbits > 0
+        {
+            res += &format!("qubits {}\n", self.nr_qbits);
+            for i in 0..self.nr_qbits
+            {
+                qbit_names.push(format!("q[{}]", i));
+                cbit_names.push(format!("b[{}]", i));
+            }
+        }
+
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    res += &format!("{}\n", gate.c_qasm(&qbit_names, bits)?);
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    if control.is_empty()
+                    {
+                        res += &format!("{}\n", gate.c_qasm(&qbit_names, bits)?);
+                    }
+                    else
+                    {
+                        let mut conditions = vec![];
+                        for (shift, &idx) in control.iter().enumerate()
+                        {
+                            if target & (1 << shift) == 0
+                            {
+                                res += &format!("not {}\n", cbit_names[idx]);
+                            }
+                            conditions.push(cbit_names[idx].as_str());
+                        }
+                        let condition = conditions.join(", ");
+                        let gate_qasm = gate.conditional_c_qasm(&condition,
+                            &qbit_names, bits)?;
+                        res += &format!("{}\n", gate_qasm);
+                        for (shift, &idx) in control.iter().enumerate()
+                        {
+                            if target & (1 << shift) == 0
+                            {
+                                res += &format!("not {}\n", cbit_names[idx]);
+                            }
+                        }
+                    }
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    Self::check_c_qasm_measurement(qbit, cbit)?;
+                    let op = match basis
+                    {
+                        Basis::X => "measure_x",
+                        Basis::Y => "measure_y",
+                        _        => "measure"
+                    };
+                    res += &format!("{} q[{}]\n", op, qbit);
+                }
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    for (qbit, &cbit) in cbits.iter().enumerate()
+                    {
+                        Self::check_c_qasm_measurement(qbit, cbit)?;
+                    }
+                    match basis
+                    {
+                        Basis::X => {
+                            for bit in 0..self.nr_qbits
+                            {
+                                res += &format!("{}\n",
+                                    crate::gates::H::new().c_qasm(&qbit_names, &[bit])?);
+                            }
+                        },
+                        Basis::Y => {
+                            for bit in 0..self.nr_qbits
+                            {
+                                res += &format!("{}\n",
+                                    crate::gates::Sdg::new().c_qasm(&qbit_names, &[bit])?);
+                                res += &format!("{}\n",
+                                    crate::gates::H::new().c_qasm(&qbit_names, &[bit])?);
+                            }
+                        },
+                        _ => {
+                            /* do nothing */
+                        }
+                    }
+                    res += &format!("measure_all\n");
+                },
+                CircuitOp::Peek(_, _, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("c-Qasm")
+                    ));
+                },
+                CircuitOp::PeekAll(_, _) | CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("c-Qasm")
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    res += &format!("prep_z {}\n", qbit_names[qbit]);
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    res += &format!("prep_z {}\n", qbit_names[qbit]);
+                    if value
+                    {
+                        res += &format!("x {}\n", qbit_names[qbit]);
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    for i in 0..self.nr_qbits
+                    {
+                        res += &format!("prep_z {}\n", qbit_names[i]);
+                    }
+                },
+                CircuitOp::Barrier(_) | CircuitOp::Noise(_, _) => {
+                    /* Not available */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("c-Qasm",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Return the JSON representation of a TKET command performing operation
+    /// `op`, conditioned on classical bits `control` having value `target`. If
+    /// `control` is empty, the operation is unconditional.
+    fn tket_command(op: &crate::export::TketOp, control: &[usize], target: u64) -> String
+    {
+        let mut args: Vec<String> = control.iter()
+            .map(|&b| format!("[\"c\", [{}]]", b))
+            .collect();
+        args.extend(op.bits.iter().map(|&b| format!("[\"q\", [{}]]", b)));
+
+        let op_json = if control.is_empty()
+            {
+                op.op_json()
+            }
+            else
+            {
+                format!("{{\"type\": \"Conditional\", \"conditional\": {{\"op\": {}, \"width\": {}, \"value\": {}}}}}",
+                    op.op_json(), control.len(), target)
+            };
+
+        format!("{{\"op\": {}, \"args\": [{}]}}", op_json, args.join(", "))
+    }
+
+    /// Return the JSON representation of a TKET measurement of qubit `qbit`
+    /// into classical bit `cbit` in basis `basis`. For measurements in the `X`
+    /// or `Y` basis, the necessary basis change is prepended.
+    fn tket_measurement(qbit: usize, cbit: usize, basis: Basis) -> Vec<String>
+    {
+        let mut res = vec![];
+        match basis
+        {
+            Basis::X => {
+                res.push(Self::tket_command(&crate::export::TketOp::new("H", vec![], &[qbit]), &[], 0));
+            },
+            Basis::Y => {
+                res.push(Self::tket_command(&crate::export::TketOp::new("Sdg", vec![], &[qbit]), &[], 0));
+                res.push(Self::tket_command(&crate::export::TketOp::new("H", vec![], &[qbit]), &[], 0));
+            },
+            _ => {}
+        }
+        res.push(format!("{{\"op\": {{\"type\": \"Measure\"}}, \"args\": [[\"q\", [{}]], [\"c\", [{}]]]}}",
+            qbit, cbit));
+        res
+    }
+
+    /// Export to TKET
+    ///
+    /// Export this circuit to TKET's JSON format, as can be read by
+    /// `pytket.Circuit.from_dict()` (after parsing the JSON). Qubits are
+    /// placed in a register named `q`, classical bits in a register named `c`.
+    /// On a successful conversion, the result is `Ok` with the JSON text. When
+    /// the conversion fails, `Err` with an error message is returned.
+    pub fn tket_json(&self) -> crate::error::Result<String>
+    {
+        let mut commands = vec![];
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    for top in gate.tket_ops(bits)?
+                    {
+                        commands.push(Self::tket_command(&top, &[], 0));
+                    }
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    for top in gate.tket_ops(bits)?
+                    {
+                        commands.push(Self::tket_command(&top, control, target));
+                    }
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    commands.extend(Self::tket_measurement(qbit, cbit, basis));
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    for (qbit, &cbit) in cbits.iter().enumerate()
+                    {
+                        commands.extend(Self::tket_measurement(qbit, cbit, basis));
+                    }
+                },
+                CircuitOp::Peek(_, _, _) | CircuitOp::PeekAll(_, _) | CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::ExportPeekInvalid("TKET")
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    let reset = crate::export::TketOp::new("Reset", vec![], &[qbit]);
+                    commands.push(Self::tket_command(&reset, &[], 0));
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    let reset = crate::export::TketOp::new("Reset", vec![], &[qbit]);
+                    commands.push(Self::tket_command(&reset, &[], 0));
+                    if value
+                    {
+                        let x = crate::export::TketOp::new("X", vec![], &[qbit]);
+                        commands.push(Self::tket_command(&x, &[], 0));
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    for qbit in 0..self.nr_qbits
+                    {
+                        let reset = crate::export::TketOp::new("Reset", vec![], &[qbit]);
+                        commands.push(Self::tket_command(&reset, &[], 0));
+                    }
+                },
+                CircuitOp::Barrier(ref qbits) => {
+                    let signature = vec!["\"Q\""; qbits.len()].join(", ");
+                    let args: Vec<String> = qbits.iter()
+                        .map(|&b| format!("[\"q\", [{}]]", b))
+                        .collect();
+                    commands.push(format!("{{\"op\": {{\"type\": \"Barrier\", \"signature\": [{}]}}, \"args\": [{}]}}",
+                        signature, args.join(", ")));
+                },
+                CircuitOp::Noise(_, _) => {
+                    /* Noise is not exported */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("TKET",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+
+        let qbits: Vec<String> = (0..self.nr_qbits)
+            .map(|i| format!("[\"q\", [{}]]", i))
+            .collect();
+        let cbits: Vec<String> = (0..self.nr_cbits)
+            .map(|i| format!("[\"c\", [{}]]", i))
+            .collect();
+        let permutation: Vec<String> = qbits.iter()
+            .map(|q| format!("[{}, {}]", q, q))
+            .collect();
+
+        let mut res = String::from("{\n");
+        res += "  \"phase\": \"0.0\",\n";
+        res += &format!("  \"qubits\": [{}],\n", qbits.join(", "));
+        res += &format!("  \"bits\": [{}],\n", cbits.join(", "));
+        if commands.is_empty()
+        {
+            res += "  \"commands\": [],\n";
+        }
+        else
+        {
+            res += &format!("  \"commands\": [\n    {}\n  ],\n", commands.join(",\n    "));
+        }
+        res += &format!("  \"implicit_permutation\": [{}]\n", permutation.join(", "));
+        res += "}\n";
+
+        Ok(res)
+    }
+
+    /// Export to LaTeX
+    ///
+    /// Export this circuit to LaTeX using the qcircuit package. On a successful
+    /// conversion, the result is `Ok` with the LaTeX code. When the conversion
+    /// to LaTeX fails, `Err` with an error message is returned.
+    pub fn latex(&self) -> crate::error::Result<String>
+    {
+        let mut state = crate::export::LatexExportState::new(self.nr_qbits, self.nr_cbits);
+        for op in self.ops.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    gate.latex(bits, &mut state)?;
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    state.start_range_op(bits, Some(control))?;
+                    let controlled = state.set_controlled(true);
+                    gate.latex(bits, &mut state)?;
+                    state.set_controlled(controlled);
+                    state.set_condition(control, target, bits)?;
+                    state.end_range_op();
+                },
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    let basis_lbl = match basis
+                    {
+                        Basis::X => Some("X"),
+                        Basis::Y => Some("Y"),
+                        _        => None
+                    };
+                    state.set_measurement(qbit, cbit, basis_lbl)?;
+                }
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    let basis_lbl = match basis
+                    {
+                        Basis::X => Some("X"),
+                        Basis::Y => Some("Y"),
+                        _        => None
+                    };
+                    for (qbit, &cbit) in cbits.iter().enumerate()
+                    {
+                        state.set_measurement(qbit, cbit, basis_lbl)?;
+                    }
+                },
+                CircuitOp::Peek(_, _, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("LaTeX",
+                            String::from("peek")
+                        )
+                    ));
+                },
+                CircuitOp::PeekAll(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("LaTeX",
+                            String::from("peek all")
+                        )
+                    ));
+                },
+                CircuitOp::PeekPartial(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("LaTeX",
+                            String::from("partial peek")
+                        )
+                    ));
+                },
+                CircuitOp::Reset(qbit) => {
+                    state.set_reset(qbit)?;
+                },
+                CircuitOp::ResetTo(qbit, value) => {
+                    state.set_reset(qbit)?;
+                    if value
+                    {
+                        crate::export::Latex::latex(&crate::gates::X::new(), &[qbit], &mut state)?;
+                    }
+                },
+                CircuitOp::ResetAll => {
+                    state.start_range_op(&[0, self.nr_qbits-1], None)?;
+                    for qbit in 0..self.nr_qbits
+                    {
+                        state.set_reset(qbit)?;
+                    }
+                    state.end_range_op();
+                },
+                CircuitOp::Barrier(ref qbits) => {
+                    state.set_barrier(qbits)?;
+                },
+                CircuitOp::Noise(_, _) => {
+                    /* Noise is not exported */
+                },
+                CircuitOp::ExpectationMeasure(_, _) => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("LaTeX",
+                            String::from("expectation measurement")
+                        )
+                    ));
+                }
+            }
+        }
+
+        Ok(state.code())
+    }
+
+    /// Draw this circuit as ASCII art
+    ///
+    /// Return a fixed-width text representation of this circuit, suitable
+    /// for printing in a terminal. Each qubit is drawn as a horizontal wire
+    /// of `-` characters, with operations placed in columns such that an
+    /// operation is in the first column after the last operation on any of
+    /// the qubits it spans. Single-qubit gates are drawn as `[G]`, with `G`
+    /// the gate description. The parts of a multi-qubit gate are labeled
+    /// with the index of the qubit in the gate, e.g. `[CX:0]` and
+    /// `[CX:1]`, and connected by a `|` line. Measurements are drawn as
+    /// `[M]`, peeks as `[P]`, resets as `[R]`, and barriers as `|`.
+    /// Measurements and peeks in a basis other than `Z` get the basis
+    /// appended to the label, conditional gates are marked with a `?`.
+    /// Noise operations are not drawn. All columns have the width of the
+    /// widest label.
+    pub fn draw_ascii(&self) -> String
+    {
+        struct Column
+        {
+            /// The label of the operation on each qubit, if any
+            labels: Vec<Option<String>>,
+            /// Ranges of qubits connected by a vertical line
+            lines: Vec<(usize, usize)>
+        }
+
+        let mut columns: Vec<Column> = vec![];
+        let mut next_col = vec![0; self.nr_qbits];
+        let all_bits: Vec<usize> = (0..self.nr_qbits).collect();
+        let basis_sfx = |basis| match basis
+            {
+                Basis::X => "X",
+                Basis::Y => "Y",
+                Basis::Z => ""
+            };
+        for op in self.ops.iter()
+        {
+            let (bits, labels): (&[usize], Vec<String>) = match *op
+                {
+                    CircuitOp::Gate(ref gate, ref bits) => {
+                        (bits, Self::ascii_gate_labels(gate.description(), bits.len(), ""))
+                    },
+                    CircuitOp::ConditionalGate(_, _, ref gate, ref bits) => {
+                        (bits, Self::ascii_gate_labels(gate.description(), bits.len(), "?"))
+                    },
+                    CircuitOp::Reset(ref qbit) => {
+                        (::std::slice::from_ref(qbit), vec![String::from("[R]")])
+                    },
+                    CircuitOp::ResetTo(ref qbit, value) => {
+                        (::std::slice::from_ref(qbit), vec![format!("[R{}]", value as u8)])
+                    },
+                    CircuitOp::ResetAll => {
+                        (all_bits.as_slice(), vec![String::from("[R]"); self.nr_qbits])
+                    },
+                    CircuitOp::Measure(ref qbit, _, basis) => {
+                        (::std::slice::from_ref(qbit), vec![format!("[M{}]", basis_sfx(basis))])
+                    },
+                    CircuitOp::MeasureAll(_, basis) => {
+                        (all_bits.as_slice(), vec![format!("[M{}]", basis_sfx(basis)); self.nr_qbits])
+                    },
+                    CircuitOp::Peek(ref qbit, _, basis) => {
+                        (::std::slice::from_ref(qbit), vec![format!("[P{}]", basis_sfx(basis))])
+                    },
+                    CircuitOp::PeekAll(_, basis) => {
+                        (all_bits.as_slice(), vec![format!("[P{}]", basis_sfx(basis)); self.nr_qbits])
+                    },
+                    CircuitOp::PeekPartial(ref qbits, _) => {
+                        (qbits, vec![String::from("[P]"); qbits.len()])
+                    },
+                    CircuitOp::ExpectationMeasure(ref pauli, _) => {
+                        let labels = pauli.ops().iter().map(|op| format!("[<{}>]", op)).collect();
+                        (all_bits.as_slice(), labels)
+                    },
+                    CircuitOp::Barrier(ref bits) => {
+                        (bits, vec![String::from("|"); bits.len()])
+                    },
+                    CircuitOp::Noise(_, _) => continue
+                };
+
+            if bits.is_empty()
+            {
+                continue;
+            }
+
+            // A barrier is only drawn on the qubits it acts on, for other
+            // operations the line connecting the parts blocks all qubits
+            // in between.
+            let lo = *bits.iter().min().unwrap();
+            let hi = *bits.iter().max().unwrap();
+            let blocked: Vec<usize> = if let CircuitOp::Barrier(_) = *op
+                {
+                    bits.to_vec()
+                }
+                else
+                {
+                    (lo..hi+1).collect()
+                };
+            let col = blocked.iter().map(|&b| next_col[b]).max().unwrap_or(0);
+            for &bit in blocked.iter()
+            {
+                next_col[bit] = col + 1;
+            }
+
+            if col == columns.len()
+            {
+                columns.push(Column { labels: vec![None; self.nr_qbits], lines: vec![] });
+            }
+            let column = &mut columns[col];
+            for (&bit, label) in bits.iter().zip(labels)
+            {
+                column.labels[bit] = Some(label);
+            }
+            if let CircuitOp::Barrier(_) = *op
+            {
+                // Connect adjacent qubits in the barrier only
+                for &bit in bits.iter()
+                {
+                    if bits.contains(&(bit + 1))
+                    {
+                        column.lines.push((bit, bit + 1));
+                    }
+                }
+            }
+            else if hi > lo
+            {
+                column.lines.push((lo, hi));
+            }
+        }
+
+        let width = columns.iter()
+            .flat_map(|column| column.labels.iter())
+            .filter_map(|label| label.as_ref().map(|l| l.chars().count()))
+            .max()
+            .unwrap_or(1);
+        let center = (width - 1) / 2;
+        let prefix_width = format!("q{}: ", self.nr_qbits.saturating_sub(1)).len();
+
+        let mut res = String::new();
+        for bit in 0..self.nr_qbits
+        {
+            // The wire of the qubit itself
+            res += &format!("{:<1$}", format!("q{}: ", bit), prefix_width);
+            for column in columns.iter()
+            {
+                res.push('-');
+                match column.labels[bit]
+                {
+                    Some(ref label) => {
+                        let len = label.chars().count();
+                        let left = (width - len) / 2;
+                        res += &"-".repeat(left);
+                        res += label;
+                        res += &"-".repeat(width - len - left);
+                    },
+                    None if column.lines.iter().any(|&(lo, hi)| lo < bit && bit < hi) => {
+                        res += &"-".repeat(center);
+                        res.push('|');
+                        res += &"-".repeat(width - center - 1);
+                    },
+                    None => {
+                        res += &"-".repeat(width);
+                    }
+                }
+            }
+            res += "-\n";
+
+            // The space between this qubit and the next
+            if bit + 1 < self.nr_qbits
+            {
+                let mut line = " ".repeat(prefix_width);
+                for column in columns.iter()
+                {
+                    line.push(' ');
+                    if column.lines.iter().any(|&(lo, hi)| lo <= bit && bit < hi)
+                    {
+                        line += &" ".repeat(center);
+                        line.push('|');
+                        line += &" ".repeat(width - center - 1);
+                    }
+                    else
+                    {
+                        line += &" ".repeat(width);
+                    }
+                }
+                res += line.trim_end();
+                res.push('\n');
+            }
+        }
+
+        res
+    }
+
+    /// Labels for the parts of a gate with description `desc`, operating
+    /// on `nr_bits` qubits, in an ASCII drawing of the circuit. The suffix
+    /// `sfx` is appended to the description.
+    fn ascii_gate_labels(desc: &str, nr_bits: usize, sfx: &str) -> Vec<String>
+    {
+        if nr_bits == 1
+        {
+            vec![format!("[{}{}]", desc, sfx)]
+        }
+        else
+        {
+            (0..nr_bits).map(|i| format!("[{}{}:{}]", desc, sfx, i)).collect()
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! circuit_method_check
+{
+    ( add_conditional_gate $res:expr ) => { $res? };
+    ( add_gate $res:expr ) => { $res? };
+    ( add_noise $res:expr ) => { $res? };
+    ( apply_all $res:expr ) => { $res? };
+    ( apply_range $res:expr ) => { $res? };
+    ( barrier $res:expr ) => { $res? };
+    ( cx $res:expr ) => { $res? };
+    ( echo_sequence $res:expr ) => { $res? };
+    ( h $res:expr ) => { $res? };
+    ( measure $res:expr ) => { $res? };
+    ( measure_basis $res:expr ) => { $res? };
+    ( measure_all $res:expr ) => { $res? };
+    ( measure_all_basis $res:expr ) => { $res? };
+    ( measure_expectation $res:expr ) => { $res? };
+    ( measure_x $res:expr ) => { $res? };
+    ( measure_y $res:expr ) => { $res? };
+    ( measure_z $res:expr ) => { $res? };
+    ( peek $res:expr ) => { $res? };
+    ( peek_x $res:expr ) => { $res? };
+    ( peek_y $res:expr ) => { $res? };
+    ( peek_z $res:expr ) => { $res? };
+    ( peek_all $res:expr ) => { $res? };
+    ( peek_all_basis $res:expr ) => { $res? };
+    ( peek_partial $res:expr ) => { $res? };
+    ( reset $res:expr ) => { $res? };
+    ( reset_to $res:expr ) => { $res? };
+    ( rx $res:expr ) => { $res? };
+    ( ry $res:expr ) => { $res? };
+    ( rz $res:expr ) => { $res? };
+    ( s $res:expr ) => { $res? };
+    ( sdg $res:expr ) => { $res? };
+    ( u1 $res:expr ) => { $res? };
+    ( u2 $res:expr ) => { $res? };
+    ( u3 $res:expr ) => { $res? };
+    ( v $res:expr ) => { $res? };
+    ( vdg $res:expr ) => { $res? };
+    ( x $res:expr ) => { $res? };
+    ( y $res:expr ) => { $res? };
+    ( z $res:expr ) => { $res? };
+    ( $name:ident $res:expr ) => { $res };
+}
+
+#[macro_export]
+macro_rules! circuit
+{
+    ($nr_qbits:expr, $nr_cbits:expr, { $( $method_name:ident ( $( $arg:expr ),* ) );* ; } ) => {
+        {
+            let generator = || {
+                let mut circuit = $crate::circuit::Circuit::new($nr_qbits, $nr_cbits);
+                $(
+                    circuit_method_check!(
+                        $method_name
+                        circuit.$method_name($($arg),*)
+                    );
+                );*
+                Ok(circuit) as $crate::error::Result<$crate::circuit::Circuit>
+            };
+            generator()
+        }
+    }
+}
+
+#[cfg(feature = "parallel")] mod parallel;
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Basis, Circuit, CircuitOp, QuStateRepr, SimulationMode};
+    use crate::gates::{CX, CY, H, S, X};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    #[test]
+    fn test_gate_methods()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), &array![[x, x], [x, -x]]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not an H gate"),
+            None => panic!("H gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.x(1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[z, o], [o, z]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not an X gate"),
+            None => panic!("X gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.y(0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[z, -i], [i, z]]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a Y gate"),
+            None => panic!("Y gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.z(1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, -o]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a Z gate"),
+            None => panic!("Z gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.rx(::std::f64::consts::PI, 1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[z, -i], [-i, z]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not an RX gate"),
+            None => panic!("RX gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.ry(::std::f64::consts::PI, 0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[z, -o], [o, z]]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not an RY gate"),
+            None => panic!("RY gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.rz(::std::f64::consts::PI, 1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[-i, z], [z, i]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not an RZ gate"),
+            None => panic!("RZ gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.u1(::std::f64::consts::FRAC_PI_4, 1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, x*(o+i)]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a U1 gate"),
+            None => panic!("U1 gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.u2(::std::f64::consts::FRAC_PI_4,
+            ::std::f64::consts::FRAC_PI_2, 0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![
+                    [x, -x*i],
+                    [0.5*(o+i), 0.5*(-o+i)]
+                ]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a U2 gate"),
+            None => panic!("U2 gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.u3(::std::f64::consts::PI, ::std::f64::consts::FRAC_PI_4,
+            ::std::f64::consts::FRAC_PI_2, 0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![
+                    [z, -i],
+                    [x*(o+i), z]
+                ]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a U3 gate"),
+            None => panic!("U3 gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.cx(1, 0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![
+                    [o, z, z, z],
+                    [z, o, z, z],
+                    [z, z, z, o],
+                    [z, z, o, z]
+                ]);
+                assert_eq!(bits, &vec![1, 0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a CX gate"),
+            None => panic!("CX gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.swap(0, 1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![
+                    [o, z, z, z],
+                    [z, z, o, z],
+                    [z, o, z, z],
+                    [z, z, z, o]
+                ]);
+                assert_eq!(bits, &vec![0, 1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a Swap gate"),
+            None => panic!("Swap gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.mcx(&[1], 0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_eq!(gate.description(), "CX");
+                assert_eq!(bits, &vec![1, 0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a CX gate"),
+            None => panic!("CX gate was not added")
+            // LCOV_EXCL_STOP
+        }
+        assert_eq!(circuit.mcx(&[0, 1], 2), Err(crate::error::Error::InvalidQBit(2)));
+
+        let h = 0.5 * o;
+        let hi = 0.5 * i;
+        assert_eq!(circuit.v(0), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[h+hi, h-hi], [h-hi, h+hi]]);
+                assert_eq!(bits, &vec![0]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a V gate"),
+            None => panic!("V gate was not added")
+            // LCOV_EXCL_STOP
+        }
+
+        assert_eq!(circuit.vdg(1), Ok(()));
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_complex_matrix_eq!(gate.matrix(), array![[h-hi, h+hi], [h+hi, h-hi]]);
+                assert_eq!(bits, &vec![1]);
+            },
+            // LCOV_EXCL_START
+            Some(_) => panic!("Value added was not a V† gate"),
+            None => panic!("V† gate was not added")
+            // LCOV_EXCL_STOP
+        }
+        assert_eq!(circuit.v(2), Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_execute()
+    {
+        let nr_shots = 5;
+        let mut circuit = circuit!(2, 2, {
+            add_gate(X::new(), &[0]);
+            add_gate(X::new(), &[1]);
+            add_gate(CX::new(), &[0, 1]);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        assert_eq!(circuit.cstate(), Some(&array![0b01, 0b01, 0b01, 0b01, 0b01]));
+    }
+
+    #[test]
+    fn test_with_seed()
+    {
+        let nr_shots = 100;
+        let create = |seed: u64| -> Circuit {
+            let mut circuit = Circuit::new(3, 3);
+            for qbit in 0..3
+            {
+                assert_eq!(circuit.h(qbit), Ok(()));
+            }
+            assert_eq!(circuit.add_gate(crate::gates::T::new(), &[0]), Ok(()));
+            assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+            circuit.with_seed(seed);
+            circuit
+        };
+
+        let mut circuit0 = create(0x1234);
+        let mut circuit1 = create(0x1234);
+        let mut histograms = vec![];
+        for _ in 0..3
+        {
+            assert_eq!(circuit0.execute(nr_shots), Ok(()));
+            assert_eq!(circuit1.execute(nr_shots), Ok(()));
+            let hist = circuit0.histogram_vec().unwrap();
+            assert_eq!(circuit1.histogram_vec().unwrap(), hist);
+            histograms.push(hist);
+        }
+        // The generator is not reseeded for every execution
+        assert!(histograms[1] != histograms[0] || histograms[2] != histograms[0]);
+
+        assert_eq!(circuit0.reexecute(), Ok(()));
+        assert_eq!(circuit1.reexecute(), Ok(()));
+        assert_eq!(circuit0.histogram_vec(), circuit1.histogram_vec());
+        assert_eq!(circuit0.execute_density(nr_shots), Ok(()));
+        assert_eq!(circuit1.execute_density(nr_shots), Ok(()));
+        assert_eq!(circuit0.histogram_vec(), circuit1.histogram_vec());
+
+        let mut circuit0 = Circuit::new(2, 0);
+        assert_eq!(circuit0.h(0), Ok(()));
+        assert_eq!(circuit0.h(1), Ok(()));
+        let mut circuit1 = Circuit::new(2, 0);
+        assert_eq!(circuit1.h(0), Ok(()));
+        assert_eq!(circuit1.h(1), Ok(()));
+        assert_eq!(circuit0.with_seed(5).sample_statevector(50),
+            circuit1.with_seed(5).sample_statevector(50));
+    }
+
+    #[test]
+    fn test_bind_parameters()
+    {
+        let ry = crate::gates::Kron::new(crate::gates::RY::new(crate::gates::Parameter::symbol("phi")),
+            crate::gates::I::new());
+
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.add_gate(crate::gates::RX::new(crate::gates::Parameter::symbol("theta")), &[0]), Ok(()));
+        assert_eq!(circuit.add_gate(ry, &[1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CRZ::new(crate::gates::Parameter::symbol("theta")), &[0, 2]), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.execute(10),
+            Err(crate::error::Error::UnboundParameter(String::from("theta"))));
+
+        let mut store = crate::gates::ParameterStore::new();
+        store.set("theta", ::std::f64::consts::PI);
+        assert_eq!(circuit.bind_parameters(&store),
+            Err(crate::error::Error::UnboundParameter(String::from("phi"))));
+        assert_eq!(circuit.execute(10),
+            Err(crate::error::Error::UnboundParameter(String::from("phi"))));
+
+        store.set("phi", ::std::f64::consts::PI);
+        assert_eq!(circuit.bind_parameters(&store), Ok(()));
+        match circuit.ops[0]
+        {
+            CircuitOp::Gate(ref gate, _) => { assert_eq!(gate.description(), "RX(3.1416)"); },
+            // LCOV_EXCL_START
+            _ => panic!("Expected a gate")
+            // LCOV_EXCL_STOP
+        }
+        match circuit.ops[2]
+        {
+            CircuitOp::Gate(ref gate, _) => { assert_eq!(gate.description(), "CRZ(3.1416)"); },
+            // LCOV_EXCL_START
+            _ => panic!("Expected a gate")
+            // LCOV_EXCL_STOP
+        }
+        assert_eq!(circuit.execute(10), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert_eq!(hist.len(), 1);
+        assert_eq!(hist.get(&3), Some(&10));
+    }
+
+    #[test]
+    fn test_execute_density()
+    {
+        let nr_shots = 5;
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        // Full decay of qubit 1 to |0⟩
+        assert_eq!(circuit.add_noise(crate::gates::ThermalRelaxation::new(1.0e6, 1.0, 1.0), &[1]),
+            Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+
+        assert_eq!(circuit.execute_density(nr_shots), Ok(()));
+        assert!(matches!(circuit.q_state, Some(QuStateRepr::Density(_))));
+        assert_eq!(circuit.cstate(), Some(&array![0b01, 0b01, 0b01, 0b01, 0b01]));
+        assert_eq!(circuit.simulation_mode(), &SimulationMode::Automatic);
+
+        // Noise is ignored in state vector simulations
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        assert_eq!(circuit.cstate(), Some(&array![0b11, 0b11, 0b11, 0b11, 0b11]));
+    }
+
+    #[test]
+    fn test_add_noise_after_gate()
+    {
+        let mut circuit = Circuit::new(3, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+
+        assert_eq!(circuit.add_noise_after_gate(1, crate::gates::Depolarizing::new(0.1)), Ok(()));
+        assert_eq!(circuit.ops.len(), 6);
+        assert!(matches!(circuit.ops[2], CircuitOp::Noise(_, ref bits) if bits == &[0]));
+        assert!(matches!(circuit.ops[3], CircuitOp::Noise(_, ref bits) if bits == &[1]));
+        assert_eq!(circuit.add_noise_after_gate(1,
+            crate::gates::TwoQubitDepolarizing::new(0.1)), Ok(()));
+        assert!(matches!(circuit.ops[2], CircuitOp::Noise(_, ref bits) if bits == &[0, 1]));
+        assert!(matches!(circuit.ops[5], CircuitOp::Gate(ref gate, _) if gate.description() == "X"));
+
+        assert_eq!(circuit.add_noise_after_gate(5, crate::gates::TwoQubitDepolarizing::new(0.1)),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("TwoQubitDepolarizing(0.1)"))));
+        assert!(matches!(circuit.add_noise_after_gate(6, crate::gates::Depolarizing::new(0.1)),
+            Err(crate::error::Error::NotAGate(_))));
+        assert_eq!(circuit.add_noise_after_gate(7, crate::gates::Depolarizing::new(0.1)),
+            Err(crate::error::Error::InvalidOpIndex(7)));
+        assert_eq!(circuit.ops.len(), 7);
+
+        // Noise does not affect pure state simulations
+        assert_eq!(circuit.measure(2, 1), Ok(()));
+        assert_eq!(circuit.execute(10), Ok(()));
+        assert!(circuit.cstate().unwrap().iter().all(|&c| c & 0b10 != 0));
+
+        // Fully depolarizing noise after the X gate in density matrix mode
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.add_noise_after_gate(0, crate::gates::Depolarizing::new(0.75)), Ok(()));
+        let rho = circuit.density_matrix().unwrap();
+        assert_complex_matrix_eq!(rho.matrix(), &(crate::cmatrix::CMatrix::eye(2) * 0.5));
+    }
+
+    #[test]
+    fn test_execute_with_timing()
+    {
+        use rand::SeedableRng;
+
+        let mut gate_times = ::std::collections::HashMap::new();
+        gate_times.insert(String::from("X"), 1.0);
+        gate_times.insert(String::from("RX"), 0.5);
+        gate_times.insert(String::from("measure"), 0.25);
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.x(1), Ok(()));
+        for _ in 0..4
+        {
+            assert_eq!(circuit.x(0), Ok(()));
+        }
+        assert_eq!(circuit.rx(0.0, 0), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1]), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+
+        let nr_shots = 4096;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1234);
+        let (hist, stats) = circuit.execute_with_timing_and_rng(nr_shots, &gate_times,
+            &[100.0, 4.0], &[100.0, 8.0], &mut rng).unwrap();
+        assert_eq!(stats.total_time, 5.75);
+        assert_eq!(stats.critical_path_time, 4.75);
+        assert_eq!(stats.idle_times, vec![0.0, 3.5]);
+        assert_eq!(stats.t1_errors[0], 0.0);
+        assert!((stats.t1_errors[1] - (1.0 - (-3.5f64 / 4.0).exp())).abs() < 1.0e-12);
+        assert!((stats.t2_errors[1] - (1.0 - (-3.5f64 / 8.0).exp())).abs() < 1.0e-12);
+
+        // Qubit 1 decays while waiting for qubit 0
+        let excited = *hist.get(&0b10).unwrap_or(&0) as f64 / nr_shots as f64;
+        assert!((excited - (-3.5f64 / 4.0).exp()).abs() < 0.03);
+        assert_eq!(hist.keys().filter(|&&k| k & 1 == 1).count(), 0);
+        assert_eq!(circuit.histogram(), Ok(hist));
+
+        assert_eq!(circuit.execute_with_timing(nr_shots, &gate_times, &[1.0], &[1.0, 1.0]).err(),
+            Some(crate::error::Error::InvalidNrBits(1, 2, String::from("T1 times"))));
+        assert_eq!(circuit.execute_with_timing(nr_shots, &gate_times, &[1.0, 1.0], &[1.0, 3.0]).err(),
+            Some(crate::error::Error::InvalidRelaxationTimes(1)));
+    }
+
+    #[test]
+    fn test_cache_gates()
+    {
+        let nr_shots = 5;
+        let mut circuit = circuit!(3, 3, {
+            h(0);
+            rz(::std::f64::consts::PI, 0);
+            h(0);
+            ry(::std::f64::consts::PI, 1);
+            measure(0, 0);
+            measure(1, 1);
+            add_conditional_gate(&[0, 1], 3, CX::new(), &[1, 2]);
+            measure(2, 2);
+        }).unwrap();
+        circuit.set_cache_gates(true);
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        assert_eq!(circuit.cstate(), Some(&array![0b111, 0b111, 0b111, 0b111, 0b111]));
+
+        let cached = circuit.cached_ops();
+        assert_eq!(cached.len(), circuit.ops.len());
+        for (op0, op1) in circuit.ops.iter().zip(cached.iter())
+        {
+            if let (CircuitOp::Gate(g0, b0), CircuitOp::Gate(g1, b1)) = (op0, op1)
+            {
+                assert_eq!(g0.description(), g1.description());
+                assert_complex_matrix_eq!(g0.matrix(), &g1.matrix());
+                assert_eq!(b0, b1);
+            }
+        }
+
+        // Stabilizer circuits remain stabilizer circuits
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        circuit.set_cache_gates(true);
+        assert!(circuit.is_stabilizer_circuit());
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        assert!(circuit.cstate().unwrap().iter().all(|&b| b == 0 || b == 3));
+
+        circuit.set_cache_gates(false);
+        assert_eq!(circuit.reexecute(), Ok(()));
+    }
+
+    #[test]
+    fn test_simulation_mode()
+    {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(31415);
+        let nr_shots = 1024;
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+            measure_all(&[0, 1]);
+        }).unwrap();
+        assert_eq!(circuit.simulation_mode(), &SimulationMode::Automatic);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        assert!(matches!(circuit.q_state, Some(QuStateRepr::Stabilizer(_))));
+
+        for &(mode, nr_warnings) in [
+            (SimulationMode::Statevector, 0),
+            (SimulationMode::DensityMatrix, 0),
+            (SimulationMode::Stabilizer, 0),
+            (SimulationMode::MPS(2), 0),
+            (SimulationMode::MPS(1), 1)].iter()
+        {
+            circuit.set_simulation_mode(mode);
+            assert_eq!(circuit.simulation_mode(), &mode);
+            assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+            assert_eq!(circuit.warnings().len(), nr_warnings);
+            let hist = circuit.histogram_vec().unwrap();
+            if nr_warnings == 0
+            {
+                assert_eq!(hist[0] + hist[3], nr_shots);
+                assert!(hist[0] > 400 && hist[3] > 400);
+            }
+        }
+        assert!(matches!(circuit.q_state, Some(QuStateRepr::MPS(_))));
+        assert_eq!(circuit.warnings(), &[crate::error::Warning::TruncationOccurred]);
+
+        let mut circuit = circuit!(1, 1, {
+            h(0);
+            rz(0.25 * ::std::f64::consts::PI, 0);
+            measure(0, 0);
+        }).unwrap();
+        circuit.set_simulation_mode(SimulationMode::Stabilizer);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng),
+            Err(crate::error::Error::NotACliffordCircuit));
+
+        // Noise is only taken into account in density matrix mode
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.add_noise(crate::gates::Depolarizing::new(0.75), &[0]), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        circuit.set_simulation_mode(SimulationMode::Statevector);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        assert_eq!(circuit.histogram_vec(), Ok(vec![0, nr_shots]));
+        circuit.set_simulation_mode(SimulationMode::DensityMatrix);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(hist[0] > 400 && hist[1] > 400);
+    }
+
+    #[test]
+    fn test_measure()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            measure_x(0, 0);
+            measure_x(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(hist.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            h(0);
+            h(1);
+            measure_x(0, 0);
+            measure_x(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            measure_y(0, 0);
+            measure_y(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(hist.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_peek()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(1, 3, {
+            h(0);
+            peek(0, 0);
+            h(0);
+            peek(0, 1);
+            h(0);
+            peek(0, 2);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
+        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
+        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
+        assert!(n10 == nr_shots);
+        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
+        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+
+        let mut circuit = circuit!(2, 6, {
+            h(0);
+            h(1);
+            peek(0, 0);
+            h(0);
+            peek(0, 1);
+            h(0);
+            peek(0, 2);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let mut n0 = [0; 2];
+        let mut n1 = [0; 2];
+        let mut n2 = [0; 2];
+        for (key, count) in hist
+        {
+            n0[key as usize & 1] += count;
+            n1[(key as usize >> 1) & 1] += count;
+            n2[(key as usize >> 2) & 1] += count;
+        }
+        assert!(n0.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.5, tol)
+        ));
+        assert_eq!(n1, [nr_shots, 0]);
+        assert!(n2.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.5, tol)
+        ));
+    }
+
+    #[test]
+    fn test_peek_partial()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        for &stabilizer in [true, false].iter()
+        {
+            let mut circuit = Circuit::new(3, 4);
+            assert_eq!(circuit.h(0), Ok(()));
+            assert_eq!(circuit.cx(0, 2), Ok(()));
+            if !stabilizer
+            {
+                assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+            }
+            assert_eq!(circuit.peek_partial(&[0, 2], &[0, 1]), Ok(()));
+            assert_eq!(circuit.measure(0, 2), Ok(()));
+            assert_eq!(circuit.measure(2, 3), Ok(()));
+            assert_eq!(circuit.is_stabilizer_circuit(), stabilizer);
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+            // Peeked results are correlated, and the measurement statistics
+            // afterwards are the same as without peeking
+            let hist = circuit.histogram().unwrap();
+            let npeek = hist.iter().filter(|&(&k, _)| k & 3 == 3).map(|(_, &c)| c).sum();
+            assert!(crate::stats::measurement_ok(npeek, nr_shots, 0.5, tol));
+            assert!(hist.keys().all(|&k| k & 3 == 0 || k & 3 == 3));
+            let nmeasure = hist.iter().filter(|&(&k, _)| k >> 2 == 3).map(|(_, &c)| c).sum();
+            assert!(crate::stats::measurement_ok(nmeasure, nr_shots, 0.5, tol));
+            assert!(hist.keys().all(|&k| k >> 2 == 0 || k >> 2 == 3));
+        }
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.peek_partial(&[0, 1], &[0]),
+            Err(crate::error::Error::InvalidNrMeasurementBits(1, 2)));
+        assert_eq!(circuit.peek_partial(&[0, 2], &[0, 1]), Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(circuit.peek_partial(&[0, 1], &[1, 2]), Err(crate::error::Error::InvalidCBit(2)));
+        assert_eq!(circuit.peek_partial(&[1], &[0]), Ok(()));
+        assert!(circuit.open_qasm().is_err());
+        assert!(circuit.c_qasm().is_err());
+    }
+
+    #[test]
+    fn test_peek_basis()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(1, 3, {
+            peek_x(0, 0);
+            h(0);
+            peek_x(0, 1);
+            h(0);
+            peek_x(0, 2);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
+        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
+        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
+        assert_eq!(n10, nr_shots);
+        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
+        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+
+        let mut circuit = circuit!(2, 6, {
+            peek_y(0, 0);
+            h(0);
+            peek_y(0, 1);
+            sdg(0);
+            peek_y(0, 2);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // Results of first and second measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 1.
+        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
+        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
+        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
+        assert!(crate::stats::measurement_ok(n10, nr_shots, 0.5, tol));
+        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
+        assert_eq!(n20, 0);
+    }
+
+    #[test]
+    fn test_conditional()
+    {
+        let mut circuit = circuit!(2, 2, {
+            add_conditional_gate(&[0, 1], 1, X::new(), &[1]);
+            measure_all(&[0, 1]);
+        }).unwrap();
+        assert_eq!(circuit.execute(5), Ok(()));
+        assert_eq!(circuit.c_state, Some(array![0b00, 0b00, 0b00, 0b00, 0b00]));
+
+        let mut circuit = Circuit::new(2, 2);
+        circuit.q_state = Some(QuStateRepr::vector(2, 5));
+        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
+        circuit.add_conditional_gate(&[0, 1], 1, X::new(), &[1]).unwrap();
+        circuit.measure_all(&[0, 1]).unwrap();
+        assert_eq!(circuit.reexecute(), Ok(()));
+        assert_eq!(circuit.c_state, Some(array![0b10, 0b00, 0b00, 0b00, 0b00]));
+
+        let mut circuit = Circuit::new(2, 2);
+        circuit.q_state = Some(QuStateRepr::vector(2, 5));
+        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
+        circuit.add_conditional_gate(&[0, 1], 2, X::new(), &[1]).unwrap();
+        circuit.measure_all(&[0, 1]).unwrap();
+        assert_eq!(circuit.reexecute(), Ok(()));
+        assert_eq!(circuit.c_state, Some(array![0b00, 0b10, 0b10, 0b00, 0b00]));
+
+        let mut circuit = Circuit::new(2, 2);
+        circuit.q_state = Some(QuStateRepr::vector(2, 5));
+        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
+        circuit.add_conditional_gate(&[1], 1, X::new(), &[0]).unwrap();
+        circuit.measure_all(&[0, 1]).unwrap();
+        assert_eq!(circuit.reexecute(), Ok(()));
+        assert_eq!(circuit.c_state, Some(array![0b00, 0b01, 0b01, 0b01, 0b00]));
+    }
+
+    #[test]
+    fn test_measure_all()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            measure_all(&[0, 1]);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            measure_all(&[1, 0]);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, 0, nr_shots, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            h(1);
+            measure_all(&[0, 1]);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(hist.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_measure_all_basis()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            h(1);
+            measure_all_basis(&[0, 1], Basis::X);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![nr_shots, 0, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            h(0);
+            h(1);
+            measure_all_basis(&[0, 1], Basis::X);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            x(0);
+            h(0);
+            h(1);
+            add_gate(S::new(), &[0]);
+            add_gate(S::new(), &[1]);
+            measure_all_basis(&[0, 1], Basis::Y);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            measure_all_basis(&[0, 1], Basis::Y);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(hist.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_peek_all()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(1, 3, {
+            h(0);
+            peek_all(&[0]);
+            h(0);
+            peek_all(&[1]);
+            h(0);
+            peek_all(&[2]);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
+        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
+        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
+        assert!(n10 == nr_shots);
+        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
+        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+
+        let mut circuit = circuit!(2, 6, {
+            h(0);
+            h(1);
+            peek_all(&[0, 1]);
+            h(0);
+            peek_all(&[2, 3]);
+            h(0);
+            peek_all(&[4, 5]);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let mut n0 = [0; 4];
+        let mut n1 = [0; 4];
+        let mut n2 = [0; 4];
+        for (key, count) in hist
+        {
+            n0[key as usize & 0x03] += count;
+            n1[(key as usize >> 2) & 0x03] += count;
+            n2[(key as usize >> 4) & 0x03] += count;
+        }
+        assert!(n0.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+        assert_eq!(n1[1], 0);
+        assert_eq!(n1[3], 0);
+        assert!(crate::stats::measurement_ok(n1[0], nr_shots, 0.5, tol));
+        assert!(crate::stats::measurement_ok(n1[2], nr_shots, 0.5, tol));
+        assert!(n2.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_peek_all_basis()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(1, 3, {
+            peek_all_basis(&[0], Basis::X);
+            h(0);
+            peek_all_basis(&[1], Basis::X);
+            h(0);
+            peek_all_basis(&[2], Basis::X);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // Results of first and third measurement should be approximately equally
+        // distributed over 0 and 1, second should be pure 0.
+        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
+        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
+        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
+        assert!(n10 == nr_shots);
+        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
+        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+
+        let mut circuit = circuit!(2, 6, {
+            h(0);
+            h(1);
+            peek_all_basis(&[0, 1], Basis::Y);
+            s(1);
+            peek_all_basis(&[2, 3], Basis::Y);
+            s(0);
+            peek_all_basis(&[4, 5], Basis::Y);
+        }).unwrap();
+        assert_eq!(circuit.execute(1024), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        // Results of first measurement should be approximately equally
+        // distributed over 0 and 1 for both qubits, second should be pure 0
+        // for second qubit, third pure |00⟩.
+        let mut n0 = [0; 4];
+        let mut n1 = [0; 4];
+        let mut n2 = [0; 4];
+        for (key, count) in hist
+        {
+            n0[key as usize & 0x03] += count;
+            n1[(key as usize >> 2) & 0x03] += count;
+            n2[(key as usize >> 4) & 0x03] += count;
+        }
+        assert!(n0.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+        assert_eq!(n1[2], 0);
+        assert_eq!(n1[3], 0);
+        assert!(crate::stats::measurement_ok(n1[0], nr_shots, 0.5, tol));
+        assert!(crate::stats::measurement_ok(n1[1], nr_shots, 0.5, tol));
+        assert_eq!(n2, [nr_shots, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_measure_expectation()
+    {
+        let nr_shots = 1024;
+        let zz = PauliString::new(&[PauliOp::Z, PauliOp::Z]);
+        let xx = PauliString::new(&[PauliOp::X, PauliOp::X]);
+
+        let mut circuit = circuit!(2, 4, {
+            h(0);
+            cx(0, 1);
+            measure_expectation(&zz, 0);
+            measure_expectation(&xx, 1);
+            z(0);
+            measure_expectation(&xx, 2);
+            measure(0, 3);
+        }).unwrap();
+        assert!(!circuit.is_stabilizer_circuit());
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        // The state should not have been collapsed by the expectation values
+        assert_eq!(hist[3] + hist[11], nr_shots);
+        assert!(crate::stats::measurement_ok(hist[3], nr_shots, 0.5, 1.0e-5));
+
+        // Same in stabilizer representation
+        let q_state = QuStateRepr::stabilizer(2, nr_shots);
+        assert_eq!(circuit.execute_with(nr_shots, &mut rand::thread_rng(), q_state), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[3] + hist[11], nr_shots);
+
+        assert!(matches!(circuit.measure_expectation(&PauliString::new(&[PauliOp::Z]), 0),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+        assert_eq!(circuit.measure_expectation(&zz, 4), Err(crate::error::Error::InvalidCBit(4)));
+        assert!(circuit.open_qasm().is_err());
+        assert!(circuit.c_qasm().is_err());
+        assert!(circuit.latex().is_err());
+    }
+
+    #[test]
+    fn test_measure_pauli()
+    {
+        let nr_shots = 256;
+
+        // (|00⟩ + |11⟩)/√2 is an eigenstate of ZZ and XX with eigenvalue 1,
+        // and of YY with eigenvalue -1.
+        let mut circuit = Circuit::new(3, 4);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::Z], &[0, 2], 0), Ok(()));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::X, PauliOp::X], &[2, 0], 1), Ok(()));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Y, PauliOp::Y], &[0, 2], 2), Ok(()));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::Z], &[0, 2], 3), Ok(()));
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[0b0100], nr_shots);
+
+        // Single qubit Z on |+⟩ is random, and collapses the state
+        let mut circuit = Circuit::new(1, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z], &[0], 0), Ok(()));
+        assert_eq!(circuit.measure(0, 1), Ok(()));
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[0] + hist[3], nr_shots);
+        assert!(hist[0] > 0 && hist[3] > 0);
+
+        let qasm = circuit.open_qasm().unwrap();
+        assert!(qasm.contains("h q[0];\nmeasure q[0] -> b[0];\nmeasure q[0] -> b[1];\n"));
+
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::I], &[0, 1], 0),
+            Err(crate::error::Error::InvalidPauliMeasurement(String::from("ZI"))));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::X], &[0], 0),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("ZX"))));
+        assert_eq!(circuit.measure_pauli(&[], &[], 0),
+            Err(crate::error::Error::InvalidNrBits(0, 0, String::new())));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::X], &[0, 3], 0),
+            Err(crate::error::Error::InvalidQBit(3)));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::X], &[1, 1], 0),
+            Err(crate::error::Error::InvalidQBit(1)));
+        assert_eq!(circuit.measure_pauli(&[PauliOp::Z, PauliOp::X], &[0, 1], 1),
+            Err(crate::error::Error::InvalidCBit(1)));
+        assert_eq!(circuit.gate_count(), 0);
+    }
+
+    #[test]
+    fn test_purity()
+    {
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert!((circuit.purity().unwrap() - 1.0).abs() < 1.0e-12);
+
+        // Depolarizing with p = 3/4 leaves a maximally mixed qubit
+        assert_eq!(circuit.add_noise(crate::gates::Depolarizing::new(0.75), &[1]), Ok(()));
+        assert!((circuit.purity().unwrap() - 0.25).abs() < 1.0e-12);
+
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert!(circuit.purity().is_err());
+    }
+
+    #[test]
+    fn test_fidelity_to()
+    {
+        let ideal = circuit!(2, 0, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        let mut noisy = circuit!(2, 0, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        assert!((ideal.fidelity_to(&noisy).unwrap() - 1.0).abs() < 1.0e-10);
+
+        // Full depolarization of one qubit of a Bell state leaves ρ = I/4
+        assert_eq!(noisy.add_noise(crate::gates::Depolarizing::new(0.75), &[1]), Ok(()));
+        assert!((ideal.fidelity_to(&noisy).unwrap() - 0.25).abs() < 1.0e-10);
+        assert!((noisy.fidelity_to(&ideal).unwrap() - 0.25).abs() < 1.0e-10);
+
+        let other = Circuit::new(3, 0);
+        assert_eq!(ideal.fidelity_to(&other), Err(crate::error::Error::DimensionMismatch(4, 8)));
+    }
+
+    #[test]
+    fn test_expectation_value()
+    {
+        let mut circuit = circuit!(2, 0, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::Z]);
+        assert_eq!(circuit.expectation_value(&obs), Err(crate::error::Error::NotExecuted));
+        assert_eq!(circuit.execute(1), Ok(()));
+        assert!((circuit.expectation_value(&obs).unwrap() - 1.0).abs() < 1.0e-12);
+        let obs = PauliString::new(&[PauliOp::Y, PauliOp::Y]);
+        assert!((circuit.expectation_value(&obs).unwrap() + 1.0).abs() < 1.0e-12);
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::I]);
+        assert!(circuit.expectation_value(obs).unwrap().abs() < 1.0e-12);
+
+        assert_eq!(circuit.add_noise(crate::gates::Depolarizing::new(0.75), &[0]), Ok(()));
+        assert_eq!(circuit.execute_density(1), Ok(()));
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::Z]);
+        assert!(circuit.expectation_value(&obs).unwrap().abs() < 1.0e-12);
+
+        let obs = PauliString::new(&[PauliOp::Z; 3]);
+        assert_eq!(circuit.expectation_value(&obs),
+            Err(crate::error::Error::InvalidNrBits(3, 2, String::from("ZZZ"))));
+    }
+
+    #[test]
+    fn test_statevector()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        assert_complex_vector_eq!(&circuit.statevector().unwrap(), &array![x, z, z, x]);
+
+        // Noise and barriers are ignored, a full reset starts again from |00⟩
+        assert_eq!(circuit.add_noise(crate::gates::Depolarizing::new(0.5), &[0]), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1]), Ok(()));
+        assert_complex_vector_eq!(&circuit.statevector().unwrap(), &array![x, z, z, x]);
+        circuit.reset_all();
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_complex_vector_eq!(&circuit.statevector().unwrap(), &array![z, o, z, z]);
+
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.statevector(), Err(crate::error::Error::OpNotImplemented(
+            String::from("statevector"), String::from("measurement or conditional"))));
+
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.add_gate(crate::gates::RX::new(crate::gates::Parameter::symbol("t")),
+            &[0]), Ok(()));
+        assert_eq!(circuit.statevector(),
+            Err(crate::error::Error::UnboundParameter(String::from("t"))));
+    }
+
+    #[test]
+    fn test_statevector_fidelity()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        let fidelity = circuit.statevector_fidelity(&array![x, z, z, x]).unwrap();
+        assert!((fidelity - 1.0).abs() < 1.0e-12);
+        let fidelity = circuit.statevector_fidelity(&array![z, z, z, o]).unwrap();
+        assert!((fidelity - 0.5).abs() < 1.0e-12);
+
+        // Fidelity is symmetric in the two states
+        let other = circuit!(2, 0, {
+            h(1);
+            s(1);
+        }).unwrap();
+        let f0 = circuit.statevector_fidelity(&other.statevector().unwrap()).unwrap();
+        let f1 = other.statevector_fidelity(&circuit.statevector().unwrap()).unwrap();
+        assert!((f0 - 0.25).abs() < 1.0e-12);
+        assert!((f0 - f1).abs() < 1.0e-12);
+
+        assert_eq!(circuit.statevector_fidelity(&array![o, z]),
+            Err(crate::error::Error::DimensionMismatch(4, 2)));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert!(matches!(circuit.statevector_fidelity(&array![x, z, z, x]),
+            Err(crate::error::Error::OpNotImplemented(_, _))));
+    }
+
+    #[test]
+    fn test_sample_statevector()
+    {
+        let nr_shots = 4096;
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(533);
+
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.sample_statevector_with_rng(5, &mut rng), Ok(vec![4; 5]));
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        let samples = circuit.sample_statevector_with_rng(nr_shots, &mut rng).unwrap();
+        assert_eq!(samples.len(), nr_shots);
+        assert!(samples.iter().all(|&s| s == 0 || s == 3));
+        let sampled = samples.iter().filter(|&&s| s == 3).count();
+
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[0] + hist[3], nr_shots);
+
+        // Chi-squared test for equal distributions over the two outcomes,
+        // with one degree of freedom. The critical value corresponds to a
+        // significance level of 10⁻⁵.
+        let observed = [[nr_shots - sampled, sampled], [hist[0], hist[3]]];
+        let mut chi2 = 0.0;
+        for j in 0..2
+        {
+            let expected = (observed[0][j] + observed[1][j]) as f64 / 2.0;
+            for row in observed.iter()
+            {
+                chi2 += (row[j] as f64 - expected).powi(2) / expected;
+            }
+        }
+        assert!(chi2 < 19.51);
+
+        assert!(circuit.sample_statevector(10).is_err());
+        assert!(Circuit::new(65, 0).sample_statevector(1).is_err());
+    }
+
+    #[test]
+    fn test_expectation_value_sum()
+    {
+        let x = crate::observable::SparsePauliOp::from_terms(vec![(1.0, vec![PauliOp::X])]);
+        let z = crate::observable::SparsePauliOp::from_terms(vec![(1.0, vec![PauliOp::Z])]);
+
+        let mut circuit = circuit!(1, 1, {
+            h(0);
+        }).unwrap();
+        assert_eq!(circuit.expectation_value(&x), Err(crate::error::Error::NotExecuted));
+        assert_eq!(circuit.execute(10), Ok(()));
+        assert!((circuit.expectation_value(&x).unwrap() - 1.0).abs() < 1.0e-12);
+        assert!(circuit.expectation_value(&z).unwrap().abs() < 1.0e-12);
+
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.execute(10), Ok(()));
+        assert!((circuit.expectation_value(&z).unwrap() - 1.0).abs() < 1.0e-12);
+
+        let obs = crate::observable::SparsePauliOp::from_terms(vec![
+            (0.5, vec![PauliOp::X, PauliOp::X]),
+            (-0.25, vec![PauliOp::Y, PauliOp::Y]),
+            (1.0, vec![PauliOp::Z, PauliOp::Z]),
+            (2.0, vec![PauliOp::Z])
+        ]);
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        for &mode in &[SimulationMode::Statevector, SimulationMode::Stabilizer,
+            SimulationMode::DensityMatrix, SimulationMode::MPS(2)]
+        {
+            circuit.set_simulation_mode(mode);
+            assert_eq!(circuit.execute(10), Ok(()));
+            assert!((circuit.expectation_value(&obs).unwrap() - 1.75).abs() < 1.0e-12);
+        }
+
+        // After measurement, the expectation value is averaged over the runs
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        circuit.set_simulation_mode(SimulationMode::Statevector);
+        assert_eq!(circuit.execute(100), Ok(()));
+        let nr_ones = circuit.histogram_vec().unwrap()[1] as f64;
+        let expected = 1.0 + 2.0 * (100.0 - 2.0 * nr_ones) / 100.0;
+        assert!((circuit.expectation_value(&obs).unwrap() - expected).abs() < 1.0e-12);
+
+        let obs = crate::observable::SparsePauliOp::from_terms(vec![(1.0, vec![PauliOp::Z; 3])]);
+        assert_eq!(circuit.expectation_value(&obs),
+            Err(crate::error::Error::InvalidNrBits(3, 2, String::from("ZZZ"))));
+    }
+
+    #[test]
+    fn test_measure_expectation_density()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let x = PauliString::new(&[PauliOp::X]);
+
+        // Measuring X on |+⟩ leaves the state unchanged
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure_expectation(&x, 0), Ok(()));
+        let rho = circuit.density_matrix().unwrap();
+        assert_complex_matrix_eq!(rho.matrix(), &array![[h, h], [h, h]], 1.0e-14);
+
+        // Measuring X on |0⟩ results in the maximally mixed state
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.measure_expectation(&x, 0), Ok(()));
+        let rho = circuit.density_matrix().unwrap();
+        assert_complex_matrix_eq!(rho.matrix(), &array![[h, z], [z, h]], 1.0e-14);
+    }
+
+    #[test]
+    fn test_histogram()
+    {
+        let nr_shots = 4096;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            add_gate(H::new(), &[0]);
+            add_gate(H::new(), &[1]);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+        let hist = circuit.histogram().unwrap();
+        // With this many shots, we expect all keys to be present
+        let mut keys: Vec<&u64> = hist.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec![&0, &1, &2, &3]);
+
+        assert_eq!(hist.values().sum::<usize>(), nr_shots);
+        assert!(hist.values().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_histogram_vec()
+    {
+        let nr_shots = 4096;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            add_gate(H::new(), &[0]);
+            add_gate(H::new(), &[1]);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist.iter().sum::<usize>(), nr_shots);
+        assert!(hist.iter().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_histogram_string()
+    {
+        let nr_shots = 4096;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            add_gate(H::new(), &[0]);
+            add_gate(H::new(), &[1]);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+        let hist = circuit.histogram_string().unwrap();
+        // With this many shots, we expect all keys to be present
+        let mut keys: Vec<&String> = hist.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["00", "01", "10", "11"]);
+
+        assert_eq!(hist.values().sum::<usize>(), nr_shots);
+        assert!(hist.values().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+        ));
+    }
+
+    #[test]
+    fn test_histogram_probabilities()
+    {
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.histogram_probabilities(), Err(crate::error::Error::NotExecuted));
+        assert_eq!(circuit.histogram_vec_probabilities(), Err(crate::error::Error::NotExecuted));
+        assert_eq!(circuit.histogram_string_probabilities(), Err(crate::error::Error::NotExecuted));
+
+        let nr_shots = 4096;
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+        let counts = circuit.histogram_vec().unwrap();
+        let probs = circuit.histogram_vec_probabilities().unwrap();
+        assert_eq!(probs.len(), 4);
+        assert_eq!(probs[0], 0.0);
+        assert_eq!(probs[1], 0.0);
+        assert_eq!(probs[2], counts[2] as f64 / nr_shots as f64);
+        assert_eq!(probs[3], counts[3] as f64 / nr_shots as f64);
+        assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1.0e-12);
+
+        let probs = circuit.histogram_probabilities().unwrap();
+        assert_eq!(probs.len(), 2);
+        assert_eq!(probs[&2], counts[2] as f64 / nr_shots as f64);
+        assert_eq!(probs[&3], counts[3] as f64 / nr_shots as f64);
+
+        let probs = circuit.histogram_string_probabilities().unwrap();
+        assert_eq!(probs.len(), 2);
+        assert_eq!(probs["10"], counts[2] as f64 / nr_shots as f64);
+        assert_eq!(probs["11"], counts[3] as f64 / nr_shots as f64);
+    }
+
+    #[test]
+    fn test_reset()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            z(0);
+            reset(0);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![nr_shots, 0, 0, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            z(0);
+            x(1);
+            reset(0);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist, vec![0, 0, nr_shots, 0]);
+
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            z(0);
+            h(1);
+            reset(0);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert!(crate::stats::measurement_ok(hist[0], nr_shots, 0.5, tol));
+        assert_eq!(hist[1], 0);
+        assert!(crate::stats::measurement_ok(hist[2], nr_shots, 0.5, tol));
+        assert_eq!(hist[3], 0);
+    }
+
+    #[test]
+    fn test_reset_after_measure()
+    {
+        let nr_shots = 4096;
+        let tol = 1.0e-5;
+
+        // The outcome on qubit 2 should not be correlated with the earlier
+        // measurement of qubit 0, which is reset before the final measurement
+        for &mode in &[SimulationMode::Statevector, SimulationMode::Stabilizer]
+        {
+            let mut circuit = circuit!(3, 5, {
+                h(0);
+                measure(0, 4);
+                h(2);
+                reset(0);
+            }).unwrap();
+            assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+            circuit.set_simulation_mode(mode);
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+            let hist = circuit.histogram().unwrap();
+            let mut keys: Vec<u64> = hist.keys().cloned().collect();
+            keys.sort();
+            assert_eq!(keys, vec![0, 4, 16, 20]);
+            assert!(hist.values().all(
+                |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_reset_to()
+    {
+        let nr_shots = 1024;
+        let tol = 1.0e-5;
+
+        // Resetting one qubit of a Bell pair collapses the other
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+            reset_to(0, true);
+            measure(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[0], 0);
+        assert!(crate::stats::measurement_ok(hist[1], nr_shots, 0.5, tol));
+        assert_eq!(hist[2], 0);
+        assert!(crate::stats::measurement_ok(hist[3], nr_shots, 0.5, tol));
+        assert_eq!(hist[1] + hist[3], nr_shots);
+
+        let mut circuit = circuit!(2, 0, {
+            h(0);
+            cx(0, 1);
+            reset_to(0, true);
+        }).unwrap();
+        let rho = circuit.density_matrix().unwrap();
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [z, z, z, z],
+            [z, z, z, z],
+            [z, z, h, z],
+            [z, z, z, h]
+        ]);
+
+        assert_eq!(circuit.reset_to(1, false), Ok(()));
+        let rho = circuit.density_matrix().unwrap();
+        assert!((rho.matrix()[[2, 2]].re - 1.0).abs() < 1.0e-12);
+
+        assert_eq!(circuit.reset_to(2, true), Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_recycle_ancilla()
+    {
+        let nr_shots = 1024;
+
+        // Use a single ancilla to measure the parity of two pairs of qubits
+        let mut circuit = Circuit::new(5, 3);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.x(3), Ok(()));
+        assert_eq!(circuit.cx(0, 4), Ok(()));
+        assert_eq!(circuit.cx(1, 4), Ok(()));
+        assert_eq!(circuit.recycle_ancilla(4, 0), Ok(()));
+        assert_eq!(circuit.cx(2, 4), Ok(()));
+        assert_eq!(circuit.cx(3, 4), Ok(()));
+        assert_eq!(circuit.recycle_ancilla(4, 1), Ok(()));
+        assert_eq!(circuit.measure(4, 2), Ok(()));
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        assert!(circuit.cstate().unwrap().iter().all(|&b| b == 0b011));
+
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[5];
+creg b[3];
+x q[0];
+x q[3];
+cx q[0], q[4];
+cx q[1], q[4];
+measure q[4] -> b[0];
+reset q[4];
+cx q[2], q[4];
+cx q[3], q[4];
+measure q[4] -> b[1];
+reset q[4];
+measure q[4] -> b[2];
+"#)));
+
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.recycle_and_reuse(0, 1, 0), Ok(()));
+        assert_eq!(circuit.ops.len(), 3);
+        assert!(matches!(circuit.ops[1], CircuitOp::Measure(0, 0, Basis::Z)));
+        assert!(matches!(circuit.ops[2], CircuitOp::Reset(1)));
+
+        assert_eq!(circuit.recycle_ancilla(2, 0), Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(circuit.recycle_and_reuse(0, 2, 0), Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(circuit.recycle_ancilla(0, 1), Err(crate::error::Error::InvalidCBit(1)));
+        assert_eq!(circuit.ops.len(), 3);
+    }
+
+    #[test]
+    fn test_reset_to_export()
+    {
+        let circuit = circuit!(2, 0, {
+            reset_to(0, true);
+            reset_to(1, false);
+        }).unwrap();
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+reset q[0];
+x q[0];
+reset q[1];
+"#)));
+        assert_eq!(circuit.c_qasm(), Ok(String::from(
+r#"version 1.0
+qubits 2
+prep_z q[0]
+x q[0]
+prep_z q[1]
+"#)));
+    }
+
+    #[test]
+    fn test_reset_all()
+    {
+        let nr_shots = 1024;
+
+        let mut circuit = circuit!(5, 5, {
+            h(0);
+            z(0);
+            x(4);
+            h(3);
+            reset_all();
+            measure_all(&[0, 1, 2, 3, 4]);
+        }).unwrap();
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist[0], nr_shots);
+        assert!(hist[1..].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_open_qasm()
+    {
+        let circuit = circuit!(2, 2, {
+            x(0);
+            cx(0, 1);
+            barrier(&[0, 1]);
+            cx(1, 0);
+            barrier(&[1]);
+            cx(0, 1);
+            barrier(&[1, 0]);
+            measure_x(0, 0);
+            measure_y(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+x q[0];
+cx q[0], q[1];
+barrier q;
+cx q[1], q[0];
+barrier q[1];
+cx q[0], q[1];
+barrier q[1], q[0];
+h q[0];
+measure q[0] -> b[0];
+sdg q[1];
+h q[1];
+measure q[1] -> b[1];
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 2, {
             x(0);
-            measure(0, 0);
-            measure(1, 1);
+            measure_all(&[0, 1]);
+            measure_all(&[1, 0]);
+            measure_all_basis(&[0, 1], Basis::X);
+            measure_all_basis(&[0, 1], Basis::Y);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+x q[0];
+measure q -> b;
+measure q[0] -> b[1];
+measure q[1] -> b[0];
+h q;
+measure q -> b;
+sdg q;
+h q;
+measure q -> b;
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 0, {
             x(0);
-            measure_x(0, 0);
-            measure_x(1, 1);
+            h(1);
+            reset(0);
+            x(0);
+            reset_all();
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert!(hist.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+x q[0];
+h q[1];
+reset q[0];
+x q[0];
+reset q;
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 2, {
             x(0);
-            h(0);
-            h(1);
-            measure_x(0, 0);
-            measure_x(1, 1);
+            measure_all(&[0, 1]);
+            add_conditional_gate(&[0, 1], 1, X::new(), &[0]);
+            add_conditional_gate(&[], 1, X::new(), &[1]);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+x q[0];
+measure q -> b;
+if (b == 1) x q[0];
+x q[1];
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
-            x(0);
-            measure_y(0, 0);
-            measure_y(1, 1);
+        let circuit = circuit!(2, 2, {
+            add_conditional_gate(&[0], 1, X::new(), &[0]);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert!(hist.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+        assert!(matches!(circuit.open_qasm(), Err(_)));
     }
 
     #[test]
-    fn test_peek()
+    fn test_open_qasm3()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
-
-        let mut circuit = circuit!(1, 3, {
+        let mut circuit = circuit!(3, 2, {
             h(0);
-            peek(0, 0);
-            h(0);
-            peek(0, 1);
-            h(0);
-            peek(0, 2);
+            cx(0, 1);
+            barrier(&[0, 1]);
+            measure(0, 0);
+            measure_x(1, 1);
+            reset(2);
+            measure_all(&[1, 0]);
         }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
-        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
-        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
-        assert!(n10 == nr_shots);
-        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
-        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, X::new(), &[2]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0, 1], 0, X::new(), &[2]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[1, 0], 1, crate::gates::Swap::new(), &[1, 2]), Ok(()));
+        assert_eq!(circuit.open_qasm3(), Ok(String::from(
+r#"OPENQASM 3.0;
+include "stdgates.inc";
+qubit[3] q;
+bit[2] c;
+h q[0];
+CX q[0], q[1];
+barrier q[0], q[1];
+c[0] = measure q[0];
+h q[1];
+c[1] = measure q[1];
+reset q[2];
+c[1] = measure q[0];
+c[0] = measure q[1];
+if (c[0]) { x q[2]; }
+if (c == 0) { x q[2]; }
+if (c == 2) { CX q[1], q[2]; CX q[2], q[1]; CX q[1], q[2]; }
+"#)));
 
-        let mut circuit = circuit!(2, 6, {
-            h(0);
-            h(1);
-            peek(0, 0);
-            h(0);
-            peek(0, 1);
-            h(0);
-            peek(0, 2);
+        let circuit = circuit!(2, 2, {
+            x(0);
+            measure_all(&[0, 1]);
+            measure_all_basis(&[0, 1], Basis::Y);
         }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let mut n0 = [0; 2];
-        let mut n1 = [0; 2];
-        let mut n2 = [0; 2];
-        for (key, count) in hist
-        {
-            n0[key as usize & 1] += count;
-            n1[(key as usize >> 1) & 1] += count;
-            n2[(key as usize >> 2) & 1] += count;
-        }
-        assert!(n0.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.5, tol)
-        ));
-        assert_eq!(n1, [nr_shots, 0]);
-        assert!(n2.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.5, tol)
-        ));
+        assert_eq!(circuit.open_qasm3(), Ok(String::from(
+r#"OPENQASM 3.0;
+include "stdgates.inc";
+qubit[2] q;
+bit[2] c;
+x q[0];
+c = measure q;
+sdg q;
+h q;
+c = measure q;
+"#)));
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.peek(0, 0), Ok(()));
+        assert_eq!(circuit.open_qasm3(), Err(crate::error::Error::from(
+            crate::error::ExportError::ExportPeekInvalid("OpenQasm")
+        )));
     }
 
     #[test]
-    fn test_peek_basis()
+    fn test_open_qasm_global_phase()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
-
-        let mut circuit = circuit!(1, 3, {
-            peek_x(0, 0);
-            h(0);
-            peek_x(0, 1);
+        let mut circuit = circuit!(1, 1, {
             h(0);
-            peek_x(0, 2);
         }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
-        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
-        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
-        assert_eq!(n10, nr_shots);
-        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
-        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+        assert_eq!(circuit.add_gate(crate::gates::GlobalPhase::new(0.5), &[]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, crate::gates::GlobalPhase::new(0.5), &[]),
+            Ok(()));
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[1];
+creg b[1];
+h q[0];
+"#)));
+        assert_eq!(circuit.open_qasm3(), Ok(String::from(
+r#"OPENQASM 3.0;
+include "stdgates.inc";
+qubit[1] q;
+bit[1] c;
+h q[0];
+gphase(0.5);
+if (c == 1) { gphase(0.5); }
+"#)));
+    }
 
-        let mut circuit = circuit!(2, 6, {
-            peek_y(0, 0);
-            h(0);
-            peek_y(0, 1);
-            sdg(0);
-            peek_y(0, 2);
-        }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        // Results of first and second measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 1.
-        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
-        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
-        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
-        assert!(crate::stats::measurement_ok(n10, nr_shots, 0.5, tol));
-        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
-        assert_eq!(n20, 0);
+    #[test]
+    fn test_open_qasm_global_phase_composite()
+    {
+        let mut gate = crate::gates::Composite::new("Phased", 1);
+        gate.add_gate(crate::gates::H::new(), &[0]);
+        gate.add_gate(crate::gates::GlobalPhase::new(0.5), &[]);
+        gate.add_gate(crate::gates::X::new(), &[0]);
+
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.add_gate(gate.clone(), &[0]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, gate, &[0]), Ok(()));
+        let qasm = circuit.open_qasm().unwrap();
+        assert_eq!(qasm, String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[1];
+creg b[1];
+h q[0]; x q[0];
+if (b == 1) h q[0]; if (b == 1) x q[0];
+"#));
+        let res = Circuit::from_open_qasm(&qasm).unwrap();
+        assert_eq!(res.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[1];
+creg b[1];
+h q[0];
+x q[0];
+if (b == 1) h q[0];
+if (b == 1) x q[0];
+"#)));
+
+        let mut gate = crate::gates::Composite::new("phased", 1);
+        gate.add_gate(crate::gates::H::new(), &[0]);
+        gate.add_gate(crate::gates::GlobalPhase::new(0.5), &[]);
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.add_gate(gate, &[0]), Ok(()));
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+gate phased a0 { h a0; }
+qreg q[1];
+phased q[0];
+"#)));
     }
 
     #[test]
-    fn test_conditional()
+    fn test_open_qasm_definitions()
     {
-        let mut circuit = circuit!(2, 2, {
-            add_conditional_gate(&[0, 1], 1, X::new(), &[1]);
-            measure_all(&[0, 1]);
-        }).unwrap();
-        assert_eq!(circuit.execute(5), Ok(()));
-        assert_eq!(circuit.c_state, Some(array![0b00, 0b00, 0b00, 0b00, 0b00]));
+        use crate::export::OpenQasm;
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let custom = crate::gates::Custom::new("NOT", array![[z, o], [o, z]]);
+        let name = custom.open_qasm(&[String::from("a0")], &[0]).unwrap().replace(" a0", "");
+
+        let mut inc = crate::gates::Composite::new("inc2", 2);
+        inc.add_gate(CX::new(), &[0, 1]);
+        inc.add_gate(custom.clone(), &[1]);
+        let mut twice = crate::gates::Composite::new("twice", 2);
+        twice.add_gate(inc.clone(), &[0, 1]);
+        twice.add_gate(inc.clone(), &[1, 0]);
 
         let mut circuit = Circuit::new(2, 2);
-        circuit.q_state = Some(QuStateRepr::vector(2, 5));
-        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
-        circuit.add_conditional_gate(&[0, 1], 1, X::new(), &[1]).unwrap();
-        circuit.measure_all(&[0, 1]).unwrap();
-        assert_eq!(circuit.reexecute(), Ok(()));
-        assert_eq!(circuit.c_state, Some(array![0b10, 0b00, 0b00, 0b00, 0b00]));
+        assert_eq!(circuit.add_gate(custom.clone(), &[0]), Ok(()));
+        assert_eq!(circuit.add_gate(inc.clone(), &[1, 0]), Ok(()));
+        assert_eq!(circuit.add_gate(twice, &[0, 1]), Ok(()));
+        assert_eq!(circuit.add_gate(custom, &[1]), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0, 1], 2, inc, &[0, 1]), Ok(()));
+        let qasm = circuit.open_qasm().unwrap();
+        assert_eq!(qasm, format!(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+gate {0} a0 {{ u3(3.141592653589793, 0, -3.141592653589793) a0; }}
+gate inc2 a0, a1 {{ cx a0, a1; {0} a1; }}
+gate twice a0, a1 {{ inc2 a0, a1; inc2 a1, a0; }}
+qreg q[2];
+creg b[2];
+{0} q[0];
+inc2 q[1], q[0];
+twice q[0], q[1];
+{0} q[1];
+measure q -> b;
+if (b == 2) inc2 q[0], q[1];
+"#, name));
+
+        // The definitions are imported as composite gates
+        let imported = Circuit::from_open_qasm(&qasm).unwrap();
+        assert_eq!(imported.open_qasm(), Ok(qasm));
+
+        // Composite gates without a valid name are written out in full
+        let mut gate = crate::gates::Composite::new("Inc2", 2);
+        gate.add_gate(CX::new(), &[0, 1]);
+        gate.add_gate(X::new(), &[1]);
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.add_gate(gate, &[1, 0]), Ok(()));
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+cx q[1], q[0]; x q[0];
+"#)));
+    }
+
+    #[test]
+    fn test_open_qasm_with_decomposition()
+    {
+        use crate::gates::Gate;
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let not = crate::gates::Custom::new("NOT", array![[z, o], [o, z]]);
+        let cnot = crate::gates::Custom::new("CNOT", CX::new().matrix());
+
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.add_gate(not.clone(), &[1]), Ok(()));
+        assert_eq!(circuit.add_gate(cnot, &[1, 0]), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, not, &[1]), Ok(()));
+        let qasm = circuit.open_qasm_with_decomposition().unwrap();
+        assert!(!qasm.contains("custom"));
+        assert!(qasm.starts_with("OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\ncreg b[1];\nh q[0];\nu3(3.141592653589793, 0, -3.141592653589793) q[1];\n"));
+        assert!(qasm.ends_with("measure q[0] -> b[0];\nif (b == 1) u3(3.141592653589793, 0, -3.141592653589793) q[1];\n"));
+        assert_eq!(qasm.matches("cx q[1], q[0];").count(), 6);
+
+        // Circuits without custom gates are exported as is
+        let circuit = circuit!(2, 0, {
+            h(0);
+            cx(0, 1);
+        }).unwrap();
+        assert_eq!(circuit.open_qasm_with_decomposition(), circuit.open_qasm());
 
-        let mut circuit = Circuit::new(2, 2);
-        circuit.q_state = Some(QuStateRepr::vector(2, 5));
-        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
-        circuit.add_conditional_gate(&[0, 1], 2, X::new(), &[1]).unwrap();
-        circuit.measure_all(&[0, 1]).unwrap();
-        assert_eq!(circuit.reexecute(), Ok(()));
-        assert_eq!(circuit.c_state, Some(array![0b00, 0b10, 0b10, 0b00, 0b00]));
+        let mut circuit = Circuit::new(3, 0);
+        let ccnot = crate::gates::Custom::new("CCNOT", crate::gates::CCX::new().matrix());
+        assert_eq!(circuit.add_gate(ccnot, &[0, 1, 2]), Ok(()));
+        match circuit.open_qasm_with_decomposition()
+        {
+            Err(crate::error::Error::ExportError(
+                crate::error::ExportError::UndecomposedGate(desc, qasm))) => {
+                assert_eq!(desc, "CCNOT");
+                assert!(qasm.contains("// No decomposition for CCNOT: [[1+0i, 0+0i"));
+                assert!(qasm.ends_with("on q[0], q[1], q[2];\n"));
+            },
+            res => { panic!("Unexpected result {:?}", res); }
+        }
+    }
 
-        let mut circuit = Circuit::new(2, 2);
-        circuit.q_state = Some(QuStateRepr::vector(2, 5));
-        circuit.c_state = Some(array![0b01, 0b10, 0b10, 0b11, 0b00]);
-        circuit.add_conditional_gate(&[1], 1, X::new(), &[0]).unwrap();
-        circuit.measure_all(&[0, 1]).unwrap();
-        assert_eq!(circuit.reexecute(), Ok(()));
-        assert_eq!(circuit.c_state, Some(array![0b00, 0b01, 0b01, 0b01, 0b00]));
+    #[test]
+    fn test_to_qsharp()
+    {
+        let mut circuit = circuit!(3, 2, {
+            h(0);
+            rz(0.5, 1);
+            cx(0, 1);
+            s(2);
+            reset(2);
+            barrier(&[0, 1]);
+            measure_x(0, 0);
+            measure(1, 1);
+        }).unwrap();
+        assert_eq!(circuit.add_gate(crate::gates::CZ::new(), &[1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[2]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0, 1], 1, X::new(), &[2]), Ok(()));
+        assert_eq!(circuit.to_qsharp(), Ok(String::from(
+r#"operation CircuitOp(q : Qubit[]) : Result[] {
+    mutable b = [Zero, size = 2];
+    H(q[0]);
+    Rz(0.5, q[1]);
+    CNOT(q[0], q[1]);
+    S(q[2]);
+    Reset(q[2]);
+    H(q[0]);
+    set b w/= 0 <- M(q[0]);
+    set b w/= 1 <- M(q[1]);
+    Controlled Z([q[1]], q[2]);
+    T(q[2]);
+    if (b[0] == One and b[1] == Zero) { X(q[2]); }
+    return b;
+}
+"#)));
+
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.peek(0, 0), Ok(()));
+        assert_eq!(circuit.to_qsharp(), Err(crate::error::Error::from(
+            crate::error::ExportError::ExportPeekInvalid("Q#")
+        )));
     }
 
     #[test]
-    fn test_measure_all()
+    fn test_c_qasm()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
+        let circuit = circuit!(3, 3, {
+            x(0);
+            cx(0, 1);
+            cx(1, 0);
+            cx(0, 1);
+            measure(0, 0);
+            measure_x(1, 1);
+            measure_y(2, 2);
+        }).unwrap();
+        assert_eq!(circuit.c_qasm(), Ok(String::from(
+r#"version 1.0
+qubits 3
+x q[0]
+cnot q[0], q[1]
+cnot q[1], q[0]
+cnot q[0], q[1]
+measure q[0]
+measure_x q[1]
+measure_y q[2]
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 2, {
             x(0);
+            h(1);
             measure_all(&[0, 1]);
+            reset_all();
+            measure_all_basis(&[0, 1], Basis::X);
+            reset(1);
+            measure_all_basis(&[0, 1], Basis::Y);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+        assert_eq!(circuit.c_qasm(), Ok(String::from(
+r#"version 1.0
+qubits 2
+x q[0]
+h q[1]
+measure_all
+prep_z q[0]
+prep_z q[1]
+h q[0]
+h q[1]
+measure_all
+prep_z q[1]
+sdag q[0]
+h q[0]
+sdag q[1]
+h q[1]
+measure_all
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 2, {
             x(0);
-            measure_all(&[1, 0]);
+            measure_all(&[0, 1]);
+            add_conditional_gate(&[0, 1], 1, X::new(), &[0]);
+            add_conditional_gate(&[], 1, X::new(), &[1]);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, 0, nr_shots, 0]);
+        assert_eq!(circuit.c_qasm(), Ok(String::from(
+r#"version 1.0
+qubits 2
+x q[0]
+measure_all
+not b[1]
+c-x b[0], b[1], q[0]
+not b[1]
+x q[1]
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
-            h(0);
-            h(1);
-            measure_all(&[0, 1]);
+        let circuit = circuit!(2, 2, {
+            measure(0, 1);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert!(hist.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+        // c-Qasm only allows for measuring to the classical bit with the same index
+        assert!(matches!(circuit.c_qasm(), Err(_)));
     }
 
     #[test]
-    fn test_measure_all_basis()
+    fn test_tket_json()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
-
-        let mut circuit = circuit!(2, 2, {
+        let circuit = circuit!(2, 2, {
             h(0);
-            h(1);
-            measure_all_basis(&[0, 1], Basis::X);
+            cx(0, 1);
+            rz(::std::f64::consts::FRAC_PI_2, 1);
+            barrier(&[0, 1]);
+            measure(0, 0);
+            measure_x(1, 1);
+            add_conditional_gate(&[0, 1], 2, X::new(), &[0]);
+            reset_to(1, true);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![nr_shots, 0, 0, 0]);
+        assert_eq!(circuit.tket_json(), Ok(String::from(
+r#"{
+  "phase": "0.0",
+  "qubits": [["q", [0]], ["q", [1]]],
+  "bits": [["c", [0]], ["c", [1]]],
+  "commands": [
+    {"op": {"type": "H"}, "args": [["q", [0]]]},
+    {"op": {"type": "CX"}, "args": [["q", [0]], ["q", [1]]]},
+    {"op": {"type": "Rz", "params": ["0.5"]}, "args": [["q", [1]]]},
+    {"op": {"type": "Barrier", "signature": ["Q", "Q"]}, "args": [["q", [0]], ["q", [1]]]},
+    {"op": {"type": "Measure"}, "args": [["q", [0]], ["c", [0]]]},
+    {"op": {"type": "H"}, "args": [["q", [1]]]},
+    {"op": {"type": "Measure"}, "args": [["q", [1]], ["c", [1]]]},
+    {"op": {"type": "Conditional", "conditional": {"op": {"type": "X"}, "width": 2, "value": 2}}, "args": [["c", [0]], ["c", [1]], ["q", [0]]]},
+    {"op": {"type": "Reset"}, "args": [["q", [1]]]},
+    {"op": {"type": "X"}, "args": [["q", [1]]]}
+  ],
+  "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]]]
+}
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
-            x(0);
-            h(0);
-            h(1);
-            measure_all_basis(&[0, 1], Basis::X);
+        let circuit = circuit!(3, 0, {
+            add_gate(crate::gates::CCX::new(), &[2, 0, 1]);
+            add_gate(crate::gates::CU3::new(::std::f64::consts::PI, 0.0, -::std::f64::consts::FRAC_PI_2), &[1, 2]);
+            add_gate(crate::gates::Composite::from_string("G", "H 0; V 1").unwrap(), &[1, 0]);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+        assert_eq!(circuit.tket_json(), Ok(String::from(
+r#"{
+  "phase": "0.0",
+  "qubits": [["q", [0]], ["q", [1]], ["q", [2]]],
+  "bits": [],
+  "commands": [
+    {"op": {"type": "CCX"}, "args": [["q", [2]], ["q", [0]], ["q", [1]]]},
+    {"op": {"type": "CU3", "params": ["1", "0", "-0.5"]}, "args": [["q", [1]], ["q", [2]]]},
+    {"op": {"type": "H"}, "args": [["q", [1]]]},
+    {"op": {"type": "SX"}, "args": [["q", [0]]]}
+  ],
+  "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]], [["q", [2]], ["q", [2]]]]
+}
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
-            x(0);
-            h(0);
-            h(1);
-            add_gate(S::new(), &[0]);
-            add_gate(S::new(), &[1]);
-            measure_all_basis(&[0, 1], Basis::Y);
+        let circuit = circuit!(1, 1, {
+            peek(0, 0);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, nr_shots, 0, 0]);
+        assert_eq!(circuit.tket_json(), Err(crate::error::Error::from(
+            crate::error::ExportError::ExportPeekInvalid("TKET"))));
 
-        let mut circuit = circuit!(2, 2, {
-            measure_all_basis(&[0, 1], Basis::Y);
+        let circuit = circuit!(2, 0, {
+            add_gate(crate::gates::CT::new(), &[0, 1]);
         }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert!(hist.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+        assert_eq!(circuit.tket_json(), Err(crate::error::Error::from(
+            crate::error::ExportError::NotImplemented("TKET", String::from("CT")))));
     }
 
     #[test]
-    fn test_peek_all()
+    fn test_instructions()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
+        use crate::instruction::Instruction;
+        use rand::SeedableRng;
 
-        let mut circuit = circuit!(1, 3, {
-            h(0);
-            peek_all(&[0]);
-            h(0);
-            peek_all(&[1]);
+        let mut circuit = circuit!(3, 2, {
             h(0);
-            peek_all(&[2]);
+            cx(0, 1);
+            rz(0.25, 2);
+            add_gate(crate::gates::CU1::new(-0.5), &[2, 0]);
+            apply_all(X::new());
+            barrier(&[0, 2]);
+            measure(0, 0);
+            peek_x(1, 1);
+            add_conditional_gate(&[0, 1], 2, CY::new(), &[2, 0]);
+            reset_to(1, true);
+            reset_all();
+            measure_all_basis(&[1, 0, 0], Basis::Y);
         }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
-        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
-        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
-        assert!(n10 == nr_shots);
-        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
-        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+        let instructions = circuit.to_instructions();
+        assert_eq!(instructions, vec![
+            Instruction::H(0),
+            Instruction::CX(0, 1),
+            Instruction::RZ(0.25, 2),
+            Instruction::CU1(-0.5, 2, 0),
+            Instruction::X(0),
+            Instruction::X(1),
+            Instruction::X(2),
+            Instruction::Barrier(vec![0, 2]),
+            Instruction::Measure(0, 0, Basis::Z),
+            Instruction::Peek(1, 1, Basis::X),
+            Instruction::ConditionalGate(vec![0, 1], 2, Box::new(Instruction::CY(2, 0))),
+            Instruction::ResetTo(1, true),
+            Instruction::ResetAll,
+            Instruction::MeasureAll(vec![1, 0, 0], Basis::Y)
+        ]);
+
+        let mut copy = Circuit::from_instructions(&instructions, 3, 2).unwrap();
+        assert_eq!(copy.to_instructions(), instructions);
+        assert_eq!(copy.open_qasm(), circuit.open_qasm());
+
+        // Running with the same random number sequence should give the same
+        // results
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1234);
+        assert_eq!(circuit.execute_with_rng(100, &mut rng), Ok(()));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1234);
+        assert_eq!(copy.execute_with_rng(100, &mut rng), Ok(()));
+        assert_eq!(copy.histogram(), circuit.histogram());
 
-        let mut circuit = circuit!(2, 6, {
-            h(0);
-            h(1);
-            peek_all(&[0, 1]);
-            h(0);
-            peek_all(&[2, 3]);
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let circuit = circuit!(2, 1, {
+            add_gate(crate::gates::Custom::new("iSWAP",
+                array![[o, z, z, z], [z, z, i, z], [z, i, z, z], [z, z, z, o]]), &[1, 0]);
+            add_noise(crate::gates::Depolarizing::new(0.2), &[1]);
+            measure_expectation(&PauliString::new(&[PauliOp::Z, PauliOp::X]), 0);
+        }).unwrap();
+        let instructions = circuit.to_instructions();
+        assert_eq!(instructions[0], Instruction::Custom(vec![
+            vec![o, z, z, z], vec![z, z, i, z], vec![z, i, z, z], vec![z, z, z, o]
+        ], vec![1, 0]));
+        assert!(matches!(instructions[1], Instruction::Noise(ref ops, ref bits)
+            if ops.len() == 4 && bits == &[1]));
+        assert_eq!(instructions[2],
+            Instruction::ExpectationMeasure(vec![PauliOp::Z, PauliOp::X], 0));
+        let copy = Circuit::from_instructions(&instructions, 2, 1).unwrap();
+        assert_eq!(copy.to_instructions(), instructions);
+        let rho = circuit.density_matrix().unwrap();
+        assert_eq!(copy.density_matrix().unwrap().matrix(), rho.matrix());
+
+        assert!(matches!(Circuit::from_instructions(&instructions, 1, 1),
+            Err(crate::error::Error::InvalidQBit(1))));
+        assert!(matches!(Circuit::from_instructions(&instructions, 2, 0),
+            Err(crate::error::Error::InvalidCBit(0))));
+    }
+
+    #[test]
+    fn test_latex()
+    {
+        let circuit = circuit!(2, 2, {
             h(0);
-            peek_all(&[4, 5]);
+            x(1);
+            measure(0, 0);
+            measure_x(1, 1);
+            add_conditional_gate(&[0, 1], 2, X::new(), &[0]);
+            reset_all();
+            measure_all_basis(&[1, 0], Basis::Y);
+            reset(0);
+            measure_y(1, 0);
+            barrier(&[1]);
         }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let mut n0 = [0; 4];
-        let mut n1 = [0; 4];
-        let mut n2 = [0; 4];
-        for (key, count) in hist
+
+        assert_eq!(circuit.latex(), Ok(String::from(
+r#"\Qcircuit @C=1em @R=.7em {
+    \lstick{\ket{0}} & \gate{H} & \meter & \qw & \targ & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \meterB{Y} & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \qw & \qw & \qw \\
+    \lstick{\ket{0}} & \gate{X} & \qw & \meterB{X} & \qw & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \qw & \meterB{Y} & \meterB{Y} & \qw \barrier{0} & \qw \\
+    \lstick{0} & \cw & \cw \cwx[-2] & \cw & \cctrlo{-2} & \cw & \cw & \cw \cwx[-1] & \cw \cwx[-1] & \cw & \cw \\
+    \lstick{0} & \cw & \cw & \cw \cwx[-2] & \cctrl{-1} & \cw & \cw \cwx[-3] & \cw & \cw & \cw & \cw \\
+}
+"#)));
+    }
+
+    #[test]
+    fn test_resource_estimate()
+    {
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Tdg::new(), &[1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CCX::new(), &[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.u1(0.3, 2), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.reset(1), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+        circuit.reset_all();
+
+        let estimate = circuit.resource_estimate();
+        assert_eq!(estimate, super::ResourceEstimate
+        {
+            total_gates: 7,
+            single_qubit_gates: 5,
+            two_qubit_gates: 1,
+            t_gates: 3,
+            clifford_gates: 2,
+            measurements: 4,
+            resets: 4,
+            depth: 6,
+            t_depth: 2
+        });
+
+        let table = format!("{}", estimate);
+        assert_eq!(table.lines().count(), 9);
+        assert!(table.starts_with("Total gates                7\n"));
+        assert!(table.contains("T depth                    2\n"));
+
+        let estimate = Circuit::new(2, 0).resource_estimate();
+        assert_eq!(estimate.total_gates, 0);
+        assert_eq!(estimate.depth, 0);
+        assert_eq!(estimate.t_depth, 0);
+    }
+
+    #[test]
+    fn test_draw_ascii()
+    {
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.draw_ascii(),
+r#"q0: -[H]-[X]-[M]-
+
+q1: -[H]---------
+"#);
+
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.add_gate(CY::new(), &[1, 0]), Ok(()));
+        assert_eq!(circuit.measure_basis(2, 0, Basis::X), Ok(()));
+        assert_eq!(circuit.draw_ascii(),
+r#"q0: --[H]---[CX:0]--------[CY:1]-
+              |             |
+q1: ----------|-----[X]---[CY:0]-
+              |
+q2: --------[CX:1]--[MX]---------
+"#);
+
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 2]), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(circuit.draw_ascii(),
+r#"q0: -[H]--|---|------
+          |
+q1: ------|--[X]-----
+          |
+q2: ------|---|--[H]-
+"#);
+    }
+
+    #[test]
+    fn test_is_stabilizer()
+    {
+        let mut circuit = Circuit::new(100, 1);
+        for i in 0..99
+        {
+            assert_eq!(circuit.h(i), Ok(()));
+            assert_eq!(circuit.cx(i, i+1), Ok(()));
+            assert_eq!(circuit.x(i+1), Ok(()));
+        }
+        assert!(circuit.is_stabilizer_circuit());
+
+        assert_eq!(circuit.measure(55, 0), Ok(()));
+        assert!(circuit.is_stabilizer_circuit());
+
+        assert_eq!(circuit.add_gate(CY::new(), &[99, 0]), Ok(()));
+        assert!(circuit.is_stabilizer_circuit());
+
+        assert_eq!(circuit.u1(0.99, 5), Ok(()));
+        assert!(!circuit.is_stabilizer_circuit());
+    }
+
+    #[test]
+    fn test_to_stabilizer_circuit()
+    {
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.s(1), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Sdg::new(), &[2]), Ok(()));
+        assert_eq!(circuit.measure(1, 0), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+        let stab = circuit.to_stabilizer_circuit().unwrap();
+        assert_eq!(stab.nr_bits(), 3);
+        assert_eq!(stab.ops().len(), 8);
+        let res = stab.execute();
+        assert_eq!(res.outcomes().len(), 4);
+        assert_eq!(res.outcomes()[0], res.outcomes()[1]);
+        assert_eq!(res.outcomes()[1], res.outcomes()[2]);
+        assert!(!res.outcomes()[3]);
+
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Tdg::new(), &[1]), Ok(()));
+        match circuit.to_stabilizer_circuit()
         {
-            n0[key as usize & 0x03] += count;
-            n1[(key as usize >> 2) & 0x03] += count;
-            n2[(key as usize >> 4) & 0x03] += count;
+            Err(err) => {
+                assert_eq!(err, crate::error::Error::NotAStabilizer(String::from("T")));
+                assert!(format!("{}", err).starts_with("T "));
+            },
+            Ok(_) => panic!("Circuit with T gate converted to stabilizer circuit")
         }
-        assert!(n0.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
-        assert_eq!(n1[1], 0);
-        assert_eq!(n1[3], 0);
-        assert!(crate::stats::measurement_ok(n1[0], nr_shots, 0.5, tol));
-        assert!(crate::stats::measurement_ok(n1[2], nr_shots, 0.5, tol));
-        assert!(n2.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
     }
 
     #[test]
-    fn test_peek_all_basis()
+    fn test_cost()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.total_cost(), 0.0);
+        assert!(circuit.cost_breakdown().is_empty());
+        assert_eq!(circuit.critical_path_cost(), 0.0);
 
-        let mut circuit = circuit!(1, 3, {
-            peek_all_basis(&[0], Basis::X);
-            h(0);
-            peek_all_basis(&[1], Basis::X);
-            h(0);
-            peek_all_basis(&[2], Basis::X);
-        }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        // Results of first and third measurement should be approximately equally
-        // distributed over 0 and 1, second should be pure 0.
-        let n00 = hist[0] + hist[2] + hist[4] + hist[6];
-        assert!(crate::stats::measurement_ok(n00, nr_shots, 0.5, tol));
-        let n10 = hist[0] + hist[1] + hist[4] + hist[5];
-        assert!(n10 == nr_shots);
-        let n20 = hist[0] + hist[1] + hist[2] + hist[3];
-        assert!(crate::stats::measurement_ok(n20, nr_shots, 0.5, tol));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+
+        let (h, x, cx, t) = (H::cost(), crate::gates::U3::cost(), CX::cost(),
+            crate::gates::U1::cost());
+        assert_eq!(circuit.total_cost(), 2.0*h + 2.0*x + cx + t);
+
+        let breakdown = circuit.cost_breakdown();
+        assert_eq!(breakdown.len(), 4);
+        assert_eq!(breakdown["H"], 2.0*h);
+        assert_eq!(breakdown["X"], 2.0*x);
+        assert_eq!(breakdown["CX"], cx);
+        assert_eq!(breakdown["T"], t);
+
+        // Longest path: H on qubit 0 or 1, then CX and T on qubit 1
+        assert_eq!(circuit.critical_path_cost(), h + cx + t);
+        assert_eq!(circuit.cx(1, 2), Ok(()));
+        assert_eq!(circuit.critical_path_cost(), h + cx + t + cx);
+        assert_eq!(circuit.barrier(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.critical_path_cost(), h + cx + t + cx + h);
+    }
 
-        let mut circuit = circuit!(2, 6, {
-            h(0);
-            h(1);
-            peek_all_basis(&[0, 1], Basis::Y);
-            s(1);
-            peek_all_basis(&[2, 3], Basis::Y);
-            s(0);
-            peek_all_basis(&[4, 5], Basis::Y);
-        }).unwrap();
-        assert_eq!(circuit.execute(1024), Ok(()));
-        let hist = circuit.histogram().unwrap();
-        // Results of first measurement should be approximately equally
-        // distributed over 0 and 1 for both qubits, second should be pure 0
-        // for second qubit, third pure |00⟩.
-        let mut n0 = [0; 4];
-        let mut n1 = [0; 4];
-        let mut n2 = [0; 4];
-        for (key, count) in hist
+    #[test]
+    fn test_depth()
+    {
+        let mut circuit = Circuit::new(4, 1);
+        assert_eq!(circuit.depth(), 0);
+        assert_eq!(circuit.gate_count(), 0);
+
+        // Parallel single qubit gates
+        for bit in 0..4
         {
-            n0[key as usize & 0x03] += count;
-            n1[(key as usize >> 2) & 0x03] += count;
-            n2[(key as usize >> 4) & 0x03] += count;
+            assert_eq!(circuit.h(bit), Ok(()));
         }
-        assert!(n0.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
-        assert_eq!(n1[2], 0);
-        assert_eq!(n1[3], 0);
-        assert!(crate::stats::measurement_ok(n1[0], nr_shots, 0.5, tol));
-        assert!(crate::stats::measurement_ok(n1[1], nr_shots, 0.5, tol));
-        assert_eq!(n2, [nr_shots, 0, 0, 0]);
+        assert_eq!(circuit.depth(), 1);
+        assert_eq!(circuit.gate_count(), 4);
+
+        // Linear chain of CNOTs
+        for bit in 0..3
+        {
+            assert_eq!(circuit.cx(bit, bit+1), Ok(()));
+        }
+        assert_eq!(circuit.depth(), 4);
+        assert_eq!(circuit.gate_count(), 7);
+
+        // Measurements do not add to the depth, conditional gates do
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.depth(), 4);
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, crate::gates::X::new(), &[0]), Ok(()));
+        assert_eq!(circuit.depth(), 4);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.depth(), 5);
+        assert_eq!(circuit.gate_count(), 9);
+
+        // A barrier synchronizes the qubits
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.depth(), 2);
+        assert_eq!(circuit.barrier(&[0, 1]), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(circuit.depth(), 3);
+        assert_eq!(circuit.gate_count(), 5);
     }
 
     #[test]
-    fn test_histogram()
+    fn test_satisfies_topology()
     {
-        let nr_shots = 4096;
-        let tol = 1.0e-5;
+        let topology = crate::topology::Topology::linear(3);
 
-        let mut circuit = circuit!(2, 2, {
-            add_gate(H::new(), &[0]);
-            add_gate(H::new(), &[1]);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.satisfies_topology(topology.edges()), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(1, 0), Ok(()));
+        assert_eq!(circuit.cx(1, 2), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.satisfies_topology(topology.edges()), Ok(()));
+        assert!(circuit.is_native_for(&topology, &["h", "CX"]));
+        assert!(!circuit.is_native_for(&topology, &["CX"]));
+        assert!(!circuit.is_native_for(&crate::topology::Topology::linear(2), &["H", "CX"]));
+
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CCX::new(), &[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.satisfies_topology(topology.edges()), Err(vec![(5, 0, 2), (6, 0, 2)]));
+        assert!(!circuit.is_native_for(&topology, &["H", "CX", "CCX"]));
+
+        let topology = crate::topology::Topology::complete(3);
+        assert_eq!(circuit.satisfies_topology(topology.edges()), Ok(()));
+        assert!(circuit.is_native_for(&topology, &["H", "CX", "CCX"]));
 
-        let hist = circuit.histogram().unwrap();
-        // With this many shots, we expect all keys to be present
-        let mut keys: Vec<&u64> = hist.keys().collect();
-        keys.sort();
-        assert_eq!(keys, vec![&0, &1, &2, &3]);
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.rz(0.5, 0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert!(circuit.is_native_for(&topology, &["RZ", "CX"]));
+        assert!(!circuit.is_native_for(&topology, &["RX", "CX"]));
+    }
 
-        assert_eq!(hist.values().sum::<usize>(), nr_shots);
-        assert!(hist.values().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+    #[test]
+    fn test_structural_hash()
+    {
+        let circuit = |theta: f64, phi: f64, p: f64| {
+            let mut circuit = Circuit::new(2, 2);
+            circuit.h(0).unwrap();
+            circuit.rx(theta, 1).unwrap();
+            circuit.add_gate(crate::gates::CRZ::new(phi), &[0, 1]).unwrap();
+            circuit.add_noise(crate::gates::Depolarizing::new(p), &[1]).unwrap();
+            circuit.measure_all(&[0, 1]).unwrap();
+            circuit
+        };
+
+        let hash = circuit(0.5, 0.3, 0.01).structural_hash();
+        assert_eq!(circuit(0.5, 0.3, 0.01).structural_hash(), hash);
+        assert_eq!(circuit(-1.5, 2.0, 0.2).structural_hash(), hash);
+
+        let mut other = circuit(0.5, 0.3, 0.01);
+        other.x(0).unwrap();
+        assert_ne!(other.structural_hash(), hash);
+
+        // Different qubits
+        let mut other = Circuit::new(2, 2);
+        other.h(1).unwrap();
+        other.rx(0.5, 1).unwrap();
+        other.add_gate(crate::gates::CRZ::new(0.3), &[0, 1]).unwrap();
+        other.add_noise(crate::gates::Depolarizing::new(0.01), &[1]).unwrap();
+        other.measure_all(&[0, 1]).unwrap();
+        assert_ne!(other.structural_hash(), hash);
+
+        // Different gate type
+        let mut other = Circuit::new(2, 2);
+        other.h(0).unwrap();
+        other.ry(0.5, 1).unwrap();
+        other.add_gate(crate::gates::CRZ::new(0.3), &[0, 1]).unwrap();
+        other.add_noise(crate::gates::Depolarizing::new(0.01), &[1]).unwrap();
+        other.measure_all(&[0, 1]).unwrap();
+        assert_ne!(other.structural_hash(), hash);
+
+        assert_ne!(Circuit::new(2, 0).structural_hash(), Circuit::new(3, 0).structural_hash());
+        let mut measured = Circuit::new(1, 1);
+        measured.measure_x(0, 0).unwrap();
+        let mut peeked = Circuit::new(1, 1);
+        peeked.peek_x(0, 0).unwrap();
+        assert_ne!(measured.structural_hash(), peeked.structural_hash());
     }
 
     #[test]
-    fn test_histogram_vec()
+    fn test_get_unitary()
     {
-        let nr_shots = 4096;
-        let tol = 1.0e-5;
+        use crate::gates::Gate;
 
-        let mut circuit = circuit!(2, 2, {
-            add_gate(H::new(), &[0]);
-            add_gate(H::new(), &[1]);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
 
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist.iter().sum::<usize>(), nr_shots);
-        assert!(hist.iter().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1]), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_complex_matrix_eq!(&circuit.get_unitary().unwrap(), &array![
+            [x, z,  x,  z],
+            [z, x,  z,  x],
+            [z, x,  z, -x],
+            [x, z, -x,  z]
+        ]);
+
+        // Gates on non-adjacent qubits, in reverse order
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.cx(2, 0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+        let expected = crate::gates::Composite::from_circuit("U", &circuit).unwrap().matrix();
+        assert_complex_matrix_eq!(&circuit.get_unitary().unwrap(), &expected);
+
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.get_unitary(),
+            Err(crate::error::Error::NotAGate(String::from("measurement"))));
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.reset(0), Ok(()));
+        assert_eq!(circuit.get_unitary(),
+            Err(crate::error::Error::NotAGate(String::from("reset"))));
     }
 
     #[test]
-    fn test_histogram_string()
+    fn test_inverse()
     {
-        let nr_shots = 4096;
-        let tol = 1.0e-5;
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+        assert_eq!(circuit.u3(0.3, -1.2, 0.5, 2), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::ISWAP::new(), &[1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CRZ::new(0.4), &[2, 0]), Ok(()));
+
+        let inverse = circuit.inverse().unwrap();
+        assert_eq!(inverse.nr_qbits(), 3);
+        assert_eq!(inverse.nr_cbits(), 1);
+        assert_eq!(inverse.ops.len(), circuit.ops.len());
+        assert!(matches!(inverse.ops[2], CircuitOp::Barrier(_)));
+        match inverse.ops[1]
+        {
+            CircuitOp::Gate(ref gate, ref bits) => {
+                assert_eq!(gate.description(), "iSWAP†");
+                assert_eq!(bits, &vec![1, 2]);
+            },
+            // LCOV_EXCL_START
+            _ => panic!("Operation is not a gate")
+            // LCOV_EXCL_STOP
+        }
 
-        let mut circuit = circuit!(2, 2, {
-            add_gate(H::new(), &[0]);
-            add_gate(H::new(), &[1]);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let product = inverse.get_unitary().unwrap().dot(&circuit.get_unitary().unwrap());
+        assert_complex_matrix_eq!(&product, &crate::cmatrix::CMatrix::eye(8));
 
-        let hist = circuit.histogram_string().unwrap();
-        // With this many shots, we expect all keys to be present
-        let mut keys: Vec<&String> = hist.keys().collect();
-        keys.sort();
-        assert_eq!(keys, vec!["00", "01", "10", "11"]);
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert!(matches!(circuit.inverse(), Err(crate::error::Error::NotAGate(_))));
+    }
 
-        assert_eq!(hist.values().sum::<usize>(), nr_shots);
-        assert!(hist.values().all(
-            |&count| crate::stats::measurement_ok(count, nr_shots, 0.25, tol)
-        ));
+    #[test]
+    fn test_inverse_open_qasm()
+    {
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.add_gate(crate::gates::CCX::new(), &[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CRY::new(0.3), &[0, 1]), Ok(()));
+
+        let inverse = circuit.inverse().unwrap();
+        let qasm = inverse.open_qasm().unwrap();
+        assert!(qasm.ends_with("qreg q[3];\ncx q[0], q[1]; u3(--0.3/2, 0, 0) q[1]; cx q[0], q[1]; u3(-0.3/2, 0, 0) q[1];\nccx q[0], q[1], q[2];\n"), "{}", qasm);
     }
 
     #[test]
-    fn test_reset()
+    fn test_qft_layer()
     {
-        let nr_shots = 1024;
-        let tol = 1.0e-5;
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.qft_layer(0, &[1, 2]), Ok(()));
+        assert_eq!(circuit.qft_layer(1, &[2]), Ok(()));
+        assert_eq!(circuit.qft_layer(2, &[]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Swap::new(), &[0, 2]), Ok(()));
+        assert_eq!(circuit.ops.len(), 7);
+        assert_eq!(circuit.c_qasm(), Ok(String::from(
+r#"version 1.0
+qubits 3
+h q[0]
+crk q[1], q[0], 2
+crk q[2], q[0], 3
+h q[1]
+crk q[2], q[1], 2
+h q[2]
+swap q[0], q[2]
+"#)));
 
-        let mut circuit = circuit!(2, 2, {
-            h(0);
-            z(0);
-            reset(0);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![nr_shots, 0, 0, 0]);
+        let mut expected = Circuit::new(3, 0);
+        assert_eq!(crate::algorithms::qft_approx(&mut expected, &[0, 1, 2], 8, 0.0), Ok(()));
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &circuit_unitary(&expected));
 
-        let mut circuit = circuit!(2, 2, {
-            h(0);
-            z(0);
-            x(1);
-            reset(0);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist, vec![0, 0, nr_shots, 0]);
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.controlled_phase_rotation(1, 0, 2),
+            Err(crate::error::Error::InvalidQBit(2)));
+    }
 
-        let mut circuit = circuit!(2, 2, {
-            h(0);
-            z(0);
-            h(1);
-            reset(0);
-            measure(0, 0);
-            measure(1, 1);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert!(crate::stats::measurement_ok(hist[0], nr_shots, 0.5, tol));
-        assert_eq!(hist[1], 0);
-        assert!(crate::stats::measurement_ok(hist[2], nr_shots, 0.5, tol));
-        assert_eq!(hist[3], 0);
+    /// Compute the unitary transformation performed by the gates in `circuit`
+    fn circuit_unitary(circuit: &Circuit) -> crate::cmatrix::CMatrix
+    {
+        let mut res = crate::cmatrix::CMatrix::eye(1 << circuit.nr_qbits);
+        for op in circuit.ops.iter()
+        {
+            if let CircuitOp::Gate(ref gate, ref bits) = *op
+            {
+                crate::gates::apply_gate_mat_slice(res.view_mut(), gate.as_gate(),
+                    bits, circuit.nr_qbits);
+            }
+        }
+        res
     }
 
     #[test]
-    fn test_reset_all()
+    fn test_repeat()
     {
-        let nr_shots = 1024;
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
 
-        let mut circuit = circuit!(5, 5, {
-            h(0);
-            z(0);
-            x(4);
-            h(3);
-            reset_all();
-            measure_all(&[0, 1, 2, 3, 4]);
-        }).unwrap();
-        assert_eq!(circuit.execute(nr_shots), Ok(()));
-        let hist = circuit.histogram_vec().unwrap();
-        assert_eq!(hist[0], nr_shots);
-        assert!(hist[1..].iter().all(|&c| c == 0));
+        // Each repetition flips the qubit again
+        for n in 1..4
+        {
+            let mut repeated = circuit.repeat(n);
+            assert_eq!(repeated.nr_qbits(), 2);
+            assert_eq!(repeated.nr_cbits(), 1);
+            assert_eq!(repeated.ops.len(), 2 * n);
+            assert_eq!(repeated.execute(10), Ok(()));
+            let expected = if n % 2 == 0 { vec![10, 0] } else { vec![0, 10] };
+            assert_eq!(repeated.histogram_vec(), Ok(expected));
+        }
+
+        let mut repeated = circuit.repeat_with_reset(2);
+        assert_eq!(repeated.ops.len(), 5);
+        assert!(matches!(repeated.ops[2], CircuitOp::ResetAll));
+        assert_eq!(repeated.execute(10), Ok(()));
+        assert_eq!(repeated.histogram_vec(), Ok(vec![0, 10]));
+
+        assert!(circuit.repeat(0).ops.is_empty());
+        assert!(circuit.repeat_with_reset(0).ops.is_empty());
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        for n in 0..5
+        {
+            assert_eq!(circuit.repeat(n).depth(), 2 * n);
+        }
+    }
+
+    #[test]
+    fn test_reversed()
+    {
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(circuit.cx(2, 0), Ok(()));
+        assert_eq!(circuit.z(1), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+
+        let reversed = circuit.reversed();
+        assert_eq!(reversed.nr_qbits(), 3);
+        assert_eq!(reversed.ops.len(), circuit.ops.len());
+        for (op0, op1) in circuit.ops.iter().rev().zip(reversed.ops.iter())
+        {
+            match (op0, op1)
+            {
+                (CircuitOp::Gate(g0, b0), CircuitOp::Gate(g1, b1)) => {
+                    assert_eq!(g0.description(), g1.description());
+                    assert_eq!(b0, b1);
+                },
+                // LCOV_EXCL_START
+                _ => panic!("Unexpected operation in reversed circuit")
+                // LCOV_EXCL_STOP
+            }
+        }
+
+        // For real symmetric gates, the reversed circuit implements the transpose
+        let u = circuit_unitary(&circuit);
+        let ur = circuit_unitary(&reversed);
+        assert_complex_matrix_eq!(&ur, &u.t(), 1.0e-14);
+    }
+
+    #[test]
+    fn test_echo_sequence()
+    {
+        let mut other = Circuit::new(2, 0);
+        assert_eq!(other.h(0), Ok(()));
+        assert_eq!(other.cx(0, 1), Ok(()));
+        assert_eq!(other.z(1), Ok(()));
+
+        let mut circuit = Circuit::new(2, 1);
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.echo_sequence(&other), Ok(()));
+        let descs: Vec<&str> = circuit.ops.iter().map(|op| {
+            match *op
+            {
+                CircuitOp::Gate(ref gate, _) => gate.description(),
+                // LCOV_EXCL_START
+                _ => panic!("Unexpected operation in echo sequence")
+                // LCOV_EXCL_STOP
+            }
+        }).collect();
+        assert_eq!(descs, vec!["X", "H", "CX", "Z", "Z", "CX", "H"]);
+
+        // H, CX, Z, Z, CX, H cancels
+        let u = circuit_unitary(&circuit);
+        let mut ux = Circuit::new(2, 1);
+        assert_eq!(ux.x(1), Ok(()));
+        assert_complex_matrix_eq!(&u, &circuit_unitary(&ux), 1.0e-14);
+
+        let big = Circuit::new(3, 0);
+        assert_eq!(circuit.echo_sequence(&big), Err(crate::error::Error::InvalidQBit(2)));
+        let big = Circuit::new(2, 2);
+        assert_eq!(circuit.echo_sequence(&big), Err(crate::error::Error::InvalidCBit(1)));
     }
 
     #[test]
-    fn test_open_qasm()
+    fn test_compose()
     {
-        let circuit = circuit!(2, 2, {
-            x(0);
-            cx(0, 1);
-            barrier(&[0, 1]);
-            cx(1, 0);
-            barrier(&[1]);
-            cx(0, 1);
-            barrier(&[1, 0]);
-            measure_x(0, 0);
-            measure_y(1, 1);
-        }).unwrap();
+        let mut prep = Circuit::new(2, 2);
+        assert_eq!(prep.h(0), Ok(()));
+        assert_eq!(prep.cx(0, 1), Ok(()));
+        let mut meas = Circuit::new(2, 2);
+        assert_eq!(meas.measure_all(&[0, 1]), Ok(()));
+
+        assert_eq!(prep.compose(&meas), Ok(()));
+        assert_eq!(prep.execute(100), Ok(()));
+        let hist = prep.histogram().unwrap();
+        assert!(hist.keys().all(|&key| key == 0 || key == 3));
+        assert_eq!(hist.values().sum::<usize>(), 100);
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.compose(&Circuit::new(3, 2)),
+            Err(crate::error::Error::InvalidNrBits(3, 2, String::from("quantum register"))));
+        assert_eq!(circuit.compose(&Circuit::new(2, 1)),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("classical register"))));
+    }
+
+    #[test]
+    fn test_append_on_qubits()
+    {
+        let mut prep = Circuit::new(2, 0);
+        assert_eq!(prep.h(0), Ok(()));
+        assert_eq!(prep.cx(0, 1), Ok(()));
+        let mut meas = Circuit::new(2, 2);
+        assert_eq!(meas.measure_all(&[0, 1]), Ok(()));
+
+        // Bell pair on qubits 3 and 1, measured into bits 2 and 0
+        let mut circuit = Circuit::new(4, 3);
+        assert_eq!(circuit.append_on_qubits(&prep, &[3, 1], &[]), Ok(()));
+        assert_eq!(circuit.append_on_qubits(&meas, &[3, 1], &[2, 0]), Ok(()));
+        assert_eq!(circuit.execute(100), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert!(hist.keys().all(|&key| key == 0 || key == 5));
+
+        let mut other = Circuit::new(2, 2);
+        other.reset_all();
+        assert_eq!(other.add_conditional_gate(&[1, 0], 2, crate::gates::X::new(), &[1]), Ok(()));
+        assert_eq!(other.barrier(&[0, 1]), Ok(()));
+        assert_eq!(other.measure(0, 1), Ok(()));
+        assert_eq!(other.measure_all(&[1, 0]), Ok(()));
+        let mut circuit = Circuit::new(3, 2);
+        assert_eq!(circuit.append_on_qubits(&other, &[2, 0], &[1, 0]), Ok(()));
         assert_eq!(circuit.open_qasm(), Ok(String::from(
 r#"OPENQASM 2.0;
 include "qelib1.inc";
-qreg q[2];
+qreg q[3];
 creg b[2];
-x q[0];
-cx q[0], q[1];
-barrier q;
-cx q[1], q[0];
-barrier q[1];
-cx q[0], q[1];
-barrier q[1], q[0];
-h q[0];
-measure q[0] -> b[0];
-sdg q[1];
-h q[1];
-measure q[1] -> b[1];
+reset q[2];
+reset q[0];
+if (b == 2) x q[0];
+barrier q[2], q[0];
+measure q[2] -> b[0];
+measure q[2] -> b[0];
+measure q[0] -> b[1];
 "#)));
 
-        let circuit = circuit!(2, 2, {
-            x(0);
-            measure_all(&[0, 1]);
-            measure_all(&[1, 0]);
-            measure_all_basis(&[0, 1], Basis::X);
-            measure_all_basis(&[0, 1], Basis::Y);
-        }).unwrap();
+        // Full mapping keeps operations on all qubits
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.append_on_qubits(&other, &[1, 0], &[0, 1]), Ok(()));
         assert_eq!(circuit.open_qasm(), Ok(String::from(
 r#"OPENQASM 2.0;
 include "qelib1.inc";
 qreg q[2];
 creg b[2];
-x q[0];
-measure q -> b;
-measure q[0] -> b[1];
-measure q[1] -> b[0];
-h q;
-measure q -> b;
-sdg q;
-h q;
+reset q;
+if (b == 1) x q[0];
+barrier q[1], q[0];
+measure q[1] -> b[1];
 measure q -> b;
 "#)));
 
-        let circuit = circuit!(2, 0, {
-            x(0);
-            h(1);
-            reset(0);
-            x(0);
-            reset_all();
-        }).unwrap();
-        assert_eq!(circuit.open_qasm(), Ok(String::from(
+        assert_eq!(circuit.append_on_qubits(&other, &[1], &[0, 1]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("qubit map"))));
+        assert_eq!(circuit.append_on_qubits(&other, &[1, 0], &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("classical bit map"))));
+        assert_eq!(circuit.append_on_qubits(&other, &[1, 2], &[0, 1]),
+            Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(circuit.append_on_qubits(&other, &[1, 1], &[0, 1]),
+            Err(crate::error::Error::InvalidQBit(1)));
+        assert_eq!(circuit.append_on_qubits(&other, &[1, 0], &[0, 2]),
+            Err(crate::error::Error::InvalidCBit(2)));
+    }
+
+    #[test]
+    fn test_subcircuit()
+    {
+        let mut circuit = Circuit::new(4, 4);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.cx(1, 2), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1, 2, 3]), Ok(()));
+        assert_eq!(circuit.cx(3, 0), Ok(()));
+        assert_eq!(circuit.z(3), Ok(()));
+        assert_eq!(circuit.measure(2, 1), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+
+        let sub = circuit.subcircuit(&[2, 1], 1, 6);
+        assert_eq!(sub.nr_qbits(), 2);
+        assert_eq!(sub.nr_cbits(), 4);
+        assert_eq!(sub.open_qasm(), Ok(String::from(
 r#"OPENQASM 2.0;
 include "qelib1.inc";
 qreg q[2];
+creg b[4];
 x q[0];
-h q[1];
-reset q[0];
-x q[0];
-reset q;
+cx q[1], q[0];
+barrier q[1], q[0];
+measure q[0] -> b[1];
 "#)));
 
-        let circuit = circuit!(2, 2, {
-            x(0);
-            measure_all(&[0, 1]);
-            add_conditional_gate(&[0, 1], 1, X::new(), &[0]);
-            add_conditional_gate(&[], 1, X::new(), &[1]);
-        }).unwrap();
-        assert_eq!(circuit.open_qasm(), Ok(String::from(
+        // Gate on an unlisted qubit is included, and the qubit added
+        let sub = circuit.subcircuit(&[3], 2, 7);
+        assert_eq!(sub.nr_qbits(), 2);
+        assert_eq!(sub.open_qasm(), Ok(String::from(
 r#"OPENQASM 2.0;
 include "qelib1.inc";
 qreg q[2];
-creg b[2];
-x q[0];
-measure q -> b;
-if (b == 1) x q[0];
-x q[1];
+creg b[4];
+barrier q[1], q[0];
+cx q[0], q[1];
+z q[0];
 "#)));
 
-        let circuit = circuit!(2, 2, {
-            add_conditional_gate(&[0], 1, X::new(), &[0]);
-        }).unwrap();
-        assert!(matches!(circuit.open_qasm(), Err(_)));
+        // Empty range
+        let sub = circuit.subcircuit(&[0, 1], 3, 2);
+        assert_eq!(sub.nr_qbits(), 2);
+        assert!(sub.ops.is_empty());
     }
 
     #[test]
-    fn test_c_qasm()
+    fn test_remove_identities()
     {
-        let circuit = circuit!(3, 3, {
-            x(0);
+        // Clifford circuit, with interleaved gates on other qubits
+        let circuit = circuit!(3, 0, {
             cx(0, 1);
-            cx(1, 0);
+            h(2);
             cx(0, 1);
-            measure(0, 0);
-            measure_x(1, 1);
-            measure_y(2, 2);
+            x(1);
+            cx(0, 1);
+            x(1);
+            cx(0, 1);
+            s(2);
         }).unwrap();
-        assert_eq!(circuit.c_qasm(), Ok(String::from(
-r#"version 1.0
-qubits 3
-x q[0]
-cnot q[0], q[1]
-cnot q[1], q[0]
-cnot q[0], q[1]
-measure q[0]
-measure_x q[1]
-measure_y q[2]
+        let reduced = circuit.remove_identities();
+        assert_eq!(reduced.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[3];
+h q[2];
+s q[2];
 "#)));
 
-        let circuit = circuit!(2, 2, {
-            x(0);
-            h(1);
-            measure_all(&[0, 1]);
-            reset_all();
-            measure_all_basis(&[0, 1], Basis::X);
-            reset(1);
-            measure_all_basis(&[0, 1], Basis::Y);
+        // Non-Clifford gates, and identity up to a global phase
+        let mut circuit = circuit!(2, 1, {
+            h(0);
+            z(1);
+            x(1);
+            z(1);
+            x(1);
+            rz(0.3, 0);
+            h(0);
+            rz(-0.3, 0);
+            measure(0, 0);
+            h(0);
         }).unwrap();
-        assert_eq!(circuit.c_qasm(), Ok(String::from(
-r#"version 1.0
-qubits 2
-x q[0]
-h q[1]
-measure_all
-prep_z q[0]
-prep_z q[1]
-h q[0]
-h q[1]
-measure_all
-prep_z q[1]
-sdag q[0]
-h q[0]
-sdag q[1]
-h q[1]
-measure_all
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Sdg::new(), &[1]), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        let reduced = circuit.remove_identities();
+        assert_eq!(reduced.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[1];
+h q[0];
+rz(0.3) q[0];
+h q[0];
+rz(-0.3) q[0];
+measure q[0] -> b[0];
 "#)));
 
-        let circuit = circuit!(2, 2, {
-            x(0);
-            measure_all(&[0, 1]);
-            add_conditional_gate(&[0, 1], 1, X::new(), &[0]);
-            add_conditional_gate(&[], 1, X::new(), &[1]);
-        }).unwrap();
-        assert_eq!(circuit.c_qasm(), Ok(String::from(
-r#"version 1.0
-qubits 2
-x q[0]
-measure_all
-not b[1]
-c-x b[0], b[1], q[0]
-not b[1]
-x q[1]
+        // Non-Clifford circuit too large for the full unitary
+        let mut circuit = Circuit::new(12, 0);
+        assert_eq!(circuit.add_gate(crate::gates::T::new(), &[11]), Ok(()));
+        assert_eq!(circuit.h(3), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Tdg::new(), &[11]), Ok(()));
+        assert_eq!(circuit.rx(0.4, 0), Ok(()));
+        assert_eq!(circuit.cx(0, 5), Ok(()));
+        assert_eq!(circuit.cx(0, 5), Ok(()));
+        let reduced = circuit.remove_identities();
+        assert_eq!(reduced.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[12];
+h q[3];
+rx(0.4) q[0];
 "#)));
-
-        let circuit = circuit!(2, 2, {
-            measure(0, 1);
-        }).unwrap();
-        // c-Qasm only allows for measuring to the classical bit with the same index
-        assert!(matches!(circuit.c_qasm(), Err(_)));
     }
 
     #[test]
-    fn test_latex()
+    fn test_remove_local_identities()
     {
-        let circuit = circuit!(2, 2, {
-            h(0);
-            x(1);
-            measure(0, 0);
-            measure_x(1, 1);
-            add_conditional_gate(&[0, 1], 2, X::new(), &[0]);
-            reset_all();
-            measure_all_basis(&[1, 0], Basis::Y);
-            reset(0);
-            measure_y(1, 0);
-            barrier(&[1]);
-        }).unwrap();
+        let mut circuit = Circuit::new(12, 0);
+        assert_eq!(circuit.x(11), Ok(()));
+        assert_eq!(circuit.x(11), Ok(()));
+        assert_eq!(circuit.h(3), Ok(()));
+        for _ in 0..4
+        {
+            assert_eq!(circuit.s(3), Ok(()));
+        }
+        assert_eq!(circuit.h(3), Ok(()));
+        assert_eq!(circuit.rx(0.5, 5), Ok(()));
 
-        assert_eq!(circuit.latex(), Ok(String::from(
-r#"\Qcircuit @C=1em @R=.7em {
-    \lstick{\ket{0}} & \gate{H} & \meter & \qw & \targ & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \meterB{Y} & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \qw & \qw & \qw \\
-    \lstick{\ket{0}} & \gate{X} & \qw & \meterB{X} & \qw & \push{~\ket{0}~} \ar @{|-{}} [0,-1] & \qw & \meterB{Y} & \meterB{Y} & \qw \barrier{0} & \qw \\
-    \lstick{0} & \cw & \cw \cwx[-2] & \cw & \cctrlo{-2} & \cw & \cw & \cw \cwx[-1] & \cw \cwx[-1] & \cw & \cw \\
-    \lstick{0} & \cw & \cw & \cw \cwx[-2] & \cctrl{-1} & \cw & \cw \cwx[-3] & \cw & \cw & \cw & \cw \\
-}
+        // Too large for the full unitary, so only local identities are removed
+        assert_eq!(circuit.remove_identities().open_qasm(),
+            circuit.remove_local_identities(8).open_qasm());
+        assert_eq!(circuit.remove_identities().ops.len(), 1);
+
+        let reduced = circuit.remove_local_identities(2);
+        assert_eq!(reduced.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[12];
+h q[3];
+s q[3];
+s q[3];
+s q[3];
+s q[3];
+h q[3];
+rx(0.5) q[5];
+"#)));
+        let reduced = circuit.remove_local_identities(6);
+        assert_eq!(reduced.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[12];
+rx(0.5) q[5];
 "#)));
     }
 
     #[test]
-    fn test_is_stabilizer()
+    fn test_optimize()
     {
-        let mut circuit = Circuit::new(100, 1);
-        for i in 0..99
+        use super::OptimizationConfig;
+        use crate::instruction::Instruction;
+
+        let config = OptimizationConfig::level(0);
+        assert!(!config.cancel_adjacent_gates && !config.merge_rotations);
+        let config = OptimizationConfig::level(1);
+        assert!(config.cancel_adjacent_gates && config.merge_rotations);
+        assert!(!config.decompose_multi_qubit && !config.commute_past_barriers);
+        let config = OptimizationConfig::level(2);
+        assert!(config.decompose_multi_qubit && config.commute_past_barriers);
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.rx(0.25, 0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.rx(0.25, 0), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.rz(0.4, 1), Ok(()));
+        assert_eq!(circuit.rz(-0.4, 1), Ok(()));
+        assert_eq!(circuit.barrier(&[0]), Ok(()));
+        assert_eq!(circuit.rx(0.5, 0), Ok(()));
+
+        // No optimization
+        let optimized = circuit.optimize(&OptimizationConfig::level(0));
+        assert_eq!(optimized.to_instructions(), circuit.to_instructions());
+
+        // Light optimization, rotation merged to zero is cancelled, barrier is kept
+        let optimized = circuit.optimize(&OptimizationConfig::level(1));
+        assert_eq!(optimized.to_instructions(), vec![
+            Instruction::RX(0.5, 0),
+            Instruction::H(1),
+            Instruction::Barrier(vec![0]),
+            Instruction::RX(0.5, 0)
+        ]);
+
+        let mut config = OptimizationConfig::level(1);
+        config.commute_past_barriers = true;
+        let optimized = circuit.optimize(&config);
+        assert_eq!(optimized.to_instructions(), vec![
+            Instruction::RX(1.0, 0),
+            Instruction::H(1),
+            Instruction::Barrier(vec![0])
+        ]);
+
+        // Decomposition of multi-qubit gates
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Swap::new(), &[0, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CZ::new(), &[1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(CY::new(), &[2, 0]), Ok(()));
+        let optimized = circuit.optimize(&OptimizationConfig::level(2));
+        assert!(!optimized.to_instructions().iter().any(|instr| {
+            matches!(instr, Instruction::Swap(_, _) | Instruction::CY(_, _) | Instruction::CZ(_, _))
+        }));
+        let actual = circuit_unitary(&optimized);
+        let mut expected = circuit_unitary(&circuit);
+        let phase = expected.iter().zip(actual.iter())
+            .find(|(e, _)| e.norm() > 1.0e-10)
+            .map(|(&e, &a)| a / e)
+            .unwrap();
+        expected *= phase;
+        assert_complex_matrix_eq!(&actual, &expected);
+
+        // Swap gates cancel before decomposition
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.add_gate(crate::gates::Swap::new(), &[0, 1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Swap::new(), &[1, 0]), Ok(()));
+        assert_eq!(circuit.optimize(&OptimizationConfig::level(2)).ops.len(), 0);
+    }
+
+    #[test]
+    fn test_phase_tracking()
+    {
+        use super::OptimizationConfig;
+        use crate::gates::{Gate, Phase, C, CRZ, CU1, U1};
+
+        let lambda = 0.9;
+
+        // Controlled RZ from CX and RZ gates
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.rz(0.5 * lambda, 1), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.rz(-0.5 * lambda, 1), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &CRZ::new(lambda).matrix());
+        let mut rz = Circuit::new(1, 0);
+        assert_eq!(rz.rz(lambda, 0), Ok(()));
+        let rz = crate::gates::Composite::from_circuit("RZ", &rz).unwrap();
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &C::new(rz.clone()).matrix());
+
+        // U1 = exp(iλ/2) RZ, which becomes a relative phase under control
+        assert!((CU1::new(lambda).matrix() - C::new(rz).matrix()).iter()
+            .any(|x| x.norm() > 1.0e-3));
+        let mut u1 = Circuit::new(1, 0);
+        assert_eq!(u1.rz(lambda, 0), Ok(()));
+        assert_eq!(u1.add_gate(Phase::new(0.5 * lambda), &[]), Ok(()));
+        assert_complex_matrix_eq!(&circuit_unitary(&u1), &U1::new(lambda).matrix());
+        let cu1 = C::new(crate::gates::Composite::from_circuit("U1", &u1).unwrap()).matrix();
+        assert_complex_matrix_eq!(&cu1, &CU1::new(lambda).matrix());
+        // C(Phase(λ/2)) = U1(λ/2) on the control qubit
+        assert_eq!(circuit.u1(0.5 * lambda, 0), Ok(()));
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &CU1::new(lambda).matrix());
+
+        // Global phase factor
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.global_phase_factor(), crate::cmatrix::COMPLEX_ONE);
+        assert_eq!(circuit.add_gate(Phase::new(0.25), &[]), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.add_gate(Phase::new(0.5), &[]), Ok(()));
+        let expected = num_complex::Complex::from_polar(&1.0, &0.75);
+        assert!((circuit.global_phase_factor() - expected).norm() < 1.0e-12);
+
+        // XZXZ = -I, removed without trace if phases are not tracked
+        let mut circuit = Circuit::new(1, 0);
+        for _ in 0..2
         {
-            assert_eq!(circuit.h(i), Ok(()));
-            assert_eq!(circuit.cx(i, i+1), Ok(()));
-            assert_eq!(circuit.x(i+1), Ok(()));
+            assert_eq!(circuit.x(0), Ok(()));
+            assert_eq!(circuit.z(0), Ok(()));
         }
-        assert!(circuit.is_stabilizer_circuit());
+        assert!(!circuit.phase_tracking());
+        let removed = circuit.remove_identities();
+        assert_eq!(removed.ops.len(), 0);
+        assert_eq!(removed.global_phase_factor(), crate::cmatrix::COMPLEX_ONE);
+        circuit.set_phase_tracking(true);
+        let removed = circuit.remove_identities();
+        assert!(removed.phase_tracking());
+        assert_eq!(removed.ops.len(), 1);
+        assert!((removed.global_phase_factor() + crate::cmatrix::COMPLEX_ONE).norm() < 1.0e-12);
+
+        // Optimization keeps the exact unitary
+        let mut circuit = Circuit::new(2, 0);
+        circuit.set_phase_tracking(true);
+        assert_eq!(circuit.u1(0.3, 0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CZ::new(), &[0, 1]), Ok(()));
+        assert_eq!(circuit.add_gate(Phase::new(0.2), &[]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CZ::new(), &[1, 0]), Ok(()));
+        assert_eq!(circuit.rz(-0.3, 0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        let optimized = circuit.optimize(&OptimizationConfig::level(2));
+        assert_eq!(optimized.ops.len(), 2);
+        assert_complex_matrix_eq!(&circuit_unitary(&optimized), &circuit_unitary(&circuit));
+        let expected = num_complex::Complex::from_polar(&1.0, &0.35);
+        assert!((optimized.global_phase_factor() - expected).norm() < 1.0e-12);
+    }
 
-        assert_eq!(circuit.measure(55, 0), Ok(()));
-        assert!(circuit.is_stabilizer_circuit());
+    #[test]
+    fn test_apply_all()
+    {
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(circuit.apply_all(H::new()), Ok(()));
+        assert_eq!(circuit.ops.len(), 1);
+        match circuit.ops.last()
+        {
+            Some(CircuitOp::Gate(gate, bits)) => {
+                assert_eq!(gate.description(), "H⊗H⊗H⊗H");
+                assert_eq!(bits, &vec![0, 1, 2, 3]);
+            },
+            // LCOV_EXCL_START
+            _ => panic!("Kronecker product gate was not added")
+            // LCOV_EXCL_STOP
+        }
 
-        assert_eq!(circuit.add_gate(CY::new(), &[99, 0]), Ok(()));
-        assert!(circuit.is_stabilizer_circuit());
+        let mut single = Circuit::new(4, 0);
+        for i in 0..4
+        {
+            assert_eq!(single.h(i), Ok(()));
+        }
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &circuit_unitary(&single), 1.0e-14);
 
-        assert_eq!(circuit.u1(0.99, 5), Ok(()));
-        assert!(!circuit.is_stabilizer_circuit());
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+            "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[4];\nh q[0]; h q[1]; h q[2]; h q[3];\n")));
+
+        assert!(matches!(circuit.apply_all(CX::new()),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+    }
+
+    #[test]
+    fn test_apply_range()
+    {
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(circuit.apply_range(X::new(), &[0, 2, 3]), Ok(()));
+        assert_eq!(circuit.ops.len(), 3);
+
+        let mut single = Circuit::new(4, 0);
+        assert_eq!(single.x(0), Ok(()));
+        assert_eq!(single.x(2), Ok(()));
+        assert_eq!(single.x(3), Ok(()));
+        assert_complex_matrix_eq!(&circuit_unitary(&circuit), &circuit_unitary(&single));
+
+        assert_eq!(circuit.apply_range(X::new(), &[1, 4]), Err(crate::error::Error::InvalidQBit(4)));
+        assert_eq!(circuit.ops.len(), 3);
+        assert!(matches!(circuit.apply_range(CX::new(), &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
     }
 
     #[test]
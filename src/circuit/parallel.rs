@@ -0,0 +1,560 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parallel execution of circuits.
+//!
+//! The shots of a circuit are split into batches, which are executed on the
+//! threads of the `rayon` thread pool. Since the gates in a circuit cannot in
+//! general be shared between threads, as their parameters may refer to
+//! shared values, a snapshot of each gate is made before execution.
+
+use crate::circuit::{Basis, Circuit, CircuitOp, SimulationMode};
+use crate::export::CircuitGate;
+use crate::stabilizer::PauliOp;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// The images of the `X` and `Z` operators on a single qubit under
+/// conjugation with a gate, and whether their sign is flipped
+type PauliImages = Vec<(Vec<PauliOp>, bool)>;
+
+/// Snapshot of a gate that can be shared between threads
+///
+/// Struct `ThreadSafeGate` holds what is needed to apply a gate in a
+/// simulation. For stabilizer simulations, this is the image of the `X` and
+/// `Z` operators on each qubit under conjugation with the gate; for other
+/// simulations, the matrix of the gate. The matrix is computed once, and
+/// shared between the copies of the gate in all batches, which multiply the
+/// state by the shared matrix directly.
+#[derive(Clone)]
+struct ThreadSafeGate
+{
+    /// The description of the gate
+    desc: String,
+    /// The number of qubits the gate operates on
+    nr_bits: usize,
+    /// The cost of the gate
+    cost: f64,
+    /// The matrix of the gate, if not executed on a stabilizer state
+    matrix: Option<::std::sync::Arc<crate::cmatrix::CMatrix>>,
+    /// If executed on a stabilizer state, for each qubit the conjugated `X`
+    /// and `Z` operators on that qubit
+    conjugates: Option<Vec<PauliImages>>
+}
+
+impl ThreadSafeGate
+{
+    /// Create a snapshot of `gate`, for execution on a stabilizer state if
+    /// `stabilizer` is `true`.
+    fn new(gate: &dyn CircuitGate, stabilizer: bool) -> crate::error::Result<Self>
+    {
+        let nr_bits = gate.nr_affected_bits();
+        let (matrix, conjugates) = if stabilizer
+            {
+                let mut conjugates = vec![];
+                for i in 0..nr_bits
+                {
+                    let mut images = vec![];
+                    for &op in [PauliOp::X, PauliOp::Z].iter()
+                    {
+                        let mut ops = vec![PauliOp::I; nr_bits];
+                        ops[i] = op;
+                        let flip = gate.conjugate(&mut ops)?;
+                        images.push((ops, flip));
+                    }
+                    conjugates.push(images);
+                }
+                (None, Some(conjugates))
+            }
+            else
+            {
+                (Some(::std::sync::Arc::new(gate.matrix())), None)
+            };
+
+        Ok(ThreadSafeGate
+        {
+            desc: String::from(gate.description()),
+            nr_bits: nr_bits,
+            cost: gate.cost(),
+            matrix: matrix,
+            conjugates: conjugates
+        })
+    }
+
+    /// The matrix of this gate, shared between all copies
+    fn shared_matrix(&self) -> &crate::cmatrix::CMatrix
+    {
+        self.matrix.as_ref()
+            .expect("The matrix of a gate for stabilizer execution should not be needed")
+    }
+
+    /// Check that a state with `len` rows is valid for this gate, and return
+    /// the size of the blocks of rows that the matrix elements of the gate
+    /// act on.
+    fn block_size(&self, len: usize) -> usize
+    {
+        assert!(len % (1 << self.nr_bits) == 0,
+            "The number of rows in the state is {}, which is not valid for a {}-bit gate.",
+            len, self.nr_bits);
+        len >> self.nr_bits
+    }
+}
+
+impl crate::gates::Gate for ThreadSafeGate
+{
+    fn cost(&self) -> f64
+    {
+        self.cost
+    }
+
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        self.shared_matrix().clone()
+    }
+
+    fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
+    {
+        let len = state.len();
+        let n = self.block_size(len);
+        let blocks = state.to_owned().into_shape((1 << self.nr_bits, n)).unwrap();
+        let res = self.shared_matrix().dot(&blocks);
+        state.assign(&res.into_shape(len).unwrap());
+    }
+
+    fn apply_mat_slice(&self, mut state: crate::cmatrix::CMatSliceMut)
+    {
+        let (rows, cols) = (state.rows(), state.cols());
+        let n = self.block_size(rows);
+        let blocks = state.to_owned().into_shape((1 << self.nr_bits, n * cols)).unwrap();
+        let res = self.shared_matrix().dot(&blocks);
+        state.assign(&res.into_shape((rows, cols)).unwrap());
+    }
+
+    fn is_stabilizer(&self) -> bool
+    {
+        self.conjugates.is_some()
+    }
+
+    fn conjugate(&self, ops: &mut [PauliOp]) -> crate::error::Result<bool>
+    {
+        let conjugates = match self.conjugates
+            {
+                Some(ref conjugates) => conjugates,
+                None => return Err(crate::error::Error::NotAStabilizer(self.desc.clone()))
+            };
+        self.check_nr_bits(ops.len())?;
+
+        // Write the operator as a product of X and Z operators on single
+        // qubits, using Y = iXZ, and multiply their conjugates.
+        let mut res = vec![PauliOp::I; ops.len()];
+        let mut phase = crate::cmatrix::COMPLEX_ONE;
+        for (&op, images) in ops.iter().zip(conjugates.iter())
+        {
+            let factors: &[usize] = match op
+                {
+                    PauliOp::I => &[],
+                    PauliOp::X => &[0],
+                    PauliOp::Z => &[1],
+                    PauliOp::Y => {
+                        phase *= crate::cmatrix::COMPLEX_I;
+                        &[0, 1]
+                    }
+                };
+            for &k in factors
+            {
+                let (ref image, flip) = images[k];
+                if flip
+                {
+                    phase = -phase;
+                }
+                for (r, &p) in res.iter_mut().zip(image.iter())
+                {
+                    let (prod, factor) = r.multiply(p);
+                    *r = prod;
+                    phase *= factor;
+                }
+            }
+        }
+
+        ops.copy_from_slice(&res);
+        Ok(phase.re < 0.0)
+    }
+}
+
+impl crate::export::OpenQasm for ThreadSafeGate {}
+impl crate::export::CQasm for ThreadSafeGate {}
+impl crate::export::Latex for ThreadSafeGate {}
+impl crate::export::TketJson for ThreadSafeGate {}
+impl crate::instruction::ToInstruction for ThreadSafeGate {}
+
+/// Circuit operation that can be shared between threads
+///
+/// Enum `ThreadSafeOp` mirrors `CircuitOp`, with the gates replaced by their
+/// thread safe snapshots.
+enum ThreadSafeOp
+{
+    /// Apply a gate to the state
+    Gate(ThreadSafeGate, Vec<usize>),
+    /// Conditionally apply a gate, depending on classical bits
+    ConditionalGate(Vec<usize>, u64, ThreadSafeGate, Vec<usize>),
+    /// Reset a qubit to |0⟩
+    Reset(usize),
+    /// Reset a qubit to |0⟩ (`false`) or |1⟩ (`true`)
+    ResetTo(usize, bool),
+    /// Reset the quantum state to |00...0⟩
+    ResetAll,
+    /// Measure a qubit in a certain basis
+    Measure(usize, usize, Basis),
+    /// Measure all qubits
+    MeasureAll(Vec<usize>, Basis),
+    /// Measure a single qubit in a certain basis without affecting state
+    Peek(usize, usize, Basis),
+    /// Measure all qubits in a certain basis without affecting state
+    PeekAll(Vec<usize>, Basis),
+    /// Measure a subset of qubits without affecting state
+    PeekPartial(Vec<usize>, Vec<usize>),
+    /// Prevent gate reordering on the associated bits across the barrier
+    Barrier(Vec<usize>),
+    /// Apply a noise channel, described by its Kraus operators
+    Noise(Vec<crate::cmatrix::CMatrix>, Vec<usize>),
+    /// Compute the sign of an expectation value without affecting state
+    ExpectationMeasure(crate::stabilizer::PauliString, usize)
+}
+
+impl ThreadSafeOp
+{
+    /// Create a thread safe copy of operation `op`, for execution on a
+    /// stabilizer state if `stabilizer` is `true`.
+    fn new(op: &CircuitOp, stabilizer: bool) -> crate::error::Result<Self>
+    {
+        let res = match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    ThreadSafeOp::Gate(ThreadSafeGate::new(gate.as_ref(), stabilizer)?, bits.clone())
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    ThreadSafeOp::ConditionalGate(control.clone(), target,
+                        ThreadSafeGate::new(gate.as_ref(), stabilizer)?, bits.clone())
+                },
+                CircuitOp::Reset(bit) => ThreadSafeOp::Reset(bit),
+                CircuitOp::ResetTo(bit, value) => ThreadSafeOp::ResetTo(bit, value),
+                CircuitOp::ResetAll => ThreadSafeOp::ResetAll,
+                CircuitOp::Measure(qbit, cbit, basis) => ThreadSafeOp::Measure(qbit, cbit, basis),
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    ThreadSafeOp::MeasureAll(cbits.clone(), basis)
+                },
+                CircuitOp::Peek(qbit, cbit, basis) => ThreadSafeOp::Peek(qbit, cbit, basis),
+                CircuitOp::PeekAll(ref cbits, basis) => ThreadSafeOp::PeekAll(cbits.clone(), basis),
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    ThreadSafeOp::PeekPartial(qbits.clone(), cbits.clone())
+                },
+                CircuitOp::Barrier(ref bits) => ThreadSafeOp::Barrier(bits.clone()),
+                CircuitOp::Noise(ref ops, ref bits) => {
+                    ThreadSafeOp::Noise(ops.clone(), bits.clone())
+                },
+                CircuitOp::ExpectationMeasure(ref pauli, cbit) => {
+                    ThreadSafeOp::ExpectationMeasure(pauli.clone(), cbit)
+                }
+            };
+        Ok(res)
+    }
+
+    /// Convert this operation back into a circuit operation.
+    fn circuit_op(&self) -> CircuitOp
+    {
+        match *self
+        {
+            ThreadSafeOp::Gate(ref gate, ref bits) => {
+                CircuitOp::Gate(Box::new(gate.clone()), bits.clone())
+            },
+            ThreadSafeOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                CircuitOp::ConditionalGate(control.clone(), target, Box::new(gate.clone()),
+                    bits.clone())
+            },
+            ThreadSafeOp::Reset(bit) => CircuitOp::Reset(bit),
+            ThreadSafeOp::ResetTo(bit, value) => CircuitOp::ResetTo(bit, value),
+            ThreadSafeOp::ResetAll => CircuitOp::ResetAll,
+            ThreadSafeOp::Measure(qbit, cbit, basis) => CircuitOp::Measure(qbit, cbit, basis),
+            ThreadSafeOp::MeasureAll(ref cbits, basis) => {
+                CircuitOp::MeasureAll(cbits.clone(), basis)
+            },
+            ThreadSafeOp::Peek(qbit, cbit, basis) => CircuitOp::Peek(qbit, cbit, basis),
+            ThreadSafeOp::PeekAll(ref cbits, basis) => CircuitOp::PeekAll(cbits.clone(), basis),
+            ThreadSafeOp::PeekPartial(ref qbits, ref cbits) => {
+                CircuitOp::PeekPartial(qbits.clone(), cbits.clone())
+            },
+            ThreadSafeOp::Barrier(ref bits) => CircuitOp::Barrier(bits.clone()),
+            ThreadSafeOp::Noise(ref ops, ref bits) => CircuitOp::Noise(ops.clone(), bits.clone()),
+            ThreadSafeOp::ExpectationMeasure(ref pauli, cbit) => {
+                CircuitOp::ExpectationMeasure(pauli.clone(), cbit)
+            }
+        }
+    }
+}
+
+impl Circuit
+{
+    /// Execute this circuit in parallel
+    ///
+    /// Execute this circuit for `nr_shots` shots, like `execute()`, but split
+    /// the shots into `rayon::current_num_threads()` batches that are executed
+    /// in parallel. Each batch uses its own `ChaCha20` random number
+    /// generator, seeded with a master seed taken from the default random
+    /// number generator of this circuit (see `with_seed()`), on a stream
+    /// given by the batch index. The classical states of the batches are
+    /// combined into the classical state of this circuit. The quantum states
+    /// of the batches are discarded, so the circuit cannot be run again with
+    /// `reexecute()`.
+    pub fn run_shots_parallel(&mut self, nr_shots: usize) -> crate::error::Result<()>
+    {
+        self.run_shots_in_batches(nr_shots, rayon::current_num_threads())
+    }
+
+    /// Execute this circuit for `nr_shots` shots, split into (at most)
+    /// `nr_batches` batches that are executed in parallel. See
+    /// `run_shots_parallel()`.
+    fn run_shots_in_batches(&mut self, nr_shots: usize, nr_batches: usize)
+        -> crate::error::Result<()>
+    {
+        self.check_parameters_bound()?;
+
+        let stabilizer = match self.simulation_mode
+            {
+                SimulationMode::Automatic => self.is_stabilizer_circuit(),
+                SimulationMode::Stabilizer => {
+                    if !self.is_stabilizer_circuit()
+                    {
+                        return Err(crate::error::Error::NotACliffordCircuit);
+                    }
+                    true
+                },
+                _ => false
+            };
+        let ops = self.ops.iter()
+            .map(|op| ThreadSafeOp::new(op, stabilizer))
+            .collect::<crate::error::Result<Vec<_>>>()?;
+
+        let seed = self.with_default_rng(|_, rng| rng.next_u64());
+        let nr_batches = nr_batches.min(nr_shots).max(1);
+        let (nr_qbits, nr_cbits, mode) = (self.nr_qbits, self.nr_cbits, self.simulation_mode);
+        let results: Vec<crate::error::Result<_>> = (0..nr_batches).into_par_iter().map(|batch| {
+            let batch_shots = nr_shots / nr_batches
+                + if batch < nr_shots % nr_batches { 1 } else { 0 };
+            let mut rng = <rand_chacha::ChaCha20Rng as rand::SeedableRng>::seed_from_u64(seed);
+            rng.set_stream(batch as u64);
+
+            let mut circuit = Circuit::new(nr_qbits, nr_cbits);
+            circuit.ops = ops.iter().map(ThreadSafeOp::circuit_op).collect();
+            circuit.simulation_mode = mode;
+            circuit.execute_with_rng(batch_shots, &mut rng)?;
+            Ok((circuit.c_state.take().unwrap_or_default(), circuit.warnings))
+        }).collect();
+
+        let mut c_state = Vec::with_capacity(nr_shots);
+        let mut warnings = vec![];
+        for res in results
+        {
+            let (batch_state, batch_warnings) = res?;
+            c_state.extend(batch_state.iter());
+            for warning in batch_warnings
+            {
+                if !warnings.contains(&warning)
+                {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        self.q_state = None;
+        self.c_state = Some(ndarray::Array1::from_vec(c_state));
+        self.warnings = warnings;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::ThreadSafeGate;
+    use crate::circuit::{Circuit, SimulationMode};
+    use crate::gates::Gate;
+    use crate::stabilizer::PauliOp;
+
+    #[test]
+    fn test_conjugate()
+    {
+        let gates: Vec<Box<dyn crate::export::CircuitGate>> = vec![
+            Box::new(crate::gates::H::new()),
+            Box::new(crate::gates::S::new()),
+            Box::new(crate::gates::Sdg::new()),
+            Box::new(crate::gates::Y::new()),
+            Box::new(crate::gates::CX::new()),
+            Box::new(crate::gates::CY::new()),
+            Box::new(crate::gates::CZ::new()),
+            Box::new(crate::gates::Swap::new())
+        ];
+
+        let ops = [PauliOp::I, PauliOp::Z, PauliOp::X, PauliOp::Y];
+        for gate in gates.iter()
+        {
+            let snapshot = ThreadSafeGate::new(gate.as_ref(), true).unwrap();
+            assert!(snapshot.is_stabilizer());
+            assert_eq!(snapshot.description(), gate.description());
+
+            let nr_bits = gate.nr_affected_bits();
+            for idx in 0..(1 << (2 * nr_bits))
+            {
+                let mut expected: Vec<PauliOp> = (0..nr_bits).map(|i| ops[(idx >> (2*i)) & 3])
+                    .collect();
+                let mut result = expected.clone();
+                let expected_flip = gate.conjugate(&mut expected);
+                assert_eq!(snapshot.conjugate(&mut result), expected_flip);
+                assert_eq!(result, expected);
+            }
+        }
+
+        let snapshot = ThreadSafeGate::new(&crate::gates::T::new(), false).unwrap();
+        assert!(!snapshot.is_stabilizer());
+        assert_complex_matrix_eq!(snapshot.matrix(), crate::gates::T::new().matrix());
+        assert_eq!(snapshot.conjugate(&mut [PauliOp::X]),
+            Err(crate::error::Error::NotAStabilizer(String::from("T"))));
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let gates: Vec<Box<dyn crate::export::CircuitGate>> = vec![
+            Box::new(crate::gates::T::new()),
+            Box::new(crate::gates::U3::new(0.3, -1.2, 2.5)),
+            Box::new(crate::gates::CRY::new(0.7)),
+            Box::new(crate::gates::CCX::new())
+        ];
+
+        for gate in gates.iter()
+        {
+            let snapshot = ThreadSafeGate::new(gate.as_ref(), false).unwrap();
+
+            let state = crate::cmatrix::CVector::from_shape_fn(16,
+                |i| num_complex::Complex::new(i as f64, 1.0 - 0.5 * i as f64));
+            let mut expected = state.clone();
+            gate.apply_slice(expected.view_mut());
+            let mut result = state.clone();
+            snapshot.apply_slice(result.view_mut());
+            assert_complex_vector_eq!(&result, &expected, 1.0e-12);
+
+            let state = crate::cmatrix::CMatrix::from_shape_fn((16, 3),
+                |(i, j)| num_complex::Complex::new((i * j) as f64, i as f64 - j as f64));
+            let mut expected = state.clone();
+            gate.apply_mat_slice(expected.view_mut());
+            let mut result = state.clone();
+            snapshot.apply_mat_slice(result.view_mut());
+            assert_complex_matrix_eq!(&result, &expected, 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_run_shots_parallel()
+    {
+        let nr_shots = 8192;
+        let tol = 5.0e-3;
+
+        // Stabilizer circuit
+        let mut circuit = circuit!(2, 2, {
+            h(0);
+            cx(0, 1);
+            measure_all(&[0, 1]);
+        }).unwrap();
+        assert_eq!(circuit.run_shots_parallel(nr_shots), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert_eq!(circuit.cstate().unwrap().len(), nr_shots);
+        assert!(hist.keys().all(|&k| k == 0 || k == 3));
+        assert!(crate::stats::measurement_ok(hist[&0], nr_shots, 0.5, tol));
+
+        assert_eq!(circuit.run_shots_in_batches(nr_shots + 1, 3), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert_eq!(circuit.cstate().unwrap().len(), nr_shots + 1);
+        assert!(hist.keys().all(|&k| k == 0 || k == 3));
+        assert!(crate::stats::measurement_ok(hist[&0], nr_shots + 1, 0.5, tol));
+
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+        let seq_hist = circuit.histogram().unwrap();
+        assert!(seq_hist.keys().all(|&k| k == 0 || k == 3));
+        assert!(crate::stats::measurement_ok(seq_hist[&0], nr_shots, 0.5, tol));
+
+        // Vector state, with a conditional gate
+        let p = (0.35f64).sin().powi(2);
+        let mut circuit = circuit!(2, 2, {
+            rx(0.7, 0);
+            measure(0, 0);
+        }).unwrap();
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, crate::gates::X::new(), &[1]), Ok(()));
+        assert_eq!(circuit.measure(1, 1), Ok(()));
+        assert_eq!(circuit.run_shots_parallel(nr_shots), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert!(hist.keys().all(|&k| k == 0 || k == 3));
+        assert!(crate::stats::measurement_ok(hist.get(&3).cloned().unwrap_or(0), nr_shots, p, tol));
+        assert_eq!(circuit.reexecute(), Err(crate::error::Error::NotExecuted));
+
+        // Explicit stabilizer mode on a non-Clifford circuit
+        circuit.set_simulation_mode(SimulationMode::Stabilizer);
+        assert_eq!(circuit.run_shots_parallel(nr_shots),
+            Err(crate::error::Error::NotACliffordCircuit));
+    }
+
+    #[test]
+    fn test_run_shots_parallel_seed()
+    {
+        let build = || {
+            let mut circuit = circuit!(3, 3, {
+                h(0);
+                u1(::std::f64::consts::FRAC_PI_4, 0);
+                h(0);
+                cx(0, 1);
+                h(2);
+                measure_all(&[0, 1, 2]);
+            }).unwrap();
+            circuit.with_seed(0x3c41);
+            circuit
+        };
+
+        let mut circuit0 = build();
+        let mut circuit1 = build();
+        assert_eq!(circuit0.run_shots_parallel(1000), Ok(()));
+        assert_eq!(circuit1.run_shots_parallel(1000), Ok(()));
+        assert_eq!(circuit0.cstate(), circuit1.cstate());
+
+        assert_eq!(circuit0.run_shots_parallel(0), Ok(()));
+        assert_eq!(circuit0.cstate().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_run_shots_parallel_unbound()
+    {
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.add_gate(crate::gates::RX::new(crate::gates::Parameter::symbol("theta")),
+            &[0]), Ok(()));
+        assert!(matches!(circuit.run_shots_parallel(10),
+            Err(crate::error::Error::UnboundParameter(_))));
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Import of circuits in the JSON format of Cirq.
+
+use crate::json::Value;
+
+/// Qubit identifiers in Cirq, ordered in the same way as Cirq orders them
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum QubitId
+{
+    Line(i64),
+    Grid(i64, i64),
+    Named(String)
+}
+
+/// Create an error for an invalid Cirq JSON description
+fn invalid(what: &str) -> crate::error::ParseError
+{
+    crate::error::ParseError::InvalidCirqJson(String::from(what))
+}
+
+/// The Cirq type of object `value`, without a `cirq.` prefix
+fn cirq_type(value: &Value) -> crate::error::ParseResult<&str>
+{
+    let name = value.get("cirq_type").and_then(Value::as_str)
+        .ok_or_else(|| invalid("cirq_type"))?;
+    Ok(name.trim_start_matches("cirq."))
+}
+
+/// Numerical field `key` of object `value`
+fn number(value: &Value, key: &str) -> crate::error::ParseResult<f64>
+{
+    value.get(key).and_then(Value::as_f64).ok_or_else(|| invalid(key))
+}
+
+/// Array field `key` of object `value`
+fn array<'a>(value: &'a Value, key: &str) -> crate::error::ParseResult<&'a [Value]>
+{
+    value.get(key).and_then(Value::as_array).ok_or_else(|| invalid(key))
+}
+
+/// Parse a Cirq qubit object
+fn qubit_id(value: &Value) -> crate::error::ParseResult<QubitId>
+{
+    match cirq_type(value)?
+    {
+        "LineQubit" => Ok(QubitId::Line(number(value, "x")? as i64)),
+        "GridQubit" => Ok(QubitId::Grid(number(value, "row")? as i64, number(value, "col")? as i64)),
+        "NamedQubit" => {
+            let name = value.get("name").and_then(Value::as_str).ok_or_else(|| invalid("name"))?;
+            Ok(QubitId::Named(String::from(name)))
+        },
+        name => Err(invalid(name))
+    }
+}
+
+/// Find the gate and the qubits it operates on in operation `op`, looking
+/// through tagged operations.
+fn gate_operation(op: &Value) -> crate::error::ParseResult<(&Value, Vec<QubitId>)>
+{
+    match cirq_type(op)?
+    {
+        "GateOperation" => {
+            let gate = op.get("gate").ok_or_else(|| invalid("gate"))?;
+            let qubits = array(op, "qubits")?.iter()
+                .map(qubit_id)
+                .collect::<crate::error::ParseResult<Vec<QubitId>>>()?;
+            Ok((gate, qubits))
+        },
+        "TaggedOperation" => {
+            gate_operation(op.get("sub_operation").ok_or_else(|| invalid("sub_operation"))?)
+        },
+        name => Err(crate::error::ParseError::UnsupportedCirqGate(String::from(name)))
+    }
+}
+
+/// Add gate `gate` to `circuit`, after checking that it operates on as
+/// many qubits as there are in `bits`.
+fn push_gate<G>(circuit: &mut crate::circuit::Circuit, gate: G, bits: &[usize])
+    -> crate::error::ParseResult<()>
+where G: crate::export::CircuitGate + 'static
+{
+    match gate.check_nr_bits(bits.len()).and_then(|_| circuit.add_gate(gate, bits))
+    {
+        Err(crate::error::Error::InvalidNrBits(actual, expected, desc)) => {
+            Err(crate::error::ParseError::InvalidNrBits(actual, expected, desc))
+        },
+        // All qubits are valid, so this cannot happen
+        Err(_) => Err(invalid("qubits")),
+        Ok(()) => Ok(())
+    }
+}
+
+/// Add Cirq gate `gate`, operating on qubits `bits`, to `circuit`.
+/// Measurement results are stored in consecutive classical bits, starting
+/// at `cbit`, which is updated accordingly.
+fn add_gate(circuit: &mut crate::circuit::Circuit, gate: &Value, bits: &[usize],
+    cbit: &mut usize) -> crate::error::ParseResult<()>
+{
+    let name = cirq_type(gate)?;
+    let unsupported = || crate::error::ParseError::UnsupportedCirqGate(String::from(name));
+    // Gates in the Cirq gate set are stored as powers of a basic gate
+    let exponent = match gate.get("exponent")
+        {
+            None => 1.0,
+            Some(value) => value.as_f64().ok_or_else(unsupported)?
+        };
+    let is = |t: f64| (exponent - t).abs() < 1.0e-10;
+    let theta = ::std::f64::consts::PI * exponent;
+
+    match name
+    {
+        "HPowGate" if is(1.0) => push_gate(circuit, crate::gates::H::new(), bits),
+        "XPowGate" if is(1.0) => push_gate(circuit, crate::gates::X::new(), bits),
+        "XPowGate" if is(0.5) => push_gate(circuit, crate::gates::V::new(), bits),
+        "XPowGate" if is(-0.5) => push_gate(circuit, crate::gates::Vdg::new(), bits),
+        "XPowGate" => push_gate(circuit, crate::gates::RX::new(theta), bits),
+        "YPowGate" if is(1.0) => push_gate(circuit, crate::gates::Y::new(), bits),
+        "YPowGate" => push_gate(circuit, crate::gates::RY::new(theta), bits),
+        "ZPowGate" if is(1.0) => push_gate(circuit, crate::gates::Z::new(), bits),
+        "ZPowGate" if is(0.5) => push_gate(circuit, crate::gates::S::new(), bits),
+        "ZPowGate" if is(-0.5) => push_gate(circuit, crate::gates::Sdg::new(), bits),
+        "ZPowGate" if is(0.25) => push_gate(circuit, crate::gates::T::new(), bits),
+        "ZPowGate" if is(-0.25) => push_gate(circuit, crate::gates::Tdg::new(), bits),
+        "ZPowGate" => push_gate(circuit, crate::gates::U1::new(theta), bits),
+        "Rx" => push_gate(circuit, crate::gates::RX::new(number(gate, "rads")?), bits),
+        "Ry" => push_gate(circuit, crate::gates::RY::new(number(gate, "rads")?), bits),
+        "Rz" => push_gate(circuit, crate::gates::RZ::new(number(gate, "rads")?), bits),
+        "CXPowGate" | "CNotPowGate" if is(1.0) => push_gate(circuit, crate::gates::CX::new(), bits),
+        "CZPowGate" if is(1.0) => push_gate(circuit, crate::gates::CZ::new(), bits),
+        "CZPowGate" => push_gate(circuit, crate::gates::CU1::new(theta), bits),
+        "SwapPowGate" if is(1.0) => push_gate(circuit, crate::gates::Swap::new(), bits),
+        "CCXPowGate" | "CCNotPowGate" if is(1.0) => push_gate(circuit, crate::gates::CCX::new(), bits),
+        "CCZPowGate" if is(1.0) => push_gate(circuit, crate::gates::CCZ::new(), bits),
+        "CSwapGate" => push_gate(circuit, crate::gates::CSwap::new(), bits),
+        "IdentityGate" => {
+            bits.iter().try_for_each(|&bit| push_gate(circuit, crate::gates::I::new(), &[bit]))
+        },
+        "MeasurementGate" => {
+            let invert_mask = gate.get("invert_mask").and_then(Value::as_array).unwrap_or(&[]);
+            for (i, &bit) in bits.iter().enumerate()
+            {
+                // Results of inverted measurements are flipped, which is done
+                // by flipping the qubit before and after measuring it
+                let invert = invert_mask.get(i).and_then(Value::as_bool).unwrap_or(false);
+                if invert
+                {
+                    push_gate(circuit, crate::gates::X::new(), &[bit])?;
+                }
+                // Enough classical bits were allocated for all measurements
+                circuit.measure(bit, *cbit).map_err(|_| invalid("qubits"))?;
+                if invert
+                {
+                    push_gate(circuit, crate::gates::X::new(), &[bit])?;
+                }
+                *cbit += 1;
+            }
+            Ok(())
+        },
+        _ => Err(unsupported())
+    }
+}
+
+/// Parse a circuit in Cirq JSON format.
+///
+/// Create a new circuit from the description `json` of a Cirq circuit, as
+/// produced by `cirq.to_json()`. See `Circuit::from_cirq_json()` for
+/// details.
+pub fn circuit_from_cirq_json(json: &str) -> crate::error::ParseResult<crate::circuit::Circuit>
+{
+    let value = crate::json::parse(json)?;
+    if cirq_type(&value)? != "Circuit"
+    {
+        return Err(invalid("cirq_type"));
+    }
+
+    let mut ops = vec![];
+    for moment in array(&value, "moments")?
+    {
+        for op in array(moment, "operations")?
+        {
+            ops.push(gate_operation(op)?);
+        }
+    }
+
+    let qubits: ::std::collections::BTreeSet<&QubitId> = ops.iter()
+        .flat_map(|(_, qubits)| qubits.iter())
+        .collect();
+    let indices: ::std::collections::BTreeMap<&QubitId, usize> = qubits.into_iter()
+        .enumerate()
+        .map(|(idx, id)| (id, idx))
+        .collect();
+    let nr_cbits = ops.iter()
+        .filter(|(gate, _)| cirq_type(gate).ok() == Some("MeasurementGate"))
+        .map(|(_, qubits)| qubits.len())
+        .sum();
+
+    let mut circuit = crate::circuit::Circuit::new(indices.len(), nr_cbits);
+    let mut cbit = 0;
+    for (gate, qubits) in ops.iter()
+    {
+        let bits: Vec<usize> = qubits.iter().map(|id| indices[id]).collect();
+        add_gate(&mut circuit, gate, &bits, &mut cbit)?;
+    }
+
+    Ok(circuit)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::circuit_from_cirq_json;
+
+    const BELL: &str = r#"{
+      "cirq_type": "Circuit",
+      "moments": [
+        {
+          "cirq_type": "Moment",
+          "operations": [
+            {
+              "cirq_type": "GateOperation",
+              "gate": { "cirq_type": "HPowGate", "exponent": 1.0, "global_shift": 0.0 },
+              "qubits": [ { "cirq_type": "LineQubit", "x": 3 } ]
+            }
+          ]
+        },
+        {
+          "cirq_type": "Moment",
+          "operations": [
+            {
+              "cirq_type": "GateOperation",
+              "gate": { "cirq_type": "CXPowGate", "exponent": 1.0, "global_shift": 0.0 },
+              "qubits": [
+                { "cirq_type": "LineQubit", "x": 3 },
+                { "cirq_type": "LineQubit", "x": 5 }
+              ]
+            }
+          ]
+        },
+        {
+          "cirq_type": "Moment",
+          "operations": [
+            {
+              "cirq_type": "GateOperation",
+              "gate": {
+                "cirq_type": "MeasurementGate",
+                "num_qubits": 2,
+                "key": "m",
+                "invert_mask": []
+              },
+              "qubits": [
+                { "cirq_type": "LineQubit", "x": 3 },
+                { "cirq_type": "LineQubit", "x": 5 }
+              ]
+            }
+          ]
+        }
+      ],
+      "device": { "cirq_type": "_UnconstrainedDevice" }
+    }"#;
+
+    /// Wrap the operations in `ops` in a single moment of a Cirq circuit
+    fn cirq_circuit(ops: &[&str]) -> String
+    {
+        format!(r#"{{"cirq_type": "Circuit", "moments": [{{"cirq_type": "Moment", "operations": [{}]}}]}}"#,
+            ops.join(", "))
+    }
+
+    /// Cirq operation applying gate `gate` to line qubits `qubits`
+    fn op(gate: &str, qubits: &[usize]) -> String
+    {
+        let qubits: Vec<String> = qubits.iter()
+            .map(|x| format!(r#"{{"cirq_type": "LineQubit", "x": {}}}"#, x))
+            .collect();
+        format!(r#"{{"cirq_type": "GateOperation", "gate": {}, "qubits": [{}]}}"#,
+            gate, qubits.join(", "))
+    }
+
+    #[test]
+    fn test_bell()
+    {
+        let mut circuit = circuit_from_cirq_json(BELL).unwrap();
+        assert_eq!(circuit.nr_qbits(), 2);
+        assert_eq!(circuit.nr_cbits(), 2);
+        assert_eq!(format!("{:?}", circuit.to_instructions()),
+            "[H(0), CX(0, 1), Measure(0, 0, Z), Measure(1, 1, Z)]");
+
+        assert_eq!(circuit.execute(100), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert_eq!(hist.values().sum::<usize>(), 100);
+        assert!(hist.keys().all(|&key| key == 0 || key == 3));
+    }
+
+    #[test]
+    fn test_gates()
+    {
+        let json = cirq_circuit(&[
+            &op(r#"{"cirq_type": "cirq.ZPowGate", "exponent": 0.25, "global_shift": 0.0}"#, &[0]),
+            &op(r#"{"cirq_type": "XPowGate", "exponent": 0.5, "global_shift": 0.0}"#, &[1]),
+            &op(r#"{"cirq_type": "YPowGate", "exponent": 0.1, "global_shift": 0.0}"#, &[2]),
+            &op(r#"{"cirq_type": "Rz", "rads": 0.3}"#, &[0]),
+            &op(r#"{"cirq_type": "CZPowGate", "exponent": 1, "global_shift": 0.0}"#, &[2, 0]),
+            &op(r#"{"cirq_type": "SwapPowGate", "exponent": 1.0, "global_shift": 0.0}"#, &[0, 1]),
+            r#"{"cirq_type": "TaggedOperation", "tags": [], "sub_operation":
+                {"cirq_type": "GateOperation", "gate": {"cirq_type": "IdentityGate", "num_qubits": 1},
+                "qubits": [{"cirq_type": "NamedQubit", "name": "a"}]}}"#,
+            &op(r#"{"cirq_type": "MeasurementGate", "num_qubits": 2, "key": "x",
+                "invert_mask": [false, true]}"#, &[1, 2])
+        ]);
+        let circuit = circuit_from_cirq_json(&json).unwrap();
+        assert_eq!(circuit.nr_qbits(), 4);
+        assert_eq!(circuit.nr_cbits(), 2);
+        let instrs: Vec<String> = circuit.to_instructions().iter()
+            .map(|instr| format!("{:?}", instr))
+            .collect();
+        assert_eq!(instrs.len(), 11);
+        assert_eq!(instrs[0], "T(0)");
+        assert_eq!(instrs[1], "V(1)");
+        assert!(instrs[2].starts_with("RY(") && instrs[3].starts_with("RZ("));
+        assert_eq!(&instrs[4..], ["CZ(2, 0)", "Swap(0, 1)", "I(3)", "Measure(1, 0, Z)",
+            "X(2)", "Measure(2, 1, Z)", "X(2)"]);
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        let json = cirq_circuit(&[
+            &op(r#"{"cirq_type": "HPowGate", "exponent": 0.5, "global_shift": 0.0}"#, &[0])
+        ]);
+        assert_eq!(circuit_from_cirq_json(&json).map(|_| ()),
+            Err(crate::error::ParseError::UnsupportedCirqGate(String::from("HPowGate"))));
+
+        let json = cirq_circuit(&[&op(r#"{"cirq_type": "FSimGate", "theta": 0.5, "phi": 0.2}"#, &[0, 1])]);
+        assert_eq!(circuit_from_cirq_json(&json).map(|_| ()),
+            Err(crate::error::ParseError::UnsupportedCirqGate(String::from("FSimGate"))));
+
+        let json = cirq_circuit(&[
+            &op(r#"{"cirq_type": "CXPowGate", "exponent": 1.0, "global_shift": 0.0}"#, &[0])
+        ]);
+        assert_eq!(circuit_from_cirq_json(&json).map(|_| ()),
+            Err(crate::error::ParseError::InvalidNrBits(1, 2, String::from("CX"))));
+
+        let json = cirq_circuit(&[&op(r#"{"cirq_type": "Rx"}"#, &[0])]);
+        assert_eq!(circuit_from_cirq_json(&json).map(|_| ()),
+            Err(crate::error::ParseError::InvalidCirqJson(String::from("rads"))));
+
+        assert_eq!(circuit_from_cirq_json(r#"{"cirq_type": "Moment", "operations": []}"#).map(|_| ()),
+            Err(crate::error::ParseError::InvalidCirqJson(String::from("cirq_type"))));
+        assert!(matches!(circuit_from_cirq_json(&BELL[..100]),
+            Err(crate::error::ParseError::InvalidJson(_))));
+    }
+}
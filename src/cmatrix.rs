@@ -50,17 +50,199 @@ pub fn kron_mat(a0: &CMatrix, a1: &CMatrix) -> CMatrix
     res
 }
 
+/// Compute the spectral norm of `m`.
+///
+/// Compute the operator 2-norm of matrix `m`, i.e. its largest singular value.
+pub fn spectral_norm(m: &CMatrix) -> f64
+{
+    if m.is_empty()
+    {
+        0.0
+    }
+    else
+    {
+        let (_, sigma, _) = crate::linalg::svd(m);
+        sigma[0]
+    }
+}
+
+/// Check whether `m` is unitary.
+///
+/// Return `true` if `m` is a square matrix for which the spectral norm of
+/// `M`<sup>`†`</sup>`M - I` is less than `tol`, and `false` otherwise. A
+/// matrix that is not square is never unitary, and always results in `false`.
+///
+/// The spectral norm lies between the largest absolute value of the elements
+/// and the Frobenius norm of the difference, so that in most cases these cheap
+/// bounds decide the result, and the singular value decomposition is only
+/// computed for matrices close to the threshold.
+pub fn is_unitary(m: &CMatrix, tol: f64) -> bool
+{
+    if m.rows() != m.cols()
+    {
+        return false;
+    }
+
+    let diff = crate::linalg::adjoint(m).dot(m) - CMatrix::eye(m.rows());
+    let max_elem = diff.iter().map(|c| c.norm()).fold(0.0, f64::max);
+    if max_elem >= tol
+    {
+        return false;
+    }
+    let frobenius = diff.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+    if frobenius < tol
+    {
+        return true;
+    }
+
+    spectral_norm(&diff) < tol
+}
+
+/// Check whether `m` is Hermitian.
+///
+/// Return `true` if `m` is a square matrix for which all elements of
+/// `M - M`<sup>`†`</sup> are less than `tol` in absolute value, and `false`
+/// otherwise.
+pub fn is_hermitian(m: &CMatrix, tol: f64) -> bool
+{
+    if m.rows() != m.cols()
+    {
+        return false;
+    }
+
+    m.indexed_iter().all(|((i, j), &c)| (c - m[[j, i]].conj()).norm() < tol)
+}
+
+/// Logarithm of a unitary matrix.
+///
+/// Compute the logarithm `log(U)` of unitary matrix `u`, such that
+/// `exp(log(U)) = U`. The matrix is diagonalized as `U = VDV`<sup>`†`</sup>,
+/// from which the logarithm is computed as `V log(D) V`<sup>`†`</sup>. The
+/// logarithm of eigenvalue `exp(iφ)`, with `φ` in the interval `(-π, π]`, is
+/// taken to be `i(φ + 2πk)` with `k` equal to `branch`, so that a branch of
+/// zero results in the principal logarithm. For `U = exp(-iHt)`, the
+/// Hamiltonian can then be retrieved as `H = i·log(U)/t`, provided the
+/// eigenvalues of `Ht` lie in the interval `[-π, π)`. If `u` is not a unitary
+/// matrix, a `MatrixNotUnitary` error is returned.
+pub fn matrix_log_unitary(u: &CMatrix, branch: i32) -> crate::error::Result<CMatrix>
+{
+    if !is_unitary(u, 1.0e-10)
+    {
+        return Err(crate::error::Error::MatrixNotUnitary);
+    }
+
+    // The Hermitian matrices A = (U + U†)/2 and B = (U - U†)/2i commute, and
+    // have the same eigenvectors as U. Diagonalize A first, and within
+    // eigenspaces of A with a degenerate eigenvalue, diagonalize B.
+    let n = u.rows();
+    let ud = crate::linalg::adjoint(u);
+    let a = (u + &ud) * 0.5;
+    let b = (u - &ud) * CNumber::new(0.0, -0.5);
+    let (alpha, mut v) = crate::linalg::eigh(&a);
+    let mut start = 0;
+    while start < n
+    {
+        let mut end = start + 1;
+        while end < n && alpha[end] - alpha[end-1] < 1.0e-8
+        {
+            end += 1;
+        }
+        if end > start + 1
+        {
+            let vc = v.slice(s![.., start..end]).to_owned();
+            let (_, w) = crate::linalg::eigh(&crate::linalg::adjoint(&vc).dot(&b).dot(&vc));
+            v.slice_mut(s![.., start..end]).assign(&vc.dot(&w));
+        }
+        start = end;
+    }
+
+    let offset = 2.0 * ::std::f64::consts::PI * f64::from(branch);
+    let mut log_d = CMatrix::zeros((n, n));
+    for k in 0..n
+    {
+        let vk = v.column(k);
+        let lambda = vk.iter().zip(u.dot(&vk).iter()).map(|(x, y)| x.conj() * y).sum::<CNumber>();
+        let mut phi = lambda.arg();
+        if phi <= -::std::f64::consts::PI + 1.0e-12
+        {
+            phi = ::std::f64::consts::PI;
+        }
+        log_d[[k, k]] = CNumber::new(0.0, phi + offset);
+    }
+
+    Ok(v.dot(&log_d).dot(&crate::linalg::adjoint(&v)))
+}
+
+/// Format a real number with at most `precision` decimals, stripping
+/// trailing zeros.
+fn format_real(x: f64, precision: usize) -> String
+{
+    let mut s = format!("{:.*}", precision, x);
+    if s.contains('.')
+    {
+        let len = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(len);
+    }
+    if s == "-0" { String::from("0") } else { s }
+}
+
+/// Format a complex number.
+///
+/// Format complex number `x` as `a+bi`, with real part `a` and imaginary part
+/// `b` rounded to `precision` decimals, and trailing zeros removed. For
+/// example, the number 1/√2 is formatted as "0.707+0i" for a precision of 3.
+pub fn format_complex(x: CNumber, precision: usize) -> String
+{
+    let re = format_real(x.re, precision);
+    let im = format_real(x.im, precision);
+    if im.starts_with('-')
+    {
+        format!("{}{}i", re, im)
+    }
+    else
+    {
+        format!("{}+{}i", re, im)
+    }
+}
+
+/// Format a complex amplitude.
+///
+/// Format complex number `x` as a coefficient in a linear combination, rounded
+/// to `precision` decimals. Real numbers are printed as such, pure imaginary
+/// numbers with a suffix `i`, and numbers with both a real and imaginary
+/// part as `(a+bi)`.
+pub fn format_amplitude(x: CNumber, precision: usize) -> String
+{
+    let re = format_real(x.re, precision);
+    let im = format_real(x.im, precision);
+    if im == "0"
+    {
+        re
+    }
+    else if re == "0"
+    {
+        format!("{}i", im)
+    }
+    else
+    {
+        format!("({})", format_complex(x, precision))
+    }
+}
+
 #[macro_export]
 macro_rules! assert_complex_vector_eq
 {
     ($a0:expr, $a1:expr) => {
+        $crate::assert_complex_vector_eq!($a0, $a1, 1.0e-15)
+    };
+    ($a0:expr, $a1:expr, $tol:expr) => {
         {
             let (n0, n1) = ($a0.len(), $a1.len());
 
             assert!(n0 == n1, "Incompatible array dimensions, {} vs {}", n0, n1);
 
             let diff = $a0 - $a1;
-            let tol = 1.0e-15;
+            let tol = $tol;
             let mut diff_elems = vec![];
             for i in 0..n0
             {
@@ -88,6 +270,9 @@ macro_rules! assert_complex_vector_eq
 macro_rules! assert_complex_matrix_eq
 {
     ($a0:expr, $a1:expr) => {
+        $crate::assert_complex_matrix_eq!($a0, $a1, 1.0e-15)
+    };
+    ($a0:expr, $a1:expr, $tol:expr) => {
         {
             let (n0, m0, n1, m1) = ($a0.rows(), $a0.cols(), $a1.rows(), $a1.cols());
 
@@ -95,7 +280,7 @@ macro_rules! assert_complex_matrix_eq
                 n0, m0, n1, m1);
 
             let diff = $a0 - $a1;
-            let tol = 1.0e-15;
+            let tol = $tol;
             let mut diff_elems = vec![];
             for i in 0..n0
             {
@@ -121,3 +306,169 @@ macro_rules! assert_complex_matrix_eq
         }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::{format_amplitude, format_complex, is_hermitian, is_unitary, matrix_log_unitary,
+        spectral_norm};
+
+    #[test]
+    fn test_spectral_norm()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        assert_eq!(spectral_norm(&crate::cmatrix::CMatrix::zeros((0, 0))), 0.0);
+        assert!((spectral_norm(&array![[3.0*o, z], [z, -4.0*i]]) - 4.0).abs() < 1.0e-12);
+        // Singular values of [[1, 1], [0, 1]] are (1 ± √5)/2 in absolute value
+        let golden = 0.5 * (1.0 + 5.0f64.sqrt());
+        assert!((spectral_norm(&array![[o, o], [z, o]]) - golden).abs() < 1.0e-12);
+        // Non-square matrix
+        assert!((spectral_norm(&array![[o, o, o]]) - 3.0f64.sqrt()).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_is_unitary()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        assert!(is_unitary(&array![[x, x], [x, -x]], 1.0e-12));
+        assert!(is_unitary(&array![[z, i], [o, z]], 1.0e-12));
+        assert!(is_unitary(&array![[o, z, z, z], [z, o, z, z], [z, z, z, o], [z, z, o, z]],
+            1.0e-12));
+        assert!(!is_unitary(&array![[o, o], [z, o]], 1.0e-12));
+        assert!(!is_unitary(&array![[1.001*o, z], [z, o]], 1.0e-3));
+        assert!(is_unitary(&array![[1.001*o, z], [z, o]], 1.0e-2));
+        assert!(!is_unitary(&array![[o, z]], 1.0e-12));
+
+        // Close to the threshold: the largest element of M†M - I is 0.002001,
+        // its Frobenius norm 0.00283, and its spectral norm 0.002001
+        let m = array![[1.001*o, z], [z, 1.001*o]];
+        assert!(is_unitary(&m, 2.5e-3));
+        assert!(!is_unitary(&m, 2.0e-3));
+        // Here M†M - I has diagonal elements 0.02 and off-diagonal elements
+        // 0.21, a spectral norm of 0.44, and a Frobenius norm of 0.516
+        let c = 0.1 * o;
+        let m = array![[o, c, c], [c, o, c], [c, c, o]];
+        assert!(!is_unitary(&m, 0.3));
+        assert!(is_unitary(&m, 0.5));
+    }
+
+    #[test]
+    fn test_is_hermitian()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        assert!(is_hermitian(&array![[o, -i], [i, -o]], 1.0e-12));
+        assert!(is_hermitian(&array![[2.0*o, z], [z, z]], 1.0e-12));
+        assert!(!is_hermitian(&array![[o, i], [i, o]], 1.0e-12));
+        assert!(!is_hermitian(&array![[i, z], [z, o]], 1.0e-12));
+        assert!(!is_hermitian(&array![[o, z]], 1.0e-12));
+    }
+
+    #[test]
+    fn test_matrix_log_unitary()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        let pi = ::std::f64::consts::PI;
+
+        let u = array![[o, z], [z, -o]];
+        assert_complex_matrix_eq!(&matrix_log_unitary(&u, 0).unwrap(),
+            &array![[z, z], [z, pi*i]], 1.0e-12);
+        assert_complex_matrix_eq!(&matrix_log_unitary(&u, 1).unwrap(),
+            &array![[2.0*pi*i, z], [z, 3.0*pi*i]], 1.0e-12);
+        assert_complex_matrix_eq!(&matrix_log_unitary(&crate::cmatrix::CMatrix::eye(3), 0).unwrap(),
+            &crate::cmatrix::CMatrix::zeros((3, 3)), 1.0e-12);
+
+        // Retrieve the Hamiltonian from its time evolution operator
+        let h = array![
+            [0.5*o,  0.2*o-0.1*i,  z],
+            [0.2*o+0.1*i, -o,  0.3*i],
+            [z,     -0.3*i,  0.9*o]
+        ];
+        let t = 1.5;
+        let u = crate::linalg::expm(&(&h * (-i * t)));
+        let log_u = matrix_log_unitary(&u, 0).unwrap();
+        assert_complex_matrix_eq!(&(&log_u * (i / t)), &h, 1.0e-10);
+
+        // Degenerate eigenvalues i and -i of U have the same real part
+        let w = array![[x, x, z], [x, -x, z], [z, z, o]];
+        let u = w.dot(&array![[i, z, z], [z, -i, z], [z, z, o]]).dot(&w);
+        let log_u = matrix_log_unitary(&u, 0).unwrap();
+        let expected = w.dot(&array![[0.5*pi*i, z, z], [z, -0.5*pi*i, z], [z, z, z]]).dot(&w);
+        assert_complex_matrix_eq!(&log_u, &expected, 1.0e-10);
+        assert_complex_matrix_eq!(&crate::linalg::expm(&log_u), &u, 1.0e-10);
+
+        assert_eq!(matrix_log_unitary(&array![[o, o], [z, o]], 0),
+            Err(crate::error::Error::MatrixNotUnitary));
+        assert_eq!(matrix_log_unitary(&array![[o, z]], 0),
+            Err(crate::error::Error::MatrixNotUnitary));
+    }
+
+    #[test]
+    fn test_format_complex()
+    {
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        assert_eq!(format_complex(x, 3), "0.707+0i");
+        assert_eq!(format_complex(-x * i, 3), "0-0.707i");
+        assert_eq!(format_complex(1.5 * x - 0.25 * i, 2), "1.06-0.25i");
+        assert_eq!(format_complex(-1.0e-6 * x, 3), "0+0i");
+
+        assert_eq!(format_amplitude(-x, 3), "-0.707");
+        assert_eq!(format_amplitude(x * i, 3), "0.707i");
+        assert_eq!(format_amplitude(0.5 * (x + x * i), 3), "(0.354+0.354i)");
+    }
+
+    #[test]
+    fn test_assert_complex_vector_eq_tolerance()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let v0 = array![o, 2.0*o];
+        let v1 = array![o, (2.0+1.0e-10)*o];
+        assert_complex_vector_eq!(&v0, &v0);
+        assert_complex_vector_eq!(&v0, &v1, 1.0e-9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assert_complex_vector_eq_fail()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let v0 = array![o, 2.0*o];
+        let v1 = array![o, (2.0+1.0e-10)*o];
+        assert_complex_vector_eq!(&v0, &v1, 1.0e-11);
+    }
+
+    #[test]
+    fn test_assert_complex_matrix_eq_tolerance()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let m0 = array![[o, i], [-i, o]];
+        let m1 = array![[o, (1.0+1.0e-7)*i], [-i, o]];
+        assert_complex_matrix_eq!(&m0, &m0);
+        assert_complex_matrix_eq!(&m0, &m1, 1.0e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assert_complex_matrix_eq_fail()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let m0 = array![[o, i], [-i, o]];
+        let m1 = array![[o, (1.0+1.0e-7)*i], [-i, o]];
+        assert_complex_matrix_eq!(&m0, &m1);
+    }
+}
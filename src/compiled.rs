@@ -0,0 +1,429 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compiled circuits
+//!
+//! This module provides circuits that can be evaluated repeatedly for different
+//! values of their parameters, as is done in e.g. variational algorithms. A
+//! compiled circuit caches the intermediate quantum states of its evaluation,
+//! so that only the part of the circuit following the first gate whose
+//! parameters changed needs to be recomputed.
+
+use crate::export::CircuitGate;
+use crate::gates::Gate;
+
+/// A set of parameter values.
+///
+/// Struct `ParameterMap` maps the names of reference parameters (see
+/// `Parameter::from_refcell()`) to their values, to be used when evaluating a
+/// `CompiledCircuit`.
+#[derive(Clone, Debug, Default)]
+pub struct ParameterMap
+{
+    /// The values of the parameters, by name
+    values: ::std::collections::HashMap<String, f64>
+}
+
+impl ParameterMap
+{
+    /// Create a new, empty, parameter map.
+    pub fn new() -> Self
+    {
+        ParameterMap { values: ::std::collections::HashMap::new() }
+    }
+
+    /// Set the value of the parameter with name `name` to `value`.
+    pub fn set(&mut self, name: &str, value: f64)
+    {
+        self.values.insert(String::from(name), value);
+    }
+
+    /// Return the value of the parameter with name `name`, or `None` if it
+    /// has not been set in this map.
+    pub fn get(&self, name: &str) -> Option<f64>
+    {
+        self.values.get(name).cloned()
+    }
+}
+
+/// A single gate in a compiled circuit, along with the quantum state resulting
+/// from its application
+struct Node
+{
+    /// The gate to apply
+    gate: Box<dyn CircuitGate>,
+    /// The bits on which the gate acts
+    bits: Vec<usize>,
+    /// The parameter values with which `state` was computed
+    values: Vec<f64>,
+    /// The state after applying the gate, if computed
+    state: Option<crate::cmatrix::CVector>
+}
+
+impl Node
+{
+    /// Return the current values of the parameters of the gate in this node
+    fn current_values(&self) -> Vec<f64>
+    {
+        self.gate.parameters().iter().map(|p| p.value()).collect()
+    }
+}
+
+/// A compiled circuit
+///
+/// Struct `CompiledCircuit` holds a circuit that is prepared for repeated
+/// evaluation with different parameter values. The gates up to the first
+/// measurement, reset, or other non-unitary operation in the circuit form
+/// a chain of nodes, each of which stores the coefficient vector after its
+/// gate is applied. When the circuit is evaluated again, only the gates
+/// starting from the first one whose parameter values changed are reapplied.
+/// The remaining operations are performed from the resulting state in each
+/// evaluation. Note that caching the states takes memory proportional to the
+/// number of gates times 2<sup>`n`</sup> for a circuit on `n` qubits.
+pub struct CompiledCircuit
+{
+    /// The number of qubits in the circuit
+    nr_qbits: usize,
+    /// The unitary part of the circuit
+    nodes: Vec<Node>,
+    /// The reference parameters used in the gates, with their names
+    cells: Vec<(String, ::std::rc::Rc<::std::cell::RefCell<f64>>)>,
+    /// The operations following the unitary part of the circuit
+    tail: crate::circuit::Circuit
+}
+
+impl CompiledCircuit
+{
+    /// Create a new compiled circuit on `nr_qbits` qubits, with unitary part
+    /// `gates`, followed by the operations in `tail`.
+    pub(crate) fn new(nr_qbits: usize, gates: Vec<(Box<dyn CircuitGate>, Vec<usize>)>,
+        tail: crate::circuit::Circuit) -> Self
+    {
+        let mut cells: Vec<(String, ::std::rc::Rc<::std::cell::RefCell<f64>>)> = vec![];
+        for (gate, _) in gates.iter()
+        {
+            for param in gate.parameters()
+            {
+                if let crate::gates::Parameter::Reference(ref cell, ref name) = *param
+                {
+                    if !cells.iter().any(|(_, c)| ::std::rc::Rc::ptr_eq(c, cell))
+                    {
+                        cells.push((name.clone(), cell.clone()));
+                    }
+                }
+            }
+        }
+
+        let nodes = gates.into_iter().map(|(gate, bits)| {
+            Node { gate: gate, bits: bits, values: vec![], state: None }
+        }).collect();
+
+        CompiledCircuit
+        {
+            nr_qbits: nr_qbits,
+            nodes: nodes,
+            cells: cells,
+            tail: tail
+        }
+    }
+
+    /// The number of quantum bits in this circuit
+    pub fn nr_qbits(&self) -> usize
+    {
+        self.nr_qbits
+    }
+
+    /// Set the values of the reference parameters in this circuit to those
+    /// in `params`. All names are checked before any value is changed, so
+    /// that on an `UnknownVariable` error the parameters keep their old values.
+    fn set_parameters(&self, params: &ParameterMap) -> crate::error::Result<()>
+    {
+        if let Some(name) = params.values.keys()
+            .find(|name| !self.cells.iter().any(|(n, _)| n == *name))
+        {
+            return Err(crate::error::Error::UnknownVariable(name.clone()));
+        }
+
+        for (name, &value) in params.values.iter()
+        {
+            for (_, cell) in self.cells.iter().filter(|(n, _)| n == name)
+            {
+                *cell.borrow_mut() = value;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the index of the first node whose cached state is no longer
+    /// valid for the current parameter values.
+    fn first_changed_node(&self) -> usize
+    {
+        self.nodes.iter()
+            .position(|node| node.state.is_none() || node.values != node.current_values())
+            .unwrap_or(self.nodes.len())
+    }
+
+    /// Compute the state at the end of the unitary part of the circuit, reusing
+    /// cached states where possible.
+    fn final_state(&mut self) -> crate::cmatrix::CVector
+    {
+        let start = self.first_changed_node();
+        let mut state = if start == 0
+            {
+                let mut state = crate::cmatrix::CVector::zeros(1 << self.nr_qbits);
+                state[0] = crate::cmatrix::COMPLEX_ONE;
+                state
+            }
+            else
+            {
+                self.nodes[start-1].state.clone().unwrap()
+            };
+
+        for node in self.nodes[start..].iter_mut()
+        {
+            crate::gates::apply_gate_slice(state.view_mut(), &node.gate, &node.bits,
+                self.nr_qbits);
+            node.values = node.current_values();
+            node.state = Some(state.clone());
+        }
+
+        state
+    }
+
+    /// Evaluate this circuit.
+    ///
+    /// Evaluate this circuit with the parameter values from `params`, running
+    /// the operations after the unitary part of the circuit `nr_shots` times,
+    /// and return a histogram of the measured classical bits. Parameters not
+    /// found in `params` keep their current value. If `params` contains
+    /// a parameter that does not occur in the circuit, an `UnknownVariable`
+    /// error is returned.
+    #[inline(always)]
+    pub fn execute_with_params(&mut self, params: &ParameterMap, nr_shots: usize)
+        -> crate::error::Result<crate::idhash::U64HashMap<usize>>
+    {
+        self.execute_with_params_and_rng(params, nr_shots, &mut rand::thread_rng())
+    }
+
+    /// Evaluate this circuit.
+    ///
+    /// Evaluate this circuit with the parameter values from `params`, running
+    /// the operations after the unitary part of the circuit `nr_shots` times
+    /// using random number generator `rng`, and return a histogram of the
+    /// measured classical bits. If `params` contains a parameter that does not
+    /// occur in the circuit, an `UnknownVariable` error is returned.
+    pub fn execute_with_params_and_rng<R: rand::RngCore>(&mut self, params: &ParameterMap,
+        nr_shots: usize, rng: &mut R)
+        -> crate::error::Result<crate::idhash::U64HashMap<usize>>
+    {
+        self.set_parameters(params)?;
+        let state = self.final_state();
+        let q_state = crate::circuit::QuStateRepr::Vector(
+            crate::vectorstate::VectorState::from_coefficients(state, nr_shots));
+        self.tail.execute_with(nr_shots, rng, q_state)?;
+        self.tail.histogram()
+    }
+}
+
+/// A cache of values computed from circuits.
+///
+/// Struct `CircuitCache` stores values computed from a circuit, e.g. the
+/// results of compiling it, keyed by the structural hash of the circuit (see
+/// `Circuit::structural_hash()`). Since the structural hash does not depend
+/// on the parameters of gates, a value computed for one circuit is reused
+/// for all circuits with the same structure, so it should only be used for
+/// values that are independent of the gate parameters.
+pub struct CircuitCache<V>
+{
+    /// The cached values, by structural hash of the circuit
+    values: crate::idhash::U64HashMap<V>
+}
+
+impl<V> CircuitCache<V>
+{
+    /// Create a new, empty, cache.
+    pub fn new() -> Self
+    {
+        CircuitCache { values: crate::idhash::new_u64_hash_map() }
+    }
+
+    /// The number of values stored in this cache
+    pub fn len(&self) -> usize
+    {
+        self.values.len()
+    }
+
+    /// Return whether this cache is empty
+    pub fn is_empty(&self) -> bool
+    {
+        self.values.is_empty()
+    }
+
+    /// Return the value stored for circuits with the same structure as
+    /// `circuit`, or `None` if no such value is stored.
+    pub fn get(&self, circuit: &crate::circuit::Circuit) -> Option<&V>
+    {
+        self.values.get(&circuit.structural_hash())
+    }
+
+    /// Return the value stored for circuits with the same structure as
+    /// `circuit`. If no such value is stored yet, it is computed by calling
+    /// `f` with `circuit`, and stored in the cache.
+    pub fn get_or_insert<F>(&mut self, circuit: &crate::circuit::Circuit, f: F) -> &V
+    where F: FnOnce(&crate::circuit::Circuit) -> V
+    {
+        self.values.entry(circuit.structural_hash()).or_insert_with(|| f(circuit))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{CircuitCache, ParameterMap};
+    use crate::circuit::Circuit;
+    use crate::gates::Parameter;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_parameter_map()
+    {
+        let mut params = ParameterMap::new();
+        assert_eq!(params.get("theta"), None);
+        params.set("theta", 0.5);
+        params.set("phi", -1.25);
+        assert_eq!(params.get("theta"), Some(0.5));
+        assert_eq!(params.get("phi"), Some(-1.25));
+        params.set("theta", 2.0);
+        assert_eq!(params.get("theta"), Some(2.0));
+    }
+
+    #[test]
+    fn test_execute_with_params()
+    {
+        let theta = ::std::rc::Rc::new(::std::cell::RefCell::new(0.0));
+        let phi = ::std::rc::Rc::new(::std::cell::RefCell::new(0.0));
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.ry(Parameter::from_refcell(&theta, "theta"), 0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.ry(Parameter::from_refcell(&phi, "phi"), 1), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+
+        let mut compiled = circuit.compile();
+        assert_eq!(compiled.nr_qbits(), 2);
+        assert_eq!(compiled.first_changed_node(), 0);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(15);
+        let nr_shots = 1000;
+
+        let mut params = ParameterMap::new();
+        params.set("theta", ::std::f64::consts::PI);
+        params.set("phi", 0.0);
+        let hist = compiled.execute_with_params_and_rng(&params, nr_shots, &mut rng).unwrap();
+        assert_eq!(hist.len(), 1);
+        assert_eq!(hist.get(&3), Some(&nr_shots));
+        assert_eq!(*theta.borrow(), ::std::f64::consts::PI);
+        assert_eq!(compiled.first_changed_node(), 3);
+
+        // Only the last gate needs to be recomputed
+        params.set("phi", ::std::f64::consts::PI);
+        assert_eq!(compiled.set_parameters(&params), Ok(()));
+        assert_eq!(compiled.first_changed_node(), 2);
+        let hist = compiled.execute_with_params_and_rng(&params, nr_shots, &mut rng).unwrap();
+        assert_eq!(hist.len(), 1);
+        assert_eq!(hist.get(&1), Some(&nr_shots));
+
+        // Changing a parameter outside the map is picked up as well
+        *theta.borrow_mut() = 0.5 * ::std::f64::consts::PI;
+        assert_eq!(compiled.first_changed_node(), 0);
+        let hist = compiled.execute_with_params_and_rng(&ParameterMap::new(), nr_shots,
+            &mut rng).unwrap();
+        assert_eq!(hist.len(), 2);
+        assert!(hist.contains_key(&1) && hist.contains_key(&2));
+        assert_eq!(hist.values().sum::<usize>(), nr_shots);
+
+        // (|01⟩ - |10⟩)/√2
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        assert_complex_vector_eq!(&compiled.final_state(), &array![z, x, -x, z]);
+
+        params.set("lambda", 1.0);
+        assert_eq!(compiled.execute_with_params(&params, nr_shots),
+            Err(crate::error::Error::UnknownVariable(String::from("lambda"))));
+
+        // A failed update leaves all parameters unchanged
+        let mut params = ParameterMap::new();
+        params.set("theta", 0.25);
+        params.set("lambda", 1.0);
+        params.set("phi", 0.75);
+        assert_eq!(compiled.set_parameters(&params),
+            Err(crate::error::Error::UnknownVariable(String::from("lambda"))));
+        assert_eq!(*theta.borrow(), 0.5 * ::std::f64::consts::PI);
+        assert_eq!(*phi.borrow(), ::std::f64::consts::PI);
+    }
+
+    #[test]
+    fn test_execute_with_params_tail()
+    {
+        let theta = ::std::rc::Rc::new(::std::cell::RefCell::new(0.0));
+
+        // Gates after a measurement are applied in every evaluation
+        let mut circuit = Circuit::new(1, 2);
+        assert_eq!(circuit.rx(Parameter::from_refcell(&theta, "theta"), 0), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.measure(0, 1), Ok(()));
+
+        let mut compiled = circuit.compile();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut params = ParameterMap::new();
+
+        params.set("theta", 0.0);
+        let hist = compiled.execute_with_params_and_rng(&params, 100, &mut rng).unwrap();
+        assert_eq!(hist.get(&2), Some(&100));
+
+        params.set("theta", ::std::f64::consts::PI);
+        let hist = compiled.execute_with_params_and_rng(&params, 100, &mut rng).unwrap();
+        assert_eq!(hist.get(&1), Some(&100));
+    }
+
+    #[test]
+    fn test_circuit_cache()
+    {
+        let circuit = |theta: f64, bit: usize| {
+            let mut circuit = Circuit::new(2, 0);
+            circuit.h(0).unwrap();
+            circuit.rx(theta, bit).unwrap();
+            circuit
+        };
+
+        let mut cache = CircuitCache::new();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&circuit(0.5, 1)), None);
+
+        let mut nr_calls = 0;
+        assert_eq!(*cache.get_or_insert(&circuit(0.5, 1), |c| { nr_calls += 1; c.total_cost() }),
+            circuit(0.5, 1).total_cost());
+        assert_eq!(*cache.get_or_insert(&circuit(1.5, 1), |c| { nr_calls += 1; c.total_cost() }),
+            circuit(0.5, 1).total_cost());
+        assert_eq!(nr_calls, 1);
+        assert_eq!(cache.len(), 1);
+
+        cache.get_or_insert(&circuit(1.5, 0), |c| { nr_calls += 1; c.total_cost() });
+        assert_eq!(nr_calls, 2);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&circuit(-0.3, 0)).is_some());
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decomposition of unitary matrices
+//!
+//! This module decomposes arbitrary single- and two-qubit unitaries into
+//! standard gates. Single-qubit unitaries are written as a `U`<sub>`3`</sub>
+//! gate using Euler angles, while two-qubit unitaries are brought into the
+//! canonical (KAK) form of local gates around a non-local core
+//! `exp(i(aXX + bYY + cZZ))`.
+
+use crate::cmatrix::{CMatrix, CNumber};
+use crate::instruction::Instruction;
+
+/// Mixing coefficient for the simultaneous diagonalization of the real and
+/// imaginary parts of a symmetric unitary. Any value works, except for a
+/// few that accidentally make distinct eigenvalues degenerate.
+const MIXING_COEFFICIENT: f64 = 0.754_877_666_246_693;
+
+/// Euler decomposition of a single-qubit gate.
+///
+/// Compute the angles `(θ, ϕ, λ)` such that the 2×2 unitary matrix `m` equals
+/// `U`<sub>`3`</sub>`(θ, ϕ, λ) = R`<sub>`Z`</sub>`(ϕ)R`<sub>`Y`</sub>`(θ)R`<sub>`Z`</sub>`(λ)`
+/// up to a global phase.
+pub fn euler_decompose(m: &CMatrix) -> (f64, f64, f64)
+{
+    crate::gates::u3_angles(m)
+}
+
+/// Canonical decomposition of a two-qubit gate
+///
+/// Struct `KakDecomposition` describes a two-qubit unitary `U` as
+/// ```text
+/// U = exp(iγ) (A0⊗A1) exp(i(aXX + bYY + cZZ)) (B0⊗B1)
+/// ```
+/// where `A0`, `A1`, `B0` and `B1` are single-qubit unitaries, with `A0` and
+/// `B0` acting on the first qubit.
+#[derive(Clone, Debug)]
+pub struct KakDecomposition
+{
+    /// The global phase `γ`
+    pub global_phase: f64,
+    /// The local gates `(A0, A1)` applied after the non-local core
+    pub after: (CMatrix, CMatrix),
+    /// The coefficients `(a, b, c)` of the non-local core
+    pub interaction: (f64, f64, f64),
+    /// The local gates `(B0, B1)` applied before the non-local core
+    pub before: (CMatrix, CMatrix)
+}
+
+impl KakDecomposition
+{
+    /// The matrix of the unitary described by this decomposition
+    pub fn matrix(&self) -> CMatrix
+    {
+        let (a, b, c) = self.interaction;
+        let core = crate::linalg::expm(
+            &((pauli_pair(0) * a + pauli_pair(1) * b + pauli_pair(2) * c)
+                * crate::cmatrix::COMPLEX_I)
+        );
+        let after = crate::cmatrix::kron_mat(&self.after.0, &self.after.1);
+        let before = crate::cmatrix::kron_mat(&self.before.0, &self.before.1);
+        after.dot(&core).dot(&before) * CNumber::from_polar(&1.0, &self.global_phase)
+    }
+
+    /// Gate instructions implementing this decomposition on qubits `bits`, up
+    /// to the global phase.
+    pub fn instructions(&self, bits: &[usize]) -> Vec<Instruction>
+    {
+        let (b0, b1) = (bits[0], bits[1]);
+        let mut res = vec![u3_instruction(&self.before.0, b0), u3_instruction(&self.before.1, b1)];
+
+        // exp(iaXX) = exp(-i(-a)XX), implemented as a ZZ rotation after
+        // rotating the X or Y operators to Z
+        let (a, b, c) = self.interaction;
+        res.extend(vec![Instruction::H(b0), Instruction::H(b1)]);
+        res.extend(zz_rotation(-a, b0, b1));
+        res.extend(vec![Instruction::H(b0), Instruction::H(b1)]);
+        res.extend(vec![Instruction::Sdg(b0), Instruction::H(b0), Instruction::Sdg(b1), Instruction::H(b1)]);
+        res.extend(zz_rotation(-b, b0, b1));
+        res.extend(vec![Instruction::H(b0), Instruction::S(b0), Instruction::H(b1), Instruction::S(b1)]);
+        res.extend(zz_rotation(-c, b0, b1));
+
+        res.push(u3_instruction(&self.after.0, b0));
+        res.push(u3_instruction(&self.after.1, b1));
+        res
+    }
+}
+
+/// The tensor product `σ⊗σ` of Pauli operator `X`, `Y` or `Z` with itself,
+/// for `idx` equal to 0, 1 or 2 respectively.
+fn pauli_pair(idx: usize) -> CMatrix
+{
+    const OPS: [crate::stabilizer::PauliOp; 3] = [
+        crate::stabilizer::PauliOp::X,
+        crate::stabilizer::PauliOp::Y,
+        crate::stabilizer::PauliOp::Z
+    ];
+    crate::stabilizer::PauliString::new(&[OPS[idx], OPS[idx]]).matrix()
+}
+
+/// The magic basis, in which local gates `A⊗B` with `A` and `B` in `SU(2)`
+/// become real orthogonal matrices
+fn magic_basis() -> CMatrix
+{
+    let z = crate::cmatrix::COMPLEX_ZERO;
+    let h = crate::cmatrix::COMPLEX_HSQRT2;
+    let i = crate::cmatrix::COMPLEX_I * h;
+    array![
+        [h, z,  z,  i],
+        [z, i,  h,  z],
+        [z, i, -h,  z],
+        [h, z,  z, -i]
+    ]
+}
+
+/// Instruction for a `U`<sub>`3`</sub> gate equal to the single-qubit unitary
+/// `m` up to a global phase, on qubit `bit`
+fn u3_instruction(m: &CMatrix, bit: usize) -> Instruction
+{
+    let (theta, phi, lambda) = euler_decompose(m);
+    Instruction::U3(theta, phi, lambda, bit)
+}
+
+/// Instructions for `exp(-iθZZ)` on qubits `b0` and `b1`
+fn zz_rotation(theta: f64, b0: usize, b1: usize) -> Vec<Instruction>
+{
+    vec![Instruction::CX(b0, b1), Instruction::RZ(2.0 * theta, b1), Instruction::CX(b0, b1)]
+}
+
+/// Factor a local two-qubit operator.
+///
+/// Write the 4×4 matrix `m`, which should be a tensor product, as `A⊗B`, with
+/// `B` scaled to have determinant 1.
+fn factor_local(m: &CMatrix) -> (CMatrix, CMatrix)
+{
+    // The block of m with the largest norm is proportional to B
+    let (r, c) = (0..2).flat_map(|r| (0..2).map(move |c| (r, c)))
+        .max_by(|&(r0, c0), &(r1, c1)| {
+            let n0: f64 = m.slice(s![2*r0..2*r0+2, 2*c0..2*c0+2]).iter().map(|x| x.norm_sqr()).sum();
+            let n1: f64 = m.slice(s![2*r1..2*r1+2, 2*c1..2*c1+2]).iter().map(|x| x.norm_sqr()).sum();
+            n0.partial_cmp(&n1).unwrap()
+        })
+        .unwrap();
+    let block = m.slice(s![2*r..2*r+2, 2*c..2*c+2]).to_owned();
+    let b = &block / crate::linalg::determinant(&block).sqrt();
+
+    let norm: f64 = b.iter().map(|x| x.norm_sqr()).sum();
+    let mut a = CMatrix::zeros((2, 2));
+    for i in 0..2
+    {
+        for j in 0..2
+        {
+            let blk = m.slice(s![2*i..2*i+2, 2*j..2*j+2]);
+            a[[i, j]] = blk.iter().zip(b.iter()).map(|(x, y)| x * y.conj()).sum::<CNumber>() / norm;
+        }
+    }
+    (a, b)
+}
+
+/// Canonical decomposition of a two-qubit gate.
+///
+/// Compute the KAK decomposition of the 4×4 unitary matrix `m`, see
+/// `KakDecomposition`. The unitary is transformed to the magic basis, in
+/// which local gates are real orthogonal and the non-local core is diagonal,
+/// after which the local gates are found by simultaneously diagonalizing the
+/// real and imaginary parts of the symmetric unitary `U`<sup>`T`</sup>`U`.
+/// This function panics if `m` is not a 4×4 matrix.
+pub fn kak_decompose(m: &CMatrix) -> KakDecomposition
+{
+    assert!(m.rows() == 4 && m.cols() == 4, "The KAK decomposition requires a 4×4 matrix");
+
+    let magic = magic_basis();
+    let magic_adj = crate::linalg::adjoint(&magic);
+    let phase = crate::linalg::determinant(m).sqrt().sqrt();
+    let ub = magic_adj.dot(&(m / phase)).dot(&magic);
+
+    // U_B^T U_B = O2^T D² O2
+    let p = ub.t().dot(&ub);
+    let mixed = p.mapv(|x| x.re + MIXING_COEFFICIENT * x.im);
+    let (_, vs) = crate::linalg::eigh(&mixed.mapv(|x| CNumber::new(x, 0.0)));
+    let mut o2 = vs.mapv(|x| x.re).reversed_axes();
+    if crate::linalg::determinant(&o2.mapv(|x| CNumber::new(x, 0.0))).re < 0.0
+    {
+        o2.row_mut(0).mapv_inplace(|x| -x);
+    }
+    let o2c = o2.mapv(|x| CNumber::new(x, 0.0));
+    let d2 = o2c.dot(&p).dot(&o2c.t());
+    let mut d: Vec<CNumber> = (0..4).map(|k| d2[[k, k]].sqrt()).collect();
+
+    // U_B = O1 D O2
+    let mut o1 = ub.dot(&o2c.t());
+    for (k, &dk) in d.iter().enumerate()
+    {
+        o1.column_mut(k).mapv_inplace(|x| x / dk);
+    }
+    if crate::linalg::determinant(&o1.mapv(|x| CNumber::new(x.re, 0.0))).re < 0.0
+    {
+        d[0] = -d[0];
+        o1.column_mut(0).mapv_inplace(|x| -x);
+    }
+    let o1 = o1.mapv(|x| CNumber::new(x.re, 0.0));
+
+    // Coefficients of the core, from the diagonal of XX, YY and ZZ in the
+    // magic basis
+    let thetas: Vec<f64> = d.iter().map(|x| x.arg()).collect();
+    let diag: Vec<Vec<f64>> = (0..3).map(|idx| {
+            let pp = magic_adj.dot(&pauli_pair(idx)).dot(&magic);
+            (0..4).map(|k| pp[[k, k]].re).collect()
+        })
+        .collect();
+    let coef = |idx: usize| -> f64 {
+        diag[idx].iter().zip(thetas.iter()).map(|(x, t)| x * t).sum::<f64>() / 4.0
+    };
+
+    let after = factor_local(&magic.dot(&o1).dot(&magic_adj));
+    let before = factor_local(&magic.dot(&o2c).dot(&magic_adj));
+    let mut res = KakDecomposition
+    {
+        global_phase: 0.0,
+        after: after,
+        interaction: (coef(0), coef(1), coef(2)),
+        before: before
+    };
+
+    let overlap: CNumber = res.matrix().iter().zip(m.iter()).map(|(x, y)| x.conj() * y).sum();
+    res.global_phase = overlap.arg();
+    res
+}
+
+/// Decompose a unitary into standard gates.
+///
+/// Return instructions implementing the unitary `m`, acting on qubits `bits`,
+/// up to a global phase, using `euler_decompose()` for single-qubit gates and
+/// `kak_decompose()` for two-qubit gates. For unitaries on more qubits, `None`
+/// is returned.
+pub fn decompose_instructions(m: &CMatrix, bits: &[usize]) -> Option<Vec<Instruction>>
+{
+    match bits.len()
+    {
+        0 => Some(vec![]),
+        1 => Some(vec![u3_instruction(m, bits[0])]),
+        2 => Some(kak_decompose(m).instructions(bits)),
+        _ => None
+    }
+}
+
+/// Placeholder for a gate that could not be decomposed.
+///
+/// Struct `UndecomposedGate` replaces a gate on more than two qubits when
+/// exporting a circuit with decomposed custom gates. Its OpenQasm export is a
+/// comment listing the matrix of the original gate.
+#[derive(Clone)]
+pub(crate) struct UndecomposedGate
+{
+    desc: String,
+    matrix: CMatrix
+}
+
+impl UndecomposedGate
+{
+    /// Create a new placeholder for the gate with description `desc` and
+    /// matrix `matrix`.
+    pub(crate) fn new(desc: &str, matrix: CMatrix) -> Self
+    {
+        UndecomposedGate { desc: desc.to_owned(), matrix: matrix }
+    }
+}
+
+impl crate::gates::Gate for UndecomposedGate
+{
+    fn cost(&self) -> f64
+    {
+        0.0
+    }
+
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        (self.matrix.rows() as f64).log2().round() as usize
+    }
+
+    fn matrix(&self) -> CMatrix
+    {
+        self.matrix.clone()
+    }
+}
+
+impl crate::export::OpenQasm for UndecomposedGate
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize]) -> crate::error::Result<String>
+    {
+        let names: Vec<&str> = bits.iter().map(|&b| bit_names[b].as_str()).collect();
+        Ok(format!("// No decomposition for {:.6} on {}", crate::gates::GateDisplay(self),
+            names.join(", ")))
+    }
+}
+impl crate::export::CQasm for UndecomposedGate {}
+impl crate::export::Latex for UndecomposedGate {}
+impl crate::export::TketJson for UndecomposedGate {}
+impl crate::instruction::ToInstruction for UndecomposedGate {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{decompose_instructions, euler_decompose, kak_decompose, magic_basis, pauli_pair};
+    use crate::cmatrix::{CMatrix, CNumber};
+    use crate::gates::{Gate, CX, CZ, H, RY, Swap, T, U3};
+
+    /// Check that `m0` and `m1` are equal up to a global phase.
+    fn assert_equal_up_to_phase(m0: &CMatrix, m1: &CMatrix)
+    {
+        let overlap: CNumber = m0.iter().zip(m1.iter()).map(|(x, y)| x.conj() * y).sum();
+        let phase = overlap / overlap.norm();
+        assert_complex_matrix_eq!(&(m0 * phase), m1, 1.0e-9);
+    }
+
+    fn instructions_matrix(instrs: &[crate::instruction::Instruction], nr_bits: usize) -> CMatrix
+    {
+        let circuit = crate::circuit::Circuit::from_instructions(instrs, nr_bits, 0).unwrap();
+        crate::gates::Composite::from_circuit("decomposed", &circuit).unwrap().matrix()
+    }
+
+    /// A fixed, non-trivial two-qubit unitary
+    fn random_unitary() -> CMatrix
+    {
+        let u0 = crate::cmatrix::kron_mat(&U3::new(0.3, 1.2, -0.7).matrix(),
+            &U3::new(2.1, -0.4, 0.9).matrix());
+        let u1 = crate::cmatrix::kron_mat(&U3::new(-1.3, 0.2, 0.5).matrix(),
+            &U3::new(0.6, 2.4, -1.9).matrix());
+        let core = crate::linalg::expm(&((pauli_pair(0) * 0.41 + pauli_pair(1) * -0.23
+            + pauli_pair(2) * 0.67) * crate::cmatrix::COMPLEX_I));
+        u0.dot(&CX::new().matrix()).dot(&core).dot(&u1)
+            * CNumber::from_polar(&1.0, &0.3)
+    }
+
+    #[test]
+    fn test_magic_basis()
+    {
+        let magic = magic_basis();
+        let adj = crate::linalg::adjoint(&magic);
+        assert!(crate::cmatrix::is_unitary(&magic, 1.0e-12));
+        for idx in 0..3
+        {
+            let pp = adj.dot(&pauli_pair(idx)).dot(&magic);
+            for ((i, j), x) in pp.indexed_iter()
+            {
+                assert!(i == j || x.norm() < 1.0e-12);
+            }
+        }
+        let local = adj.dot(&crate::cmatrix::kron_mat(&RY::new(0.3).matrix(),
+            &(H::new().matrix() * crate::cmatrix::COMPLEX_I))).dot(&magic);
+        assert!(local.iter().all(|x| x.im.abs() < 1.0e-12));
+    }
+
+    #[test]
+    fn test_euler_decompose()
+    {
+        let m = T::new().matrix().dot(&H::new().matrix());
+        let (theta, phi, lambda) = euler_decompose(&m);
+        assert_equal_up_to_phase(&U3::new(theta, phi, lambda).matrix(), &m);
+    }
+
+    #[test]
+    fn test_kak_decompose()
+    {
+        let cz = CZ::new().matrix();
+        for m in [CX::new().matrix(), cz.clone(), Swap::new().matrix(), random_unitary(),
+            CMatrix::eye(4), crate::cmatrix::kron_mat(&H::new().matrix(), &T::new().matrix())].iter()
+        {
+            let kak = kak_decompose(m);
+            assert!(crate::cmatrix::is_unitary(&kak.after.0, 1.0e-10));
+            assert!(crate::cmatrix::is_unitary(&kak.before.1, 1.0e-10));
+            assert_complex_matrix_eq!(&kak.matrix(), m, 1.0e-9);
+            assert_equal_up_to_phase(&instructions_matrix(&kak.instructions(&[0, 1]), 2), m);
+        }
+
+        // Reversed qubits
+        let kak = kak_decompose(&cz);
+        assert_equal_up_to_phase(&instructions_matrix(&kak.instructions(&[1, 0]), 2), &cz);
+    }
+
+    #[test]
+    fn test_decompose_instructions()
+    {
+        let m = random_unitary();
+        let instrs = decompose_instructions(&m, &[2, 0]).unwrap();
+        let expected = crate::gates::Composite::from_circuit("u", &{
+                let mut circuit = crate::circuit::Circuit::new(3, 0);
+                circuit.add_gate(crate::gates::Custom::new("U", m.clone()), &[2, 0]).unwrap();
+                circuit
+            }).unwrap().matrix();
+        assert_equal_up_to_phase(&instructions_matrix(&instrs, 3), &expected);
+        assert_eq!(decompose_instructions(&CMatrix::eye(8), &[0, 1, 2]), None);
+    }
+}
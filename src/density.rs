@@ -0,0 +1,428 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+
+/// Gate wrapping a plain matrix.
+///
+/// Used to let the general gate application machinery operate with Kraus
+/// operators, which need not be unitary.
+struct MatrixOp<'a>
+{
+    /// The matrix to apply
+    matrix: &'a crate::cmatrix::CMatrix,
+    /// The number of bits this matrix operates on
+    nr_bits: usize
+}
+
+impl<'a> crate::gates::Gate for MatrixOp<'a>
+{
+    fn description(&self) -> &str
+    {
+        "K"
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        self.matrix.clone()
+    }
+}
+
+/// Density matrix
+///
+/// Struct `DensityMatrix` describes the (possibly mixed) state of a collection
+/// of qubits, as a `2`<sup>`n`</sup>`×2`<sup>`n`</sup> density matrix `ρ`.
+/// In contrast to the coefficient vector representation, this allows for the
+/// simulation of noise processes.
+#[derive(Clone)]
+pub struct DensityMatrix
+{
+    /// The number of qubits in the system
+    nr_bits: usize,
+    /// The density matrix itself
+    rho: crate::cmatrix::CMatrix
+}
+
+impl DensityMatrix
+{
+    /// Create a new density matrix.
+    ///
+    /// Create a new density matrix for a system of `nr_bits` qubits, in the
+    /// pure state |00...0⟩⟨00...0|.
+    pub fn new(nr_bits: usize) -> Self
+    {
+        let size = 1 << nr_bits;
+        let mut rho = crate::cmatrix::CMatrix::zeros((size, size));
+        rho[[0, 0]] = crate::cmatrix::COMPLEX_ONE;
+        DensityMatrix { nr_bits: nr_bits, rho: rho }
+    }
+
+    /// The number of qubits in this system
+    pub fn nr_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    /// The density matrix for this state
+    pub fn matrix(&self) -> &crate::cmatrix::CMatrix
+    {
+        &self.rho
+    }
+
+    /// Check that all bits in `bits` are valid qubit indices
+    fn check_bits(&self, bits: &[usize]) -> crate::error::Result<()>
+    {
+        if let Some(&bit) = bits.iter().find(|&&b| b >= self.nr_bits)
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    /// Compute `GρG`<sup>`†`</sup> for gate `gate` operating on `bits`.
+    ///
+    /// As the density matrix is Hermitian, `(Gρ)`<sup>`†`</sup> =
+    /// `ρG`<sup>`†`</sup>, so the result can be computed by applying `gate`
+    /// twice from the left.
+    fn sandwich<G>(&self, gate: &G, bits: &[usize]) -> crate::cmatrix::CMatrix
+    where G: Gate + ?Sized
+    {
+        let mut res = self.rho.clone();
+        crate::gates::apply_gate_mat_slice(res.view_mut(), gate, bits, self.nr_bits);
+        let mut res = res.t().mapv(|c| c.conj());
+        crate::gates::apply_gate_mat_slice(res.view_mut(), gate, bits, self.nr_bits);
+        res
+    }
+
+    /// Apply a gate.
+    ///
+    /// Apply the unitary transformation `U` described by gate `gate` on the
+    /// qubits in `bits`, transforming the density matrix `ρ` into
+    /// `UρU`<sup>`†`</sup>.
+    pub fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: Gate + ?Sized
+    {
+        gate.check_nr_bits(bits.len())?;
+        self.check_bits(bits)?;
+        self.rho = self.sandwich(gate, bits);
+        Ok(())
+    }
+
+    /// Apply a quantum channel.
+    ///
+    /// Apply the channel described by the Kraus operators in `kraus`,
+    /// operating on the qubits in `bits`, to this state. The density matrix
+    /// `ρ` is transformed into `Σ`<sub>`i`</sub>`K`<sub>`i`</sub>`ρK`<sub>`i`</sub><sup>`†`</sup>.
+    pub fn apply_channel(&mut self, kraus: &[crate::cmatrix::CMatrix], bits: &[usize])
+        -> crate::error::Result<()>
+    {
+        self.check_bits(bits)?;
+        let size = 1 << bits.len();
+        if let Some(k) = kraus.iter().find(|k| k.rows() != size || k.cols() != size)
+        {
+            return Err(crate::error::Error::InvalidNrBits(bits.len(),
+                k.rows().trailing_zeros() as usize, String::from("Kraus operator")));
+        }
+
+        let mut res = crate::cmatrix::CMatrix::zeros(self.rho.dim());
+        for k in kraus
+        {
+            let op = MatrixOp { matrix: k, nr_bits: bits.len() };
+            res += &self.sandwich(&op, bits);
+        }
+        self.rho = res;
+
+        Ok(())
+    }
+
+    /// Measure a qubit.
+    ///
+    /// Measure qubit `bit` in the `Z` basis, without recording the outcome,
+    /// and return the probability of measuring 1. The density matrix is
+    /// transformed into `P`<sub>`0`</sub>`ρP`<sub>`0`</sub>` +
+    /// P`<sub>`1`</sub>`ρP`<sub>`1`</sub>, where `P`<sub>`0`</sub> and
+    /// `P`<sub>`1`</sub> project qubit `bit` onto |0⟩ and |1⟩ respectively.
+    pub fn measure_z(&mut self, bit: usize) -> crate::error::Result<f64>
+    {
+        self.check_bits(&[bit])?;
+
+        let mask = 1 << (self.nr_bits - bit - 1);
+        let mut prob = 0.0;
+        for ((i, j), c) in self.rho.indexed_iter_mut()
+        {
+            if (i ^ j) & mask != 0
+            {
+                *c = crate::cmatrix::COMPLEX_ZERO;
+            }
+            else if i == j && i & mask != 0
+            {
+                prob += c.re;
+            }
+        }
+
+        Ok(prob)
+    }
+
+    /// Reset a qubit.
+    ///
+    /// Reset qubit `bit` to |0⟩, leaving the other qubits untouched.
+    pub fn reset(&mut self, bit: usize) -> crate::error::Result<()>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        self.apply_channel(&[array![[o, z], [z, z]], array![[z, o], [z, z]]], &[bit])
+    }
+
+    /// Reset all qubits.
+    ///
+    /// Reset the state to the pure state |00...0⟩⟨00...0|.
+    pub fn reset_all(&mut self)
+    {
+        *self = Self::new(self.nr_bits);
+    }
+
+    /// Compute an expectation value.
+    ///
+    /// Compute the expectation value `Tr(ρP)` of the Pauli string `obs` in
+    /// this state.
+    pub fn expectation_value(&self, obs: &crate::stabilizer::PauliString)
+        -> crate::error::Result<f64>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+
+        let mut res = crate::cmatrix::COMPLEX_ZERO;
+        for col in 0..self.rho.rows()
+        {
+            let (row, phase) = obs.apply_to_basis_state(col);
+            res += self.rho[[col, row]] * phase;
+        }
+        Ok(res.re)
+    }
+}
+
+impl crate::branched::BranchState for DensityMatrix
+{
+    fn nr_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: Gate + ?Sized
+    {
+        DensityMatrix::apply_gate(self, gate, bits)
+    }
+
+    fn apply_channel(&mut self, kraus: &[crate::cmatrix::CMatrix], bits: &[usize])
+        -> crate::error::Result<()>
+    {
+        DensityMatrix::apply_channel(self, kraus, bits)
+    }
+
+    fn probability_one(&self, bit: usize) -> f64
+    {
+        let mask = 1 << (self.nr_bits - bit - 1);
+        (0..self.rho.rows()).filter(|&i| i & mask != 0).map(|i| self.rho[[i, i]].re).sum()
+    }
+
+    fn project(&mut self, bit: usize, value: bool, prob: f64)
+    {
+        let mask = 1 << (self.nr_bits - bit - 1);
+        let keep = |i: usize| (i & mask != 0) == value;
+        let scale = 1.0 / prob;
+        for ((i, j), c) in self.rho.indexed_iter_mut()
+        {
+            if keep(i) && keep(j)
+            {
+                *c *= scale;
+            }
+            else
+            {
+                *c = crate::cmatrix::COMPLEX_ZERO;
+            }
+        }
+    }
+
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString) -> f64
+    {
+        DensityMatrix::expectation_value(self, obs).unwrap_or(0.0)
+    }
+
+    fn reset_all(&mut self)
+    {
+        DensityMatrix::reset_all(self);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::DensityMatrix;
+    use crate::gates::{NoiseChannel, Depolarizing, CX, H, X};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    #[test]
+    fn test_new()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        let rho = DensityMatrix::new(1);
+        assert_eq!(rho.nr_bits(), 1);
+        assert_complex_matrix_eq!(rho.matrix(), &array![[o, z], [z, z]]);
+
+        let rho = DensityMatrix::new(2);
+        assert_eq!(rho.nr_bits(), 2);
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [o, z, z, z],
+            [z, z, z, z],
+            [z, z, z, z],
+            [z, z, z, z]
+        ]);
+    }
+
+    #[test]
+    fn test_apply_gate()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+
+        let mut rho = DensityMatrix::new(2);
+        assert_eq!(rho.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(rho.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [h, z, z, h],
+            [z, z, z, z],
+            [z, z, z, z],
+            [h, z, z, h]
+        ]);
+
+        assert_eq!(rho.apply_gate(&X::new(), &[2]), Err(crate::error::Error::InvalidQBit(2)));
+        assert!(matches!(rho.apply_gate(&CX::new(), &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+    }
+
+    #[test]
+    fn test_apply_channel()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        // Fully depolarizing, should end up in maximally mixed state
+        let mut rho = DensityMatrix::new(1);
+        let kraus = Depolarizing::new(0.75).kraus_operators();
+        assert_eq!(rho.apply_channel(&kraus, &[0]), Ok(()));
+        assert_complex_matrix_eq!(rho.matrix(), &array![[0.5*o, z], [z, 0.5*o]]);
+
+        let mut rho = DensityMatrix::new(2);
+        assert_eq!(rho.apply_gate(&X::new(), &[1]), Ok(()));
+        let kraus = Depolarizing::new(0.3).kraus_operators();
+        assert_eq!(rho.apply_channel(&kraus, &[1]), Ok(()));
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [0.2*o, z,     z, z],
+            [z,     0.8*o, z, z],
+            [z,     z,     z, z],
+            [z,     z,     z, z]
+        ]);
+
+        assert_eq!(rho.apply_channel(&kraus, &[2]), Err(crate::error::Error::InvalidQBit(2)));
+        assert!(matches!(rho.apply_channel(&kraus, &[0, 1]),
+            Err(crate::error::Error::InvalidNrBits(2, 1, _))));
+    }
+
+    #[test]
+    fn test_measure_z()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        let mut rho = DensityMatrix::new(2);
+        assert_eq!(rho.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(rho.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(rho.apply_gate(&X::new(), &[1]), Ok(()));
+        let prob = rho.measure_z(1).unwrap();
+        assert!((prob - 0.5).abs() < 1.0e-12);
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [z, z,     z,     z],
+            [z, 0.5*o, z,     z],
+            [z, z,     0.5*o, z],
+            [z, z,     z,     z]
+        ]);
+        // The state is now diagonal in the Z basis of both qubits
+        let prob = rho.measure_z(0).unwrap();
+        assert!((prob - 0.5).abs() < 1.0e-12);
+
+        let mut rho = DensityMatrix::new(1);
+        assert_eq!(rho.apply_gate(&X::new(), &[0]), Ok(()));
+        assert_eq!(rho.measure_z(0), Ok(1.0));
+        assert_eq!(rho.measure_z(1), Err(crate::error::Error::InvalidQBit(1)));
+    }
+
+    #[test]
+    fn test_reset()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        let mut rho = DensityMatrix::new(2);
+        assert_eq!(rho.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(rho.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(rho.reset(1), Ok(()));
+        assert_complex_matrix_eq!(rho.matrix(), &array![
+            [0.5*o, z, z,     z],
+            [z,     z, z,     z],
+            [z,     z, 0.5*o, z],
+            [z,     z, z,     z]
+        ]);
+
+        rho.reset_all();
+        assert_complex_matrix_eq!(rho.matrix(), DensityMatrix::new(2).matrix());
+    }
+
+    #[test]
+    fn test_expectation_value()
+    {
+        let mut rho = DensityMatrix::new(2);
+        let zz = PauliString::new(&[PauliOp::Z, PauliOp::Z]);
+        let xx = PauliString::new(&[PauliOp::X, PauliOp::X]);
+        let yy = PauliString::new(&[PauliOp::Y, PauliOp::Y]);
+        let zi = PauliString::new(&[PauliOp::Z, PauliOp::I]);
+        assert_eq!(rho.expectation_value(&zz), Ok(1.0));
+        assert_eq!(rho.expectation_value(&zi), Ok(1.0));
+        assert_eq!(rho.expectation_value(&xx), Ok(0.0));
+
+        assert_eq!(rho.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(rho.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert!((rho.expectation_value(&zz).unwrap() - 1.0).abs() < 1.0e-15);
+        assert!((rho.expectation_value(&xx).unwrap() - 1.0).abs() < 1.0e-15);
+        assert!((rho.expectation_value(&yy).unwrap() + 1.0).abs() < 1.0e-15);
+        assert!(rho.expectation_value(&zi).unwrap().abs() < 1.0e-15);
+
+        let z = PauliString::new(&[PauliOp::Z]);
+        assert!(matches!(rho.expectation_value(&z),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+    }
+}
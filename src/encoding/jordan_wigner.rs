@@ -0,0 +1,346 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::cmatrix::CNumber;
+use crate::stabilizer::{PauliOp, PauliString};
+
+/// Coefficients with a norm below this threshold are considered to be zero
+const ZERO_THRESHOLD: f64 = 1.0e-12;
+
+/// A fermionic creation or annihilation operator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LadderOp
+{
+    /// Creation operator a<sup>†</sup><sub>j</sub> on mode `j`
+    Creation(usize),
+    /// Annihilation operator a<sub>j</sub> on mode `j`
+    Annihilation(usize)
+}
+
+impl LadderOp
+{
+    /// The mode this operator acts on
+    pub fn mode(self) -> usize
+    {
+        match self
+        {
+            LadderOp::Creation(mode) | LadderOp::Annihilation(mode) => mode
+        }
+    }
+}
+
+/// Product of fermionic ladder operators
+///
+/// Struct `FermionicOperator` describes a product of creation and
+/// annihilation operators on a system of `nr_modes` fermionic modes. The
+/// operators are applied from right to left, i.e. the last operator in the
+/// product acts first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FermionicOperator
+{
+    /// The number of fermionic modes in the system
+    nr_modes: usize,
+    /// The ladder operators in the product
+    ops: Vec<LadderOp>
+}
+
+impl FermionicOperator
+{
+    /// Create a new fermionic operator.
+    ///
+    /// Create the product of the ladder operators in `ops`, acting on a
+    /// system of `nr_modes` modes. An `InvalidMode` error is returned when
+    /// an operator acts on a mode outside the system.
+    pub fn new(nr_modes: usize, ops: &[LadderOp]) -> crate::error::Result<Self>
+    {
+        if let Some(op) = ops.iter().find(|op| op.mode() >= nr_modes)
+        {
+            return Err(crate::error::Error::InvalidMode(op.mode()));
+        }
+
+        Ok(FermionicOperator { nr_modes: nr_modes, ops: ops.to_vec() })
+    }
+
+    /// The number of modes in the system
+    pub fn nr_modes(&self) -> usize
+    {
+        self.nr_modes
+    }
+
+    /// The ladder operators in this product
+    pub fn ops(&self) -> &[LadderOp]
+    {
+        &self.ops
+    }
+}
+
+/// Multiply single qubit Pauli operators `a` and `b`, returning the phase and
+/// operator of the product `ab`.
+fn multiply_ops(a: PauliOp, b: PauliOp) -> (CNumber, PauliOp)
+{
+    let i = crate::cmatrix::COMPLEX_I;
+    match (a, b)
+    {
+        (PauliOp::I, op) | (op, PauliOp::I) => (crate::cmatrix::COMPLEX_ONE, op),
+        (PauliOp::X, PauliOp::Y) => (i, PauliOp::Z),
+        (PauliOp::Y, PauliOp::X) => (-i, PauliOp::Z),
+        (PauliOp::Y, PauliOp::Z) => (i, PauliOp::X),
+        (PauliOp::Z, PauliOp::Y) => (-i, PauliOp::X),
+        (PauliOp::Z, PauliOp::X) => (i, PauliOp::Y),
+        (PauliOp::X, PauliOp::Z) => (-i, PauliOp::Y),
+        _ => (crate::cmatrix::COMPLEX_ONE, PauliOp::I)
+    }
+}
+
+/// Multiply the weighted Pauli strings `a` and `b`.
+fn multiply_strings(a: &(CNumber, Vec<PauliOp>), b: &(CNumber, Vec<PauliOp>))
+    -> (CNumber, Vec<PauliOp>)
+{
+    let mut coeff = a.0 * b.0;
+    let ops = a.1.iter().zip(b.1.iter())
+        .map(|(&pa, &pb)| {
+            let (phase, op) = multiply_ops(pa, pb);
+            coeff *= phase;
+            op
+        })
+        .collect();
+    (coeff, ops)
+}
+
+/// Combine the terms with equal Pauli strings in `terms`, and remove the
+/// terms with a vanishing coefficient. The terms are returned in the order of
+/// first occurrence of their Pauli string.
+fn simplify(terms: Vec<(CNumber, Vec<PauliOp>)>) -> Vec<(CNumber, Vec<PauliOp>)>
+{
+    let mut index = ::std::collections::HashMap::<Vec<u64>, usize>::new();
+    let mut res: Vec<(CNumber, Vec<PauliOp>)> = vec![];
+    for (coeff, ops) in terms
+    {
+        let key: Vec<u64> = ops.iter().map(|op| op.to_bits()).collect();
+        match index.get(&key)
+        {
+            Some(&idx) => { res[idx].0 += coeff; },
+            None => {
+                index.insert(key, res.len());
+                res.push((coeff, ops));
+            }
+        }
+    }
+    res.retain(|(coeff, _)| coeff.norm() >= ZERO_THRESHOLD);
+    res
+}
+
+/// Return the Jordan-Wigner encoding of ladder operator `op` on a system of
+/// `nr_modes` modes.
+fn encode_ladder_op(op: LadderOp, nr_modes: usize) -> Vec<(CNumber, Vec<PauliOp>)>
+{
+    let mode = op.mode();
+    let mut x_ops = vec![PauliOp::I; nr_modes];
+    for z_op in x_ops.iter_mut().take(mode)
+    {
+        *z_op = PauliOp::Z;
+    }
+    let mut y_ops = x_ops.clone();
+    x_ops[mode] = PauliOp::X;
+    y_ops[mode] = PauliOp::Y;
+
+    let half = 0.5 * crate::cmatrix::COMPLEX_ONE;
+    let half_i = 0.5 * crate::cmatrix::COMPLEX_I;
+    match op
+    {
+        LadderOp::Creation(_) => vec![(half, x_ops), (-half_i, y_ops)],
+        LadderOp::Annihilation(_) => vec![(half, x_ops), (half_i, y_ops)]
+    }
+}
+
+/// Jordan-Wigner encoding of a product of ladder operators, without
+/// conversion of the Pauli strings.
+fn encode_ops(ops: &[LadderOp], nr_modes: usize) -> Vec<(CNumber, Vec<PauliOp>)>
+{
+    let mut res = vec![(crate::cmatrix::COMPLEX_ONE, vec![PauliOp::I; nr_modes])];
+    for &op in ops
+    {
+        let encoded = encode_ladder_op(op, nr_modes);
+        let product = res.iter()
+            .flat_map(|a| encoded.iter().map(move |b| multiply_strings(a, b)))
+            .collect();
+        res = simplify(product);
+    }
+    res
+}
+
+/// Jordan-Wigner encoding.
+///
+/// Map the product of fermionic ladder operators `fermionic_op` to qubit
+/// operators using the Jordan-Wigner transformation, where mode `j` is
+/// represented by qubit `j`, and an occupied mode corresponds to state |1⟩.
+/// The annihilation operator a<sub>j</sub> is mapped to
+/// `½(X`<sub>`j`</sub>` + iY`<sub>`j`</sub>`) Z`<sub>`j-1`</sub>` ⋯ Z`<sub>`0`</sub>,
+/// and the creation operator a<sup>†</sup><sub>j</sub> to its adjoint. Since a
+/// product of ladder operators is in general not a single Pauli string, the
+/// result is returned as a list of Pauli strings with complex coefficients,
+/// in which equal strings are combined and vanishing terms are removed.
+pub fn jordan_wigner_encode(fermionic_op: &FermionicOperator) -> Vec<(CNumber, PauliString)>
+{
+    encode_ops(fermionic_op.ops(), fermionic_op.nr_modes()).into_iter()
+        .map(|(coeff, ops)| (coeff, PauliString::new(&ops)))
+        .collect()
+}
+
+/// Encode a molecular Hamiltonian.
+///
+/// Map the second-quantized molecular Hamiltonian
+/// `H = Σ`<sub>`pq`</sub>` h`<sub>`pq`</sub>` a`<sup>`†`</sup><sub>`p`</sub>` a`<sub>`q`</sub>` + ½ Σ`<sub>`pqrs`</sub>` h`<sub>`pqrs`</sub>` a`<sup>`†`</sup><sub>`p`</sub>` a`<sup>`†`</sup><sub>`q`</sub>` a`<sub>`r`</sub>` a`<sub>`s`</sub>
+/// with one-electron integrals `h_one` and two-electron integrals `h_two` to
+/// a qubit Hamiltonian, using the Jordan-Wigner encoding as in
+/// `jordan_wigner_encode()`. The integrals are assumed to have the symmetries
+/// that make `H` Hermitian; the imaginary parts of the coefficients, which
+/// then vanish, are discarded. An `InvalidNrBits` error is returned when the
+/// dimensions of the integral arrays do not match.
+pub fn molecular_hamiltonian_to_qubit_hamiltonian(h_one: &ndarray::Array2<f64>,
+    h_two: &ndarray::Array4<f64>) -> crate::error::Result<crate::hamiltonian::Hamiltonian>
+{
+    let nr_modes = h_one.rows();
+    if h_one.cols() != nr_modes
+    {
+        return Err(crate::error::Error::InvalidNrBits(h_one.cols(), nr_modes,
+            String::from("one-electron integrals")));
+    }
+    if let Some(&dim) = h_two.shape().iter().find(|&&dim| dim != nr_modes)
+    {
+        return Err(crate::error::Error::InvalidNrBits(dim, nr_modes,
+            String::from("two-electron integrals")));
+    }
+
+    let mut terms = vec![];
+    for ((p, q), &h) in h_one.indexed_iter()
+    {
+        if h != 0.0
+        {
+            let ops = [LadderOp::Creation(p), LadderOp::Annihilation(q)];
+            terms.extend(encode_ops(&ops, nr_modes).into_iter().map(|(c, ops)| (c * h, ops)));
+        }
+    }
+    for ((p, q, r, s), &h) in h_two.indexed_iter()
+    {
+        if h != 0.0
+        {
+            let ops = [LadderOp::Creation(p), LadderOp::Creation(q),
+                LadderOp::Annihilation(r), LadderOp::Annihilation(s)];
+            terms.extend(encode_ops(&ops, nr_modes).into_iter()
+                .map(|(c, ops)| (c * (0.5 * h), ops)));
+        }
+    }
+
+    let mut hamiltonian = crate::hamiltonian::Hamiltonian::new();
+    for (coeff, ops) in simplify(terms)
+    {
+        if coeff.re.abs() >= ZERO_THRESHOLD
+        {
+            hamiltonian.add_term(coeff.re, PauliString::new(&ops));
+        }
+    }
+    Ok(hamiltonian)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{jordan_wigner_encode, molecular_hamiltonian_to_qubit_hamiltonian,
+        FermionicOperator, LadderOp};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    #[test]
+    fn test_new()
+    {
+        let op = FermionicOperator::new(3, &[LadderOp::Creation(2), LadderOp::Annihilation(0)]);
+        assert!(op.is_ok());
+        let op = op.unwrap();
+        assert_eq!(op.nr_modes(), 3);
+        assert_eq!(op.ops(), &[LadderOp::Creation(2), LadderOp::Annihilation(0)]);
+
+        assert_eq!(FermionicOperator::new(2, &[LadderOp::Annihilation(2)]),
+            Err(crate::error::Error::InvalidMode(2)));
+    }
+
+    #[test]
+    fn test_jordan_wigner_encode()
+    {
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let hi = 0.5 * crate::cmatrix::COMPLEX_I;
+
+        let op = FermionicOperator::new(3, &[LadderOp::Annihilation(1)]).unwrap();
+        assert_eq!(jordan_wigner_encode(&op), vec![
+            (h, PauliString::new(&[PauliOp::Z, PauliOp::X, PauliOp::I])),
+            (hi, PauliString::new(&[PauliOp::Z, PauliOp::Y, PauliOp::I]))
+        ]);
+
+        // Number operator n = (I - Z)/2
+        let op = FermionicOperator::new(2, &[LadderOp::Creation(1), LadderOp::Annihilation(1)])
+            .unwrap();
+        assert_eq!(jordan_wigner_encode(&op), vec![
+            (h, PauliString::new(&[PauliOp::I, PauliOp::I])),
+            (-h, PauliString::new(&[PauliOp::I, PauliOp::Z]))
+        ]);
+
+        // Pauli exclusion
+        let op = FermionicOperator::new(2, &[LadderOp::Creation(0), LadderOp::Creation(0)])
+            .unwrap();
+        assert!(jordan_wigner_encode(&op).is_empty());
+
+        // Encoded operators satisfy the anticommutation relations
+        let nr_modes = 3;
+        let matrix = |ops: &[LadderOp]| {
+            let op = FermionicOperator::new(nr_modes, ops).unwrap();
+            jordan_wigner_encode(&op).iter()
+                .fold(crate::cmatrix::CMatrix::zeros((8, 8)), |m, (c, p)| m + p.matrix() * *c)
+        };
+        for p in 0..nr_modes
+        {
+            for q in 0..nr_modes
+            {
+                let ac = matrix(&[LadderOp::Annihilation(p), LadderOp::Creation(q)])
+                    + matrix(&[LadderOp::Creation(q), LadderOp::Annihilation(p)]);
+                let expected = if p == q { crate::cmatrix::CMatrix::eye(8) }
+                    else { crate::cmatrix::CMatrix::zeros((8, 8)) };
+                assert_complex_matrix_eq!(&ac, &expected);
+
+                let aa = matrix(&[LadderOp::Annihilation(p), LadderOp::Annihilation(q)])
+                    + matrix(&[LadderOp::Annihilation(q), LadderOp::Annihilation(p)]);
+                assert_complex_matrix_eq!(&aa, &crate::cmatrix::CMatrix::zeros((8, 8)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_molecular_hamiltonian()
+    {
+        let h_one = array![[-1.0, 0.0], [0.0, 0.5]];
+        let mut h_two = ndarray::Array4::zeros((2, 2, 2, 2));
+        h_two[[0, 1, 1, 0]] = 2.0;
+        h_two[[1, 0, 0, 1]] = 2.0;
+
+        // H = -n_0 + 0.5 n_1 + 2 n_0 n_1
+        let hamiltonian = molecular_hamiltonian_to_qubit_hamiltonian(&h_one, &h_two).unwrap();
+        assert_eq!(hamiltonian.to_pauli_strings(), &[
+            (0.25, PauliString::new(&[PauliOp::I, PauliOp::I])),
+            (-0.75, PauliString::new(&[PauliOp::I, PauliOp::Z])),
+            (0.5, PauliString::new(&[PauliOp::Z, PauliOp::Z]))
+        ][..]);
+
+        assert_eq!(molecular_hamiltonian_to_qubit_hamiltonian(&h_one,
+            &ndarray::Array4::zeros((2, 3, 2, 2))),
+            Err(crate::error::Error::InvalidNrBits(3, 2, String::from("two-electron integrals"))));
+    }
+}
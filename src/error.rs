@@ -29,7 +29,10 @@ pub enum ExportError
     /// Trying to close a loop where none is open in LaTeX export
     CantCloseLoop,
     /// Trying to reserve range in LaTeX export, but previous reservation is open
-    RangeAlreadyOpen
+    RangeAlreadyOpen,
+    /// Unable to decompose a gate into standard gates. Holds the gate
+    /// description, and the exported program with the gate left as a comment.
+    UndecomposedGate(String, String)
 }
 
 impl ::std::fmt::Display for ExportError
@@ -58,6 +61,9 @@ impl ::std::fmt::Display for ExportError
             },
             ExportError::RangeAlreadyOpen => {
                 write!(f, "Trying to reserve range of bits, but a previous reservation is still open")
+            },
+            ExportError::UndecomposedGate(ref desc, _) => {
+                write!(f, "Unable to decompose gate \"{}\" into standard gates", desc)
             }
         }
     }
@@ -88,6 +94,19 @@ pub enum ParseError
     TrailingText(String),
     /// Unclosed parentheses in argument expression
     UnclosedParentheses(String),
+    /// Syntax error in JSON text
+    InvalidJson(String),
+    /// JSON description of a Cirq circuit lacks a field, or has a field of
+    /// the wrong type
+    InvalidCirqJson(String),
+    /// Cirq gate or operation that cannot be imported
+    UnsupportedCirqGate(String),
+    /// Malformed statement in an OpenQasm program
+    InvalidOpenQasm(String),
+    /// Use of a register that was not declared
+    UndefinedRegister(String),
+    /// OpenQasm feature that cannot be imported
+    UnsupportedOpenQasm(String)
 }
 
 impl ::std::fmt::Display for ParseError
@@ -122,6 +141,24 @@ impl ::std::fmt::Display for ParseError
             },
             ParseError::UnclosedParentheses(ref text) => {
                 write!(f, "Unclosed parentheses in expression: \"{}\"", text)
+            },
+            ParseError::InvalidJson(ref text) => {
+                write!(f, "Invalid JSON at \"{}\"", text)
+            },
+            ParseError::InvalidCirqJson(ref field) => {
+                write!(f, "Missing or invalid field \"{}\" in Cirq circuit", field)
+            },
+            ParseError::UnsupportedCirqGate(ref name) => {
+                write!(f, "Unable to import Cirq gate \"{}\"", name)
+            },
+            ParseError::InvalidOpenQasm(ref text) => {
+                write!(f, "Invalid OpenQasm statement \"{}\"", text)
+            },
+            ParseError::UndefinedRegister(ref name) => {
+                write!(f, "Undefined register \"{}\"", name)
+            },
+            ParseError::UnsupportedOpenQasm(ref what) => {
+                write!(f, "Unable to import OpenQasm {}", what)
             }
         }
     }
@@ -140,6 +177,8 @@ pub enum Error
     InvalidQBit(usize),
     /// Invalid index for classical bit
     InvalidCBit(usize),
+    /// Invalid index for bosonic mode
+    InvalidMode(usize),
     /// Using classically controlled operation with wrong number of control bits
     InvalidNrControlBits(usize, usize, String),
     /// Trying to measure all qbits with incorrect number of measurement bits
@@ -150,6 +189,10 @@ pub enum Error
     NotExecuted,
     /// Acting with a non-stabilizer gate on a stabilizer circuit
     NotAStabilizer(String),
+    /// Trying to run a circuit with non-Clifford operations in stabilizer mode
+    NotACliffordCircuit,
+    /// Using an operation that is not a gate where a gate is required
+    NotAGate(String),
     /// Trying to create an empty permutation
     EmptyPermutation,
     /// Permutation contains elements higher than its length
@@ -164,6 +207,38 @@ pub enum Error
     UnknownFunction(String),
     /// Unknown variable when evalutating expression
     UnknownVariable(String),
+    /// Invalid set of noise scaling factors for zero-noise extrapolation
+    InvalidNoiseFactors,
+    /// Non-unitary operation that annihilates the quantum state
+    ZeroProbability(String),
+    /// Trying to invert a singular matrix
+    SingularMatrix,
+    /// Relaxation times of a qubit that do not satisfy 0 < T2 ≤ 2T1
+    InvalidRelaxationTimes(usize),
+    /// Comparing states or operators of different dimensions
+    DimensionMismatch(usize, usize),
+    /// Dimension that is not a power of two, where one is required
+    InvalidDimension(usize),
+    /// Using a matrix that is not Hermitian where an observable is required
+    NotHermitian,
+    /// Using a matrix that is not unitary where a unitary matrix is required
+    MatrixNotUnitary,
+    /// Invalid index for an operation in a circuit
+    InvalidOpIndex(usize),
+    /// Using a free parameter that has not been bound to a value
+    UnboundParameter(String),
+    /// Measuring a Pauli string that holds an identity operator
+    InvalidPauliMeasurement(String),
+    /// Requesting the pure state of a system of multiple runs, or of a
+    /// representation without state vectors
+    NoPureState,
+    /// External program needed for an operation could not be found
+    ExternalToolNotFound(String),
+    /// External program did not finish successfully. Holds the program name
+    /// and its output.
+    ExternalToolFailed(String, String),
+    /// Failure reading or writing a file
+    IoError(String),
     /// Other errors that should not occur
     InternalError(String),
     /// Error reating to the export of a circuit
@@ -204,6 +279,9 @@ impl ::std::fmt::Display for Error
             Error::InvalidCBit(bit) => {
                 write!(f, "Invalid index {} for a classical bit", bit)
             },
+            Error::InvalidMode(mode) => {
+                write!(f, "Invalid index {} for a bosonic mode", mode)
+            },
             Error::InvalidNrControlBits(actual, expected, ref desc) => {
                 write!(f, "The number of runs is {}, but received {} control bits for controlled {} operation",
                     expected, actual, desc)
@@ -221,6 +299,12 @@ impl ::std::fmt::Display for Error
             Error::NotAStabilizer(ref desc) => {
                 write!(f, "{} is no a stabilizer gate", desc)
             },
+            Error::NotACliffordCircuit => {
+                write!(f, "The circuit contains non-Clifford operations, and cannot be simulated in stabilizer mode")
+            },
+            Error::NotAGate(ref desc) => {
+                write!(f, "Operation {} is not a gate", desc)
+            },
             Error::EmptyPermutation => {
                 write!(f, "The permutation is empty")
             },
@@ -242,6 +326,51 @@ impl ::std::fmt::Display for Error
             Error::UnknownVariable(ref name) => {
                 write!(f, "Unknown variable {}", name)
             },
+            Error::InvalidNoiseFactors => {
+                write!(f, "Noise factors should be a non-empty list of distinct integers, at least 1")
+            },
+            Error::ZeroProbability(ref desc) => {
+                write!(f, "Operation {} has zero probability on the current state", desc)
+            },
+            Error::SingularMatrix => {
+                write!(f, "Matrix is singular")
+            },
+            Error::InvalidRelaxationTimes(bit) => {
+                write!(f, "Relaxation times for qubit {} do not satisfy 0 < T2 ≤ 2T1", bit)
+            },
+            Error::DimensionMismatch(dim0, dim1) => {
+                write!(f, "Unable to compare objects of dimensions {} and {}", dim0, dim1)
+            },
+            Error::InvalidDimension(dim) => {
+                write!(f, "Dimension {} is not a power of two", dim)
+            },
+            Error::NotHermitian => {
+                write!(f, "Matrix is not Hermitian")
+            },
+            Error::MatrixNotUnitary => {
+                write!(f, "Matrix is not unitary")
+            },
+            Error::InvalidOpIndex(idx) => {
+                write!(f, "Invalid index {} for an operation in the circuit", idx)
+            },
+            Error::UnboundParameter(ref name) => {
+                write!(f, "Parameter {} has not been bound to a value", name)
+            },
+            Error::InvalidPauliMeasurement(ref desc) => {
+                write!(f, "Unable to measure Pauli string {}, because it contains an identity operator", desc)
+            },
+            Error::NoPureState => {
+                write!(f, "The state is not the pure state of a single run")
+            },
+            Error::ExternalToolNotFound(ref name) => {
+                write!(f, "Unable to find external program {}", name)
+            },
+            Error::ExternalToolFailed(ref name, ref output) => {
+                write!(f, "External program {} failed: {}", name, output)
+            },
+            Error::IoError(ref err) => {
+                write!(f, "I/O error: {}", err)
+            },
             Error::InternalError(ref err) => {
                 write!(f, "Internal error: {}", err)
             },
@@ -257,3 +386,25 @@ impl ::std::fmt::Display for Error
 
 /// Type alias for a result with a q1tsim error
 pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Enumeration for warnings emitted while executing a circuit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Warning
+{
+    /// The bond dimension of a matrix product state simulation was too small
+    /// for an exact simulation
+    TruncationOccurred
+}
+
+impl ::std::fmt::Display for Warning
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        match *self
+        {
+            Warning::TruncationOccurred => {
+                write!(f, "The maximum bond dimension was too small for an exact simulation, the results are approximate")
+            }
+        }
+    }
+}
@@ -13,23 +13,29 @@
 // limitations under the License.
 
 use crate::gates::Gate;
+use crate::instruction::ToInstruction;
 
 mod cqasm;
 mod latex;
 mod openqasm;
+mod qsharp;
+mod tket;
 
 pub use self::cqasm::CQasm;
 pub use self::latex::{Latex, LatexExportState};
 pub use self::openqasm::OpenQasm;
+pub(crate) use self::openqasm::{add_open_qasm_definitions, is_valid_gate_name};
+pub use self::qsharp::QSharp;
+pub use self::tket::{TketJson, TketOp};
 
 /// Trait combining the traits necessary for a gate in a quantum circuit
-pub trait CircuitGate: Gate + OpenQasm + CQasm + Latex
+pub trait CircuitGate: Gate + OpenQasm + CQasm + Latex + TketJson + ToInstruction
 {
     fn as_gate(&self) -> &dyn Gate;
     fn clone_box(&self) -> Box<dyn CircuitGate>;
 }
 
-impl<G: 'static + Clone + Gate + OpenQasm + CQasm + Latex> CircuitGate for G
+impl<G: 'static + Clone + Gate + OpenQasm + CQasm + Latex + TketJson + ToInstruction> CircuitGate for G
 {
     fn as_gate(&self) -> &dyn Gate
     {
@@ -46,6 +52,108 @@ impl Clone for Box<dyn CircuitGate>
 {
     fn clone(&self) -> Self
     {
-        self.clone_box()
+        (**self).clone_box()
+    }
+}
+
+impl Gate for Box<dyn CircuitGate>
+{
+    fn cost(&self) -> f64 { (**self).cost() }
+    fn description(&self) -> &str { (**self).description() }
+    fn nr_affected_bits(&self) -> usize { (**self).nr_affected_bits() }
+    fn parameters(&self) -> Vec<&crate::gates::Parameter> { (**self).parameters() }
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        (**self).bind(store)
+    }
+    fn matrix(&self) -> crate::cmatrix::CMatrix { (**self).matrix() }
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        (**self).sparsity()
+    }
+    fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
+    {
+        (**self).apply_slice(state)
+    }
+    fn apply_mat_slice(&self, state: crate::cmatrix::CMatSliceMut)
+    {
+        (**self).apply_mat_slice(state)
+    }
+    fn apply_columns(&self, state: &mut crate::cmatrix::CMatrix, bits: &[usize], nr_bits: usize)
+    {
+        (**self).apply_columns(state, bits, nr_bits)
+    }
+    fn is_stabilizer(&self) -> bool { (**self).is_stabilizer() }
+    fn conjugate(&self, ops: &mut [crate::stabilizer::PauliOp]) -> crate::error::Result<bool>
+    {
+        (**self).conjugate(ops)
+    }
+    fn adjoint(&self) -> Box<dyn CircuitGate> { (**self).adjoint() }
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>> { (**self).structure() }
+}
+
+impl OpenQasm for Box<dyn CircuitGate>
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        (**self).open_qasm(bit_names, bits)
+    }
+
+    fn open_qasm3(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        (**self).open_qasm3(bit_names, bits)
+    }
+
+    fn conditional_open_qasm(&self, condition: &str, bit_names: &[String],
+        bits: &[usize]) -> crate::error::Result<String>
+    {
+        (**self).conditional_open_qasm(condition, bit_names, bits)
+    }
+
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        (**self).open_qasm_definition()
+    }
+}
+
+impl CQasm for Box<dyn CircuitGate>
+{
+    fn c_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        (**self).c_qasm(bit_names, bits)
+    }
+
+    fn conditional_c_qasm(&self, condition: &str, bit_names: &[String],
+        bits: &[usize]) -> crate::error::Result<String>
+    {
+        (**self).conditional_c_qasm(condition, bit_names, bits)
+    }
+}
+
+impl Latex for Box<dyn CircuitGate>
+{
+    fn latex(&self, bits: &[usize], state: &mut LatexExportState)
+        -> crate::error::Result<()>
+    {
+        (**self).latex(bits, state)
+    }
+}
+
+impl TketJson for Box<dyn CircuitGate>
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<TketOp>>
+    {
+        (**self).tket_ops(bits)
+    }
+}
+
+impl ToInstruction for Box<dyn CircuitGate>
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        (**self).instructions(bits)
     }
 }
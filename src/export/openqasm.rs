@@ -12,6 +12,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// Names that are either keywords in OpenQasm, or that are used by the gates
+/// in the standard `qelib1.inc` header, and which can therefore not be used as
+/// the name of a gate definition.
+const RESERVED_NAMES: &[&str] = &[
+    "barrier", "ccx", "ch", "cos", "creg", "crz", "cswap", "cu1", "cu3", "cx",
+    "cy", "cz", "exp", "gate", "h", "id", "if", "include", "ln", "measure",
+    "opaque", "pi", "qreg", "reset", "rx", "ry", "rz", "s", "sdg", "sin",
+    "sqrt", "swap", "t", "tan", "tdg", "u1", "u2", "u3", "x", "y", "z"
+];
+
+/// Check whether `name` can be used as the name of a gate definition in
+/// OpenQasm, i.e. whether it is an identifier starting with a lower case
+/// letter that does not clash with a keyword or standard gate.
+pub(crate) fn is_valid_gate_name(name: &str) -> bool
+{
+    let mut chars = name.chars();
+    match chars.next()
+    {
+        Some(c) if c.is_ascii_lowercase() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !RESERVED_NAMES.contains(&name)
+        },
+        _ => false
+    }
+}
+
+/// Add the gate definitions in `def` (if any) to the list of definitions
+/// `defs`, skipping those that already occur in `defs`.
+pub(crate) fn add_open_qasm_definitions(defs: &mut Vec<String>, def: Option<String>)
+{
+    if let Some(def) = def
+    {
+        for line in def.lines()
+        {
+            if !defs.iter().any(|d| d == line)
+            {
+                defs.push(String::from(line));
+            }
+        }
+    }
+}
+
+/// Convert a single OpenQasm 2.0 statement `stmt` to OpenQasm 3.0, renaming
+/// the `cx` gate to its built-in name `CX`. Leading white space in `stmt` is
+/// preserved.
+fn open_qasm3_statement(stmt: &str) -> String
+{
+    let body = stmt.trim_start();
+    let indent = &stmt[..stmt.len()-body.len()];
+    if body.starts_with("cx ")
+    {
+        format!("{}CX{}", indent, &body[2..])
+    }
+    else
+    {
+        String::from(stmt)
+    }
+}
+
 /// Trait for gates that can be represented in OpenQasm.
 pub trait OpenQasm: crate::gates::Gate
 {
@@ -28,6 +87,20 @@ pub trait OpenQasm: crate::gates::Gate
         ))
     }
 
+    /// OpenQasm 3.0 representation
+    ///
+    /// Return an OpenQasm 3.0 instruction string for this gate operating on
+    /// qubits `bits`. The default implementation takes the OpenQasm 2.0
+    /// representation, and renames the `cx` gate to the built-in `CX` gate
+    /// of OpenQasm 3.0.
+    fn open_qasm3(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        let qasm = self.open_qasm(bit_names, bits)?;
+        let stmts: Vec<String> = qasm.split(';').map(open_qasm3_statement).collect();
+        Ok(stmts.join(";"))
+    }
+
     /// OpenQasm representation of conditional gate.
     ///
     /// Return the OpenQasm representation of a gate that is only executed when
@@ -42,12 +115,25 @@ pub trait OpenQasm: crate::gates::Gate
         let uncond_qasm = self.open_qasm(bit_names, bits)?;
         Ok(format!("if ({}) {}", condition, uncond_qasm))
     }
+
+    /// OpenQasm gate definition
+    ///
+    /// For gates that are not part of the OpenQasm standard library, return
+    /// the gate definitions needed to use this gate by name, as a string of
+    /// `gate name(params) bits { ... }` blocks, one per line. Definitions of
+    /// any gates used in the body of the definition precede the definition
+    /// itself. The default implementation returns `None`, indicating that
+    /// the gate needs no definition.
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests
 {
-    use super::OpenQasm;
+    use super::{is_valid_gate_name, OpenQasm};
 
     #[test]
     fn test_conditional_open_qasm()
@@ -57,4 +143,42 @@ mod tests
         let res = crate::gates::H::new().conditional_open_qasm("b == 0", &bit_names, &[1]);
         assert_eq!(res, Ok(String::from("if (b == 0) h qb1")));
     }
+
+    #[test]
+    fn test_open_qasm3()
+    {
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+
+        assert_eq!(crate::gates::H::new().open_qasm3(&bit_names, &[1]),
+            Ok(String::from("h qb1")));
+        assert_eq!(crate::gates::CX::new().open_qasm3(&bit_names, &[1, 0]),
+            Ok(String::from("CX qb1, qb0")));
+        assert_eq!(crate::gates::Swap::new().open_qasm3(&bit_names, &[0, 1]),
+            Ok(String::from("CX qb0, qb1; CX qb1, qb0; CX qb0, qb1")));
+        assert_eq!(crate::gates::CZ::new().open_qasm3(&bit_names, &[0, 1]),
+            Ok(String::from("cz qb0, qb1")));
+    }
+
+    #[test]
+    fn test_open_qasm_definition()
+    {
+        assert_eq!(crate::gates::H::new().open_qasm_definition(), None);
+        assert_eq!(crate::gates::CX::new().open_qasm_definition(), None);
+    }
+
+    #[test]
+    fn test_is_valid_gate_name()
+    {
+        assert!(is_valid_gate_name("inc2"));
+        assert!(is_valid_gate_name("my_gate"));
+        assert!(is_valid_gate_name("custom_12"));
+        assert!(!is_valid_gate_name(""));
+        assert!(!is_valid_gate_name("Inc2"));
+        assert!(!is_valid_gate_name("_gate"));
+        assert!(!is_valid_gate_name("2gate"));
+        assert!(!is_valid_gate_name("my gate"));
+        assert!(!is_valid_gate_name("rx(0.5)"));
+        assert!(!is_valid_gate_name("cx"));
+        assert!(!is_valid_gate_name("measure"));
+    }
 }
@@ -0,0 +1,230 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::instruction::Instruction;
+
+/// Format `x` as a Q# `Double` literal, which requires a decimal point.
+pub(crate) fn q_sharp_double(x: f64) -> String
+{
+    let s = format!("{:?}", x);
+    if s.contains('.') || !x.is_finite()
+    {
+        s
+    }
+    else if let Some(pos) = s.find('e')
+    {
+        format!("{}.0{}", &s[..pos], &s[pos..])
+    }
+    else
+    {
+        s + ".0"
+    }
+}
+
+/// Return the Q# operations, with their angle arguments, that together
+/// implement the single-qubit gate `instr`, or `None` if the instruction is
+/// not a single-qubit gate.
+fn single_qubit_ops(instr: &Instruction) -> Option<Vec<(&'static str, Vec<f64>)>>
+{
+    let ops = match *instr
+        {
+            Instruction::I(_) => vec![("I", vec![])],
+            Instruction::H(_) => vec![("H", vec![])],
+            Instruction::X(_) => vec![("X", vec![])],
+            Instruction::Y(_) => vec![("Y", vec![])],
+            Instruction::Z(_) => vec![("Z", vec![])],
+            Instruction::S(_) => vec![("S", vec![])],
+            Instruction::Sdg(_) => vec![("Adjoint S", vec![])],
+            Instruction::T(_) => vec![("T", vec![])],
+            Instruction::Tdg(_) => vec![("Adjoint T", vec![])],
+            Instruction::V(_) => vec![("H", vec![]), ("S", vec![]), ("H", vec![])],
+            Instruction::Vdg(_) => vec![("H", vec![]), ("Adjoint S", vec![]), ("H", vec![])],
+            Instruction::RX(theta, _) => vec![("Rx", vec![theta])],
+            Instruction::RY(theta, _) => vec![("Ry", vec![theta])],
+            Instruction::RZ(lambda, _) => vec![("Rz", vec![lambda])],
+            Instruction::U1(lambda, _) => vec![("R1", vec![lambda])],
+            Instruction::U2(phi, lambda, _) => vec![
+                ("R1", vec![lambda]),
+                ("Ry", vec![::std::f64::consts::FRAC_PI_2]),
+                ("R1", vec![phi])
+            ],
+            Instruction::U3(theta, phi, lambda, _) => vec![
+                ("R1", vec![lambda]),
+                ("Ry", vec![theta]),
+                ("R1", vec![phi])
+            ],
+            _ => { return None; }
+        };
+    Some(ops)
+}
+
+/// Return the control qubits, the target qubit, and the uncontrolled gate
+/// for the gate instruction `instr`. Returns `None` for instructions that are
+/// not (controlled) single-qubit gates.
+fn split_controls(instr: &Instruction) -> Option<(Vec<usize>, usize, Instruction)>
+{
+    let split = match *instr
+        {
+            Instruction::I(t) | Instruction::H(t) | Instruction::X(t) | Instruction::Y(t)
+            | Instruction::Z(t) | Instruction::S(t) | Instruction::Sdg(t) | Instruction::T(t)
+            | Instruction::Tdg(t) | Instruction::V(t) | Instruction::Vdg(t)
+            | Instruction::RX(_, t) | Instruction::RY(_, t) | Instruction::RZ(_, t)
+            | Instruction::U1(_, t) | Instruction::U2(_, _, t) | Instruction::U3(_, _, _, t) => {
+                (vec![], t, instr.clone())
+            },
+            Instruction::CX(c, t) => (vec![c], t, Instruction::X(t)),
+            Instruction::CY(c, t) => (vec![c], t, Instruction::Y(t)),
+            Instruction::CZ(c, t) => (vec![c], t, Instruction::Z(t)),
+            Instruction::CH(c, t) => (vec![c], t, Instruction::H(t)),
+            Instruction::CRX(theta, c, t) => (vec![c], t, Instruction::RX(theta, t)),
+            Instruction::CRY(theta, c, t) => (vec![c], t, Instruction::RY(theta, t)),
+            Instruction::CRZ(lambda, c, t) => (vec![c], t, Instruction::RZ(lambda, t)),
+            Instruction::CS(c, t) => (vec![c], t, Instruction::S(t)),
+            Instruction::CSdg(c, t) => (vec![c], t, Instruction::Sdg(t)),
+            Instruction::CT(c, t) => (vec![c], t, Instruction::T(t)),
+            Instruction::CTdg(c, t) => (vec![c], t, Instruction::Tdg(t)),
+            Instruction::CU1(lambda, c, t) => (vec![c], t, Instruction::U1(lambda, t)),
+            Instruction::CU2(phi, lambda, c, t) => (vec![c], t, Instruction::U2(phi, lambda, t)),
+            Instruction::CU3(theta, phi, lambda, c, t) => {
+                (vec![c], t, Instruction::U3(theta, phi, lambda, t))
+            },
+            Instruction::CV(c, t) => (vec![c], t, Instruction::V(t)),
+            Instruction::CVdg(c, t) => (vec![c], t, Instruction::Vdg(t)),
+            Instruction::CCRX(theta, c0, c1, t) => (vec![c0, c1], t, Instruction::RX(theta, t)),
+            Instruction::CCRY(theta, c0, c1, t) => (vec![c0, c1], t, Instruction::RY(theta, t)),
+            Instruction::CCRZ(lambda, c0, c1, t) => (vec![c0, c1], t, Instruction::RZ(lambda, t)),
+            Instruction::CCX(c0, c1, t) => (vec![c0, c1], t, Instruction::X(t)),
+            Instruction::CCZ(c0, c1, t) => (vec![c0, c1], t, Instruction::Z(t)),
+            _ => { return None; }
+        };
+    Some(split)
+}
+
+/// Return the Q# statements, separated by `";\n"`, implementing gate
+/// instruction `instr`, or `None` if the instruction cannot be represented in
+/// Q#. The array `bit_names` contains the names of all qubits.
+fn instruction_q_sharp(instr: &Instruction, bit_names: &[String]) -> Option<String>
+{
+    match *instr
+    {
+        Instruction::CX(c, t) => {
+            return Some(format!("CNOT({}, {})", bit_names[c], bit_names[t]));
+        },
+        Instruction::Swap(b0, b1) => {
+            return Some(format!("SWAP({}, {})", bit_names[b0], bit_names[b1]));
+        },
+        Instruction::CSwap(c, b0, b1) => {
+            return Some(format!("Controlled SWAP([{}], ({}, {}))", bit_names[c],
+                bit_names[b0], bit_names[b1]));
+        },
+        _ => {}
+    }
+
+    let (controls, target, base) = split_controls(instr)?;
+    let ops = single_qubit_ops(&base)?;
+    let controls: Vec<&str> = controls.iter().map(|&c| bit_names[c].as_str()).collect();
+    let stmts: Vec<String> = ops.iter().map(|(name, params)| {
+            let mut args: Vec<String> = params.iter().map(|&p| q_sharp_double(p)).collect();
+            args.push(bit_names[target].clone());
+            if controls.is_empty()
+            {
+                format!("{}({})", name, args.join(", "))
+            }
+            else if args.len() == 1
+            {
+                format!("Controlled {}([{}], {})", name, controls.join(", "), args[0])
+            }
+            else
+            {
+                format!("Controlled {}([{}], ({}))", name, controls.join(", "), args.join(", "))
+            }
+        })
+        .collect();
+    Some(stmts.join(";\n"))
+}
+
+/// Trait for gates that can be represented in Q#.
+pub trait QSharp: crate::gates::Gate + crate::instruction::ToInstruction
+{
+    /// Q# representation
+    ///
+    /// Return the Q# statements, separated by `";\n"`, for this gate
+    /// operating on qubits `bits`. The array `bit_names` contains the names of
+    /// all qubits. The default implementation translates the instructions
+    /// for this gate (see `ToInstruction`) to operations in the Q# standard
+    /// library, and returns a NotImplemented error when no such translation
+    /// is known, e.g. for custom gates.
+    fn q_sharp(&self, bit_names: &[String], bits: &[usize]) -> crate::error::Result<String>
+    {
+        let mut stmts = vec![];
+        for instr in self.instructions(bits)
+        {
+            match instruction_q_sharp(&instr, bit_names)
+            {
+                Some(stmt) => { stmts.push(stmt); },
+                None => {
+                    return Err(crate::error::Error::from(
+                        crate::error::ExportError::NotImplemented("Q#",
+                            String::from(self.description()))
+                    ));
+                }
+            }
+        }
+        Ok(stmts.join(";\n"))
+    }
+}
+
+impl<G: crate::gates::Gate + crate::instruction::ToInstruction + ?Sized> QSharp for G {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{q_sharp_double, QSharp};
+    use crate::gates::*;
+
+    #[test]
+    fn test_q_sharp_double()
+    {
+        assert_eq!(q_sharp_double(0.5), String::from("0.5"));
+        assert_eq!(q_sharp_double(-2.0), String::from("-2.0"));
+        assert_eq!(q_sharp_double(1.0e-20), String::from("1.0e-20"));
+    }
+
+    #[test]
+    fn test_q_sharp()
+    {
+        let bit_names = [String::from("q[0]"), String::from("q[1]"), String::from("q[2]")];
+
+        assert_eq!(H::new().q_sharp(&bit_names, &[1]), Ok(String::from("H(q[1])")));
+        assert_eq!(Sdg::new().q_sharp(&bit_names, &[0]), Ok(String::from("Adjoint S(q[0])")));
+        assert_eq!(RZ::new(0.25).q_sharp(&bit_names, &[2]),
+            Ok(String::from("Rz(0.25, q[2])")));
+        assert_eq!(U3::new(1.0, 0.5, 0.25).q_sharp(&bit_names, &[0]),
+            Ok(String::from("R1(0.25, q[0]);\nRy(1.0, q[0]);\nR1(0.5, q[0])")));
+        assert_eq!(CX::new().q_sharp(&bit_names, &[0, 1]), Ok(String::from("CNOT(q[0], q[1])")));
+        assert_eq!(CZ::new().q_sharp(&bit_names, &[2, 0]),
+            Ok(String::from("Controlled Z([q[2]], q[0])")));
+        assert_eq!(CRY::new(0.5).q_sharp(&bit_names, &[0, 1]),
+            Ok(String::from("Controlled Ry([q[0]], (0.5, q[1]))")));
+        assert_eq!(CCX::new().q_sharp(&bit_names, &[0, 1, 2]),
+            Ok(String::from("Controlled X([q[0], q[1]], q[2])")));
+        assert_eq!(Swap::new().q_sharp(&bit_names, &[0, 2]),
+            Ok(String::from("SWAP(q[0], q[2])")));
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let gate = Custom::new("U", array![[z, o], [o, z]]);
+        assert!(gate.q_sharp(&bit_names, &[0]).is_err());
+    }
+}
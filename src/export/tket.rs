@@ -0,0 +1,121 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// A single operation in TKET
+///
+/// Struct `TketOp` describes a single operation in TKET's circuit
+/// representation, consisting of the name of the operation type, its
+/// parameters, and the qubits it operates on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TketOp
+{
+    /// The TKET name of the operation type
+    pub op_type: &'static str,
+    /// The parameters of the operation, in units of π (half turns)
+    pub params: Vec<String>,
+    /// The qubits the operation acts on
+    pub bits: Vec<usize>
+}
+
+impl TketOp
+{
+    /// Create a new TKET operation of type `op_type` with parameters
+    /// `params`, acting on qubits `bits`.
+    pub fn new(op_type: &'static str, params: Vec<String>, bits: &[usize]) -> Self
+    {
+        TketOp { op_type: op_type, params: params, bits: bits.to_vec() }
+    }
+
+    /// Convert angle `param` in radians to a TKET parameter.
+    ///
+    /// Convert angle `param`, given in radians, to a parameter expression
+    /// in TKET, where angles are expressed in units of π. Reference parameters
+    /// are exported as a symbolic expression with the parameter name.
+    pub fn param(param: &crate::gates::Parameter) -> String
+    {
+        match *param
+        {
+            crate::gates::Parameter::Reference(_, ref name)
+            | crate::gates::Parameter::Symbol(ref name) => format!("{}/pi", name),
+            _ => format!("{}", param.value() / ::std::f64::consts::PI)
+        }
+    }
+
+    /// Return the JSON representation of the operation type and parameters,
+    /// i.e. the `"op"` field of a command.
+    pub fn op_json(&self) -> String
+    {
+        if self.params.is_empty()
+        {
+            format!("{{\"type\": \"{}\"}}", self.op_type)
+        }
+        else
+        {
+            let params: Vec<String> = self.params.iter()
+                .map(|p| format!("\"{}\"", p))
+                .collect();
+            format!("{{\"type\": \"{}\", \"params\": [{}]}}", self.op_type, params.join(", "))
+        }
+    }
+}
+
+/// Trait for gates that can be represented in TKET's JSON format.
+pub trait TketJson: crate::gates::Gate
+{
+    /// TKET representation
+    ///
+    /// Return the list of TKET operations that implement this gate when it
+    /// operates on qubits `bits`. The default implementation returns a
+    /// NotImplemented error.
+    fn tket_ops(&self, _bits: &[usize]) -> crate::error::Result<Vec<TketOp>>
+    {
+        Err(crate::error::Error::from(
+            crate::error::ExportError::NotImplemented("TKET", String::from(self.description()))
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{TketJson, TketOp};
+
+    #[test]
+    fn test_param()
+    {
+        let cell = ::std::rc::Rc::new(::std::cell::RefCell::new(0.25));
+        assert_eq!(TketOp::param(&crate::gates::Parameter::from(::std::f64::consts::FRAC_PI_2)),
+            String::from("0.5"));
+        assert_eq!(TketOp::param(&crate::gates::Parameter::from_refcell(&cell, "alpha")),
+            String::from("alpha/pi"));
+    }
+
+    #[test]
+    fn test_op_json()
+    {
+        let op = TketOp::new("H", vec![], &[1]);
+        assert_eq!(op.op_json(), String::from(r#"{"type": "H"}"#));
+        let op = TketOp::new("U2", vec![String::from("0.5"), String::from("1")], &[0]);
+        assert_eq!(op.op_json(), String::from(r#"{"type": "U2", "params": ["0.5", "1"]}"#));
+    }
+
+    #[test]
+    fn test_default()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let gate = crate::gates::Custom::new("U", array![[z, o], [o, z]]);
+        assert!(gate.tket_ops(&[0]).is_err());
+    }
+}
@@ -0,0 +1,764 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::PauliOp;
+
+/// A stabilizer generator, described by its Pauli type and the qubits it acts on
+type Stabilizer = (PauliOp, Vec<usize>);
+
+/// Compute the plaquettes of a rotated surface code on a rectangle.
+///
+/// Compute the stabilizer generators of a rotated surface code on the
+/// data qubits in the rectangle of `height` rows and `width` columns, with its
+/// top left corner at position (`row`, `col`) in the global grid. Every
+/// plaquette has the data qubits at positions (`r`, `c`), (`r`, `c+1`),
+/// (`r+1`, `c`), and (`r+1`, `c+1`) on its corners, and is of type `X` if
+/// `r+c` is even, and of type `Z` otherwise. Along the top and bottom
+/// boundaries, only the weight two `X` plaquettes are kept, along the left and
+/// right boundaries the weight two `Z` plaquettes. The stabilizers are
+/// returned as lists of grid positions.
+fn rectangle_stabilizers(row: usize, col: usize, height: usize, width: usize)
+    -> Vec<(PauliOp, Vec<(usize, usize)>)>
+{
+    let (row, col) = (row as isize, col as isize);
+    let (height, width) = (height as isize, width as isize);
+
+    let mut res = vec![];
+    for r in row-1..row+height
+    {
+        for c in col-1..col+width
+        {
+            let cells: Vec<(usize, usize)> = [(r, c), (r, c+1), (r+1, c), (r+1, c+1)].iter()
+                .filter(|&&(i, j)| i >= row && i < row+height && j >= col && j < col+width)
+                .map(|&(i, j)| (i as usize, j as usize))
+                .collect();
+            let op = if (r + c).rem_euclid(2) == 0 { PauliOp::X } else { PauliOp::Z };
+            let keep = match cells.len()
+                {
+                    4 => true,
+                    2 if r == row-1 || r == row+height-1 => op == PauliOp::X,
+                    2 => op == PauliOp::Z,
+                    _ => false
+                };
+            if keep
+            {
+                res.push((op, cells));
+            }
+        }
+    }
+
+    res
+}
+
+/// Check whether stabilizers `s0` and `s1` anticommute.
+fn anticommute(s0: &Stabilizer, s1: &Stabilizer) -> bool
+{
+    s0.0 != s1.0 && s0.1.iter().filter(|q| s1.1.contains(q)).count() % 2 == 1
+}
+
+/// Solve a linear system over GF(2).
+///
+/// Find a solution `x` to the linear system `Ax = b` over GF(2), where the
+/// rows of `A` are given in `rows`, and `b` in `rhs`. Returns `None` if the
+/// system has no solution.
+fn gf2_solve(rows: &[Vec<bool>], rhs: &[bool]) -> Option<Vec<bool>>
+{
+    let nr_vars = rows.first().map_or(0, |row| row.len());
+    let mut system: Vec<(Vec<bool>, bool)> = rows.iter().cloned().zip(rhs.iter().cloned()).collect();
+
+    let mut pivots = vec![];
+    let mut nr_pivots = 0;
+    for var in 0..nr_vars
+    {
+        if let Some(i) = (nr_pivots..system.len()).find(|&i| system[i].0[var])
+        {
+            system.swap(nr_pivots, i);
+            let pivot = system[nr_pivots].clone();
+            for (i, eq) in system.iter_mut().enumerate()
+            {
+                if i != nr_pivots && eq.0[var]
+                {
+                    eq.0.iter_mut().zip(pivot.0.iter()).for_each(|(a, &b)| *a ^= b);
+                    eq.1 ^= pivot.1;
+                }
+            }
+            pivots.push(var);
+            nr_pivots += 1;
+        }
+    }
+
+    if system[nr_pivots..].iter().any(|eq| eq.1)
+    {
+        return None;
+    }
+
+    let mut x = vec![false; nr_vars];
+    for (eq, &var) in system.iter().zip(pivots.iter())
+    {
+        x[var] = eq.1;
+    }
+    Some(x)
+}
+
+/// Compute corrections for stabilizer outcomes.
+///
+/// For each stabilizer in `targets`, find a product of the operators in
+/// `candidates` that anticommutes with that stabilizer, and commutes with all
+/// other stabilizers in `targets`. All candidates should be of the same Pauli
+/// type. Returns the qubits on which each correction acts.
+fn corrections(targets: &[Stabilizer], candidates: &[Stabilizer]) -> Vec<Vec<usize>>
+{
+    let rows: Vec<Vec<bool>> = targets.iter()
+        .map(|t| candidates.iter().map(|c| anticommute(t, c)).collect())
+        .collect();
+
+    (0..targets.len()).map(|i| {
+        let rhs: Vec<bool> = (0..targets.len()).map(|k| k == i).collect();
+        let x = gf2_solve(&rows, &rhs).expect("Unable to find a correction operator");
+        let mut qubits: Vec<usize> = vec![];
+        for (cand, _) in candidates.iter().zip(x).filter(|&(_, xi)| xi)
+        {
+            for &q in cand.1.iter()
+            {
+                if let Some(pos) = qubits.iter().position(|&p| p == q)
+                {
+                    qubits.remove(pos);
+                }
+                else
+                {
+                    qubits.push(q);
+                }
+            }
+        }
+        qubits.sort();
+        qubits
+    }).collect()
+}
+
+/// Add the measurement of stabilizer `stabilizer` to `circuit`, using qubit
+/// `ancilla`, which should be in state |0⟩. The outcome is left in the
+/// ancilla.
+fn add_measure_stabilizer(circuit: &mut crate::circuit::Circuit, stabilizer: &Stabilizer,
+    ancilla: usize) -> crate::error::Result<()>
+{
+    match stabilizer.0
+    {
+        PauliOp::X => {
+            circuit.h(ancilla)?;
+            for &q in stabilizer.1.iter()
+            {
+                circuit.cx(ancilla, q)?;
+            }
+            circuit.h(ancilla)
+        },
+        _ => {
+            for &q in stabilizer.1.iter()
+            {
+                circuit.cx(q, ancilla)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Add Pauli operation `op` on `qubits`, controlled by qubit `control`, to
+/// `circuit`.
+fn add_controlled_pauli(circuit: &mut crate::circuit::Circuit, control: usize,
+    op: PauliOp, qubits: &[usize]) -> crate::error::Result<()>
+{
+    for &q in qubits.iter()
+    {
+        match op
+        {
+            PauliOp::X => circuit.cx(control, q)?,
+            _ => circuit.add_gate(crate::gates::CZ::new(), &[control, q])?
+        }
+    }
+    Ok(())
+}
+
+/// Add the measurement of stabilizers `targets` to `circuit`, followed by
+/// the corrections in `corrections` of type `op`, which are applied when the
+/// corresponding outcome is -1. The measurement results are not stored in the
+/// classical register; instead, the corrections are controlled by the
+/// ancilla qubit holding the outcome, which is reset afterwards.
+fn add_fixup(circuit: &mut crate::circuit::Circuit, targets: &[Stabilizer],
+    corrections: &[Vec<usize>], op: PauliOp, ancilla: usize) -> crate::error::Result<()>
+{
+    for (stabilizer, correction) in targets.iter().zip(corrections.iter())
+    {
+        add_measure_stabilizer(circuit, stabilizer, ancilla)?;
+        add_controlled_pauli(circuit, ancilla, op, correction)?;
+        circuit.reset(ancilla)?;
+    }
+    Ok(())
+}
+
+/// A surface code patch
+///
+/// Struct `SurfaceCodePatch` describes a single logical qubit, encoded in a
+/// rotated surface code of distance `d` on a square of `d×d` data qubits. The
+/// patch has a position on a global grid of data qubits, which determines how
+/// it can be merged with neighbouring patches. The top and bottom boundaries
+/// of the patch are `X` boundaries, the left and right boundaries `Z`
+/// boundaries. The logical `Z` operator acts on a row of the patch, the
+/// logical `X` operator on a column.
+#[derive(Clone, Debug)]
+pub struct SurfaceCodePatch
+{
+    /// The distance of the code
+    d: usize,
+    /// The grid row of the top left data qubit
+    row: usize,
+    /// The grid column of the top left data qubit
+    col: usize,
+    /// The circuit qubits holding the data qubits, in row major order
+    qubits: Vec<usize>,
+    /// The stabilizer generators of the code
+    stabilizers: Vec<Stabilizer>
+}
+
+impl SurfaceCodePatch
+{
+    /// Create a new surface code patch.
+    ///
+    /// Create a new patch of distance `d`, with its top left data qubit at
+    /// position (`row`, `col`) in the grid. The data qubits are stored in
+    /// circuit qubits `first_qubit` up to `first_qubit + d²`, in row major
+    /// order. The distance should be odd, this function panics if it is not.
+    pub fn new(d: usize, row: usize, col: usize, first_qubit: usize) -> Self
+    {
+        assert!(d % 2 == 1, "The distance of a surface code patch should be odd");
+
+        let qubits: Vec<usize> = (first_qubit..first_qubit + d*d).collect();
+        let stabilizers = rectangle_stabilizers(row, col, d, d).into_iter()
+            .map(|(op, cells)| {
+                let bits = cells.iter().map(|&(r, c)| qubits[(r-row)*d + c-col]).collect();
+                (op, bits)
+            })
+            .collect();
+
+        SurfaceCodePatch
+        {
+            d: d,
+            row: row,
+            col: col,
+            qubits: qubits,
+            stabilizers: stabilizers
+        }
+    }
+
+    /// The distance of the code
+    pub fn distance(&self) -> usize
+    {
+        self.d
+    }
+
+    /// The grid position of the top left data qubit of this patch
+    pub fn position(&self) -> (usize, usize)
+    {
+        (self.row, self.col)
+    }
+
+    /// The circuit qubits holding the data qubits, in row major order
+    pub fn qubits(&self) -> &[usize]
+    {
+        &self.qubits
+    }
+
+    /// The stabilizer generators of the code, as pairs of Pauli type and the
+    /// circuit qubits they act on.
+    pub fn stabilizers(&self) -> &[(PauliOp, Vec<usize>)]
+    {
+        &self.stabilizers
+    }
+
+    /// The qubits on which the logical `X` operator acts, i.e. the first
+    /// column of the patch.
+    pub fn logical_x(&self) -> Vec<usize>
+    {
+        (0..self.d).map(|r| self.qubits[r*self.d]).collect()
+    }
+
+    /// The qubits on which the logical `Z` operator acts, i.e. the first row
+    /// of the patch.
+    pub fn logical_z(&self) -> Vec<usize>
+    {
+        self.qubits[..self.d].to_vec()
+    }
+
+    /// Return the circuit qubit for grid position (`r`, `c`), if it lies in
+    /// this patch.
+    fn qubit_at(&self, r: usize, c: usize) -> Option<usize>
+    {
+        if r >= self.row && r < self.row + self.d && c >= self.col && c < self.col + self.d
+        {
+            Some(self.qubits[(r-self.row)*self.d + c-self.col])
+        }
+        else
+        {
+            None
+        }
+    }
+
+    /// Add the preparation of a logical basis state to a circuit.
+    ///
+    /// Add operations to `circuit` that prepare this patch in the logical
+    /// state |0⟩ if `basis` is `Basis::Z` or |+⟩ if `basis` is `Basis::X`.
+    /// All data qubits are reset and prepared in the corresponding physical
+    /// state, after which the stabilizers of the other type are measured
+    /// using qubit `ancilla`, and corrected to have eigenvalue +1. This
+    /// function panics when `basis` is `Basis::Y`.
+    pub fn prepare(&self, circuit: &mut crate::circuit::Circuit,
+        basis: crate::circuit::Basis, ancilla: usize) -> crate::error::Result<()>
+    {
+        let (op, random_op) = match basis
+            {
+                crate::circuit::Basis::Z => (PauliOp::Z, PauliOp::X),
+                crate::circuit::Basis::X => (PauliOp::X, PauliOp::Z),
+                crate::circuit::Basis::Y => {
+                    panic!("Only preparation in the X or Z basis is supported");
+                }
+            };
+
+        for &q in self.qubits.iter()
+        {
+            circuit.reset(q)?;
+            if op == PauliOp::X
+            {
+                circuit.h(q)?;
+            }
+        }
+
+        // The physical state is an eigenstate of the logical operator of the
+        // same type, so any correction of the same type will do
+        let random: Vec<Stabilizer> = self.stabilizers.iter()
+            .filter(|s| s.0 == random_op)
+            .cloned()
+            .collect();
+        let candidates: Vec<Stabilizer> = self.qubits.iter().map(|&q| (op, vec![q])).collect();
+        let fixes = corrections(&random, &candidates);
+        add_fixup(circuit, &random, &fixes, op, ancilla)?;
+        circuit.reset(ancilla)
+    }
+}
+
+/// Add a merge and split of two patches to a circuit.
+///
+/// Add a merge of adjacent patches `patch0` and `patch1` into a single
+/// rectangular patch to `circuit`, followed by `d` rounds of stabilizer
+/// measurements, and a split into the original patches. When the patches are
+/// on top of each other, this measures the product of their logical `Z`
+/// operators, when they are next to each other, the product of their logical
+/// `X` operators. The parity of the outcome is added to qubit `parity`, the
+/// stabilizers are measured using qubit `ancilla`.
+fn add_merge_split(circuit: &mut crate::circuit::Circuit, patch0: &SurfaceCodePatch,
+    patch1: &SurfaceCodePatch, ancilla: usize, parity: usize) -> crate::error::Result<()>
+{
+    let d = patch0.d;
+    let row = patch0.row.min(patch1.row);
+    let col = patch0.col.min(patch1.col);
+    let height = (patch0.row + d).max(patch1.row + d) - row;
+    let width = (patch0.col + d).max(patch1.col + d) - col;
+    assert!(height * width == 2 * d * d, "Merged patches should be adjacent");
+
+    let merged: Vec<Stabilizer> = rectangle_stabilizers(row, col, height, width).into_iter()
+        .map(|(op, cells)| {
+            let bits = cells.iter()
+                .map(|&(r, c)| patch0.qubit_at(r, c).or_else(|| patch1.qubit_at(r, c)).unwrap())
+                .collect();
+            (op, bits)
+        })
+        .collect();
+
+    // The stabilizers along the seam that do not commute with the original
+    // codes have a random outcome; the product of their outcomes is the
+    // outcome of the logical measurement.
+    let old: Vec<&Stabilizer> = patch0.stabilizers.iter().chain(patch1.stabilizers.iter()).collect();
+    let seam: Vec<bool> = merged.iter()
+        .map(|s| old.iter().any(|o| anticommute(s, o)))
+        .collect();
+
+    for round in 0..d
+    {
+        for (stabilizer, &is_seam) in merged.iter().zip(seam.iter())
+        {
+            add_measure_stabilizer(circuit, stabilizer, ancilla)?;
+            if round == 0 && is_seam
+            {
+                circuit.cx(ancilla, parity)?;
+            }
+            circuit.reset(ancilla)?;
+        }
+    }
+
+    // Split the patches by measuring the boundary stabilizers of the first
+    // patch again. Their outcomes are random, and are fixed by a product of
+    // the seam stabilizers, which acts trivially on the merged code. The
+    // corresponding boundary stabilizers of the second patch have the same
+    // outcome, and are fixed by the same operator.
+    let seam_stabilizers: Vec<Stabilizer> = merged.iter().zip(seam.iter())
+        .filter(|&(_, &is_seam)| is_seam)
+        .map(|(s, _)| s.clone())
+        .collect();
+    let boundary: Vec<Stabilizer> = patch0.stabilizers.iter()
+        .filter(|s| seam_stabilizers.iter().any(|n| anticommute(s, n)))
+        .cloned()
+        .collect();
+    let op = seam_stabilizers[0].0;
+    let fixes = corrections(&boundary, &seam_stabilizers);
+    add_fixup(circuit, &boundary, &fixes, op, ancilla)
+}
+
+/// Add a logical CNOT between two patches to a circuit.
+///
+/// Add a lattice surgery CNOT to `circuit`, with control patch `source` and
+/// target patch `target`. The ancilla patch and helper qubits use circuit
+/// qubits `first_ancilla` up to `first_ancilla + d² + 4`. See
+/// `logical_cnot()` for the requirements on the placement of the patches.
+fn add_logical_cnot(circuit: &mut crate::circuit::Circuit, source: &SurfaceCodePatch,
+    target: &SurfaceCodePatch, first_ancilla: usize) -> crate::error::Result<()>
+{
+    let d = source.d;
+    assert!(target.d == d, "The patches should have the same distance");
+    assert!(target.position() == (source.row + d, source.col + d),
+        "The target patch should be placed diagonally below and to the right of the source patch");
+
+    let patch = SurfaceCodePatch::new(d, source.row + d, source.col, first_ancilla);
+    let ancilla = first_ancilla + d*d;
+    let (pzz, pxx, pz) = (ancilla + 1, ancilla + 2, ancilla + 3);
+
+    // Measure Z_S Z_A, X_A X_T, and Z_A, with the ancilla patch A prepared
+    // in |+⟩
+    patch.prepare(circuit, crate::circuit::Basis::X, ancilla)?;
+    add_merge_split(circuit, source, &patch, ancilla, pzz)?;
+    add_merge_split(circuit, &patch, target, ancilla, pxx)?;
+    for q in patch.logical_z()
+    {
+        circuit.cx(q, pz)?;
+    }
+
+    // Apply the Pauli corrections X_T^(m_zz + m_z) and Z_S^(m_xx)
+    let target_x = target.logical_x();
+    add_controlled_pauli(circuit, pzz, PauliOp::X, &target_x)?;
+    add_controlled_pauli(circuit, pz, PauliOp::X, &target_x)?;
+    add_controlled_pauli(circuit, pxx, PauliOp::Z, &source.logical_z())?;
+
+    for &q in patch.qubits().iter().chain([pzz, pxx, pz].iter())
+    {
+        circuit.reset(q)?;
+    }
+
+    Ok(())
+}
+
+/// Logical CNOT using lattice surgery.
+///
+/// Create a circuit that implements a logical CNOT between surface code
+/// patches `source` (the control) and `target`, using lattice surgery. Both
+/// patches should have the same distance `d`, and the target patch should be
+/// placed at grid position `d` rows below and `d` columns to the right of the
+/// source patch; this function panics if they are not. An ancilla patch is
+/// prepared in logical state |+⟩ on the free square below the source patch,
+/// after which the products `Z`<sub>`S`</sub>`Z`<sub>`A`</sub> and
+/// `X`<sub>`A`</sub>`X`<sub>`T`</sub> are measured by merging and splitting the
+/// patches, and the ancilla is measured in the `Z` basis. Each merged patch is
+/// measured for `d` rounds. Instead of measuring into the classical register,
+/// the outcomes are collected in helper qubits, which control the Pauli
+/// corrections. The ancilla patch and helper qubits are placed after the
+/// highest qubit used by the two patches, and are reset to |0⟩ at the end of
+/// the circuit.
+pub fn logical_cnot(source: &SurfaceCodePatch, target: &SurfaceCodePatch)
+    -> crate::circuit::Circuit
+{
+    let first_ancilla = source.qubits.iter().chain(target.qubits.iter())
+        .max()
+        .map_or(0, |&q| q + 1);
+    let d = source.d;
+    let mut circuit = crate::circuit::Circuit::new(first_ancilla + d*d + 4, 0);
+    // All qubit indices are valid, so adding operations cannot fail
+    add_logical_cnot(&mut circuit, source, target, first_ancilla).unwrap();
+    circuit
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{add_logical_cnot, anticommute, gf2_solve, logical_cnot, rectangle_stabilizers,
+        SurfaceCodePatch};
+    use crate::circuit::{Basis, Circuit};
+    use crate::stabilizer::PauliOp;
+
+    fn parity(key: u64, bits: &[usize]) -> bool
+    {
+        bits.iter().fold(false, |p, &b| p ^ ((key >> b) & 1 == 1))
+    }
+
+    /// Check whether the product of the operators on `qubits` lies in the span
+    /// of `stabilizers`.
+    fn in_span(stabilizers: &[&(PauliOp, Vec<usize>)], qubits: &[usize], nr_qubits: usize) -> bool
+    {
+        let rows: Vec<Vec<bool>> = (0..nr_qubits)
+            .map(|q| stabilizers.iter().map(|s| s.1.contains(&q)).collect())
+            .collect();
+        let rhs: Vec<bool> = (0..nr_qubits).map(|q| qubits.contains(&q)).collect();
+        gf2_solve(&rows, &rhs).is_some()
+    }
+
+    #[test]
+    fn test_gf2_solve()
+    {
+        let rows = vec![vec![true, true, false], vec![false, true, true]];
+        assert_eq!(gf2_solve(&rows, &[true, false]), Some(vec![true, false, false]));
+        assert_eq!(gf2_solve(&rows, &[false, true]), Some(vec![true, true, false]));
+        let rows = vec![vec![true, true], vec![true, true]];
+        assert_eq!(gf2_solve(&rows, &[true, false]), None);
+    }
+
+    #[test]
+    fn test_rectangle_stabilizers()
+    {
+        let stabilizers = rectangle_stabilizers(0, 0, 3, 3);
+        assert_eq!(stabilizers.len(), 8);
+        assert_eq!(stabilizers[0], (PauliOp::X, vec![(0, 1), (0, 2)]));
+        assert_eq!(stabilizers[2], (PauliOp::X, vec![(0, 0), (0, 1), (1, 0), (1, 1)]));
+
+        let stabilizers = rectangle_stabilizers(3, 0, 3, 6);
+        assert_eq!(stabilizers.len(), 17);
+    }
+
+    #[test]
+    fn test_patch()
+    {
+        for &(d, row, col, first) in [(3, 0, 0, 0), (3, 3, 3, 9), (5, 5, 0, 2)].iter()
+        {
+            let patch = SurfaceCodePatch::new(d, row, col, first);
+            assert_eq!(patch.distance(), d);
+            assert_eq!(patch.position(), (row, col));
+            assert_eq!(patch.qubits().len(), d*d);
+            assert_eq!(patch.qubits()[0], first);
+
+            let stabilizers = patch.stabilizers();
+            assert_eq!(stabilizers.len(), d*d - 1);
+            assert_eq!(stabilizers.iter().filter(|s| s.0 == PauliOp::X).count(), (d*d - 1) / 2);
+            for s0 in stabilizers.iter()
+            {
+                assert!(stabilizers.iter().all(|s1| !anticommute(s0, s1)));
+            }
+
+            let lx = (PauliOp::X, patch.logical_x());
+            let lz = (PauliOp::Z, patch.logical_z());
+            assert!(stabilizers.iter().all(|s| !anticommute(s, &lx) && !anticommute(s, &lz)));
+            assert!(anticommute(&lx, &lz));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_patch_even()
+    {
+        SurfaceCodePatch::new(4, 0, 0, 0);
+    }
+
+    #[test]
+    fn test_merge_product()
+    {
+        let d = 3;
+        let source = SurfaceCodePatch::new(d, 0, 0, 0);
+        let ancilla = SurfaceCodePatch::new(d, d, 0, d*d);
+        let target = SurfaceCodePatch::new(d, d, d, 2*d*d);
+        for &(p0, p1, op) in [(&source, &ancilla, PauliOp::Z), (&ancilla, &target, PauliOp::X)].iter()
+        {
+            let layout: Vec<(usize, usize)> = [p0, p1].iter()
+                .flat_map(|p| (0..d*d).map(move |i| (p.row + i / d, p.col + i % d)))
+                .collect();
+            let row = p0.row.min(p1.row);
+            let col = p0.col.min(p1.col);
+            let height = (p0.row + d).max(p1.row + d) - row;
+            let width = (p0.col + d).max(p1.col + d) - col;
+            let merged: Vec<(PauliOp, Vec<usize>)> = rectangle_stabilizers(row, col, height, width)
+                .into_iter()
+                .map(|(op, cells)| {
+                    (op, cells.iter().map(|cell| {
+                        p0.qubits()[0] + layout.iter().position(|c| c == cell).unwrap()
+                    }).collect())
+                })
+                .collect();
+            let old: Vec<&(PauliOp, Vec<usize>)> = p0.stabilizers().iter()
+                .chain(p1.stabilizers().iter())
+                .collect();
+            let seam: Vec<&(PauliOp, Vec<usize>)> = merged.iter()
+                .filter(|s| old.iter().any(|o| anticommute(s, o)))
+                .collect();
+            assert!(seam.iter().all(|s| s.0 == op));
+
+            // The product of the seam stabilizers should be equal to the
+            // product of the logical operators, up to stabilizers
+            let mut qubits: Vec<usize> = vec![];
+            let logical = if op == PauliOp::Z
+                {
+                    [p0.logical_z(), p1.logical_z()].concat()
+                }
+                else
+                {
+                    [p0.logical_x(), p1.logical_x()].concat()
+                };
+            for &q in seam.iter().flat_map(|s| s.1.iter()).chain(logical.iter())
+            {
+                if let Some(pos) = qubits.iter().position(|&p| p == q)
+                {
+                    qubits.remove(pos);
+                }
+                else
+                {
+                    qubits.push(q);
+                }
+            }
+            let same_type: Vec<&(PauliOp, Vec<usize>)> = old.iter()
+                .filter(|s| s.0 == op)
+                .cloned()
+                .collect();
+            assert!(in_span(&same_type, &qubits, 3*d*d));
+        }
+    }
+
+    #[test]
+    fn test_prepare()
+    {
+        let d = 3;
+        let nr_shots = 10;
+        let patch = SurfaceCodePatch::new(d, 0, 0, 0);
+        for &basis in [Basis::Z, Basis::X].iter()
+        {
+            let mut circuit = Circuit::new(d*d + 1, d*d);
+            assert_eq!(patch.prepare(&mut circuit, basis, d*d), Ok(()));
+            for &q in patch.qubits()
+            {
+                assert_eq!(circuit.measure_basis(q, q, basis), Ok(()));
+            }
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+            let (op, logical) = match basis
+                {
+                    Basis::Z => (PauliOp::Z, patch.logical_z()),
+                    _        => (PauliOp::X, patch.logical_x())
+                };
+            for (&key, _) in circuit.histogram().unwrap().iter()
+            {
+                assert!(!parity(key, &logical));
+                for s in patch.stabilizers().iter().filter(|s| s.0 == op)
+                {
+                    assert!(!parity(key, &s.1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_logical_cnot_basis()
+    {
+        let d = 3;
+        let nr_shots = 5;
+        let source = SurfaceCodePatch::new(d, 0, 0, 0);
+        let target = SurfaceCodePatch::new(d, d, d, d*d);
+        let first_ancilla = 2*d*d;
+        let ancilla = first_ancilla + d*d;
+
+        let circuit = logical_cnot(&source, &target);
+        assert_eq!(circuit.nr_qbits(), 3*d*d + 4);
+        assert!(circuit.is_stabilizer_circuit());
+
+        for &(c, t) in [(false, false), (false, true), (true, false), (true, true)].iter()
+        {
+            let mut circuit = Circuit::new(3*d*d + 4, 2*d*d);
+            assert_eq!(source.prepare(&mut circuit, Basis::Z, ancilla), Ok(()));
+            assert_eq!(target.prepare(&mut circuit, Basis::Z, ancilla), Ok(()));
+            for &(patch, flip) in [(&source, c), (&target, t)].iter()
+            {
+                if flip
+                {
+                    for q in patch.logical_x()
+                    {
+                        assert_eq!(circuit.x(q), Ok(()));
+                    }
+                }
+            }
+            assert_eq!(add_logical_cnot(&mut circuit, &source, &target, first_ancilla), Ok(()));
+            for q in 0..2*d*d
+            {
+                assert_eq!(circuit.measure(q, q), Ok(()));
+            }
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+            for (&key, _) in circuit.histogram().unwrap().iter()
+            {
+                assert_eq!(parity(key, &source.logical_z()), c);
+                assert_eq!(parity(key, &target.logical_z()), c ^ t);
+                for s in source.stabilizers().iter().chain(target.stabilizers().iter())
+                    .filter(|s| s.0 == PauliOp::Z)
+                {
+                    assert!(!parity(key, &s.1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_logical_cnot_bell()
+    {
+        let d = 3;
+        let nr_shots = 20;
+        let source = SurfaceCodePatch::new(d, 0, 0, 0);
+        let target = SurfaceCodePatch::new(d, d, d, d*d);
+        let first_ancilla = 2*d*d;
+        let ancilla = first_ancilla + d*d;
+
+        // Control in |+⟩ and target in |0⟩ should result in a Bell state, with
+        // Z_S Z_T = X_S X_T = 1.
+        for &basis in [Basis::Z, Basis::X].iter()
+        {
+            let mut circuit = Circuit::new(3*d*d + 4, 2*d*d);
+            assert_eq!(source.prepare(&mut circuit, Basis::X, ancilla), Ok(()));
+            assert_eq!(target.prepare(&mut circuit, Basis::Z, ancilla), Ok(()));
+            assert_eq!(add_logical_cnot(&mut circuit, &source, &target, first_ancilla), Ok(()));
+            for q in 0..2*d*d
+            {
+                assert_eq!(circuit.measure_basis(q, q, basis), Ok(()));
+            }
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+            let (op, ls, lt) = match basis
+                {
+                    Basis::Z => (PauliOp::Z, source.logical_z(), target.logical_z()),
+                    _        => (PauliOp::X, source.logical_x(), target.logical_x())
+                };
+            let hist = circuit.histogram().unwrap();
+            for (&key, _) in hist.iter()
+            {
+                assert_eq!(parity(key, &ls), parity(key, &lt));
+                for s in source.stabilizers().iter().chain(target.stabilizers().iter())
+                    .filter(|s| s.0 == op)
+                {
+                    assert!(!parity(key, &s.1));
+                }
+            }
+            if let Basis::Z = basis
+            {
+                // Both logical outcomes should occur
+                assert!(hist.keys().any(|&key| parity(key, &ls)));
+                assert!(hist.keys().any(|&key| !parity(key, &ls)));
+            }
+        }
+    }
+}
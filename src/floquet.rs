@@ -0,0 +1,190 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Floquet circuits
+//!
+//! A periodically driven system with Hamiltonian `H(t) = H(t+T)` is described
+//! over a full period by the Floquet unitary `U(T)`, whose eigenvalues
+//! `exp(-iεT)` define the quasi-energies `ε` of the system. Here, the drive is
+//! given as a cycle of piecewise constant Hamiltonians, each applied for the
+//! same time step, and the evolution under each of them is approximated by a
+//! single first order Trotter step.
+
+use crate::hamiltonian::Hamiltonian;
+
+/// The number of qubits the Hamiltonians in `hamiltonian_cycle` act on
+fn nr_qbits_for(hamiltonian_cycle: &[Hamiltonian]) -> usize
+{
+    hamiltonian_cycle.iter()
+        .flat_map(|h| h.to_pauli_strings().iter())
+        .map(|(_, ps)| ps.nr_bits())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Add a single period of the Floquet evolution under `hamiltonian_cycle` to
+/// `circuit`.
+fn add_floquet_period(circuit: &mut crate::circuit::Circuit, hamiltonian_cycle: &[Hamiltonian],
+    time_step: f64)
+{
+    for hamiltonian in hamiltonian_cycle
+    {
+        for (coef, ps) in hamiltonian.to_pauli_strings()
+        {
+            // All qubits are valid, so this cannot fail
+            crate::walk::add_pauli_rotation(circuit, ps, coef * time_step).unwrap();
+        }
+    }
+}
+
+/// Floquet circuit.
+///
+/// Create a circuit implementing a single period of the evolution under the
+/// periodic drive described by `hamiltonian_cycle`, in which every
+/// Hamiltonian is applied in turn for a time `time_step`. The evolution under
+/// each Hamiltonian `H = Σ c`<sub>`P`</sub>` P` is approximated by the first
+/// order Trotter step `Π exp(-ic`<sub>`P`</sub>`P·time_step)`, which is exact
+/// up to a global phase when all its terms commute. The circuit acts on as
+/// many qubits as the largest Pauli string in the cycle, and has no classical
+/// bits.
+pub fn floquet_circuit(hamiltonian_cycle: &[Hamiltonian], time_step: f64)
+    -> crate::circuit::Circuit
+{
+    floquet_quasi_energy_circuit(hamiltonian_cycle, time_step, 1)
+}
+
+/// Repeated Floquet circuit.
+///
+/// Create a circuit applying the Floquet period of `floquet_circuit()`
+/// `nr_periods` times, i.e. implementing `U(T)`<sup>`nr_periods`</sup>. Used
+/// as the controlled unitaries in phase estimation, these circuits give the
+/// quasi-energies of the drive.
+pub fn floquet_quasi_energy_circuit(hamiltonian_cycle: &[Hamiltonian], time_step: f64,
+    nr_periods: usize) -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(nr_qbits_for(hamiltonian_cycle), 0);
+    for _ in 0..nr_periods
+    {
+        add_floquet_period(&mut circuit, hamiltonian_cycle, time_step);
+    }
+    circuit
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{floquet_circuit, floquet_quasi_energy_circuit};
+    use crate::cmatrix::{CMatrix, CNumber};
+    use crate::gates::Gate;
+    use crate::hamiltonian::Hamiltonian;
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    /// Compute exp(-iHt) for Hermitian matrix `h` using a Taylor series
+    fn evolution(h: &CMatrix, t: f64) -> CMatrix
+    {
+        let mh = h.mapv(|x| x * CNumber::new(0.0, -t));
+        let mut term = CMatrix::eye(h.rows());
+        let mut res = term.clone();
+        for k in 1..60
+        {
+            term = mh.dot(&term) / k as f64;
+            res += &term;
+        }
+        res
+    }
+
+    /// Check that `m0` and `m1` are equal up to a global phase, within `tol`.
+    fn equal_up_to_phase(m0: &CMatrix, m1: &CMatrix, tol: f64) -> bool
+    {
+        let (idx, _) = m1.indexed_iter()
+            .max_by(|(_, x), (_, y)| x.norm().partial_cmp(&y.norm()).unwrap())
+            .unwrap();
+        let phase = m0[idx] / m1[idx];
+        let phase = phase / phase.norm();
+        m0.iter().zip(m1.iter()).all(|(&x, &y)| (x - phase * y).norm() < tol)
+    }
+
+    /// The field `B(cos(ωt)X + sin(ωt)Y)` at time `t`
+    fn rotating_field(b: f64, omega: f64, t: f64) -> Hamiltonian
+    {
+        let mut h = Hamiltonian::new();
+        h.add_term(b * (omega * t).cos(), PauliString::new(&[PauliOp::X]));
+        h.add_term(b * (omega * t).sin(), PauliString::new(&[PauliOp::Y]));
+        h
+    }
+
+    fn hamiltonian_matrix(h: &Hamiltonian) -> CMatrix
+    {
+        let mut m = CMatrix::zeros((2, 2));
+        for (c, ps) in h.to_pauli_strings()
+        {
+            m += &(ps.matrix() * *c);
+        }
+        m
+    }
+
+    #[test]
+    fn test_floquet_circuit()
+    {
+        let (b, omega, dt) = (0.8, 2.0, 0.01);
+        let cycle: Vec<Hamiltonian> = (0..4).map(|k| rotating_field(b, omega, k as f64 * dt))
+            .collect();
+        let circuit = floquet_circuit(&cycle, dt);
+        assert_eq!(circuit.nr_qbits(), 1);
+        assert_eq!(circuit.nr_cbits(), 0);
+
+        let u = crate::gates::Composite::from_circuit("floquet", &circuit).unwrap().matrix();
+        let mut expected = CMatrix::eye(2);
+        for h in cycle.iter()
+        {
+            expected = evolution(&hamiltonian_matrix(h), dt).dot(&expected);
+        }
+        // Error of a Trotter step ~ [X, Y]·dt² ~ 1e-4 per step
+        assert!(equal_up_to_phase(&u, &expected, 1.0e-3));
+        assert!(!equal_up_to_phase(&u, &CMatrix::eye(2), 1.0e-3));
+
+        // Single term Hamiltonians are exact
+        let cycle: Vec<Hamiltonian> = [PauliOp::X, PauliOp::Z].iter().map(|&op| {
+                let mut h = Hamiltonian::new();
+                h.add_term(0.6, PauliString::new(&[op]));
+                h
+            }).collect();
+        let u = crate::gates::Composite::from_circuit("floquet", &floquet_circuit(&cycle, 0.3))
+            .unwrap().matrix();
+        let expected = evolution(&hamiltonian_matrix(&cycle[1]), 0.3)
+            .dot(&evolution(&hamiltonian_matrix(&cycle[0]), 0.3));
+        assert!(equal_up_to_phase(&u, &expected, 1.0e-10));
+    }
+
+    #[test]
+    fn test_floquet_quasi_energy_circuit()
+    {
+        let mut h = Hamiltonian::new();
+        h.add_term(0.5, PauliString::new(&[PauliOp::Z, PauliOp::Z]));
+        let mut g = Hamiltonian::new();
+        g.add_term(0.25, PauliString::new(&[PauliOp::X, PauliOp::I]));
+        let cycle = [h, g];
+
+        let period = crate::gates::Composite::from_circuit("floquet", &floquet_circuit(&cycle, 0.2))
+            .unwrap().matrix();
+        let circuit = floquet_quasi_energy_circuit(&cycle, 0.2, 3);
+        assert_eq!(circuit.nr_qbits(), 2);
+        let u = crate::gates::Composite::from_circuit("floquet", &circuit).unwrap().matrix();
+        assert!(equal_up_to_phase(&u, &period.dot(&period).dot(&period), 1.0e-10));
+
+        let circuit = floquet_quasi_energy_circuit(&cycle, 0.2, 0);
+        assert_eq!(circuit.nr_qbits(), 2);
+        assert!(circuit.to_instructions().is_empty());
+    }
+}
@@ -12,15 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod cached;
 mod controlled;
+mod controlledphase;
 mod composite;
+mod custom;
 mod cx;
 mod cy;
 mod cz;
 mod hadamard;
 mod identity;
+mod iswap;
 mod kron;
+mod mcx;
+mod noise;
 mod parameter;
+mod phase;
 mod rx;
 mod ry;
 mod rz;
@@ -32,25 +39,84 @@ mod v;
 mod u1;
 mod u2;
 mod u3;
+mod universal;
 mod x;
 mod y;
 mod z;
 
-/// Reorder bits.
+/// Default number of bit permutations cached per thread
+const DEFAULT_BIT_PERMUTATION_CACHE_SIZE: usize = 16;
+
+/// Maximum number of bit permutations cached per thread
+static BIT_PERMUTATION_CACHE_SIZE: ::std::sync::atomic::AtomicUsize
+    = ::std::sync::atomic::AtomicUsize::new(DEFAULT_BIT_PERMUTATION_CACHE_SIZE);
+
+/// Least recently used cache of bit permutations
 ///
-/// When applying multi-bit gates, the rows in the state are shuffled
-/// such that:
-/// * The first half of the rows correspond to components with the first
-///   affected bit being 0, the second half to those with this bit being 1.
-/// * Within each of these two blocks, the first half corresponds to
-///   components with the second bit 0, the second half to those with the
-///   second bit 1.
-/// * And so on, for each affected bit.
+/// Struct `BitPermutationCache` holds the most recently computed bit
+/// permutations, keyed on the number of bits and the affected bits, in order
+/// of last use. The most recently used permutation is at the front.
+struct BitPermutationCache
+{
+    entries: ::std::collections::VecDeque<((usize, Vec<usize>), crate::permutation::Permutation)>
+}
+
+impl BitPermutationCache
+{
+    /// Create a new, empty, cache
+    fn new() -> Self
+    {
+        BitPermutationCache { entries: ::std::collections::VecDeque::new() }
+    }
+
+    /// Look up the permutation for affected bits `affected_bits` in a
+    /// `nr_bits`-sized system, and mark it as most recently used.
+    fn get(&mut self, nr_bits: usize, affected_bits: &[usize])
+        -> Option<&crate::permutation::Permutation>
+    {
+        let pos = self.entries.iter()
+            .position(|((n, bits), _)| *n == nr_bits && bits.as_slice() == affected_bits)?;
+        if pos > 0
+        {
+            let entry = self.entries.remove(pos).unwrap();
+            self.entries.push_front(entry);
+        }
+        self.entries.front().map(|(_, perm)| perm)
+    }
+
+    /// Store permutation `perm` for affected bits `affected_bits` in a
+    /// `nr_bits`-sized system, evicting the least recently used permutations
+    /// if the cache holds more than `capacity` entries.
+    fn insert(&mut self, nr_bits: usize, affected_bits: &[usize],
+        perm: crate::permutation::Permutation, capacity: usize)
+    {
+        self.entries.push_front(((nr_bits, affected_bits.to_vec()), perm));
+        self.entries.truncate(capacity);
+    }
+}
+
+thread_local!
+{
+    static BIT_PERMUTATION_CACHE: ::std::cell::RefCell<BitPermutationCache>
+        = ::std::cell::RefCell::new(BitPermutationCache::new());
+}
+
+/// Set the bit permutation cache size.
 ///
-/// This function returns a permutation matrix `P`, such that the matrix
-/// `P (G ⊗ I ⊗ ... ⊗ I) P`<sup>`T`</sup> describes the effect of operating with a
-/// gate `G` on bits `affected_bits` in a `nr_bits`-sized system.
-pub fn bit_permutation(nr_bits: usize, affected_bits: &[usize]) -> crate::permutation::Permutation
+/// Set the maximum number of permutations computed by `bit_permutation()`
+/// that are kept for reuse in each thread to `size`. Note that a permutation
+/// in a `n`-bit system takes 2<sup>`n`</sup> indices of memory. Setting the
+/// size to zero disables the cache. The default size is 16.
+pub fn set_bit_permutation_cache_size(size: usize)
+{
+    BIT_PERMUTATION_CACHE_SIZE.store(size, ::std::sync::atomic::Ordering::Relaxed);
+    BIT_PERMUTATION_CACHE.with(|cache| cache.borrow_mut().entries.truncate(size));
+}
+
+/// Compute the bit permutation for `affected_bits` in a `nr_bits`-sized
+/// system, without consulting the cache.
+fn compute_bit_permutation(nr_bits: usize, affected_bits: &[usize])
+    -> crate::permutation::Permutation
 {
     let mut perm1: Vec<_> = (0..(1 << nr_bits)).collect();
     let mut ab = affected_bits.to_vec();
@@ -79,6 +145,72 @@ pub fn bit_permutation(nr_bits: usize, affected_bits: &[usize]) -> crate::permut
     crate::permutation::Permutation::new(perm1).unwrap().inverse()
 }
 
+/// Reorder bits.
+///
+/// When applying multi-bit gates, the rows in the state are shuffled
+/// such that:
+/// * The first half of the rows correspond to components with the first
+///   affected bit being 0, the second half to those with this bit being 1.
+/// * Within each of these two blocks, the first half corresponds to
+///   components with the second bit 0, the second half to those with the
+///   second bit 1.
+/// * And so on, for each affected bit.
+///
+/// This function returns a permutation matrix `P`, such that the matrix
+/// `P (G ⊗ I ⊗ ... ⊗ I) P`<sup>`T`</sup> describes the effect of operating with a
+/// gate `G` on bits `affected_bits` in a `nr_bits`-sized system.
+///
+/// Recently computed permutations are cached, so that repeated application
+/// of gates on the same bits does not need to compute the permutation again.
+/// See `set_bit_permutation_cache_size()`.
+pub fn bit_permutation(nr_bits: usize, affected_bits: &[usize]) -> crate::permutation::Permutation
+{
+    let capacity = BIT_PERMUTATION_CACHE_SIZE.load(::std::sync::atomic::Ordering::Relaxed);
+    if capacity == 0
+    {
+        return compute_bit_permutation(nr_bits, affected_bits);
+    }
+
+    BIT_PERMUTATION_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(perm) = cache.get(nr_bits, affected_bits)
+        {
+            return perm.clone();
+        }
+
+        let perm = compute_bit_permutation(nr_bits, affected_bits);
+        cache.insert(nr_bits, affected_bits, perm.clone(), capacity);
+        perm
+    })
+}
+
+/// Sparse representation of a diagonal gate
+///
+/// Return the non-zero elements of a gate whose matrix is diagonal, with
+/// diagonal entries `diag`, in the `(row, column, value)` format returned by
+/// `Gate::sparsity()`.
+pub fn diagonal_sparsity(diag: &[crate::cmatrix::CNumber])
+    -> Vec<(usize, usize, crate::cmatrix::CNumber)>
+{
+    diag.iter().enumerate()
+        .filter(|&(_, &c)| c != crate::cmatrix::COMPLEX_ZERO)
+        .map(|(i, &c)| (i, i, c))
+        .collect()
+}
+
+/// Sparse representation of a permutation gate
+///
+/// Return the non-zero elements of a gate that maps basis state |`j`⟩ to
+/// basis state |`perm[j]`⟩, in the `(row, column, value)` format returned by
+/// `Gate::sparsity()`.
+pub fn permutation_sparsity(perm: &[usize])
+    -> Vec<(usize, usize, crate::cmatrix::CNumber)>
+{
+    perm.iter().enumerate()
+        .map(|(j, &i)| (i, j, crate::cmatrix::COMPLEX_ONE))
+        .collect()
+}
+
 /// Apply a gate
 ///
 /// Apply gate `gate` operating on the bits in `bits` to a vector `vec`. The
@@ -169,6 +301,29 @@ pub trait Gate
     /// The number of qubits affected by this gate.
     fn nr_affected_bits(&self) -> usize;
 
+    /// Return the parameters of this gate.
+    ///
+    /// Return references to the parameters (usually angles) that determine the
+    /// matrix of this gate. Gates made up of other gates return the
+    /// parameters of their constituent gates. The default implementation
+    /// returns an empty list, for gates without parameters.
+    fn parameters(&self) -> Vec<&Parameter>
+    {
+        vec![]
+    }
+
+    /// Bind the parameters of this gate.
+    ///
+    /// Replace the free and reference parameters of this gate whose names
+    /// occur in `store` by their values in the store (see
+    /// `Parameter::bind()`). When a free parameter has no value in `store`,
+    /// an `UnboundParameter` error is returned. The default implementation
+    /// does nothing, for gates without parameters.
+    fn bind(&mut self, _store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        Ok(())
+    }
+
     /// Return a matrix describing the unitary transformation that the gate
     /// provides
     fn matrix(&self) -> crate::cmatrix::CMatrix;
@@ -325,6 +480,64 @@ pub trait Gate
         }
     }
 
+    /// Apply a gate to all columns.
+    ///
+    /// Apply this gate, operating on qubits `bits` in a `nr_bits`-sized
+    /// system, to all columns of `state` at once, where each column holds the
+    /// state vector for a single run of a circuit. Unlike `apply_mat()`, the
+    /// rows in `state` are in the natural order of the system's basis states,
+    /// and are permuted as needed. For single-qubit gates, the gate is applied
+    /// to blocks of rows of the full matrix, without building a permutation.
+    /// The default implementation uses `apply_gate_mat_slice()`.
+    fn apply_columns(&self, state: &mut crate::cmatrix::CMatrix, bits: &[usize], nr_bits: usize)
+    {
+        apply_gate_mat_slice(state.view_mut(), self, bits, nr_bits);
+    }
+
+    /// Sparse gate matrix
+    ///
+    /// Return the non-zero elements of the gate matrix as a list of
+    /// `(row, column, value)` tuples, if the matrix is sparse enough for
+    /// this representation to be worthwhile (say, less than 10% non-zero
+    /// elements, or a diagonal or permutation-like structure). Gates whose
+    /// matrix is dense return `None`, which is what the default
+    /// implementation does.
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        None
+    }
+
+    /// Apply a gate using its sparse representation.
+    ///
+    /// Apply a gate to quantum state `state`, using the sparse matrix elements
+    /// returned by `sparsity()`. The requirements on `state` are the same as
+    /// for `apply()`. When the gate does not provide a sparse representation,
+    /// this falls back to `apply_slice()`.
+    fn apply_sparse(&self, state: &mut crate::cmatrix::CVector)
+    {
+        if let Some(elements) = self.sparsity()
+        {
+            let nr_bits = self.nr_affected_bits();
+            assert!(state.len() % (1 << nr_bits) == 0,
+                "The number of rows in the state is {}, which is not valid for a {}-bit gate.",
+                state.len(), nr_bits);
+
+            let n = state.len() >> nr_bits;
+            let mut res = crate::cmatrix::CVector::zeros(state.len());
+            for &(i, j, c) in elements.iter()
+            {
+                let x = &state.slice(s![j*n..(j+1)*n]) * c;
+                let mut slice = res.slice_mut(s![i*n..(i+1)*n]);
+                slice += &x;
+            }
+            state.assign(&res);
+        }
+        else
+        {
+            self.apply_slice(state.view_mut());
+        }
+    }
+
     /// Check the number of bits
     ///
     /// Check if the number of bit indices `n` is equal to the number
@@ -367,6 +580,87 @@ pub trait Gate
     {
         Err(crate::error::Error::NotAStabilizer(String::from(self.description())))
     }
+
+    /// Hermitian conjugate of this gate
+    ///
+    /// Return a gate performing the inverse `G`<sup>`†`</sup> of this gate
+    /// `G`. The default implementation returns a `Custom` gate holding the
+    /// conjugate transpose of the matrix of this gate; note that this
+    /// captures the current value of any reference parameters.
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        matrix_adjoint(self)
+    }
+
+    /// Structure of this gate
+    ///
+    /// For gates that are built from other gates, return how this gate is
+    /// composed, so that it can be recreated from its parts. The default
+    /// implementation returns `None`.
+    fn structure(&self) -> Option<GateStructure<'_>>
+    {
+        None
+    }
+
+    /// Cache the matrix of this gate
+    ///
+    /// Wrap this gate in a `CachedGate`, which computes the gate matrix only
+    /// once, and uses it in subsequent applications of the gate.
+    fn cached(self) -> CachedGate<Self>
+    where Self: Sized
+    {
+        CachedGate::new(self)
+    }
+}
+
+/// Structure of a gate that is built from other gates
+pub enum GateStructure<'a>
+{
+    /// Composite gate named `name`, operating on `nr_bits` qubits, made up of
+    /// the gates in `ops` operating on the associated bits
+    Composite { name: &'a str, nr_bits: usize, ops: Vec<(&'a dyn Gate, &'a [usize])> },
+    /// Controlled version of a gate
+    Controlled(&'a dyn Gate),
+    /// Kronecker product of two gates
+    Kron(&'a dyn Gate, &'a dyn Gate),
+    /// Static loop with label `label`, executing `body` `nr_iterations` times
+    Loop { label: &'a str, nr_iterations: usize, body: &'a Composite }
+}
+
+/// Hermitian conjugate of a gate.
+///
+/// Return a `Custom` gate, whose matrix is the conjugate transpose of the
+/// matrix of gate `gate`.
+pub(crate) fn matrix_adjoint<G>(gate: &G) -> Box<dyn crate::export::CircuitGate>
+where G: Gate + ?Sized
+{
+    let desc = format!("{}†", gate.description());
+    Box::new(Custom::new(&desc, crate::linalg::adjoint(&gate.matrix())))
+}
+
+/// Wrapper for displaying a gate
+///
+/// Struct `GateDisplay` implements `Display` for a gate, printing its
+/// description followed by its matrix, as e.g.
+/// `X: [[0+0i, 1+0i], [1+0i, 0+0i]]`. Matrix elements are rounded to the
+/// precision of the formatter, or three decimals when no precision is given.
+pub struct GateDisplay<'a>(pub &'a dyn Gate);
+
+impl<'a> ::std::fmt::Display for GateDisplay<'a>
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        let precision = f.precision().unwrap_or(3);
+        let rows: Vec<String> = self.0.matrix().outer_iter()
+            .map(|row| {
+                let elems: Vec<String> = row.iter()
+                    .map(|&x| crate::cmatrix::format_complex(x, precision))
+                    .collect();
+                format!("[{}]", elems.join(", "))
+            })
+            .collect();
+        write!(f, "{}: [{}]", self.0.description(), rows.join(", "))
+    }
 }
 
 #[cfg(test)]
@@ -380,16 +674,48 @@ where G: Gate
     assert_complex_matrix_eq!(&*state, result);
 }
 
-pub use self::parameter::Parameter;
+#[cfg(test)]
+fn sparse_test<G>(gate: G)
+where G: Gate
+{
+    let elements = gate.sparsity().expect("Gate should have a sparse representation");
+    let size = 1 << gate.nr_affected_bits();
+    let mut mat = crate::cmatrix::CMatrix::zeros((size, size));
+    for &(i, j, c) in elements.iter()
+    {
+        mat[[i, j]] += c;
+    }
+    assert_complex_matrix_eq!(&mat, &gate.matrix());
+
+    let state: crate::cmatrix::CVector = (0..2*size)
+        .map(|k| crate::cmatrix::CNumber::new(k as f64, 1.0 - 0.5 * k as f64))
+        .collect();
+    let mut sparse = state.clone();
+    gate.apply_sparse(&mut sparse);
+    let mut dense = state.clone();
+    gate.apply(&mut dense);
+    assert_complex_vector_eq!(&sparse, &dense);
+}
+
+pub use self::parameter::{Parameter, ParameterStore};
+pub use self::cached::CachedGate;
 
-pub use self::controlled::{C, CH, CRX, CRY, CRZ, CS, CSdg, CT, CTdg, CU1, CU2, CU3, CV, CVdg, CCRX, CCRY, CCRZ, CCX, CCZ};
+pub use self::controlled::{C, CH, CRX, CRY, CRZ, CS, CSdg, CT, CTdg, CU1, CU2, CU3, CV, CVdg, CCRX, CCRY, CCRZ, CCX, CCZ, CSwap};
+pub use self::controlledphase::ControlledPhase;
 pub use self::composite::Composite;
+pub use self::custom::Custom;
+pub(crate) use self::custom::u3_angles;
 pub use self::cx::CX;
 pub use self::cy::CY;
 pub use self::cz::CZ;
 pub use self::hadamard::H;
 pub use self::identity::I;
+pub use self::iswap::{ISWAP, ISWAPdg};
 pub use self::kron::Kron;
+pub use self::mcx::n_controlled_x;
+pub use self::noise::{AmplitudeDamping, Depolarizing, NoiseChannel, PhaseDamping, ThermalRelaxation,
+    TwoQubitDepolarizing};
+pub use self::phase::{GlobalPhase, Phase};
 pub use self::rx::RX;
 pub use self::ry::RY;
 pub use self::rz::RZ;
@@ -400,7 +726,131 @@ pub use self::swap::Swap;
 pub use self::u1::U1;
 pub use self::u2::U2;
 pub use self::u3::U3;
+pub use self::universal::is_universal_gate_set;
 pub use self::v::{V, Vdg};
 pub use self::x::X;
 pub use self::y::Y;
 pub use self::z::Z;
+
+#[cfg(test)]
+mod tests
+{
+    use super::{bit_permutation, compute_bit_permutation, GateDisplay, H, RX};
+
+    #[test]
+    fn test_gate_display()
+    {
+        assert_eq!(format!("{}", GateDisplay(&H::new())),
+            "H: [[0.707+0i, 0.707+0i], [0.707+0i, -0.707+0i]]");
+        assert_eq!(format!("{:.1}", GateDisplay(&RX::new(::std::f64::consts::PI))),
+            "RX(3.1416): [[0+0i, 0-1i], [0-1i, 0+0i]]");
+    }
+
+    #[test]
+    fn test_bit_permutation_cache()
+    {
+        let perm = bit_permutation(3, &[2, 0]);
+        assert_eq!(perm.indices(), &[0, 2, 4, 6, 1, 3, 5, 7]);
+        assert_eq!(bit_permutation(3, &[2, 0]), perm);
+        // The permutation depends on the order of the affected bits
+        assert_ne!(bit_permutation(3, &[0, 2]), perm);
+
+        let mut cache = super::BitPermutationCache::new();
+        for (nr_bits, bits) in [(3, vec![0, 1]), (3, vec![1, 0]), (4, vec![0, 1])].iter()
+        {
+            cache.insert(*nr_bits, bits, compute_bit_permutation(*nr_bits, bits), 2);
+        }
+        assert_eq!(cache.entries.len(), 2);
+        assert!(cache.get(3, &[0, 1]).is_none());
+        assert_eq!(cache.get(3, &[1, 0]), Some(&compute_bit_permutation(3, &[1, 0])));
+        assert_eq!(cache.get(4, &[0, 1]), Some(&compute_bit_permutation(4, &[0, 1])));
+        // Most recently used entry was (4, [0, 1]), so inserting evicts (3, [1, 0])
+        cache.insert(5, &[2, 3], compute_bit_permutation(5, &[2, 3]), 2);
+        assert!(cache.get(3, &[1, 0]).is_none());
+        assert!(cache.get(4, &[0, 1]).is_some());
+    }
+
+    #[test]
+    fn test_apply_columns()
+    {
+        use crate::gates::*;
+
+        let gates: Vec<(Box<dyn crate::export::CircuitGate>, Vec<usize>)> = vec![
+            (Box::new(H::new()), vec![1]),
+            (Box::new(U3::new(1.1, 0.2, -0.8)), vec![2]),
+            (Box::new(CX::new()), vec![2, 0]),
+            (Box::new(CCX::new()), vec![1, 2, 0])
+        ];
+
+        let nr_bits = 3;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let mut state = crate::cmatrix::CMatrix::zeros((1 << nr_bits, 4));
+        state[[0, 0]] = crate::cmatrix::COMPLEX_ONE;
+        state[[5, 1]] = crate::cmatrix::COMPLEX_I;
+        state[[3, 2]] = x;
+        state[[6, 2]] = -x;
+        state.column_mut(3).fill(crate::cmatrix::COMPLEX_HSQRT2 * x * x);
+
+        for (gate, bits) in gates.iter()
+        {
+            let mut expected = state.clone();
+            for mut col in expected.gencolumns_mut()
+            {
+                apply_gate_slice(col.view_mut(), gate, bits, nr_bits);
+            }
+            gate.apply_columns(&mut state, bits, nr_bits);
+            assert_complex_matrix_eq!(&state, &expected);
+        }
+    }
+
+    #[test]
+    fn test_adjoint()
+    {
+        use crate::gates::*;
+
+        let gates: Vec<Box<dyn crate::export::CircuitGate>> = vec![
+            Box::new(H::new()), Box::new(I::new()), Box::new(X::new()), Box::new(Y::new()),
+            Box::new(Z::new()), Box::new(S::new()), Box::new(Sdg::new()), Box::new(T::new()),
+            Box::new(Tdg::new()), Box::new(V::new()), Box::new(Vdg::new()),
+            Box::new(RX::new(0.3)), Box::new(RY::new(-1.2)), Box::new(RZ::new(2.1)),
+            Box::new(U1::new(0.7)), Box::new(U2::new(0.4, -1.3)), Box::new(U3::new(1.1, 0.2, -0.8)),
+            Box::new(Phase::new(0.9)), Box::new(CX::new()), Box::new(CY::new()), Box::new(CZ::new()),
+            Box::new(Swap::new()), Box::new(ISWAP::new()), Box::new(ISWAPdg::new()),
+            Box::new(CH::new()), Box::new(CRX::new(0.2)), Box::new(CRY::new(0.6)),
+            Box::new(CRZ::new(-0.4)), Box::new(CS::new()), Box::new(CSdg::new()),
+            Box::new(CT::new()), Box::new(CTdg::new()), Box::new(CU1::new(1.4)),
+            Box::new(CU2::new(0.4, -1.3)), Box::new(CU3::new(1.1, 0.2, -0.8)),
+            Box::new(CV::new()), Box::new(CVdg::new()), Box::new(CCRX::new(0.5)),
+            Box::new(CCRY::new(-0.9)), Box::new(CCRZ::new(1.7)), Box::new(CCX::new()),
+            Box::new(CCZ::new()), Box::new(CSwap::new()), Box::new(C::new(U2::new(0.4, -1.3))),
+            Box::new(Kron::new(S::new(), RY::new(0.8))),
+            Box::new(Composite::from_string("G", "H 0; CRY(0.3) 0 1; T 1").unwrap())
+        ];
+        for gate in gates.iter()
+        {
+            let adjoint = gate.adjoint();
+            assert_eq!(adjoint.nr_affected_bits(), gate.nr_affected_bits());
+            assert_complex_matrix_eq!(&adjoint.matrix(), &gate.matrix().t().mapv(|x| x.conj()));
+        }
+
+        assert_eq!(S::new().adjoint().description(), "S†");
+        assert_eq!(RX::new(0.5).adjoint().description(), "RX(-0.5000)");
+        assert_eq!(H::new().adjoint().description(), "H");
+        assert_eq!(CRY::new(0.5).adjoint().description(), "CRY(-0.5000)");
+        assert_eq!(CS::new().adjoint().description(), "CS†");
+        assert_eq!(CCX::new().adjoint().description(), "CCX");
+        assert_eq!(C::new(T::new()).adjoint().description(), "CT†");
+        assert_eq!(Kron::new(S::new(), H::new()).adjoint().description(), "S†⊗H");
+        assert_eq!(Composite::from_string("G", "H 0").unwrap().adjoint().description(), "Gdg");
+        let gate: Box<dyn crate::export::CircuitGate> = Box::new(CRY::new(0.5));
+        assert_eq!(Gate::adjoint(&gate).description(), "CRY(-0.5000)");
+        assert_eq!(C::new(RX::new(0.5)).adjoint().adjoint().description(), "CRX(0.5000)");
+
+        // Reference parameters are evaluated at their current value
+        let theta = ::std::rc::Rc::new(::std::cell::RefCell::new(0.5));
+        let gate = RX::new(Parameter::from_refcell(&theta, "theta"));
+        let adjoint = gate.adjoint();
+        assert_eq!(adjoint.description(), "RX(theta)†");
+        assert_complex_matrix_eq!(&adjoint.matrix(), &RX::new(-0.5).matrix());
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+
+/// Gate with a cached matrix
+///
+/// Struct `CachedGate` wraps another gate, and computes its matrix only once,
+/// at construction. Requesting the matrix of the gate then returns this
+/// matrix, instead of recomputing it (e.g. from trigonometric functions of the
+/// gate parameters) on every call. Applying the gate to a state is forwarded
+/// to the wrapped gate, so that gates with a specialized implementation (like
+/// permutations or diagonal gates) keep using it. In all other respects, the
+/// cached gate behaves like the gate it wraps.
+#[derive(Clone)]
+pub struct CachedGate<G>
+{
+    /// The gate itself
+    gate: G,
+    /// The precomputed matrix for `gate`
+    matrix: crate::cmatrix::CMatrix
+}
+
+impl<G> CachedGate<G>
+where G: Gate
+{
+    /// Create a new cached gate for `gate`.
+    pub fn new(gate: G) -> Self
+    {
+        let matrix = gate.matrix();
+        CachedGate { gate: gate, matrix: matrix }
+    }
+
+    /// The gate wrapped by this cached gate
+    pub fn gate(&self) -> &G
+    {
+        &self.gate
+    }
+}
+
+impl<G> crate::gates::Gate for CachedGate<G>
+where G: Gate
+{
+    fn cost(&self) -> f64
+    {
+        self.gate.cost()
+    }
+
+    fn description(&self) -> &str
+    {
+        self.gate.description()
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        self.gate.nr_affected_bits()
+    }
+
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        self.gate.parameters()
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.gate.bind(store)?;
+        self.matrix = self.gate.matrix();
+        Ok(())
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        self.matrix.clone()
+    }
+
+    fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
+    {
+        self.gate.apply_slice(state);
+    }
+
+    fn apply_mat_slice(&self, state: crate::cmatrix::CMatSliceMut)
+    {
+        self.gate.apply_mat_slice(state);
+    }
+
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        self.gate.sparsity()
+    }
+
+    fn is_stabilizer(&self) -> bool
+    {
+        self.gate.is_stabilizer()
+    }
+
+    fn conjugate(&self, ops: &mut [crate::stabilizer::PauliOp]) -> crate::error::Result<bool>
+    {
+        self.gate.conjugate(ops)
+    }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        self.gate.adjoint()
+    }
+
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>>
+    {
+        self.gate.structure()
+    }
+}
+
+impl<G> crate::export::OpenQasm for CachedGate<G>
+where G: Gate + crate::export::OpenQasm
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.gate.open_qasm(bit_names, bits)
+    }
+
+    fn open_qasm3(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.gate.open_qasm3(bit_names, bits)
+    }
+
+    fn conditional_open_qasm(&self, condition: &str, bit_names: &[String],
+        bits: &[usize]) -> crate::error::Result<String>
+    {
+        self.gate.conditional_open_qasm(condition, bit_names, bits)
+    }
+
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        self.gate.open_qasm_definition()
+    }
+}
+
+impl<G> crate::export::CQasm for CachedGate<G>
+where G: Gate + crate::export::CQasm
+{
+    fn c_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.gate.c_qasm(bit_names, bits)
+    }
+
+    fn conditional_c_qasm(&self, condition: &str, bit_names: &[String],
+        bits: &[usize]) -> crate::error::Result<String>
+    {
+        self.gate.conditional_c_qasm(condition, bit_names, bits)
+    }
+}
+
+impl<G> crate::export::TketJson for CachedGate<G>
+where G: Gate + crate::export::TketJson
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        self.gate.tket_ops(bits)
+    }
+}
+
+impl<G> crate::instruction::ToInstruction for CachedGate<G>
+where G: Gate + crate::instruction::ToInstruction
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        self.gate.instructions(bits)
+    }
+}
+
+impl<G> crate::export::Latex for CachedGate<G>
+where G: Gate + crate::export::Latex
+{
+    fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
+        -> crate::error::Result<()>
+    {
+        self.gate.latex(bits, state)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::CachedGate;
+    use crate::export::{OpenQasm, CQasm};
+    use crate::gates::{gate_test, sparse_test, Gate, CX, H, RZ};
+    use crate::stabilizer::PauliOp;
+
+    #[test]
+    fn test_description()
+    {
+        let gate = CachedGate::new(RZ::new(0.5));
+        assert_eq!(gate.description(), "RZ(0.5000)");
+        assert_eq!(gate.nr_affected_bits(), 1);
+        assert_eq!(gate.cost(), RZ::new(0.5).cost());
+        assert_eq!(gate.gate().description(), "RZ(0.5000)");
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let gate = RZ::new(1.25);
+        let cached = gate.clone().cached();
+        assert_complex_matrix_eq!(cached.matrix(), &gate.matrix());
+
+        let gate = CX::new();
+        let cached = CachedGate::new(gate.clone());
+        assert_complex_matrix_eq!(cached.matrix(), &gate.matrix());
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let h = 0.5 * o;
+
+        let mut state = array![[o, z, x, h], [z, o, x, -h], [z, z, z, h], [z, z, z, -h]];
+        let result = array![[o, z, x, h], [z, o, x, -h], [z, z, z, -h], [z, z, z, h]];
+        gate_test(CX::new().cached(), &mut state, &result);
+
+        let mut state = array![[o, z, x], [z, o, x]];
+        let result = array![[x, x, o], [x, -x, z]];
+        gate_test(H::new().cached(), &mut state, &result);
+    }
+
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(RZ::new(0.7).cached());
+
+        let gate = H::new().cached();
+        assert_eq!(gate.sparsity(), None);
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let mut state = array![x, x];
+        gate.apply_sparse(&mut state);
+        assert_complex_vector_eq!(&state, &array![crate::cmatrix::COMPLEX_ONE, crate::cmatrix::COMPLEX_ZERO]);
+    }
+
+    #[test]
+    fn test_stabilizer()
+    {
+        let gate = H::new().cached();
+        assert!(gate.is_stabilizer());
+        let mut ops = [PauliOp::X];
+        assert_eq!(gate.conjugate(&mut ops), Ok(false));
+        assert_eq!(ops, [PauliOp::Z]);
+
+        let gate = RZ::new(0.3).cached();
+        assert!(!gate.is_stabilizer());
+    }
+
+    #[test]
+    fn test_qasm()
+    {
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+        let gate = RZ::new(0.5).cached();
+        assert_eq!(gate.open_qasm(&bit_names, &[1]), RZ::new(0.5).open_qasm(&bit_names, &[1]));
+        assert_eq!(gate.c_qasm(&bit_names, &[1]), RZ::new(0.5).c_qasm(&bit_names, &[1]));
+    }
+}
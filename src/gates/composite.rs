@@ -70,6 +70,9 @@ impl SubGate
 ///
 /// Struct Composite provides for user-defined gates that are made out of a
 /// sequence of more primitive gates.
+/// Its matrix is computed on first use, and kept for subsequent calls as long
+/// as the gate is not changed, unless the matrix depends on referenced
+/// parameters.
 #[derive(Clone)]
 pub struct Composite
 {
@@ -78,7 +81,10 @@ pub struct Composite
     // The number of gates on which this gate operates
     nr_bits: usize,
     // The operations making up the gate
-    ops: Vec<SubGate>
+    ops: Vec<SubGate>,
+    // The matrix of the gate, computed on first use when it does not depend
+    // on referenced parameters
+    matrix: ::std::cell::RefCell<Option<crate::cmatrix::CMatrix>>
 }
 
 impl Composite
@@ -94,10 +100,29 @@ impl Composite
         {
             name: name.to_owned(),
             nr_bits: nr_bits,
-            ops: vec![]
+            ops: vec![],
+            matrix: ::std::cell::RefCell::new(None)
         }
     }
 
+    /// Create a composite gate from a circuit.
+    ///
+    /// Create a new composite gate with name `name`, operating on all qubits of
+    /// `circuit`, and consisting of the gates in `circuit`. The circuit should
+    /// be purely unitary; when it contains measurements, resets, conditional
+    /// gates or noise, `Error::NotAGate` is returned. Barriers are ignored.
+    pub fn from_circuit(name: &str, circuit: &crate::circuit::Circuit)
+        -> crate::error::Result<Self>
+    {
+        let mut composite = Self::new(name, circuit.nr_qbits());
+        for (gate, bits) in circuit.gate_ops()?
+        {
+            composite.ops.push(SubGate { gate: gate.clone_box(), bits: bits.to_vec() });
+        }
+
+        Ok(composite)
+    }
+
     /// Parse the subgate name.
     ///
     /// Try to retrieve the name of the subgate from `desc`. On success,
@@ -457,6 +482,16 @@ impl Composite
     where G: CircuitGate
     {
         self.ops.push(SubGate::new(gate, bits));
+        self.matrix.replace(None);
+    }
+
+    /// Return the total number of bits in a state of length `len`, of which
+    /// the most significant `self.nr_bits` bits are affected by this gate.
+    fn nr_state_bits(&self, len: usize) -> usize
+    {
+        assert!(len % (1 << self.nr_bits) == 0,
+            "Number of rows is not a multiple of 2^{}.", self.nr_bits);
+        self.nr_bits + (len >> self.nr_bits).trailing_zeros() as usize
     }
 }
 
@@ -477,26 +512,53 @@ impl crate::gates::Gate for Composite
         self.nr_bits
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        self.ops.iter().flat_map(|op| op.gate.parameters()).collect()
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.matrix.replace(None);
+        self.ops.iter_mut().try_for_each(|op| op.gate.bind(store))
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
+        if let Some(ref matrix) = *self.matrix.borrow()
+        {
+            return matrix.clone();
+        }
+
         let mut res = crate::cmatrix::CMatrix::eye(1 << self.nr_bits);
         self.apply_mat_slice(res.view_mut());
+        // The values of referenced parameters can change outside of this
+        // gate, so the matrix can only be kept if it depends on direct
+        // values alone.
+        if self.parameters().iter().all(|p| matches!(p, crate::gates::Parameter::Direct(_)))
+        {
+            self.matrix.replace(Some(res.clone()));
+        }
         res
     }
 
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
+        // The bits this gate operates on are the most significant bits of
+        // the state, which may hold additional, unaffected bits.
+        let nr_bits = self.nr_state_bits(state.len());
         for op in self.ops.iter()
         {
-            apply_gate_slice(state.view_mut(), &*op.gate, &op.bits, self.nr_bits);
+            apply_gate_slice(state.view_mut(), &*op.gate, &op.bits, nr_bits);
         }
     }
 
     fn apply_mat_slice(&self, mut state: crate::cmatrix::CMatSliceMut)
     {
+        let nr_bits = self.nr_state_bits(state.rows());
         for op in self.ops.iter()
         {
-            apply_gate_mat_slice(state.view_mut(), &*op.gate, &op.bits, self.nr_bits);
+            apply_gate_mat_slice(state.view_mut(), &*op.gate, &op.bits, nr_bits);
         }
     }
 
@@ -520,6 +582,26 @@ impl crate::gates::Gate for Composite
         }
         Ok(flip_sign)
     }
+
+    fn adjoint(&self) -> Box<dyn CircuitGate>
+    {
+        let mut res = Composite::new(&format!("{}dg", self.name), self.nr_bits);
+        for op in self.ops.iter().rev()
+        {
+            res.ops.push(SubGate { gate: op.gate.adjoint(), bits: op.bits.clone() });
+        }
+        Box::new(res)
+    }
+
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>>
+    {
+        Some(crate::gates::GateStructure::Composite
+        {
+            name: &self.name,
+            nr_bits: self.nr_bits,
+            ops: self.ops.iter().map(|op| (op.gate.as_gate(), op.bits.as_slice())).collect()
+        })
+    }
 }
 
 impl crate::export::OpenQasm for Composite
@@ -527,40 +609,74 @@ impl crate::export::OpenQasm for Composite
     fn open_qasm(&self, bit_names: &[String], bits: &[usize])
         -> crate::error::Result<String>
     {
-        let mut res = String::new();
-        if self.ops.len() > 0
+        if self.open_qasm_definition().is_some()
         {
-            let gate_bits: Vec<usize> = self.ops[0].bits.iter().map(|&b| bits[b]).collect();
-            res = self.ops[0].gate.open_qasm(bit_names, &gate_bits)?;
-            for op in self.ops[1..].iter()
+            let names: Vec<&str> = bits.iter().map(|&b| bit_names[b].as_str()).collect();
+            return Ok(format!("{} {}", self.name, names.join(", ")));
+        }
+
+        // Skip gates without representation, like a global phase
+        let mut res = vec![];
+        for op in self.ops.iter()
+        {
+            let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
+            let qasm = op.gate.open_qasm(bit_names, &gate_bits)?;
+            if !qasm.is_empty()
             {
-                let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
-                let qasm = op.gate.open_qasm(bit_names, &gate_bits)?;
-                res += &format!("; {}", qasm);
+                res.push(qasm);
             }
         }
-        Ok(res)
+        Ok(res.join("; "))
     }
 
     fn conditional_open_qasm(&self, condition: &str, bit_names: &[String],
         bits: &[usize]) -> crate::error::Result<String>
     {
-        let mut res = String::new();
-        if self.ops.len() > 0
+        if self.open_qasm_definition().is_some()
         {
-            let gate_bits: Vec<usize> = self.ops[0].bits.iter().map(|&b| bits[b]).collect();
-            res = self.ops[0].gate.conditional_open_qasm(condition, bit_names,
-                &gate_bits)?;
-            for op in self.ops[1..].iter()
+            let uncond_qasm = self.open_qasm(bit_names, bits)?;
+            return Ok(format!("if ({}) {}", condition, uncond_qasm));
+        }
+
+        let mut res = vec![];
+        for op in self.ops.iter()
+        {
+            let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
+            let qasm = op.gate.conditional_open_qasm(condition, bit_names, &gate_bits)?;
+            if !qasm.is_empty()
             {
-                let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
-                let qasm = op.gate.conditional_open_qasm(condition, bit_names,
-                    &gate_bits)?;
-                res += "; ";
-                res += &qasm;
+                res.push(qasm);
             }
         }
-        Ok(res)
+        Ok(res.join("; "))
+    }
+
+    /// Return the definition of this composite gate, when its name is a valid
+    /// OpenQasm identifier and all gates in it can be exported. Otherwise,
+    /// `None` is returned, and the gate is written out in full where it is used.
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        if !crate::export::is_valid_gate_name(&self.name)
+        {
+            return None;
+        }
+
+        let arg_names: Vec<String> = (0..self.nr_bits).map(|i| format!("a{}", i)).collect();
+        let mut defs = vec![];
+        let mut body = vec![];
+        for op in self.ops.iter()
+        {
+            crate::export::add_open_qasm_definitions(&mut defs, op.gate.open_qasm_definition());
+            let qasm = op.gate.open_qasm(&arg_names, &op.bits).ok()?;
+            if !qasm.is_empty()
+            {
+                body.push(qasm.replace('\n', " ") + ";");
+            }
+        }
+        body.push(String::from("}"));
+
+        defs.push(format!("gate {} {} {{ {}", self.name, arg_names.join(", "), body.join(" ")));
+        Some(defs.join("\n"))
     }
 }
 
@@ -607,6 +723,34 @@ impl crate::export::CQasm for Composite
     }
 }
 
+impl crate::export::TketJson for Composite
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let mut res = vec![];
+        for op in self.ops.iter()
+        {
+            let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
+            res.extend(op.gate.tket_ops(&gate_bits)?);
+        }
+        Ok(res)
+    }
+}
+
+impl crate::instruction::ToInstruction for Composite
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        let mut res = vec![];
+        for op in self.ops.iter()
+        {
+            let gate_bits: Vec<usize> = op.bits.iter().map(|&b| bits[b]).collect();
+            res.extend(op.gate.instructions(&gate_bits));
+        }
+        res
+    }
+}
+
 impl crate::export::Latex for Composite
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -716,6 +860,96 @@ mod tests
         ]);
     }
 
+    #[test]
+    fn test_apply_mat_slice_extra_bits()
+    {
+        let gate = Composite::from_string("G", "H 0; CX 0 1; T 1").unwrap();
+
+        // Apply to the two most significant bits of a three-bit state
+        let mut state = crate::cmatrix::CMatrix::eye(8);
+        gate.apply_mat_slice(state.view_mut());
+        let expected = crate::cmatrix::kron_mat(&gate.matrix(), &crate::cmatrix::CMatrix::eye(2));
+        assert_complex_matrix_eq!(&state, &expected);
+
+        let mut state = crate::cmatrix::CVector::zeros(16);
+        state[5] = crate::cmatrix::COMPLEX_ONE;
+        gate.apply_slice(state.view_mut());
+        let expected = crate::cmatrix::kron_mat(&gate.matrix(), &crate::cmatrix::CMatrix::eye(4));
+        assert_complex_vector_eq!(&state, &expected.column(5).to_owned());
+    }
+
+    #[test]
+    fn test_bind()
+    {
+        let mut gate = Composite::new("G", 2);
+        gate.add_gate(crate::gates::RX::new(crate::gates::Parameter::symbol("x")), &[0]);
+        gate.add_gate(CX::new(), &[0, 1]);
+        gate.add_gate(crate::gates::U1::new(crate::gates::Parameter::symbol("y")), &[1]);
+        assert!(gate.parameters().iter().all(|p| !p.is_bound()));
+
+        let mut store = crate::gates::ParameterStore::new();
+        store.set("x", 0.3);
+        store.set("y", -1.1);
+        assert_eq!(gate.bind(&store), Ok(()));
+        assert!(gate.parameters().iter().all(|p| p.is_bound()));
+
+        let mut expected = Composite::new("G", 2);
+        expected.add_gate(crate::gates::RX::new(0.3), &[0]);
+        expected.add_gate(CX::new(), &[0, 1]);
+        expected.add_gate(crate::gates::U1::new(-1.1), &[1]);
+        assert_complex_matrix_eq!(&gate.matrix(), &expected.matrix());
+    }
+
+    #[test]
+    fn test_matrix_cache()
+    {
+        let mut gate = Composite::new("G", 1);
+        gate.add_gate(H::new(), &[0]);
+        assert_complex_matrix_eq!(&gate.matrix(), &H::new().matrix());
+        assert_complex_matrix_eq!(&gate.matrix(), &H::new().matrix());
+
+        // Adding a gate invalidates the cached matrix
+        gate.add_gate(X::new(), &[0]);
+        assert_complex_matrix_eq!(&gate.matrix(), &X::new().matrix().dot(&H::new().matrix()));
+
+        // The matrix follows changes in referenced parameters
+        let cell = ::std::rc::Rc::new(::std::cell::RefCell::new(0.5));
+        let mut gate = Composite::new("G", 1);
+        gate.add_gate(crate::gates::RZ::new(crate::gates::Parameter::from_refcell(&cell, "x")), &[0]);
+        assert_complex_matrix_eq!(&gate.matrix(), &crate::gates::RZ::new(0.5).matrix());
+        *cell.borrow_mut() = -1.3;
+        assert_complex_matrix_eq!(&gate.matrix(), &crate::gates::RZ::new(-1.3).matrix());
+    }
+
+    #[test]
+    fn test_from_circuit()
+    {
+        let mut circuit = crate::circuit::Circuit::new(3, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        let gate = Composite::from_circuit("G", &circuit).unwrap();
+        assert_eq!(gate.description(), "G");
+        assert_eq!(gate.nr_affected_bits(), 3);
+        assert_eq!(gate.ops.len(), 3);
+
+        let mut expected = Composite::new("G", 3);
+        expected.add_gate(H::new(), &[0]);
+        expected.add_gate(CX::new(), &[0, 2]);
+        expected.add_gate(X::new(), &[1]);
+        assert_complex_matrix_eq!(&gate.matrix(), &expected.matrix());
+
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(Composite::from_circuit("G", &circuit).err(),
+            Some(crate::error::Error::NotAGate(String::from("measurement"))));
+
+        let mut circuit = crate::circuit::Circuit::new(2, 1);
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, X::new(), &[1]), Ok(()));
+        assert_eq!(Composite::from_circuit("G", &circuit).err(),
+            Some(crate::error::Error::NotAGate(String::from("conditional X"))));
+    }
+
     #[test]
     fn test_from_string()
     {
@@ -1625,6 +1859,17 @@ mod tests
         gate.add_gate(X::new(), &[1]);
         let qasm = gate.open_qasm(&bit_names, &[0, 1]);
         assert_eq!(qasm, Ok(String::from("cx qb0, qb1; x qb1")));
+        assert_eq!(gate.open_qasm_definition(), None);
+
+        let mut gate = Composite::new("inc2", 2);
+        gate.add_gate(CX::new(), &[0, 1]);
+        gate.add_gate(X::new(), &[1]);
+        let qasm = gate.open_qasm(&bit_names, &[1, 0]);
+        assert_eq!(qasm, Ok(String::from("inc2 qb1, qb0")));
+        assert_eq!(gate.open_qasm_definition(),
+            Some(String::from("gate inc2 a0, a1 { cx a0, a1; x a1; }")));
+        let qasm = gate.conditional_open_qasm("b == 3", &bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("if (b == 3) inc2 qb0, qb1")));
     }
 
     #[test]
@@ -57,6 +57,18 @@ where G: 'static + Clone + crate::gates::Gate
         1 + self.gate.nr_affected_bits()
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        self.gate.parameters()
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.gate.bind(store)?;
+        self.desc = format!("C{}", self.gate.description());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let gm = self.gate.matrix();
@@ -68,6 +80,17 @@ where G: 'static + Clone + crate::gates::Gate
         res
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        // The identity on the first half, and the elements of the gate,
+        // if sparse, in the second
+        let elements = self.gate.sparsity()?;
+        let gsize = 1 << self.gate.nr_affected_bits();
+        let mut res: Vec<_> = (0..gsize).map(|i| (i, i, crate::cmatrix::COMPLEX_ONE)).collect();
+        res.extend(elements.into_iter().map(|(i, j, c)| (gsize + i, gsize + j, c)));
+        Some(res)
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         let n = state.len() / 2;
@@ -79,8 +102,27 @@ where G: 'static + Clone + crate::gates::Gate
         let n = state.rows() / 2;
         self.gate.apply_mat_slice(state.slice_mut(s![n.., ..]));
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(C::new(self.gate.adjoint()))
+    }
+
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>>
+    {
+        Some(crate::gates::GateStructure::Controlled(&self.gate))
+    }
 }
 
+impl<G> crate::export::OpenQasm for C<G>
+where G: 'static + Clone + crate::gates::Gate {}
+impl<G> crate::export::CQasm for C<G>
+where G: 'static + Clone + crate::gates::Gate {}
+impl<G> crate::export::TketJson for C<G>
+where G: 'static + Clone + crate::gates::Gate {}
+impl<G> crate::instruction::ToInstruction for C<G>
+where G: 'static + Clone + crate::gates::Gate {}
+
 impl<G> crate::export::Latex for C<G>
 where G: 'static + Clone + crate::gates::Gate + crate::export::Latex
 {
@@ -228,16 +270,48 @@ macro_rules! declare_controlled_cost
     };
 }
 
+#[macro_export]
+macro_rules! declare_controlled_adjoint
+{
+    ($($arg:ident),* ;) => {};
+    ($($arg:ident),* ; $adjoint:expr) => {
+        fn adjoint(&self) -> Box<dyn $crate::export::CircuitGate>
+        {
+            let params: Vec<&$crate::gates::Parameter> = vec![ $( &self.$arg, )* ];
+            if params.iter().all(|p| matches!(p, $crate::gates::Parameter::Direct(_)))
+            {
+                let params: Vec<f64> = params.iter().map(|p| p.value()).collect();
+                let adjoint: fn(&[f64]) -> Box<dyn $crate::export::CircuitGate> = $adjoint;
+                adjoint(&params)
+            }
+            else
+            {
+                $crate::gates::matrix_adjoint(self)
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! declare_controlled_impl_gate
 {
-    ($name:ident, $gate_type:ty $(, cost=$cost:expr)*) => {
+    ($name:ident, $gate_type:ty $(, cost=$cost:expr)* $(, arg=$arg:ident)* $(, adjoint=$adjoint:expr)*) => {
         impl $crate::gates::Gate for $name
         {
             declare_controlled_cost!($($cost)*);
             fn description(&self) -> &str { self.cgate.description() }
             fn nr_affected_bits(&self) -> usize { self.cgate.nr_affected_bits() }
+            fn parameters(&self) -> Vec<&$crate::gates::Parameter> { self.cgate.parameters() }
+            fn bind(&mut self, store: &$crate::gates::ParameterStore) -> $crate::error::Result<()>
+            {
+                $( self.$arg.bind(store)?; )*
+                self.cgate.bind(store)
+            }
             fn matrix(&self) -> $crate::cmatrix::CMatrix { self.cgate.matrix() }
+            fn sparsity(&self) -> Option<Vec<(usize, usize, $crate::cmatrix::CNumber)>>
+            {
+                self.cgate.sparsity()
+            }
             fn apply_slice(&self, state: $crate::cmatrix::CVecSliceMut)
             {
                 self.cgate.apply_slice(state);
@@ -246,6 +320,7 @@ macro_rules! declare_controlled_impl_gate
             {
                 self.cgate.apply_mat_slice(state);
             }
+            $crate::declare_controlled_adjoint!($($arg),* ; $($adjoint)*);
         }
     };
 }
@@ -345,6 +420,51 @@ macro_rules! declare_controlled_qasm
     };
 }
 
+#[macro_export]
+macro_rules! declare_controlled_tket
+{
+    ($gate_name:ident $(, arg=$arg:ident)* $(, tket=$tket:expr)*) => {
+        impl $crate::export::TketJson for $gate_name
+        {
+            #[allow(unreachable_code, unused_variables)]
+            fn tket_ops(&self, bits: &[usize])
+                -> $crate::error::Result<Vec<$crate::export::TketOp>>
+            {
+                let params: Vec<String> = vec![
+                    $( $crate::export::TketOp::param(&self.$arg), )*
+                ];
+                $(
+                    return Ok(vec![$crate::export::TketOp::new($tket, params, bits)]);
+                )*
+                Err($crate::error::Error::from(
+                    $crate::error::ExportError::NotImplemented("TKET",
+                        String::from($crate::gates::Gate::description(self)))
+                ))
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! declare_controlled_instruction
+{
+    ($gate_name:ident $(, arg=$arg:ident)*) => {
+        impl $crate::instruction::ToInstruction for $gate_name {}
+    };
+    ($gate_name:ident $(, arg=$arg:ident)*, instruction=$instruction:expr) => {
+        impl $crate::instruction::ToInstruction for $gate_name
+        {
+            fn instructions(&self, bits: &[usize]) -> Vec<$crate::instruction::Instruction>
+            {
+                let params: Vec<f64> = vec![ $( self.$arg.value(), )* ];
+                let instruction: fn(&[f64], &[usize]) -> $crate::instruction::Instruction
+                    = $instruction;
+                vec![instruction(&params, bits)]
+            }
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! declare_controlled_latex
 {
@@ -385,15 +505,19 @@ macro_rules! declare_controlled
         declare_controlled_impl_gate!($name, $gate_type);
         declare_controlled_qasm!(OpenQasm, $name, open_qasm);
         declare_controlled_qasm!(CQasm, $name, c_qasm);
+        declare_controlled_tket!($name);
+        declare_controlled_instruction!($name);
         declare_controlled_latex!($name);
         declare_controlled_square!($name, $gate_type);
     };
-    ($(#[$attr:meta])* $name:ident, $gate_type:ty, cost=$cost:expr $(, arg=$arg:ident)* $(, open_qasm=$open_qasm:expr)* $(, c_qasm=$c_qasm:expr)*) => {
+    ($(#[$attr:meta])* $name:ident, $gate_type:ty, cost=$cost:expr $(, arg=$arg:ident)* $(, open_qasm=$open_qasm:expr)* $(, c_qasm=$c_qasm:expr)* $(, tket=$tket:expr)* $(, instruction=$instruction:expr)* $(, adjoint=$adjoint:expr)*) => {
         declare_controlled_type!($(#[$attr])* $name, $gate_type $(, $arg)*);
         declare_controlled_impl!($name, $gate_type, cost=$cost $(, $arg)*);
-        declare_controlled_impl_gate!($name, $gate_type, cost=Self::cost());
+        declare_controlled_impl_gate!($name, $gate_type, cost=Self::cost() $(, arg=$arg)* $(, adjoint=$adjoint)*);
         declare_controlled_qasm!(OpenQasm, $name, open_qasm $(, qasm=$open_qasm)* $(, arg=$arg)*);
         declare_controlled_qasm!(CQasm, $name, c_qasm $(, qasm=$c_qasm)* $(, arg=$arg)*);
+        declare_controlled_tket!($name $(, arg=$arg)* $(, tket=$tket)*);
+        declare_controlled_instruction!($name $(, arg=$arg)* $(, instruction=$instruction)*);
         declare_controlled_latex!($name);
         declare_controlled_square!($name, $gate_type);
     };
@@ -402,7 +526,10 @@ macro_rules! declare_controlled
 declare_controlled!(
     /// Controlled Hadamard gate.
     CH, crate::gates::H,
-    cost=2.0*CX::cost() + 5.0*crate::gates::U1::cost() + 3.0*crate::gates::U2::cost() + crate::gates::U3::cost());
+    cost=2.0*CX::cost() + 5.0*crate::gates::U1::cost() + 3.0*crate::gates::U2::cost() + crate::gates::U3::cost(),
+    tket="CH",
+    instruction=|_, b| crate::instruction::Instruction::CH(b[0], b[1]),
+    adjoint=|_| Box::new(CH::new()));
 
 declare_controlled!(
     /// Controlled `R`<sub>`X`</sub> gate.
@@ -415,53 +542,77 @@ cnot {0}, {1}
 ry {1}, {-0.5 * {theta}}
 cnot {0}, {1}
 ry {1}, {0.5 * {theta}}
-sdag {1}"#);
+sdag {1}"#,
+    tket="CRx",
+    instruction=|p, b| crate::instruction::Instruction::CRX(p[0], b[0], b[1]),
+    adjoint=|p| Box::new(CRX::new(-p[0])));
 declare_controlled!(
     /// Controlled `R`<sub>`Y`</sub> gate.
     CRY, crate::gates::RY,
     cost=2.0*CX::cost() + 2.0*crate::gates::U3::cost(),
     arg=theta,
     open_qasm="cx {0}, {1}; u3(-{theta}/2, 0, 0) {1}; cx {0}, {1}; u3({theta}/2, 0, 0) {1}",
-    c_qasm="cnot {0}, {1}\nry {1}, -{0.5 * {theta}}\ncnot {0}, {1}\nry {1}, {0.5 * {theta}}");
+    c_qasm="cnot {0}, {1}\nry {1}, -{0.5 * {theta}}\ncnot {0}, {1}\nry {1}, {0.5 * {theta}}",
+    tket="CRy",
+    instruction=|p, b| crate::instruction::Instruction::CRY(p[0], b[0], b[1]),
+    adjoint=|p| Box::new(CRY::new(-p[0])));
 declare_controlled!(
     /// Controlled `R`<sub>`Z`</sub> gate.
     CRZ, crate::gates::RZ,
     cost=2.0*CX::cost() + 2.0*crate::gates::U1::cost(),
-    arg=lambda);
+    arg=lambda,
+    tket="CRz",
+    instruction=|p, b| crate::instruction::Instruction::CRZ(p[0], b[0], b[1]),
+    adjoint=|p| Box::new(CRZ::new(-p[0])));
 
 declare_controlled!(
     /// Controlled `S` gate.
     CS, crate::gates::S, cost=2.0*CX::cost() + 3.0*crate::gates::U1::cost(),
     open_qasm="cu1(pi/2) {0}, {1}",
-    c_qasm="crk {0}, {1}, 1");
+    c_qasm="crk {0}, {1}, 1",
+    tket="CS",
+    instruction=|_, b| crate::instruction::Instruction::CS(b[0], b[1]),
+    adjoint=|_| Box::new(CSdg::new()));
 declare_controlled!(
     /// Controlled `S`<sup>`†`</sup> gate.
     CSdg, crate::gates::Sdg, cost=2.0*CX::cost() + 3.0*crate::gates::U1::cost(),
     open_qasm="cu1(-pi/2) {0}, {1}",
-    c_qasm="cr {0}, {1}, -1.570796326794897");
+    c_qasm="cr {0}, {1}, -1.570796326794897",
+    tket="CSdg",
+    instruction=|_, b| crate::instruction::Instruction::CSdg(b[0], b[1]),
+    adjoint=|_| Box::new(CS::new()));
 
 declare_controlled!(
     /// Controlled `T` gate.
     CT, crate::gates::T, cost=2.0*CX::cost() + 3.0*crate::gates::U1::cost(),
     open_qasm="cu1(pi/4) {0}, {1}",
-    c_qasm="crk {0}, {1}, 2");
+    c_qasm="crk {0}, {1}, 2",
+    instruction=|_, b| crate::instruction::Instruction::CT(b[0], b[1]),
+    adjoint=|_| Box::new(CTdg::new()));
 declare_controlled!(
     /// Controlled `T`<sup>`†`</sup> gate.
     CTdg, crate::gates::Tdg, cost=2.0*CX::cost() + 3.0*crate::gates::U1::cost(),
     open_qasm="cu1(-pi/4) {0}, {1}",
-    c_qasm="cr {0}, {1}, -0.7853981633974483");
+    c_qasm="cr {0}, {1}, -0.7853981633974483",
+    instruction=|_, b| crate::instruction::Instruction::CTdg(b[0], b[1]),
+    adjoint=|_| Box::new(CT::new()));
 
 declare_controlled!(
     /// Controlled `U`<sub>`1`</sub> gate.
     CU1, crate::gates::U1,
     cost=2.0*CX::cost() + 3.0*crate::gates::U1::cost(),
     arg=lambda,
-    c_qasm="cr {0}, {1}, {lambda}");
+    c_qasm="cr {0}, {1}, {lambda}",
+    tket="CU1",
+    instruction=|p, b| crate::instruction::Instruction::CU1(p[0], b[0], b[1]),
+    adjoint=|p| Box::new(CU1::new(-p[0])));
 declare_controlled!(
     /// Controlled `U`<sub>`2`</sub> gate.
     CU2, crate::gates::U2,
     cost=2.0*CX::cost() + 2.0*crate::gates::U1::cost() + crate::gates::U2::cost(),
-    arg=phi, arg=lambda);
+    arg=phi, arg=lambda,
+    instruction=|p, b| crate::instruction::Instruction::CU2(p[0], p[1], b[0], b[1]),
+    adjoint=|p| Box::new(CU3::new(-::std::f64::consts::FRAC_PI_2, -p[1], -p[0])));
 declare_controlled!(
     /// Controlled `U`<sub>`3`</sub> gate.
     CU3, crate::gates::U3,
@@ -474,16 +625,25 @@ ry {1}, {-0.5 * {theta}}
 cnot {0}, {1}
 ry {1}, {0.5 * {theta}}
 rz {1}, {phi}
-rz {0}, {0.5 * ({phi} + {lambda})}"#);
+rz {0}, {0.5 * ({phi} + {lambda})}"#,
+    tket="CU3",
+    instruction=|p, b| crate::instruction::Instruction::CU3(p[0], p[1], p[2], b[0], b[1]),
+    adjoint=|p| Box::new(CU3::new(-p[0], -p[2], -p[1])));
 
 declare_controlled!(
     /// Controlled `V` gate.
     CV, crate::gates::V,
-    cost=2.0*CX::cost() + crate::gates::U1::cost() + 2.0*crate::gates::U3::cost());
+    cost=2.0*CX::cost() + crate::gates::U1::cost() + 2.0*crate::gates::U3::cost(),
+    tket="CSX",
+    instruction=|_, b| crate::instruction::Instruction::CV(b[0], b[1]),
+    adjoint=|_| Box::new(CVdg::new()));
 declare_controlled!(
     /// Controlled `V`<sup>`†`</sup> gate.
     CVdg, crate::gates::Vdg,
-    cost=2.0*CX::cost() + crate::gates::U1::cost() + 2.0*crate::gates::U3::cost());
+    cost=2.0*CX::cost() + crate::gates::U1::cost() + 2.0*crate::gates::U3::cost(),
+    tket="CSXdg",
+    instruction=|_, b| crate::instruction::Instruction::CVdg(b[0], b[1]),
+    adjoint=|_| Box::new(CV::new()));
 
 declare_controlled!(
     /// Doubly controlled `R`<sub>`X`</sub> gate.
@@ -506,7 +666,9 @@ cnot {0}, {2}
 ry {2}, {-0.25 * {theta}}
 cnot {0}, {2}
 ry {2}, {0.25 * {theta}}
-sdag {2}"#);
+sdag {2}"#,
+    instruction=|p, b| crate::instruction::Instruction::CCRX(p[0], b[0], b[1], b[2]),
+    adjoint=|p| Box::new(CCRX::new(-p[0])));
 declare_controlled!(
     /// Doubly controlled `R`<sub>`Y`</sub> gate.
     CCRY, crate::gates::CRY,
@@ -526,7 +688,9 @@ cnot {0}, {1}
 cnot {0}, {2}
 ry {2}, {-0.25 * {theta}}
 cnot {0}, {2}
-ry {2}, {0.25 * {theta}}"#);
+ry {2}, {0.25 * {theta}}"#,
+    instruction=|p, b| crate::instruction::Instruction::CCRY(p[0], b[0], b[1], b[2]),
+    adjoint=|p| Box::new(CCRY::new(-p[0])));
 declare_controlled!(
     /// Doubly controlled `R`<sub>`Z`</sub> gate.
     CCRZ, crate::gates::CRZ,
@@ -537,27 +701,44 @@ declare_controlled!(
 cnot {0}, {1}
 cr {1}, {2}, {-0.5 * {lambda}}
 cnot {0}, {1}
-cr {0}, {2}, {0.5 * {lambda}}"#);
+cr {0}, {2}, {0.5 * {lambda}}"#,
+    instruction=|p, b| crate::instruction::Instruction::CCRZ(p[0], b[0], b[1], b[2]),
+    adjoint=|p| Box::new(CCRZ::new(-p[0])));
 
 declare_controlled!(
     /// Doubly controlled `X` gate.
     CCX, crate::gates::CX,
     cost=6.0*CX::cost() + 7.0*crate::gates::U1::cost() + 2.0*crate::gates::U2::cost(),
-    c_qasm="toffoli {0}, {1}, {2}");
+    c_qasm="toffoli {0}, {1}, {2}",
+    tket="CCX",
+    instruction=|_, b| crate::instruction::Instruction::CCX(b[0], b[1], b[2]),
+    adjoint=|_| Box::new(CCX::new()));
 declare_controlled!(
     /// Doubly controlled `Z` gate.
     CCZ, crate::gates::CZ,
     cost=CCX::cost() + 2.0*crate::gates::H::cost(),
     open_qasm="h {2}; ccx {0}, {1}, {2}; h {2}",
-    c_qasm="h {2}\ntoffoli {0}, {1}, {2}\nh {2}");
+    c_qasm="h {2}\ntoffoli {0}, {1}, {2}\nh {2}",
+    instruction=|_, b| crate::instruction::Instruction::CCZ(b[0], b[1], b[2]),
+    adjoint=|_| Box::new(CCZ::new()));
+
+declare_controlled!(
+    /// Controlled swap (Fredkin) gate.
+    CSwap, crate::gates::Swap,
+    cost=CCX::cost() + 2.0*CX::cost(),
+    open_qasm="cx {2}, {1}; ccx {0}, {1}, {2}; cx {2}, {1}",
+    c_qasm="cnot {2}, {1}\ntoffoli {0}, {1}, {2}\ncnot {2}, {1}",
+    tket="CSWAP",
+    instruction=|_, b| crate::instruction::Instruction::CSwap(b[0], b[1], b[2]),
+    adjoint=|_| Box::new(CSwap::new()));
 
 #[cfg(test)]
 mod tests
 {
-    use super::{C, CCRX, CCRY, CCRZ, CCX, CCZ, CH, CRX, CRY, CRZ, CS, CTdg,
-        CU1, CU3, CV};
+    use super::{C, CCRX, CCRY, CCRZ, CCX, CCZ, CH, CRX, CRY, CRZ, CS, CSwap,
+        CTdg, CU1, CU3, CV};
     use crate::arithmetic::Square;
-    use crate::gates::{gate_test, Gate, H, RY, X};
+    use crate::gates::{gate_test, sparse_test, Gate, H, RY, X};
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
     use crate::cmatrix;
 
@@ -568,6 +749,8 @@ mod tests
         assert_eq!(gate.description(), "CX");
         let gate = CH::new();
         assert_eq!(gate.description(), "CH");
+        let gate = CSwap::new();
+        assert_eq!(gate.description(), "CSwap");
     }
 
     #[test]
@@ -605,6 +788,18 @@ mod tests
             [z, z, z, z, z, z, -i, z],
             [z, z, z, z, z, z,  z, i]
         ]);
+
+        let gate = CSwap::new();
+        assert_complex_matrix_eq!(gate.matrix(), array![
+            [o, z, z, z, z, z, z, z],
+            [z, o, z, z, z, z, z, z],
+            [z, z, o, z, z, z, z, z],
+            [z, z, z, o, z, z, z, z],
+            [z, z, z, z, o, z, z, z],
+            [z, z, z, z, z, z, o, z],
+            [z, z, z, z, z, o, z, z],
+            [z, z, z, z, z, z, z, o]
+        ]);
     }
 
     #[test]
@@ -674,6 +869,22 @@ mod tests
         gate_test(CCX::new(), &mut state, &result);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(C::new(X::new()));
+        sparse_test(C::new(crate::gates::T::new()));
+        sparse_test(CCX::new());
+        sparse_test(CCZ::new());
+        sparse_test(CU1::new(0.7));
+
+        let gate: Box<dyn crate::export::CircuitGate> = Box::new(CCX::new());
+        sparse_test(gate);
+
+        assert_eq!(C::new(H::new()).sparsity(), None);
+        assert_eq!(CH::new().sparsity(), None);
+    }
+
     #[test]
     fn test_cost()
     {
@@ -683,6 +894,7 @@ mod tests
         assert_eq!(CRZ::cost(), 2016.0);
         assert_eq!(CCX::new().cost(), 6263.0);
         assert_eq!(CCZ::new().cost(), 6471.0);
+        assert_eq!(CSwap::new().cost(), 8265.0);
         assert_eq!(CCRX::new(0.9).cost(), 9235.0);
         assert_eq!(CCRY::new(1.6).cost(), 9214.0);
         assert_eq!(CCRZ::new(2.12).cost(), 8050.0);
@@ -710,6 +922,9 @@ mod tests
         let open_qasm = CCZ::new().open_qasm(&bit_names, &[0, 1, 2]);
         assert_eq!(open_qasm, Ok(String::from("h qb2; ccx qb0, qb1, qb2; h qb2")));
 
+        let open_qasm = CSwap::new().open_qasm(&bit_names, &[2, 0, 1]);
+        assert_eq!(open_qasm, Ok(String::from("cx qb1, qb0; ccx qb2, qb0, qb1; cx qb1, qb0")));
+
         let bit_names = [String::from("qb0"), String::from("qb1")];
         let qasm = CS::new().open_qasm(&bit_names, &[0, 1]);
         assert_eq!(qasm, Ok(String::from("cu1(pi/2) qb0, qb1")));
@@ -781,6 +996,9 @@ cr qb1, qb0, 1.06"#)));
         let c_qasm = CCZ::new().c_qasm(&bit_names, &[0, 1, 2]);
         assert_eq!(c_qasm, Ok(String::from("h qb2\ntoffoli qb0, qb1, qb2\nh qb2")));
 
+        let c_qasm = CSwap::new().c_qasm(&bit_names, &[2, 0, 1]);
+        assert_eq!(c_qasm, Ok(String::from("cnot qb1, qb0\ntoffoli qb2, qb0, qb1\ncnot qb1, qb0")));
+
         let bit_names = [String::from("qb0"), String::from("qb1")];
         let c_qasm = CRX::new(0.9).c_qasm(&bit_names, &[0, 1]);
         assert_eq!(c_qasm, Ok(String::from(
@@ -0,0 +1,191 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+
+/// Controlled phase rotation of order `k`.
+///
+/// The `CR`<sub>`k`</sub> gate is the controlled version of the phase
+/// rotation `R`<sub>`k`</sub>` = diag(1, exp(2πi/2`<sup>`k`</sup>`))`, as used
+/// in the quantum Fourier transform. It is equivalent to the
+/// `CU`<sub>`1`</sub>`(2π/2`<sup>`k`</sup>`)` gate, with the first qubit as
+/// control, and the second as target.
+#[derive(Clone)]
+pub struct ControlledPhase
+{
+    k: u32,
+    cgate: crate::gates::CU1,
+    desc: String
+}
+
+impl ControlledPhase
+{
+    /// Create a new controlled phase rotation of order `k`.
+    pub fn new(k: u32) -> Self
+    {
+        let lambda = 2.0 * ::std::f64::consts::PI / 2.0f64.powi(k as i32);
+        ControlledPhase
+        {
+            k: k,
+            cgate: crate::gates::CU1::new(lambda),
+            desc: format!("CR_{}", k)
+        }
+    }
+
+    /// The order `k` of this rotation
+    pub fn order(&self) -> u32
+    {
+        self.k
+    }
+
+    pub fn cost() -> f64
+    {
+        crate::gates::CU1::cost()
+    }
+}
+
+impl crate::gates::Gate for ControlledPhase
+{
+    fn cost(&self) -> f64 { Self::cost() }
+    fn description(&self) -> &str { &self.desc }
+    fn nr_affected_bits(&self) -> usize { 2 }
+    fn matrix(&self) -> crate::cmatrix::CMatrix { self.cgate.matrix() }
+    fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
+    {
+        self.cgate.apply_slice(state);
+    }
+    fn apply_mat_slice(&self, state: crate::cmatrix::CMatSliceMut)
+    {
+        self.cgate.apply_mat_slice(state);
+    }
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        self.cgate.adjoint()
+    }
+}
+
+impl crate::export::OpenQasm for ControlledPhase
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.cgate.open_qasm(bit_names, bits)
+    }
+}
+
+impl crate::export::CQasm for ControlledPhase
+{
+    fn c_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.check_nr_bits(bits.len())?;
+        Ok(format!("crk {}, {}, {}", bit_names[bits[0]], bit_names[bits[1]], self.k))
+    }
+}
+
+impl crate::export::TketJson for ControlledPhase
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        self.cgate.tket_ops(bits)
+    }
+}
+
+impl crate::instruction::ToInstruction for ControlledPhase
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        self.cgate.instructions(bits)
+    }
+}
+
+impl crate::export::Latex for ControlledPhase
+{
+    fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
+        -> crate::error::Result<()>
+    {
+        self.cgate.latex(bits, state)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::ControlledPhase;
+    use crate::export::{CQasm, OpenQasm};
+    use crate::gates::{gate_test, Gate, CU1};
+    use crate::instruction::ToInstruction;
+
+    #[test]
+    fn test_description()
+    {
+        assert_eq!(ControlledPhase::new(3).description(), "CR_3");
+        assert_eq!(ControlledPhase::new(3).order(), 3);
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        assert_complex_matrix_eq!(ControlledPhase::new(1).matrix(), crate::gates::CZ::new().matrix());
+        assert_complex_matrix_eq!(ControlledPhase::new(2).matrix(), array![
+            [o, z, z, z],
+            [z, o, z, z],
+            [z, z, o, z],
+            [z, z, z, i]
+        ], 1.0e-15);
+        for k in 0..6
+        {
+            let lambda = 2.0 * ::std::f64::consts::PI / (1 << k) as f64;
+            assert_complex_matrix_eq!(ControlledPhase::new(k).matrix(), CU1::new(lambda).matrix());
+        }
+    }
+
+    #[test]
+    fn test_adjoint()
+    {
+        let gate = ControlledPhase::new(3);
+        let adjoint = gate.adjoint();
+        assert_eq!(adjoint.description(), CU1::new(-::std::f64::consts::FRAC_PI_4).description());
+        assert_complex_matrix_eq!(adjoint.matrix(), crate::linalg::adjoint(&gate.matrix()));
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let mut state = array![[o, z, 0.5*o], [z, z, 0.5*o], [z, o, 0.5*o], [z, z, 0.5*o]];
+        let result = array![[o, z, 0.5*o], [z, z, 0.5*o], [z, o, 0.5*o], [z, z, 0.5*x*(o+i)]];
+        gate_test(ControlledPhase::new(3), &mut state, &result);
+    }
+
+    #[test]
+    fn test_export()
+    {
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+        let gate = ControlledPhase::new(2);
+        assert_eq!(gate.open_qasm(&bit_names, &[1, 0]),
+            CU1::new(::std::f64::consts::FRAC_PI_2).open_qasm(&bit_names, &[1, 0]));
+        assert_eq!(gate.c_qasm(&bit_names, &[1, 0]), Ok(String::from("crk qb1, qb0, 2")));
+        assert_eq!(format!("{:?}", gate.instructions(&[0, 1])),
+            format!("{:?}", CU1::new(::std::f64::consts::FRAC_PI_2).instructions(&[0, 1])));
+    }
+}
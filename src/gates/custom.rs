@@ -0,0 +1,319 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Counter for generating unique numbers for custom gates
+static NEXT_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+
+/// Gate with a user-supplied matrix
+///
+/// Struct `Custom` describes a gate by its unitary matrix directly, rather than
+/// by a sequence of known gates. This is useful e.g. for randomly generated
+/// unitaries or for testing. Since the gate has no known decomposition, only
+/// single-qubit custom gates can be exported to OpenQasm, where they are
+/// written as a gate definition `custom_n` in terms of a `U`<sub>`3`</sub>
+/// gate. Custom gates cannot be exported to c-Qasm.
+#[derive(Clone)]
+pub struct Custom
+{
+    /// Unique number of this gate, used to name its OpenQasm definition
+    id: usize,
+    /// The number of qubits this gate operates on
+    nr_bits: usize,
+    /// Description of this gate
+    desc: String,
+    /// The unitary matrix of this gate
+    matrix: crate::cmatrix::CMatrix
+}
+
+impl Custom
+{
+    /// Create a new custom gate.
+    ///
+    /// Create a new custom gate with description `desc`, acting on the
+    /// state with matrix `matrix`. The matrix should be a square matrix of
+    /// size `2`<sup>`n`</sup>, where `n` is the number of qubits the gate
+    /// operates on; this function panics if it is not. The matrix is not
+    /// checked for unitarity.
+    pub fn new(desc: &str, matrix: crate::cmatrix::CMatrix) -> Self
+    {
+        let size = matrix.rows();
+        assert!(matrix.cols() == size && size.is_power_of_two(),
+            "The matrix of a custom gate should be square, with a power of two as size");
+
+        Custom
+        {
+            id: NEXT_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed),
+            nr_bits: size.trailing_zeros() as usize,
+            desc: String::from(desc),
+            matrix: matrix
+        }
+    }
+
+    /// Create a new custom gate, checking its matrix.
+    ///
+    /// Create a new custom gate with description `desc`, operating on
+    /// `nr_bits` qubits, with matrix `matrix`. If `matrix` is not a square
+    /// matrix of size `2`<sup>`nr_bits`</sup>, a `DimensionMismatch` error is
+    /// returned. If the matrix is not unitary to within a tolerance of
+    /// 10<sup>-10</sup>, a `MatrixNotUnitary` error is returned.
+    pub fn try_new(desc: &str, nr_bits: usize, matrix: crate::cmatrix::CMatrix)
+        -> crate::error::Result<Self>
+    {
+        let size = 1 << nr_bits;
+        if matrix.rows() != size || matrix.cols() != size
+        {
+            let actual = if matrix.rows() != size { matrix.rows() } else { matrix.cols() };
+            return Err(crate::error::Error::DimensionMismatch(actual, size));
+        }
+
+        let gate = Custom::new(desc, matrix);
+        if !gate.is_unitary(1.0e-10)
+        {
+            return Err(crate::error::Error::MatrixNotUnitary);
+        }
+
+        Ok(gate)
+    }
+
+    /// Check whether the matrix of this gate is unitary, i.e. whether the
+    /// spectral norm of `M`<sup>`†`</sup>`M - I` is less than `tol`.
+    pub fn is_unitary(&self, tol: f64) -> bool
+    {
+        crate::cmatrix::is_unitary(&self.matrix, tol)
+    }
+
+    /// Hermitian conjugate of this gate
+    ///
+    /// Return a custom gate with the conjugate transpose of the matrix of
+    /// this gate. The description of the new gate is the description of this
+    /// gate, followed by a dagger.
+    pub fn dagger(&self) -> Self
+    {
+        Custom::new(&format!("{}†", self.desc), crate::linalg::adjoint(&self.matrix))
+    }
+
+    /// The name of this gate in OpenQasm gate definitions
+    fn open_qasm_name(&self) -> String
+    {
+        format!("custom_{}", self.id)
+    }
+
+    /// Compute the angles `(θ, ϕ, λ)` such that the matrix of this gate equals
+    /// `U`<sub>`3`</sub>`(θ, ϕ, λ)` up to a global phase. Only valid for
+    /// single-qubit gates.
+    fn u3_angles(&self) -> (f64, f64, f64)
+    {
+        u3_angles(&self.matrix)
+    }
+}
+
+/// Compute the angles `(θ, ϕ, λ)` such that the 2×2 unitary matrix `m` equals
+/// `U`<sub>`3`</sub>`(θ, ϕ, λ)` up to a global phase.
+pub(crate) fn u3_angles(m: &crate::cmatrix::CMatrix) -> (f64, f64, f64)
+{
+    let theta = 2.0 * m[[1, 0]].norm().atan2(m[[0, 0]].norm());
+    let alpha = if m[[0, 0]].norm() > 1.0e-12 { m[[0, 0]].arg() } else { m[[1, 0]].arg() };
+    if m[[1, 0]].norm() > 1.0e-12
+    {
+        (theta, m[[1, 0]].arg() - alpha, (-m[[0, 1]]).arg() - alpha)
+    }
+    else
+    {
+        (theta, 0.0, m[[1, 1]].arg() - alpha)
+    }
+}
+
+impl crate::gates::Gate for Custom
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        self.matrix.clone()
+    }
+}
+
+impl crate::export::OpenQasm for Custom
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        if self.nr_bits == 1
+        {
+            Ok(format!("{} {}", self.open_qasm_name(), bit_names[bits[0]]))
+        }
+        else
+        {
+            Err(crate::error::Error::from(
+                crate::error::ExportError::NotImplemented("OpenQasm", self.desc.clone())
+            ))
+        }
+    }
+
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        if self.nr_bits == 1
+        {
+            let (theta, phi, lambda) = self.u3_angles();
+            let body = crate::gates::U3::new(theta, phi, lambda)
+                .open_qasm(&[String::from("a0")], &[0]).ok()?;
+            Some(format!("gate {} a0 {{ {}; }}", self.open_qasm_name(), body))
+        }
+        else
+        {
+            None
+        }
+    }
+}
+impl crate::export::CQasm for Custom {}
+impl crate::export::Latex for Custom {}
+impl crate::export::TketJson for Custom {}
+impl crate::instruction::ToInstruction for Custom {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Custom;
+    use crate::export::{OpenQasm, CQasm};
+    use crate::gates::{gate_test, Gate};
+
+    #[test]
+    fn test_description()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let gate = Custom::new("U", array![[z, o], [o, z]]);
+        assert_eq!(gate.description(), "U");
+        assert_eq!(gate.nr_affected_bits(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_invalid()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        Custom::new("U", array![[o, o, o], [o, o, o], [o, o, o]]);
+    }
+
+    #[test]
+    fn test_try_new()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let gate = Custom::try_new("H", 1, array![[x, x], [x, -x]]).unwrap();
+        assert_eq!(gate.description(), "H");
+        assert!(gate.is_unitary(1.0e-10));
+
+        assert!(matches!(Custom::try_new("U", 1, array![[o, o], [z, o]]),
+            Err(crate::error::Error::MatrixNotUnitary)));
+        assert!(matches!(Custom::try_new("U", 2, array![[o, z], [z, o]]),
+            Err(crate::error::Error::DimensionMismatch(2, 4))));
+        assert!(matches!(Custom::try_new("U", 1, array![[o, z, z], [z, o, z]]),
+            Err(crate::error::Error::DimensionMismatch(3, 2))));
+
+        assert!(!Custom::new("U", array![[o, o], [z, o]]).is_unitary(1.0e-10));
+    }
+
+    #[test]
+    fn test_dagger()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let gate = Custom::new("U", array![[x, i*x], [x, -i*x]]);
+        let dagger = gate.dagger();
+        assert_eq!(dagger.description(), "U†");
+        assert_eq!(dagger.nr_affected_bits(), 1);
+        assert_complex_matrix_eq!(dagger.matrix(), array![[x, x], [-i*x, i*x]]);
+        assert_complex_matrix_eq!(dagger.matrix().dot(&gate.matrix()), array![[o, z], [z, o]]);
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let m = array![[o, z, z, z], [z, z, i, z], [z, i, z, z], [z, z, z, o]];
+        let gate = Custom::new("iSWAP", m.clone());
+        assert_eq!(gate.nr_affected_bits(), 2);
+        assert_complex_matrix_eq!(gate.matrix(), &m);
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let gate = Custom::new("iSWAP", array![[o, z, z, z], [z, z, i, z], [z, i, z, z], [z, z, z, o]]);
+        let mut state = array![[o, z, x], [z, o, z], [z, z, x], [z, z, z]];
+        let result = array![[o, z, x], [z, z, i*x], [z, i, z], [z, z, z]];
+        gate_test(gate, &mut state, &result);
+    }
+
+    #[test]
+    fn test_qasm()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let gate = Custom::new("U", array![[z, o], [o, z]]);
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+        let name = format!("custom_{}", gate.id);
+        assert_eq!(gate.open_qasm(&bit_names, &[1]), Ok(format!("{} qb1", name)));
+        assert_eq!(gate.open_qasm_definition(),
+            Some(format!("gate {} a0 {{ u3(3.141592653589793, 0, -3.141592653589793) a0; }}", name)));
+        assert!(gate.c_qasm(&bit_names, &[0]).is_err());
+
+        let gate = Custom::new("iSWAP", array![[o, z, z, z], [z, z, i, z], [z, i, z, z], [z, z, z, o]]);
+        assert!(gate.open_qasm(&bit_names, &[0, 1]).is_err());
+        assert_eq!(gate.open_qasm_definition(), None);
+    }
+
+    #[test]
+    fn test_u3_angles()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let phase = crate::cmatrix::CNumber::from_polar(&1.0, &0.7);
+        for m in [array![[x, x], [x, -x]], array![[o, z], [z, i]], array![[z, i], [i, z]],
+            array![[phase, z], [z, phase.conj()]], crate::gates::U3::new(0.3, -1.2, 2.5).matrix()].iter()
+        {
+            let gate = Custom::new("U", m.clone());
+            let (theta, phi, lambda) = gate.u3_angles();
+            let u3 = crate::gates::U3::new(theta, phi, lambda).matrix();
+            // Equal up to a global phase
+            let overlap = crate::linalg::adjoint(&u3).dot(m).diag().sum();
+            assert!((overlap.norm() - 2.0).abs() < 1.0e-12);
+        }
+    }
+}
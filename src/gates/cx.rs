@@ -44,6 +44,10 @@ impl crate::gates::Gate for CX
     fn description(&self) -> &str { self.cgate.description() }
     fn nr_affected_bits(&self) -> usize { self.cgate.nr_affected_bits() }
     fn matrix(&self) -> crate::cmatrix::CMatrix { self.cgate.matrix() }
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        self.cgate.sparsity()
+    }
     fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
     {
         self.cgate.apply_slice(state);
@@ -82,6 +86,11 @@ impl crate::gates::Gate for CX
         ops[1] = op1;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for CX
@@ -104,6 +113,22 @@ impl crate::export::CQasm for CX
     }
 }
 
+impl crate::export::TketJson for CX
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("CX", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for CX
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::CX(bits[0], bits[1])]
+    }
+}
+
 impl crate::export::Latex for CX
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -129,7 +154,7 @@ mod tests
     use super::CX;
     use crate::cmatrix;
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -155,6 +180,12 @@ mod tests
         ]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(CX::new());
+    }
+
     #[test]
     fn test_apply()
     {
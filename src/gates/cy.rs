@@ -82,6 +82,11 @@ impl crate::gates::Gate for CY
         ops[1] = op1;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for CY
@@ -106,6 +111,22 @@ impl crate::export::CQasm for CY
     }
 }
 
+impl crate::export::TketJson for CY
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("CY", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for CY
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::CY(bits[0], bits[1])]
+    }
+}
+
 impl crate::export::Latex for CY
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -44,6 +44,11 @@ impl crate::gates::Gate for CZ
     fn description(&self) -> &str { self.cgate.description() }
     fn nr_affected_bits(&self) -> usize { self.cgate.nr_affected_bits() }
     fn matrix(&self) -> crate::cmatrix::CMatrix { self.cgate.matrix() }
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        Some(crate::gates::diagonal_sparsity(&[o, o, o, -o]))
+    }
     fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
     {
         self.cgate.apply_slice(state);
@@ -82,6 +87,11 @@ impl crate::gates::Gate for CZ
         ops[1] = op1;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for CZ
@@ -104,6 +114,22 @@ impl crate::export::CQasm for CZ
     }
 }
 
+impl crate::export::TketJson for CZ
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("CZ", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for CZ
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::CZ(bits[0], bits[1])]
+    }
+}
+
 impl crate::export::Latex for CZ
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -129,7 +155,7 @@ mod tests
     use super::CZ;
     use crate::cmatrix;
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -155,6 +181,12 @@ mod tests
         ]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(CZ::new());
+    }
+
     #[test]
     fn test_apply()
     {
@@ -120,6 +120,11 @@ impl crate::gates::Gate for H
         ops[0] = op;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for H
@@ -140,6 +145,22 @@ impl crate::export::CQasm for H
     }
 }
 
+impl crate::export::TketJson for H
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("H", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for H
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::H(bits[0])]
+    }
+}
+
 impl crate::export::Latex for H
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -54,6 +54,12 @@ impl crate::gates::Gate for I
         crate::cmatrix::CMatrix::eye(2)
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        Some(crate::gates::diagonal_sparsity(&[o, o]))
+    }
+
     fn apply_slice(&self, _state: crate::cmatrix::CVecSliceMut)
     {
         // Identity, leave state unchanged, so do nothing
@@ -68,6 +74,11 @@ impl crate::gates::Gate for I
     {
         Ok(false)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for I
@@ -88,6 +99,22 @@ impl crate::export::CQasm for I
     }
 }
 
+impl crate::export::TketJson for I
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("noop", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for I
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::I(bits[0])]
+    }
+}
+
 impl crate::export::Latex for I
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -113,7 +140,7 @@ mod tests
 {
     use super::I;
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -147,6 +174,12 @@ mod tests
         assert_complex_matrix_eq!(i.matrix(), array![[o, z], [z, o]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(I::new());
+    }
+
     #[test]
     fn test_apply()
     {
@@ -0,0 +1,591 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+use crate::stabilizer::PauliOp;
+
+/// Swap the states of two qubits, multiplying the amplitudes of the swapped
+/// states by `phase`.
+fn transform(mut state: crate::cmatrix::CVecSliceMut, phase: crate::cmatrix::CNumber)
+{
+    assert!(state.len() % 4 == 0, "Number of rows is not a mutiple of 4.");
+
+    let n = state.len() / 4;
+    for i in n..2*n
+    {
+        state.swap(i, i+n);
+        state[i] *= phase;
+        state[i+n] *= phase;
+    }
+}
+
+/// Swap the states of two qubits, multiplying the amplitudes of the swapped
+/// states by `phase`, for every column in `state`.
+fn transform_mat(mut state: crate::cmatrix::CMatSliceMut, phase: crate::cmatrix::CNumber)
+{
+    assert!(state.len() % 4 == 0, "Number of rows is not a multiple of 4.");
+
+    let n = state.rows() / 4;
+    let m = state.cols();
+    for i in n..2*n
+    {
+        for j in 0..m
+        {
+            state.swap((i, j), (i+n, j));
+            state[[i, j]] *= phase;
+            state[[i+n, j]] *= phase;
+        }
+    }
+}
+
+/// The `iSWAP` gate
+///
+/// The `iSWAP` gate swaps two qubits, and multiplies the amplitudes of the
+/// states `|01⟩` and `|10⟩` by `i`. It is a native gate on many
+/// superconducting devices. The associated matrix is
+/// ```text
+/// ┌         ┐
+/// │ 1 0 0 0 │
+/// │ 0 0 i 0 │
+/// │ 0 i 0 0 │
+/// │ 0 0 0 1 │
+/// └         ┘
+/// ```
+#[derive(Clone)]
+pub struct ISWAP
+{
+}
+
+impl ISWAP
+{
+    /// Create a new `iSWAP` gate.
+    pub fn new() -> Self
+    {
+        ISWAP { }
+    }
+
+    pub fn cost() -> f64
+    {
+        2.0 * crate::gates::CX::cost() + 2.0 * crate::gates::H::cost()
+            + 2.0 * crate::gates::U1::cost()
+    }
+}
+
+impl crate::gates::Gate for ISWAP
+{
+    fn cost(&self) -> f64
+    {
+        Self::cost()
+    }
+
+    fn description(&self) -> &str
+    {
+        "iSWAP"
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        2
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        array![
+            [o, z, z, z],
+            [z, z, i, z],
+            [z, i, z, z],
+            [z, z, z, o]
+        ]
+    }
+
+    fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
+    {
+        transform(state, crate::cmatrix::COMPLEX_I);
+    }
+
+    fn apply_mat_slice(&self, state: crate::cmatrix::CMatSliceMut)
+    {
+        transform_mat(state, crate::cmatrix::COMPLEX_I);
+    }
+
+    fn is_stabilizer(&self) -> bool
+    {
+        true
+    }
+
+    fn conjugate(&self, ops: &mut [PauliOp]) -> crate::error::Result<bool>
+    {
+        self.check_nr_bits(ops.len())?;
+        // Apart from swapping the operators, the phases on the swapped
+        // states turn X and Y on one qubit into Y and X, combined with a Z
+        // on the other qubit. For XY and YX the Z's cancel, and the operators
+        // are only swapped.
+        let (phase, op0, op1) = match (ops[0], ops[1])
+        {
+            (PauliOp::I, PauliOp::I) => (false, PauliOp::I, PauliOp::I),
+            (PauliOp::I, PauliOp::Z) => (false, PauliOp::Z, PauliOp::I),
+            (PauliOp::I, PauliOp::X) => (false, PauliOp::Y, PauliOp::Z),
+            (PauliOp::I, PauliOp::Y) => (true,  PauliOp::X, PauliOp::Z),
+            (PauliOp::Z, PauliOp::I) => (false, PauliOp::I, PauliOp::Z),
+            (PauliOp::Z, PauliOp::Z) => (false, PauliOp::Z, PauliOp::Z),
+            (PauliOp::Z, PauliOp::X) => (false, PauliOp::Y, PauliOp::I),
+            (PauliOp::Z, PauliOp::Y) => (true,  PauliOp::X, PauliOp::I),
+            (PauliOp::X, PauliOp::I) => (false, PauliOp::Z, PauliOp::Y),
+            (PauliOp::X, PauliOp::Z) => (false, PauliOp::I, PauliOp::Y),
+            (PauliOp::X, PauliOp::X) => (false, PauliOp::X, PauliOp::X),
+            (PauliOp::X, PauliOp::Y) => (false, PauliOp::Y, PauliOp::X),
+            (PauliOp::Y, PauliOp::I) => (true,  PauliOp::Z, PauliOp::X),
+            (PauliOp::Y, PauliOp::Z) => (true,  PauliOp::I, PauliOp::X),
+            (PauliOp::Y, PauliOp::X) => (false, PauliOp::X, PauliOp::Y),
+            (PauliOp::Y, PauliOp::Y) => (false, PauliOp::Y, PauliOp::Y),
+        };
+        ops[0] = op0;
+        ops[1] = op1;
+        Ok(phase)
+    }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(ISWAPdg::new())
+    }
+}
+
+impl crate::export::OpenQasm for ISWAP
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.check_nr_bits(bits.len())?;
+        let b0 = &bit_names[bits[0]];
+        let b1 = &bit_names[bits[1]];
+        Ok(format!("s {}; s {}; h {}; cx {}, {}; cx {}, {}; h {}",
+            b0, b1, b0, b0, b1, b1, b0, b1))
+    }
+}
+
+impl crate::export::CQasm for ISWAP
+{
+    fn c_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.check_nr_bits(bits.len())?;
+        let b0 = &bit_names[bits[0]];
+        let b1 = &bit_names[bits[1]];
+        Ok(format!("s {}\ns {}\nh {}\ncnot {}, {}\ncnot {}, {}\nh {}",
+            b0, b1, b0, b0, b1, b1, b0, b1))
+    }
+}
+
+impl crate::export::TketJson for ISWAP
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        self.check_nr_bits(bits.len())?;
+        Ok(vec![crate::export::TketOp::new("ISWAPMax", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for ISWAP
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        use crate::instruction::Instruction;
+        vec![
+            Instruction::S(bits[0]),
+            Instruction::S(bits[1]),
+            Instruction::H(bits[0]),
+            Instruction::CX(bits[0], bits[1]),
+            Instruction::CX(bits[1], bits[0]),
+            Instruction::H(bits[1])
+        ]
+    }
+}
+
+impl crate::export::Latex for ISWAP
+{
+    fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
+        -> crate::error::Result<()>
+    {
+        self.check_nr_bits(bits.len())?;
+        state.add_block_gate(bits, "iSWAP")
+    }
+}
+
+/// The `iSWAP`<sup>`†`</sup> gate
+///
+/// The `iSWAP`<sup>`†`</sup> gate is the Hermitian conjugate of the `iSWAP`
+/// gate. It swaps two qubits, and multiplies the amplitudes of the states
+/// `|01⟩` and `|10⟩` by `-i`. The associated matrix is
+/// ```text
+/// ┌           ┐
+/// │ 1  0  0 0 │
+/// │ 0  0 -i 0 │
+/// │ 0 -i  0 0 │
+/// │ 0  0  0 1 │
+/// └           ┘
+/// ```
+#[derive(Clone)]
+pub struct ISWAPdg
+{
+}
+
+impl ISWAPdg
+{
+    /// Create a new `iSWAP`<sup>`†`</sup> gate.
+    pub fn new() -> Self
+    {
+        ISWAPdg { }
+    }
+
+    pub fn cost() -> f64
+    {
+        ISWAP::cost()
+    }
+}
+
+impl crate::gates::Gate for ISWAPdg
+{
+    fn cost(&self) -> f64
+    {
+        Self::cost()
+    }
+
+    fn description(&self) -> &str
+    {
+        "iSWAP†"
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        2
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        array![
+            [o,  z,  z, z],
+            [z,  z, -i, z],
+            [z, -i,  z, z],
+            [z,  z,  z, o]
+        ]
+    }
+
+    fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
+    {
+        transform(state, -crate::cmatrix::COMPLEX_I);
+    }
+
+    fn apply_mat_slice(&self, state: crate::cmatrix::CMatSliceMut)
+    {
+        transform_mat(state, -crate::cmatrix::COMPLEX_I);
+    }
+
+    fn is_stabilizer(&self) -> bool
+    {
+        true
+    }
+
+    fn conjugate(&self, ops: &mut [PauliOp]) -> crate::error::Result<bool>
+    {
+        self.check_nr_bits(ops.len())?;
+        let (phase, op0, op1) = match (ops[0], ops[1])
+        {
+            (PauliOp::I, PauliOp::I) => (false, PauliOp::I, PauliOp::I),
+            (PauliOp::I, PauliOp::Z) => (false, PauliOp::Z, PauliOp::I),
+            (PauliOp::I, PauliOp::X) => (true,  PauliOp::Y, PauliOp::Z),
+            (PauliOp::I, PauliOp::Y) => (false, PauliOp::X, PauliOp::Z),
+            (PauliOp::Z, PauliOp::I) => (false, PauliOp::I, PauliOp::Z),
+            (PauliOp::Z, PauliOp::Z) => (false, PauliOp::Z, PauliOp::Z),
+            (PauliOp::Z, PauliOp::X) => (true,  PauliOp::Y, PauliOp::I),
+            (PauliOp::Z, PauliOp::Y) => (false, PauliOp::X, PauliOp::I),
+            (PauliOp::X, PauliOp::I) => (true,  PauliOp::Z, PauliOp::Y),
+            (PauliOp::X, PauliOp::Z) => (true,  PauliOp::I, PauliOp::Y),
+            (PauliOp::X, PauliOp::X) => (false, PauliOp::X, PauliOp::X),
+            (PauliOp::X, PauliOp::Y) => (false, PauliOp::Y, PauliOp::X),
+            (PauliOp::Y, PauliOp::I) => (false, PauliOp::Z, PauliOp::X),
+            (PauliOp::Y, PauliOp::Z) => (false, PauliOp::I, PauliOp::X),
+            (PauliOp::Y, PauliOp::X) => (false, PauliOp::X, PauliOp::Y),
+            (PauliOp::Y, PauliOp::Y) => (false, PauliOp::Y, PauliOp::Y),
+        };
+        ops[0] = op0;
+        ops[1] = op1;
+        Ok(phase)
+    }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(ISWAP::new())
+    }
+}
+
+impl crate::export::OpenQasm for ISWAPdg
+{
+    fn open_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.check_nr_bits(bits.len())?;
+        let b0 = &bit_names[bits[0]];
+        let b1 = &bit_names[bits[1]];
+        Ok(format!("h {}; cx {}, {}; cx {}, {}; h {}; sdg {}; sdg {}",
+            b1, b1, b0, b0, b1, b0, b1, b0))
+    }
+}
+
+impl crate::export::CQasm for ISWAPdg
+{
+    fn c_qasm(&self, bit_names: &[String], bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        self.check_nr_bits(bits.len())?;
+        let b0 = &bit_names[bits[0]];
+        let b1 = &bit_names[bits[1]];
+        Ok(format!("h {}\ncnot {}, {}\ncnot {}, {}\nh {}\nsdag {}\nsdag {}",
+            b1, b1, b0, b0, b1, b0, b1, b0))
+    }
+}
+
+impl crate::export::TketJson for ISWAPdg
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        self.check_nr_bits(bits.len())?;
+        Ok(vec![crate::export::TketOp::new("ISWAP", vec![String::from("-1")], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for ISWAPdg
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        use crate::instruction::Instruction;
+        vec![
+            Instruction::H(bits[1]),
+            Instruction::CX(bits[1], bits[0]),
+            Instruction::CX(bits[0], bits[1]),
+            Instruction::H(bits[0]),
+            Instruction::Sdg(bits[1]),
+            Instruction::Sdg(bits[0])
+        ]
+    }
+}
+
+impl crate::export::Latex for ISWAPdg
+{
+    fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
+        -> crate::error::Result<()>
+    {
+        self.check_nr_bits(bits.len())?;
+        state.add_block_gate(bits, r"iSWAP^\dagger")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{ISWAP, ISWAPdg};
+    use crate::export::{LatexExportState, Latex, OpenQasm, CQasm};
+    use crate::gates::{gate_test, Gate};
+    use crate::instruction::ToInstruction;
+    use crate::stabilizer::PauliOp;
+
+    /// Compute the matrix of the gates in `instructions`, operating on two
+    /// qubits
+    fn instructions_matrix(instructions: Vec<crate::instruction::Instruction>)
+        -> crate::cmatrix::CMatrix
+    {
+        let circuit = crate::circuit::Circuit::from_instructions(&instructions, 2, 0).unwrap();
+        crate::gates::Composite::from_circuit("U", &circuit).unwrap().matrix()
+    }
+
+    /// The matrix of Pauli operator `op`
+    fn pauli_matrix(op: PauliOp) -> crate::cmatrix::CMatrix
+    {
+        match op
+        {
+            PauliOp::I => crate::gates::I::new().matrix(),
+            PauliOp::Z => crate::gates::Z::new().matrix(),
+            PauliOp::X => crate::gates::X::new().matrix(),
+            PauliOp::Y => crate::gates::Y::new().matrix()
+        }
+    }
+
+    /// Compute `G·P·G`<sup>`†`</sup> for Pauli operator `P = ops[0]⊗ops[1]`
+    fn conjugated_matrix(gate: &dyn Gate, ops: [PauliOp; 2]) -> crate::cmatrix::CMatrix
+    {
+        let m = gate.matrix();
+        let p = crate::cmatrix::kron_mat(&pauli_matrix(ops[0]), &pauli_matrix(ops[1]));
+        m.dot(&p).dot(&m.t().mapv(|x| x.conj()))
+    }
+
+    #[test]
+    fn test_description()
+    {
+        assert_eq!(ISWAP::new().description(), "iSWAP");
+        assert_eq!(ISWAPdg::new().description(), "iSWAP†");
+    }
+
+    #[test]
+    fn test_cost()
+    {
+        assert_eq!(ISWAP::new().cost(), 2.0 * 1001.0 + 2.0 * 104.0 + 2.0 * 7.0);
+        assert_eq!(ISWAPdg::new().cost(), 2.0 * 1001.0 + 2.0 * 104.0 + 2.0 * 7.0);
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        assert_complex_matrix_eq!(ISWAP::new().matrix(), array![
+            [o, z, z, z],
+            [z, z, i, z],
+            [z, i, z, z],
+            [z, z, z, o]
+        ]);
+        assert_complex_matrix_eq!(ISWAPdg::new().matrix(), array![
+            [o,  z,  z, z],
+            [z,  z, -i, z],
+            [z, -i,  z, z],
+            [z,  z,  z, o]
+        ]);
+        assert_complex_matrix_eq!(ISWAP::new().matrix().dot(&ISWAPdg::new().matrix()),
+            crate::cmatrix::CMatrix::eye(4));
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        let h = 0.5 * o;
+        let mut state = array![
+            [o, z, x,  x, x,  h, z],
+            [z, o, x, -x, z, -h, z],
+            [z, z, z,  z, x,  h, o],
+            [z, z, z,  z, z, -h, z]
+        ];
+        let result = array![
+            [o, z,   x,    x,   x,    h, z],
+            [z, z,   z,    z, i*x,  i*h, i],
+            [z, i, i*x, -i*x,   z, -i*h, z],
+            [z, z,   z,    z,   z,   -h, z]
+        ];
+        gate_test(ISWAP::new(), &mut state, &result);
+
+        let mut state = array![
+            [o, z, x,  x, x,  h, z],
+            [z, o, x, -x, z, -h, z],
+            [z, z, z,  z, x,  h, o],
+            [z, z, z,  z, z, -h, z]
+        ];
+        let result = array![
+            [o,  z,    x,   x,    x,    h,  z],
+            [z,  z,    z,   z, -i*x, -i*h, -i],
+            [z, -i, -i*x, i*x,    z,  i*h,  z],
+            [z,  z,    z,   z,    z,   -h,  z]
+        ];
+        gate_test(ISWAPdg::new(), &mut state, &result);
+    }
+
+    #[test]
+    fn test_open_qasm()
+    {
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+        let qasm = ISWAP::new().open_qasm(&bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("s qb0; s qb1; h qb0; cx qb0, qb1; cx qb1, qb0; h qb1")));
+        let qasm = ISWAPdg::new().open_qasm(&bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("h qb1; cx qb1, qb0; cx qb0, qb1; h qb0; sdg qb1; sdg qb0")));
+    }
+
+    #[test]
+    fn test_c_qasm()
+    {
+        let bit_names = [String::from("qb0"), String::from("qb1")];
+        let qasm = ISWAP::new().c_qasm(&bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("s qb0\ns qb1\nh qb0\ncnot qb0, qb1\ncnot qb1, qb0\nh qb1")));
+        let qasm = ISWAPdg::new().c_qasm(&bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("h qb1\ncnot qb1, qb0\ncnot qb0, qb1\nh qb0\nsdag qb1\nsdag qb0")));
+    }
+
+    #[test]
+    fn test_latex()
+    {
+        let gate = ISWAP::new();
+        let mut state = LatexExportState::new(2, 0);
+        assert_eq!(gate.latex(&[0, 1], &mut state), Ok(()));
+        assert_eq!(state.code(),
+r#"\Qcircuit @C=1em @R=.7em {
+    \lstick{\ket{0}} & \multigate{1}{iSWAP} & \qw \\
+    \lstick{\ket{0}} & \ghost{iSWAP} & \qw \\
+}
+"#);
+
+        let gate = ISWAPdg::new();
+        let mut state = LatexExportState::new(2, 0);
+        assert_eq!(gate.latex(&[0, 1], &mut state), Ok(()));
+        assert_eq!(state.code(),
+r#"\Qcircuit @C=1em @R=.7em {
+    \lstick{\ket{0}} & \multigate{1}{iSWAP^\dagger} & \qw \\
+    \lstick{\ket{0}} & \ghost{iSWAP^\dagger} & \qw \\
+}
+"#);
+    }
+
+    #[test]
+    fn test_instructions()
+    {
+        let gate = ISWAP::new();
+        assert_complex_matrix_eq!(&instructions_matrix(gate.instructions(&[0, 1])), &gate.matrix());
+        let gate = ISWAPdg::new();
+        assert_complex_matrix_eq!(&instructions_matrix(gate.instructions(&[0, 1])), &gate.matrix());
+    }
+
+    #[test]
+    fn test_conjugate()
+    {
+        let paulis = [PauliOp::I, PauliOp::Z, PauliOp::X, PauliOp::Y];
+        let gates: [&dyn Gate; 2] = [&ISWAP::new(), &ISWAPdg::new()];
+        for gate in gates.iter()
+        {
+            for &op0 in paulis.iter()
+            {
+                for &op1 in paulis.iter()
+                {
+                    let expected = conjugated_matrix(*gate, [op0, op1]);
+                    let mut ops = [op0, op1];
+                    let phase = gate.conjugate(&mut ops).unwrap();
+                    let sign = if phase { -1.0 } else { 1.0 };
+                    let result = crate::cmatrix::kron_mat(&pauli_matrix(ops[0]), &pauli_matrix(ops[1])) * sign;
+                    assert_complex_matrix_eq!(&result, &expected);
+                }
+            }
+        }
+
+        let mut ops = [PauliOp::Y, PauliOp::Y];
+        assert_eq!(ISWAP::new().conjugate(&mut ops), Ok(false));
+        assert_eq!(ops, [PauliOp::Y, PauliOp::Y]);
+        let mut ops = [PauliOp::I, PauliOp::Y];
+        assert_eq!(ISWAP::new().conjugate(&mut ops), Ok(true));
+        assert_eq!(ops, [PauliOp::X, PauliOp::Z]);
+    }
+}
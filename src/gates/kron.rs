@@ -56,6 +56,21 @@ where G0: 'static + crate::gates::Gate + Clone,
         self.g0.nr_affected_bits() + self.g1.nr_affected_bits()
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        let mut res = self.g0.parameters();
+        res.extend(self.g1.parameters());
+        res
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.g0.bind(store)?;
+        self.g1.bind(store)?;
+        self.desc = format!("{}⊗{}", self.g0.description(), self.g1.description());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         crate::cmatrix::kron_mat(&self.g0.matrix(), &self.g1.matrix())
@@ -85,6 +100,16 @@ where G0: 'static + crate::gates::Gate + Clone,
         flip_sign ^= self.g1.conjugate(&mut ops[n0..])?;
         Ok(flip_sign)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(Kron::new(self.g0.adjoint(), self.g1.adjoint()))
+    }
+
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>>
+    {
+        Some(crate::gates::GateStructure::Kron(&self.g0, &self.g1))
+    }
 }
 
 impl<G0, G1> crate::export::OpenQasm for Kron<G0, G1>
@@ -97,7 +122,8 @@ where G0: 'static + crate::export::OpenQasm + Clone,
         let n0 = self.g0.nr_affected_bits();
         let op0 = self.g0.open_qasm(bit_names, &bits[..n0])?;
         let op1 = self.g1.open_qasm(bit_names, &bits[n0..])?;
-        Ok(format!("{}; {}", op0, op1))
+        let ops: Vec<String> = vec![op0, op1].into_iter().filter(|op| !op.is_empty()).collect();
+        Ok(ops.join("; "))
     }
 
     fn conditional_open_qasm(&self, condition: &str, bit_names: &[String],
@@ -106,7 +132,16 @@ where G0: 'static + crate::export::OpenQasm + Clone,
         let n0 = self.g0.nr_affected_bits();
         let op0 = self.g0.conditional_open_qasm(condition, bit_names, &bits[..n0])?;
         let op1 = self.g1.conditional_open_qasm(condition, bit_names, &bits[n0..])?;
-        Ok(op0 + "; " + &op1)
+        let ops: Vec<String> = vec![op0, op1].into_iter().filter(|op| !op.is_empty()).collect();
+        Ok(ops.join("; "))
+    }
+
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        let mut defs = vec![];
+        crate::export::add_open_qasm_definitions(&mut defs, self.g0.open_qasm_definition());
+        crate::export::add_open_qasm_definitions(&mut defs, self.g1.open_qasm_definition());
+        if defs.is_empty() { None } else { Some(defs.join("\n")) }
     }
 }
 
@@ -133,6 +168,32 @@ where G0: 'static + crate::export::CQasm + Clone,
     }
 }
 
+impl<G0, G1> crate::export::TketJson for Kron<G0, G1>
+where G0: 'static + crate::export::TketJson + Clone,
+    G1: 'static + crate::export::TketJson + Clone
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let n0 = self.g0.nr_affected_bits();
+        let mut res = self.g0.tket_ops(&bits[..n0])?;
+        res.extend(self.g1.tket_ops(&bits[n0..])?);
+        Ok(res)
+    }
+}
+
+impl<G0, G1> crate::instruction::ToInstruction for Kron<G0, G1>
+where G0: 'static + crate::instruction::ToInstruction + Clone,
+    G1: 'static + crate::instruction::ToInstruction + Clone
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        let n0 = self.g0.nr_affected_bits();
+        let mut res = self.g0.instructions(&bits[..n0]);
+        res.extend(self.g1.instructions(&bits[n0..]));
+        res
+    }
+}
+
 impl<G0, G1> crate::export::Latex for Kron<G0, G1>
 where G0: 'static + crate::export::Latex + Clone,
     G1: 'static + crate::export::Latex + Clone
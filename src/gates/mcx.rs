@@ -0,0 +1,197 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Composite;
+
+/// Add a multi-controlled `X` gate on `target`, controlled by `controls`, to
+/// `gate`, using the qubits in `ancillas` as scratch space. The ancillas may
+/// be in any state, and are restored afterwards; at least `m-2` ancillas are
+/// needed for `m` controls. This uses `4(m-2)` Toffoli gates (Barenco et al.,
+/// lemma 7.2).
+fn add_mcx_dirty(gate: &mut Composite, controls: &[usize], target: usize, ancillas: &[usize])
+{
+    let m = controls.len();
+    match m
+    {
+        0 => gate.add_gate(crate::gates::X::new(), &[target]),
+        1 => gate.add_gate(crate::gates::CX::new(), &[controls[0], target]),
+        2 => gate.add_gate(crate::gates::CCX::new(), &[controls[0], controls[1], target]),
+        _ => {
+            // Ancilla a[i-2] holds the partial product of the first i controls
+            let chain = |gate: &mut Composite| {
+                for i in (2..m-1).rev()
+                {
+                    gate.add_gate(crate::gates::CCX::new(),
+                        &[controls[i], ancillas[i-2], ancillas[i-1]]);
+                }
+                gate.add_gate(crate::gates::CCX::new(), &[controls[0], controls[1], ancillas[0]]);
+                for i in 2..m-1
+                {
+                    gate.add_gate(crate::gates::CCX::new(),
+                        &[controls[i], ancillas[i-2], ancillas[i-1]]);
+                }
+            };
+
+            let last = [controls[m-1], ancillas[m-3], target];
+            gate.add_gate(crate::gates::CCX::new(), &last);
+            chain(gate);
+            gate.add_gate(crate::gates::CCX::new(), &last);
+            chain(gate);
+        }
+    }
+}
+
+/// Add a multi-controlled `X` gate on `target`, controlled by `controls`, to
+/// `gate`, using a single ancilla `ancilla` that may be in any state. The
+/// controls are split in two halves, such that each half can use the other
+/// as ancillas (Barenco et al., lemma 7.3).
+fn add_mcx_one_dirty(gate: &mut Composite, controls: &[usize], target: usize, ancilla: usize)
+{
+    let m = controls.len();
+    if m <= 2
+    {
+        add_mcx_dirty(gate, controls, target, &[]);
+    }
+    else
+    {
+        let (first, second) = controls.split_at((m + 1) / 2);
+        let mut second_controls = second.to_vec();
+        second_controls.push(ancilla);
+        let mut first_ancillas = second.to_vec();
+        first_ancillas.push(target);
+        for _ in 0..2
+        {
+            add_mcx_dirty(gate, &second_controls, target, first);
+            add_mcx_dirty(gate, first, ancilla, &first_ancillas);
+        }
+    }
+}
+
+/// Add a controlled `X`<sup>`power`</sup> gate on `target`, controlled by
+/// the (at least one) qubits in `controls`, to `gate`. With `V` the square
+/// root of `X`<sup>`power`</sup>, the gate is decomposed as `CV` controlled
+/// by the last control, conjugated by multi-controlled `X` gates from the
+/// other controls onto the last control, followed by `V` controlled by the
+/// other controls (Barenco et al., lemma 7.5). The multi-controlled `X`
+/// gates borrow the target as ancilla, so that no extra qubits are needed.
+fn add_controlled_x_power(gate: &mut Composite, controls: &[usize], target: usize, power: f64)
+{
+    let k = controls.len();
+    if power == 1.0 && k <= 2
+    {
+        add_mcx_dirty(gate, controls, target, &[]);
+    }
+    else if k == 1
+    {
+        // X^p = H·U1(πp)·H
+        let lambda = ::std::f64::consts::PI * power;
+        gate.add_gate(crate::gates::H::new(), &[target]);
+        gate.add_gate(crate::gates::CU1::new(lambda), &[controls[0], target]);
+        gate.add_gate(crate::gates::H::new(), &[target]);
+    }
+    else
+    {
+        let (&last, rest) = controls.split_last().unwrap();
+        add_controlled_x_power(gate, &[last], target, 0.5 * power);
+        add_mcx_one_dirty(gate, rest, last, target);
+        add_controlled_x_power(gate, &[last], target, -0.5 * power);
+        add_mcx_one_dirty(gate, rest, last, target);
+        add_controlled_x_power(gate, rest, target, 0.5 * power);
+    }
+}
+
+/// Multi-controlled `X` gate.
+///
+/// Return a gate operating on `nr_controls + 1` qubits, that flips the last
+/// qubit if and only if all of the first `nr_controls` qubits are |1⟩. For
+/// up to two controls, this is an `X`, `CX` or `CCX` gate. For more controls,
+/// a composite gate is returned that uses `O(n²)` Toffoli gates and
+/// controlled phase rotations, without the need for ancilla qubits.
+pub fn n_controlled_x(nr_controls: usize) -> Box<dyn crate::export::CircuitGate>
+{
+    match nr_controls
+    {
+        0 => Box::new(crate::gates::X::new()),
+        1 => Box::new(crate::gates::CX::new()),
+        2 => Box::new(crate::gates::CCX::new()),
+        _ => {
+            let mut gate = Composite::new(&format!("C{}X", nr_controls), nr_controls + 1);
+            let controls: Vec<usize> = (0..nr_controls).collect();
+            add_controlled_x_power(&mut gate, &controls, nr_controls, 1.0);
+            Box::new(gate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::n_controlled_x;
+    use crate::export::OpenQasm;
+    use crate::gates::Gate;
+    use crate::instruction::ToInstruction;
+
+    #[test]
+    fn test_description()
+    {
+        assert_eq!(n_controlled_x(0).description(), "X");
+        assert_eq!(n_controlled_x(1).description(), "CX");
+        assert_eq!(n_controlled_x(2).description(), "CCX");
+        assert_eq!(n_controlled_x(3).description(), "C3X");
+        assert_eq!(n_controlled_x(7).nr_affected_bits(), 8);
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        for nr_controls in 0..7
+        {
+            let size = 1 << (nr_controls + 1);
+            let mut expected = crate::cmatrix::CMatrix::eye(size);
+            expected[[size-2, size-2]] = crate::cmatrix::COMPLEX_ZERO;
+            expected[[size-1, size-1]] = crate::cmatrix::COMPLEX_ZERO;
+            expected[[size-2, size-1]] = o;
+            expected[[size-1, size-2]] = o;
+            assert_complex_matrix_eq!(&n_controlled_x(nr_controls).matrix(), &expected,
+                1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_nr_toffoli()
+    {
+        let nr_toffoli = |nr_controls: usize| {
+            n_controlled_x(nr_controls).instructions(&(0..nr_controls+1).collect::<Vec<_>>())
+                .iter()
+                .filter(|instr| format!("{:?}", instr).starts_with("CCX"))
+                .count()
+        };
+        for n in 3..20
+        {
+            assert!(nr_toffoli(n) <= 6 * n * n);
+        }
+    }
+
+    #[test]
+    fn test_open_qasm()
+    {
+        let bit_names: Vec<String> = (0..4).map(|i| format!("qb{}", i)).collect();
+        assert_eq!(n_controlled_x(2).open_qasm(&bit_names, &[3, 1, 0]),
+            Ok(String::from("ccx qb3, qb1, qb0")));
+        let qasm = n_controlled_x(3).open_qasm(&bit_names, &[0, 1, 2, 3]).unwrap();
+        assert!(qasm.starts_with("h qb3; cu1("));
+        assert!(qasm.contains("ccx qb0, qb1, qb2"));
+    }
+}
@@ -0,0 +1,568 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Trait for noise channels
+///
+/// A noise channel describes a (non-unitary) process acting on one or more
+/// qubits. It is described by a set of Kraus operators `K`<sub>`i`</sub>,
+/// which change a density matrix `ρ` into `Σ`<sub>`i`</sub>
+/// `K`<sub>`i`</sub>`ρK`<sub>`i`</sub><sup>`†`</sup>. Noise channels only have
+/// an effect in density matrix simulations, and are ignored when simulating
+/// pure states.
+pub trait NoiseChannel
+{
+    /// Return a short description of the noise channel.
+    fn description(&self) -> &str;
+
+    /// The number of qubits affected by this channel.
+    fn nr_affected_bits(&self) -> usize;
+
+    /// Return the Kraus operators describing this channel.
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>;
+}
+
+/// Depolarizing channel
+///
+/// The single qubit depolarizing channel with error probability `p` leaves
+/// the qubit untouched with probability `1-p`, and applies one of the Pauli
+/// operators `X`, `Y` or `Z`, each with probability `p/3`.
+#[derive(Clone)]
+pub struct Depolarizing
+{
+    /// The error probability
+    p: f64,
+    /// Description of the channel
+    desc: String
+}
+
+impl Depolarizing
+{
+    /// Create a new depolarizing channel with error probability `p`.
+    pub fn new(p: f64) -> Self
+    {
+        let desc = format!("Depolarizing({})", p);
+        Depolarizing { p: p, desc: desc }
+    }
+
+    /// The error probability of this channel
+    pub fn probability(&self) -> f64
+    {
+        self.p
+    }
+}
+
+impl NoiseChannel for Depolarizing
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        1
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let a = (1.0 - self.p).sqrt();
+        let b = (self.p / 3.0).sqrt();
+        vec![
+            array![[a*o, z], [z, a*o]],
+            array![[z, b*o], [b*o, z]],
+            array![[z, -b*i], [b*i, z]],
+            array![[b*o, z], [z, -b*o]]
+        ]
+    }
+}
+
+/// Two-qubit depolarizing channel
+///
+/// The two qubit depolarizing channel with error probability `p` leaves the
+/// qubits untouched with probability `1-p`, and applies one of the fifteen
+/// non-trivial tensor products of two Pauli operators, each with probability
+/// `p/15`.
+#[derive(Clone)]
+pub struct TwoQubitDepolarizing
+{
+    /// The error probability
+    p: f64,
+    /// Description of the channel
+    desc: String
+}
+
+impl TwoQubitDepolarizing
+{
+    /// Create a new two-qubit depolarizing channel with error probability `p`.
+    pub fn new(p: f64) -> Self
+    {
+        let desc = format!("TwoQubitDepolarizing({})", p);
+        TwoQubitDepolarizing { p: p, desc: desc }
+    }
+
+    /// The error probability of this channel
+    pub fn probability(&self) -> f64
+    {
+        self.p
+    }
+}
+
+impl NoiseChannel for TwoQubitDepolarizing
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        2
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let paulis = [
+            array![[o, z], [z, o]],
+            array![[z, o], [o, z]],
+            array![[z, -i], [i, z]],
+            array![[o, z], [z, -o]]
+        ];
+
+        let a = (1.0 - self.p).sqrt();
+        let b = (self.p / 15.0).sqrt();
+        let mut res = vec![];
+        for (k0, p0) in paulis.iter().enumerate()
+        {
+            for (k1, p1) in paulis.iter().enumerate()
+            {
+                let scale = if k0 == 0 && k1 == 0 { a } else { b };
+                res.push(crate::cmatrix::kron_mat(p0, p1) * scale);
+            }
+        }
+        res
+    }
+}
+
+/// Amplitude damping channel
+///
+/// The single qubit amplitude damping channel describes energy relaxation,
+/// where a qubit in state |1⟩ decays to |0⟩ with probability `γ`. It is
+/// described by the Kraus operators
+/// ```text
+///      ┌          ┐        ┌        ┐
+///      │1     0   │        │0   √γ  │
+/// K₀ = │          │   K₁ = │        │
+///      │0  √(1-γ) │        │0    0  │
+///      └          ┘        └        ┘
+/// ```
+#[derive(Clone)]
+pub struct AmplitudeDamping
+{
+    /// The decay probability
+    gamma: f64,
+    /// Description of the channel
+    desc: String
+}
+
+impl AmplitudeDamping
+{
+    /// Create a new amplitude damping channel with decay probability `gamma`.
+    pub fn new(gamma: f64) -> Self
+    {
+        let desc = format!("AmplitudeDamping({})", gamma);
+        AmplitudeDamping { gamma: gamma, desc: desc }
+    }
+
+    /// The probability of decay from |1⟩ to |0⟩
+    pub fn decay_probability(&self) -> f64
+    {
+        self.gamma
+    }
+}
+
+impl NoiseChannel for AmplitudeDamping
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        1
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let a = (1.0 - self.gamma).sqrt();
+        let b = self.gamma.sqrt();
+        vec![
+            array![[o, z], [z, a*o]],
+            array![[z, b*o], [z, z]]
+        ]
+    }
+}
+
+/// Phase damping channel
+///
+/// The single qubit phase damping channel describes pure dephasing, where
+/// the populations of |0⟩ and |1⟩ are unchanged, but the off-diagonal
+/// elements of the density matrix are multiplied by `√(1-λ)`. It is
+/// described by the Kraus operators
+/// ```text
+///      ┌          ┐        ┌        ┐
+///      │1     0   │        │0    0  │
+/// K₀ = │          │   K₁ = │        │
+///      │0  √(1-λ) │        │0   √λ  │
+///      └          ┘        └        ┘
+/// ```
+#[derive(Clone)]
+pub struct PhaseDamping
+{
+    /// The damping parameter
+    lambda: f64,
+    /// Description of the channel
+    desc: String
+}
+
+impl PhaseDamping
+{
+    /// Create a new phase damping channel with damping parameter `lambda`.
+    pub fn new(lambda: f64) -> Self
+    {
+        let desc = format!("PhaseDamping({})", lambda);
+        PhaseDamping { lambda: lambda, desc: desc }
+    }
+
+    /// The damping parameter `λ` of this channel
+    pub fn damping(&self) -> f64
+    {
+        self.lambda
+    }
+}
+
+impl NoiseChannel for PhaseDamping
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        1
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let a = (1.0 - self.lambda).sqrt();
+        let b = self.lambda.sqrt();
+        vec![
+            array![[o, z], [z, a*o]],
+            array![[z, z], [z, b*o]]
+        ]
+    }
+}
+
+/// Thermal relaxation channel
+///
+/// The single qubit thermal relaxation channel describes the decay of a qubit
+/// that is left idle for a time `t`, with energy relaxation time `T1` and
+/// dephasing time `T2`. It combines amplitude damping towards |0⟩ with
+/// probability `γ = 1 - exp(-t/T1)`, with pure dephasing such that the
+/// off-diagonal elements of the density matrix decay as `exp(-t/T2)`. This
+/// requires `T2 ≤ 2T1`; for larger `T2`, the coherences decay as
+/// `exp(-t/(2T1))`.
+#[derive(Clone)]
+pub struct ThermalRelaxation
+{
+    /// The amplitude damping probability
+    gamma: f64,
+    /// The factor by which coherences are multiplied by pure dephasing
+    dephasing: f64,
+    /// Description of the channel
+    desc: String
+}
+
+impl ThermalRelaxation
+{
+    /// Create a new thermal relaxation channel for a qubit that is idle for
+    /// time `time`, with relaxation times `t1` and `t2`.
+    pub fn new(time: f64, t1: f64, t2: f64) -> Self
+    {
+        let gamma = 1.0 - (-time / t1).exp();
+        let dephasing = (-time / t2 + 0.5 * time / t1).exp().min(1.0);
+        let desc = format!("ThermalRelaxation({}, {}, {})", time, t1, t2);
+        ThermalRelaxation { gamma: gamma, dephasing: dephasing, desc: desc }
+    }
+
+    /// The probability of decay from |1⟩ to |0⟩
+    pub fn decay_probability(&self) -> f64
+    {
+        self.gamma
+    }
+}
+
+impl NoiseChannel for ThermalRelaxation
+{
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        1
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let a = (1.0 - self.gamma).sqrt();
+        let b = self.gamma.sqrt();
+        let c = (0.5 * (1.0 + self.dephasing)).sqrt();
+        let d = (0.5 * (1.0 - self.dephasing)).sqrt();
+        vec![
+            array![[c*o, z], [z, (a*c)*o]],
+            array![[z, (b*c)*o], [z, z]],
+            array![[d*o, z], [z, -(a*d)*o]],
+            array![[z, (b*d)*o], [z, z]]
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{AmplitudeDamping, Depolarizing, NoiseChannel, PhaseDamping, ThermalRelaxation,
+        TwoQubitDepolarizing};
+
+    fn apply_kraus(kraus: &[crate::cmatrix::CMatrix], rho: &crate::cmatrix::CMatrix)
+        -> crate::cmatrix::CMatrix
+    {
+        let mut res = crate::cmatrix::CMatrix::zeros(rho.dim());
+        for k in kraus.iter()
+        {
+            res += &k.dot(rho).dot(&k.t().mapv(|c| c.conj()));
+        }
+        res
+    }
+
+    #[test]
+    fn test_description()
+    {
+        let channel = Depolarizing::new(0.25);
+        assert_eq!(channel.description(), "Depolarizing(0.25)");
+        assert_eq!(channel.nr_affected_bits(), 1);
+        assert_eq!(channel.probability(), 0.25);
+    }
+
+    #[test]
+    fn test_kraus_operators()
+    {
+        let channel = Depolarizing::new(0.3);
+        let kraus = channel.kraus_operators();
+        assert_eq!(kraus.len(), 4);
+
+        // Check completeness: Σ K†K = I
+        let mut sum = crate::cmatrix::CMatrix::zeros((2, 2));
+        for k in kraus.iter()
+        {
+            sum += &k.t().mapv(|c| c.conj()).dot(k);
+        }
+        assert_complex_matrix_eq!(&sum, &crate::cmatrix::CMatrix::eye(2));
+    }
+
+    #[test]
+    fn test_depolarizing_bloch_vector()
+    {
+        // Starting from |+⟩, the Bloch vector (1, 0, 0) shrinks by a factor
+        // 1 - 4p/3 on every application of the channel
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let p = 1.0 / 3.0;
+        let kraus = Depolarizing::new(p).kraus_operators();
+        let mut rho = array![[h, h], [h, h]];
+        let mut length = 1.0;
+        for _ in 0..3
+        {
+            let mut res = crate::cmatrix::CMatrix::zeros((2, 2));
+            for k in kraus.iter()
+            {
+                res += &k.dot(&rho).dot(&k.t().mapv(|c| c.conj()));
+            }
+            rho = res;
+            length *= 1.0 - 4.0 * p / 3.0;
+
+            let bloch_x = 2.0 * rho[[0, 1]].re;
+            assert!((bloch_x - length).abs() < 1.0e-12);
+            assert!(rho[[0, 1]].im.abs() < 1.0e-12);
+            assert!((rho[[0, 0]].re - 0.5).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_two_qubit_depolarizing()
+    {
+        let channel = TwoQubitDepolarizing::new(0.3);
+        assert_eq!(channel.description(), "TwoQubitDepolarizing(0.3)");
+        assert_eq!(channel.nr_affected_bits(), 2);
+        assert_eq!(channel.probability(), 0.3);
+
+        let kraus = channel.kraus_operators();
+        assert_eq!(kraus.len(), 16);
+        let mut sum = crate::cmatrix::CMatrix::zeros((4, 4));
+        for k in kraus.iter()
+        {
+            sum += &k.t().mapv(|c| c.conj()).dot(k);
+        }
+        assert_complex_matrix_eq!(&sum, &crate::cmatrix::CMatrix::eye(4));
+
+        // With p = 15/16, any state is mapped to the maximally mixed state
+        let kraus = TwoQubitDepolarizing::new(15.0 / 16.0).kraus_operators();
+        let mut rho = crate::cmatrix::CMatrix::zeros((4, 4));
+        rho[[0, 0]] = crate::cmatrix::COMPLEX_ONE;
+        let mut res = crate::cmatrix::CMatrix::zeros((4, 4));
+        for k in kraus.iter()
+        {
+            res += &k.dot(&rho).dot(&k.t().mapv(|c| c.conj()));
+        }
+        assert_complex_matrix_eq!(&res, &(crate::cmatrix::CMatrix::eye(4) * 0.25));
+    }
+
+    #[test]
+    fn test_thermal_relaxation()
+    {
+        let channel = ThermalRelaxation::new(1.0, 2.0, 3.0);
+        assert_eq!(channel.description(), "ThermalRelaxation(1, 2, 3)");
+        assert_eq!(channel.nr_affected_bits(), 1);
+        assert!((channel.decay_probability() - (1.0 - (-0.5f64).exp())).abs() < 1.0e-12);
+
+        let kraus = channel.kraus_operators();
+        let mut sum = crate::cmatrix::CMatrix::zeros((2, 2));
+        for k in kraus.iter()
+        {
+            sum += &k.t().mapv(|c| c.conj()).dot(k);
+        }
+        assert_complex_matrix_eq!(&sum, &crate::cmatrix::CMatrix::eye(2));
+
+        // Excited population decays with T1, coherences with T2
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let rho = array![[h, h], [h, h]];
+        let mut res = crate::cmatrix::CMatrix::zeros((2, 2));
+        for k in kraus.iter()
+        {
+            res += &k.dot(&rho).dot(&k.t().mapv(|c| c.conj()));
+        }
+        assert!((res[[1, 1]].re - 0.5 * (-0.5f64).exp()).abs() < 1.0e-12);
+        assert!((res[[0, 1]].re - 0.5 * (-1.0f64 / 3.0).exp()).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_amplitude_damping()
+    {
+        let channel = AmplitudeDamping::new(0.3);
+        assert_eq!(channel.description(), "AmplitudeDamping(0.3)");
+        assert_eq!(channel.nr_affected_bits(), 1);
+        assert_eq!(channel.decay_probability(), 0.3);
+
+        let kraus = channel.kraus_operators();
+        let mut sum = crate::cmatrix::CMatrix::zeros((2, 2));
+        for k in kraus.iter()
+        {
+            sum += &k.t().mapv(|c| c.conj()).dot(k);
+        }
+        assert_complex_matrix_eq!(&sum, &crate::cmatrix::CMatrix::eye(2));
+
+        // Starting from |+⟩, the excited population decays by a factor 1-γ,
+        // and the coherences by a factor √(1-γ)
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let rho = apply_kraus(&kraus, &array![[h, h], [h, h]]);
+        assert!((rho[[0, 0]].re - 0.65).abs() < 1.0e-12);
+        assert!((rho[[1, 1]].re - 0.35).abs() < 1.0e-12);
+        assert!((rho[[0, 1]].re - 0.5 * 0.7f64.sqrt()).abs() < 1.0e-12);
+        assert!((rho[[1, 0]].re - 0.5 * 0.7f64.sqrt()).abs() < 1.0e-12);
+
+        // Repeated application drives the state to |0⟩, faster for larger γ
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let ground = array![[o, z], [z, z]];
+        for &gamma in &[0.5, 0.9, 0.99]
+        {
+            let kraus = AmplitudeDamping::new(gamma).kraus_operators();
+            let mut rho = array![[z, z], [z, o]];
+            for n in 1..=20
+            {
+                rho = apply_kraus(&kraus, &rho);
+                assert!((rho[[1, 1]].re - (1.0 - gamma).powi(n)).abs() < 1.0e-12);
+            }
+            assert_complex_matrix_eq!(&rho, &ground, 1.0e-6);
+        }
+        let kraus = AmplitudeDamping::new(1.0).kraus_operators();
+        let rho = apply_kraus(&kraus, &array![[h, h], [h, h]]);
+        assert_complex_matrix_eq!(&rho, &ground);
+    }
+
+    #[test]
+    fn test_phase_damping()
+    {
+        let channel = PhaseDamping::new(0.36);
+        assert_eq!(channel.description(), "PhaseDamping(0.36)");
+        assert_eq!(channel.nr_affected_bits(), 1);
+        assert_eq!(channel.damping(), 0.36);
+
+        let kraus = channel.kraus_operators();
+        let mut sum = crate::cmatrix::CMatrix::zeros((2, 2));
+        for k in kraus.iter()
+        {
+            sum += &k.t().mapv(|c| c.conj()).dot(k);
+        }
+        assert_complex_matrix_eq!(&sum, &crate::cmatrix::CMatrix::eye(2));
+
+        // Populations are unchanged, coherences decay by a factor √(1-λ)
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let rho = apply_kraus(&kraus, &array![[h, h], [h, h]]);
+        assert_complex_matrix_eq!(&rho, &array![[h, 0.8*h], [0.8*h, h]], 1.0e-12);
+    }
+
+    #[test]
+    fn test_execute_density()
+    {
+        let mut circuit = crate::circuit::Circuit::new(2, 2);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.add_noise(AmplitudeDamping::new(0.25), &[0]), Ok(()));
+        assert_eq!(circuit.add_noise(PhaseDamping::new(1.0), &[1]), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1]), Ok(()));
+        assert_eq!(circuit.execute_density(1000), Ok(()));
+        // Qubit 0 decayed to |0⟩ in a quarter of the runs, qubit 1 is still
+        // measured as 0 or 1 with equal probability
+        let hist = circuit.histogram_vec().unwrap();
+        assert_eq!(hist.iter().sum::<usize>(), 1000);
+        let nr_decayed = hist[0] + hist[2];
+        assert!(nr_decayed > 150 && nr_decayed < 350);
+        let nr_one = hist[2] + hist[3];
+        assert!(nr_one > 400 && nr_one < 600);
+    }
+}
@@ -18,7 +18,8 @@
 /// be useful to e.g. to reexecute a circuit with the end state of the last
 /// excution, but with a different value for a parameter. For reference
 /// parameters, a name is associated with the parameter for the description,
-/// since its value can change.
+/// since its value can change. Finally, a parameter can be a free symbol,
+/// which has no value until it is bound to one using `bind()`.
 #[derive(Clone)]
 pub enum Parameter
 {
@@ -27,7 +28,39 @@ pub enum Parameter
     /// Reference value, mutable outside the circuit, with its name
     Reference(::std::rc::Rc<::std::cell::RefCell<f64>>, String),
     /// Reference parameter from external code
-    FFIRef(*const f64)
+    FFIRef(*const f64),
+    /// Free parameter with its name, that has not been bound to a value yet
+    Symbol(String)
+}
+
+/// A set of values for named parameters, used to bind the parameters of the
+/// gates in a circuit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterStore
+{
+    values: ::std::collections::HashMap<String, f64>
+}
+
+impl ParameterStore
+{
+    /// Create a new, empty, parameter store.
+    pub fn new() -> Self
+    {
+        ParameterStore { values: ::std::collections::HashMap::new() }
+    }
+
+    /// Set the value of parameter `name` to `value`.
+    pub fn set(&mut self, name: &str, value: f64)
+    {
+        self.values.insert(String::from(name), value);
+    }
+
+    /// Return the value of parameter `name`, or `None` when the store holds
+    /// no value for it.
+    pub fn get(&self, name: &str) -> Option<f64>
+    {
+        self.values.get(name).cloned()
+    }
 }
 
 impl Parameter
@@ -38,16 +71,57 @@ impl Parameter
         Parameter::Reference(cell.clone(), String::from(name))
     }
 
-    /// Return the current value of the parameter
+    /// Create a new free parameter with name `name`.
+    pub fn symbol(name: &str) -> Self
+    {
+        Parameter::Symbol(String::from(name))
+    }
+
+    /// Return the current value of the parameter. Free parameters have no
+    /// value, and return NaN.
     pub fn value(&self) -> f64
     {
         match *self
         {
             Parameter::Direct(p) => p,
             Parameter::Reference(ref p, _) => *p.borrow(),
-            Parameter::FFIRef(p) => unsafe { *p }
+            Parameter::FFIRef(p) => unsafe { *p },
+            Parameter::Symbol(_) => f64::NAN
         }
     }
+
+    /// Return whether this parameter has a value, i.e. whether it is not a
+    /// free parameter.
+    pub fn is_bound(&self) -> bool
+    {
+        !matches!(*self, Parameter::Symbol(_))
+    }
+
+    /// Bind this parameter.
+    ///
+    /// Replace a free or reference parameter whose name occurs in `store`
+    /// by a direct parameter with the value from the store. Reference
+    /// parameters not in `store` are left unchanged, but for free parameters
+    /// not in `store`, an `UnboundParameter` error is returned.
+    pub fn bind(&mut self, store: &ParameterStore) -> crate::error::Result<()>
+    {
+        let value = match *self
+            {
+                Parameter::Reference(_, ref name) => store.get(name),
+                Parameter::Symbol(ref name) => {
+                    let value = store.get(name).ok_or_else(|| {
+                        crate::error::Error::UnboundParameter(name.clone())
+                    })?;
+                    Some(value)
+                },
+                _ => None
+            };
+        if let Some(value) = value
+        {
+            *self = Parameter::Direct(value);
+        }
+        Ok(())
+    }
 }
 
 impl From<f64> for Parameter
@@ -69,7 +143,8 @@ impl ::std::fmt::Display for Parameter
             Parameter::FFIRef(ptr) => {
                 let p = unsafe { *ptr };
                 p.fmt(f)
-            }
+            },
+            Parameter::Symbol(ref name) => write!(f, "{}", name)
         }
     }
 }
@@ -77,7 +152,7 @@ impl ::std::fmt::Display for Parameter
 #[cfg(test)]
 mod tests
 {
-    use super::Parameter;
+    use super::{Parameter, ParameterStore};
 
     #[test]
     fn test_from()
@@ -124,4 +199,41 @@ mod tests
         assert_eq!(format!("{:.4}", p1), String::from("x"));
         assert_eq!(format!("{:.4}", p2), String::from("longname"));
     }
+
+    #[test]
+    fn test_bind()
+    {
+        let cell = ::std::rc::Rc::new(::std::cell::RefCell::new(0.12));
+        let mut store = ParameterStore::new();
+        store.set("x", 1.5);
+        store.set("y", -2.0);
+        assert_eq!(store.get("x"), Some(1.5));
+        assert_eq!(store.get("z"), None);
+
+        let mut p = Parameter::symbol("x");
+        assert!(!p.is_bound());
+        assert!(p.value().is_nan());
+        assert_eq!(format!("{:.4}", p), String::from("x"));
+        assert_eq!(p.bind(&store), Ok(()));
+        assert!(matches!(p, Parameter::Direct(_)));
+        assert_eq!(p.value(), 1.5);
+
+        let mut p = Parameter::from_refcell(&cell, "y");
+        assert!(p.is_bound());
+        assert_eq!(p.bind(&store), Ok(()));
+        assert!(matches!(p, Parameter::Direct(_)));
+        assert_eq!(p.value(), -2.0);
+
+        let mut p = Parameter::from_refcell(&cell, "z");
+        assert_eq!(p.bind(&store), Ok(()));
+        assert!(matches!(p, Parameter::Reference(_, _)));
+
+        let mut p = Parameter::symbol("z");
+        assert_eq!(p.bind(&store), Err(crate::error::Error::UnboundParameter(String::from("z"))));
+        assert!(!p.is_bound());
+
+        let mut p = Parameter::from(0.5);
+        assert_eq!(p.bind(&store), Ok(()));
+        assert_eq!(p.value(), 0.5);
+    }
 }
@@ -0,0 +1,248 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+use crate::stabilizer::PauliOp;
+
+/// Global phase gate.
+///
+/// The `Phase(ϕ)` gate acts on no qubits at all, and multiplies the state by
+/// the global phase factor `exp(iϕ)`. Its associated matrix is the 1×1 matrix
+/// `[exp(iϕ)]`. Though a global phase has no observable effect by itself, it
+/// becomes a relative phase when the gate is controlled: `C(Phase(ϕ))` is
+/// the `U`<sub>`1`</sub>`(ϕ)` gate on the control qubit.
+#[derive(Clone)]
+pub struct Phase
+{
+    phi: crate::gates::Parameter,
+    desc: String
+}
+
+/// Alternative name for the global phase gate `Phase`.
+pub type GlobalPhase = Phase;
+
+impl Phase
+{
+    /// Create a new global phase gate.
+    pub fn new<T>(phi: T) -> Self
+    where crate::gates::Parameter: From<T>
+    {
+        let param = crate::gates::Parameter::from(phi);
+        let desc = format!("Phase({:.4})", param);
+        Phase { phi: param, desc: desc }
+    }
+
+    /// The phase factor `exp(iϕ)` of this gate
+    pub fn factor(&self) -> crate::cmatrix::CNumber
+    {
+        num_complex::Complex::from_polar(&1.0, &self.phi.value())
+    }
+}
+
+impl crate::gates::Gate for Phase
+{
+    fn cost(&self) -> f64
+    {
+        0.0
+    }
+
+    fn description(&self) -> &str
+    {
+        &self.desc
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        0
+    }
+
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.phi]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.phi.bind(store)?;
+        *self = Self::new(self.phi.clone());
+        Ok(())
+    }
+
+    fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        array![[self.factor()]]
+    }
+
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        Some(crate::gates::diagonal_sparsity(&[self.factor()]))
+    }
+
+    fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
+    {
+        state *= self.factor();
+    }
+
+    fn apply_mat_slice(&self, mut state: crate::cmatrix::CMatSliceMut)
+    {
+        state *= self.factor();
+    }
+
+    fn is_stabilizer(&self) -> bool
+    {
+        true
+    }
+
+    fn conjugate(&self, _ops: &mut [PauliOp]) -> crate::error::Result<bool>
+    {
+        Ok(false)
+    }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        if let crate::gates::Parameter::Direct(phi) = self.phi
+        {
+            Box::new(Phase::new(-phi))
+        }
+        else
+        {
+            crate::gates::matrix_adjoint(self)
+        }
+    }
+}
+
+impl crate::export::OpenQasm for Phase
+{
+    fn open_qasm(&self, _bit_names: &[String], _bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        // OpenQasm 2.0 cannot represent a global phase. Since it has no
+        // observable effect, the gate is simply left out of the program.
+        Ok(String::new())
+    }
+
+    fn open_qasm3(&self, _bit_names: &[String], _bits: &[usize])
+        -> crate::error::Result<String>
+    {
+        Ok(format!("gphase({})", self.phi))
+    }
+
+    fn conditional_open_qasm(&self, _condition: &str, bit_names: &[String],
+        bits: &[usize]) -> crate::error::Result<String>
+    {
+        self.open_qasm(bit_names, bits)
+    }
+}
+
+impl crate::export::CQasm for Phase {}
+
+impl crate::export::TketJson for Phase
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.phi)
+        ];
+        Ok(vec![crate::export::TketOp::new("Phase", params, bits)])
+    }
+}
+
+impl crate::export::Latex for Phase
+{
+    fn latex(&self, bits: &[usize], _state: &mut crate::export::LatexExportState)
+        -> crate::error::Result<()>
+    {
+        // A global phase is not drawn
+        self.check_nr_bits(bits.len())
+    }
+}
+
+impl crate::instruction::ToInstruction for Phase {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{GlobalPhase, Phase};
+    use crate::export::OpenQasm;
+    use crate::gates::{gate_test, Gate, C};
+
+    #[test]
+    fn test_description()
+    {
+        let gate = Phase::new(::std::f64::consts::FRAC_PI_4);
+        assert_eq!(gate.description(), "Phase(0.7854)");
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let i = crate::cmatrix::COMPLEX_I;
+        let gate = Phase::new(::std::f64::consts::FRAC_PI_2);
+        assert_eq!(gate.nr_affected_bits(), 0);
+        assert_complex_matrix_eq!(gate.matrix(), array![[i]]);
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        let mut state = array![[o, z, x], [z, o, -x]];
+        let result = array![[-o, z, -x], [z, -o, x]];
+        gate_test(Phase::new(::std::f64::consts::PI), &mut state, &result);
+
+        let mut state = array![[o, x], [z, x * i]];
+        let result = array![[i, x * i], [z, -x]];
+        let mut work = state.clone();
+        Phase::new(::std::f64::consts::FRAC_PI_2).apply_mat(&mut work);
+        assert_complex_matrix_eq!(&work, &result);
+        gate_test(Phase::new(::std::f64::consts::FRAC_PI_2), &mut state, &result);
+    }
+
+    #[test]
+    fn test_global_phase()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let theta = 0.3;
+        let factor = num_complex::Complex::from_polar(&1.0, &theta);
+
+        let gate = GlobalPhase::new(theta);
+        assert_eq!(gate.nr_affected_bits(), 0);
+        assert_complex_matrix_eq!(gate.matrix(), array![[factor]]);
+
+        let mut state = array![x * o, o * 0.5, -o * 0.5];
+        gate.apply(&mut state);
+        assert_complex_vector_eq!(&state, &array![x * factor, factor * 0.5, -factor * 0.5]);
+    }
+
+    #[test]
+    fn test_open_qasm()
+    {
+        let gate = Phase::new(0.25);
+        assert_eq!(gate.open_qasm(&[], &[]), Ok(String::new()));
+        assert_eq!(gate.conditional_open_qasm("b == 1", &[], &[]), Ok(String::new()));
+        assert_eq!(gate.open_qasm3(&[], &[]), Ok(String::from("gphase(0.25)")));
+    }
+
+    #[test]
+    fn test_controlled()
+    {
+        let gate = C::new(Phase::new(0.7));
+        assert_eq!(gate.nr_affected_bits(), 1);
+        assert_complex_matrix_eq!(gate.matrix(), crate::gates::U1::new(0.7).matrix());
+    }
+}
@@ -61,6 +61,18 @@ impl crate::gates::Gate for RX
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.theta]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.theta.bind(store)?;
+        *self = Self::new(self.theta.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let htheta = 0.5 * self.theta.value();
@@ -110,6 +122,18 @@ impl crate::gates::Gate for RX
             slice -= &s.slice(s![..n, ..]);
         }
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        if let crate::gates::Parameter::Direct(theta) = self.theta
+        {
+            Box::new(RX::new(-theta))
+        }
+        else
+        {
+            crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for RX
@@ -130,6 +154,25 @@ impl crate::export::CQasm for RX
     }
 }
 
+impl crate::export::TketJson for RX
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.theta)
+        ];
+        Ok(vec![crate::export::TketOp::new("Rx", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for RX
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::RX(self.theta.value(), bits[0])]
+    }
+}
+
 impl crate::export::Latex for RX
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -61,6 +61,18 @@ impl crate::gates::Gate for RY
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.theta]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.theta.bind(store)?;
+        *self = Self::new(self.theta.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let htheta = 0.5 * self.theta.value();
@@ -110,6 +122,18 @@ impl crate::gates::Gate for RY
             slice += &s.slice(s![..n, ..]);
         }
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        if let crate::gates::Parameter::Direct(theta) = self.theta
+        {
+            Box::new(RY::new(-theta))
+        }
+        else
+        {
+            crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for RY
@@ -133,6 +157,25 @@ impl crate::export::CQasm for RY
     }
 }
 
+impl crate::export::TketJson for RY
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.theta)
+        ];
+        Ok(vec![crate::export::TketOp::new("Ry", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for RY
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::RY(self.theta.value(), bits[0])]
+    }
+}
+
 impl crate::export::Latex for RY
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -62,6 +62,18 @@ impl crate::gates::Gate for RZ
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.lambda]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.lambda.bind(store)?;
+        *self = Self::new(self.lambda.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let z = crate::cmatrix::COMPLEX_ZERO;
@@ -69,6 +81,12 @@ impl crate::gates::Gate for RZ
         array![[p.conj(), z], [z, p]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let p = num_complex::Complex::from_polar(&1.0, &(0.5 * self.lambda.value()));
+        Some(crate::gates::diagonal_sparsity(&[p.conj(), p]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -100,6 +118,18 @@ impl crate::gates::Gate for RZ
             slice *= num_complex::Complex::from_polar(&1.0, &( hlambda));
         }
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        if let crate::gates::Parameter::Direct(lambda) = self.lambda
+        {
+            Box::new(RZ::new(-lambda))
+        }
+        else
+        {
+            crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for RZ
@@ -120,6 +150,25 @@ impl crate::export::CQasm for RZ
     }
 }
 
+impl crate::export::TketJson for RZ
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.lambda)
+        ];
+        Ok(vec![crate::export::TketOp::new("Rz", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for RZ
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::RZ(self.lambda.value(), bits[0])]
+    }
+}
+
 impl crate::export::Latex for RZ
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -150,7 +199,7 @@ mod tests
 {
     use super::RZ;
     use crate::arithmetic::Square;
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
 
     #[test]
@@ -176,6 +225,13 @@ mod tests
         assert_complex_matrix_eq!(gate.matrix(), array![[-i, z], [z, i]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(RZ::new(0.0));
+        sparse_test(RZ::new(1.3));
+    }
+
     #[test]
     fn test_apply()
     {
@@ -65,6 +65,13 @@ impl crate::gates::Gate for S
         array![[o, z], [z, i]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        Some(crate::gates::diagonal_sparsity(&[o, i]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -101,6 +108,11 @@ impl crate::gates::Gate for S
         ops[0] = op;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(Sdg::new())
+    }
 }
 
 impl crate::export::OpenQasm for S
@@ -121,6 +133,22 @@ impl crate::export::CQasm for S
     }
 }
 
+impl crate::export::TketJson for S
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("S", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for S
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::S(bits[0])]
+    }
+}
+
 impl crate::export::Latex for S
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -191,6 +219,13 @@ impl crate::gates::Gate for Sdg
         array![[o, z], [z, -i]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        Some(crate::gates::diagonal_sparsity(&[o, -i]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -227,6 +262,11 @@ impl crate::gates::Gate for Sdg
         ops[0] = op;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(S::new())
+    }
 }
 
 impl crate::export::OpenQasm for Sdg
@@ -247,6 +287,22 @@ impl crate::export::CQasm for Sdg
     }
 }
 
+impl crate::export::TketJson for Sdg
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("Sdg", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Sdg
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Sdg(bits[0])]
+    }
+}
+
 impl crate::export::Latex for Sdg
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -271,7 +327,7 @@ impl crate::arithmetic::Square for Sdg
 mod tests
 {
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate, S, Sdg};
+    use crate::gates::{gate_test, sparse_test, Gate, S, Sdg};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -307,6 +363,13 @@ mod tests
         assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, -i]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(S::new());
+        sparse_test(Sdg::new());
+    }
+
     #[test]
     fn test_apply()
     {
@@ -68,6 +68,16 @@ impl crate::gates::Gate for Loop
         self.body.nr_affected_bits()
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        self.body.parameters()
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.body.bind(store)
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let mut res = crate::cmatrix::CMatrix::eye(1 << self.nr_affected_bits());
@@ -106,6 +116,16 @@ impl crate::gates::Gate for Loop
         }
         Ok(flip_sign)
     }
+
+    fn structure(&self) -> Option<crate::gates::GateStructure<'_>>
+    {
+        Some(crate::gates::GateStructure::Loop
+        {
+            label: &self.label,
+            nr_iterations: self.nr_iterations,
+            body: &self.body
+        })
+    }
 }
 
 impl crate::export::OpenQasm for Loop
@@ -150,6 +170,11 @@ impl crate::export::OpenQasm for Loop
             Ok(res)
         }
     }
+
+    fn open_qasm_definition(&self) -> Option<String>
+    {
+        self.body.open_qasm_definition()
+    }
 }
 
 impl crate::export::CQasm for Loop
@@ -186,6 +211,34 @@ impl crate::export::CQasm for Loop
     }
 }
 
+impl crate::export::TketJson for Loop
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let body_ops = self.body.tket_ops(bits)?;
+        let mut res = vec![];
+        for _ in 0..self.nr_iterations
+        {
+            res.extend(body_ops.iter().cloned());
+        }
+        Ok(res)
+    }
+}
+
+impl crate::instruction::ToInstruction for Loop
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        let body_instructions = self.body.instructions(bits);
+        let mut res = vec![];
+        for _ in 0..self.nr_iterations
+        {
+            res.extend(body_instructions.iter().cloned());
+        }
+        res
+    }
+}
+
 impl crate::export::Latex for Loop
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -330,7 +383,7 @@ mod tests
         let qasm = gate.open_qasm(&bit_names, &[0]);
         assert_eq!(qasm, Ok(String::new()));
 
-        let body = Composite::from_string("body", "H 0; H 1; CX 0 1").unwrap();
+        let body = Composite::from_string("Body", "H 0; H 1; CX 0 1").unwrap();
         let gate = Loop::new("myloop", 3, body);
         let bit_names = [String::from("qb0"), String::from("qb1")];
         let qasm = gate.open_qasm(&bit_names, &[0, 1]);
@@ -338,12 +391,21 @@ mod tests
 r#"h qb0; h qb1; cx qb0, qb1;
 h qb0; h qb1; cx qb0, qb1;
 h qb0; h qb1; cx qb0, qb1"#)));
+        assert_eq!(gate.open_qasm_definition(), None);
+
+        // Body with a valid OpenQasm name is defined separately
+        let body = Composite::from_string("body", "H 0; H 1; CX 0 1").unwrap();
+        let gate = Loop::new("myloop", 3, body);
+        let qasm = gate.open_qasm(&bit_names, &[0, 1]);
+        assert_eq!(qasm, Ok(String::from("body qb0, qb1;\nbody qb0, qb1;\nbody qb0, qb1")));
+        assert_eq!(gate.open_qasm_definition(),
+            Some(String::from("gate body a0, a1 { h a0; h a1; cx a0, a1; }")));
     }
 
     #[test]
     fn test_conditional_open_qasm()
     {
-        let body = Composite::from_string("body", "H 0; H 1; CX 0 1").unwrap();
+        let body = Composite::from_string("Body", "H 0; H 1; CX 0 1").unwrap();
         let gate = Loop::new("myloop", 3, body);
         let bit_names = [String::from("qb0"), String::from("qb1")];
         let res = gate.conditional_open_qasm("c == 3", &bit_names, &[0, 1]);
@@ -352,6 +414,11 @@ r#"if (c == 3) h qb0; if (c == 3) h qb1; if (c == 3) cx qb0, qb1;
 if (c == 3) h qb0; if (c == 3) h qb1; if (c == 3) cx qb0, qb1;
 if (c == 3) h qb0; if (c == 3) h qb1; if (c == 3) cx qb0, qb1"#);
         assert_eq!(res, Ok(expected));
+
+        let body = Composite::from_string("body", "H 0; H 1; CX 0 1").unwrap();
+        let gate = Loop::new("myloop", 2, body);
+        let res = gate.conditional_open_qasm("c == 3", &bit_names, &[0, 1]);
+        assert_eq!(res, Ok(String::from("if (c == 3) body qb0, qb1;\nif (c == 3) body qb0, qb1")));
     }
 
     #[test]
@@ -87,6 +87,11 @@ impl crate::gates::Gate for Swap
         ]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        Some(crate::gates::permutation_sparsity(&[0, 2, 1, 3]))
+    }
+
     fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
     {
         Self::transform(state);
@@ -108,6 +113,11 @@ impl crate::gates::Gate for Swap
         ops.swap(0, 1);
         Ok(false)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for Swap
@@ -130,6 +140,22 @@ impl crate::export::CQasm for Swap
     }
 }
 
+impl crate::export::TketJson for Swap
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("SWAP", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Swap
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Swap(bits[0], bits[1])]
+    }
+}
+
 impl crate::export::Latex for Swap
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -167,7 +193,7 @@ mod tests
 {
     use super::Swap;
     use crate::export::{LatexExportState, Latex, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -199,6 +225,12 @@ mod tests
         ]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(Swap::new());
+    }
+
     #[test]
     fn test_apply()
     {
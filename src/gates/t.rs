@@ -58,6 +58,14 @@ impl crate::gates::Gate for T
         array![[o, z], [z, x+x*i]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        Some(crate::gates::diagonal_sparsity(&[o, x+x*i]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -75,6 +83,11 @@ impl crate::gates::Gate for T
         let mut slice = state.slice_mut(s![n.., ..]);
         slice *= num_complex::Complex::from_polar(&1.0, &::std::f64::consts::FRAC_PI_4);
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(Tdg::new())
+    }
 }
 
 impl crate::export::OpenQasm for T
@@ -95,6 +108,22 @@ impl crate::export::CQasm for T
     }
 }
 
+impl crate::export::TketJson for T
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("T", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for T
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::T(bits[0])]
+    }
+}
+
 impl crate::export::Latex for T
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -159,6 +188,14 @@ impl crate::gates::Gate for Tdg
         array![[o, z], [z, x-x*i]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+        Some(crate::gates::diagonal_sparsity(&[o, x-x*i]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -176,6 +213,11 @@ impl crate::gates::Gate for Tdg
         let mut slice = state.slice_mut(s![n.., ..]);
         slice *= num_complex::Complex::from_polar(&1.0, &-::std::f64::consts::FRAC_PI_4);
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(T::new())
+    }
 }
 
 impl crate::export::OpenQasm for Tdg
@@ -196,6 +238,22 @@ impl crate::export::CQasm for Tdg
     }
 }
 
+impl crate::export::TketJson for Tdg
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("Tdg", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Tdg
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Tdg(bits[0])]
+    }
+}
+
 impl crate::export::Latex for Tdg
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -220,7 +278,7 @@ impl crate::arithmetic::Square for Tdg
 mod tests
 {
     use super::{T, Tdg};
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
     use crate::arithmetic::Square;
 
@@ -256,6 +314,13 @@ mod tests
         assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, t.conj()]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(T::new());
+        sparse_test(Tdg::new());
+    }
+
     #[test]
     fn test_apply()
     {
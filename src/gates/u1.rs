@@ -66,6 +66,18 @@ impl crate::gates::Gate for U1
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.lambda]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.lambda.bind(store)?;
+        *self = Self::new(self.lambda.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let z = crate::cmatrix::COMPLEX_ZERO;
@@ -74,6 +86,13 @@ impl crate::gates::Gate for U1
         array![[o, z], [z, p]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let p = num_complex::Complex::from_polar(&1.0, &self.lambda.value());
+        Some(crate::gates::diagonal_sparsity(&[o, p]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -82,6 +101,18 @@ impl crate::gates::Gate for U1
         let mut slice = state.slice_mut(s![n..]);
         slice *= num_complex::Complex::from_polar(&1.0, &self.lambda.value());
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        if let crate::gates::Parameter::Direct(lambda) = self.lambda
+        {
+            Box::new(U1::new(-lambda))
+        }
+        else
+        {
+            crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for U1
@@ -103,6 +134,25 @@ impl crate::export::CQasm for U1
     }
 }
 
+impl crate::export::TketJson for U1
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.lambda)
+        ];
+        Ok(vec![crate::export::TketOp::new("U1", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for U1
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::U1(self.lambda.value(), bits[0])]
+    }
+}
+
 impl crate::export::Latex for U1
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -133,7 +183,7 @@ mod tests
 {
     use super::U1;
     use crate::arithmetic::Square;
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
 
     #[test]
@@ -160,6 +210,13 @@ mod tests
         assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, i]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(U1::new(0.0));
+        sparse_test(U1::new(-2.1));
+    }
+
     #[test]
     fn test_apply()
     {
@@ -67,6 +67,19 @@ impl crate::gates::Gate for U2
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.phi, &self.lambda]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.phi.bind(store)?;
+        self.lambda.bind(store)?;
+        *self = Self::new(self.phi.clone(), self.lambda.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let lambda = self.lambda.value();
@@ -77,6 +90,18 @@ impl crate::gates::Gate for U2
                [ num_complex::Complex::from_polar(&x, &phi),
                  num_complex::Complex::from_polar(&x, &(phi+lambda))]]
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        // U2(ϕ, λ) = U3(π/2, ϕ, λ)
+        match (&self.phi, &self.lambda)
+        {
+            (&crate::gates::Parameter::Direct(phi), &crate::gates::Parameter::Direct(lambda)) => {
+                Box::new(crate::gates::U3::new(-::std::f64::consts::FRAC_PI_2, -lambda, -phi))
+            },
+            _ => crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for U2
@@ -99,6 +124,26 @@ impl crate::export::CQasm for U2
     }
 }
 
+impl crate::export::TketJson for U2
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.phi),
+            crate::export::TketOp::param(&self.lambda)
+        ];
+        Ok(vec![crate::export::TketOp::new("U2", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for U2
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::U2(self.phi.value(), self.lambda.value(), bits[0])]
+    }
+}
+
 impl crate::export::Latex for U2
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -71,6 +71,20 @@ impl crate::gates::Gate for U3
         1
     }
 
+    fn parameters(&self) -> Vec<&crate::gates::Parameter>
+    {
+        vec![&self.theta, &self.phi, &self.lambda]
+    }
+
+    fn bind(&mut self, store: &crate::gates::ParameterStore) -> crate::error::Result<()>
+    {
+        self.theta.bind(store)?;
+        self.phi.bind(store)?;
+        self.lambda.bind(store)?;
+        *self = Self::new(self.theta.clone(), self.phi.clone(), self.lambda.clone());
+        Ok(())
+    }
+
     fn matrix(&self) -> crate::cmatrix::CMatrix
     {
         let htheta = 0.5 * self.theta.value();
@@ -82,6 +96,18 @@ impl crate::gates::Gate for U3
                [ num_complex::Complex::from_polar(&s, &phi),
                  num_complex::Complex::from_polar(&c, &(phi+lambda))]]
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        use crate::gates::Parameter::Direct;
+        match (&self.theta, &self.phi, &self.lambda)
+        {
+            (&Direct(theta), &Direct(phi), &Direct(lambda)) => {
+                Box::new(U3::new(-theta, -lambda, -phi))
+            },
+            _ => crate::gates::matrix_adjoint(self)
+        }
+    }
 }
 
 impl crate::export::OpenQasm for U3
@@ -105,6 +131,30 @@ impl crate::export::CQasm for U3
     }
 }
 
+impl crate::export::TketJson for U3
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        let params = vec![
+            crate::export::TketOp::param(&self.theta),
+            crate::export::TketOp::param(&self.phi),
+            crate::export::TketOp::param(&self.lambda)
+        ];
+        Ok(vec![crate::export::TketOp::new("U3", params, bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for U3
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![
+            crate::instruction::Instruction::U3(self.theta.value(), self.phi.value(),
+                self.lambda.value(), bits[0])
+        ]
+    }
+}
+
 impl crate::export::Latex for U3
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -0,0 +1,227 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::cmatrix::CMatrix;
+use crate::gates::Gate;
+
+/// Tolerance used in comparing matrix elements
+const TOLERANCE: f64 = 1.0e-9;
+/// Rotations whose angle is a multiple of `2π/k` for `k` up to this order are
+/// considered to be of finite order
+const MAX_ORDER: usize = 1000;
+/// Maximum length of products of single-qubit gates that are considered
+const MAX_WORD_LENGTH: usize = 3;
+
+/// A single-qubit rotation `cos(θ/2)I - i sin(θ/2)(n·σ)` in `SU(2)`, stored
+/// as the components `(cos(θ/2), sin(θ/2)n)`.
+#[derive(Clone, Copy)]
+struct Rotation
+{
+    c: f64,
+    v: [f64; 3]
+}
+
+impl Rotation
+{
+    /// Convert the 2×2 unitary matrix `m` to a rotation, dropping its global
+    /// phase.
+    fn from_matrix(m: &CMatrix) -> Self
+    {
+        let det = m[[0, 0]] * m[[1, 1]] - m[[0, 1]] * m[[1, 0]];
+        let phase = det.sqrt();
+        let u00 = m[[0, 0]] / phase;
+        let u10 = m[[1, 0]] / phase;
+        Rotation { c: u00.re, v: [-u10.im, u10.re, -u00.im] }
+    }
+
+    /// The product `self·other` of two rotations.
+    fn mul(&self, other: &Rotation) -> Self
+    {
+        // Quaternion multiplication, with i, j, k mapped to -iσ_x, -iσ_y, -iσ_z
+        let (a, b) = (&self.v, &other.v);
+        let cross = [a[1]*b[2] - a[2]*b[1], a[2]*b[0] - a[0]*b[2], a[0]*b[1] - a[1]*b[0]];
+        let dot = a[0]*b[0] + a[1]*b[1] + a[2]*b[2];
+        Rotation
+        {
+            c: self.c * other.c - dot,
+            v: [
+                self.c * b[0] + other.c * a[0] + cross[0],
+                self.c * b[1] + other.c * a[1] + cross[1],
+                self.c * b[2] + other.c * a[2] + cross[2]
+            ]
+        }
+    }
+
+    /// The rotation axis, or `None` if this rotation is the identity (up to a
+    /// global phase).
+    fn axis(&self) -> Option<[f64; 3]>
+    {
+        let s = self.v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if s < TOLERANCE
+        {
+            None
+        }
+        else
+        {
+            Some([self.v[0] / s, self.v[1] / s, self.v[2] / s])
+        }
+    }
+
+    /// Check whether this rotation is of infinite order as a rotation of the
+    /// Bloch sphere, i.e. whether its rotation angle is an irrational multiple
+    /// of `π`. This is approximated by checking that no power up to
+    /// `MAX_ORDER` is the identity.
+    fn has_infinite_order(&self) -> bool
+    {
+        let half_angle = self.c.clamp(-1.0, 1.0).acos();
+        self.axis().is_some()
+            && (1..=MAX_ORDER).all(|k| (k as f64 * half_angle).sin().abs() > TOLERANCE)
+    }
+
+    /// Rotate the vector `n` by this rotation.
+    fn rotate(&self, n: &[f64; 3]) -> [f64; 3]
+    {
+        let conj = Rotation { c: self.c, v: [-self.v[0], -self.v[1], -self.v[2]] };
+        self.mul(&Rotation { c: 0.0, v: *n }).mul(&conj).v
+    }
+}
+
+/// Check whether the single-qubit gates with matrices `matrices` generate a
+/// dense subgroup of `SU(2)` (up to global phases).
+///
+/// The closed subgroups of `SU(2)` are the finite groups, the one-parameter
+/// groups of rotations about a single axis, their normalizers, and `SU(2)`
+/// itself. Hence the generated group is dense when it contains an element
+/// `A` of infinite order, whose powers approximate every rotation about its
+/// axis `n`, together with an element `B` that does not map `n` to `±n`. Both
+/// are searched for among the products of at most `MAX_WORD_LENGTH` gates.
+fn generates_dense_su2(matrices: &[CMatrix]) -> bool
+{
+    let gens: Vec<Rotation> = matrices.iter().map(Rotation::from_matrix).collect();
+    let mut words = gens.clone();
+    let mut last = gens.clone();
+    for _ in 1..MAX_WORD_LENGTH
+    {
+        last = last.iter().flat_map(|w| gens.iter().map(move |g| w.mul(g))).collect();
+        words.extend(last.iter().cloned());
+    }
+
+    words.iter().filter(|a| a.has_infinite_order()).any(|a| {
+        let n = a.axis().unwrap();
+        words.iter().any(|b| {
+            let m = b.rotate(&n);
+            let dot = m[0]*n[0] + m[1]*n[1] + m[2]*n[2];
+            (dot.abs() - 1.0).abs() > TOLERANCE
+        })
+    })
+}
+
+/// Check whether the 4×4 matrix `m` is a tensor product of two single-qubit
+/// operators, i.e. whether it has operator Schmidt rank 1.
+fn is_product_operator(m: &CMatrix) -> bool
+{
+    let mut realigned = CMatrix::zeros((4, 4));
+    for ((i, j), &x) in m.indexed_iter()
+    {
+        let (i0, i1, j0, j1) = (i >> 1, i & 1, j >> 1, j & 1);
+        realigned[[2*i0 + j0, 2*i1 + j1]] = x;
+    }
+    let (_, sigma, _) = crate::linalg::svd(&realigned);
+    sigma[1] < TOLERANCE * sigma[0]
+}
+
+/// Check whether the two-qubit gate with matrix `m` can create entanglement,
+/// i.e. whether it is neither a product of single-qubit gates, nor such a
+/// product followed by a swap of the qubits.
+fn is_entangling(m: &CMatrix) -> bool
+{
+    let o = crate::cmatrix::COMPLEX_ONE;
+    let z = crate::cmatrix::COMPLEX_ZERO;
+    let swap: CMatrix = array![[o, z, z, z], [z, z, o, z], [z, o, z, z], [z, z, z, o]];
+    !is_product_operator(m) && !is_product_operator(&swap.dot(m))
+}
+
+/// Check for universality of a gate set.
+///
+/// Check a sufficient condition for the gates in `gates` to form a universal
+/// gate set, i.e. for products of these gates to approximate any unitary
+/// transformation on any number of qubits to arbitrary precision. This is the
+/// case when the single-qubit gates in the set generate a dense subgroup of
+/// `SU(2)`, and the set contains a two-qubit gate that creates entanglement.
+/// So e.g. `{H, T, CX}` is universal, while the Clifford set `{H, S, CX}` is
+/// not. Gates acting on more than two qubits are not taken into account, and
+/// since rotation angles can only be checked for irrationality numerically,
+/// rotations over a rational multiple of `π` with a very large denominator
+/// are treated as irrational.
+pub fn is_universal_gate_set(gates: &[Box<dyn Gate>]) -> bool
+{
+    let single: Vec<CMatrix> = gates.iter()
+        .filter(|g| g.nr_affected_bits() == 1)
+        .map(|g| g.matrix())
+        .collect();
+    let has_entangler = gates.iter()
+        .filter(|g| g.nr_affected_bits() == 2)
+        .any(|g| is_entangling(&g.matrix()));
+
+    has_entangler && generates_dense_su2(&single)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{generates_dense_su2, is_entangling, is_universal_gate_set};
+    use crate::gates::{Gate, CU1, CX, CZ, H, RX, RZ, S, Swap, T, U3};
+
+    #[test]
+    fn test_generates_dense_su2()
+    {
+        assert!(generates_dense_su2(&[H::new().matrix(), T::new().matrix()]));
+        assert!(generates_dense_su2(&[RX::new(1.0).matrix(), RZ::new(1.0).matrix()]));
+        assert!(!generates_dense_su2(&[H::new().matrix(), S::new().matrix()]));
+        assert!(!generates_dense_su2(&[T::new().matrix(), RZ::new(1.0).matrix()]));
+        assert!(!generates_dense_su2(&[U3::new(0.3, 0.2, 0.1).matrix()]));
+        assert!(!generates_dense_su2(&[]));
+    }
+
+    #[test]
+    fn test_is_entangling()
+    {
+        assert!(is_entangling(&CX::new().matrix()));
+        assert!(is_entangling(&CZ::new().matrix()));
+        assert!(is_entangling(&CU1::new(0.5).matrix()));
+        assert!(!is_entangling(&Swap::new().matrix()));
+        assert!(!is_entangling(&CU1::new(0.0).matrix()));
+        assert!(!is_entangling(&crate::gates::Kron::new(H::new(), T::new()).matrix()));
+    }
+
+    #[test]
+    fn test_is_universal_gate_set()
+    {
+        let gates: Vec<Box<dyn Gate>> = vec![Box::new(H::new()), Box::new(T::new()),
+            Box::new(CX::new())];
+        assert!(is_universal_gate_set(&gates));
+
+        let gates: Vec<Box<dyn Gate>> = vec![Box::new(H::new()), Box::new(S::new()),
+            Box::new(CX::new())];
+        assert!(!is_universal_gate_set(&gates));
+
+        let gates: Vec<Box<dyn Gate>> = vec![Box::new(H::new()), Box::new(T::new()),
+            Box::new(Swap::new())];
+        assert!(!is_universal_gate_set(&gates));
+
+        let gates: Vec<Box<dyn Gate>> = vec![Box::new(RX::new(1.0)), Box::new(RZ::new(1.0)),
+            Box::new(CZ::new())];
+        assert!(is_universal_gate_set(&gates));
+    }
+}
@@ -80,6 +80,11 @@ impl crate::gates::Gate for V
         ops[0] = op;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(Vdg::new())
+    }
 }
 
 impl crate::export::OpenQasm for V
@@ -100,6 +105,22 @@ impl crate::export::CQasm for V
     }
 }
 
+impl crate::export::TketJson for V
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("SX", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for V
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::V(bits[0])]
+    }
+}
+
 impl crate::export::Latex for V
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -179,6 +200,11 @@ impl crate::gates::Gate for Vdg
         ops[0] = op;
         Ok(phase)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(V::new())
+    }
 }
 
 impl crate::export::OpenQasm for Vdg
@@ -199,6 +225,22 @@ impl crate::export::CQasm for Vdg
     }
 }
 
+impl crate::export::TketJson for Vdg
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("SXdg", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Vdg
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Vdg(bits[0])]
+    }
+}
+
 impl crate::export::Latex for Vdg
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -83,6 +83,11 @@ impl crate::gates::Gate for X
         array![[z, o], [o, z]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        Some(crate::gates::permutation_sparsity(&[1, 0]))
+    }
+
     fn apply_slice(&self, state: crate::cmatrix::CVecSliceMut)
     {
         Self::transform(state);
@@ -103,6 +108,11 @@ impl crate::gates::Gate for X
         self.check_nr_bits(ops.len())?;
         Ok(ops[0] == PauliOp::Z || ops[0] == PauliOp::Y)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for X
@@ -123,6 +133,22 @@ impl crate::export::CQasm for X
     }
 }
 
+impl crate::export::TketJson for X
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("X", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for X
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::X(bits[0])]
+    }
+}
+
 impl crate::export::Latex for X
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -149,7 +175,7 @@ impl crate::arithmetic::Square for X
 mod tests
 {
     use crate::export::{LatexExportState, Latex, OpenQasm, CQasm};
-    use crate::gates::{gate_test, Gate, X};
+    use crate::gates::{gate_test, sparse_test, Gate, X};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
 
@@ -180,6 +206,12 @@ mod tests
         gate_test(X::new(), &mut state, &result);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(X::new());
+    }
+
     #[test]
     fn test_open_qasm()
     {
@@ -95,6 +95,11 @@ impl crate::gates::Gate for Y
         self.check_nr_bits(ops.len())?;
         Ok(ops[0] == PauliOp::Z || ops[0] == PauliOp::X)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for Y
@@ -115,6 +120,22 @@ impl crate::export::CQasm for Y
     }
 }
 
+impl crate::export::TketJson for Y
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("Y", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Y
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Y(bits[0])]
+    }
+}
+
 impl crate::export::Latex for Y
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
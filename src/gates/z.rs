@@ -57,6 +57,12 @@ impl crate::gates::Gate for Z
         array![[o, z], [z, -o]]
     }
 
+    fn sparsity(&self) -> Option<Vec<(usize, usize, crate::cmatrix::CNumber)>>
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        Some(crate::gates::diagonal_sparsity(&[o, -o]))
+    }
+
     fn apply_slice(&self, mut state: crate::cmatrix::CVecSliceMut)
     {
         assert!(state.len() % 2 == 0, "Number of rows is not even.");
@@ -83,6 +89,11 @@ impl crate::gates::Gate for Z
         self.check_nr_bits(ops.len())?;
         Ok(ops[0] == PauliOp::X || ops[0] == PauliOp::Y)
     }
+
+    fn adjoint(&self) -> Box<dyn crate::export::CircuitGate>
+    {
+        Box::new(self.clone())
+    }
 }
 
 impl crate::export::OpenQasm for Z
@@ -103,6 +114,22 @@ impl crate::export::CQasm for Z
     }
 }
 
+impl crate::export::TketJson for Z
+{
+    fn tket_ops(&self, bits: &[usize]) -> crate::error::Result<Vec<crate::export::TketOp>>
+    {
+        Ok(vec![crate::export::TketOp::new("Z", vec![], bits)])
+    }
+}
+
+impl crate::instruction::ToInstruction for Z
+{
+    fn instructions(&self, bits: &[usize]) -> Vec<crate::instruction::Instruction>
+    {
+        vec![crate::instruction::Instruction::Z(bits[0])]
+    }
+}
+
 impl crate::export::Latex for Z
 {
     fn latex(&self, bits: &[usize], state: &mut crate::export::LatexExportState)
@@ -129,7 +156,7 @@ impl crate::arithmetic::Square for Z
 mod tests
 {
     use super::Z;
-    use crate::gates::{gate_test, Gate};
+    use crate::gates::{gate_test, sparse_test, Gate};
     use crate::export::{Latex, LatexExportState, OpenQasm, CQasm};
     use crate::arithmetic::Square;
     use crate::stabilizer::PauliOp;
@@ -157,6 +184,12 @@ mod tests
         assert_complex_matrix_eq!(gate.matrix(), array![[o, z], [z, -o]]);
     }
 
+    #[test]
+    fn test_sparsity()
+    {
+        sparse_test(Z::new());
+    }
+
     #[test]
     fn test_apply()
     {
@@ -0,0 +1,275 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::{PauliOp, PauliString};
+
+/// Tolerance in the Hermiticity check and for dropping terms in the Pauli
+/// decomposition of a matrix
+const TOLERANCE: f64 = 1.0e-12;
+/// Maximum number of qubits for which the ground state energy is computed by
+/// diagonalizing the full matrix
+const MAX_DIAGONALIZATION_BITS: usize = 8;
+
+/// Qubit Hamiltonian
+///
+/// Struct `Hamiltonian` describes a Hamiltonian acting on qubits, as a sum of
+/// Pauli strings with real coefficients.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hamiltonian
+{
+    /// The terms in the Hamiltonian, as pairs of coefficient and Pauli string
+    terms: Vec<(f64, PauliString)>
+}
+
+impl Hamiltonian
+{
+    /// Create a new, empty, Hamiltonian.
+    pub fn new() -> Self
+    {
+        Hamiltonian { terms: vec![] }
+    }
+
+    /// Pauli decomposition of a matrix.
+    ///
+    /// Decompose the Hermitian `2`<sup>`n`</sup>`×2`<sup>`n`</sup> matrix `m`
+    /// into a sum of `n`-qubit Pauli strings `P`<sub>`j`</sub>, with
+    /// coefficients `c`<sub>`j`</sub>` = Tr(P`<sub>`j`</sub>`H)/2`<sup>`n`</sup>.
+    /// All `4`<sup>`n`</sup> Pauli strings are tried, and only those with a
+    /// non-zero coefficient are added. A `DimensionMismatch` error is returned
+    /// when `m` is not square, or its size is not a power of two, and a
+    /// `NotHermitian` error when `m` is not Hermitian.
+    pub fn from_matrix(m: &crate::cmatrix::CMatrix) -> crate::error::Result<Self>
+    {
+        let size = m.rows();
+        if m.cols() != size
+        {
+            return Err(crate::error::Error::DimensionMismatch(size, m.cols()));
+        }
+        if !size.is_power_of_two()
+        {
+            return Err(crate::error::Error::DimensionMismatch(size, size.next_power_of_two()));
+        }
+        if !crate::cmatrix::is_hermitian(m, TOLERANCE)
+        {
+            return Err(crate::error::Error::NotHermitian);
+        }
+
+        let nr_bits = size.trailing_zeros() as usize;
+        let mut res = Hamiltonian::new();
+        for idx in 0..1u64 << (2 * nr_bits)
+        {
+            let ops: Vec<PauliOp> = (0..nr_bits)
+                .map(|i| PauliOp::from_bits(idx >> (2 * (nr_bits - i - 1))))
+                .collect();
+            let ps = PauliString::new(&ops);
+            // Tr(PH) = Σ_k P[r(k), k] H[k, r(k)], with r(k) the row of the
+            // only non-zero element in column k of P
+            let trace: crate::cmatrix::CNumber = (0..size).map(|k| {
+                    let (row, phase) = ps.apply_to_basis_state(k);
+                    phase * m[[k, row]]
+                })
+                .sum();
+            let coef = trace.re / size as f64;
+            if coef.abs() > TOLERANCE
+            {
+                res.add_term(coef, ps);
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Add the term `coeff` × `pauli` to this Hamiltonian.
+    pub fn add_term(&mut self, coeff: f64, pauli: PauliString)
+    {
+        self.terms.push((coeff, pauli));
+    }
+
+    /// The terms in this Hamiltonian, as pairs of coefficient and Pauli string
+    pub fn to_pauli_strings(&self) -> &[(f64, PauliString)]
+    {
+        &self.terms
+    }
+
+    /// The number of qubits this Hamiltonian acts on, i.e. the length of the
+    /// longest Pauli string in its terms
+    pub fn nr_bits(&self) -> usize
+    {
+        self.terms.iter().map(|(_, ps)| ps.nr_bits()).max().unwrap_or(0)
+    }
+
+    /// Matrix of this Hamiltonian.
+    ///
+    /// Return the matrix of this Hamiltonian when acting on `nr_bits` qubits.
+    /// Pauli strings on fewer qubits act on the first qubits, with the
+    /// identity on the remaining qubits. This function panics if `nr_bits`
+    /// is smaller than `self.nr_bits()`.
+    pub fn matrix(&self, nr_bits: usize) -> crate::cmatrix::CMatrix
+    {
+        assert!(nr_bits >= self.nr_bits(), "The Hamiltonian acts on more than {} qubits", nr_bits);
+
+        let mut res = crate::cmatrix::CMatrix::zeros((1 << nr_bits, 1 << nr_bits));
+        for (coef, ps) in self.terms.iter()
+        {
+            let pad = crate::cmatrix::CMatrix::eye(1 << (nr_bits - ps.nr_bits()));
+            res += &(crate::cmatrix::kron_mat(&ps.matrix(), &pad) * *coef);
+        }
+        res
+    }
+
+    /// Ground state energy.
+    ///
+    /// Compute the lowest eigenvalue of this Hamiltonian, by diagonalizing
+    /// its full matrix. As the size of the matrix grows exponentially with
+    /// the number of qubits, this is only done for Hamiltonians acting on at
+    /// most `MAX_DIAGONALIZATION_BITS` qubits; for larger Hamiltonians an
+    /// `InvalidNrBits` error is returned.
+    pub fn ground_state_energy(&self) -> crate::error::Result<f64>
+    {
+        let nr_bits = self.nr_bits();
+        if nr_bits > MAX_DIAGONALIZATION_BITS
+        {
+            return Err(crate::error::Error::InvalidNrBits(nr_bits, MAX_DIAGONALIZATION_BITS,
+                String::from("ground state energy")));
+        }
+
+        Ok(crate::linalg::eigvalsh(&self.matrix(nr_bits))[0])
+    }
+}
+
+impl ::std::ops::Add for Hamiltonian
+{
+    type Output = Hamiltonian;
+
+    /// The sum of two Hamiltonians, containing the terms of both.
+    fn add(mut self, other: Hamiltonian) -> Hamiltonian
+    {
+        self.terms.extend(other.terms);
+        self
+    }
+}
+
+impl ::std::ops::Mul<f64> for Hamiltonian
+{
+    type Output = Hamiltonian;
+
+    /// This Hamiltonian, with all coefficients multiplied by `factor`.
+    fn mul(mut self, factor: f64) -> Hamiltonian
+    {
+        for (coef, _) in self.terms.iter_mut()
+        {
+            *coef *= factor;
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Hamiltonian;
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    #[test]
+    fn test_add_term()
+    {
+        let mut h = Hamiltonian::new();
+        assert!(h.to_pauli_strings().is_empty());
+
+        h.add_term(0.5, PauliString::new(&[PauliOp::Z, PauliOp::I]));
+        h.add_term(-1.5, PauliString::new(&[PauliOp::X, PauliOp::X]));
+        assert_eq!(h.to_pauli_strings(), &[
+            (0.5, PauliString::new(&[PauliOp::Z, PauliOp::I])),
+            (-1.5, PauliString::new(&[PauliOp::X, PauliOp::X]))
+        ]);
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        let mut h = Hamiltonian::new();
+        assert_eq!(h.nr_bits(), 0);
+        h.add_term(0.5, PauliString::new(&[PauliOp::Z]));
+        h.add_term(-1.0, PauliString::new(&[PauliOp::X, PauliOp::X]));
+        assert_eq!(h.nr_bits(), 2);
+        assert_complex_matrix_eq!(h.matrix(2), array![
+            [0.5*o,     z,      z,    -o],
+            [    z, 0.5*o,     -o,     z],
+            [    z,    -o, -0.5*o,     z],
+            [   -o,     z,      z, -0.5*o]
+        ]);
+    }
+
+    #[test]
+    fn test_add_mul()
+    {
+        let mut h0 = Hamiltonian::new();
+        h0.add_term(0.5, PauliString::new(&[PauliOp::Z]));
+        let mut h1 = Hamiltonian::new();
+        h1.add_term(-1.0, PauliString::new(&[PauliOp::X, PauliOp::X]));
+
+        let h = (h0 + h1) * 2.0;
+        assert_eq!(h.to_pauli_strings(), &[
+            (1.0, PauliString::new(&[PauliOp::Z])),
+            (-2.0, PauliString::new(&[PauliOp::X, PauliOp::X]))
+        ]);
+        assert_eq!(Hamiltonian::new() + Hamiltonian::new(), Hamiltonian::new());
+    }
+
+    #[test]
+    fn test_from_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let mut h = Hamiltonian::new();
+        h.add_term(0.5, PauliString::new(&[PauliOp::Z, PauliOp::I]));
+        h.add_term(-1.5, PauliString::new(&[PauliOp::X, PauliOp::Y]));
+        h.add_term(0.25, PauliString::new(&[PauliOp::I, PauliOp::I]));
+        let decomposed = Hamiltonian::from_matrix(&h.matrix(2)).unwrap();
+        assert_eq!(decomposed.to_pauli_strings().len(), 3);
+        assert_complex_matrix_eq!(decomposed.matrix(2), h.matrix(2));
+
+        let h = Hamiltonian::from_matrix(&array![[z, -i], [i, z]]).unwrap();
+        assert_eq!(h.to_pauli_strings(), &[(1.0, PauliString::new(&[PauliOp::Y]))]);
+
+        assert_eq!(Hamiltonian::from_matrix(&array![[z, o], [z, z]]),
+            Err(crate::error::Error::NotHermitian));
+        assert_eq!(Hamiltonian::from_matrix(&crate::cmatrix::CMatrix::eye(3)),
+            Err(crate::error::Error::DimensionMismatch(3, 4)));
+        assert_eq!(Hamiltonian::from_matrix(&crate::cmatrix::CMatrix::zeros((2, 4))),
+            Err(crate::error::Error::DimensionMismatch(2, 4)));
+    }
+
+    #[test]
+    fn test_ground_state_energy()
+    {
+        // Transverse field Ising model on two qubits: E_0 = -√(J² + 4h²) = -√2
+        let mut h = Hamiltonian::new();
+        h.add_term(-1.0, PauliString::new(&[PauliOp::Z, PauliOp::Z]));
+        h.add_term(-0.5, PauliString::new(&[PauliOp::X, PauliOp::I]));
+        h.add_term(-0.5, PauliString::new(&[PauliOp::I, PauliOp::X]));
+        assert!((h.ground_state_energy().unwrap() + 2.0f64.sqrt()).abs() < 1.0e-10);
+
+        assert_eq!(Hamiltonian::new().ground_state_energy(), Ok(0.0));
+
+        let mut h = Hamiltonian::new();
+        h.add_term(1.0, PauliString::identity(9));
+        assert!(h.ground_state_energy().is_err());
+    }
+}
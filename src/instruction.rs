@@ -0,0 +1,415 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::cmatrix::CNumber;
+
+/// A single circuit instruction
+///
+/// Enum `Instruction` describes a single operation in a quantum circuit, using
+/// only plain data types. Gate instructions hold their parameters (if any),
+/// followed by the qubits they operate on; for controlled gates, the control
+/// qubits come first. Gates that have no dedicated instruction are stored as
+/// their unitary matrix in `Custom`, with the matrix given as a list of rows.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction
+{
+    /// Identity gate
+    I(usize),
+    /// Hadamard gate
+    H(usize),
+    /// Pauli `X` gate
+    X(usize),
+    /// Pauli `Y` gate
+    Y(usize),
+    /// Pauli `Z` gate
+    Z(usize),
+    /// Phase gate `S`
+    S(usize),
+    /// Conjugate of the phase gate `S`
+    Sdg(usize),
+    /// `T` gate
+    T(usize),
+    /// Conjugate of the `T` gate
+    Tdg(usize),
+    /// Square root of `X`
+    V(usize),
+    /// Conjugate of the square root of `X`
+    Vdg(usize),
+    /// Rotation around the `x` axis over angle `θ`
+    RX(f64, usize),
+    /// Rotation around the `y` axis over angle `θ`
+    RY(f64, usize),
+    /// Rotation around the `z` axis over angle `λ`
+    RZ(f64, usize),
+    /// Phase gate `U`<sub>`1`</sub>`(λ)`
+    U1(f64, usize),
+    /// Gate `U`<sub>`2`</sub>`(ϕ, λ)`
+    U2(f64, f64, usize),
+    /// Gate `U`<sub>`3`</sub>`(θ, ϕ, λ)`
+    U3(f64, f64, f64, usize),
+    /// Controlled `X` gate
+    CX(usize, usize),
+    /// Controlled `Y` gate
+    CY(usize, usize),
+    /// Controlled `Z` gate
+    CZ(usize, usize),
+    /// Controlled Hadamard gate
+    CH(usize, usize),
+    /// Controlled `R`<sub>`X`</sub> gate
+    CRX(f64, usize, usize),
+    /// Controlled `R`<sub>`Y`</sub> gate
+    CRY(f64, usize, usize),
+    /// Controlled `R`<sub>`Z`</sub> gate
+    CRZ(f64, usize, usize),
+    /// Controlled `S` gate
+    CS(usize, usize),
+    /// Controlled `S`<sup>`†`</sup> gate
+    CSdg(usize, usize),
+    /// Controlled `T` gate
+    CT(usize, usize),
+    /// Controlled `T`<sup>`†`</sup> gate
+    CTdg(usize, usize),
+    /// Controlled `U`<sub>`1`</sub> gate
+    CU1(f64, usize, usize),
+    /// Controlled `U`<sub>`2`</sub> gate
+    CU2(f64, f64, usize, usize),
+    /// Controlled `U`<sub>`3`</sub> gate
+    CU3(f64, f64, f64, usize, usize),
+    /// Controlled `V` gate
+    CV(usize, usize),
+    /// Controlled `V`<sup>`†`</sup> gate
+    CVdg(usize, usize),
+    /// Doubly controlled `R`<sub>`X`</sub> gate
+    CCRX(f64, usize, usize, usize),
+    /// Doubly controlled `R`<sub>`Y`</sub> gate
+    CCRY(f64, usize, usize, usize),
+    /// Doubly controlled `R`<sub>`Z`</sub> gate
+    CCRZ(f64, usize, usize, usize),
+    /// Toffoli gate
+    CCX(usize, usize, usize),
+    /// Doubly controlled `Z` gate
+    CCZ(usize, usize, usize),
+    /// Swap gate
+    Swap(usize, usize),
+    /// Fredkin gate
+    CSwap(usize, usize, usize),
+    /// Arbitrary gate, described by the rows of its unitary matrix, operating
+    /// on the given qubits
+    Custom(Vec<Vec<CNumber>>, Vec<usize>),
+    /// Gate instruction, that is only executed when the classical bits in the
+    /// first field form the target word in the second field
+    ConditionalGate(Vec<usize>, u64, Box<Instruction>),
+    /// Reset a qubit to |0⟩
+    Reset(usize),
+    /// Reset a qubit to |0⟩ (`false`) or |1⟩ (`true`)
+    ResetTo(usize, bool),
+    /// Reset the quantum state to |00...0⟩
+    ResetAll,
+    /// Measure a qubit in a certain basis into a classical bit
+    Measure(usize, usize, crate::circuit::Basis),
+    /// Measure all qubits into the given classical bits
+    MeasureAll(Vec<usize>, crate::circuit::Basis),
+    /// Measure a single qubit in a certain basis without affecting state
+    Peek(usize, usize, crate::circuit::Basis),
+    /// Measure all qubits in a certain basis without affecting state
+    PeekAll(Vec<usize>, crate::circuit::Basis),
+    /// Measure the qubits in the first field into the classical bits in the
+    /// second field without affecting state
+    PeekPartial(Vec<usize>, Vec<usize>),
+    /// Prevent gate reordering on the associated bits across the barrier
+    Barrier(Vec<usize>),
+    /// Apply a noise channel, described by its Kraus operators
+    Noise(Vec<Vec<Vec<CNumber>>>, Vec<usize>),
+    /// Compute the sign of the expectation value of a Pauli string, with one
+    /// operator for each qubit, without affecting state
+    ExpectationMeasure(Vec<crate::stabilizer::PauliOp>, usize)
+}
+
+/// Convert a matrix to a list of rows
+fn matrix_rows(matrix: &crate::cmatrix::CMatrix) -> Vec<Vec<CNumber>>
+{
+    matrix.genrows().into_iter().map(|row| row.to_vec()).collect()
+}
+
+/// Convert a list of rows to a matrix.
+///
+/// Convert the list of rows `rows` into a square matrix, for a gate or noise
+/// channel described by `desc` operating on `nr_bits` qubits. If the
+/// dimensions of the matrix do not match the number of qubits, an
+/// `InvalidNrBits` error is returned.
+fn rows_matrix(rows: &[Vec<CNumber>], nr_bits: usize, desc: &str)
+    -> crate::error::Result<crate::cmatrix::CMatrix>
+{
+    let n = rows.len();
+    if n != 1 << nr_bits || rows.iter().any(|row| row.len() != n)
+    {
+        Err(crate::error::Error::InvalidNrBits(nr_bits, n.trailing_zeros() as usize,
+            String::from(desc)))
+    }
+    else
+    {
+        Ok(crate::cmatrix::CMatrix::from_shape_fn((n, n), |(i, j)| rows[i][j]))
+    }
+}
+
+/// Noise channel defined by an explicit set of Kraus operators
+struct KrausChannel
+{
+    /// The number of qubits affected by the channel
+    nr_bits: usize,
+    /// The Kraus operators describing the channel
+    ops: Vec<crate::cmatrix::CMatrix>
+}
+
+impl crate::gates::NoiseChannel for KrausChannel
+{
+    fn description(&self) -> &str
+    {
+        "Kraus"
+    }
+
+    fn nr_affected_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    fn kraus_operators(&self) -> Vec<crate::cmatrix::CMatrix>
+    {
+        self.ops.clone()
+    }
+}
+
+/// Add gate `gate` on qubits `bits` to `circuit`, only applied when the
+/// `condition` (if any) is satisfied.
+fn add_gate<G>(circuit: &mut crate::circuit::Circuit, condition: Option<(&[usize], u64)>,
+    gate: G, bits: &[usize]) -> crate::error::Result<()>
+where G: 'static + crate::export::CircuitGate
+{
+    match condition
+    {
+        Some((control, target)) => circuit.add_conditional_gate(control, target, gate, bits),
+        None                    => circuit.add_gate(gate, bits)
+    }
+}
+
+impl Instruction
+{
+    /// Convert the Kraus operators `ops` of a noise channel on qubits `bits`
+    /// to a noise instruction.
+    pub(crate) fn noise(ops: &[crate::cmatrix::CMatrix], bits: &[usize]) -> Self
+    {
+        Instruction::Noise(ops.iter().map(matrix_rows).collect(), bits.to_vec())
+    }
+
+    /// Add this instruction to a circuit.
+    ///
+    /// Append the operation described by this instruction to circuit
+    /// `circuit`, applying a gate instruction only when `condition` is
+    /// satisfied, if given. An error is returned when the qubits or classical
+    /// bits are not valid for the circuit, when a custom matrix does not
+    /// match the number of qubits, or when a non-gate instruction is used
+    /// conditionally.
+    pub(crate) fn add_to_circuit(&self, circuit: &mut crate::circuit::Circuit,
+        condition: Option<(&[usize], u64)>) -> crate::error::Result<()>
+    {
+        use crate::gates::*;
+
+        match *self
+        {
+            Instruction::I(q)    => add_gate(circuit, condition, I::new(), &[q]),
+            Instruction::H(q)    => add_gate(circuit, condition, H::new(), &[q]),
+            Instruction::X(q)    => add_gate(circuit, condition, X::new(), &[q]),
+            Instruction::Y(q)    => add_gate(circuit, condition, Y::new(), &[q]),
+            Instruction::Z(q)    => add_gate(circuit, condition, Z::new(), &[q]),
+            Instruction::S(q)    => add_gate(circuit, condition, S::new(), &[q]),
+            Instruction::Sdg(q)  => add_gate(circuit, condition, Sdg::new(), &[q]),
+            Instruction::T(q)    => add_gate(circuit, condition, T::new(), &[q]),
+            Instruction::Tdg(q)  => add_gate(circuit, condition, Tdg::new(), &[q]),
+            Instruction::V(q)    => add_gate(circuit, condition, V::new(), &[q]),
+            Instruction::Vdg(q)  => add_gate(circuit, condition, Vdg::new(), &[q]),
+            Instruction::RX(theta, q) => add_gate(circuit, condition, RX::new(theta), &[q]),
+            Instruction::RY(theta, q) => add_gate(circuit, condition, RY::new(theta), &[q]),
+            Instruction::RZ(lambda, q) => add_gate(circuit, condition, RZ::new(lambda), &[q]),
+            Instruction::U1(lambda, q) => add_gate(circuit, condition, U1::new(lambda), &[q]),
+            Instruction::U2(phi, lambda, q) => {
+                add_gate(circuit, condition, U2::new(phi, lambda), &[q])
+            },
+            Instruction::U3(theta, phi, lambda, q) => {
+                add_gate(circuit, condition, U3::new(theta, phi, lambda), &[q])
+            },
+            Instruction::CX(c, t)   => add_gate(circuit, condition, CX::new(), &[c, t]),
+            Instruction::CY(c, t)   => add_gate(circuit, condition, CY::new(), &[c, t]),
+            Instruction::CZ(c, t)   => add_gate(circuit, condition, CZ::new(), &[c, t]),
+            Instruction::CH(c, t)   => add_gate(circuit, condition, CH::new(), &[c, t]),
+            Instruction::CRX(theta, c, t) => add_gate(circuit, condition, CRX::new(theta), &[c, t]),
+            Instruction::CRY(theta, c, t) => add_gate(circuit, condition, CRY::new(theta), &[c, t]),
+            Instruction::CRZ(lambda, c, t) => {
+                add_gate(circuit, condition, CRZ::new(lambda), &[c, t])
+            },
+            Instruction::CS(c, t)   => add_gate(circuit, condition, CS::new(), &[c, t]),
+            Instruction::CSdg(c, t) => add_gate(circuit, condition, CSdg::new(), &[c, t]),
+            Instruction::CT(c, t)   => add_gate(circuit, condition, CT::new(), &[c, t]),
+            Instruction::CTdg(c, t) => add_gate(circuit, condition, CTdg::new(), &[c, t]),
+            Instruction::CU1(lambda, c, t) => {
+                add_gate(circuit, condition, CU1::new(lambda), &[c, t])
+            },
+            Instruction::CU2(phi, lambda, c, t) => {
+                add_gate(circuit, condition, CU2::new(phi, lambda), &[c, t])
+            },
+            Instruction::CU3(theta, phi, lambda, c, t) => {
+                add_gate(circuit, condition, CU3::new(theta, phi, lambda), &[c, t])
+            },
+            Instruction::CV(c, t)   => add_gate(circuit, condition, CV::new(), &[c, t]),
+            Instruction::CVdg(c, t) => add_gate(circuit, condition, CVdg::new(), &[c, t]),
+            Instruction::CCRX(theta, c0, c1, t) => {
+                add_gate(circuit, condition, CCRX::new(theta), &[c0, c1, t])
+            },
+            Instruction::CCRY(theta, c0, c1, t) => {
+                add_gate(circuit, condition, CCRY::new(theta), &[c0, c1, t])
+            },
+            Instruction::CCRZ(lambda, c0, c1, t) => {
+                add_gate(circuit, condition, CCRZ::new(lambda), &[c0, c1, t])
+            },
+            Instruction::CCX(c0, c1, t) => add_gate(circuit, condition, CCX::new(), &[c0, c1, t]),
+            Instruction::CCZ(c0, c1, t) => add_gate(circuit, condition, CCZ::new(), &[c0, c1, t]),
+            Instruction::Swap(q0, q1) => add_gate(circuit, condition, Swap::new(), &[q0, q1]),
+            Instruction::CSwap(c, q0, q1) => add_gate(circuit, condition, CSwap::new(), &[c, q0, q1]),
+            Instruction::Custom(ref rows, ref bits) => {
+                let matrix = rows_matrix(rows, bits.len(), "U")?;
+                add_gate(circuit, condition, Custom::new("U", matrix), bits)
+            },
+            Instruction::ConditionalGate(ref control, target, ref instruction) => {
+                if condition.is_some()
+                {
+                    Err(crate::error::Error::NotAGate(format!("{:?}", self)))
+                }
+                else
+                {
+                    instruction.add_to_circuit(circuit, Some((control, target)))
+                }
+            },
+            _ if condition.is_some() => {
+                Err(crate::error::Error::NotAGate(format!("{:?}", self)))
+            },
+            Instruction::Reset(q) => circuit.reset(q),
+            Instruction::ResetTo(q, value) => circuit.reset_to(q, value),
+            Instruction::ResetAll => {
+                circuit.reset_all();
+                Ok(())
+            },
+            Instruction::Measure(q, c, basis) => circuit.measure_basis(q, c, basis),
+            Instruction::MeasureAll(ref cbits, basis) => circuit.measure_all_basis(cbits, basis),
+            Instruction::Peek(q, c, basis) => circuit.peek_basis(q, c, basis),
+            Instruction::PeekAll(ref cbits, basis) => circuit.peek_all_basis(cbits, basis),
+            Instruction::PeekPartial(ref qbits, ref cbits) => circuit.peek_partial(qbits, cbits),
+            Instruction::Barrier(ref qbits) => circuit.barrier(qbits),
+            Instruction::Noise(ref ops, ref qbits) => {
+                let ops = ops.iter()
+                    .map(|rows| rows_matrix(rows, qbits.len(), "Kraus"))
+                    .collect::<crate::error::Result<Vec<_>>>()?;
+                let channel = KrausChannel { nr_bits: qbits.len(), ops: ops };
+                circuit.add_noise(channel, qbits)
+            },
+            Instruction::ExpectationMeasure(ref ops, cbit) => {
+                let observable = crate::stabilizer::PauliString::new(ops);
+                circuit.measure_expectation(&observable, cbit)
+            }
+        }
+    }
+}
+
+/// Trait for gates that can be converted to instructions.
+pub trait ToInstruction: crate::gates::Gate
+{
+    /// Instruction representation
+    ///
+    /// Return the list of instructions that implement this gate when it
+    /// operates on qubits `bits`. The default implementation returns a single
+    /// `Custom` instruction holding the gate's matrix.
+    fn instructions(&self, bits: &[usize]) -> Vec<Instruction>
+    {
+        vec![Instruction::Custom(matrix_rows(&self.matrix()), bits.to_vec())]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{Instruction, ToInstruction};
+    use crate::circuit::{Basis, Circuit};
+    use crate::gates::{CCX, CH, Composite, CU3, Custom, H, Kron, Loop, RX, X};
+
+    #[test]
+    fn test_instructions()
+    {
+        assert_eq!(H::new().instructions(&[2]), vec![Instruction::H(2)]);
+        assert_eq!(RX::new(0.5).instructions(&[1]), vec![Instruction::RX(0.5, 1)]);
+        assert_eq!(CH::new().instructions(&[1, 0]), vec![Instruction::CH(1, 0)]);
+        assert_eq!(CU3::new(0.1, 0.2, 0.3).instructions(&[0, 1]),
+            vec![Instruction::CU3(0.1, 0.2, 0.3, 0, 1)]);
+        assert_eq!(CCX::new().instructions(&[2, 0, 1]), vec![Instruction::CCX(2, 0, 1)]);
+        assert_eq!(Kron::new(H::new(), X::new()).instructions(&[1, 0]),
+            vec![Instruction::H(1), Instruction::X(0)]);
+        let gate = Composite::from_string("G", "H 0; CX 0 1").unwrap();
+        assert_eq!(gate.instructions(&[2, 1]), vec![Instruction::H(2), Instruction::CX(2, 1)]);
+        let gate = Loop::new("L", 2, Composite::from_string("G", "RZ(0.5) 0").unwrap());
+        assert_eq!(gate.instructions(&[1]),
+            vec![Instruction::RZ(0.5, 1), Instruction::RZ(0.5, 1)]);
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let gate = Custom::new("U", array![[z, o], [o, z]]);
+        assert_eq!(gate.instructions(&[3]),
+            vec![Instruction::Custom(vec![vec![z, o], vec![o, z]], vec![3])]);
+    }
+
+    #[test]
+    fn test_reference_parameter()
+    {
+        let cell = ::std::rc::Rc::new(::std::cell::RefCell::new(0.25));
+        let gate = RX::new(crate::gates::Parameter::from_refcell(&cell, "theta"));
+        *cell.borrow_mut() = 0.75;
+        assert_eq!(gate.instructions(&[0]), vec![Instruction::RX(0.75, 0)]);
+    }
+
+    #[test]
+    fn test_invalid()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(Instruction::CX(0, 2).add_to_circuit(&mut circuit, None),
+            Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(Instruction::Measure(0, 2, Basis::Z).add_to_circuit(&mut circuit, None),
+            Err(crate::error::Error::InvalidCBit(2)));
+        assert!(matches!(Instruction::Custom(vec![vec![o]], vec![0]).add_to_circuit(&mut circuit, None),
+            Err(crate::error::Error::InvalidNrBits(1, 0, _))));
+        let instruction = Instruction::ConditionalGate(vec![0], 1, Box::new(Instruction::Reset(0)));
+        assert!(matches!(instruction.add_to_circuit(&mut circuit, None),
+            Err(crate::error::Error::NotAGate(_))));
+        assert!(circuit.ops().is_empty());
+    }
+
+    #[test]
+    fn test_noise()
+    {
+        let channel = crate::gates::Depolarizing::new(0.1);
+        let ops = crate::gates::NoiseChannel::kraus_operators(&channel);
+        let instruction = Instruction::noise(&ops, &[1]);
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(instruction.add_to_circuit(&mut circuit, None), Ok(()));
+        assert_eq!(circuit.to_instructions(), vec![instruction]);
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal JSON parser, used for importing circuits from other frameworks.
+
+/// A JSON value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value
+{
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Object members, in the order in which they occur
+    Object(Vec<(String, Value)>)
+}
+
+impl Value
+{
+    /// Return the member with name `key` if this is an object, or `None`
+    /// otherwise or when there is no such member.
+    pub fn get(&self, key: &str) -> Option<&Value>
+    {
+        match *self
+        {
+            Value::Object(ref members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    /// Return the number if this is a number, or `None` otherwise
+    pub fn as_f64(&self) -> Option<f64>
+    {
+        match *self
+        {
+            Value::Number(x) => Some(x),
+            _ => None
+        }
+    }
+
+    /// Return the string if this is a string, or `None` otherwise
+    pub fn as_str(&self) -> Option<&str>
+    {
+        match *self
+        {
+            Value::String(ref s) => Some(s),
+            _ => None
+        }
+    }
+
+    /// Return the boolean if this is a boolean, or `None` otherwise
+    pub fn as_bool(&self) -> Option<bool>
+    {
+        match *self
+        {
+            Value::Bool(b) => Some(b),
+            _ => None
+        }
+    }
+
+    /// Return the elements if this is an array, or `None` otherwise
+    pub fn as_array(&self) -> Option<&[Value]>
+    {
+        match *self
+        {
+            Value::Array(ref elems) => Some(elems),
+            _ => None
+        }
+    }
+}
+
+/// Recursive descent parser for JSON text
+struct Parser<'a>
+{
+    /// The text being parsed
+    text: &'a str,
+    /// The current position in `text`
+    pos: usize
+}
+
+impl<'a> Parser<'a>
+{
+    /// Create an error for the text at the current position
+    fn error(&self) -> crate::error::ParseError
+    {
+        let rest: String = self.text[self.pos..].chars().take(20).collect();
+        crate::error::ParseError::InvalidJson(rest)
+    }
+
+    /// Return the next character without consuming it
+    fn peek(&self) -> Option<char>
+    {
+        self.text[self.pos..].chars().next()
+    }
+
+    /// Consume and return the next character
+    fn next(&mut self) -> Option<char>
+    {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self)
+    {
+        while let Some(c) = self.peek()
+        {
+            if !c.is_whitespace()
+            {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Consume character `c`, or return an error if the next character is
+    /// not `c`
+    fn expect(&mut self, c: char) -> crate::error::ParseResult<()>
+    {
+        self.skip_whitespace();
+        if self.peek() == Some(c)
+        {
+            self.pos += c.len_utf8();
+            Ok(())
+        }
+        else
+        {
+            Err(self.error())
+        }
+    }
+
+    /// Consume literal `word`, and return `value`
+    fn literal(&mut self, word: &str, value: Value) -> crate::error::ParseResult<Value>
+    {
+        if self.text[self.pos..].starts_with(word)
+        {
+            self.pos += word.len();
+            Ok(value)
+        }
+        else
+        {
+            Err(self.error())
+        }
+    }
+
+    fn value(&mut self) -> crate::error::ParseResult<Value>
+    {
+        self.skip_whitespace();
+        match self.peek()
+        {
+            Some('n') => self.literal("null", Value::Null),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error())
+        }
+    }
+
+    fn number(&mut self) -> crate::error::ParseResult<Value>
+    {
+        let start = self.pos;
+        while let Some(c) = self.peek()
+        {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c))
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.text[start..self.pos].parse().map(Value::Number).map_err(|_| {
+            self.pos = start;
+            self.error()
+        })
+    }
+
+    fn string(&mut self) -> crate::error::ParseResult<String>
+    {
+        self.expect('"')?;
+        let mut res = String::new();
+        loop
+        {
+            match self.next()
+            {
+                Some('"') => return Ok(res),
+                Some('\\') => {
+                    let c = match self.next()
+                        {
+                            Some('"') => '"',
+                            Some('\\') => '\\',
+                            Some('/') => '/',
+                            Some('b') => '\u{8}',
+                            Some('f') => '\u{c}',
+                            Some('n') => '\n',
+                            Some('r') => '\r',
+                            Some('t') => '\t',
+                            Some('u') => {
+                                let code = self.text.get(self.pos..self.pos+4)
+                                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                    .ok_or_else(|| self.error())?;
+                                self.pos += 4;
+                                ::std::char::from_u32(code).unwrap_or('\u{fffd}')
+                            },
+                            _ => return Err(self.error())
+                        };
+                    res.push(c);
+                },
+                Some(c) => res.push(c),
+                None => return Err(self.error())
+            }
+        }
+    }
+
+    /// Parse a comma separated list of items, closed by `close`, calling
+    /// `item` for every element
+    fn list<F>(&mut self, close: char, mut item: F) -> crate::error::ParseResult<()>
+    where F: FnMut(&mut Self) -> crate::error::ParseResult<()>
+    {
+        self.skip_whitespace();
+        if self.peek() == Some(close)
+        {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop
+        {
+            item(self)?;
+            self.skip_whitespace();
+            match self.next()
+            {
+                Some(',') => { },
+                Some(c) if c == close => return Ok(()),
+                _ => return Err(self.error())
+            }
+        }
+    }
+
+    fn array(&mut self) -> crate::error::ParseResult<Value>
+    {
+        self.expect('[')?;
+        let mut elems = vec![];
+        self.list(']', |parser| {
+            elems.push(parser.value()?);
+            Ok(())
+        })?;
+        Ok(Value::Array(elems))
+    }
+
+    fn object(&mut self) -> crate::error::ParseResult<Value>
+    {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.list('}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+            parser.expect(':')?;
+            members.push((key, parser.value()?));
+            Ok(())
+        })?;
+        Ok(Value::Object(members))
+    }
+}
+
+/// Parse JSON text.
+///
+/// Parse `text` as a single JSON value. On failure, an `InvalidJson` error
+/// is returned, holding the text where parsing failed.
+pub fn parse(text: &str) -> crate::error::ParseResult<Value>
+{
+    let mut parser = Parser { text: text, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len()
+    {
+        Err(crate::error::ParseError::TrailingText(String::from(&text[parser.pos..])))
+    }
+    else
+    {
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{parse, Value};
+
+    #[test]
+    fn test_parse()
+    {
+        let value = parse(r#" { "a": [1, -2.5e1, true, null], "b": {}, "c": "x\"é\n" } "#)
+            .unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Array(vec![
+            Value::Number(1.0), Value::Number(-25.0), Value::Bool(true), Value::Null
+        ])));
+        assert_eq!(value.get("b"), Some(&Value::Object(vec![])));
+        assert_eq!(value.get("c").and_then(Value::as_str), Some("x\"é\n"));
+        assert_eq!(value.get("d"), None);
+        assert_eq!(parse("[]"), Ok(Value::Array(vec![])));
+
+        assert_eq!(parse("[1, 2"), Err(crate::error::ParseError::InvalidJson(String::new())));
+        assert_eq!(parse("{\"a\" 1}"), Err(crate::error::ParseError::InvalidJson(String::from("1}"))));
+        assert_eq!(parse("[1] x"), Err(crate::error::ParseError::TrailingText(String::from("x"))));
+        assert!(parse("nul").is_err());
+        assert!(parse("\"abc").is_err());
+        assert!(parse("-").is_err());
+    }
+}
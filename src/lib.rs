@@ -200,10 +200,14 @@
 //! The discerning reader may have notices the `#[derive(ExportGate)]` statement
 //! on the custom gate in the listing above. This makes the type use the default
 //! implementations of the export functions for a gate. Currently, there are
-//! three traits for exporting a gate:
+//! five traits for exporting a gate:
 //! - [OpenQasm](export/trait.OpenQasm.html) for exporting a gate to OpenQasm code.
 //! - [CQasm](export/trait.CQasm.html) for exporting a gate to c-Qasm code.
 //! - [Latex](export/trait.Latex.html) for exporting a gate to LaTeX.
+//! - [TketJson](export/trait.TketJson.html) for exporting a gate to TKET's
+//!   JSON format.
+//! - [ToInstruction](instruction/trait.ToInstruction.html) for converting a
+//!   gate to a list of [Instruction](instruction/enum.Instruction.html)s.
 //!
 //! You can use the default implementation for each of these traits by deriving
 //! them, e.g.
@@ -221,12 +225,18 @@
 //!     # fn matrix(&self) -> q1tsim::cmatrix::CMatrix { q1tsim::cmatrix::CMatrix::zeros((0,0)) }
 //! }
 //! ```
-//! The default implementations for OpenQasm and CQasm simply return an error,
+//! The default implementations for OpenQasm, CQasm and TketJson simply return an error,
 //! since there is no way [^no_qasm] to know how to encode a custom gate
 //! in these formats. The default implementation for the LaTeX export simply draws
-//! a rectangular box with the gate description inside. As seen before, if you
+//! a rectangular box with the gate description inside, and the default
+//! conversion to instructions stores the gate's matrix. As seen before, if you
 //! want to use default definitions for all export traits, derive from `ExportGate`.
 //!
+//! Export to Q# through the [QSharp](export/trait.QSharp.html) trait needs no
+//! separate implementation: it is derived from a gate's instructions, so that
+//! any gate whose instructions map onto the Q# standard library can be
+//! exported.
+//!
 //! Note that to use a gate type in a circuit, it must be exportable, so an
 //! implementation for the export traits must be defined for your custom type,
 //! either through deriving or by providing your own implementation.
@@ -284,18 +294,52 @@
 
 #[macro_use] pub mod cmatrix;
 #[macro_use] pub mod gates;
+pub mod algorithms;
+pub mod annealing;
 pub mod arithmetic;
+pub mod bosonic;
+pub mod bqc;
+pub mod branched;
 pub mod circuit;
+pub mod compiled;
+pub mod decompose;
+pub mod density;
+pub mod encoding;
 pub mod error;
+pub mod fault_tolerant;
+pub mod floquet;
 pub mod ffi;
 pub mod export;
 pub mod expression;
+pub mod hamiltonian;
+pub mod instruction;
+pub mod lint;
+pub mod measures;
+pub mod mitigation;
+pub mod mps;
+pub mod observable;
 pub mod permutation;
+pub mod qram;
 pub mod qustate;
+pub mod random;
+pub mod rewrite_rules;
+pub mod spin;
 pub mod vectorstate;
 pub mod stabilizer;
+pub mod superdense;
+pub mod swaptest;
+pub mod tn;
+pub mod topology;
+pub mod volume;
+pub mod walk;
 
+mod cirq;
 mod idhash;
+mod json;
+mod linalg;
+mod parse;
+#[cfg(feature = "render")] mod render;
+#[cfg(feature = "serde")] mod serialize;
 mod support;
 #[cfg(test)] mod stats;
 
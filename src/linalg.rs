@@ -0,0 +1,484 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Return the conjugate transpose of matrix `m`, in standard layout
+pub fn adjoint(m: &crate::cmatrix::CMatrix) -> crate::cmatrix::CMatrix
+{
+    crate::cmatrix::CMatrix::from_shape_fn((m.cols(), m.rows()), |(i, j)| m[[j, i]].conj())
+}
+
+/// Jacobi rotation.
+///
+/// Compute the unitary 2×2 matrix `G` such that `G`<sup>`†`</sup>`HG` is
+/// diagonal, for Hermitian matrix `H = [[a, b], [b*, d]]`. The result is
+/// returned as the tuple `(G`<sub>`00`</sub>`, G`<sub>`01`</sub>`,
+/// G`<sub>`10`</sub>`, G`<sub>`11`</sub>`)`.
+fn jacobi_rotation(a: f64, b: crate::cmatrix::CNumber, d: f64)
+    -> (crate::cmatrix::CNumber, crate::cmatrix::CNumber,
+        crate::cmatrix::CNumber, crate::cmatrix::CNumber)
+{
+    // Remove the phase of the off-diagonal element, and do a real Jacobi
+    // rotation on the resulting symmetric matrix.
+    let nb = b.norm();
+    let e = b / nb;
+    let theta = (d - a) / (2.0 * nb);
+    let t = theta.signum() / (theta.abs() + (theta*theta + 1.0).sqrt());
+    let c = 1.0 / (t*t + 1.0).sqrt();
+    let s = t * c;
+
+    (
+        crate::cmatrix::CNumber::new(c, 0.0),
+        crate::cmatrix::CNumber::new(s, 0.0),
+        -s * e.conj(),
+        c * e.conj()
+    )
+}
+
+/// Singular value decomposition.
+///
+/// Compute the singular value decomposition `M = UΣV`<sup>`†`</sup> of
+/// matrix `m` with `n` columns, using one-sided Jacobi rotations. The result
+/// is the tuple `(U, σ, V)`, where `U` has `n` columns, `V` is a unitary
+/// `n×n` matrix, and the singular values in `σ` are sorted in descending
+/// order. Columns of `U` corresponding to a zero singular value are zero.
+pub fn svd(m: &crate::cmatrix::CMatrix)
+    -> (crate::cmatrix::CMatrix, Vec<f64>, crate::cmatrix::CMatrix)
+{
+    let n = m.cols();
+    let mut w = m.clone();
+    let mut v = crate::cmatrix::CMatrix::eye(n);
+
+    for _ in 0..100
+    {
+        let mut rotated = false;
+        for p in 0..n
+        {
+            for q in p+1..n
+            {
+                let alpha = w.column(p).iter().map(|c| c.norm_sqr()).sum::<f64>();
+                let beta = w.column(q).iter().map(|c| c.norm_sqr()).sum::<f64>();
+                let gamma = w.column(p).iter().zip(w.column(q).iter())
+                    .map(|(x, y)| x.conj() * y)
+                    .sum::<crate::cmatrix::CNumber>();
+                if gamma.norm() <= 1.0e-15 * (alpha * beta).sqrt() || gamma.norm() == 0.0
+                {
+                    continue;
+                }
+
+                rotated = true;
+                let (gpp, gpq, gqp, gqq) = jacobi_rotation(alpha, gamma, beta);
+                for mat in [&mut w, &mut v].iter_mut()
+                {
+                    for k in 0..mat.rows()
+                    {
+                        let (xp, xq) = (mat[[k, p]], mat[[k, q]]);
+                        mat[[k, p]] = xp * gpp + xq * gqp;
+                        mat[[k, q]] = xp * gpq + xq * gqq;
+                    }
+                }
+            }
+        }
+
+        if !rotated
+        {
+            break;
+        }
+    }
+
+    let norms: Vec<f64> = (0..n)
+        .map(|k| w.column(k).iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt())
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| norms[j].partial_cmp(&norms[i]).unwrap());
+
+    let mut u = crate::cmatrix::CMatrix::zeros((m.rows(), n));
+    let mut vs = crate::cmatrix::CMatrix::zeros((n, n));
+    let mut sigma = vec![];
+    for (dst, &src) in order.iter().enumerate()
+    {
+        if norms[src] > 0.0
+        {
+            u.column_mut(dst).assign(&w.column(src).mapv(|c| c / norms[src]));
+        }
+        vs.column_mut(dst).assign(&v.column(src));
+        sigma.push(norms[src]);
+    }
+
+    (u, sigma, vs)
+}
+
+/// QR decomposition.
+///
+/// Compute the decomposition `M = QR` of square matrix `m` using modified
+/// Gram-Schmidt orthogonalization, where `Q` is unitary, and `R` is upper
+/// triangular with a real, non-negative diagonal. The result is returned as
+/// the tuple `(Q, R)`. Matrix `m` should have full rank.
+pub fn qr(m: &crate::cmatrix::CMatrix) -> (crate::cmatrix::CMatrix, crate::cmatrix::CMatrix)
+{
+    let n = m.cols();
+    let mut q = m.clone();
+    let mut r = crate::cmatrix::CMatrix::zeros((n, n));
+    for k in 0..n
+    {
+        for j in 0..k
+        {
+            let rjk = q.column(j).iter().zip(q.column(k).iter())
+                .map(|(x, y)| x.conj() * y)
+                .sum::<crate::cmatrix::CNumber>();
+            let qj = q.column(j).to_owned();
+            q.column_mut(k).scaled_add(-rjk, &qj);
+            r[[j, k]] = rjk;
+        }
+
+        let norm = q.column(k).iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+        q.column_mut(k).mapv_inplace(|c| c / norm);
+        r[[k, k]] = crate::cmatrix::CNumber::new(norm, 0.0);
+    }
+
+    (q, r)
+}
+
+/// Matrix exponential.
+///
+/// Compute `exp(M)` for square matrix `m`, using scaling and squaring with a
+/// truncated Taylor series.
+pub fn expm(m: &crate::cmatrix::CMatrix) -> crate::cmatrix::CMatrix
+{
+    let n = m.rows();
+    let norm = m.genrows().into_iter()
+        .map(|row| row.iter().map(|c| c.norm()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let nr_squarings = if norm > 0.5 { (norm / 0.5).log2().ceil() as i32 } else { 0 };
+    let a = m / crate::cmatrix::CNumber::new(2.0f64.powi(nr_squarings), 0.0);
+
+    let mut res = crate::cmatrix::CMatrix::eye(n);
+    let mut term = crate::cmatrix::CMatrix::eye(n);
+    for k in 1..20
+    {
+        term = term.dot(&a) / crate::cmatrix::CNumber::new(k as f64, 0.0);
+        res += &term;
+    }
+
+    for _ in 0..nr_squarings
+    {
+        res = res.dot(&res);
+    }
+
+    res
+}
+
+/// Eigenvalues of a Hermitian matrix.
+///
+/// Compute the eigenvalues of Hermitian matrix `m`, sorted in ascending order.
+/// The matrix is shifted by a multiple `c` of the identity, with `c` the
+/// maximum absolute row sum of `m`, to make it positive semi-definite, so that
+/// its eigenvalues are the singular values of `m + cI`, minus `c`.
+pub fn eigvalsh(m: &crate::cmatrix::CMatrix) -> Vec<f64>
+{
+    let shift = m.genrows().into_iter()
+        .map(|row| row.iter().map(|c| c.norm()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let shifted = m + &(crate::cmatrix::CMatrix::eye(m.rows()) * shift);
+    let (_, sigma, _) = svd(&shifted);
+    sigma.iter().rev().map(|s| s - shift).collect()
+}
+
+/// Eigendecomposition of a Hermitian matrix.
+///
+/// Compute the eigenvalues and eigenvectors of Hermitian matrix `m`, using
+/// two-sided Jacobi rotations. The result is the tuple `(λ, V)`, where the
+/// eigenvalues in `λ` are sorted in ascending order, and column `k` of unitary
+/// matrix `V` holds the eigenvector for eigenvalue `λ`<sub>`k`</sub>.
+pub fn eigh(m: &crate::cmatrix::CMatrix) -> (Vec<f64>, crate::cmatrix::CMatrix)
+{
+    let n = m.rows();
+    let mut a = m.clone();
+    let mut v = crate::cmatrix::CMatrix::eye(n);
+
+    for _ in 0..100
+    {
+        let mut rotated = false;
+        for p in 0..n
+        {
+            for q in p+1..n
+            {
+                let b = a[[p, q]];
+                if b.norm() <= 1.0e-15 * (a[[p, p]].norm() + a[[q, q]].norm()) || b.norm() == 0.0
+                {
+                    continue;
+                }
+
+                rotated = true;
+                let (gpp, gpq, gqp, gqq) = jacobi_rotation(a[[p, p]].re, b, a[[q, q]].re);
+                for mat in [&mut a, &mut v].iter_mut()
+                {
+                    for k in 0..n
+                    {
+                        let (xp, xq) = (mat[[k, p]], mat[[k, q]]);
+                        mat[[k, p]] = xp * gpp + xq * gqp;
+                        mat[[k, q]] = xp * gpq + xq * gqq;
+                    }
+                }
+                for k in 0..n
+                {
+                    let (xp, xq) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = gpp.conj() * xp + gqp.conj() * xq;
+                    a[[q, k]] = gpq.conj() * xp + gqq.conj() * xq;
+                }
+            }
+        }
+
+        if !rotated
+        {
+            break;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| a[[i, i]].re.partial_cmp(&a[[j, j]].re).unwrap());
+
+    let lambda = order.iter().map(|&k| a[[k, k]].re).collect();
+    let mut vs = crate::cmatrix::CMatrix::zeros((n, n));
+    for (dst, &src) in order.iter().enumerate()
+    {
+        vs.column_mut(dst).assign(&v.column(src));
+    }
+
+    (lambda, vs)
+}
+
+/// Determinant of a square matrix.
+///
+/// Compute the determinant of square matrix `m`, using Gaussian elimination
+/// with partial pivoting.
+pub fn determinant(m: &crate::cmatrix::CMatrix) -> crate::cmatrix::CNumber
+{
+    let n = m.rows();
+    let mut a = m.clone();
+    let mut det = crate::cmatrix::COMPLEX_ONE;
+    for k in 0..n
+    {
+        let pivot = (k..n).max_by(|&i, &j| a[[i, k]].norm().partial_cmp(&a[[j, k]].norm()).unwrap())
+            .unwrap();
+        if a[[pivot, k]].norm() == 0.0
+        {
+            return crate::cmatrix::COMPLEX_ZERO;
+        }
+        if pivot != k
+        {
+            for j in 0..n
+            {
+                a.swap([k, j], [pivot, j]);
+            }
+            det = -det;
+        }
+        det *= a[[k, k]];
+        for i in k+1..n
+        {
+            let f = a[[i, k]] / a[[k, k]];
+            for j in k..n
+            {
+                let x = a[[k, j]];
+                a[[i, j]] -= f * x;
+            }
+        }
+    }
+    det
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{adjoint, determinant, eigh, eigvalsh, expm, qr, svd};
+
+    #[test]
+    fn test_adjoint()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![[o, i, z], [2.0*o, z, -i]];
+        assert_complex_matrix_eq!(&adjoint(&m), &array![[o, 2.0*o], [-i, z], [z, i]]);
+    }
+
+    #[test]
+    fn test_svd()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![
+            [2.0*o,   o-i,     z],
+            [o+i,    -o,     0.5*i],
+            [z,     -0.5*i,  3.0*o],
+            [0.3*o,   z,       i]
+        ];
+        let (u, sigma, v) = svd(&m);
+        assert_eq!(sigma.len(), 3);
+        assert!(sigma[0] >= sigma[1] && sigma[1] >= sigma[2]);
+
+        let vv = adjoint(&v).dot(&v);
+        assert!((vv - crate::cmatrix::CMatrix::eye(3)).iter().all(|c| c.norm() < 1.0e-12));
+        let uu = adjoint(&u).dot(&u);
+        assert!((uu - crate::cmatrix::CMatrix::eye(3)).iter().all(|c| c.norm() < 1.0e-12));
+
+        let mut s = crate::cmatrix::CMatrix::zeros((3, 3));
+        for (k, &sk) in sigma.iter().enumerate()
+        {
+            s[[k, k]] = crate::cmatrix::CNumber::new(sk, 0.0);
+        }
+        let rec = u.dot(&s).dot(&adjoint(&v));
+        assert!((rec - &m).iter().all(|c| c.norm() < 1.0e-12));
+    }
+
+    #[test]
+    fn test_svd_rank_deficient()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![[o, i], [2.0*o, 2.0*i]];
+        let (u, sigma, v) = svd(&m);
+        assert!((sigma[0] - 10.0f64.sqrt()).abs() < 1.0e-12);
+        assert!(sigma[1] < 1.0e-12);
+
+        let rec = u.column(0).insert_axis(ndarray::Axis(1))
+            .dot(&adjoint(&v).row(0).insert_axis(ndarray::Axis(0))) * sigma[0];
+        assert!((rec - &m).iter().all(|c| c.norm() < 1.0e-12));
+    }
+
+    #[test]
+    fn test_qr()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![
+            [2.0*o,  o-i,    z],
+            [o+i,   -o,      0.5*i],
+            [z,     -0.5*i,  3.0*o]
+        ];
+        let (q, r) = qr(&m);
+
+        let qq = adjoint(&q).dot(&q);
+        assert!((qq - crate::cmatrix::CMatrix::eye(3)).iter().all(|c| c.norm() < 1.0e-12));
+        for j in 0..3
+        {
+            assert!(r[[j, j]].re >= 0.0 && r[[j, j]].im == 0.0);
+            for k in 0..j
+            {
+                assert_eq!(r[[j, k]], z);
+            }
+        }
+        assert!((q.dot(&r) - &m).iter().all(|c| c.norm() < 1.0e-12));
+    }
+
+    #[test]
+    fn test_eigvalsh()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let ev = eigvalsh(&array![[o, -i], [i, o]]);
+        assert_eq!(ev.len(), 2);
+        assert!(ev[0].abs() < 1.0e-12 && (ev[1] - 2.0).abs() < 1.0e-12);
+
+        let ev = eigvalsh(&array![[-3.0*o, z, z], [z, 2.0*o, o], [z, o, 2.0*o]]);
+        assert_eq!(ev.len(), 3);
+        for (&x, &e) in ev.iter().zip([-3.0, 1.0, 3.0].iter())
+        {
+            assert!((x - e).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_eigh()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![
+            [2.0*o,  o-i,    z,      0.3*o],
+            [o+i,   -o,      0.5*i,  z],
+            [z,     -0.5*i,  3.0*o,  i],
+            [0.3*o,  z,     -i,      2.0*o]
+        ];
+        let (lambda, v) = eigh(&m);
+        assert_eq!(lambda.len(), 4);
+        assert!(lambda.windows(2).all(|w| w[0] <= w[1]));
+        for (&x, &e) in lambda.iter().zip(eigvalsh(&m).iter())
+        {
+            assert!((x - e).abs() < 1.0e-12);
+        }
+
+        let vv = adjoint(&v).dot(&v);
+        assert!((vv - crate::cmatrix::CMatrix::eye(4)).iter().all(|c| c.norm() < 1.0e-12));
+        let mut d = crate::cmatrix::CMatrix::zeros((4, 4));
+        for (k, &lk) in lambda.iter().enumerate()
+        {
+            d[[k, k]] = crate::cmatrix::CNumber::new(lk, 0.0);
+        }
+        assert!((v.dot(&d).dot(&adjoint(&v)) - &m).iter().all(|c| c.norm() < 1.0e-12));
+
+        // Degenerate eigenvalues, zero diagonal
+        let (lambda, v) = eigh(&array![[z, o, z], [o, z, z], [z, z, o]]);
+        assert!((lambda[0] + 1.0).abs() < 1.0e-12);
+        assert!((lambda[1] - 1.0).abs() < 1.0e-12 && (lambda[2] - 1.0).abs() < 1.0e-12);
+        assert!((v[[0, 0]] + v[[1, 0]]).norm() < 1.0e-12 && v[[2, 0]].norm() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_expm()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = crate::cmatrix::CMatrix::zeros((3, 3));
+        assert_complex_matrix_eq!(&expm(&m), &crate::cmatrix::CMatrix::eye(3));
+
+        // exp(-iθX) = cos(θ) I - i sin(θ) X
+        let theta = 2.3;
+        let m = array![[z, -i*theta], [-i*theta, z]];
+        let (c, s) = (theta.cos(), theta.sin());
+        assert_complex_matrix_eq!(&expm(&m), &array![[c*o, -i*s], [-i*s, c*o]], 1.0e-12);
+
+        let m = array![[o, o], [z, o]] * 3.0;
+        let e3 = 3.0f64.exp();
+        assert_complex_matrix_eq!(&expm(&m), &array![[e3*o, 3.0*e3*o], [z, e3*o]], 1.0e-10);
+    }
+
+    #[test]
+    fn test_determinant()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = array![[z, 2.0*o, z], [i, o, z], [z, o+i, 3.0*o]];
+        assert!((determinant(&m) + 6.0*i).norm() < 1.0e-12);
+
+        let m = array![[o, i], [i, -o]];
+        assert_eq!(determinant(&m), z);
+
+        // The determinant of a unitary has unit norm
+        let m = crate::linalg::expm(&array![[z, o+i], [-o+i, 2.0*i]]);
+        assert!((determinant(&m).norm() - 1.0).abs() < 1.0e-12);
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::circuit::{Basis, CircuitOp};
+use crate::gates::Gate;
+
+/// Warning about a possible mistake in a circuit
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintWarning
+{
+    /// Description of the problem
+    pub message: String,
+    /// Index of the operation in the circuit that triggered the warning. For
+    /// warnings about the circuit as a whole, this is the number of
+    /// operations in the circuit.
+    pub op_index: usize
+}
+
+impl LintWarning
+{
+    /// Create a new warning with message `message` for operation `op_index`
+    fn new(message: String, op_index: usize) -> Self
+    {
+        LintWarning { message: message, op_index: op_index }
+    }
+}
+
+impl ::std::fmt::Display for LintWarning
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        write!(f, "Operation {}: {}", self.op_index, self.message)
+    }
+}
+
+/// Return the classical bits an operation writes to or reads from
+fn op_cbits(op: &CircuitOp) -> Vec<usize>
+{
+    match *op
+    {
+        CircuitOp::ConditionalGate(ref control, _, _, _) => control.clone(),
+        CircuitOp::Measure(_, cbit, _) | CircuitOp::Peek(_, cbit, _)
+            | CircuitOp::ExpectationMeasure(_, cbit) => vec![cbit],
+        CircuitOp::MeasureAll(ref cbits, _) | CircuitOp::PeekAll(ref cbits, _)
+            | CircuitOp::PeekPartial(_, ref cbits) => cbits.clone(),
+        _ => vec![]
+    }
+}
+
+/// Return the qubits measured in the `Z` basis in an operation
+fn z_measured_qbits(op: &CircuitOp, nr_qbits: usize) -> Vec<usize>
+{
+    match *op
+    {
+        CircuitOp::Measure(qbit, _, Basis::Z) | CircuitOp::Peek(qbit, _, Basis::Z) => vec![qbit],
+        CircuitOp::MeasureAll(_, Basis::Z) | CircuitOp::PeekAll(_, Basis::Z) => (0..nr_qbits).collect(),
+        CircuitOp::PeekPartial(ref qbits, _) => qbits.clone(),
+        _ => vec![]
+    }
+}
+
+/// Check whether applying `gate` twice yields the identity, up to a global
+/// phase
+fn is_self_inverse(gate: &dyn Gate) -> bool
+{
+    let m = gate.matrix();
+    let mm = m.dot(&m);
+    let phase = mm[[0, 0]];
+    (phase.norm() - 1.0).abs() < 1.0e-10
+        && mm.indexed_iter().all(|((i, j), &x)| {
+            let expected = if i == j { phase } else { crate::cmatrix::COMPLEX_ZERO };
+            (x - expected).norm() < 1.0e-10
+        })
+}
+
+/// Check a circuit for common mistakes.
+///
+/// Check circuit `circuit` for operations that are likely not what the user
+/// intended, or that will fail when the circuit is executed or exported. The
+/// checks performed are:
+/// * operations on qubits that are not in the circuit,
+/// * operations on classical bits that are not in the circuit,
+/// * measurements in the `Z` basis of qubits that have not been acted upon by
+///   any gate since the start of the circuit or the last reset, which
+///   always yield the same result,
+/// * qubits that are never acted upon by any gate,
+/// * conditional gates without control bits, which are either always or never
+///   applied,
+/// * self-inverse gates that are applied twice in succession on the same
+///   qubits, and so cancel out.
+///
+/// The result is a list of warnings, ordered by the index of the operation
+/// that triggered them. An empty list means no problems were found.
+pub fn lint_circuit(circuit: &crate::circuit::Circuit) -> Vec<LintWarning>
+{
+    lint_ops(circuit.nr_qbits(), circuit.nr_cbits(), circuit.ops())
+}
+
+/// Check the operations `ops` in a circuit with `nr_qbits` qubits and
+/// `nr_cbits` classical bits for common mistakes.
+fn lint_ops(nr_qbits: usize, nr_cbits: usize, ops: &[CircuitOp]) -> Vec<LintWarning>
+{
+    let mut warnings = vec![];
+    // Known basis state of each qubit, if no gate acted on it yet
+    let mut basis_state = vec![Some(false); nr_qbits];
+    let mut used = vec![false; nr_qbits];
+    // Index of the last operation acting on each qubit
+    let mut last_op: Vec<Option<usize>> = vec![None; nr_qbits];
+
+    for (idx, op) in ops.iter().enumerate()
+    {
+        let bits = op.qbits();
+        if let Some(&bit) = bits.and_then(|bits| bits.iter().find(|&&b| b >= nr_qbits))
+        {
+            warnings.push(LintWarning::new(format!(
+                "Operation acts on qubit {}, but the circuit has only {} qubits", bit, nr_qbits
+            ), idx));
+            continue;
+        }
+        if let Some(&bit) = op_cbits(op).iter().find(|&&b| b >= nr_cbits)
+        {
+            warnings.push(LintWarning::new(format!(
+                "Operation uses classical bit {}, but the circuit has only {} classical bits",
+                bit, nr_cbits
+            ), idx));
+        }
+
+        for qbit in z_measured_qbits(op, nr_qbits)
+        {
+            if let Some(value) = basis_state[qbit]
+            {
+                warnings.push(LintWarning::new(format!(
+                    "Qubit {} is measured before any gate acts on it, so the result is always {}",
+                    qbit, value as u8
+                ), idx));
+            }
+        }
+
+        match *op
+        {
+            CircuitOp::Gate(ref gate, ref bits) => {
+                if let Some(prev) = last_op[bits[0]]
+                {
+                    if let CircuitOp::Gate(ref prev_gate, ref prev_bits) = ops[prev]
+                    {
+                        if prev_bits == bits
+                            && bits.iter().all(|&b| last_op[b] == Some(prev))
+                            && prev_gate.description() == gate.description()
+                            && is_self_inverse(gate.as_gate())
+                        {
+                            warnings.push(LintWarning::new(format!(
+                                "Gate {} is applied twice in succession on the same qubits, and cancels out",
+                                gate.description()
+                            ), idx));
+                        }
+                    }
+                }
+            },
+            CircuitOp::ConditionalGate(ref control, target, ref gate, _) if control.is_empty() => {
+                let when = if target == 0 { "always" } else { "never" };
+                warnings.push(LintWarning::new(format!(
+                    "Conditional gate {} has no control bits, and is {} applied",
+                    gate.description(), when
+                ), idx));
+            },
+            _ => { }
+        }
+
+        let touched: Vec<usize> = match bits
+            {
+                Some(bits) => bits.to_vec(),
+                None => (0..nr_qbits).collect()
+            };
+        for &bit in touched.iter()
+        {
+            match *op
+            {
+                CircuitOp::Gate(_, _) | CircuitOp::ConditionalGate(_, _, _, _) => {
+                    used[bit] = true;
+                    basis_state[bit] = None;
+                },
+                CircuitOp::Noise(_, _) => {
+                    basis_state[bit] = None;
+                },
+                CircuitOp::Reset(_) | CircuitOp::ResetAll => {
+                    basis_state[bit] = Some(false);
+                },
+                CircuitOp::ResetTo(_, value) => {
+                    basis_state[bit] = Some(value);
+                },
+                _ => { }
+            }
+            last_op[bit] = Some(idx);
+        }
+    }
+
+    for bit in (0..nr_qbits).filter(|&b| !used[b])
+    {
+        warnings.push(LintWarning::new(format!(
+            "Qubit {} is not acted upon by any gate", bit
+        ), ops.len()));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{lint_circuit, lint_ops, LintWarning};
+    use crate::circuit::Circuit;
+
+    #[test]
+    fn test_clean()
+    {
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.measure(1, 1), Ok(()));
+        assert_eq!(lint_circuit(&circuit), vec![]);
+    }
+
+    #[test]
+    fn test_measure_before_gate()
+    {
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure_x(1, 1), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.reset_to(2, true), Ok(()));
+        assert_eq!(circuit.measure(2, 2), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(lint_circuit(&circuit), vec![
+            LintWarning::new(String::from(
+                "Qubit 0 is measured before any gate acts on it, so the result is always 0"), 0),
+            LintWarning::new(String::from(
+                "Qubit 2 is measured before any gate acts on it, so the result is always 1"), 6)
+        ]);
+    }
+
+    #[test]
+    fn test_unused_qubits()
+    {
+        let mut circuit = Circuit::new(4, 1);
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.reset(3), Ok(()));
+        let warnings = lint_circuit(&circuit);
+        assert_eq!(warnings, vec![
+            LintWarning::new(String::from("Qubit 0 is not acted upon by any gate"), 2),
+            LintWarning::new(String::from("Qubit 2 is not acted upon by any gate"), 2),
+            LintWarning::new(String::from("Qubit 3 is not acted upon by any gate"), 2)
+        ]);
+        assert_eq!(format!("{}", warnings[0]),
+            String::from("Operation 2: Qubit 0 is not acted upon by any gate"));
+    }
+
+    #[test]
+    fn test_empty_control()
+    {
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.add_conditional_gate(&[], 0, crate::gates::X::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[], 1, crate::gates::X::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0], 1, crate::gates::X::new(), &[0]), Ok(()));
+        assert_eq!(lint_circuit(&circuit), vec![
+            LintWarning::new(String::from(
+                "Conditional gate X has no control bits, and is always applied"), 0),
+            LintWarning::new(String::from(
+                "Conditional gate X has no control bits, and is never applied"), 1)
+        ]);
+    }
+
+    #[test]
+    fn test_cancelling_gates()
+    {
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.cx(1, 0), Ok(()));
+        assert_eq!(circuit.s(2), Ok(()));
+        assert_eq!(circuit.s(2), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.barrier(&[2]), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(lint_circuit(&circuit), vec![
+            LintWarning::new(String::from(
+                "Gate H is applied twice in succession on the same qubits, and cancels out"), 2),
+            LintWarning::new(String::from(
+                "Gate CX is applied twice in succession on the same qubits, and cancels out"), 4)
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_bits()
+    {
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.cx(1, 2), Ok(()));
+        assert_eq!(circuit.measure(0, 2), Ok(()));
+        assert_eq!(circuit.measure(1, 1), Ok(()));
+        assert_eq!(lint_circuit(&circuit), vec![]);
+
+        // The circuit API refuses invalid bits, so check the operations as if
+        // they were part of a smaller circuit
+        assert_eq!(lint_ops(2, 2, circuit.ops()), vec![
+            LintWarning::new(String::from(
+                "Operation acts on qubit 2, but the circuit has only 2 qubits"), 2),
+            LintWarning::new(String::from(
+                "Operation uses classical bit 2, but the circuit has only 2 classical bits"), 3)
+        ]);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures of quantum states
+//!
+//! This module provides functions quantifying properties of quantum states,
+//! like how mixed a state described by a density matrix is, and how close two
+//! states are to each other.
+
+/// Purity of a density matrix.
+///
+/// Compute the purity `Tr(ρ²)` of density matrix `rho`. For a Hermitian
+/// matrix, this equals `Σ`<sub>`ij`</sub>` |ρ`<sub>`ij`</sub>`|²`, the squared
+/// Frobenius norm of `rho`. The purity ranges from `1/d` for the maximally
+/// mixed state in dimension `d`, to 1 for a pure state.
+pub fn purity(rho: &crate::cmatrix::CMatrix) -> f64
+{
+    rho.iter().map(|x| x.norm_sqr()).sum()
+}
+
+/// Purity of a pure state.
+///
+/// Compute the purity `Tr(ρ²)` of the density matrix `ρ = |ψ⟩⟨ψ|` for the
+/// state vector `psi`, which is `⟨ψ|ψ⟩²`. For a normalized state, this is
+/// always 1.
+pub fn statevector_purity(psi: &crate::cmatrix::CVector) -> f64
+{
+    let norm_sqr: f64 = psi.iter().map(|x| x.norm_sqr()).sum();
+    norm_sqr * norm_sqr
+}
+
+/// Linear entropy of a density matrix.
+///
+/// Compute the linear entropy `1 - Tr(ρ²)` of density matrix `rho`. This is
+/// zero for a pure state, and can be used as a cheaper alternative to the Von
+/// Neumann entropy when only a measure of the mixedness of a state is needed.
+pub fn linear_entropy(rho: &crate::cmatrix::CMatrix) -> f64
+{
+    1.0 - purity(rho)
+}
+
+/// Square root of a positive semi-definite matrix.
+///
+/// Compute `√M` for the Hermitian, positive semi-definite matrix `m`, from its
+/// singular value decomposition `M = VΣV`<sup>`†`</sup>.
+fn sqrt_psd(m: &crate::cmatrix::CMatrix) -> crate::cmatrix::CMatrix
+{
+    let (_, sigma, v) = crate::linalg::svd(m);
+    let mut scaled = v.clone();
+    for (k, s) in sigma.iter().enumerate()
+    {
+        let f = s.sqrt();
+        scaled.column_mut(k).mapv_inplace(|x| x * f);
+    }
+    scaled.dot(&crate::linalg::adjoint(&v))
+}
+
+/// Check that square matrices `m0` and `m1` have the same dimension.
+fn check_dimensions(m0: &crate::cmatrix::CMatrix, m1: &crate::cmatrix::CMatrix)
+    -> crate::error::Result<()>
+{
+    if m0.rows() != m1.rows()
+    {
+        Err(crate::error::Error::DimensionMismatch(m0.rows(), m1.rows()))
+    }
+    else
+    {
+        Ok(())
+    }
+}
+
+/// Fidelity of two pure states.
+///
+/// Compute the fidelity `|⟨ψ|ϕ⟩|²` between the normalized state vectors `psi`
+/// and `phi`. A `DimensionMismatch` error is returned when the lengths of the
+/// vectors differ.
+pub fn statevector_fidelity(psi: &crate::cmatrix::CVector, phi: &crate::cmatrix::CVector)
+    -> crate::error::Result<f64>
+{
+    if psi.len() != phi.len()
+    {
+        return Err(crate::error::Error::DimensionMismatch(psi.len(), phi.len()));
+    }
+
+    let overlap: crate::cmatrix::CNumber = psi.iter().zip(phi.iter())
+        .map(|(x, y)| x.conj() * y)
+        .sum();
+    Ok(overlap.norm_sqr())
+}
+
+/// Fidelity of two density matrices.
+///
+/// Compute the Uhlmann fidelity `(Tr √(√ρ σ √ρ))²` between density matrices
+/// `rho` and `sigma`. The trace is the sum of the singular values of
+/// `√ρ√σ`, which is how it is computed here. For pure states this reduces to
+/// `|⟨ψ|ϕ⟩|²`. A `DimensionMismatch` error is returned when the dimensions of
+/// the matrices differ.
+pub fn density_matrix_fidelity(rho: &crate::cmatrix::CMatrix, sigma: &crate::cmatrix::CMatrix)
+    -> crate::error::Result<f64>
+{
+    check_dimensions(rho, sigma)?;
+    let (_, s, _) = crate::linalg::svd(&sqrt_psd(rho).dot(&sqrt_psd(sigma)));
+    let trace: f64 = s.iter().sum();
+    Ok(trace * trace)
+}
+
+/// Trace distance between two density matrices.
+///
+/// Compute the trace distance `½Tr|ρ - σ|` between density matrices `rho` and
+/// `sigma`, which is half the sum of the absolute values of the eigenvalues of
+/// `ρ - σ`. The distance ranges from 0 for equal states, to 1 for states with
+/// orthogonal support. A `DimensionMismatch` error is returned when the
+/// dimensions of the matrices differ.
+pub fn trace_distance(rho: &crate::cmatrix::CMatrix, sigma: &crate::cmatrix::CMatrix)
+    -> crate::error::Result<f64>
+{
+    check_dimensions(rho, sigma)?;
+    // For a Hermitian matrix, the singular values are the absolute values of
+    // the eigenvalues
+    let (_, s, _) = crate::linalg::svd(&(rho - sigma));
+    Ok(0.5 * s.iter().sum::<f64>())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{density_matrix_fidelity, linear_entropy, purity, statevector_fidelity,
+        statevector_purity, trace_distance};
+    use crate::cmatrix::{CMatrix, CVector};
+
+    #[test]
+    fn test_purity()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let h = crate::cmatrix::COMPLEX_HSQRT2;
+        let rho = array![[h * h, h * h], [h * h, h * h]];
+        assert!((purity(&rho) - 1.0).abs() < 1.0e-12);
+        assert!(linear_entropy(&rho).abs() < 1.0e-12);
+
+        let rho = CMatrix::eye(4) * 0.25;
+        assert!((purity(&rho) - 0.25).abs() < 1.0e-12);
+        assert!((linear_entropy(&rho) - 0.75).abs() < 1.0e-12);
+
+        let rho = array![[h * h, z], [z, h * h]];
+        assert!((purity(&rho) - 0.5).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_statevector_purity()
+    {
+        let h = crate::cmatrix::COMPLEX_HSQRT2;
+        let psi: CVector = array![h, h * crate::cmatrix::COMPLEX_I];
+        assert!((statevector_purity(&psi) - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_statevector_fidelity()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let h = crate::cmatrix::COMPLEX_HSQRT2;
+        let psi: CVector = array![o, z];
+        let phi: CVector = array![h, h * crate::cmatrix::COMPLEX_I];
+        assert!((statevector_fidelity(&psi, &phi).unwrap() - 0.5).abs() < 1.0e-12);
+        assert!((statevector_fidelity(&phi, &phi).unwrap() - 1.0).abs() < 1.0e-12);
+        assert!(statevector_fidelity(&psi, &array![z, o]).unwrap().abs() < 1.0e-12);
+        assert_eq!(statevector_fidelity(&psi, &array![o, z, z, z]),
+            Err(crate::error::Error::DimensionMismatch(2, 4)));
+    }
+
+    #[test]
+    fn test_density_matrix_fidelity()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let h = crate::cmatrix::COMPLEX_HSQRT2;
+
+        // Pure states: fidelity is the squared overlap
+        let rho = array![[o, z], [z, z]];
+        let sigma = array![[h * h, h * h], [h * h, h * h]];
+        assert!((density_matrix_fidelity(&rho, &sigma).unwrap() - 0.5).abs() < 1.0e-10);
+        assert!((density_matrix_fidelity(&sigma, &sigma).unwrap() - 1.0).abs() < 1.0e-10);
+
+        // With the maximally mixed state, F = Tr(ρ)/d for pure ρ
+        let mixed = CMatrix::eye(2) * 0.5;
+        assert!((density_matrix_fidelity(&rho, &mixed).unwrap() - 0.5).abs() < 1.0e-10);
+        let diag = array![[0.75 * o, z], [z, 0.25 * o]];
+        let expected = (0.375f64.sqrt() + 0.125f64.sqrt()).powi(2);
+        assert!((density_matrix_fidelity(&diag, &mixed).unwrap() - expected).abs() < 1.0e-10);
+
+        assert_eq!(density_matrix_fidelity(&rho, &CMatrix::eye(4)),
+            Err(crate::error::Error::DimensionMismatch(2, 4)));
+    }
+
+    #[test]
+    fn test_trace_distance()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let h = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let rho = array![[o, z], [z, z]];
+        let sigma = array![[z, z], [z, o]];
+        assert!((trace_distance(&rho, &sigma).unwrap() - 1.0).abs() < 1.0e-12);
+        assert!(trace_distance(&rho, &rho).unwrap().abs() < 1.0e-12);
+        let plus = array![[h * h, h * h], [h * h, h * h]];
+        assert!((trace_distance(&rho, &plus).unwrap() - h.re).abs() < 1.0e-12);
+        assert!((trace_distance(&rho, &(CMatrix::eye(2) * 0.5)).unwrap() - 0.5).abs() < 1.0e-12);
+
+        assert_eq!(trace_distance(&CMatrix::eye(4), &rho),
+            Err(crate::error::Error::DimensionMismatch(4, 2)));
+    }
+}
@@ -0,0 +1,532 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rand_distr::Distribution;
+
+/// Richardson extrapolation.
+///
+/// Compute the value at `x=0` of the unique polynomial of degree `n-1` through
+/// the `n` points `(scales[i], values[i])`.
+fn richardson_extrapolate(scales: &[f64], values: &[f64]) -> f64
+{
+    let mut res = 0.0;
+    for (i, (&si, &vi)) in scales.iter().zip(values).enumerate()
+    {
+        let weight: f64 = scales.iter().enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, &sj)| sj / (sj - si))
+            .product();
+        res += weight * vi;
+    }
+    res
+}
+
+/// Estimate an expectation value from measurements.
+///
+/// Estimate the expectation value of an observable with exact expectation
+/// value `exact` and eigenvalues ±1, from `nr_shots` simulated measurements.
+/// When `nr_shots` is zero, the exact value is returned.
+fn sample_expectation_value(exact: f64, nr_shots: usize) -> crate::error::Result<f64>
+{
+    if nr_shots == 0
+    {
+        return Ok(exact);
+    }
+
+    let p = (0.5 * (1.0 + exact)).clamp(0.0, 1.0);
+    let distr = rand_distr::Binomial::new(nr_shots as u64, p)
+        .map_err(|err| crate::error::Error::InternalError(format!("{:?}", err)))?;
+    let nr_plus = distr.sample(&mut rand::thread_rng()) as f64;
+    Ok((2.0 * nr_plus - nr_shots as f64) / nr_shots as f64)
+}
+
+/// Zero-noise extrapolation.
+///
+/// Estimate the expectation value of Pauli string `observable` in the final
+/// state of `circuit` in the absence of noise. For every factor `k` in
+/// `noise_factors`, `k-1` pairs of `C`<sub>`X`</sub> gates are inserted after
+/// every `C`<sub>`X`</sub> gate in the circuit, repeating any noise following
+/// the original gate. The expectation value of the resulting circuit is
+/// computed in density matrix mode, and estimated from `nr_shots`
+/// measurements (or computed exactly when `nr_shots` is zero). The results
+/// are extrapolated to zero noise using Richardson extrapolation.
+///
+/// The noise factors should be distinct integers, at least 1, otherwise an
+/// `InvalidNoiseFactors` error is returned.
+pub fn zne_extrapolate(circuit: &crate::circuit::Circuit,
+    observable: &crate::stabilizer::PauliString, noise_factors: &[f64],
+    nr_shots: usize) -> crate::error::Result<f64>
+{
+    let mut nr_pairs: Vec<usize> = vec![];
+    for &factor in noise_factors
+    {
+        if factor < 1.0 || factor.fract() != 0.0
+        {
+            return Err(crate::error::Error::InvalidNoiseFactors);
+        }
+        let pairs = factor as usize - 1;
+        if nr_pairs.contains(&pairs)
+        {
+            return Err(crate::error::Error::InvalidNoiseFactors);
+        }
+        nr_pairs.push(pairs);
+    }
+    if nr_pairs.is_empty()
+    {
+        return Err(crate::error::Error::InvalidNoiseFactors);
+    }
+
+    let mut scales = vec![];
+    let mut values = vec![];
+    for pairs in nr_pairs
+    {
+        let rho = circuit.fold_cx(pairs).density_matrix()?;
+        let exact = rho.expectation_value(observable)?;
+        scales.push((2 * pairs + 1) as f64);
+        values.push(sample_expectation_value(exact, nr_shots)?);
+    }
+
+    Ok(richardson_extrapolate(&scales, &values))
+}
+
+/// Randomized compilation.
+///
+/// Create `nr_circuits` randomly dressed copies of `circuit`. In each copy,
+/// every Clifford gate acting on two or more qubits is preceded by a random
+/// Pauli operator `P` on the qubits of the gate, and followed by
+/// `GPG`<sup>`†`</sup>, so that in the absence of noise the dressed circuits
+/// are equivalent to the original. Noise operations directly following a
+/// gate are included between the two Pauli operators, so that averaging
+/// over the dressed circuits tailors the noise on these gates into
+/// stochastic Pauli noise. Other operations, including single-qubit gates
+/// and non-Clifford gates, are left unchanged.
+pub fn randomized_compilation<R: rand::Rng>(circuit: &crate::circuit::Circuit,
+    nr_circuits: usize, rng: &mut R) -> Vec<crate::circuit::Circuit>
+{
+    (0..nr_circuits).map(|_| circuit.pauli_dressed(rng)).collect()
+}
+
+/// Average expectation value over dressed circuits.
+///
+/// Compute the average of the expectation values of Pauli string
+/// `observable` in the final states of the circuits in `dressed_circuits`,
+/// as created by `randomized_compilation()`. The expectation value of each
+/// circuit is computed in density matrix mode, and estimated from `nr_shots`
+/// measurements (or computed exactly when `nr_shots` is zero). For an empty
+/// list of circuits, zero is returned.
+pub fn rc_expectation_value(dressed_circuits: &[crate::circuit::Circuit],
+    observable: &crate::stabilizer::PauliString, nr_shots: usize) -> crate::error::Result<f64>
+{
+    if dressed_circuits.is_empty()
+    {
+        return Ok(0.0);
+    }
+
+    let mut sum = 0.0;
+    for circuit in dressed_circuits
+    {
+        let exact = circuit.density_matrix()?.expectation_value(observable)?;
+        sum += sample_expectation_value(exact, nr_shots)?;
+    }
+    Ok(sum / dressed_circuits.len() as f64)
+}
+
+/// Function computing a readout calibration matrix from the histograms of the
+/// calibration circuits
+pub type CalibrationAssembler = fn(&[crate::idhash::U64HashMap<usize>]) -> ndarray::Array2<f64>;
+
+/// Create readout calibration circuits.
+///
+/// Create the `2`<sup>`n`</sup> calibration circuits for measuring
+/// `nr_bits = n` qubits. Circuit `j` prepares the basis state in which qubit
+/// `k` is |1⟩ if bit `k` of `j` is set, and measures every qubit `k` into
+/// classical bit `k`, so that in the absence of errors, the histogram of
+/// circuit `j` only contains key `j`. The circuits are executed with `nr_shots`
+/// shots before they are returned, noise can be modeled by adding noise
+/// operations and executing them again. Along with the circuits, a function is
+/// returned that assembles the calibration matrix `A`, with
+/// `A[[i, j]] = P(measure i | prepared j)`, from the histograms of the circuits,
+/// given in the same order.
+pub fn build_calibration_matrix(nr_bits: usize, nr_shots: usize)
+    -> crate::error::Result<(Vec<crate::circuit::Circuit>, CalibrationAssembler)>
+{
+    let cbits: Vec<usize> = (0..nr_bits).collect();
+    let mut circuits = vec![];
+    for j in 0..1usize << nr_bits
+    {
+        let mut circuit = crate::circuit::Circuit::new(nr_bits, nr_bits);
+        for bit in (0..nr_bits).filter(|&bit| (j >> bit) & 1 == 1)
+        {
+            circuit.x(bit)?;
+        }
+        circuit.measure_all(&cbits)?;
+        circuit.execute(nr_shots)?;
+        circuits.push(circuit);
+    }
+
+    Ok((circuits, calibration_matrix))
+}
+
+/// Assemble a calibration matrix.
+///
+/// Compute the readout calibration matrix `A`, where `A[[i, j]]` is the
+/// fraction of measurements in `results[j]` with outcome `i`.
+fn calibration_matrix(results: &[crate::idhash::U64HashMap<usize>]) -> ndarray::Array2<f64>
+{
+    let size = results.len();
+    let mut res = ndarray::Array2::zeros((size, size));
+    for (j, hist) in results.iter().enumerate()
+    {
+        let total = hist.values().sum::<usize>() as f64;
+        for (&i, &count) in hist.iter()
+        {
+            if (i as usize) < size
+            {
+                res[[i as usize, j]] = count as f64 / total;
+            }
+        }
+    }
+    res
+}
+
+/// Invert a real matrix.
+///
+/// Compute the inverse of square matrix `m` using Gauss-Jordan elimination
+/// with partial pivoting. If the matrix is (numerically) singular, a
+/// `SingularMatrix` error is returned.
+fn invert(m: &ndarray::Array2<f64>) -> crate::error::Result<ndarray::Array2<f64>>
+{
+    let n = m.rows();
+    let mut a = m.clone();
+    let mut inv = ndarray::Array2::eye(n);
+    for col in 0..n
+    {
+        let pivot = (col..n).max_by(|&i, &j| a[[i, col]].abs().partial_cmp(&a[[j, col]].abs()).unwrap())
+            .unwrap();
+        if a[[pivot, col]].abs() < 1.0e-12
+        {
+            return Err(crate::error::Error::SingularMatrix);
+        }
+        for k in 0..n
+        {
+            a.swap([col, k], [pivot, k]);
+            inv.swap([col, k], [pivot, k]);
+        }
+
+        let scale = 1.0 / a[[col, col]];
+        a.row_mut(col).mapv_inplace(|x| x * scale);
+        inv.row_mut(col).mapv_inplace(|x| x * scale);
+        for row in (0..n).filter(|&row| row != col)
+        {
+            let factor = a[[row, col]];
+            if factor != 0.0
+            {
+                let arow = a.row(col).to_owned();
+                let irow = inv.row(col).to_owned();
+                a.row_mut(row).scaled_add(-factor, &arow);
+                inv.row_mut(row).scaled_add(-factor, &irow);
+            }
+        }
+    }
+
+    Ok(inv)
+}
+
+/// Mitigate readout errors.
+///
+/// Correct the measured `histogram` for readout errors described by
+/// calibration matrix `cal_matrix`, as built from the results of the circuits
+/// created by `build_calibration_matrix()`. The measured distribution is
+/// multiplied by the inverse of the calibration matrix, and the resulting
+/// estimate of the error-free distribution is returned. Note that due to
+/// statistical noise, some of the resulting quasi-probabilities may be
+/// (slightly) negative. If the calibration matrix is not square, an
+/// `InvalidNrBits` error is returned, if it is singular, a `SingularMatrix`
+/// error. When the histogram contains outcomes on more bits than described
+/// by the calibration matrix, an `InvalidCBit` error is returned.
+pub fn apply_readout_mitigation(histogram: &crate::idhash::U64HashMap<usize>,
+    cal_matrix: &ndarray::Array2<f64>) -> crate::error::Result<crate::idhash::U64HashMap<f64>>
+{
+    let size = cal_matrix.rows();
+    if cal_matrix.cols() != size
+    {
+        return Err(crate::error::Error::InvalidNrBits(cal_matrix.cols(), size,
+            String::from("calibration matrix")));
+    }
+
+    let total = histogram.values().sum::<usize>() as f64;
+    let mut measured = ndarray::Array1::zeros(size);
+    for (&key, &count) in histogram.iter()
+    {
+        if key as usize >= size
+        {
+            let bit = 63 - key.leading_zeros() as usize;
+            return Err(crate::error::Error::InvalidCBit(bit));
+        }
+        measured[key as usize] = count as f64 / total;
+    }
+
+    let mitigated = invert(cal_matrix)?.dot(&measured);
+    let mut res = crate::idhash::new_u64_hash_map();
+    for (i, &p) in mitigated.iter().enumerate()
+    {
+        if p != 0.0
+        {
+            res.insert(i as u64, p);
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{apply_readout_mitigation, build_calibration_matrix, invert,
+        randomized_compilation, rc_expectation_value, richardson_extrapolate, zne_extrapolate};
+    use crate::circuit::Circuit;
+    use crate::gates::Depolarizing;
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    fn noisy_circuit(p: f64) -> Circuit
+    {
+        let mut circuit = Circuit::new(3, 0);
+        circuit.h(0).unwrap();
+        for &(c, t) in [(0, 1), (1, 2)].iter()
+        {
+            circuit.cx(c, t).unwrap();
+            circuit.add_noise(Depolarizing::new(p), &[c]).unwrap();
+            circuit.add_noise(Depolarizing::new(p), &[t]).unwrap();
+        }
+        circuit
+    }
+
+    #[test]
+    fn test_richardson_extrapolate()
+    {
+        // Linear
+        let res = richardson_extrapolate(&[1.0, 3.0], &[5.0, 9.0]);
+        assert!((res - 3.0).abs() < 1.0e-12);
+        // Quadratic, 1 - x + 2x²
+        let res = richardson_extrapolate(&[1.0, 2.0, 3.0], &[2.0, 7.0, 16.0]);
+        assert!((res - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_zne_exact()
+    {
+        let circuit = noisy_circuit(0.02);
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::I, PauliOp::Z]);
+
+        let noisy = zne_extrapolate(&circuit, &obs, &[1.0], 0).unwrap();
+        let mitigated = zne_extrapolate(&circuit, &obs, &[1.0, 2.0, 3.0], 0).unwrap();
+        assert!((noisy - 1.0).abs() > 0.05);
+        assert!((mitigated - 1.0).abs() < 5.0e-3);
+
+        // Without noise, folding has no effect
+        let circuit = noisy_circuit(0.0);
+        let res = zne_extrapolate(&circuit, &obs, &[1.0, 2.0, 3.0], 0).unwrap();
+        assert!((res - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_zne_sampled()
+    {
+        let circuit = noisy_circuit(0.02);
+        let obs = PauliString::new(&[PauliOp::X, PauliOp::X, PauliOp::X]);
+        let ideal = 1.0;
+        let noisy = zne_extrapolate(&circuit, &obs, &[1.0], 0).unwrap();
+        let mitigated = zne_extrapolate(&circuit, &obs, &[1.0, 2.0, 3.0], 100_000).unwrap();
+        assert!((mitigated - ideal).abs() < (noisy - ideal).abs());
+    }
+
+    #[test]
+    fn test_zne_errors()
+    {
+        let circuit = noisy_circuit(0.02);
+        let obs = PauliString::new(&[PauliOp::Z, PauliOp::I, PauliOp::Z]);
+        assert_eq!(zne_extrapolate(&circuit, &obs, &[], 0),
+            Err(crate::error::Error::InvalidNoiseFactors));
+        assert_eq!(zne_extrapolate(&circuit, &obs, &[1.0, 1.0], 0),
+            Err(crate::error::Error::InvalidNoiseFactors));
+        assert_eq!(zne_extrapolate(&circuit, &obs, &[0.0, 1.0], 0),
+            Err(crate::error::Error::InvalidNoiseFactors));
+        assert_eq!(zne_extrapolate(&circuit, &obs, &[1.0, 1.5], 0),
+            Err(crate::error::Error::InvalidNoiseFactors));
+
+        let obs = PauliString::new(&[PauliOp::Z]);
+        assert!(matches!(zne_extrapolate(&circuit, &obs, &[1.0], 0),
+            Err(crate::error::Error::InvalidNrBits(1, 3, _))));
+    }
+
+    #[test]
+    fn test_randomized_compilation()
+    {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(27);
+
+        let mut circuit = Circuit::new(3, 0);
+        circuit.h(0).unwrap();
+        circuit.cx(0, 1).unwrap();
+        circuit.add_gate(crate::gates::CRZ::new(0.3), &[2, 0]).unwrap();
+        circuit.add_gate(crate::gates::CZ::new(), &[0, 2]).unwrap();
+        circuit.add_gate(crate::gates::S::new(), &[1]).unwrap();
+        let nr_ops = circuit.to_instructions().len();
+        let dressed = randomized_compilation(&circuit, 20, &mut rng);
+        assert_eq!(dressed.len(), 20);
+
+        let expected = crate::gates::Composite::from_circuit("C", &circuit).unwrap();
+        let mut nr_distinct = ::std::collections::HashSet::new();
+        for c in dressed.iter()
+        {
+            let instrs = c.to_instructions();
+            // At most two Pauli gates on either side of the two Clifford gates
+            assert!(instrs.len() >= nr_ops && instrs.len() <= nr_ops + 8);
+            nr_distinct.insert(format!("{:?}", instrs));
+
+            // Equivalent up to a global phase
+            let m = crate::gates::Composite::from_circuit("D", c).unwrap();
+            let m = crate::gates::Gate::matrix(&m);
+            let e = crate::gates::Gate::matrix(&expected);
+            let inner: crate::cmatrix::CNumber = m.iter().zip(e.iter()).map(|(x, y)| x.conj() * y).sum();
+            assert!((inner.norm() - 8.0).abs() < 1.0e-10);
+        }
+        assert!(nr_distinct.len() > 15);
+    }
+
+    #[test]
+    fn test_rc_expectation_value()
+    {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(13);
+        let obs = PauliString::new(&[PauliOp::X, PauliOp::X, PauliOp::X]);
+
+        // Noiseless: same result as the undressed circuit
+        let circuit = noisy_circuit(0.0);
+        let dressed = randomized_compilation(&circuit, 10, &mut rng);
+        let ideal = circuit.density_matrix().unwrap().expectation_value(&obs).unwrap();
+        assert!((rc_expectation_value(&dressed, &obs, 0).unwrap() - ideal).abs() < 1.0e-12);
+        let sampled = rc_expectation_value(&dressed, &obs, 1000).unwrap();
+        assert!((sampled - ideal).abs() < 0.05);
+
+        // Depolarizing noise is already Pauli noise, and not changed by the twirl
+        let circuit = noisy_circuit(0.05);
+        let dressed = randomized_compilation(&circuit, 10, &mut rng);
+        let noisy = circuit.density_matrix().unwrap().expectation_value(&obs).unwrap();
+        assert!((noisy - ideal).abs() > 0.05);
+        assert!((rc_expectation_value(&dressed, &obs, 0).unwrap() - noisy).abs() < 1.0e-12);
+
+        assert_eq!(rc_expectation_value(&[], &obs, 0), Ok(0.0));
+        let obs = PauliString::new(&[PauliOp::Z]);
+        assert!(matches!(rc_expectation_value(&dressed, &obs, 0),
+            Err(crate::error::Error::InvalidNrBits(1, 3, _))));
+    }
+
+    #[test]
+    fn test_invert()
+    {
+        let m = array![[2.0, 1.0, 0.0], [0.0, 0.0, 3.0], [1.0, 4.0, 1.0]];
+        let inv = invert(&m).unwrap();
+        let prod = m.dot(&inv);
+        for i in 0..3
+        {
+            for j in 0..3
+            {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((prod[[i, j]] - expected).abs() < 1.0e-12);
+            }
+        }
+
+        let m = array![[1.0, 2.0], [2.0, 4.0]];
+        assert_eq!(invert(&m), Err(crate::error::Error::SingularMatrix));
+    }
+
+    #[test]
+    fn test_build_calibration_matrix()
+    {
+        let (circuits, assemble) = build_calibration_matrix(2, 100).unwrap();
+        assert_eq!(circuits.len(), 4);
+        let results: Vec<_> = circuits.iter().map(|c| c.histogram().unwrap()).collect();
+        for (j, hist) in results.iter().enumerate()
+        {
+            assert_eq!(hist.len(), 1);
+            assert_eq!(hist.get(&(j as u64)), Some(&100));
+        }
+        assert_eq!(assemble(&results), ndarray::Array2::<f64>::eye(4));
+
+        // Add noise on the first qubit before measuring
+        let noisy = |prepared: u64| {
+            let mut circuit = Circuit::new(2, 2);
+            for bit in (0..2).filter(|&bit| (prepared >> bit) & 1 == 1)
+            {
+                circuit.x(bit).unwrap();
+            }
+            circuit.add_noise(Depolarizing::new(0.3), &[0]).unwrap();
+            circuit.measure_all(&[0, 1]).unwrap();
+            circuit.set_simulation_mode(crate::circuit::SimulationMode::DensityMatrix);
+            circuit.execute(20_000).unwrap();
+            circuit.histogram().unwrap()
+        };
+        let results: Vec<_> = (0..4).map(&noisy).collect();
+        let cal_matrix = assemble(&results);
+        for j in 0..4
+        {
+            assert!(cal_matrix[[j, j]] < 0.95);
+            assert!(cal_matrix[[j ^ 1, j]] > 0.05);
+            assert_eq!(cal_matrix[[j ^ 2, j]], 0.0);
+            assert!((cal_matrix.column(j).sum() - 1.0).abs() < 1.0e-12);
+        }
+
+        let raw = noisy(2);
+        assert!(raw.contains_key(&3));
+        let mitigated = apply_readout_mitigation(&raw, &cal_matrix).unwrap();
+        assert!((mitigated[&2] - 1.0).abs() < 0.05);
+        assert!(mitigated.get(&3).map(|p| p.abs() < 0.05).unwrap_or(true));
+    }
+
+    #[test]
+    fn test_apply_readout_mitigation()
+    {
+        // First bit flipped with probability 0.1, second with 0.2
+        let cal_matrix = array![
+            [0.72, 0.08, 0.18, 0.02],
+            [0.08, 0.72, 0.02, 0.18],
+            [0.18, 0.02, 0.72, 0.08],
+            [0.02, 0.18, 0.08, 0.72]
+        ];
+
+        // Measured distribution for ideal state with P(0) = 0.25, P(3) = 0.75
+        let mut histogram = crate::idhash::new_u64_hash_map();
+        histogram.insert(0, 195);
+        histogram.insert(1, 155);
+        histogram.insert(2, 105);
+        histogram.insert(3, 545);
+        let mitigated = apply_readout_mitigation(&histogram, &cal_matrix).unwrap();
+        assert!((mitigated[&0] - 0.25).abs() < 1.0e-12);
+        assert!(mitigated.get(&1).map(|p| p.abs() < 1.0e-12).unwrap_or(true));
+        assert!(mitigated.get(&2).map(|p| p.abs() < 1.0e-12).unwrap_or(true));
+        assert!((mitigated[&3] - 0.75).abs() < 1.0e-12);
+
+        histogram.insert(4, 1);
+        assert_eq!(apply_readout_mitigation(&histogram, &cal_matrix),
+            Err(crate::error::Error::InvalidCBit(2)));
+        assert_eq!(apply_readout_mitigation(&histogram, &array![[1.0, 0.0]]),
+            Err(crate::error::Error::InvalidNrBits(2, 1, String::from("calibration matrix"))));
+        histogram.remove(&4);
+        assert_eq!(apply_readout_mitigation(&histogram, &array![[0.5, 0.5], [0.5, 0.5]]),
+            Err(crate::error::Error::InvalidCBit(1)));
+        let mut histogram = crate::idhash::new_u64_hash_map();
+        histogram.insert(0, 10);
+        assert_eq!(apply_readout_mitigation(&histogram, &array![[0.5, 0.5], [0.5, 0.5]]),
+            Err(crate::error::Error::SingularMatrix));
+    }
+}
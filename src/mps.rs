@@ -0,0 +1,502 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::Gate;
+
+/// Three-index tensor for a single site in a matrix product state
+type SiteTensor = ndarray::Array3<crate::cmatrix::CNumber>;
+
+/// Singular values below this threshold, relative to the largest singular
+/// value, are discarded
+const TRUNCATION_TOLERANCE: f64 = 1.0e-13;
+
+/// Matrix product state
+///
+/// Struct `MPSState` represents the quantum state of a system of qubits as a
+/// matrix product state. The state is described by one tensor
+/// `A`<sub>`i`</sub>`[l, s, r]` for every qubit `i`, where `s` is the
+/// physical index and `l` and `r` are bond indices connecting the tensor to
+/// its neighbours. For weakly entangled states, the bond dimensions remain
+/// small and this representation is much more compact than the full
+/// coefficient vector.
+#[derive(Clone)]
+pub struct MPSState
+{
+    /// The tensors making up the state, one for each qubit
+    tensors: Vec<SiteTensor>,
+    /// The maximum allowed bond dimension, if any
+    max_bond_dim: Option<usize>,
+    /// The total weight of the singular values discarded because of the
+    /// maximum bond dimension
+    truncation_error: f64
+}
+
+impl MPSState
+{
+    /// Create a new matrix product state.
+    ///
+    /// Create a new matrix product state for `nr_bits` qubits, initialized to
+    /// |00...0⟩.
+    pub fn new(nr_bits: usize) -> Self
+    {
+        let mut site = SiteTensor::zeros((1, 2, 1));
+        site[[0, 0, 0]] = crate::cmatrix::COMPLEX_ONE;
+        MPSState
+        {
+            tensors: vec![site; nr_bits],
+            max_bond_dim: None,
+            truncation_error: 0.0
+        }
+    }
+
+    /// Create a new matrix product state with limited bond dimension.
+    ///
+    /// Create a new matrix product state for `nr_bits` qubits, initialized to
+    /// |00...0⟩, where the dimension of the bonds between the sites is limited
+    /// to `max_bond_dim`. When a gate would lead to a larger bond dimension,
+    /// the smallest singular values are discarded, and the simulation is no
+    /// longer exact. The weight of the discarded singular values can be
+    /// retrieved using `truncation_error()`.
+    pub fn with_max_bond_dimension(nr_bits: usize, max_bond_dim: usize) -> Self
+    {
+        let mut res = Self::new(nr_bits);
+        res.max_bond_dim = Some(max_bond_dim.max(1));
+        res
+    }
+
+    /// The number of qubits in this state
+    pub fn nr_bits(&self) -> usize
+    {
+        self.tensors.len()
+    }
+
+    /// Bond dimensions.
+    ///
+    /// Return the dimensions of the `n-1` bonds between neighbouring qubits.
+    pub fn bond_dimensions(&self) -> Vec<usize>
+    {
+        self.tensors.iter().skip(1).map(|t| t.shape()[0]).collect()
+    }
+
+    /// The largest bond dimension in this state
+    pub fn max_bond_dimension(&self) -> usize
+    {
+        self.bond_dimensions().into_iter().max().unwrap_or(1)
+    }
+
+    /// Truncation error.
+    ///
+    /// Return the total weight, i.e. the sum of the squared normalized
+    /// singular values, of all singular values that were discarded because
+    /// the maximum bond dimension was reached. This is zero when the
+    /// simulation is exact.
+    pub fn truncation_error(&self) -> f64
+    {
+        self.truncation_error
+    }
+
+    /// Apply a single-qubit matrix on qubit `bit`.
+    fn apply_one_site(&mut self, mat: &crate::cmatrix::CMatrix, bit: usize)
+    {
+        let site = &mut self.tensors[bit];
+        for l in 0..site.shape()[0]
+        {
+            for r in 0..site.shape()[2]
+            {
+                let t = mat.dot(&site.slice(s![l, .., r]));
+                site.slice_mut(s![l, .., r]).assign(&t);
+            }
+        }
+    }
+
+    /// Apply a two-qubit matrix on neighbouring qubits `bit` and `bit+1`.
+    fn apply_two_site(&mut self, mat: &crate::cmatrix::CMatrix, bit: usize)
+    {
+        let (dl, dm) = (self.tensors[bit].shape()[0], self.tensors[bit].shape()[2]);
+        let dr = self.tensors[bit+1].shape()[2];
+
+        // Contract the two sites, and apply the gate
+        let a = self.tensors[bit].view().into_shape((dl*2, dm)).unwrap();
+        let b = self.tensors[bit+1].view().into_shape((dm, 2*dr)).unwrap();
+        let theta = a.dot(&b).into_shape((dl, 4, dr)).unwrap();
+        let mut m = crate::cmatrix::CMatrix::zeros((dl*2, 2*dr));
+        for l in 0..dl
+        {
+            for r in 0..dr
+            {
+                let t = mat.dot(&theta.slice(s![l, .., r]));
+                for s in 0..4
+                {
+                    m[[l*2 + s/2, (s%2)*dr + r]] = t[s];
+                }
+            }
+        }
+
+        // Split again, discarding negligible singular values
+        let (u, sigma, v) = crate::linalg::svd(&m);
+        let mut rank = sigma.iter().filter(|&&s| s > TRUNCATION_TOLERANCE * sigma[0]).count().max(1);
+        let mut scale = 1.0;
+        if let Some(max_bond_dim) = self.max_bond_dim
+        {
+            if rank > max_bond_dim
+            {
+                let total = sigma.iter().map(|s| s * s).sum::<f64>();
+                let discarded = sigma[max_bond_dim..rank].iter().map(|s| s * s).sum::<f64>();
+                self.truncation_error += discarded / total;
+                scale = (total / (total - discarded)).sqrt();
+                rank = max_bond_dim;
+            }
+        }
+        let u = u.slice(s![.., ..rank]).to_owned();
+        let mut rest = crate::linalg::adjoint(&v.slice(s![.., ..rank]).to_owned());
+        for (mut row, &s) in rest.genrows_mut().into_iter().zip(sigma.iter())
+        {
+            row *= crate::cmatrix::CNumber::new(s * scale, 0.0);
+        }
+
+        self.tensors[bit] = u.into_shape((dl, 2, rank)).unwrap();
+        self.tensors[bit+1] = rest.into_shape((rank, 2, dr)).unwrap();
+    }
+
+    /// Apply a gate.
+    ///
+    /// Apply the single- or two-qubit gate `gate` on the qubits in `bits`.
+    /// Gates on qubits that are not neighbours are implemented by swapping the
+    /// qubits next to each other, and back again.
+    pub fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: Gate + ?Sized
+    {
+        gate.check_nr_bits(bits.len())?;
+        if let Some(&bit) = bits.iter().find(|&&b| b >= self.nr_bits())
+        {
+            return Err(crate::error::Error::InvalidQBit(bit));
+        }
+
+        match bits.len()
+        {
+            1 => {
+                self.apply_one_site(&gate.matrix(), bits[0]);
+            },
+            2 => {
+                let swap = crate::gates::Swap::new().matrix();
+                let mut mat = gate.matrix();
+                let (lo, hi) = if bits[0] < bits[1]
+                    {
+                        (bits[0], bits[1])
+                    }
+                    else
+                    {
+                        mat = swap.dot(&mat).dot(&swap);
+                        (bits[1], bits[0])
+                    };
+
+                for bit in (lo+1..hi).rev()
+                {
+                    self.apply_two_site(&swap, bit);
+                }
+                self.apply_two_site(&mat, lo);
+                for bit in lo+1..hi
+                {
+                    self.apply_two_site(&swap, bit);
+                }
+            },
+            n => {
+                return Err(crate::error::Error::OpNotImplemented(
+                    String::from("apply_gate"), format!("{}-qubit MPS", n)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The coefficient vector.
+    ///
+    /// Contract the full matrix product state, and return the coefficient
+    /// vector of the state. Note that this quickly becomes very expensive
+    /// for larger systems.
+    pub fn to_statevector(&self) -> crate::cmatrix::CVector
+    {
+        let mut res = crate::cmatrix::CMatrix::ones((1, 1));
+        for site in self.tensors.iter()
+        {
+            let (dl, dr) = (site.shape()[0], site.shape()[2]);
+            let view = site.view().into_shape((dl, 2*dr)).unwrap();
+            let n = res.rows();
+            res = res.dot(&view).into_shape((n*2, dr)).unwrap();
+        }
+        res.column(0).to_owned()
+    }
+
+    /// Extend the left environment `env` by one site.
+    fn left_step(env: &crate::cmatrix::CMatrix, site: &SiteTensor,
+        op: &crate::cmatrix::CMatrix) -> crate::cmatrix::CMatrix
+    {
+        let dr = site.shape()[2];
+        let mut res = crate::cmatrix::CMatrix::zeros((dr, dr));
+        for s in 0..2
+        {
+            for t in 0..2
+            {
+                if op[[s, t]] == crate::cmatrix::COMPLEX_ZERO
+                {
+                    continue;
+                }
+                let bra = site.slice(s![.., s, ..]).mapv(|c| c.conj());
+                let ket = site.slice(s![.., t, ..]);
+                res += &(bra.t().dot(env).dot(&ket) * op[[s, t]]);
+            }
+        }
+        res
+    }
+
+    /// Extend the right environment `env` by one site.
+    fn right_step(env: &crate::cmatrix::CMatrix, site: &SiteTensor,
+        op: &crate::cmatrix::CMatrix) -> crate::cmatrix::CMatrix
+    {
+        let dl = site.shape()[0];
+        let mut res = crate::cmatrix::CMatrix::zeros((dl, dl));
+        for s in 0..2
+        {
+            for t in 0..2
+            {
+                if op[[s, t]] == crate::cmatrix::COMPLEX_ZERO
+                {
+                    continue;
+                }
+                let bra = site.slice(s![.., s, ..]).mapv(|c| c.conj());
+                let ket = site.slice(s![.., t, ..]);
+                res += &(ket.dot(env).dot(&bra.t()) * op[[s, t]]);
+            }
+        }
+        res
+    }
+
+    /// Contract the state with a single site operator on every qubit, and
+    /// return `⟨ψ|O|ψ⟩`.
+    fn contract(&self, ops: &[crate::cmatrix::CMatrix]) -> crate::cmatrix::CNumber
+    {
+        let n = self.nr_bits();
+        let mid = n / 2;
+
+        let mut left = crate::cmatrix::CMatrix::ones((1, 1));
+        for (site, op) in self.tensors[..mid].iter().zip(ops)
+        {
+            left = Self::left_step(&left, site, op);
+        }
+
+        let mut right = crate::cmatrix::CMatrix::ones((1, 1));
+        for (site, op) in self.tensors[mid..].iter().zip(&ops[mid..]).rev()
+        {
+            right = Self::right_step(&right, site, op);
+        }
+
+        // Contract left and right environments over the middle bond
+        left.iter().zip(right.t().iter()).map(|(&l, &r)| l * r).sum()
+    }
+
+    /// Compute an expectation value.
+    ///
+    /// Compute the expectation value of the Pauli string `observable` in this
+    /// state, by contracting the transfer matrices of all sites. The left
+    /// environment is built up to the middle of the chain, the right
+    /// environment from the end of the chain, and the two are contracted over
+    /// the middle bond. The time complexity is linear in the number of qubits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of qubits in `observable` does not match the
+    /// number of qubits in this state.
+    pub fn expectation_value(&self, observable: &crate::stabilizer::PauliString) -> f64
+    {
+        assert_eq!(observable.nr_bits(), self.nr_bits(),
+            "Number of qubits in observable does not match the state");
+
+        let ops: Vec<crate::cmatrix::CMatrix> = observable.ops().iter()
+            .map(|&op| crate::stabilizer::PauliString::new(&[op]).matrix())
+            .collect();
+        let identity = vec![crate::cmatrix::CMatrix::eye(2); self.nr_bits()];
+
+        let norm = self.contract(&identity).re;
+        self.contract(&ops).re / norm
+    }
+}
+
+impl crate::branched::BranchState for MPSState
+{
+    fn nr_bits(&self) -> usize
+    {
+        self.tensors.len()
+    }
+
+    fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: Gate + ?Sized
+    {
+        MPSState::apply_gate(self, gate, bits)
+    }
+
+    fn probability_one(&self, bit: usize) -> f64
+    {
+        let mut ops = vec![crate::cmatrix::CMatrix::eye(2); self.nr_bits()];
+        let norm = self.contract(&ops).re;
+        ops[bit][[0, 0]] = crate::cmatrix::COMPLEX_ZERO;
+        self.contract(&ops).re / norm
+    }
+
+    fn project(&mut self, bit: usize, value: bool, prob: f64)
+    {
+        let mut mat = crate::cmatrix::CMatrix::zeros((2, 2));
+        let idx = if value { 1 } else { 0 };
+        mat[[idx, idx]] = crate::cmatrix::CNumber::new(1.0 / prob.sqrt(), 0.0);
+        self.apply_one_site(&mat, bit);
+    }
+
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString) -> f64
+    {
+        MPSState::expectation_value(self, obs)
+    }
+
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>
+    {
+        Some(self.to_statevector())
+    }
+
+    fn reset_all(&mut self)
+    {
+        let mut state = Self::new(self.nr_bits());
+        state.max_bond_dim = self.max_bond_dim;
+        state.truncation_error = self.truncation_error;
+        *self = state;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::MPSState;
+    use crate::gates::{Gate, CX, CZ, H, RY, T, X};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    /// Dense reference implementation of the expectation value
+    fn dense_expectation_value(state: &crate::cmatrix::CVector, obs: &PauliString) -> f64
+    {
+        let res = obs.matrix().dot(state);
+        state.iter().zip(res.iter()).map(|(a, b)| a.conj() * b)
+            .sum::<crate::cmatrix::CNumber>().re
+    }
+
+    #[test]
+    fn test_new()
+    {
+        let state = MPSState::new(3);
+        assert_eq!(state.nr_bits(), 3);
+        assert_eq!(state.bond_dimensions(), vec![1, 1]);
+        assert_eq!(state.max_bond_dimension(), 1);
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        assert_complex_vector_eq!(&state.to_statevector(), &array![o, z, z, z, z, z, z, z]);
+    }
+
+    #[test]
+    fn test_apply_gate()
+    {
+        let mut state = MPSState::new(3);
+        let mut dense = crate::cmatrix::CVector::zeros(8);
+        dense[0] = crate::cmatrix::COMPLEX_ONE;
+
+        let ops: Vec<(Box<dyn Gate>, Vec<usize>)> = vec![
+            (Box::new(H::new()), vec![0]),
+            (Box::new(CX::new()), vec![0, 1]),
+            (Box::new(RY::new(0.7)), vec![2]),
+            (Box::new(CX::new()), vec![2, 0]),
+            (Box::new(T::new()), vec![1]),
+            (Box::new(CZ::new()), vec![0, 2]),
+            (Box::new(X::new()), vec![1])
+        ];
+        for (gate, bits) in ops.iter()
+        {
+            assert_eq!(state.apply_gate(gate.as_ref(), bits), Ok(()));
+            crate::gates::apply_gate_slice(dense.view_mut(), gate.as_ref(), bits, 3);
+        }
+
+        assert_complex_vector_eq!(&state.to_statevector(), &dense, 1.0e-12);
+        assert!(state.max_bond_dimension() <= 2);
+
+        assert_eq!(state.apply_gate(&X::new(), &[3]), Err(crate::error::Error::InvalidQBit(3)));
+        assert!(matches!(state.apply_gate(&CX::new(), &[1]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, _))));
+    }
+
+    #[test]
+    fn test_expectation_value()
+    {
+        let mut state = MPSState::new(4);
+        let mut dense = crate::cmatrix::CVector::zeros(16);
+        dense[0] = crate::cmatrix::COMPLEX_ONE;
+
+        let ops: Vec<(Box<dyn Gate>, Vec<usize>)> = vec![
+            (Box::new(H::new()), vec![0]),
+            (Box::new(CX::new()), vec![0, 1]),
+            (Box::new(RY::new(1.1)), vec![2]),
+            (Box::new(CX::new()), vec![1, 2]),
+            (Box::new(RY::new(-0.4)), vec![3]),
+            (Box::new(CX::new()), vec![2, 3]),
+            (Box::new(H::new()), vec![1])
+        ];
+        for (gate, bits) in ops.iter()
+        {
+            state.apply_gate(gate.as_ref(), bits).unwrap();
+            crate::gates::apply_gate_slice(dense.view_mut(), gate.as_ref(), bits, 4);
+        }
+
+        let ops = [PauliOp::I, PauliOp::X, PauliOp::Y, PauliOp::Z];
+        for idx in 0..256
+        {
+            let obs = PauliString::new(&[ops[idx & 3], ops[(idx >> 2) & 3],
+                ops[(idx >> 4) & 3], ops[idx >> 6]]);
+            let expected = dense_expectation_value(&dense, &obs);
+            assert!((state.expectation_value(&obs) - expected).abs() < 1.0e-12,
+                "expectation value for {} differs", obs);
+        }
+
+        let state = MPSState::new(1);
+        let obs = PauliString::new(&[PauliOp::Z]);
+        assert!((state.expectation_value(&obs) - 1.0).abs() < 1.0e-15);
+    }
+
+    #[test]
+    fn test_truncation()
+    {
+        let mut state = MPSState::with_max_bond_dimension(3, 2);
+        state.apply_gate(&H::new(), &[0]).unwrap();
+        state.apply_gate(&CX::new(), &[0, 1]).unwrap();
+        state.apply_gate(&CX::new(), &[1, 2]).unwrap();
+        assert_eq!(state.truncation_error(), 0.0);
+        assert_eq!(state.max_bond_dimension(), 2);
+
+        let mut state = MPSState::with_max_bond_dimension(2, 1);
+        state.apply_gate(&RY::new(0.5), &[0]).unwrap();
+        state.apply_gate(&CX::new(), &[0, 1]).unwrap();
+        assert_eq!(state.max_bond_dimension(), 1);
+        let s = 0.25f64.sin();
+        assert!((state.truncation_error() - s*s).abs() < 1.0e-12);
+
+        // Only the dominant |00⟩ component remains, normalized
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let v = state.to_statevector();
+        assert_complex_vector_eq!(&v.mapv(|c| c * v[0].conj() / v[0].norm()),
+            &array![o, z, z, z], 1.0e-12);
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::{PauliOp, PauliString};
+
+/// Weighted sum of Pauli strings
+///
+/// Struct `SparsePauliOp` describes an observable as a sum of Pauli strings
+/// with real weights. Only the terms with a non-zero weight need to be stored,
+/// which makes this a compact representation of observables like qubit
+/// Hamiltonians. A term with fewer operators than there are qubits in the
+/// system acts on the first qubits, with the identity on the remaining ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparsePauliOp
+{
+    /// The terms in the sum, as pairs of weight and Pauli operators
+    terms: Vec<(f64, Vec<PauliOp>)>
+}
+
+impl SparsePauliOp
+{
+    /// Create a new, empty, observable.
+    pub fn new() -> Self
+    {
+        SparsePauliOp { terms: vec![] }
+    }
+
+    /// Create a new observable from the pairs of weight and Pauli operators
+    /// in `terms`.
+    pub fn from_terms(terms: Vec<(f64, Vec<PauliOp>)>) -> Self
+    {
+        SparsePauliOp { terms: terms }
+    }
+
+    /// Add the term `weight` × `ops` to this observable.
+    pub fn add_term(&mut self, weight: f64, ops: &[PauliOp])
+    {
+        self.terms.push((weight, ops.to_vec()));
+    }
+
+    /// The terms in this observable, as pairs of weight and Pauli operators
+    pub fn terms(&self) -> &[(f64, Vec<PauliOp>)]
+    {
+        &self.terms
+    }
+
+    /// The number of qubits this observable acts on, i.e. the number of
+    /// operators in its longest term
+    pub fn nr_bits(&self) -> usize
+    {
+        self.terms.iter().map(|(_, ops)| ops.len()).max().unwrap_or(0)
+    }
+
+    /// Return the terms in this observable as Pauli strings on `nr_bits`
+    /// qubits, padding shorter terms with identity operators. An
+    /// `InvalidNrBits` error is returned when a term acts on more than
+    /// `nr_bits` qubits.
+    pub fn pauli_strings(&self, nr_bits: usize) -> crate::error::Result<Vec<(f64, PauliString)>>
+    {
+        let mut res = vec![];
+        for (weight, ops) in self.terms.iter()
+        {
+            if ops.len() > nr_bits
+            {
+                let desc: String = ops.iter().map(|op| format!("{}", op)).collect();
+                return Err(crate::error::Error::InvalidNrBits(ops.len(), nr_bits, desc));
+            }
+
+            let mut padded = ops.clone();
+            padded.resize(nr_bits, PauliOp::I);
+            res.push((*weight, PauliString::new(&padded)));
+        }
+        Ok(res)
+    }
+}
+
+impl From<PauliString> for SparsePauliOp
+{
+    fn from(pauli: PauliString) -> Self
+    {
+        SparsePauliOp { terms: vec![(1.0, pauli.ops().to_vec())] }
+    }
+}
+
+impl From<&PauliString> for SparsePauliOp
+{
+    fn from(pauli: &PauliString) -> Self
+    {
+        SparsePauliOp { terms: vec![(1.0, pauli.ops().to_vec())] }
+    }
+}
+
+impl From<&SparsePauliOp> for SparsePauliOp
+{
+    fn from(op: &SparsePauliOp) -> Self
+    {
+        op.clone()
+    }
+}
+
+impl From<&crate::hamiltonian::Hamiltonian> for SparsePauliOp
+{
+    fn from(hamiltonian: &crate::hamiltonian::Hamiltonian) -> Self
+    {
+        let terms = hamiltonian.to_pauli_strings().iter()
+            .map(|(weight, pauli)| (*weight, pauli.ops().to_vec()))
+            .collect();
+        SparsePauliOp { terms: terms }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::SparsePauliOp;
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    #[test]
+    fn test_new()
+    {
+        let mut obs = SparsePauliOp::new();
+        assert!(obs.terms().is_empty());
+        assert_eq!(obs.nr_bits(), 0);
+
+        obs.add_term(0.5, &[PauliOp::X, PauliOp::X]);
+        obs.add_term(-1.0, &[PauliOp::Z]);
+        assert_eq!(obs.nr_bits(), 2);
+        assert_eq!(obs, SparsePauliOp::from_terms(vec![
+            (0.5, vec![PauliOp::X, PauliOp::X]),
+            (-1.0, vec![PauliOp::Z])
+        ]));
+
+        let obs = SparsePauliOp::from(PauliString::new(&[PauliOp::Y, PauliOp::I]));
+        assert_eq!(obs.terms(), &[(1.0, vec![PauliOp::Y, PauliOp::I])]);
+
+        let mut hamiltonian = crate::hamiltonian::Hamiltonian::new();
+        hamiltonian.add_term(2.0, PauliString::new(&[PauliOp::Z, PauliOp::Z]));
+        hamiltonian.add_term(0.5, PauliString::new(&[PauliOp::X]));
+        let obs = SparsePauliOp::from(&hamiltonian);
+        assert_eq!(obs.terms(), &[
+            (2.0, vec![PauliOp::Z, PauliOp::Z]),
+            (0.5, vec![PauliOp::X])
+        ]);
+    }
+
+    #[test]
+    fn test_pauli_strings()
+    {
+        let obs = SparsePauliOp::from_terms(vec![
+            (0.5, vec![PauliOp::X, PauliOp::Y]),
+            (-1.0, vec![PauliOp::Z])
+        ]);
+        assert_eq!(obs.pauli_strings(3), Ok(vec![
+            (0.5, PauliString::new(&[PauliOp::X, PauliOp::Y, PauliOp::I])),
+            (-1.0, PauliString::new(&[PauliOp::Z, PauliOp::I, PauliOp::I]))
+        ]));
+        assert_eq!(obs.pauli_strings(1),
+            Err(crate::error::Error::InvalidNrBits(2, 1, String::from("XY"))));
+    }
+}
@@ -0,0 +1,712 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Import of circuits in OpenQasm 2.0 format.
+
+use crate::export::CircuitGate;
+
+/// The regular expressions used in parsing OpenQasm programs
+struct Patterns
+{
+    /// A single bit `name[idx]`, or a full register `name`
+    operand: regex::Regex,
+    /// Register declaration
+    declaration: regex::Regex,
+    /// Gate definition
+    definition: regex::Regex,
+    /// Gate application
+    gate: regex::Regex,
+    /// Measurement
+    measure: regex::Regex,
+    /// Conditional operation
+    condition: regex::Regex
+}
+
+lazy_static::lazy_static!
+{
+    /// The compiled regular expressions, created on first use
+    static ref PATTERNS: Patterns = Patterns
+    {
+        operand: regex::Regex::new(
+            r"^([A-Za-z_][A-Za-z0-9_]*)\s*(?:\[\s*([0-9]+)\s*\])?$").unwrap(),
+        declaration: regex::Regex::new(
+            r"^(qreg|creg)\s*([A-Za-z_][A-Za-z0-9_]*)\s*\[\s*([0-9]+)\s*\]$").unwrap(),
+        definition: regex::Regex::new(
+            r"^gate\s+([A-Za-z_][A-Za-z0-9_]*)\s*(?:\(([^)]*)\))?\s*([^{]*)\{(.*)\}$").unwrap(),
+        gate: regex::Regex::new(
+            r"^([A-Za-z_][A-Za-z0-9_]*)\s*(?:\((.*)\))?\s*(.*)$").unwrap(),
+        measure: regex::Regex::new(r"^measure\s+(.*?)\s*->\s*(.*)$").unwrap(),
+        condition: regex::Regex::new(
+            r"^if\s*\(\s*([A-Za-z_][A-Za-z0-9_]*)\s*==\s*([0-9]+)\s*\)\s*(.*)$").unwrap()
+    };
+}
+
+/// The gates defined in a program, by name
+type Definitions = Vec<(String, crate::gates::Composite)>;
+
+/// A gate, together with the sets of bits it is applied to
+type GateApplication = (Box<dyn CircuitGate>, Vec<Vec<usize>>);
+
+/// A register of quantum or classical bits, numbered consecutively from
+/// `offset` in the circuit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Register
+{
+    offset: usize,
+    size: usize
+}
+
+/// The quantum and classical registers declared in a program
+struct Registers
+{
+    qregs: Vec<(String, Register)>,
+    cregs: Vec<(String, Register)>,
+    nr_qbits: usize,
+    nr_cbits: usize
+}
+
+impl Registers
+{
+    /// Create a new, empty, set of registers
+    fn new() -> Self
+    {
+        Registers { qregs: vec![], cregs: vec![], nr_qbits: 0, nr_cbits: 0 }
+    }
+
+    /// Add register `name` of `size` bits. If `quantum` is true, a quantum
+    /// register is declared, otherwise a classical register.
+    fn declare(&mut self, name: &str, size: usize, quantum: bool)
+        -> crate::error::ParseResult<()>
+    {
+        if self.qregs.iter().chain(self.cregs.iter()).any(|(n, _)| n == name)
+        {
+            return Err(invalid(name));
+        }
+
+        let (regs, nr_bits) = if quantum
+            {
+                (&mut self.qregs, &mut self.nr_qbits)
+            }
+            else
+            {
+                (&mut self.cregs, &mut self.nr_cbits)
+            };
+        regs.push((String::from(name), Register { offset: *nr_bits, size: size }));
+        *nr_bits += size;
+        Ok(())
+    }
+
+    /// Look up register `name`, either quantum (when `quantum` is true) or
+    /// classical.
+    fn register(&self, name: &str, quantum: bool) -> crate::error::ParseResult<Register>
+    {
+        let regs = if quantum { &self.qregs } else { &self.cregs };
+        regs.iter().find(|(n, _)| n == name)
+            .map(|&(_, reg)| reg)
+            .ok_or_else(|| crate::error::ParseError::UndefinedRegister(String::from(name)))
+    }
+
+    /// Find the bits referred to by `operand`, which is either a single bit
+    /// `name[idx]`, or a full register `name`.
+    fn bits(&self, operand: &str, quantum: bool) -> crate::error::ParseResult<Vec<usize>>
+    {
+        let captures = PATTERNS.operand.captures(operand.trim())
+            .ok_or_else(|| invalid(operand))?;
+        let reg = self.register(&captures[1], quantum)?;
+        match captures.get(2)
+        {
+            Some(m) => {
+                match m.as_str().parse::<usize>()
+                {
+                    Ok(idx) if idx < reg.size => Ok(vec![reg.offset + idx]),
+                    _ => Err(crate::error::ParseError::InvalidBit(String::from(operand.trim())))
+                }
+            },
+            None => Ok((reg.offset..reg.offset+reg.size).collect())
+        }
+    }
+}
+
+/// Create an error for the invalid OpenQasm text `text`
+fn invalid(text: &str) -> crate::error::ParseError
+{
+    crate::error::ParseError::InvalidOpenQasm(String::from(text))
+}
+
+/// Create an error for the unsupported OpenQasm feature `what`
+fn unsupported(what: &str) -> crate::error::ParseError
+{
+    crate::error::ParseError::UnsupportedOpenQasm(String::from(what))
+}
+
+/// Remove comments from OpenQasm program `src`, and split the result into
+/// statements terminated by a semicolon. The body of a gate definition is
+/// kept in the definition statement, which ends with the closing brace.
+/// Returns the statements, and the text after the last statement.
+fn statements(src: &str) -> (Vec<String>, String)
+{
+    let code: Vec<&str> = src.lines()
+        .map(|line| line.find("//").map_or(line, |pos| &line[..pos]))
+        .collect();
+    let code = code.join("\n");
+
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (pos, c) in code.char_indices()
+    {
+        match c
+        {
+            '{' => { depth += 1; },
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0
+                {
+                    parts.push(&code[start..=pos]);
+                    start = pos + 1;
+                }
+            },
+            ';' if depth == 0 => {
+                parts.push(&code[start..pos]);
+                start = pos + 1;
+            },
+            _ => { }
+        }
+    }
+
+    let normalize = |stmt: &str| stmt.split_whitespace().collect::<Vec<&str>>().join(" ");
+    (parts.into_iter().map(normalize).collect(), normalize(&code[start..]))
+}
+
+/// Split the text `text` at the commas that are not enclosed in parentheses.
+fn split_top_level(text: &str) -> Vec<&str>
+{
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (pos, c) in text.char_indices()
+    {
+        match c
+        {
+            '(' => { depth += 1; },
+            ')' => { depth -= 1; },
+            ',' if depth == 0 => {
+                parts.push(&text[start..pos]);
+                start = pos + 1;
+            },
+            _ => { }
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Evaluate the comma separated list of gate arguments in `text`.
+fn parse_args(text: &str) -> crate::error::ParseResult<Vec<f64>>
+{
+    split_top_level(text).iter()
+        .map(|arg| {
+            let (expr, rest) = crate::expression::Expression::parse(arg)?;
+            if !rest.trim().is_empty()
+            {
+                return Err(crate::error::ParseError::InvalidArgument(String::from(arg.trim())));
+            }
+            expr.eval()
+                .map_err(|_| crate::error::ParseError::InvalidArgument(String::from(arg.trim())))
+        })
+        .collect()
+}
+
+/// Create the gate with OpenQasm name `name`, with arguments `args`. The gate
+/// is either one of the gates defined in the program in `defs`, or one from
+/// the `qelib1.inc` standard library.
+fn make_gate(name: &str, args: &[f64], defs: &Definitions)
+    -> crate::error::ParseResult<Box<dyn CircuitGate>>
+{
+    if let Some((_, gate)) = defs.iter().find(|(n, _)| n == name)
+    {
+        if !args.is_empty()
+        {
+            return Err(crate::error::ParseError::InvalidNrArguments(args.len(), 0,
+                String::from(name)));
+        }
+        return Ok(Box::new(gate.clone()));
+    }
+
+    let nr_args = match name
+        {
+            "u3" | "U" | "cu3" => 3,
+            "u2" => 2,
+            "u1" | "rx" | "ry" | "rz" | "cu1" | "crz" => 1,
+            _ => 0
+        };
+    if args.len() != nr_args
+    {
+        return Err(crate::error::ParseError::InvalidNrArguments(args.len(), nr_args,
+            String::from(name)));
+    }
+
+    let gate: Box<dyn CircuitGate> = match name
+        {
+            "u3" | "U" => Box::new(crate::gates::U3::new(args[0], args[1], args[2])),
+            "u2" => Box::new(crate::gates::U2::new(args[0], args[1])),
+            "u1" => Box::new(crate::gates::U1::new(args[0])),
+            "rx" => Box::new(crate::gates::RX::new(args[0])),
+            "ry" => Box::new(crate::gates::RY::new(args[0])),
+            "rz" => Box::new(crate::gates::RZ::new(args[0])),
+            "id" => Box::new(crate::gates::I::new()),
+            "x" => Box::new(crate::gates::X::new()),
+            "y" => Box::new(crate::gates::Y::new()),
+            "z" => Box::new(crate::gates::Z::new()),
+            "h" => Box::new(crate::gates::H::new()),
+            "s" => Box::new(crate::gates::S::new()),
+            "sdg" => Box::new(crate::gates::Sdg::new()),
+            "t" => Box::new(crate::gates::T::new()),
+            "tdg" => Box::new(crate::gates::Tdg::new()),
+            "cx" | "CX" => Box::new(crate::gates::CX::new()),
+            "cy" => Box::new(crate::gates::CY::new()),
+            "cz" => Box::new(crate::gates::CZ::new()),
+            "ch" => Box::new(crate::gates::CH::new()),
+            "crz" => Box::new(crate::gates::CRZ::new(args[0])),
+            "cu1" => Box::new(crate::gates::CU1::new(args[0])),
+            "cu3" => Box::new(crate::gates::CU3::new(args[0], args[1], args[2])),
+            "swap" => Box::new(crate::gates::Swap::new()),
+            "ccx" => Box::new(crate::gates::CCX::new()),
+            "cswap" => Box::new(crate::gates::CSwap::new()),
+            _ => { return Err(crate::error::ParseError::UnknownGate(String::from(name))); }
+        };
+    Ok(gate)
+}
+
+/// Expand the operands in `operands` to sets of bits on which a single
+/// operation acts. Operands that refer to a full register cause the
+/// operation to be applied to each bit in the register, and all such registers
+/// should have the same size.
+fn broadcast(operands: &[Vec<usize>], stmt: &str)
+    -> crate::error::ParseResult<Vec<Vec<usize>>>
+{
+    let n = operands.iter().map(|bits| bits.len()).max().unwrap_or(0);
+    if operands.iter().any(|bits| bits.len() != 1 && bits.len() != n)
+    {
+        return Err(invalid(stmt));
+    }
+
+    Ok((0..n).map(|i| {
+        operands.iter().map(|bits| if bits.len() == 1 { bits[0] } else { bits[i] }).collect()
+    }).collect())
+}
+
+/// Convert an error from adding an operation to a circuit into a parse
+/// error for statement `stmt`.
+fn circuit_error(err: crate::error::Error, stmt: &str) -> crate::error::ParseError
+{
+    match err
+    {
+        crate::error::Error::InvalidNrBits(actual, expected, desc) => {
+            crate::error::ParseError::InvalidNrBits(actual, expected, desc)
+        },
+        _ => invalid(stmt)
+    }
+}
+
+/// Parse the gate application in statement `stmt`, with operands in the
+/// quantum registers in `regs`. Returns the gate, and the sets of bits it
+/// is applied to.
+fn gate_application(regs: &Registers, defs: &Definitions, stmt: &str)
+    -> crate::error::ParseResult<GateApplication>
+{
+    let captures = PATTERNS.gate.captures(stmt).ok_or_else(|| invalid(stmt))?;
+    let name = &captures[1];
+    let args = match captures.get(2)
+        {
+            Some(m) => parse_args(m.as_str())?,
+            None => vec![]
+        };
+    let gate = make_gate(name, &args, defs)?;
+
+    let operands = captures[3].split(',')
+        .map(|operand| regs.bits(operand, true))
+        .collect::<crate::error::ParseResult<Vec<Vec<usize>>>>()?;
+    let bit_sets = broadcast(&operands, stmt)?;
+    for bits in bit_sets.iter()
+    {
+        gate.check_nr_bits(bits.len()).map_err(|err| circuit_error(err, stmt))?;
+    }
+
+    Ok((gate, bit_sets))
+}
+
+/// Add the gate application in statement `stmt` to `circuit`. If `condition`
+/// is not `None`, the gate is only executed when the classical bits in the
+/// condition hold the target value.
+fn add_gate(circuit: &mut crate::circuit::Circuit, regs: &Registers, defs: &Definitions,
+    stmt: &str, condition: Option<(&[usize], u64)>) -> crate::error::ParseResult<()>
+{
+    let (gate, bit_sets) = gate_application(regs, defs, stmt)?;
+    for bits in bit_sets
+    {
+        let res = match condition
+            {
+                Some((control, target)) => {
+                    circuit.add_conditional_gate(control, target, gate.clone(), &bits)
+                },
+                None => circuit.add_gate(gate.clone(), &bits)
+            };
+        res.map_err(|err| circuit_error(err, stmt))?;
+    }
+
+    Ok(())
+}
+
+/// Add the gate defined in statement `stmt` to the definitions in `defs`. The
+/// gate is stored as a composite gate, with the gates in the body of the
+/// definition acting on the arguments of the gate. Parameterized gates are not
+/// supported.
+fn define_gate(defs: &mut Definitions, stmt: &str) -> crate::error::ParseResult<()>
+{
+    let captures = PATTERNS.definition.captures(stmt).ok_or_else(|| invalid(stmt))?;
+    let name = &captures[1];
+    if captures.get(2).map_or(false, |m| !m.as_str().trim().is_empty())
+    {
+        return Err(unsupported("parameterized gate definition"));
+    }
+    if defs.iter().any(|(n, _)| n == name)
+    {
+        return Err(invalid(stmt));
+    }
+
+    // The arguments act as single bit registers local to the definition
+    let mut args = Registers::new();
+    for arg in captures[3].split(',').map(|arg| arg.trim())
+    {
+        if PATTERNS.operand.captures(arg).map_or(true, |c| c.get(2).is_some())
+        {
+            return Err(invalid(stmt));
+        }
+        args.declare(arg, 1, true)?;
+    }
+
+    let mut gate = crate::gates::Composite::new(name, args.nr_qbits);
+    let (body, rest) = statements(&captures[4]);
+    if !rest.is_empty()
+    {
+        return Err(invalid(&rest));
+    }
+    for body_stmt in body.iter()
+    {
+        if body_stmt.starts_with("barrier")
+        {
+            return Err(unsupported("barrier in gate definition"));
+        }
+        let (sub_gate, bit_sets) = gate_application(&args, defs, body_stmt)?;
+        for bits in bit_sets
+        {
+            gate.add_gate(sub_gate.clone(), &bits);
+        }
+    }
+
+    defs.push((String::from(name), gate));
+    Ok(())
+}
+
+/// Add the measurement in statement `stmt`, measuring the qubits in `qarg`
+/// into classical bits `carg`, to `circuit`.
+fn add_measurement(circuit: &mut crate::circuit::Circuit, regs: &Registers, stmt: &str,
+    qarg: &str, carg: &str) -> crate::error::ParseResult<()>
+{
+    let qbits = regs.bits(qarg, true)?;
+    let cbits = regs.bits(carg, false)?;
+    if qbits.len() != cbits.len()
+    {
+        return Err(invalid(stmt));
+    }
+
+    if qbits.len() > 1 && qbits.len() == circuit.nr_qbits()
+    {
+        circuit.measure_all(&cbits)
+    }
+    else
+    {
+        qbits.iter().zip(cbits.iter())
+            .try_for_each(|(&qbit, &cbit)| circuit.measure(qbit, cbit))
+    }.map_err(|err| circuit_error(err, stmt))
+}
+
+/// Add the operation in statement `stmt` to `circuit`, or the gate defined in
+/// it to `defs`.
+fn add_statement(circuit: &mut crate::circuit::Circuit, regs: &Registers,
+    defs: &mut Definitions, stmt: &str) -> crate::error::ParseResult<()>
+{
+    let keyword = stmt.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    match keyword
+    {
+        "OPENQASM" | "include" | "qreg" | "creg" => Ok(()),
+        "measure" => {
+            let captures = PATTERNS.measure.captures(stmt).ok_or_else(|| invalid(stmt))?;
+            add_measurement(circuit, regs, stmt, &captures[1], &captures[2])
+        },
+        "reset" => {
+            let qbits = regs.bits(&stmt["reset".len()..], true)?;
+            if qbits.len() > 1 && qbits.len() == circuit.nr_qbits()
+            {
+                circuit.reset_all();
+                Ok(())
+            }
+            else
+            {
+                qbits.iter().try_for_each(|&qbit| circuit.reset(qbit))
+                    .map_err(|err| circuit_error(err, stmt))
+            }
+        },
+        "barrier" => {
+            let mut qbits = vec![];
+            for operand in stmt["barrier".len()..].split(',')
+            {
+                qbits.extend(regs.bits(operand, true)?);
+            }
+            circuit.barrier(&qbits).map_err(|err| circuit_error(err, stmt))
+        },
+        "if" => {
+            let captures = PATTERNS.condition.captures(stmt).ok_or_else(|| invalid(stmt))?;
+            let reg = regs.register(&captures[1], false)?;
+            let target = captures[2].parse::<u64>().map_err(|_| invalid(stmt))?;
+            let control: Vec<usize> = (reg.offset..reg.offset+reg.size).collect();
+            let body = &captures[3];
+            if body.starts_with("measure") || body.starts_with("reset")
+            {
+                Err(unsupported("conditional measurement or reset"))
+            }
+            else
+            {
+                add_gate(circuit, regs, defs, body, Some((&control, target)))
+            }
+        },
+        "gate" => define_gate(defs, stmt),
+        "opaque" => Err(unsupported("opaque gate definition")),
+        _ => add_gate(circuit, regs, defs, stmt, None)
+    }
+}
+
+/// Parse a circuit in OpenQasm 2.0 format.
+///
+/// Create a new circuit from the OpenQasm 2.0 program `src`. See
+/// `Circuit::from_open_qasm()` for details.
+pub fn circuit_from_open_qasm(src: &str) -> crate::error::ParseResult<crate::circuit::Circuit>
+{
+    let (stmts, rest) = statements(src);
+
+    match stmts.first()
+    {
+        Some(stmt) if stmt.starts_with("OPENQASM") => {
+            if stmt != "OPENQASM 2.0"
+            {
+                return Err(unsupported(stmt));
+            }
+        },
+        Some(stmt) => { return Err(invalid(stmt)); },
+        None => { return Err(invalid(src)); }
+    }
+
+    let mut regs = Registers::new();
+    for stmt in stmts.iter().filter(|stmt| stmt.starts_with("qreg") || stmt.starts_with("creg"))
+    {
+        let captures = PATTERNS.declaration.captures(stmt).ok_or_else(|| invalid(stmt))?;
+        let size = captures[3].parse::<usize>().map_err(|_| invalid(stmt))?;
+        regs.declare(&captures[2], size, &captures[1] == "qreg")?;
+    }
+
+    let mut circuit = crate::circuit::Circuit::new(regs.nr_qbits, regs.nr_cbits);
+    let mut defs = Definitions::new();
+    for stmt in stmts.iter().skip(1)
+    {
+        add_statement(&mut circuit, &regs, &mut defs, stmt)?;
+    }
+    // The text after the last semicolon should be empty
+    if !rest.is_empty()
+    {
+        return Err(invalid(&rest));
+    }
+
+    Ok(circuit)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::circuit_from_open_qasm;
+    use crate::error::ParseError;
+
+    /// The instructions in `circuit`, formatted as strings
+    fn instructions(circuit: &crate::circuit::Circuit) -> Vec<String>
+    {
+        circuit.to_instructions().iter().map(|instr| format!("{:?}", instr)).collect()
+    }
+
+    #[test]
+    fn test_bell()
+    {
+        let src = r#"OPENQASM 2.0;
+include "qelib1.inc";
+// Create a Bell state
+qreg q[2];
+creg b[2];
+h q[0];
+cx q[0], q[1]; // entangle
+measure q -> b;
+"#;
+        let mut circuit = circuit_from_open_qasm(src).unwrap();
+        assert_eq!(circuit.nr_qbits(), 2);
+        assert_eq!(circuit.nr_cbits(), 2);
+        assert_eq!(instructions(&circuit), vec!["H(0)", "CX(0, 1)", "MeasureAll([0, 1], Z)"]);
+
+        assert_eq!(circuit.execute(100), Ok(()));
+        let hist = circuit.histogram().unwrap();
+        assert_eq!(hist.values().sum::<usize>(), 100);
+        assert!(hist.keys().all(|&key| key == 0 || key == 3));
+    }
+
+    #[test]
+    fn test_registers()
+    {
+        let src = "OPENQASM 2.0; qreg a[2]; qreg b[1]; creg c[1]; creg d[2];
+            x b[0]; cx a, b; barrier a, b[0]; measure a -> d; measure b[0] -> c[0]; reset a;";
+        let circuit = circuit_from_open_qasm(src).unwrap();
+        assert_eq!(circuit.nr_qbits(), 3);
+        assert_eq!(circuit.nr_cbits(), 3);
+        assert_eq!(instructions(&circuit), vec![
+            "X(2)", "CX(0, 2)", "CX(1, 2)", "Barrier([0, 1, 2])",
+            "Measure(0, 1, Z)", "Measure(1, 2, Z)", "Measure(2, 0, Z)", "Reset(0)", "Reset(1)"
+        ]);
+    }
+
+    #[test]
+    fn test_gates()
+    {
+        let src = "OPENQASM 2.0;
+            qreg q[3];
+            creg c[2];
+            u3(pi/2, -pi/2, 0.5) q[0];
+            rz(-0.25) q[1];
+            U(0, 0, 0) q[2];
+            crz(sin(pi/6)) q[0], q[1];
+            ccx q[0], q[1], q[2];
+            h q;
+            if (c == 2) x q[1];
+            reset q;
+        ";
+        let circuit = circuit_from_open_qasm(src).unwrap();
+        let instrs = instructions(&circuit);
+        assert_eq!(instrs.len(), 10);
+        assert_eq!(instrs[0], "U3(1.5707963267948966, -1.5707963267948966, 0.5, 0)");
+        assert_eq!(instrs[1], "RZ(-0.25, 1)");
+        assert_eq!(instrs[2], "U3(0.0, 0.0, 0.0, 2)");
+        assert_eq!(instrs[4], "CCX(0, 1, 2)");
+        assert_eq!(&instrs[5..8], &["H(0)", "H(1)", "H(2)"]);
+        assert_eq!(instrs[9], "ResetAll");
+    }
+
+    #[test]
+    fn test_definitions()
+    {
+        let src = "OPENQASM 2.0;
+            include \"qelib1.inc\";
+            gate flip a { x a; }
+            gate bell a, b
+            {
+                h a;
+                cx a, b;
+                flip b; // flip the target
+            }
+            qreg q[2];
+            qreg r[2];
+            bell q[1], q[0];
+            bell q, r;
+        ";
+        let circuit = circuit_from_open_qasm(src).unwrap();
+        assert_eq!(instructions(&circuit), vec![
+            "H(1)", "CX(1, 0)", "X(0)", "H(0)", "CX(0, 2)", "X(2)", "H(1)", "CX(1, 3)", "X(3)"
+        ]);
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+gate flip a0 { x a0; }
+gate bell a0, a1 { h a0; cx a0, a1; flip a1; }
+qreg q[4];
+bell q[1], q[0];
+bell q[0], q[2];
+bell q[1], q[3];
+"#)));
+    }
+
+    #[test]
+    fn test_round_trip()
+    {
+        let mut circuit = crate::circuit::Circuit::new(3, 3);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 2]), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert_eq!(circuit.reset(1), Ok(()));
+        assert_eq!(circuit.measure_all(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::U3::new(0.3, -1.2, 2.5), &[1]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::CU1::new(0.7), &[2, 0]), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::RX::new(-0.1), &[0]), Ok(()));
+        assert_eq!(circuit.add_conditional_gate(&[0, 1, 2], 5, crate::gates::Z::new(), &[1]),
+            Ok(()));
+
+        let qasm = circuit.open_qasm().unwrap();
+        let imported = circuit_from_open_qasm(&qasm).unwrap();
+        assert_eq!(imported.nr_qbits(), circuit.nr_qbits());
+        assert_eq!(imported.nr_cbits(), circuit.nr_cbits());
+        assert_eq!(instructions(&imported), instructions(&circuit));
+        assert_eq!(imported.open_qasm(), Ok(qasm));
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        assert_eq!(circuit_from_open_qasm("qreg q[1];").map(|_| ()),
+            Err(ParseError::InvalidOpenQasm(String::from("qreg q[1]"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 3.0;").map(|_| ()),
+            Err(ParseError::UnsupportedOpenQasm(String::from("OPENQASM 3.0"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; h q[0]").map(|_| ()),
+            Err(ParseError::InvalidOpenQasm(String::from("h q[0]"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; h r[0];").map(|_| ()),
+            Err(ParseError::UndefinedRegister(String::from("r"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; h q[1];").map(|_| ()),
+            Err(ParseError::InvalidBit(String::from("q[1]"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; foo q[0];").map(|_| ()),
+            Err(ParseError::UnknownGate(String::from("foo"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[2]; cx q[0];").map(|_| ()),
+            Err(ParseError::InvalidNrBits(1, 2, String::from("CX"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; rx q[0];").map(|_| ()),
+            Err(ParseError::InvalidNrArguments(0, 1, String::from("rx"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; rx(x) q[0];").map(|_| ()),
+            Err(ParseError::InvalidArgument(String::from("x"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; gate g(t) a { rx(t) a; }")
+            .map(|_| ()),
+            Err(ParseError::UnsupportedOpenQasm(String::from("parameterized gate definition"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; opaque g a;").map(|_| ()),
+            Err(ParseError::UnsupportedOpenQasm(String::from("opaque gate definition"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; gate g a { h b; }").map(|_| ()),
+            Err(ParseError::UndefinedRegister(String::from("b"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[1]; gate g a { h a; } g(0.5) q[0];")
+            .map(|_| ()), Err(ParseError::InvalidNrArguments(1, 0, String::from("g"))));
+        assert_eq!(circuit_from_open_qasm("OPENQASM 2.0; qreg q[2]; qreg r[3]; cx q, r;")
+            .map(|_| ()), Err(ParseError::InvalidOpenQasm(String::from("cx q, r"))));
+    }
+}
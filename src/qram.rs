@@ -0,0 +1,241 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gates::{CSwap, Swap, CCX, X};
+
+/// Return the router operations in the bucket brigade routing for
+/// `address_bits` address bits, as a list of `(control, bit0, bit1)` tuples.
+/// A `Some` control denotes a controlled swap, `None` a plain swap.
+fn routing_swaps(address_bits: usize, router_offset: usize)
+    -> Vec<(Option<usize>, usize, usize)>
+{
+    let mut swaps = vec![];
+    for level in 0..address_bits-1
+    {
+        for node in (1 << level) - 1 .. (1 << (level + 1)) - 1
+        {
+            let router = router_offset + node;
+            // Move the flag to the right child if the address bit is set,
+            // otherwise to the left child
+            swaps.push((Some(level), router, router_offset + 2*node + 2));
+            swaps.push((None, router, router_offset + 2*node + 1));
+        }
+    }
+    swaps
+}
+
+/// Add the routing operations in `swaps` to `circuit`, in order
+fn add_routing(circuit: &mut crate::circuit::Circuit,
+    swaps: &[(Option<usize>, usize, usize)]) -> crate::error::Result<()>
+{
+    for &(control, bit0, bit1) in swaps
+    {
+        if let Some(control) = control
+        {
+            circuit.add_gate(CSwap::new(), &[control, bit0, bit1])?;
+        }
+        else
+        {
+            circuit.add_gate(Swap::new(), &[bit0, bit1])?;
+        }
+    }
+    Ok(())
+}
+
+/// Add a bucket brigade qRAM query to `circuit`.
+///
+/// The address register consists of qubits `0..address_bits`, followed by the
+/// `data_bits` qubits of the output register, and the `2`<sup>`n`</sup>`-1`
+/// router qubits of the tree. See `bucket_brigade_qram()`.
+fn add_bucket_brigade_qram(circuit: &mut crate::circuit::Circuit, data: &[u64],
+    address_bits: usize, data_bits: usize) -> crate::error::Result<()>
+{
+    if address_bits == 0
+    {
+        let value = data.first().cloned().unwrap_or(0);
+        for i in (0..data_bits).filter(|i| (value >> (data_bits - 1 - i)) & 1 == 1)
+        {
+            circuit.x(address_bits + i)?;
+        }
+        return Ok(());
+    }
+
+    let router_offset = address_bits + data_bits;
+    let last = address_bits - 1;
+
+    // Route a flag from the root of the tree to the leaf on the path
+    // determined by the first `n-1` address bits
+    let mut swaps = routing_swaps(address_bits, router_offset);
+    circuit.add_gate(X::new(), &[router_offset])?;
+    add_routing(circuit, &swaps)?;
+
+    // Every leaf holds two data entries, selected by the last address bit
+    let first_leaf = (1 << last) - 1;
+    for leaf in 0..1 << last
+    {
+        let router = router_offset + first_leaf + leaf;
+        for low in 0..2
+        {
+            let value = data.get(2*leaf + low).cloned().unwrap_or(0);
+            let set_bits: Vec<usize> = (0..data_bits)
+                .filter(|i| (value >> (data_bits - 1 - i)) & 1 == 1)
+                .collect();
+            if set_bits.is_empty()
+            {
+                continue;
+            }
+
+            if low == 0
+            {
+                circuit.x(last)?;
+            }
+            for i in set_bits
+            {
+                circuit.add_gate(CCX::new(), &[last, router, address_bits + i])?;
+            }
+            if low == 0
+            {
+                circuit.x(last)?;
+            }
+        }
+    }
+
+    // Undo the routing, returning the routers to |0⟩
+    swaps.reverse();
+    add_routing(circuit, &swaps)?;
+    circuit.add_gate(X::new(), &[router_offset])?;
+
+    Ok(())
+}
+
+/// Bucket brigade quantum random access memory.
+///
+/// Create a circuit implementing a bucket brigade qRAM query on the classical
+/// data in `data`, mapping |j⟩|0⟩ to |j⟩|`data[j]`⟩. The circuit operates on
+/// an address register of `n = address_bits` qubits (qubits `0..n`), a data
+/// register of `data_bits` qubits (the next `data_bits` qubits), and
+/// `2`<sup>`n`</sup>`-1` router qubits arranged in a binary tree (the final
+/// qubits in the circuit). In both registers, the first qubit holds the most
+/// significant bit. Starting from the root, the address bits are routed down
+/// the tree using Fredkin gates, one level of the tree for each address bit.
+/// The data is then copied into the data register from the leaves, after
+/// which the routing is undone, leaving the routers in |0⟩.
+///
+/// Note that although the routing proceeds level by level, in `n` steps, the
+/// depth of the circuit is `O(2`<sup>`n`</sup>` · data_bits)`, not `O(n)`.
+/// All Fredkin gates on a level of the tree are controlled by the same
+/// address qubit, and all leaves write into the same data register, so the
+/// gates within a level cannot be executed in parallel. A depth of `O(n)`
+/// requires the address qubits themselves to be routed down the tree, and the
+/// data to be routed back up, which takes an extra carrier qubit for every
+/// router next to the `2`<sup>`n`</sup>`-1` routers used here.
+///
+/// Entries of `data` beyond `2`<sup>`n`</sup> are ignored, missing entries are
+/// taken to be zero, and only the lowest `data_bits` bits of each entry are
+/// loaded.
+pub fn bucket_brigade_qram(data: &[u64], address_bits: usize, data_bits: usize)
+    -> crate::circuit::Circuit
+{
+    let nr_routers = (1 << address_bits) - 1;
+    let mut circuit = crate::circuit::Circuit::new(address_bits + data_bits + nr_routers, 0);
+    // All qubit indices are valid, so this cannot fail
+    add_bucket_brigade_qram(&mut circuit, data, address_bits, data_bits).unwrap();
+    circuit
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{add_bucket_brigade_qram, bucket_brigade_qram};
+    use crate::circuit::Circuit;
+
+    #[test]
+    fn test_routers()
+    {
+        for &(address_bits, data_bits) in [(0, 2), (1, 3), (2, 3), (3, 1)].iter()
+        {
+            let circuit = bucket_brigade_qram(&[1, 0, 3], address_bits, data_bits);
+            assert_eq!(circuit.nr_qbits(), address_bits + data_bits + (1 << address_bits) - 1);
+        }
+    }
+
+    #[test]
+    fn test_superposition()
+    {
+        let data = [5, 0, 2, 7];
+        let (address_bits, data_bits) = (2, 3);
+        let nr_routers = 3;
+        let nr_qbits = address_bits + data_bits + nr_routers;
+
+        let mut circuit = Circuit::new(nr_qbits, 0);
+        for bit in 0..address_bits
+        {
+            circuit.h(bit).unwrap();
+        }
+        assert_eq!(add_bucket_brigade_qram(&mut circuit, &data, address_bits, data_bits), Ok(()));
+
+        let rho = circuit.density_matrix().unwrap();
+        for idx in 0..1 << nr_qbits
+        {
+            let address = idx >> (data_bits + nr_routers);
+            let value = (idx >> nr_routers) & ((1 << data_bits) - 1);
+            let routers = idx & ((1 << nr_routers) - 1);
+            let expected = if routers == 0 && value as u64 == data[address] { 0.25 } else { 0.0 };
+            assert!((rho.matrix()[[idx, idx]].re - expected).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_measure()
+    {
+        let nr_shots = 4096;
+        let tol = 1.0e-5;
+
+        let data = [6, 1, 3, 4, 0, 7, 2, 5];
+        let (address_bits, data_bits) = (3, 3);
+        let nr_measured = address_bits + data_bits;
+        let nr_qbits = nr_measured + 7;
+
+        let mut circuit = Circuit::new(nr_qbits, nr_measured);
+        for bit in 0..address_bits
+        {
+            circuit.h(bit).unwrap();
+        }
+        assert_eq!(add_bucket_brigade_qram(&mut circuit, &data, address_bits, data_bits), Ok(()));
+        for bit in 0..nr_measured
+        {
+            circuit.measure(bit, nr_measured - 1 - bit).unwrap();
+        }
+        assert_eq!(circuit.execute(nr_shots), Ok(()));
+
+        let hist = circuit.histogram().unwrap();
+        let mut keys: Vec<u64> = hist.keys().cloned().collect();
+        keys.sort();
+        let expected: Vec<u64> = data.iter().enumerate()
+            .map(|(j, &v)| ((j as u64) << data_bits) | v)
+            .collect();
+        assert_eq!(keys, expected);
+        assert!(hist.values().all(
+            |&count| crate::stats::measurement_ok(count, nr_shots, 0.125, tol)
+        ));
+    }
+
+    #[test]
+    fn test_no_address_bits()
+    {
+        let circuit = bucket_brigade_qram(&[5], 0, 3);
+        let rho = circuit.density_matrix().unwrap();
+        assert!((rho.matrix()[[5, 5]].re - 1.0).abs() < 1.0e-12);
+    }
+}
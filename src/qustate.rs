@@ -17,6 +17,16 @@ pub trait QuState
     fn apply_conditional_gate<G>(&mut self, control: &[bool], gate: &G,
         bits: &[usize]) -> crate::error::Result<()>
     where G: crate::gates::Gate + ?Sized;
+    /// Apply a noise channel.
+    ///
+    /// Apply the noise channel described by Kraus operators `kraus` on the
+    /// qubits in `bits`. Representations that can only describe pure states
+    /// ignore noise, which is the default.
+    fn apply_channel(&mut self, _kraus: &[crate::cmatrix::CMatrix], _bits: &[usize])
+        -> crate::error::Result<()>
+    {
+        Ok(())
+    }
 
     /// Measure a qubit.
     ///
@@ -74,6 +84,39 @@ pub trait QuState
     fn peek_all_into<R: rand::Rng>(&mut self, cbits: &[usize],
         res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>;
 
+    /// Measure a subset of qubits
+    ///
+    /// Measure the qubits in `qbits` without affecting the quantum state, i.e.
+    /// without collapsing the wave function. The outcomes are sampled from
+    /// the marginal probability distribution of the measured qubits, so that
+    /// correlations between them are retained. The result of measuring qubit
+    /// `qbits[i]` is stored at bit position `cbits[i]` in `res`, which must be
+    /// of sufficient length to hold results for the total number of runs in the
+    /// state. The random number generator `rng` is used for sampling.
+    /// NOTE: this is not a physical process, and impossible to reproduce on
+    /// a real quantum computer.
+    fn partial_measure_peek<R: rand::Rng>(&self, qbits: &[usize], cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>;
+
+    /// Compute an expectation value
+    ///
+    /// Compute the expectation value ⟨`obs`⟩ of Pauli string `obs` in each
+    /// run, without affecting the quantum state, and store the result in bit
+    /// `cbit` of `res`: 1 if the expectation value is positive, 0 otherwise.
+    /// The output array `res` should be of sufficient length to store results
+    /// for the total number of runs in the state.
+    /// NOTE: this is not a physical process, and impossible to reproduce on
+    /// a real quantum computer.
+    fn expectation_into(&self, obs: &crate::stabilizer::PauliString, cbit: usize,
+        res: &mut ndarray::Array1<u64>) -> crate::error::Result<()>;
+
+    /// Compute an average expectation value
+    ///
+    /// Compute the expectation value ⟨`obs`⟩ of Pauli string `obs` in the
+    /// current state, averaged over all runs.
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString)
+        -> crate::error::Result<f64>;
+
     /// Reset a qubit
     ///
     /// Reset the qubit with index `bit` to zero. This is done by measuring the
@@ -86,6 +129,69 @@ pub trait QuState
     /// Reset all qubits in this experiment, returning the state to |00...0⟩
     /// for all runs.
     fn reset_all(&mut self);
+
+    /// Check whether runs have diverged
+    ///
+    /// Initially, all runs of an experiment share a single quantum state,
+    /// which needs to be evolved only once. When measurement results differ
+    /// between runs, or conditional gates are applied in some runs only, the
+    /// runs are split over separately evolving states. This function returns
+    /// `true` if this has happened, i.e. when the runs are described by more
+    /// than one state.
+    fn are_shots_entangled(&self) -> bool;
+
+    /// The state vector of a single run
+    ///
+    /// Return the coefficient vector of the quantum state in run `shot`, or
+    /// `None` if `shot` is not a valid run index, or if this representation
+    /// does not store state vectors, which is the default.
+    fn shot_statevector(&self, _shot: usize)
+        -> Option<ndarray::ArrayView1<'_, crate::cmatrix::CNumber>>
+    {
+        None
+    }
+
+    /// The state vector of a single run experiment
+    ///
+    /// Return the normalized coefficient vector of the quantum state when
+    /// the experiment consists of a single run, or `None` when there are
+    /// multiple runs, or the state is not a pure state.
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>;
+
+    /// Fidelity with a pure state
+    ///
+    /// Compute the fidelity `|⟨ψ|ϕ⟩|²` between the current state `ψ` of a
+    /// single run experiment, as returned by `statevector()`, and the
+    /// normalized state vector `target`. A `NoPureState` error is returned
+    /// when there are multiple runs, the state is not a pure state, or the
+    /// state is known to have been collapsed by a measurement, and a
+    /// `DimensionMismatch` error when the length of `target` does not match
+    /// the size of the state.
+    fn fidelity_with_pure_state(&self, target: &crate::cmatrix::CVector)
+        -> crate::error::Result<f64>
+    {
+        let psi = self.statevector().ok_or(crate::error::Error::NoPureState)?;
+        crate::measures::statevector_fidelity(&psi, target)
+    }
+}
+
+/// Find the state for a run
+///
+/// Given the run counts `counts` for each of the separate states in an
+/// experiment, return the index of the state used in run `shot`, or `None` if
+/// the total number of runs is not larger than `shot`.
+pub(crate) fn shot_state_index(counts: &[usize], shot: usize) -> Option<usize>
+{
+    let mut end = 0;
+    for (idx, &count) in counts.iter().enumerate()
+    {
+        end += count;
+        if shot < end
+        {
+            return Some(idx);
+        }
+    }
+    None
 }
 
 /// Collect which states to apply conditional gate to into ranges
@@ -0,0 +1,434 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rand::seq::SliceRandom;
+use rand_distr::Distribution;
+
+/// Haar random unitary.
+///
+/// Generate a unitary gate on `nr_bits` qubits, sampled uniformly from the
+/// Haar measure on the unitary group. The unitary is obtained as the `Q`
+/// factor in the QR decomposition of a matrix with independent complex
+/// Gaussian entries, where the (real and positive) diagonal of `R` ensures
+/// that the result is distributed uniformly.
+pub fn haar_random_unitary<R: rand::Rng>(nr_bits: usize, rng: &mut R) -> crate::gates::Custom
+{
+    let size = 1 << nr_bits;
+    let normal = rand_distr::StandardNormal;
+    let scale = ::std::f64::consts::FRAC_1_SQRT_2;
+    let z = crate::cmatrix::CMatrix::from_shape_fn((size, size), |_| {
+        let re: f64 = normal.sample(rng);
+        let im: f64 = normal.sample(rng);
+        crate::cmatrix::CNumber::new(scale * re, scale * im)
+    });
+    let (q, _) = crate::linalg::qr(&z);
+    crate::gates::Custom::new("Haar", q)
+}
+
+/// Random circuit.
+///
+/// Generate a random circuit on `nr_bits` qubits, consisting of `depth`
+/// layers. Each layer consists of an independent Haar random single-qubit
+/// gate on every qubit, followed by `CX` gates on `⌊nr_bits/2⌋` random,
+/// disjoint pairs of qubits.
+pub fn haar_random_circuit<R: rand::Rng>(nr_bits: usize, depth: usize, rng: &mut R)
+    -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(nr_bits, 0);
+    let mut bits: Vec<usize> = (0..nr_bits).collect();
+    // All qubit indices are valid, so adding gates cannot fail
+    for _ in 0..depth
+    {
+        for bit in 0..nr_bits
+        {
+            circuit.add_gate(haar_random_unitary(1, rng), &[bit]).unwrap();
+        }
+
+        bits.shuffle(rng);
+        for pair in bits.chunks_exact(2)
+        {
+            circuit.cx(pair[0], pair[1]).unwrap();
+        }
+    }
+
+    circuit
+}
+
+/// Random single-qubit Clifford.
+///
+/// Sample an element uniformly from the 24 single-qubit Clifford operations
+/// (up to a global phase), and return its tableau. Every element is written
+/// uniquely as a permutation of the Pauli axes, from the six operations
+/// `I`, `H`, `S`, `HS`, `SH` and `HSH`, followed by one of the Pauli
+/// operators `I`, `X`, `Y` or `Z` fixing the signs.
+pub fn random_clifford_1q<R: rand::Rng>(rng: &mut R) -> crate::stabilizer::CliffordTableau
+{
+    use crate::gates::{H, S, X, Y, Z};
+
+    let permutations: [&[&dyn crate::gates::Gate]; 6] = [
+        &[], &[&H::new()], &[&S::new()], &[&S::new(), &H::new()], &[&H::new(), &S::new()],
+        &[&H::new(), &S::new(), &H::new()]
+    ];
+    let paulis: [&[&dyn crate::gates::Gate]; 4] = [&[], &[&X::new()], &[&Y::new()], &[&Z::new()]];
+
+    let mut tableau = crate::stabilizer::CliffordTableau::new(1);
+    let idx = rng.gen_range(0, 24);
+    // All gates are Clifford gates on a valid qubit, so this cannot fail
+    for gate in permutations[idx / 4].iter().chain(paulis[idx % 4].iter())
+    {
+        tableau.apply_gate(*gate, &[0]).unwrap();
+    }
+    tableau
+}
+
+/// Symplectic inner product of vectors `v` and `w` over GF(2), where
+/// elements `2k` and `2k+1` hold the `X` and `Z` component for qubit `k`
+fn symplectic_inner(v: &[bool], w: &[bool]) -> bool
+{
+    v.chunks(2).zip(w.chunks(2)).fold(false, |acc, (a, b)| acc ^ (a[0] & b[1]) ^ (a[1] & b[0]))
+}
+
+/// Apply the symplectic transvection `v → v + ⟨k, v⟩k` to `v`
+fn transvection(k: &[bool], v: &mut [bool])
+{
+    if symplectic_inner(k, v)
+    {
+        for (x, &y) in v.iter_mut().zip(k.iter())
+        {
+            *x ^= y;
+        }
+    }
+}
+
+/// The sum of vectors `v` and `w` over GF(2)
+fn xor(v: &[bool], w: &[bool]) -> Vec<bool>
+{
+    v.iter().zip(w.iter()).map(|(&a, &b)| a ^ b).collect()
+}
+
+/// Find transvections `h0` and `h1` such that applying `h0` and then `h1` to
+/// the non-zero vector `x` results in the non-zero vector `y`. If a single
+/// transvection suffices, `h1` is zero.
+fn find_transvection(x: &[bool], y: &[bool]) -> (Vec<bool>, Vec<bool>)
+{
+    let n = x.len();
+    if x == y
+    {
+        return (vec![false; n], vec![false; n]);
+    }
+    if symplectic_inner(x, y)
+    {
+        return (xor(x, y), vec![false; n]);
+    }
+
+    // Find a vector z with ⟨x, z⟩ = ⟨z, y⟩ = 1, first trying a qubit on
+    // which both x and y are non-zero.
+    let mut z = vec![false; n];
+    let nonzero = |v: &[bool], i: usize| v[2*i] || v[2*i+1];
+    if let Some(i) = (0..n/2).find(|&i| nonzero(x, i) && nonzero(y, i))
+    {
+        z[2*i] = x[2*i] ^ y[2*i];
+        z[2*i+1] = x[2*i+1] ^ y[2*i+1];
+        if !z[2*i] && !z[2*i+1]
+        {
+            z[2*i+1] = true;
+            if x[2*i] != x[2*i+1]
+            {
+                z[2*i] = true;
+            }
+        }
+    }
+    else
+    {
+        // Use a qubit where only x is non-zero, and one where only y is
+        for (v, w) in [(x, y), (y, x)].iter()
+        {
+            if let Some(i) = (0..n/2).find(|&i| nonzero(v, i) && !nonzero(w, i))
+            {
+                if v[2*i] == v[2*i+1]
+                {
+                    z[2*i+1] = true;
+                }
+                else
+                {
+                    z[2*i+1] = v[2*i];
+                    z[2*i] = v[2*i+1];
+                }
+            }
+        }
+    }
+
+    (xor(x, &z), xor(y, &z))
+}
+
+/// Random symplectic matrix.
+///
+/// Sample a `2n×2n` symplectic matrix over GF(2) uniformly, using the
+/// algorithm of Koenig and Smolin (J. Math. Phys. 55, 122202 (2014)). The
+/// rows `2k` and `2k+1` of the result are the images of the `X` and `Z`
+/// operator on qubit `k`. The first two rows are chosen first, by mapping
+/// the first basis vector to a random non-zero vector, and its partner to a
+/// random vector with unit inner product. The remaining rows are found by
+/// recursion, and mapped into the complement of the first pair.
+fn random_symplectic<R: rand::Rng>(n: usize, rng: &mut R) -> Vec<Vec<bool>>
+{
+    let nn = 2 * n;
+    let f1 = loop
+        {
+            let v: Vec<bool> = (0..nn).map(|_| rng.gen()).collect();
+            if v.iter().any(|&b| b)
+            {
+                break v;
+            }
+        };
+
+    let mut e1 = vec![false; nn];
+    e1[0] = true;
+    let (t0, t1) = find_transvection(&e1, &f1);
+
+    let bits: Vec<bool> = (0..nn-1).map(|_| rng.gen()).collect();
+    let mut h0 = e1.clone();
+    h0[2..].copy_from_slice(&bits[1..]);
+    transvection(&t0, &mut h0);
+    transvection(&t1, &mut h0);
+    let f1 = if bits[0] { vec![false; nn] } else { f1 };
+
+    let mut g: Vec<Vec<bool>> = (0..nn).map(|i| (0..nn).map(|j| i == j).collect()).collect();
+    if n > 1
+    {
+        for (row, sub_row) in g[2..].iter_mut().zip(random_symplectic(n - 1, rng))
+        {
+            row[2..].copy_from_slice(&sub_row);
+        }
+    }
+    for row in g.iter_mut()
+    {
+        for k in [&t0, &t1, &h0, &f1].iter()
+        {
+            transvection(k, row);
+        }
+    }
+
+    g
+}
+
+/// Random Clifford operation.
+///
+/// Sample a Clifford operation on `n` qubits uniformly (up to a global
+/// phase), and return its tableau. The images of the Pauli operators are
+/// taken from a uniformly random symplectic matrix over GF(2), generated with
+/// the algorithm of Koenig and Smolin, and combined with uniformly random
+/// signs. Use `clifford_to_circuit()` to obtain a circuit implementing the
+/// operation.
+pub fn random_clifford_nq<R: rand::Rng>(n: usize, rng: &mut R) -> crate::stabilizer::CliffordTableau
+{
+    use crate::stabilizer::PauliOp;
+
+    if n == 0
+    {
+        return crate::stabilizer::CliffordTableau::new(0);
+    }
+
+    let g = random_symplectic(n, rng);
+    let mut images = g.iter().map(|row| {
+            let ops = row.chunks(2).map(|xz| match (xz[0], xz[1])
+                {
+                    (false, false) => PauliOp::I,
+                    (true, false) => PauliOp::X,
+                    (false, true) => PauliOp::Z,
+                    (true, true) => PauliOp::Y
+                })
+                .collect();
+            (rng.gen(), ops)
+        });
+
+    let mut x_images = vec![];
+    let mut z_images = vec![];
+    for _ in 0..n
+    {
+        x_images.push(images.next().unwrap());
+        z_images.push(images.next().unwrap());
+    }
+    crate::stabilizer::CliffordTableau::from_images(x_images, z_images)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{haar_random_circuit, haar_random_unitary, random_clifford_1q,
+        random_clifford_nq};
+    use crate::gates::Gate;
+
+    #[test]
+    fn test_haar_random_unitary()
+    {
+        let mut rng = rand::thread_rng();
+        for nr_bits in 1..4
+        {
+            let gate = haar_random_unitary(nr_bits, &mut rng);
+            assert_eq!(gate.nr_affected_bits(), nr_bits);
+            let m = gate.matrix();
+            let mm = crate::linalg::adjoint(&m).dot(&m);
+            assert!((mm - crate::cmatrix::CMatrix::eye(1 << nr_bits)).iter()
+                .all(|c| c.norm() < 1.0e-12));
+        }
+    }
+
+    #[test]
+    fn test_bloch_sphere_uniform()
+    {
+        let nr_samples = 1000;
+        let nr_bins = 4;
+        let tol = 1.0e-5;
+
+        let mut rng = rand::thread_rng();
+        let mut counts = vec![vec![0; nr_bins]; 3];
+        for _ in 0..nr_samples
+        {
+            // Bloch vector of U|0⟩
+            let m = haar_random_unitary(1, &mut rng).matrix();
+            let (a, b) = (m[[0, 0]], m[[1, 0]]);
+            let ab = a.conj() * b;
+            let r = [2.0 * ab.re, 2.0 * ab.im, a.norm_sqr() - b.norm_sqr()];
+            assert!((r.iter().map(|x| x*x).sum::<f64>() - 1.0).abs() < 1.0e-12);
+
+            // For a uniform distribution on the sphere, every component is
+            // distributed uniformly on [-1, 1]
+            for (axis, &x) in r.iter().enumerate()
+            {
+                let bin = ((0.5 * (x + 1.0) * nr_bins as f64) as usize).min(nr_bins - 1);
+                counts[axis][bin] += 1;
+            }
+        }
+
+        for axis_counts in counts.iter()
+        {
+            for &count in axis_counts.iter()
+            {
+                assert!(crate::stats::measurement_ok(count, nr_samples,
+                    1.0 / nr_bins as f64, tol));
+            }
+        }
+    }
+
+    #[test]
+    fn test_haar_random_circuit()
+    {
+        let mut rng = rand::thread_rng();
+        let circuit = haar_random_circuit(3, 4, &mut rng);
+        assert_eq!(circuit.nr_qbits(), 3);
+        assert_eq!(circuit.cost_breakdown().get("CX"), Some(&(4.0 * crate::gates::CX::cost())));
+
+        let rho = circuit.density_matrix().unwrap();
+        let trace: crate::cmatrix::CNumber = rho.matrix().diag().iter().sum();
+        assert!((trace - crate::cmatrix::COMPLEX_ONE).norm() < 1.0e-12);
+        let purity: crate::cmatrix::CNumber = rho.matrix().dot(rho.matrix()).diag().iter().sum();
+        assert!((purity - crate::cmatrix::COMPLEX_ONE).norm() < 1.0e-12);
+    }
+
+    /// Check that the tableau of `circuit` describes the conjugation of Pauli
+    /// operators by its unitary.
+    fn assert_tableau_action(circuit: &crate::circuit::Circuit)
+    {
+        let tableau = circuit.clifford_tableau().unwrap();
+        let u = crate::gates::Composite::from_circuit("U", circuit).unwrap().matrix();
+        let u_adj = crate::linalg::adjoint(&u);
+        let n = circuit.nr_qbits();
+        for k in 0..n
+        {
+            for (op, (sign, image)) in [
+                    (crate::stabilizer::PauliOp::X, tableau.x_image(k)),
+                    (crate::stabilizer::PauliOp::Z, tableau.z_image(k))
+                ]
+            {
+                let mut ops = vec![crate::stabilizer::PauliOp::I; n];
+                ops[k] = op;
+                let p = crate::stabilizer::PauliString::new(&ops).matrix();
+                let expected = image.matrix() * if sign { -1.0 } else { 1.0 };
+                assert_complex_matrix_eq!(&u.dot(&p).dot(&u_adj), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_clifford_1q()
+    {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0x1c24);
+        let mut seen = vec![];
+        for _ in 0..1000
+        {
+            let tableau = random_clifford_1q(&mut rng);
+            assert_tableau_action(&crate::stabilizer::clifford_to_circuit(&tableau));
+            if !seen.contains(&tableau)
+            {
+                seen.push(tableau);
+            }
+        }
+        assert_eq!(seen.len(), 24);
+    }
+
+    #[test]
+    fn test_random_clifford_nq()
+    {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0x4b53);
+        for n in 1..4
+        {
+            for _ in 0..10
+            {
+                let tableau = random_clifford_nq(n, &mut rng);
+                assert_eq!(tableau.nr_bits(), n);
+                let circuit = crate::stabilizer::clifford_to_circuit(&tableau);
+                assert_eq!(circuit.clifford_tableau(), Ok(tableau));
+                assert_tableau_action(&circuit);
+            }
+        }
+
+        // There are 720 two-qubit symplectic matrices, times 16 sign patterns,
+        // so for a uniform distribution about 3380 of the samples are distinct
+        let mut seen = vec![];
+        for _ in 0..4000
+        {
+            let tableau = random_clifford_nq(2, &mut rng);
+            if !seen.contains(&tableau)
+            {
+                seen.push(tableau);
+            }
+        }
+        assert!(seen.len() > 3200);
+    }
+
+    #[test]
+    fn test_clifford_composition()
+    {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0x9e01);
+        for _ in 0..10
+        {
+            let a = crate::stabilizer::clifford_to_circuit(&random_clifford_nq(2, &mut rng));
+            let b = crate::stabilizer::clifford_to_circuit(&random_clifford_nq(2, &mut rng));
+            let mut instrs = a.to_instructions();
+            instrs.extend(b.to_instructions());
+            let ab = crate::circuit::Circuit::from_instructions(&instrs, 2, 0).unwrap();
+
+            // Applying the gates of b to the tableau of a gives the tableau
+            // of the composition
+            let mut tableau = a.clifford_tableau().unwrap();
+            for (gate, bits) in b.gate_ops().unwrap()
+            {
+                assert_eq!(tableau.apply_gate(gate, bits), Ok(()));
+            }
+            assert_eq!(ab.clifford_tableau(), Ok(tableau));
+            assert_tableau_action(&ab);
+        }
+    }
+}
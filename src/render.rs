@@ -0,0 +1,238 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of circuits to PDF and PNG images.
+//!
+//! The LaTeX code of a circuit is wrapped in a standalone document, which is
+//! compiled with `pdflatex`, or `lualatex` if the former is not available.
+//! PNG images are created from the resulting PDF file with `pdftoppm`, or
+//! ImageMagick's `convert`. All work is done in a temporary directory, which
+//! is removed afterwards.
+
+use crate::circuit::Circuit;
+
+/// Counter for generating unique names for temporary directories
+static NEXT_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+
+/// Number of names tried when creating a temporary directory
+const MAX_TEMP_DIR_ATTEMPTS: usize = 16;
+
+/// Base name of the files created in the temporary directory
+const BASE_NAME: &str = "circuit";
+
+/// Convert I/O error `err` into a q1tsim error
+fn io_error(err: ::std::io::Error) -> crate::error::Error
+{
+    crate::error::Error::IoError(err.to_string())
+}
+
+/// Wrap the LaTeX code for `circuit` in a standalone document
+fn latex_document(circuit: &Circuit) -> crate::error::Result<String>
+{
+    Ok(format!("\\documentclass[border=2pt]{{standalone}}\n\
+        \\usepackage[braket, qm]{{qcircuit}}\n\
+        \\begin{{document}}\n\
+        {}\
+        \\end{{document}}\n", circuit.latex()?))
+}
+
+/// Run the first of the commands in `commands` that can be found, with
+/// working directory `dir`. Each command is given as the name of the program
+/// and its arguments. An `ExternalToolNotFound` error is returned if none of
+/// the programs can be found, and an `ExternalToolFailed` error with the
+/// output of the program if it does not finish successfully.
+fn run_tool(commands: &[(&str, Vec<String>)], dir: &::std::path::Path)
+    -> crate::error::Result<()>
+{
+    for (name, args) in commands
+    {
+        match ::std::process::Command::new(name).args(args).current_dir(dir).output()
+        {
+            Ok(output) => {
+                if output.status.success()
+                {
+                    return Ok(());
+                }
+
+                // LaTeX writes its errors to standard output
+                let msg = if output.stderr.is_empty() { output.stdout } else { output.stderr };
+                return Err(crate::error::Error::ExternalToolFailed(String::from(*name),
+                    String::from_utf8_lossy(&msg).trim().to_string()));
+            },
+            Err(ref err) if err.kind() == ::std::io::ErrorKind::NotFound => {
+                /* try the next tool */
+            },
+            Err(err) => {
+                return Err(io_error(err));
+            }
+        }
+    }
+
+    let names: Vec<&str> = commands.iter().map(|&(name, _)| name).collect();
+    Err(crate::error::Error::ExternalToolNotFound(names.join(" or ")))
+}
+
+/// Temporary directory for rendering a circuit
+///
+/// Struct `TempDir` holds the path to a newly created, uniquely named,
+/// directory. The directory and its contents are removed when the struct is
+/// dropped.
+struct TempDir
+{
+    /// The path to the directory
+    path: ::std::path::PathBuf
+}
+
+impl TempDir
+{
+    /// Create a new temporary directory.
+    ///
+    /// The name of the directory contains a random suffix, and the directory
+    /// is only used when it did not exist yet, so that a directory or link
+    /// created beforehand by someone else is never written into or removed.
+    fn new() -> crate::error::Result<Self>
+    {
+        for _ in 0..MAX_TEMP_DIR_ATTEMPTS
+        {
+            let id = NEXT_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+            let path = ::std::env::temp_dir().join(format!("q1tsim-render-{}-{}-{:016x}",
+                ::std::process::id(), id, rand::random::<u64>()));
+            match ::std::fs::create_dir(&path)
+            {
+                Ok(()) => { return Ok(TempDir { path: path }); },
+                Err(ref err) if err.kind() == ::std::io::ErrorKind::AlreadyExists => { },
+                Err(err) => { return Err(io_error(err)); }
+            }
+        }
+
+        Err(crate::error::Error::IoError(String::from(
+            "unable to create a unique temporary directory")))
+    }
+
+    /// The path to file `name` in this directory
+    fn file(&self, name: &str) -> ::std::path::PathBuf
+    {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir
+{
+    fn drop(&mut self)
+    {
+        // Failing to clean up a temporary directory is not fatal
+        let _ = ::std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Compile the LaTeX code for `circuit` into PDF file `circuit.pdf` in
+/// directory `dir`.
+fn compile_pdf(circuit: &Circuit, dir: &TempDir) -> crate::error::Result<()>
+{
+    let tex_name = format!("{}.tex", BASE_NAME);
+    ::std::fs::write(dir.file(&tex_name), latex_document(circuit)?).map_err(io_error)?;
+
+    let args = vec![
+        String::from("-interaction=nonstopmode"),
+        String::from("-halt-on-error"),
+        tex_name
+    ];
+    run_tool(&[("pdflatex", args.clone()), ("lualatex", args)], &dir.path)
+}
+
+impl Circuit
+{
+    /// Render this circuit to PDF
+    ///
+    /// Export this circuit to LaTeX, and compile the result into PDF file
+    /// `path` using `pdflatex` or `lualatex`. The LaTeX installation should
+    /// provide the `qcircuit` and `standalone` packages. An
+    /// `ExternalToolNotFound` error is returned if neither program can be
+    /// found, and an `ExternalToolFailed` error if the compilation fails.
+    pub fn to_pdf(&self, path: &::std::path::Path) -> crate::error::Result<()>
+    {
+        let dir = TempDir::new()?;
+        compile_pdf(self, &dir)?;
+        ::std::fs::copy(dir.file(&format!("{}.pdf", BASE_NAME)), path).map_err(io_error)?;
+        Ok(())
+    }
+
+    /// Render this circuit to PNG
+    ///
+    /// Export this circuit to LaTeX, compile the result into PDF like
+    /// `to_pdf()`, and convert it into PNG image `path` with a resolution of
+    /// `dpi` dots per inch, using `pdftoppm` or ImageMagick's `convert`. An
+    /// `ExternalToolNotFound` error is returned if a required program cannot
+    /// be found, and an `ExternalToolFailed` error if one of the programs
+    /// fails.
+    pub fn to_png(&self, path: &::std::path::Path, dpi: u32) -> crate::error::Result<()>
+    {
+        let dir = TempDir::new()?;
+        compile_pdf(self, &dir)?;
+
+        let pdf_name = format!("{}.pdf", BASE_NAME);
+        let png_name = format!("{}.png", BASE_NAME);
+        run_tool(&[
+            ("pdftoppm", vec![String::from("-png"), String::from("-r"), dpi.to_string(),
+                String::from("-singlefile"), pdf_name.clone(), String::from(BASE_NAME)]),
+            ("convert", vec![String::from("-density"), dpi.to_string(), pdf_name,
+                png_name.clone()])
+        ], &dir.path)?;
+        ::std::fs::copy(dir.file(&png_name), path).map_err(io_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{latex_document, run_tool, TempDir};
+    use crate::circuit::Circuit;
+
+    #[test]
+    fn test_latex_document()
+    {
+        let mut circuit = Circuit::new(1, 1);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        let doc = latex_document(&circuit).unwrap();
+        assert!(doc.starts_with("\\documentclass[border=2pt]{standalone}\n"));
+        assert!(doc.contains("\\usepackage[braket, qm]{qcircuit}\n\\begin{document}\n\\Qcircuit"));
+        assert!(doc.contains(&circuit.latex().unwrap()));
+        assert!(doc.ends_with("\\end{document}\n"));
+    }
+
+    #[test]
+    fn test_run_tool()
+    {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path.clone();
+        assert!(path.is_dir());
+        let other = TempDir::new().unwrap();
+        assert_ne!(other.path, path);
+        assert!(other.path.is_dir());
+
+        assert_eq!(run_tool(&[("q1tsim-no-such-tool", vec![]), ("q1tsim-nor-this", vec![])],
+            &dir.path),
+            Err(crate::error::Error::ExternalToolNotFound(
+                String::from("q1tsim-no-such-tool or q1tsim-nor-this"))));
+
+        assert_eq!(run_tool(&[("true", vec![])], &dir.path), Ok(()));
+        assert_eq!(run_tool(&[("false", vec![])], &dir.path),
+            Err(crate::error::Error::ExternalToolFailed(String::from("false"), String::new())));
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Template based circuit rewriting
+//!
+//! A rewrite rule consists of a template circuit, and a replacement circuit
+//! implementing the same operation, usually with fewer or cheaper gates. When
+//! the gates of a template are found in a circuit, they can be replaced by
+//! the gates of the replacement.
+//!
+//! Gates in a circuit match a template gate when they are of the same type,
+//! and act on qubits that are mapped consistently from the template qubits
+//! to the circuit qubits. Angles given as a fixed value in the template anchor
+//! the match to that value, while angles given as a reference parameter match
+//! any value. All gates using the same reference parameter must match the same
+//! angle, and when the reference parameter is used in the replacement
+//! circuit, it takes the value that was matched in the circuit.
+
+use crate::circuit::{gate_type, Circuit, CircuitOp};
+use crate::export::CircuitGate;
+use crate::gates::Parameter;
+
+/// Tolerance in comparing anchored angles
+const TOLERANCE: f64 = 1.0e-10;
+
+/// The values of reference parameters in a template, as matched in a circuit
+type Bindings = Vec<(::std::rc::Rc<::std::cell::RefCell<f64>>, f64)>;
+
+/// Check whether circuit gate `gate` matches template gate `template`. On a
+/// match, the values of reference parameters in `template` are added to
+/// `bindings`.
+fn gate_matches(gate: &dyn CircuitGate, template: &dyn CircuitGate, bindings: &mut Bindings)
+    -> bool
+{
+    if gate.nr_affected_bits() != template.nr_affected_bits()
+        || gate_type(gate) != gate_type(template)
+    {
+        return false;
+    }
+
+    let params = gate.parameters();
+    let tparams = template.parameters();
+    if params.len() != tparams.len()
+    {
+        return false;
+    }
+    if params.is_empty()
+    {
+        // Descriptions of e.g. custom gates need not be unique
+        return gate.matrix() == template.matrix();
+    }
+
+    let mut new_bindings = bindings.clone();
+    for (param, tparam) in params.iter().zip(tparams.iter())
+    {
+        let value = param.value();
+        match **tparam
+        {
+            Parameter::Reference(ref cell, _) => {
+                match new_bindings.iter().find(|(c, _)| ::std::rc::Rc::ptr_eq(c, cell))
+                {
+                    Some(&(_, bound)) => {
+                        if (bound - value).abs() > TOLERANCE
+                        {
+                            return false;
+                        }
+                    },
+                    None => { new_bindings.push((cell.clone(), value)); }
+                }
+            },
+            _ => {
+                if (tparam.value() - value).abs() > TOLERANCE
+                {
+                    return false;
+                }
+            }
+        }
+    }
+
+    *bindings = new_bindings;
+    true
+}
+
+/// Try to extend the qubit mapping `mapping` from template qubits to circuit
+/// qubits, such that template qubits `tbits` map onto circuit qubits `bits`.
+/// Returns `false` if this would make the mapping inconsistent or not
+/// injective.
+fn extend_mapping(mapping: &mut [Option<usize>], tbits: &[usize], bits: &[usize]) -> bool
+{
+    let mut new_mapping = mapping.to_vec();
+    for (&tbit, &bit) in tbits.iter().zip(bits.iter())
+    {
+        match new_mapping[tbit]
+        {
+            Some(mapped) if mapped != bit => { return false; },
+            Some(_) => { },
+            None => {
+                if new_mapping.contains(&Some(bit))
+                {
+                    return false;
+                }
+                new_mapping[tbit] = Some(bit);
+            }
+        }
+    }
+
+    mapping.copy_from_slice(&new_mapping);
+    true
+}
+
+/// Check whether operation `op` acts on any of the circuit qubits in `mapping`
+fn touches_mapped(op: &CircuitOp, mapping: &[Option<usize>]) -> bool
+{
+    match op.qbits()
+    {
+        Some(bits) => bits.iter().any(|&b| mapping.contains(&Some(b))),
+        None => true
+    }
+}
+
+/// A match of a template in a circuit
+struct Match
+{
+    /// Indices of the matched operations in the circuit
+    indices: Vec<usize>,
+    /// Mapping from template qubits to circuit qubits
+    mapping: Vec<Option<usize>>,
+    /// Values of the reference parameters in the template
+    bindings: Bindings
+}
+
+/// Try to match the template gates `tgates` in circuit operations `ops`,
+/// starting at operation `start`.
+///
+/// The matched operations are required to form a contiguous block on the
+/// qubits they act on: any operation between the first and last matched
+/// operation that is not part of the match, should act on other qubits only.
+/// This ensures the replacement can be put in place of the first matched
+/// operation.
+fn match_at(ops: &[CircuitOp], tgates: &[(&dyn CircuitGate, &[usize])],
+    nr_template_bits: usize, start: usize) -> Option<Match>
+{
+    let mut res = Match
+    {
+        indices: vec![],
+        mapping: vec![None; nr_template_bits],
+        bindings: vec![]
+    };
+
+    let mut idx = start;
+    for &(tgate, tbits) in tgates.iter()
+    {
+        loop
+        {
+            let op = ops.get(idx)?;
+            if let CircuitOp::Gate(ref gate, ref bits) = *op
+            {
+                let mut mapping = res.mapping.clone();
+                let mut bindings = res.bindings.clone();
+                if extend_mapping(&mut mapping, tbits, bits)
+                    && gate_matches(gate.as_ref(), tgate, &mut bindings)
+                {
+                    res.mapping = mapping;
+                    res.bindings = bindings;
+                    res.indices.push(idx);
+                    idx += 1;
+                    break;
+                }
+            }
+
+            // This operation is not part of the match, so it should not
+            // interfere with the matched qubits.
+            if res.indices.is_empty() || touches_mapped(op, &res.mapping)
+            {
+                return None;
+            }
+            idx += 1;
+        }
+    }
+
+    // Check operations skipped before qubits were added to the mapping
+    let (first, last) = (res.indices[0], *res.indices.last().unwrap());
+    if (first..last).any(|i| !res.indices.contains(&i) && touches_mapped(&ops[i], &res.mapping))
+    {
+        return None;
+    }
+
+    Some(res)
+}
+
+/// Create the operations for the replacement circuit `replacement`, with its
+/// qubits mapped to circuit qubits as found in match `m`.
+fn replacement_ops(circuit: &Circuit, replacement: &Circuit, m: &Match)
+    -> Option<Vec<CircuitOp>>
+{
+    let mut res = vec![];
+    for op in replacement.ops()
+    {
+        let (gate, tbits) = match *op
+            {
+                CircuitOp::Gate(ref gate, ref bits) => (gate, bits),
+                _ => { return None; }
+            };
+        let bits = tbits.iter().map(|&b| m.mapping.get(b).cloned().flatten())
+            .collect::<Option<Vec<usize>>>()?;
+
+        let has_bound = gate.parameters().iter().any(|p| match **p
+            {
+                Parameter::Reference(ref cell, _) => {
+                    m.bindings.iter().any(|(c, _)| ::std::rc::Rc::ptr_eq(c, cell))
+                },
+                _ => false
+            });
+        if !has_bound
+        {
+            res.push(CircuitOp::Gate(gate.clone(), bits));
+            continue;
+        }
+
+        // Temporarily set the reference parameters to their matched values,
+        // and create fixed gates from the resulting instructions.
+        let saved: Vec<f64> = m.bindings.iter().map(|(c, _)| *c.borrow()).collect();
+        for (cell, value) in m.bindings.iter()
+        {
+            *cell.borrow_mut() = *value;
+        }
+        let instrs = gate.instructions(&bits);
+        for ((cell, _), value) in m.bindings.iter().zip(saved)
+        {
+            *cell.borrow_mut() = value;
+        }
+
+        let fixed = Circuit::from_instructions(&instrs, circuit.nr_qbits(), circuit.nr_cbits()).ok()?;
+        res.extend(fixed.ops().iter().cloned());
+    }
+
+    Some(res)
+}
+
+/// Replace the first occurrence of a template.
+///
+/// Search for the first occurrence of the gates in circuit `template` in
+/// `circuit`, and return a new circuit in which the matched gates are
+/// replaced by the gates in circuit `replacement`. The template and
+/// replacement should consist of gates only, and act on the same set of
+/// qubits. The matched gates should be contiguous on the qubits they act on,
+/// gates on other qubits may occur in between. See the module documentation
+/// for how angles are matched. Returns `None` if the template is not found.
+pub fn template_match(circuit: &Circuit, template: &Circuit, replacement: &Circuit)
+    -> Option<Circuit>
+{
+    let tgates = template.gate_ops().ok()?;
+    if tgates.is_empty()
+    {
+        return None;
+    }
+
+    let ops = circuit.ops();
+    for start in 0..ops.len()
+    {
+        if let Some(m) = match_at(ops, &tgates, template.nr_qbits(), start)
+        {
+            if let Some(new_ops) = replacement_ops(circuit, replacement, &m)
+            {
+                let mut res = vec![];
+                for (idx, op) in ops.iter().enumerate()
+                {
+                    if idx == m.indices[0]
+                    {
+                        res.extend(new_ops.iter().cloned());
+                    }
+                    else if !m.indices.contains(&idx)
+                    {
+                        res.push(op.clone());
+                    }
+                }
+                return Some(circuit.with_ops(res));
+            }
+        }
+    }
+
+    None
+}
+
+/// Rewrite a circuit until no rules apply.
+///
+/// Repeatedly replace the first occurrence of a template in `circuit` by its
+/// replacement, using the first pair of template and replacement circuits in
+/// `rules` whose template is found, until none of the templates occur in the
+/// circuit anymore. Note that this only terminates when the rules do not
+/// undo each other, which is the case e.g. when every replacement has fewer
+/// gates than its template.
+pub fn exhaustive_template_rewrite(circuit: &Circuit, rules: &[(Circuit, Circuit)]) -> Circuit
+{
+    let mut res = circuit.with_ops(circuit.ops().to_vec());
+    'outer: loop
+    {
+        for (template, replacement) in rules.iter()
+        {
+            if let Some(rewritten) = template_match(&res, template, replacement)
+            {
+                res = rewritten;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+    res
+}
+
+/// Create a rule from the gate instructions in `template` and `replacement`,
+/// acting on `nr_bits` qubits.
+fn rule(nr_bits: usize, template: &[crate::instruction::Instruction],
+    replacement: &[crate::instruction::Instruction]) -> (Circuit, Circuit)
+{
+    // The rules are fixed, and only use valid qubits
+    (
+        Circuit::from_instructions(template, nr_bits, 0).unwrap(),
+        Circuit::from_instructions(replacement, nr_bits, 0).unwrap()
+    )
+}
+
+/// Standard rewrite rules.
+///
+/// Return a set of rules that simplify circuits of standard gates, for use
+/// in `exhaustive_template_rewrite()`. Included are the cancellation of
+/// self-inverse gates like `CX·CX → I` and `H·H → I`, of gates followed by
+/// their inverse like `S·S`<sup>`†`</sup>` → I`, and the identities
+/// `H·X·H → Z`, `H·Z·H → X`, `S·S → Z`, and the reversal of a `CX` gate by
+/// conjugation with Hadamard gates on both qubits. All replacements have
+/// fewer gates than their templates.
+pub fn standard_rules() -> Vec<(Circuit, Circuit)>
+{
+    use crate::instruction::Instruction::*;
+
+    vec![
+        rule(2, &[CX(0, 1), CX(0, 1)], &[]),
+        rule(2, &[CZ(0, 1), CZ(0, 1)], &[]),
+        rule(2, &[Swap(0, 1), Swap(0, 1)], &[]),
+        rule(1, &[H(0), H(0)], &[]),
+        rule(1, &[X(0), X(0)], &[]),
+        rule(1, &[Y(0), Y(0)], &[]),
+        rule(1, &[Z(0), Z(0)], &[]),
+        rule(1, &[S(0), Sdg(0)], &[]),
+        rule(1, &[Sdg(0), S(0)], &[]),
+        rule(1, &[T(0), Tdg(0)], &[]),
+        rule(1, &[Tdg(0), T(0)], &[]),
+        rule(1, &[H(0), X(0), H(0)], &[Z(0)]),
+        rule(1, &[H(0), Z(0), H(0)], &[X(0)]),
+        rule(1, &[S(0), S(0)], &[Z(0)]),
+        rule(2, &[H(0), H(1), CX(0, 1), H(0), H(1)], &[CX(1, 0)])
+    ]
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{exhaustive_template_rewrite, standard_rules, template_match};
+    use crate::circuit::Circuit;
+    use crate::gates::{Gate, RZ};
+
+    fn circuit_unitary(circuit: &Circuit) -> crate::cmatrix::CMatrix
+    {
+        crate::gates::Composite::from_circuit("circuit", circuit).unwrap().matrix()
+    }
+
+    #[test]
+    fn test_template_match()
+    {
+        let mut template = Circuit::new(2, 0);
+        assert_eq!(template.cx(0, 1), Ok(()));
+        assert_eq!(template.cx(0, 1), Ok(()));
+        let replacement = Circuit::new(2, 0);
+
+        // Match on reversed qubits, with a gate on another qubit in between
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(2, 1), Ok(()));
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.cx(2, 1), Ok(()));
+        assert_eq!(circuit.z(1), Ok(()));
+        let res = template_match(&circuit, &template, &replacement).unwrap();
+        let descs: Vec<String> = res.to_instructions().iter().map(|i| format!("{:?}", i)).collect();
+        assert_eq!(descs, vec!["H(0)", "X(0)", "Z(1)"]);
+
+        // A gate on one of the matched qubits breaks the match
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert!(template_match(&circuit, &template, &replacement).is_none());
+
+        // Different connectivity
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.cx(1, 0), Ok(()));
+        assert!(template_match(&circuit, &template, &replacement).is_none());
+    }
+
+    #[test]
+    fn test_template_angles()
+    {
+        // Anchored angle
+        let mut template = Circuit::new(1, 0);
+        assert_eq!(template.add_gate(RZ::new(0.0), &[0]), Ok(()));
+        let replacement = Circuit::new(1, 0);
+
+        let mut circuit = Circuit::new(1, 0);
+        assert_eq!(circuit.add_gate(RZ::new(0.5), &[0]), Ok(()));
+        assert!(template_match(&circuit, &template, &replacement).is_none());
+        assert_eq!(circuit.add_gate(RZ::new(0.0), &[0]), Ok(()));
+        let res = template_match(&circuit, &template, &replacement).unwrap();
+        assert_eq!(res.to_instructions().len(), 1);
+
+        // Wildcard angle, moved through the control of a CX gate
+        let theta = ::std::rc::Rc::new(::std::cell::RefCell::new(0.0));
+        let param = crate::gates::Parameter::from_refcell(&theta, "theta");
+        let mut template = Circuit::new(2, 0);
+        assert_eq!(template.add_gate(RZ::new(param.clone()), &[0]), Ok(()));
+        assert_eq!(template.cx(0, 1), Ok(()));
+        let mut replacement = Circuit::new(2, 0);
+        assert_eq!(replacement.cx(0, 1), Ok(()));
+        assert_eq!(replacement.add_gate(RZ::new(param), &[0]), Ok(()));
+
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.add_gate(RZ::new(0.7), &[1]), Ok(()));
+        assert_eq!(circuit.cx(1, 0), Ok(()));
+        let res = template_match(&circuit, &template, &replacement).unwrap();
+        let descs: Vec<String> = res.to_instructions().iter().map(|i| format!("{:?}", i)).collect();
+        assert_eq!(descs, vec!["CX(1, 0)", "RZ(0.7, 1)"]);
+        assert_eq!(*theta.borrow(), 0.0);
+    }
+
+    #[test]
+    fn test_exhaustive_template_rewrite()
+    {
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(circuit.s(2), Ok(()));
+        assert_eq!(circuit.sdg(2), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+
+        let res = exhaustive_template_rewrite(&circuit, &standard_rules());
+        let descs: Vec<String> = res.to_instructions().iter().map(|i| format!("{:?}", i)).collect();
+        assert_eq!(descs, vec!["CX(1, 0)", "Z(2)"]);
+        assert_complex_matrix_eq!(circuit_unitary(&res), circuit_unitary(&circuit));
+
+        let empty = exhaustive_template_rewrite(&Circuit::new(2, 0), &standard_rules());
+        assert!(empty.to_instructions().is_empty());
+    }
+
+    #[test]
+    fn test_standard_rules()
+    {
+        for (template, replacement) in standard_rules()
+        {
+            assert!(replacement.to_instructions().len() < template.to_instructions().len());
+            assert_complex_matrix_eq!(circuit_unitary(&template), circuit_unitary(&replacement));
+        }
+    }
+}
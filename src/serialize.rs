@@ -0,0 +1,724 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialization of circuits through `serde`.
+//!
+//! A circuit is serialized as an object with the sizes of its quantum and
+//! classical registers, and the list of its operations. Each operation is an
+//! object whose `type` member holds the kind of operation. Gates known to
+//! q1tsim are stored by name and parameter values. Composite, controlled and
+//! Kronecker product gates, and loops, are stored by the gates they are built
+//! from. Other gates are stored by description and unitary matrix, and are
+//! deserialized as `Custom` gates.
+//! Matrices are written as rows of `[re, im]` pairs.
+
+use crate::circuit::{Circuit, CircuitOp};
+use crate::export::CircuitGate;
+use crate::gates::{Gate, GateStructure};
+use serde::{Deserialize, Serialize};
+
+/// Serialized form of a complex matrix, as rows of `[re, im]` pairs
+type MatrixData = Vec<Vec<[f64; 2]>>;
+
+/// Convert complex matrix `m` to its serialized form
+fn matrix_data(m: &crate::cmatrix::CMatrix) -> MatrixData
+{
+    m.outer_iter()
+        .map(|row| row.iter().map(|c| [c.re, c.im]).collect())
+        .collect()
+}
+
+/// Convert serialized matrix `data` back to a complex matrix. An error is
+/// returned if the rows do not all have the same length.
+fn matrix_from_data<E>(data: &[Vec<[f64; 2]>]) -> Result<crate::cmatrix::CMatrix, E>
+where E: serde::de::Error
+{
+    let nr_cols = data.first().map_or(0, |row| row.len());
+    if data.iter().any(|row| row.len() != nr_cols)
+    {
+        return Err(E::custom("rows of a matrix should have equal length"));
+    }
+    Ok(crate::cmatrix::CMatrix::from_shape_fn((data.len(), nr_cols), |(i, j)| {
+        crate::cmatrix::CNumber::new(data[i][j][0], data[i][j][1])
+    }))
+}
+
+/// Convert serialized unitary matrix `data` to a complex matrix, returning an
+/// error if it is not a square matrix with a power of two as size.
+fn unitary_from_data<E>(data: &[Vec<[f64; 2]>]) -> Result<crate::cmatrix::CMatrix, E>
+where E: serde::de::Error
+{
+    let matrix = matrix_from_data::<E>(data)?;
+    if matrix.rows() != matrix.cols() || !matrix.rows().is_power_of_two()
+    {
+        return Err(E::custom("the matrix of a gate should be square, with a power of two as size"));
+    }
+    Ok(matrix)
+}
+
+/// Create a gate from its name `name` and parameter values `params`,
+/// or return `None` if the name or number of parameters is not recognized.
+fn known_gate(name: &str, params: &[f64]) -> Option<Box<dyn CircuitGate>>
+{
+    let gate: Box<dyn CircuitGate> = match (name, params)
+        {
+            ("CCRX", &[theta]) => Box::new(crate::gates::CCRX::new(theta)),
+            ("CCRY", &[theta]) => Box::new(crate::gates::CCRY::new(theta)),
+            ("CCRZ", &[theta]) => Box::new(crate::gates::CCRZ::new(theta)),
+            ("CCX", &[]) => Box::new(crate::gates::CCX::new()),
+            ("CCZ", &[]) => Box::new(crate::gates::CCZ::new()),
+            ("CH", &[]) => Box::new(crate::gates::CH::new()),
+            ("CRX", &[theta]) => Box::new(crate::gates::CRX::new(theta)),
+            ("CRY", &[theta]) => Box::new(crate::gates::CRY::new(theta)),
+            ("CRZ", &[theta]) => Box::new(crate::gates::CRZ::new(theta)),
+            ("CS", &[]) => Box::new(crate::gates::CS::new()),
+            ("CS†", &[]) => Box::new(crate::gates::CSdg::new()),
+            ("CSwap", &[]) => Box::new(crate::gates::CSwap::new()),
+            ("CT", &[]) => Box::new(crate::gates::CT::new()),
+            ("CT†", &[]) => Box::new(crate::gates::CTdg::new()),
+            ("CU1", &[lambda]) => Box::new(crate::gates::CU1::new(lambda)),
+            ("CU2", &[phi, lambda]) => Box::new(crate::gates::CU2::new(phi, lambda)),
+            ("CU3", &[theta, phi, lambda]) => {
+                Box::new(crate::gates::CU3::new(theta, phi, lambda))
+            },
+            ("CV", &[]) => Box::new(crate::gates::CV::new()),
+            ("CV†", &[]) => Box::new(crate::gates::CVdg::new()),
+            ("CX", &[]) => Box::new(crate::gates::CX::new()),
+            ("CY", &[]) => Box::new(crate::gates::CY::new()),
+            ("CZ", &[]) => Box::new(crate::gates::CZ::new()),
+            ("H", &[]) => Box::new(crate::gates::H::new()),
+            ("I", &[]) => Box::new(crate::gates::I::new()),
+            ("iSWAP", &[]) => Box::new(crate::gates::ISWAP::new()),
+            ("iSWAP†", &[]) => Box::new(crate::gates::ISWAPdg::new()),
+            ("Phase", &[phi]) => Box::new(crate::gates::Phase::new(phi)),
+            ("RX", &[theta]) => Box::new(crate::gates::RX::new(theta)),
+            ("RY", &[theta]) => Box::new(crate::gates::RY::new(theta)),
+            ("RZ", &[theta]) => Box::new(crate::gates::RZ::new(theta)),
+            ("S", &[]) => Box::new(crate::gates::S::new()),
+            ("S†", &[]) => Box::new(crate::gates::Sdg::new()),
+            ("Swap", &[]) => Box::new(crate::gates::Swap::new()),
+            ("T", &[]) => Box::new(crate::gates::T::new()),
+            ("T†", &[]) => Box::new(crate::gates::Tdg::new()),
+            ("U1", &[lambda]) => Box::new(crate::gates::U1::new(lambda)),
+            ("U2", &[phi, lambda]) => Box::new(crate::gates::U2::new(phi, lambda)),
+            ("U3", &[theta, phi, lambda]) => Box::new(crate::gates::U3::new(theta, phi, lambda)),
+            ("V", &[]) => Box::new(crate::gates::V::new()),
+            ("V†", &[]) => Box::new(crate::gates::Vdg::new()),
+            ("X", &[]) => Box::new(crate::gates::X::new()),
+            ("Y", &[]) => Box::new(crate::gates::Y::new()),
+            ("Z", &[]) => Box::new(crate::gates::Z::new()),
+            (_, &[]) if name.starts_with("CR_") => {
+                let k = name[3..].parse().ok()?;
+                Box::new(crate::gates::ControlledPhase::new(k))
+            },
+            _ => { return None; }
+        };
+    Some(gate)
+}
+
+/// Serialized form of a gate
+#[derive(Deserialize, Serialize)]
+struct GateData
+{
+    /// The name of the gate
+    name: String,
+    /// The parameter values of a known gate
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    params: Vec<f64>,
+    /// The unitary matrix of a gate that is not known by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    matrix: Option<MatrixData>,
+    /// The structure of a gate built from other gates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    structure: Option<StructureData>
+}
+
+/// Serialized form of an operation in a composite gate
+#[derive(Deserialize, Serialize)]
+struct SubGateData
+{
+    /// The gate
+    gate: GateData,
+    /// The bits on which the gate acts
+    bits: Vec<usize>
+}
+
+/// Serialized form of the structure of a gate built from other gates
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type")]
+enum StructureData
+{
+    Composite { nr_bits: usize, ops: Vec<SubGateData> },
+    Controlled { gate: Box<GateData> },
+    Kron { g0: Box<GateData>, g1: Box<GateData> },
+    Loop { nr_iterations: usize, body: Box<GateData> }
+}
+
+impl GateData
+{
+    /// Create the serialized form of `gate`. When `gate` can be recreated
+    /// from its gate type and parameters, only these are stored. Gates that
+    /// are built from other gates are stored by their structure. Otherwise,
+    /// the description and matrix of the gate are stored. An
+    /// `UnboundParameter` error is returned when the gate has free parameters.
+    fn new(gate: &dyn Gate) -> crate::error::Result<Self>
+    {
+        let mut params = vec![];
+        for param in gate.parameters()
+        {
+            if !param.is_bound()
+            {
+                return Err(crate::error::Error::UnboundParameter(format!("{}", param)));
+            }
+            params.push(param.value());
+        }
+
+        let name = crate::circuit::gate_type(gate);
+        if let Some(known) = known_gate(name, &params)
+        {
+            if known.nr_affected_bits() == gate.nr_affected_bits() && known.matrix() == gate.matrix()
+            {
+                return Ok(GateData { name: String::from(name), params: params, matrix: None, structure: None });
+            }
+        }
+
+        let (name, structure) = match gate.structure()
+            {
+                Some(GateStructure::Composite { name, nr_bits, ops }) => {
+                    let ops = ops.iter().map(|&(gate, bits)| {
+                            Ok(SubGateData { gate: GateData::new(gate)?, bits: bits.to_vec() })
+                        }).collect::<crate::error::Result<Vec<_>>>()?;
+                    (name, StructureData::Composite { nr_bits: nr_bits, ops: ops })
+                },
+                Some(GateStructure::Controlled(cgate)) => {
+                    (gate.description(), StructureData::Controlled { gate: Box::new(GateData::new(cgate)?) })
+                },
+                Some(GateStructure::Kron(g0, g1)) => {
+                    let g0 = Box::new(GateData::new(g0)?);
+                    let g1 = Box::new(GateData::new(g1)?);
+                    (gate.description(), StructureData::Kron { g0: g0, g1: g1 })
+                },
+                Some(GateStructure::Loop { label, nr_iterations, body }) => {
+                    let body = Box::new(GateData::new(body)?);
+                    (label, StructureData::Loop { nr_iterations: nr_iterations, body: body })
+                },
+                None => {
+                    return Ok(GateData
+                    {
+                        name: String::from(gate.description()),
+                        params: vec![],
+                        matrix: Some(matrix_data(&gate.matrix())),
+                        structure: None
+                    });
+                }
+            };
+
+        Ok(GateData { name: String::from(name), params: vec![], matrix: None, structure: Some(structure) })
+    }
+
+    /// Recreate the composite gate described by this data, returning an
+    /// error if this data does not describe a valid composite gate.
+    fn composite<E>(&self) -> Result<crate::gates::Composite, E>
+    where E: serde::de::Error
+    {
+        match self.structure
+        {
+            Some(StructureData::Composite { nr_bits, ref ops }) => {
+                let mut composite = crate::gates::Composite::new(&self.name, nr_bits);
+                for op in ops.iter()
+                {
+                    let gate = op.gate.gate()?;
+                    if op.bits.len() != gate.nr_affected_bits() || op.bits.iter().any(|&b| b >= nr_bits)
+                    {
+                        return Err(E::custom(format!("invalid bits for gate {} in composite gate {}",
+                            gate.description(), self.name)));
+                    }
+                    composite.add_gate(gate, &op.bits);
+                }
+                Ok(composite)
+            },
+            _ => Err(E::custom(format!("{} is not a composite gate", self.name)))
+        }
+    }
+
+    /// Recreate the gate described by this data
+    fn gate<E>(&self) -> Result<Box<dyn CircuitGate>, E>
+    where E: serde::de::Error
+    {
+        if let Some(ref data) = self.matrix
+        {
+            let matrix = unitary_from_data(data)?;
+            return Ok(Box::new(crate::gates::Custom::new(&self.name, matrix)));
+        }
+
+        match self.structure
+        {
+            Some(StructureData::Composite { .. }) => Ok(Box::new(self.composite()?)),
+            Some(StructureData::Controlled { ref gate }) => {
+                Ok(Box::new(crate::gates::C::new(gate.gate()?)))
+            },
+            Some(StructureData::Kron { ref g0, ref g1 }) => {
+                Ok(Box::new(crate::gates::Kron::new(g0.gate()?, g1.gate()?)))
+            },
+            Some(StructureData::Loop { nr_iterations, ref body }) => {
+                Ok(Box::new(crate::gates::Loop::new(&self.name, nr_iterations, body.composite()?)))
+            },
+            None => {
+                known_gate(&self.name, &self.params).ok_or_else(|| {
+                    E::custom(crate::error::ParseError::UnknownGate(self.name.clone()))
+                })
+            }
+        }
+    }
+}
+
+impl Serialize for Box<dyn CircuitGate>
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer
+    {
+        GateData::new(self.as_gate()).map_err(serde::ser::Error::custom)?.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<dyn CircuitGate>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de>
+    {
+        GateData::deserialize(deserializer)?.gate()
+    }
+}
+
+/// Serialized form of a custom gate
+#[derive(Deserialize, Serialize)]
+struct CustomData
+{
+    /// The description of the gate
+    name: String,
+    /// The unitary matrix of the gate
+    matrix: MatrixData
+}
+
+impl Serialize for crate::gates::Custom
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer
+    {
+        let data = CustomData
+        {
+            name: String::from(self.description()),
+            matrix: matrix_data(&self.matrix())
+        };
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::gates::Custom
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de>
+    {
+        let data = CustomData::deserialize(deserializer)?;
+        let matrix = unitary_from_data(&data.matrix)?;
+        Ok(crate::gates::Custom::new(&data.name, matrix))
+    }
+}
+
+/// Serialized form of a circuit operation
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type")]
+enum OpData
+{
+    Gate { gate: Box<dyn CircuitGate>, bits: Vec<usize> },
+    ConditionalGate { control: Vec<usize>, target: u64, gate: Box<dyn CircuitGate>, bits: Vec<usize> },
+    Reset { bit: usize },
+    ResetTo { bit: usize, value: bool },
+    ResetAll,
+    Measure { qbit: usize, cbit: usize, basis: crate::circuit::Basis },
+    MeasureAll { cbits: Vec<usize>, basis: crate::circuit::Basis },
+    Peek { qbit: usize, cbit: usize, basis: crate::circuit::Basis },
+    PeekAll { cbits: Vec<usize>, basis: crate::circuit::Basis },
+    PeekPartial { qbits: Vec<usize>, cbits: Vec<usize> },
+    Barrier { bits: Vec<usize> },
+    Noise { kraus: Vec<MatrixData>, bits: Vec<usize> },
+    ExpectationMeasure { observable: String, cbit: usize }
+}
+
+impl Serialize for CircuitOp
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer
+    {
+        let data = match *self
+            {
+                CircuitOp::Gate(ref gate, ref bits) => {
+                    OpData::Gate { gate: gate.clone(), bits: bits.clone() }
+                },
+                CircuitOp::ConditionalGate(ref control, target, ref gate, ref bits) => {
+                    OpData::ConditionalGate
+                    {
+                        control: control.clone(),
+                        target: target,
+                        gate: gate.clone(),
+                        bits: bits.clone()
+                    }
+                },
+                CircuitOp::Reset(bit) => OpData::Reset { bit: bit },
+                CircuitOp::ResetTo(bit, value) => OpData::ResetTo { bit: bit, value: value },
+                CircuitOp::ResetAll => OpData::ResetAll,
+                CircuitOp::Measure(qbit, cbit, basis) => {
+                    OpData::Measure { qbit: qbit, cbit: cbit, basis: basis }
+                },
+                CircuitOp::MeasureAll(ref cbits, basis) => {
+                    OpData::MeasureAll { cbits: cbits.clone(), basis: basis }
+                },
+                CircuitOp::Peek(qbit, cbit, basis) => {
+                    OpData::Peek { qbit: qbit, cbit: cbit, basis: basis }
+                },
+                CircuitOp::PeekAll(ref cbits, basis) => {
+                    OpData::PeekAll { cbits: cbits.clone(), basis: basis }
+                },
+                CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                    OpData::PeekPartial { qbits: qbits.clone(), cbits: cbits.clone() }
+                },
+                CircuitOp::Barrier(ref bits) => OpData::Barrier { bits: bits.clone() },
+                CircuitOp::Noise(ref kraus, ref bits) => {
+                    OpData::Noise
+                    {
+                        kraus: kraus.iter().map(matrix_data).collect(),
+                        bits: bits.clone()
+                    }
+                },
+                CircuitOp::ExpectationMeasure(ref observable, cbit) => {
+                    OpData::ExpectationMeasure { observable: format!("{}", observable), cbit: cbit }
+                }
+            };
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CircuitOp
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de>
+    {
+        let op = match OpData::deserialize(deserializer)?
+            {
+                OpData::Gate { gate, bits } => CircuitOp::Gate(gate, bits),
+                OpData::ConditionalGate { control, target, gate, bits } => {
+                    CircuitOp::ConditionalGate(control, target, gate, bits)
+                },
+                OpData::Reset { bit } => CircuitOp::Reset(bit),
+                OpData::ResetTo { bit, value } => CircuitOp::ResetTo(bit, value),
+                OpData::ResetAll => CircuitOp::ResetAll,
+                OpData::Measure { qbit, cbit, basis } => CircuitOp::Measure(qbit, cbit, basis),
+                OpData::MeasureAll { cbits, basis } => CircuitOp::MeasureAll(cbits, basis),
+                OpData::Peek { qbit, cbit, basis } => CircuitOp::Peek(qbit, cbit, basis),
+                OpData::PeekAll { cbits, basis } => CircuitOp::PeekAll(cbits, basis),
+                OpData::PeekPartial { qbits, cbits } => CircuitOp::PeekPartial(qbits, cbits),
+                OpData::Barrier { bits } => CircuitOp::Barrier(bits),
+                OpData::Noise { kraus, bits } => {
+                    let kraus = kraus.iter()
+                        .map(|data| matrix_from_data(data))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    CircuitOp::Noise(kraus, bits)
+                },
+                OpData::ExpectationMeasure { observable, cbit } => {
+                    let ops = observable.chars().map(|c| {
+                            match c
+                            {
+                                'I' => Ok(crate::stabilizer::PauliOp::I),
+                                'X' => Ok(crate::stabilizer::PauliOp::X),
+                                'Y' => Ok(crate::stabilizer::PauliOp::Y),
+                                'Z' => Ok(crate::stabilizer::PauliOp::Z),
+                                _ => Err(serde::de::Error::custom(
+                                    format!("invalid Pauli operator '{}'", c)))
+                            }
+                        }).collect::<Result<Vec<_>, _>>()?;
+                    CircuitOp::ExpectationMeasure(crate::stabilizer::PauliString::new(&ops), cbit)
+                }
+            };
+        Ok(op)
+    }
+}
+
+/// Check that operation `op` fits in a circuit with `nr_qbits` quantum bits
+/// and `nr_cbits` classical bits.
+fn check_op(op: &CircuitOp, nr_qbits: usize, nr_cbits: usize) -> crate::error::Result<()>
+{
+    if let Some(&bit) = op.qbits().and_then(|bits| bits.iter().find(|&&b| b >= nr_qbits))
+    {
+        return Err(crate::error::Error::InvalidQBit(bit));
+    }
+
+    let cbits = match *op
+        {
+            CircuitOp::Gate(ref gate, ref bits) | CircuitOp::ConditionalGate(_, _, ref gate, ref bits) => {
+                gate.check_nr_bits(bits.len())?;
+                match *op
+                {
+                    CircuitOp::ConditionalGate(ref control, _, _, _) => control.as_slice(),
+                    _ => &[]
+                }
+            },
+            CircuitOp::Measure(_, ref cbit, _) | CircuitOp::Peek(_, ref cbit, _)
+                | CircuitOp::ExpectationMeasure(_, ref cbit) => ::std::slice::from_ref(cbit),
+            CircuitOp::MeasureAll(ref cbits, _) | CircuitOp::PeekAll(ref cbits, _) => {
+                if cbits.len() != nr_qbits
+                {
+                    return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), nr_qbits));
+                }
+                cbits.as_slice()
+            },
+            CircuitOp::PeekPartial(ref qbits, ref cbits) => {
+                if cbits.len() != qbits.len()
+                {
+                    return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), qbits.len()));
+                }
+                cbits.as_slice()
+            },
+            _ => &[]
+        };
+    if let Some(&bit) = cbits.iter().find(|&&b| b >= nr_cbits)
+    {
+        return Err(crate::error::Error::InvalidCBit(bit));
+    }
+
+    if let CircuitOp::ExpectationMeasure(ref observable, _) = *op
+    {
+        if observable.nr_bits() != nr_qbits
+        {
+            return Err(crate::error::Error::InvalidNrBits(observable.nr_bits(), nr_qbits,
+                format!("{}", observable)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialized form of a circuit, borrowing the operations
+#[derive(Serialize)]
+struct CircuitRef<'a>
+{
+    nr_qbits: usize,
+    nr_cbits: usize,
+    ops: &'a [CircuitOp]
+}
+
+/// Deserialized form of a circuit
+#[derive(Deserialize)]
+struct CircuitData
+{
+    nr_qbits: usize,
+    nr_cbits: usize,
+    ops: Vec<CircuitOp>
+}
+
+impl Serialize for Circuit
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer
+    {
+        let data = CircuitRef
+        {
+            nr_qbits: self.nr_qbits(),
+            nr_cbits: self.nr_cbits(),
+            ops: self.ops()
+        };
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Circuit
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de>
+    {
+        let data = CircuitData::deserialize(deserializer)?;
+        for op in data.ops.iter()
+        {
+            check_op(op, data.nr_qbits, data.nr_cbits).map_err(serde::de::Error::custom)?;
+        }
+        Ok(Circuit::new(data.nr_qbits, data.nr_cbits).with_ops(data.ops))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::circuit::{Basis, Circuit};
+    use crate::export::CircuitGate;
+    use crate::gates::*;
+
+    fn round_trip(circuit: &Circuit) -> Circuit
+    {
+        let json = serde_json::to_string(circuit).unwrap();
+        let res: Circuit = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&res).unwrap(), json);
+        res
+    }
+
+    #[test]
+    fn test_gates()
+    {
+        let gates: Vec<Box<dyn CircuitGate>> = vec![
+            Box::new(CCRX::new(0.1)), Box::new(CCRY::new(0.2)), Box::new(CCRZ::new(0.3)),
+            Box::new(CCX::new()), Box::new(CCZ::new()), Box::new(CH::new()),
+            Box::new(CRX::new(0.4)), Box::new(CRY::new(0.5)), Box::new(CRZ::new(0.6)),
+            Box::new(CS::new()), Box::new(CSdg::new()), Box::new(CSwap::new()),
+            Box::new(CT::new()), Box::new(CTdg::new()), Box::new(CU1::new(0.7)),
+            Box::new(CU2::new(0.8, 0.9)), Box::new(CU3::new(1.0, 1.1, 1.2)),
+            Box::new(CV::new()), Box::new(CVdg::new()), Box::new(CX::new()),
+            Box::new(CY::new()), Box::new(CZ::new()), Box::new(H::new()), Box::new(I::new()),
+            Box::new(ISWAP::new()), Box::new(ISWAPdg::new()), Box::new(Phase::new(1.3)),
+            Box::new(RX::new(1.4)), Box::new(RY::new(1.5)), Box::new(RZ::new(1.6)),
+            Box::new(S::new()), Box::new(Sdg::new()), Box::new(Swap::new()),
+            Box::new(T::new()), Box::new(Tdg::new()), Box::new(U1::new(1.7)),
+            Box::new(U2::new(1.8, 1.9)), Box::new(U3::new(2.0, 2.1, 2.2)),
+            Box::new(V::new()), Box::new(Vdg::new()), Box::new(X::new()),
+            Box::new(Y::new()), Box::new(Z::new()), Box::new(ControlledPhase::new(3))
+        ];
+        for gate in gates
+        {
+            let json = serde_json::to_string(&gate).unwrap();
+            assert!(!json.contains("matrix"), "{} was serialized by matrix", gate.description());
+            let res: Box<dyn CircuitGate> = serde_json::from_str(&json).unwrap();
+            assert_eq!(res.description(), gate.description());
+            assert_eq!(res.matrix(), gate.matrix());
+        }
+
+        let gate: Box<dyn CircuitGate> = Box::new(U2::new(0.5, -1.0));
+        assert_eq!(serde_json::to_string(&gate).unwrap(), r#"{"name":"U2","params":[0.5,-1.0]}"#);
+        let gate: Box<dyn CircuitGate> = Box::new(RX::new(Parameter::symbol("x")));
+        assert!(serde_json::to_string(&gate).is_err());
+        assert!(serde_json::from_str::<Box<dyn CircuitGate>>(r#"{"name":"Q"}"#).is_err());
+        assert!(serde_json::from_str::<Box<dyn CircuitGate>>(r#"{"name":"RX","params":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_custom()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let gate = Custom::new("G", array![[z, i], [o, z]]);
+        let json = serde_json::to_string(&gate).unwrap();
+        assert_eq!(json, r#"{"name":"G","matrix":[[[0.0,0.0],[0.0,1.0]],[[1.0,0.0],[0.0,0.0]]]}"#);
+        let res: Custom = serde_json::from_str(&json).unwrap();
+        assert_eq!(res.description(), "G");
+        assert_complex_matrix_eq!(&res.matrix(), &gate.matrix());
+
+        // Boxed gates that are not known by name are stored by matrix
+        let boxed: Box<dyn CircuitGate> = Box::new(gate.clone());
+        let json = serde_json::to_string(&boxed).unwrap();
+        assert_eq!(json, r#"{"name":"G","matrix":[[[0.0,0.0],[0.0,1.0]],[[1.0,0.0],[0.0,0.0]]]}"#);
+        let res: Box<dyn CircuitGate> = serde_json::from_str(&json).unwrap();
+        assert_complex_matrix_eq!(&res.matrix(), &gate.matrix());
+
+        assert!(serde_json::from_str::<Custom>(r#"{"name":"G","matrix":[[[1.0,0.0]],[[0.0,0.0],[1.0,0.0]]]}"#).is_err());
+        assert!(serde_json::from_str::<Custom>(r#"{"name":"G","matrix":[[[1.0,0.0],[0.0,0.0]]]}"#).is_err());
+    }
+
+    #[test]
+    fn test_structure()
+    {
+        let gates: Vec<Box<dyn CircuitGate>> = vec![
+            Box::new(Composite::from_string("G", "H 0; CRY(0.3) 0 1; T 1").unwrap()),
+            n_controlled_x(4),
+            Box::new(C::new(U2::new(0.4, -1.3))),
+            Box::new(Kron::new(H::new(), X::new())),
+            Box::new(Loop::new("l", 3, Composite::from_string("G", "H 0; CX 0 1").unwrap()))
+        ];
+        for gate in gates
+        {
+            let json = serde_json::to_string(&gate).unwrap();
+            assert!(!json.contains("matrix"), "{} was serialized by matrix", gate.description());
+            let res: Box<dyn CircuitGate> = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&res).unwrap(), json);
+            assert_eq!(res.description(), gate.description());
+            assert_complex_matrix_eq!(&res.matrix(), &gate.matrix());
+        }
+
+        let gate: Box<dyn CircuitGate> = Box::new(Kron::new(H::new(), X::new()));
+        assert_eq!(serde_json::to_string(&gate).unwrap(),
+            r#"{"name":"H⊗X","structure":{"type":"Kron","g0":{"name":"H"},"g1":{"name":"X"}}}"#);
+
+        let mut circuit = Circuit::new(5, 0);
+        assert_eq!(circuit.mcx(&[0, 1, 2, 3], 4), Ok(()));
+        let res = round_trip(&circuit);
+        assert_eq!(res.open_qasm().unwrap(), circuit.open_qasm().unwrap());
+
+        assert!(serde_json::from_str::<Box<dyn CircuitGate>>(
+            r#"{"name":"G","structure":{"type":"Composite","nr_bits":1,"ops":[{"gate":{"name":"CX"},"bits":[0,1]}]}}"#
+        ).is_err());
+        assert!(serde_json::from_str::<Box<dyn CircuitGate>>(
+            r#"{"name":"l","structure":{"type":"Loop","nr_iterations":2,"body":{"name":"H"}}}"#
+        ).is_err());
+    }
+
+    #[test]
+    fn test_circuit()
+    {
+        let mut circuit = Circuit::new(3, 3);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.measure(1, 0), Ok(()));
+        let json = serde_json::to_string(&circuit).unwrap();
+        assert_eq!(json, r#"{"nr_qbits":3,"nr_cbits":3,"ops":[{"type":"Gate","gate":{"name":"H"},"bits":[0]},{"type":"Gate","gate":{"name":"CX"},"bits":[0,1]},{"type":"Measure","qbit":1,"cbit":0,"basis":"Z"}]}"#);
+
+        assert_eq!(circuit.add_conditional_gate(&[0, 2], 2, RX::new(0.25), &[2]), Ok(()));
+        assert_eq!(circuit.reset(1), Ok(()));
+        assert_eq!(circuit.reset_to(2, true), Ok(()));
+        circuit.reset_all();
+        assert_eq!(circuit.measure_basis(2, 1, Basis::X), Ok(()));
+        assert_eq!(circuit.measure_all_basis(&[2, 1, 0], Basis::Y), Ok(()));
+        assert_eq!(circuit.peek_basis(0, 2, Basis::Z), Ok(()));
+        assert_eq!(circuit.peek_all(&[0, 1, 2]), Ok(()));
+        assert_eq!(circuit.peek_partial(&[2, 0], &[1, 2]), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 2]), Ok(()));
+        assert_eq!(circuit.add_noise(Depolarizing::new(0.1), &[1]), Ok(()));
+        let observable = crate::stabilizer::PauliString::new(&[
+            crate::stabilizer::PauliOp::X,
+            crate::stabilizer::PauliOp::I,
+            crate::stabilizer::PauliOp::Y
+        ]);
+        assert_eq!(circuit.measure_expectation(&observable, 1), Ok(()));
+
+        let res = round_trip(&circuit);
+        assert_eq!(res.nr_qbits(), 3);
+        assert_eq!(res.nr_cbits(), 3);
+        assert_eq!(res.ops().len(), circuit.ops().len());
+        for (op0, op1) in circuit.ops().iter().zip(res.ops())
+        {
+            assert_eq!(op0.qbits(), op1.qbits());
+        }
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        let parse = |json: &str| serde_json::from_str::<Circuit>(json).is_err();
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":1,"ops":[{"type":"Gate","gate":{"name":"H"},"bits":[2]}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":1,"ops":[{"type":"Gate","gate":{"name":"H"},"bits":[0,1]}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":1,"ops":[{"type":"Measure","qbit":0,"cbit":1,"basis":"Z"}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"MeasureAll","cbits":[0],"basis":"Z"}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"PeekPartial","qbits":[1],"cbits":[0,1]}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"ExpectationMeasure","observable":"XQ","cbit":0}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"ExpectationMeasure","observable":"XYZ","cbit":0}]}"#));
+        assert!(parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"Teleport"}]}"#));
+        assert!(!parse(r#"{"nr_qbits":2,"nr_cbits":2,"ops":[{"type":"ResetAll"}]}"#));
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Energies of spin Hamiltonians
+//!
+//! This module provides functions for evaluating the energy of the final
+//! state of a circuit for a few commonly used spin models, as needed in e.g.
+//! variational quantum eigensolvers.
+
+use crate::stabilizer::{PauliOp, PauliString};
+
+/// Compute the expectation value of the Pauli string with the operators in
+/// `ops` on the corresponding qubits, and the identity on all other qubits.
+fn term_expectation_value(circuit: &crate::circuit::Circuit, ops: &[(usize, PauliOp)])
+    -> crate::error::Result<f64>
+{
+    let mut string = vec![PauliOp::I; circuit.nr_qbits()];
+    for &(bit, op) in ops
+    {
+        if bit >= circuit.nr_qbits()
+        {
+            return Err(crate::error::Error::InvalidQBit(bit));
+        }
+        // Z_i Z_i = I, so that the expectation value of a coupling of a qubit
+        // to itself is simply one.
+        string[bit] = if string[bit] == op { PauliOp::I } else { op };
+    }
+
+    circuit.expectation_value(PauliString::new(&string))
+}
+
+/// Transverse field Ising model energy.
+///
+/// Compute the energy `⟨ψ|H|ψ⟩` of the current state `|ψ⟩` of `circuit` for the
+/// transverse field Ising model
+/// `H = Σ`<sub>`ij`</sub>` J`<sub>`ij`</sub>` Z`<sub>`i`</sub>`Z`<sub>`j`</sub>` + Σ`<sub>`i`</sub>` h`<sub>`i`</sub>` X`<sub>`i`</sub>.
+/// The couplings are given as triplets `(i, j, J`<sub>`ij`</sub>`)` in
+/// `j_couplings`, while `h_fields` holds the transverse field for each qubit in
+/// the circuit. The expectation value of each term is computed using
+/// `Circuit::expectation_value()`, so the circuit should have been executed
+/// first.
+pub fn ising_ground_state_energy(circuit: &crate::circuit::Circuit,
+    j_couplings: &[(usize, usize, f64)], h_fields: &[f64]) -> crate::error::Result<f64>
+{
+    if h_fields.len() != circuit.nr_qbits()
+    {
+        return Err(crate::error::Error::InvalidNrBits(h_fields.len(),
+            circuit.nr_qbits(), String::from("transverse field")));
+    }
+
+    let mut energy = 0.0;
+    for &(i, j, coupling) in j_couplings
+    {
+        energy += coupling * term_expectation_value(circuit, &[(i, PauliOp::Z), (j, PauliOp::Z)])?;
+    }
+    for (i, &field) in h_fields.iter().enumerate()
+    {
+        if field != 0.0
+        {
+            energy += field * term_expectation_value(circuit, &[(i, PauliOp::X)])?;
+        }
+    }
+
+    Ok(energy)
+}
+
+/// Heisenberg model energy.
+///
+/// Compute the energy `⟨ψ|H|ψ⟩` of the current state `|ψ⟩` of `circuit` for the
+/// isotropic Heisenberg model
+/// `H = J Σ`<sub>`ij`</sub>` (X`<sub>`i`</sub>`X`<sub>`j`</sub>` + Y`<sub>`i`</sub>`Y`<sub>`j`</sub>` + Z`<sub>`i`</sub>`Z`<sub>`j`</sub>`)`,
+/// where the sum runs over the pairs of qubits in `pairs`. The expectation
+/// value of each term is computed using `Circuit::expectation_value()`, so the
+/// circuit should have been executed first.
+pub fn heisenberg_energy(circuit: &crate::circuit::Circuit, j: f64, pairs: &[(usize, usize)])
+    -> crate::error::Result<f64>
+{
+    let mut energy = 0.0;
+    for &(b0, b1) in pairs
+    {
+        for &op in [PauliOp::X, PauliOp::Y, PauliOp::Z].iter()
+        {
+            energy += term_expectation_value(circuit, &[(b0, op), (b1, op)])?;
+        }
+    }
+
+    Ok(j * energy)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{heisenberg_energy, ising_ground_state_energy};
+    use crate::circuit::Circuit;
+
+    #[test]
+    fn test_ising_ground_state_energy()
+    {
+        let couplings = [(0, 1, 1.5), (1, 2, -0.5)];
+        let fields = [0.3, -0.2, 0.7];
+
+        // All spins up: only the couplings contribute
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = ising_ground_state_energy(&circuit, &couplings, &fields).unwrap();
+        assert!((energy - 1.0).abs() < 1.0e-12);
+
+        // All spins along x: only the fields contribute
+        let mut circuit = Circuit::new(3, 0);
+        for bit in 0..3
+        {
+            assert_eq!(circuit.h(bit), Ok(()));
+        }
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = ising_ground_state_energy(&circuit, &couplings, &fields).unwrap();
+        assert!((energy - 0.8).abs() < 1.0e-12);
+
+        // Anti-aligned spins
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = ising_ground_state_energy(&circuit, &couplings, &fields).unwrap();
+        assert!((energy + 1.0).abs() < 1.0e-12);
+
+        assert_eq!(ising_ground_state_energy(&circuit, &[(0, 3, 1.0)], &fields),
+            Err(crate::error::Error::InvalidQBit(3)));
+        assert_eq!(ising_ground_state_energy(&circuit, &couplings, &fields[..2]),
+            Err(crate::error::Error::InvalidNrBits(2, 3, String::from("transverse field"))));
+    }
+
+    #[test]
+    fn test_heisenberg_energy()
+    {
+        let j = 0.5;
+
+        // Singlet state has energy -3J, triplet states +J
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = heisenberg_energy(&circuit, j, &[(0, 1)]).unwrap();
+        assert!((energy - j).abs() < 1.0e-12);
+
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.z(0), Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = heisenberg_energy(&circuit, j, &[(0, 1)]).unwrap();
+        assert!((energy + 3.0 * j).abs() < 1.0e-12);
+
+        // Product state
+        let mut circuit = Circuit::new(3, 0);
+        assert_eq!(circuit.x(1), Ok(()));
+        assert_eq!(circuit.execute(1), Ok(()));
+        let energy = heisenberg_energy(&circuit, j, &[(0, 1), (1, 2), (0, 2)]).unwrap();
+        assert!((energy + j).abs() < 1.0e-12);
+
+        assert_eq!(heisenberg_energy(&circuit, j, &[(3, 1)]),
+            Err(crate::error::Error::InvalidQBit(3)));
+    }
+}
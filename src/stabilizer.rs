@@ -12,10 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod circuit;
+mod clifford;
 mod pauliop;
+mod paulistring;
 mod state;
 mod tableau;
 
+pub use circuit::{StabilizerCircuit, StabilizerOp, StabilizerResult};
+pub use clifford::{clifford_normal_form, clifford_to_circuit, CliffordTableau};
 pub use pauliop::PauliOp;
+pub use paulistring::PauliString;
 pub use state::StabilizerState;
 pub use tableau::{MeasurementInfo, StabilizerTableau};
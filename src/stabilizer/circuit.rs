@@ -0,0 +1,356 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::circuit::Basis;
+use crate::export::CircuitGate;
+use crate::stabilizer::{MeasurementInfo, StabilizerTableau};
+
+/// A single operation in a stabilizer circuit
+#[derive(Clone)]
+pub enum StabilizerOp
+{
+    /// Clifford gate, with the bits it operates on
+    Gate(Box<dyn CircuitGate>, Vec<usize>),
+    /// Measurement of a qubit in the given Pauli basis
+    Measure(usize, Basis)
+}
+
+/// The result of running a stabilizer circuit
+///
+/// Struct `StabilizerResult` holds the outcomes of the measurements in a
+/// `StabilizerCircuit`, in the order in which they were performed, along
+/// with the final state of the system.
+#[derive(Clone)]
+pub struct StabilizerResult
+{
+    /// The measurement outcomes, `true` for a result of one
+    outcomes: Vec<bool>,
+    /// The state after execution of the circuit
+    tableau: StabilizerTableau
+}
+
+impl StabilizerResult
+{
+    /// The outcomes of the measurements, in order of execution. A value of
+    /// `true` means the measurement yielded one.
+    pub fn outcomes(&self) -> &[bool]
+    {
+        &self.outcomes
+    }
+
+    /// The final state of the system
+    pub fn tableau(&self) -> &StabilizerTableau
+    {
+        &self.tableau
+    }
+}
+
+/// Circuit of Clifford operations
+///
+/// Struct `StabilizerCircuit` is a circuit that only holds Clifford gates
+/// and measurements in a Pauli basis. Every operation is checked when it is
+/// added, so that a `StabilizerCircuit` can always be run on a stabilizer
+/// tableau, at a cost of `O(n²)` per gate for a circuit on `n` qubits.
+#[derive(Clone)]
+pub struct StabilizerCircuit
+{
+    /// The number of qubits in the circuit
+    nr_bits: usize,
+    /// The operations in the circuit
+    ops: Vec<StabilizerOp>
+}
+
+impl StabilizerCircuit
+{
+    /// Create a new, empty, stabilizer circuit on `nr_bits` qubits.
+    pub fn new(nr_bits: usize) -> Self
+    {
+        StabilizerCircuit { nr_bits: nr_bits, ops: vec![] }
+    }
+
+    /// Create a stabilizer circuit from `circuit`.
+    ///
+    /// Convert the gates and measurements in `circuit` into a stabilizer
+    /// circuit. A `NotAStabilizer` error is returned when `circuit` contains
+    /// a non-Clifford gate, and a `NotACliffordCircuit` error for any other
+    /// operation that cannot be represented, like a reset or a conditional
+    /// gate. Barriers are ignored.
+    pub fn from_circuit(circuit: &crate::circuit::Circuit) -> crate::error::Result<Self>
+    {
+        let mut res = StabilizerCircuit::new(circuit.nr_qbits());
+        for op in circuit.ops()
+        {
+            match *op
+            {
+                crate::circuit::CircuitOp::Gate(ref gate, ref bits) => {
+                    if !gate.is_stabilizer()
+                    {
+                        return Err(crate::error::Error::NotAStabilizer(
+                            String::from(gate.description())));
+                    }
+                    res.ops.push(StabilizerOp::Gate(gate.clone(), bits.clone()));
+                },
+                crate::circuit::CircuitOp::Measure(qbit, _, basis) => {
+                    res.ops.push(StabilizerOp::Measure(qbit, basis));
+                },
+                crate::circuit::CircuitOp::MeasureAll(_, basis) => {
+                    for qbit in 0..res.nr_bits
+                    {
+                        res.ops.push(StabilizerOp::Measure(qbit, basis));
+                    }
+                },
+                crate::circuit::CircuitOp::Barrier(_) => { /* nothing to do */ },
+                _ => {
+                    return Err(crate::error::Error::NotACliffordCircuit);
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// The number of qubits in this circuit
+    pub fn nr_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    /// The operations in this circuit
+    pub fn ops(&self) -> &[StabilizerOp]
+    {
+        &self.ops
+    }
+
+    /// Check that the qubit indices in `bits` are valid for this circuit.
+    fn check_bits(&self, bits: &[usize]) -> crate::error::Result<()>
+    {
+        if let Some(&bit) = bits.iter().find(|&&bit| bit >= self.nr_bits)
+        {
+            Err(crate::error::Error::InvalidQBit(bit))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
+    /// Add a gate.
+    ///
+    /// Append gate `gate`, operating on qubits `bits`, to this circuit. If
+    /// `gate` is not a Clifford gate, a `NotAStabilizer` error is returned.
+    pub fn add_gate<G>(&mut self, gate: G, bits: &[usize]) -> crate::error::Result<()>
+    where G: 'static + CircuitGate
+    {
+        if !gate.is_stabilizer()
+        {
+            return Err(crate::error::Error::NotAStabilizer(String::from(gate.description())));
+        }
+        if gate.nr_affected_bits() != bits.len()
+        {
+            return Err(crate::error::Error::InvalidNrBits(bits.len(),
+                gate.nr_affected_bits(), String::from(gate.description())));
+        }
+        self.check_bits(bits)?;
+
+        self.ops.push(StabilizerOp::Gate(Box::new(gate), bits.to_vec()));
+        Ok(())
+    }
+
+    /// Add a measurement.
+    ///
+    /// Add measurement of qubit `qbit` in the Pauli `Z` basis to this circuit.
+    pub fn measure(&mut self, qbit: usize) -> crate::error::Result<()>
+    {
+        self.measure_basis(qbit, Basis::Z)
+    }
+
+    /// Add a measurement.
+    ///
+    /// Add measurement of qubit `qbit` in the Pauli basis `basis` to this
+    /// circuit.
+    pub fn measure_basis(&mut self, qbit: usize, basis: Basis) -> crate::error::Result<()>
+    {
+        self.check_bits(&[qbit])?;
+        self.ops.push(StabilizerOp::Measure(qbit, basis));
+        Ok(())
+    }
+
+    /// Execute this circuit
+    ///
+    /// Run this circuit on a stabilizer tableau, starting with all qubits
+    /// in the |0⟩ state, and return the measurement outcomes along with the
+    /// final state.
+    pub fn execute(&self) -> StabilizerResult
+    {
+        self.execute_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Execute this circuit
+    ///
+    /// Run this circuit on a stabilizer tableau, like `execute()`, using
+    /// random number generator `rng` for sampling random measurement
+    /// outcomes.
+    pub fn execute_with_rng<R: rand::Rng>(&self, rng: &mut R) -> StabilizerResult
+    {
+        let mut tableau = StabilizerTableau::new(self.nr_bits);
+        let mut outcomes = vec![];
+        for op in self.ops.iter()
+        {
+            // Gates are checked when they are added, so applying them
+            // cannot fail.
+            match *op
+            {
+                StabilizerOp::Gate(ref gate, ref bits) => {
+                    tableau.apply_gate(gate.as_gate(), bits).unwrap();
+                },
+                StabilizerOp::Measure(qbit, basis) => {
+                    match basis
+                    {
+                        Basis::X => {
+                            tableau.apply_gate(&crate::gates::H::new(), &[qbit]).unwrap();
+                            outcomes.push(Self::measure_z(&mut tableau, qbit, rng));
+                            tableau.apply_gate(&crate::gates::H::new(), &[qbit]).unwrap();
+                        },
+                        Basis::Y => {
+                            tableau.apply_gate(&crate::gates::Sdg::new(), &[qbit]).unwrap();
+                            tableau.apply_gate(&crate::gates::H::new(), &[qbit]).unwrap();
+                            outcomes.push(Self::measure_z(&mut tableau, qbit, rng));
+                            tableau.apply_gate(&crate::gates::H::new(), &[qbit]).unwrap();
+                            tableau.apply_gate(&crate::gates::S::new(), &[qbit]).unwrap();
+                        },
+                        Basis::Z => {
+                            outcomes.push(Self::measure_z(&mut tableau, qbit, rng));
+                        }
+                    }
+                }
+            }
+        }
+
+        StabilizerResult { outcomes: outcomes, tableau: tableau }
+    }
+
+    /// Measure qubit `qbit` in `tableau` in the `Z` basis, collapsing the
+    /// state if the outcome is random.
+    fn measure_z<R: rand::Rng>(tableau: &mut StabilizerTableau, qbit: usize, rng: &mut R) -> bool
+    {
+        match tableau.measure(qbit)
+        {
+            MeasurementInfo::Deterministic(value) => value,
+            MeasurementInfo::Random(i) => {
+                let value = rng.gen::<bool>();
+                tableau.collapse(i, qbit, value);
+                value
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{StabilizerCircuit, StabilizerOp};
+    use crate::circuit::Basis;
+    use crate::gates::{CX, H, S, T};
+
+    #[test]
+    fn test_add_gate()
+    {
+        let mut circuit = StabilizerCircuit::new(2);
+        assert_eq!(circuit.add_gate(H::new(), &[0]), Ok(()));
+        assert_eq!(circuit.add_gate(CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(circuit.measure_basis(1, Basis::X), Ok(()));
+        assert_eq!(circuit.nr_bits(), 2);
+        assert_eq!(circuit.ops().len(), 3);
+        assert!(matches!(circuit.ops()[2], StabilizerOp::Measure(1, Basis::X)));
+
+        assert_eq!(circuit.add_gate(T::new(), &[0]),
+            Err(crate::error::Error::NotAStabilizer(String::from("T"))));
+        assert_eq!(circuit.add_gate(H::new(), &[2]),
+            Err(crate::error::Error::InvalidQBit(2)));
+        assert_eq!(circuit.add_gate(CX::new(), &[0]),
+            Err(crate::error::Error::InvalidNrBits(1, 2, String::from("CX"))));
+        assert_eq!(circuit.measure(3), Err(crate::error::Error::InvalidQBit(3)));
+        assert_eq!(circuit.ops().len(), 3);
+    }
+
+    #[test]
+    fn test_from_circuit()
+    {
+        let mut circuit = crate::circuit::Circuit::new(2, 2);
+        circuit.add_gate(H::new(), &[0]).unwrap();
+        circuit.add_gate(CX::new(), &[0, 1]).unwrap();
+        circuit.barrier(&[0, 1]).unwrap();
+        circuit.measure_all_basis(&[0, 1], Basis::Y).unwrap();
+        let stab = StabilizerCircuit::from_circuit(&circuit).unwrap();
+        assert_eq!(stab.nr_bits(), 2);
+        assert_eq!(stab.ops().len(), 4);
+        assert!(matches!(stab.ops()[3], StabilizerOp::Measure(1, Basis::Y)));
+
+        circuit.add_gate(T::new(), &[1]).unwrap();
+        assert!(matches!(StabilizerCircuit::from_circuit(&circuit),
+            Err(crate::error::Error::NotAStabilizer(_))));
+
+        let mut circuit = crate::circuit::Circuit::new(1, 1);
+        circuit.reset(0).unwrap();
+        assert!(matches!(StabilizerCircuit::from_circuit(&circuit),
+            Err(crate::error::Error::NotACliffordCircuit)));
+    }
+
+    #[test]
+    fn test_execute()
+    {
+        let mut circuit = StabilizerCircuit::new(2);
+        circuit.add_gate(H::new(), &[0]).unwrap();
+        circuit.add_gate(CX::new(), &[0, 1]).unwrap();
+        circuit.measure(0).unwrap();
+        circuit.measure(1).unwrap();
+        for _ in 0..50
+        {
+            let res = circuit.execute();
+            assert_eq!(res.outcomes().len(), 2);
+            assert_eq!(res.outcomes()[0], res.outcomes()[1]);
+        }
+
+        // |+⟩ measured in X basis, and |i⟩ in the Y basis
+        let mut circuit = StabilizerCircuit::new(2);
+        circuit.add_gate(H::new(), &[0]).unwrap();
+        circuit.add_gate(H::new(), &[1]).unwrap();
+        circuit.add_gate(S::new(), &[1]).unwrap();
+        circuit.measure_basis(0, Basis::X).unwrap();
+        circuit.measure_basis(1, Basis::Y).unwrap();
+        circuit.measure_basis(1, Basis::X).unwrap();
+        let res = circuit.execute();
+        assert_eq!(res.outcomes().len(), 3);
+        assert_eq!(&res.outcomes()[..2], &[false, false]);
+    }
+
+    #[test]
+    fn test_execute_large()
+    {
+        let n = 200;
+        let mut circuit = StabilizerCircuit::new(n);
+        circuit.add_gate(H::new(), &[0]).unwrap();
+        for i in 1..n
+        {
+            circuit.add_gate(CX::new(), &[i-1, i]).unwrap();
+        }
+        for i in 0..n
+        {
+            circuit.measure(i).unwrap();
+        }
+        let res = circuit.execute();
+        let first = res.outcomes()[0];
+        assert!(res.outcomes().iter().all(|&v| v == first));
+    }
+}
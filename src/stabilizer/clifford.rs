@@ -0,0 +1,438 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::PauliOp;
+
+/// Gates used in the synthesis of Clifford circuits
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SynthGate
+{
+    H(usize),
+    S(usize),
+    CX(usize, usize)
+}
+
+/// Add gate `gate` to the gate sequence `gates`, removing H·H and S⁴ at the
+/// end of the sequence.
+fn push_simplified(gates: &mut Vec<SynthGate>, gate: SynthGate)
+{
+    let n = gates.len();
+    match gate
+    {
+        SynthGate::H(_) if n > 0 && gates[n-1] == gate => {
+            gates.pop();
+        },
+        SynthGate::S(_) if n > 2 && gates[n-3..].iter().all(|&g| g == gate) => {
+            gates.truncate(n-3);
+        },
+        _ => {
+            gates.push(gate);
+        }
+    }
+}
+
+/// Tableau describing a Clifford operation
+///
+/// Struct `CliffordTableau` describes a Clifford operation `U` by the images
+/// `UX`<sub>`k`</sub>`U`<sup>`†`</sup> and `UZ`<sub>`k`</sub>`U`<sup>`†`</sup>
+/// of the single qubit Pauli operators `X`<sub>`k`</sub> and
+/// `Z`<sub>`k`</sub> on each qubit `k`. These determine the operation up to a
+/// global phase, so two Clifford circuits are equivalent if and only if they
+/// have the same tableau.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliffordTableau
+{
+    /// The number of qubits the operation acts on
+    nr_bits: usize,
+    /// Signs and Pauli operators of the images. The first `nr_bits` rows hold
+    /// the images of the `X` operators, the last `nr_bits` rows the images of
+    /// the `Z` operators.
+    rows: Vec<(bool, Vec<PauliOp>)>
+}
+
+impl CliffordTableau
+{
+    /// Create a new Clifford tableau.
+    ///
+    /// Create a new tableau for the identity operation on `nr_bits` qubits.
+    pub fn new(nr_bits: usize) -> Self
+    {
+        let mut rows = vec![];
+        for op in [PauliOp::X, PauliOp::Z].iter()
+        {
+            for k in 0..nr_bits
+            {
+                let mut ops = vec![PauliOp::I; nr_bits];
+                ops[k] = *op;
+                rows.push((false, ops));
+            }
+        }
+
+        CliffordTableau { nr_bits: nr_bits, rows: rows }
+    }
+
+    /// Create a tableau from the images of the Pauli operators.
+    ///
+    /// Create a new tableau for the operation mapping `X`<sub>`k`</sub> to
+    /// `x_images[k]` and `Z`<sub>`k`</sub> to `z_images[k]`, where each image
+    /// is given as a sign (`true` for negative) and the Pauli operators on
+    /// every qubit. The images are not checked to satisfy the commutation
+    /// relations of the Pauli operators they replace.
+    pub(crate) fn from_images(x_images: Vec<(bool, Vec<PauliOp>)>,
+        z_images: Vec<(bool, Vec<PauliOp>)>) -> Self
+    {
+        let nr_bits = x_images.len();
+        let mut rows = x_images;
+        rows.extend(z_images);
+        CliffordTableau { nr_bits: nr_bits, rows: rows }
+    }
+
+    /// The number of qubits this operation acts on
+    pub fn nr_bits(&self) -> usize
+    {
+        self.nr_bits
+    }
+
+    /// The image of `X`<sub>`k`</sub>.
+    ///
+    /// Return the image of the Pauli `X` operator on qubit `k` under this
+    /// operation, as a sign (`true` for negative) and a Pauli string.
+    pub fn x_image(&self, k: usize) -> (bool, crate::stabilizer::PauliString)
+    {
+        let (sign, ref ops) = self.rows[k];
+        (sign, crate::stabilizer::PauliString::new(ops))
+    }
+
+    /// The image of `Z`<sub>`k`</sub>.
+    ///
+    /// Return the image of the Pauli `Z` operator on qubit `k` under this
+    /// operation, as a sign (`true` for negative) and a Pauli string.
+    pub fn z_image(&self, k: usize) -> (bool, crate::stabilizer::PauliString)
+    {
+        let (sign, ref ops) = self.rows[self.nr_bits + k];
+        (sign, crate::stabilizer::PauliString::new(ops))
+    }
+
+    /// Apply a gate.
+    ///
+    /// Extend the operation described by this tableau with gate `gate`,
+    /// operating on the qubits in `bits`. If `gate` is not a stabilizer gate,
+    /// a `NotAStabilizer` error is returned.
+    pub fn apply_gate<G>(&mut self, gate: &G, bits: &[usize]) -> crate::error::Result<()>
+    where G: crate::gates::Gate + ?Sized
+    {
+        gate.check_nr_bits(bits.len())?;
+        if let Some(&bit) = bits.iter().find(|&&b| b >= self.nr_bits)
+        {
+            return Err(crate::error::Error::InvalidQBit(bit));
+        }
+
+        let mut ops = vec![];
+        for (sign, row) in self.rows.iter_mut()
+        {
+            ops.clear();
+            ops.extend(bits.iter().map(|&j| row[j]));
+            *sign ^= gate.conjugate(&mut ops)?;
+            for (&j, &op) in bits.iter().zip(ops.iter())
+            {
+                row[j] = op;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply gate `gate` in the synthesis process, and record it in `gates`.
+    fn apply_synth(&mut self, gate: SynthGate, gates: &mut Vec<SynthGate>)
+    {
+        let res = match gate
+            {
+                SynthGate::H(k) => self.apply_gate(&crate::gates::H::new(), &[k]),
+                SynthGate::S(k) => self.apply_gate(&crate::gates::S::new(), &[k]),
+                SynthGate::CX(c, t) => self.apply_gate(&crate::gates::CX::new(), &[c, t])
+            };
+        // Only Clifford gates on valid qubits are used in the synthesis
+        res.unwrap();
+        gates.push(gate);
+    }
+
+    /// Synthesize a circuit.
+    ///
+    /// Create a circuit consisting of only `H`, `S` and `C`<sub>`X`</sub>
+    /// gates, implementing the Clifford operation described by this tableau
+    /// (up to a global phase). The circuit is found by reducing the tableau
+    /// to the identity, one qubit at a time, and inverting the reduction. As
+    /// this procedure is deterministic, equivalent Clifford operations result
+    /// in the same circuit.
+    pub fn to_circuit(&self) -> crate::circuit::Circuit
+    {
+        let n = self.nr_bits;
+        let mut tab = self.clone();
+        let mut gates = vec![];
+
+        for i in 0..n
+        {
+            // Reduce the image of X_i to X_i. First make all operators X.
+            for j in i..n
+            {
+                match tab.rows[i].1[j]
+                {
+                    PauliOp::Z => tab.apply_synth(SynthGate::H(j), &mut gates),
+                    PauliOp::Y => tab.apply_synth(SynthGate::S(j), &mut gates),
+                    _ => { }
+                }
+            }
+            if tab.rows[i].1[i] == PauliOp::I
+            {
+                let j = (i+1..n).find(|&j| tab.rows[i].1[j] == PauliOp::X).unwrap();
+                tab.apply_synth(SynthGate::CX(j, i), &mut gates);
+            }
+            for j in i+1..n
+            {
+                if tab.rows[i].1[j] == PauliOp::X
+                {
+                    tab.apply_synth(SynthGate::CX(i, j), &mut gates);
+                }
+            }
+
+            // Reduce the image of Z_i to Z_i, leaving X_i untouched
+            if tab.rows[n+i].1[i] == PauliOp::Y
+            {
+                tab.apply_synth(SynthGate::H(i), &mut gates);
+                tab.apply_synth(SynthGate::S(i), &mut gates);
+                tab.apply_synth(SynthGate::H(i), &mut gates);
+            }
+            for j in i+1..n
+            {
+                match tab.rows[n+i].1[j]
+                {
+                    PauliOp::X => tab.apply_synth(SynthGate::H(j), &mut gates),
+                    PauliOp::Y => {
+                        tab.apply_synth(SynthGate::S(j), &mut gates);
+                        tab.apply_synth(SynthGate::H(j), &mut gates);
+                    },
+                    _ => { }
+                }
+                if tab.rows[n+i].1[j] == PauliOp::Z
+                {
+                    tab.apply_synth(SynthGate::CX(j, i), &mut gates);
+                }
+            }
+        }
+
+        // Fix the signs: Z = S², X = HS²H
+        for i in 0..n
+        {
+            if tab.rows[i].0
+            {
+                tab.apply_synth(SynthGate::S(i), &mut gates);
+                tab.apply_synth(SynthGate::S(i), &mut gates);
+            }
+            if tab.rows[n+i].0
+            {
+                tab.apply_synth(SynthGate::H(i), &mut gates);
+                tab.apply_synth(SynthGate::S(i), &mut gates);
+                tab.apply_synth(SynthGate::S(i), &mut gates);
+                tab.apply_synth(SynthGate::H(i), &mut gates);
+            }
+        }
+
+        // The gates found reduce the operation to the identity, the circuit
+        // is formed by their inverse. Use S^† = S³.
+        let mut inverse = vec![];
+        for &gate in gates.iter().rev()
+        {
+            let count = if let SynthGate::S(_) = gate { 3 } else { 1 };
+            for _ in 0..count
+            {
+                push_simplified(&mut inverse, gate);
+            }
+        }
+
+        let mut circuit = crate::circuit::Circuit::new(n, 0);
+        for gate in inverse
+        {
+            let res = match gate
+                {
+                    SynthGate::H(k) => circuit.h(k),
+                    SynthGate::S(k) => circuit.s(k),
+                    SynthGate::CX(c, t) => circuit.cx(c, t)
+                };
+            res.unwrap();
+        }
+
+        circuit
+    }
+}
+
+/// Synthesize a Clifford circuit.
+///
+/// Create a circuit of `H`, `S` and `C`<sub>`X`</sub> gates implementing the
+/// Clifford operation described by tableau `clifford`, up to a global phase.
+/// See `CliffordTableau::to_circuit()`.
+pub fn clifford_to_circuit(clifford: &CliffordTableau) -> crate::circuit::Circuit
+{
+    clifford.to_circuit()
+}
+
+/// Clifford normal form.
+///
+/// Convert the Clifford circuit `circuit` into a canonical form, consisting of
+/// only `H`, `S` and `C`<sub>`X`</sub> gates. Two circuits implementing the
+/// same Clifford operation, up to a global phase, result in the same
+/// canonical circuit. When `circuit` contains non-Clifford gates, or
+/// operations other than gates and barriers, an error is returned.
+pub fn clifford_normal_form(circuit: &crate::circuit::Circuit)
+    -> crate::error::Result<crate::circuit::Circuit>
+{
+    Ok(circuit.clifford_tableau()?.to_circuit())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{clifford_normal_form, CliffordTableau};
+    use crate::circuit::Circuit;
+    use crate::gates::{Swap, CZ, H, T, X, Y, Z};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    /// Check that the normal forms of circuits `c0` and `c1` are the same
+    fn assert_same_normal_form(c0: &Circuit, c1: &Circuit)
+    {
+        let n0 = clifford_normal_form(c0).unwrap();
+        let n1 = clifford_normal_form(c1).unwrap();
+        assert_eq!(n0.open_qasm(), n1.open_qasm());
+        assert_eq!(n0.clifford_tableau(), c0.clifford_tableau());
+    }
+
+    #[test]
+    fn test_new()
+    {
+        let tab = CliffordTableau::new(2);
+        assert_eq!(tab.nr_bits(), 2);
+        assert_eq!(tab.x_image(0), (false, PauliString::new(&[PauliOp::X, PauliOp::I])));
+        assert_eq!(tab.x_image(1), (false, PauliString::new(&[PauliOp::I, PauliOp::X])));
+        assert_eq!(tab.z_image(0), (false, PauliString::new(&[PauliOp::Z, PauliOp::I])));
+        assert_eq!(tab.z_image(1), (false, PauliString::new(&[PauliOp::I, PauliOp::Z])));
+    }
+
+    #[test]
+    fn test_apply_gate()
+    {
+        let mut tab = CliffordTableau::new(2);
+        assert_eq!(tab.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(tab.apply_gate(&crate::gates::CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(tab.apply_gate(&Y::new(), &[1]), Ok(()));
+        assert_eq!(tab.x_image(0), (false, PauliString::new(&[PauliOp::Z, PauliOp::I])));
+        assert_eq!(tab.x_image(1), (true, PauliString::new(&[PauliOp::I, PauliOp::X])));
+        assert_eq!(tab.z_image(0), (true, PauliString::new(&[PauliOp::X, PauliOp::X])));
+        assert_eq!(tab.z_image(1), (true, PauliString::new(&[PauliOp::Z, PauliOp::Z])));
+
+        assert!(matches!(tab.apply_gate(&T::new(), &[0]),
+            Err(crate::error::Error::NotAStabilizer(_))));
+        assert_eq!(tab.apply_gate(&H::new(), &[2]), Err(crate::error::Error::InvalidQBit(2)));
+    }
+
+    #[test]
+    fn test_to_circuit()
+    {
+        let mut circuit = Circuit::new(4, 0);
+        circuit.h(0).unwrap();
+        circuit.cx(0, 3).unwrap();
+        circuit.s(2).unwrap();
+        circuit.add_gate(CZ::new(), &[2, 1]).unwrap();
+        circuit.h(1).unwrap();
+        circuit.add_gate(Y::new(), &[3]).unwrap();
+        circuit.sdg(0).unwrap();
+        circuit.add_gate(Swap::new(), &[1, 3]).unwrap();
+        circuit.cx(2, 0).unwrap();
+
+        let tab = circuit.clifford_tableau().unwrap();
+        let synth = tab.to_circuit();
+        assert_eq!(synth.clifford_tableau(), Ok(tab));
+        assert!(synth.open_qasm().unwrap().lines().skip(3)
+            .all(|l| l.starts_with("h ") || l.starts_with("s ") || l.starts_with("cx ")));
+
+        // Identity
+        let circuit = Circuit::new(3, 0);
+        let synth = clifford_normal_form(&circuit).unwrap();
+        assert_eq!(synth.open_qasm(), circuit.open_qasm());
+    }
+
+    #[test]
+    fn test_clifford_normal_form()
+    {
+        // CX with control and target exchanged by Hadamards
+        let mut c0 = Circuit::new(2, 0);
+        c0.cx(0, 1).unwrap();
+        let mut c1 = Circuit::new(2, 0);
+        c1.h(0).unwrap();
+        c1.h(1).unwrap();
+        c1.cx(1, 0).unwrap();
+        c1.h(0).unwrap();
+        c1.h(1).unwrap();
+        assert_same_normal_form(&c0, &c1);
+
+        // S^†ZXS = S^†(iY)S = iX
+        let mut c0 = Circuit::new(1, 0);
+        c0.add_gate(X::new(), &[0]).unwrap();
+        let mut c1 = Circuit::new(1, 0);
+        c1.s(0).unwrap();
+        c1.add_gate(X::new(), &[0]).unwrap();
+        c1.add_gate(Z::new(), &[0]).unwrap();
+        c1.sdg(0).unwrap();
+        assert_same_normal_form(&c0, &c1);
+
+        // Y = iXZ
+        let mut c0 = Circuit::new(2, 0);
+        c0.add_gate(Y::new(), &[1]).unwrap();
+        let mut c1 = Circuit::new(2, 0);
+        c1.add_gate(Z::new(), &[1]).unwrap();
+        c1.add_gate(X::new(), &[1]).unwrap();
+        assert_same_normal_form(&c0, &c1);
+
+        // Swap from three CX gates
+        let mut c0 = Circuit::new(3, 0);
+        c0.add_gate(Swap::new(), &[0, 2]).unwrap();
+        let mut c1 = Circuit::new(3, 0);
+        c1.cx(0, 2).unwrap();
+        c1.cx(2, 0).unwrap();
+        c1.cx(0, 2).unwrap();
+        assert_same_normal_form(&c0, &c1);
+
+        // Different operations give different normal forms
+        let mut c0 = Circuit::new(2, 0);
+        c0.cx(0, 1).unwrap();
+        let mut c1 = Circuit::new(2, 0);
+        c1.cx(1, 0).unwrap();
+        assert_ne!(clifford_normal_form(&c0).unwrap().open_qasm(),
+            clifford_normal_form(&c1).unwrap().open_qasm());
+    }
+
+    #[test]
+    fn test_clifford_normal_form_errors()
+    {
+        let mut circuit = Circuit::new(2, 1);
+        circuit.h(0).unwrap();
+        circuit.add_gate(T::new(), &[1]).unwrap();
+        assert!(matches!(clifford_normal_form(&circuit),
+            Err(crate::error::Error::NotAStabilizer(_))));
+
+        let mut circuit = Circuit::new(2, 1);
+        circuit.h(0).unwrap();
+        circuit.measure(0, 0).unwrap();
+        assert!(matches!(clifford_normal_form(&circuit),
+            Err(crate::error::Error::OpNotImplemented(_, _))));
+    }
+}
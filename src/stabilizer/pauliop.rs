@@ -52,6 +52,44 @@ impl PauliOp
             PauliOp::Y => 3
         }
     }
+
+    /// Return whether this operator commutes with `other`. Two Pauli
+    /// operators commute when either is the identity, or when they are equal.
+    pub fn commutes_with(self, other: PauliOp) -> bool
+    {
+        let (a, b) = (self.to_bits(), other.to_bits());
+        // Symplectic inner product of the (x, z) bit pairs
+        (((a >> 1) & b) ^ (a & (b >> 1))) & 1 == 0
+    }
+
+    /// Multiply Pauli operators.
+    ///
+    /// Compute the product of this operator with `other`, where `other` acts
+    /// first. The product of two Pauli operators is again a Pauli operator,
+    /// up to a phase factor of ±1 or ±i, e.g. `XY = iZ`. This function returns
+    /// the resulting operator and the phase factor.
+    pub fn multiply(self, other: PauliOp) -> (PauliOp, crate::cmatrix::CNumber)
+    {
+        let op = PauliOp::from_bits(self.to_bits() ^ other.to_bits());
+        let phase = match (self, other)
+            {
+                (PauliOp::X, PauliOp::Y) | (PauliOp::Y, PauliOp::Z) | (PauliOp::Z, PauliOp::X) => {
+                    crate::cmatrix::COMPLEX_I
+                },
+                (PauliOp::Y, PauliOp::X) | (PauliOp::Z, PauliOp::Y) | (PauliOp::X, PauliOp::Z) => {
+                    -crate::cmatrix::COMPLEX_I
+                },
+                _ => crate::cmatrix::COMPLEX_ONE
+            };
+        (op, phase)
+    }
+
+    /// Tensor product of this operator with `other`, where this operator acts
+    /// on the first qubit, and `other` on the second.
+    pub fn tensor(self, other: PauliOp) -> [PauliOp; 2]
+    {
+        [self, other]
+    }
 }
 
 impl ::std::fmt::Display for PauliOp
@@ -93,6 +131,62 @@ mod tests
         assert_eq!(PauliOp::to_bits(PauliOp::Y), 3);
     }
 
+    #[test]
+    fn test_commutes_with()
+    {
+        let ops = [PauliOp::I, PauliOp::Z, PauliOp::X, PauliOp::Y];
+        for &a in ops.iter()
+        {
+            for &b in ops.iter()
+            {
+                let expected = a == PauliOp::I || b == PauliOp::I || a == b;
+                assert_eq!(a.commutes_with(b), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiply()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let ops = [PauliOp::I, PauliOp::Z, PauliOp::X, PauliOp::Y];
+        for &op in ops.iter()
+        {
+            assert_eq!(PauliOp::I.multiply(op), (op, o));
+            assert_eq!(op.multiply(PauliOp::I), (op, o));
+            assert_eq!(op.multiply(op), (PauliOp::I, o));
+        }
+
+        assert_eq!(PauliOp::X.multiply(PauliOp::Y), (PauliOp::Z, i));
+        assert_eq!(PauliOp::Y.multiply(PauliOp::Z), (PauliOp::X, i));
+        assert_eq!(PauliOp::Z.multiply(PauliOp::X), (PauliOp::Y, i));
+        assert_eq!(PauliOp::Y.multiply(PauliOp::X), (PauliOp::Z, -i));
+        assert_eq!(PauliOp::Z.multiply(PauliOp::Y), (PauliOp::X, -i));
+        assert_eq!(PauliOp::X.multiply(PauliOp::Z), (PauliOp::Y, -i));
+
+        // Check against the matrices
+        for &a in ops.iter()
+        {
+            for &b in ops.iter()
+            {
+                let (op, phase) = a.multiply(b);
+                let ma = crate::stabilizer::PauliString::new(&[a]).matrix();
+                let mb = crate::stabilizer::PauliString::new(&[b]).matrix();
+                let expected = crate::stabilizer::PauliString::new(&[op]).matrix() * phase;
+                assert_complex_matrix_eq!(&ma.dot(&mb), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tensor()
+    {
+        assert_eq!(PauliOp::X.tensor(PauliOp::Z), [PauliOp::X, PauliOp::Z]);
+        assert_eq!(PauliOp::I.tensor(PauliOp::Y), [PauliOp::I, PauliOp::Y]);
+    }
+
     #[test]
     fn test_display()
     {
@@ -0,0 +1,275 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::PauliOp;
+
+/// Tensor product of Pauli operators
+///
+/// Struct `PauliString` describes a tensor product of single qubit Pauli
+/// operators, one for each qubit in the system. The first operator acts on
+/// qubit 0, the second on qubit 1, etc.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PauliString
+{
+    /// The Pauli operator for each qubit
+    ops: Vec<PauliOp>
+}
+
+impl PauliString
+{
+    /// Create a new Pauli string.
+    ///
+    /// Create a new Pauli string from the single qubit operators in `ops`.
+    pub fn new(ops: &[PauliOp]) -> Self
+    {
+        PauliString { ops: ops.to_vec() }
+    }
+
+    /// Create an identity operator.
+    ///
+    /// Create a Pauli string consisting of only identity operators, acting on
+    /// `nr_bits` qubits.
+    pub fn identity(nr_bits: usize) -> Self
+    {
+        PauliString { ops: vec![PauliOp::I; nr_bits] }
+    }
+
+    /// The number of qubits this Pauli string acts on
+    pub fn nr_bits(&self) -> usize
+    {
+        self.ops.len()
+    }
+
+    /// The single qubit operators making up this Pauli string
+    pub fn ops(&self) -> &[PauliOp]
+    {
+        &self.ops
+    }
+
+    /// Return whether this Pauli string is the identity operator
+    pub fn is_identity(&self) -> bool
+    {
+        self.ops.iter().all(|&op| op == PauliOp::I)
+    }
+
+    /// Return whether this Pauli string commutes with `other`.
+    ///
+    /// Two Pauli strings commute when the number of qubits on which their
+    /// operators anticommute is even, i.e. when their symplectic inner
+    /// product is zero. When the strings are of different length, the
+    /// shorter one is taken to act as the identity on the remaining qubits.
+    pub fn commutes_with(&self, other: &PauliString) -> bool
+    {
+        self.ops.iter().zip(other.ops.iter())
+            .filter(|&(&a, &b)| !a.commutes_with(b))
+            .count() % 2 == 0
+    }
+
+    /// Act on a basis state.
+    ///
+    /// Apply this operator to the basis state |`idx`⟩, where qubit 0
+    /// corresponds to the most significant bit in `idx`. A Pauli string maps
+    /// a basis state onto another basis state, multiplied by a phase factor.
+    /// This function returns the index of the resulting basis state, and the
+    /// phase factor.
+    pub fn apply_to_basis_state(&self, idx: usize) -> (usize, crate::cmatrix::CNumber)
+    {
+        let n = self.ops.len();
+        let mut res_idx = idx;
+        let mut phase = crate::cmatrix::COMPLEX_ONE;
+        for (i, &op) in self.ops.iter().enumerate()
+        {
+            let mask = 1 << (n - i - 1);
+            let bit_set = idx & mask != 0;
+            match op
+            {
+                PauliOp::I => { },
+                PauliOp::Z => {
+                    if bit_set
+                    {
+                        phase = -phase;
+                    }
+                },
+                PauliOp::X => {
+                    res_idx ^= mask;
+                },
+                PauliOp::Y => {
+                    res_idx ^= mask;
+                    phase *= if bit_set { -crate::cmatrix::COMPLEX_I } else { crate::cmatrix::COMPLEX_I };
+                }
+            }
+        }
+
+        (res_idx, phase)
+    }
+
+    /// Compute an expectation value.
+    ///
+    /// Compute the expectation value ⟨ψ|P|ψ⟩ of this Pauli string in the
+    /// (normalized) state with coefficient vector `state`.
+    pub fn expectation_value(&self, state: ndarray::ArrayView1<crate::cmatrix::CNumber>) -> f64
+    {
+        let mut res = crate::cmatrix::COMPLEX_ZERO;
+        for (col, &c) in state.iter().enumerate()
+        {
+            let (row, phase) = self.apply_to_basis_state(col);
+            res += state[row].conj() * phase * c;
+        }
+        res.re
+    }
+
+    /// The matrix for this operator.
+    ///
+    /// Return the full `2`<sup>`n`</sup>`×2`<sup>`n`</sup> matrix of this
+    /// Pauli string, where `n` is the number of qubits it acts on.
+    pub fn matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let size = 1 << self.ops.len();
+        let mut res = crate::cmatrix::CMatrix::zeros((size, size));
+        for col in 0..size
+        {
+            let (row, phase) = self.apply_to_basis_state(col);
+            res[[row, col]] = phase;
+        }
+        res
+    }
+}
+
+impl ::std::fmt::Display for PauliString
+{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        for op in self.ops.iter()
+        {
+            write!(f, "{}", op)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::PauliString;
+    use crate::stabilizer::PauliOp;
+
+    #[test]
+    fn test_new()
+    {
+        let ps = PauliString::new(&[PauliOp::X, PauliOp::I, PauliOp::Z]);
+        assert_eq!(ps.nr_bits(), 3);
+        assert_eq!(ps.ops(), &[PauliOp::X, PauliOp::I, PauliOp::Z]);
+        assert!(!ps.is_identity());
+
+        let ps = PauliString::identity(2);
+        assert_eq!(ps.ops(), &[PauliOp::I, PauliOp::I]);
+        assert!(ps.is_identity());
+    }
+
+    #[test]
+    fn test_commutes_with()
+    {
+        let xx = PauliString::new(&[PauliOp::X, PauliOp::X]);
+        let zz = PauliString::new(&[PauliOp::Z, PauliOp::Z]);
+        let zi = PauliString::new(&[PauliOp::Z, PauliOp::I]);
+        let yz = PauliString::new(&[PauliOp::Y, PauliOp::Z]);
+        assert!(xx.commutes_with(&zz));
+        assert!(!xx.commutes_with(&zi));
+        assert!(zz.commutes_with(&zi));
+        assert!(!zz.commutes_with(&yz));
+        assert_eq!(yz.commutes_with(&xx), xx.commutes_with(&yz));
+        assert!(xx.commutes_with(&xx));
+        assert!(xx.commutes_with(&PauliString::identity(2)));
+        assert!(!xx.commutes_with(&PauliString::new(&[PauliOp::Z])));
+
+        // Check against the matrices
+        let strings = [&xx, &zz, &zi, &yz];
+        for &a in strings.iter()
+        {
+            for &b in strings.iter()
+            {
+                let (ma, mb) = (a.matrix(), b.matrix());
+                let commutator = ma.dot(&mb) - mb.dot(&ma);
+                assert_eq!(a.commutes_with(b), commutator.iter().all(|c| c.norm() < 1.0e-12));
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_to_basis_state()
+    {
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let ps = PauliString::new(&[PauliOp::Z, PauliOp::X]);
+        assert_eq!(ps.apply_to_basis_state(0), (1, o));
+        assert_eq!(ps.apply_to_basis_state(1), (0, o));
+        assert_eq!(ps.apply_to_basis_state(2), (3, -o));
+        assert_eq!(ps.apply_to_basis_state(3), (2, -o));
+
+        let ps = PauliString::new(&[PauliOp::Y, PauliOp::I]);
+        assert_eq!(ps.apply_to_basis_state(0), (2, i));
+        assert_eq!(ps.apply_to_basis_state(1), (3, i));
+        assert_eq!(ps.apply_to_basis_state(2), (0, -i));
+        assert_eq!(ps.apply_to_basis_state(3), (1, -i));
+    }
+
+    #[test]
+    fn test_matrix()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let ps = PauliString::new(&[PauliOp::Y]);
+        assert_complex_matrix_eq!(ps.matrix(), &array![[z, -i], [i, z]]);
+
+        let ps = PauliString::new(&[PauliOp::X, PauliOp::Z]);
+        assert_complex_matrix_eq!(ps.matrix(), &array![
+            [z,  z, o,  z],
+            [z,  z, z, -o],
+            [o,  z, z,  z],
+            [z, -o, z,  z]
+        ]);
+    }
+
+    #[test]
+    fn test_expectation_value()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        // Bell state (|00⟩ + |11⟩)/√2
+        let state = array![x, z, z, x];
+        let ps = PauliString::new(&[PauliOp::X, PauliOp::X]);
+        assert!((ps.expectation_value(state.view()) - 1.0).abs() < 1.0e-15);
+        let ps = PauliString::new(&[PauliOp::Y, PauliOp::Y]);
+        assert!((ps.expectation_value(state.view()) + 1.0).abs() < 1.0e-15);
+        let ps = PauliString::new(&[PauliOp::Z, PauliOp::I]);
+        assert!(ps.expectation_value(state.view()).abs() < 1.0e-15);
+
+        // (|0⟩ + i|1⟩)/√2
+        let state = array![x, x*i];
+        let ps = PauliString::new(&[PauliOp::Y]);
+        assert!((ps.expectation_value(state.view()) - 1.0).abs() < 1.0e-15);
+    }
+
+    #[test]
+    fn test_display()
+    {
+        let ps = PauliString::new(&[PauliOp::X, PauliOp::I, PauliOp::Y, PauliOp::Z]);
+        assert_eq!(format!("{}", ps), "XIYZ");
+    }
+}
@@ -312,8 +312,133 @@ impl crate::qustate::QuState for StabilizerState
         Ok(())
     }
 
-    fn reset<R: rand::Rng>(&mut self, bit: usize, _rng: &mut R) -> crate::error::Result<()>
+    fn partial_measure_peek<R: rand::Rng>(&self, qbits: &[usize], cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        if cbits.len() != qbits.len()
+        {
+            return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), qbits.len()));
+        }
+        if let Some(&qbit) = qbits.iter().find(|&&b| b >= self.nr_bits)
+        {
+            return Err(crate::error::Error::InvalidQBit(qbit));
+        }
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let zero_mask = !cbits.iter().fold(0u64, |m, b| m | (1u64 << b));
+        let mut offset = 0;
+        for (tableau, &count) in self.tableaus.iter().zip(self.counts.iter())
+        {
+            // Measure the qubits one at a time, collapsing copies of the
+            // tableau, so that correlations between the outcomes are retained
+            let mut branches = vec![(0u64, count, tableau.clone())];
+            for (&qbit, &cbit) in qbits.iter().zip(cbits.iter())
+            {
+                let mut new_branches = vec![];
+                for (idx, c, mut t) in branches
+                {
+                    match t.measure(qbit)
+                    {
+                        MeasurementInfo::Deterministic(false) => {
+                            new_branches.push((idx, c, t));
+                        },
+                        MeasurementInfo::Deterministic(true) => {
+                            new_branches.push((idx | (1 << cbit), c, t));
+                        },
+                        MeasurementInfo::Random(i) => {
+                            let distribution = rand_distr::Binomial::new(c as u64, 0.5).unwrap();
+                            let n0 = rng.sample(distribution) as usize;
+                            if n0 > 0
+                            {
+                                let mut t0 = t.clone();
+                                t0.collapse(i, qbit, false);
+                                new_branches.push((idx, n0, t0));
+                            }
+                            if n0 < c
+                            {
+                                t.collapse(i, qbit, true);
+                                new_branches.push((idx | (1 << cbit), c - n0, t));
+                            }
+                        }
+                    }
+                }
+                branches = new_branches;
+            }
+
+            for (idx, c, _) in branches
+            {
+                res.slice_mut(s![offset..offset+c]).map_inplace(
+                    |b| *b = (*b & zero_mask) | idx
+                );
+                offset += c;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expectation_into(&self, obs: &crate::stabilizer::PauliString, cbit: usize,
+        res: &mut ndarray::Array1<u64>) -> crate::error::Result<()>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let one_mask = 1 << cbit;
+        let zero_mask = !one_mask;
+
+        let mut res_start = 0;
+        for (tableau, &count) in self.tableaus.iter().zip(self.counts.iter())
+        {
+            // Since this is not a physical operation, it is implemented
+            // straightforwardly on the coefficient vector of the state.
+            let value = obs.expectation_value(tableau.to_statevector().view());
+            let mut slice = res.slice_mut(s![res_start..res_start+count]);
+            if value > 0.0
+            {
+                slice.map_inplace(|b| *b |= one_mask);
+            }
+            else
+            {
+                slice.map_inplace(|b| *b &= zero_mask);
+            }
+            res_start += count;
+        }
+
+        Ok(())
+    }
+
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString)
+        -> crate::error::Result<f64>
     {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+
+        let sum: f64 = self.tableaus.iter().zip(self.counts.iter())
+            .map(|(tableau, &count)| {
+                count as f64 * obs.expectation_value(tableau.to_statevector().view())
+            })
+            .sum();
+        Ok(sum / self.nr_shots as f64)
+    }
+
+    fn reset<R: rand::Rng>(&mut self, bit: usize, rng: &mut R) -> crate::error::Result<()>
+    {
+        // Measure first, so that a qubit entangled with others collapses
+        // to a random outcome in each run, then force it to zero.
+        self.measure(bit, rng)?;
         for tableau in self.tableaus.iter_mut()
         {
             tableau.reset(bit);
@@ -326,6 +451,23 @@ impl crate::qustate::QuState for StabilizerState
         self.tableaus = vec![StabilizerTableau::new(self.nr_bits)];
         self.counts = vec![self.nr_shots];
     }
+
+    fn are_shots_entangled(&self) -> bool
+    {
+        self.counts.len() > 1
+    }
+
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>
+    {
+        if self.nr_shots == 1
+        {
+            Some(self.tableaus[0].to_statevector())
+        }
+        else
+        {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
@@ -519,6 +661,35 @@ r#"+XI
         }
     }
 
+    #[test]
+    fn test_partial_measure_peek()
+    {
+        let nr_shots = 1024;
+        let mut measurements = ndarray::Array1::zeros(nr_shots);
+
+        let mut rng = rand::thread_rng();
+
+        // (|010⟩ + |111⟩)/√2
+        let mut s = StabilizerState::new(3, nr_shots);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 2]), Ok(()));
+        assert_eq!(s.apply_gate(&X::new(), &[1]), Ok(()));
+        let before = s.tableaus.iter().map(|t| format!("{}", t)).collect::<Vec<String>>().join("\n\n");
+
+        assert_eq!(s.partial_measure_peek(&[2, 1, 0], &[0, 1, 2], &mut measurements, &mut rng), Ok(()));
+        assert!(measurements.iter().all(|&bits| bits == 2 || bits == 7));
+        let n7 = measurements.iter().filter(|&&bits| bits == 7).count();
+        assert!(crate::stats::measurement_ok(n7, nr_shots, 0.5, 1.0e-5));
+        let after = s.tableaus.iter().map(|t| format!("{}", t)).collect::<Vec<String>>().join("\n\n");
+        assert_eq!(after, before);
+        assert_eq!(s.counts, vec![nr_shots]);
+
+        assert_eq!(s.partial_measure_peek(&[0, 1], &[0], &mut measurements, &mut rng),
+            Err(crate::error::Error::InvalidNrMeasurementBits(1, 2)));
+        assert_eq!(s.partial_measure_peek(&[3], &[0], &mut measurements, &mut rng),
+            Err(crate::error::Error::InvalidQBit(3)));
+    }
+
     #[test]
     fn test_peek_into()
     {
@@ -666,8 +837,10 @@ r#"+XI
         assert_eq!(s.apply_gate(&X::new(), &[0]), Ok(()));
         assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
         assert_eq!(s.reset(0, &mut rng), Ok(()));
-        let ss = s.tableaus.iter().map(|t| format!("{}", t)).collect::<Vec<String>>().join("\n\n");
-        assert_eq!(ss, String::from("+ZI\n+IZ"));
+        // The runs are not merged after the reset, each keeps its own
+        // measurement outcome
+        assert_eq!(s.counts.iter().sum::<usize>(), nr_runs);
+        assert!(s.tableaus.iter().all(|t| format!("{}", t) == "+ZI\n+IZ"));
 
         let mut s = StabilizerState::new(2, nr_runs);
         assert_eq!(s.apply_gate(&X::new(), &[0]), Ok(()));
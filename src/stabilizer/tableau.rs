@@ -14,6 +14,15 @@
 
 use crate::stabilizer::PauliOp;
 
+/// Power of i in the product of two Pauli operators, indexed by their bit
+/// representations
+const PHASE_FACTORS: [u8; 16] = [
+    0, 0, 0, 0,
+    0, 0, 1, 3,
+    0, 3, 0, 1,
+    0, 1, 3, 0
+];
+
 /// Structure describing the expected outcome of a measurement
 #[derive(Debug, PartialEq)]
 pub enum MeasurementInfo
@@ -26,6 +35,14 @@ pub enum MeasurementInfo
 }
 
 /// Structure describing a single stabilizer state
+///
+/// Struct `StabilizerTableau` holds the generators of the stabilizer group of
+/// a stabilizer state, along with a set of destabilizer generators, as in the
+/// algorithm of Aaronson and Gottesman. Destabilizer `i` anticommutes with
+/// stabilizer `i`, and commutes with all other stabilizers and destabilizers.
+/// Keeping track of the destabilizers allows for measuring a qubit without
+/// bringing the tableau in a canonical form first. The signs of the
+/// destabilizers are irrelevant, and are not stored.
 #[derive(Clone)]
 pub struct StabilizerTableau
 {
@@ -34,7 +51,9 @@ pub struct StabilizerTableau
     /// Generators of the stabilizer group for this state
     xz: Vec<u64>,
     /// Signs of the generators
-    signs: Vec<u64>
+    signs: Vec<u64>,
+    /// Destabilizer generators
+    destabilizers: Vec<u64>
 }
 
 impl StabilizerTableau
@@ -46,12 +65,14 @@ impl StabilizerTableau
         let mut res = StabilizerTableau {
             nr_bits: nr_bits,
             xz: vec![0; xz_size],
-            signs: vec![0; signs_size]
+            signs: vec![0; signs_size],
+            destabilizers: vec![0; xz_size]
         };
 
         for i in 0..nr_bits
         {
             res.set(i, i, PauliOp::Z);
+            res.set_destabilizer_bits(i, i, PauliOp::X.to_bits());
         }
 
         res
@@ -98,6 +119,25 @@ impl StabilizerTableau
         self.set_bits(i, j, op.to_bits());
     }
 
+    fn get_destabilizer_bits(&self, i: usize, j: usize) -> u64
+    {
+        let (byte_idx, bit_idx) = self.bit_indices(i, j);
+        (self.destabilizers[byte_idx] >> bit_idx) & 0x03
+    }
+
+    fn get_destabilizer_x(&self, i: usize, j: usize) -> bool
+    {
+        let (byte_idx, bit_idx) = self.bit_indices(i, j);
+        self.destabilizers[byte_idx] & (2 << bit_idx) != 0
+    }
+
+    fn set_destabilizer_bits(&mut self, i: usize, j: usize, op: u64)
+    {
+        let (byte_idx, bit_idx) = self.bit_indices(i, j);
+        self.destabilizers[byte_idx] = (self.destabilizers[byte_idx] & !(0x03 << bit_idx))
+            | ((op & 0x03) << bit_idx);
+    }
+
     fn get_sign(&self, i: usize) -> bool
     {
         let (byte_idx, bit_idx) = (i >> 6, i & 0x3f);
@@ -136,13 +176,6 @@ impl StabilizerTableau
 
     fn multiply_row(&mut self, i0: usize, i1: usize)
     {
-        const PHASE_FACTORS: [u8; 16] = [
-            0, 0, 0, 0,
-            0, 0, 1, 3,
-            0, 1, 0, 3,
-            0, 1, 3, 0
-        ];
-
         let mut i_pow = if self.get_sign(i1) { 2 } else { 0 };
         for j in 0..self.nr_bits
         {
@@ -158,6 +191,48 @@ impl StabilizerTableau
         self.xor_sign(i0, i_pow == 2);
     }
 
+    /// Multiply destabilizer `i0` by stabilizer `i1`, ignoring the sign.
+    fn multiply_destabilizer(&mut self, i0: usize, i1: usize)
+    {
+        for j in 0..self.nr_bits
+        {
+            let xz = self.get_destabilizer_bits(i0, j) ^ self.get_bits(i1, j);
+            self.set_destabilizer_bits(i0, j, xz);
+        }
+    }
+
+    /// Compute the sign of the product of the stabilizers with indices in
+    /// `rows`.
+    fn product_sign<I>(&self, rows: I) -> bool
+    where I: Iterator<Item=usize>
+    {
+        let mut ops = vec![0; self.nr_bits];
+        let mut i_pow = 0;
+        for i in rows
+        {
+            if self.get_sign(i)
+            {
+                i_pow ^= 2;
+            }
+            for (j, op) in ops.iter_mut().enumerate()
+            {
+                let xz = self.get_bits(i, j);
+                let idx = (*op << 2 | xz) as usize;
+                i_pow = (i_pow + PHASE_FACTORS[idx]) & 0x03;
+                *op ^= xz;
+            }
+        }
+
+        assert!(i_pow == 0 || i_pow == 2);
+        i_pow == 2
+    }
+
+    /// Bring the stabilizer generators in a canonical form.
+    ///
+    /// Perform Gaussian elimination on the stabilizer generators, with the
+    /// `X` components first, so that equal states lead to equal generators.
+    /// This invalidates the destabilizers, and should therefore only be used
+    /// on a copy of the tableau.
     fn normalize(&mut self)
     {
         let n = self.nr_bits;
@@ -211,9 +286,15 @@ impl StabilizerTableau
                 self.set(i, j, op);
             }
             self.xor_sign(i, flip_sign);
-        }
 
-        self.normalize();
+            ops.clear();
+            ops.extend(bits.iter().map(|&j| PauliOp::from_bits(self.get_destabilizer_bits(i, j))));
+            gate.conjugate(&mut ops)?;
+            for (&j, &op) in bits.iter().zip(ops.iter())
+            {
+                self.set_destabilizer_bits(i, j, op.to_bits());
+            }
+        }
 
         Ok(())
     }
@@ -224,25 +305,31 @@ impl StabilizerTableau
     /// Calling this function is only done for measurements with random outcomes,
     /// and in this case there should be at least one row in the tableau with
     /// an X or Y operator at the `bit` position. The number `i` is the index
-    /// of the last such row in the tableau.
+    /// of such a row, as returned by `measure()`.
     /// Use this function only in combination with `measure()`.
     pub fn collapse(&mut self, i: usize, bit: usize, value: bool)
     {
-        for k in 0..i
+        for k in 0..self.nr_bits
         {
-            if self.get_x(k, bit)
+            if k != i && self.get_x(k, bit)
             {
                 self.multiply_row(k, i);
             }
+            if k != i && self.get_destabilizer_x(k, bit)
+            {
+                self.multiply_destabilizer(k, i);
+            }
         }
 
+        // The old stabilizer anticommutes with the measured operator, and
+        // commutes with all other generators, so it becomes the new
+        // destabilizer.
         for j in 0..self.nr_bits
         {
+            self.set_destabilizer_bits(i, j, self.get_bits(i, j));
             self.set(i, j, if j == bit { PauliOp::Z } else { PauliOp::I });
         }
         self.set_sign(i, value);
-
-        self.normalize();
     }
 
     /// Measure a qubit
@@ -253,16 +340,17 @@ impl StabilizerTableau
     /// which case a `Random` result is returned.
     pub fn measure(&self, bit: usize) -> MeasurementInfo
     {
-        if let Some(i) = (0..self.nr_bits).rev().filter(|&i| self.get_x(i, bit)).next()
+        if let Some(i) = (0..self.nr_bits).find(|&i| self.get_x(i, bit))
         {
             MeasurementInfo::Random(i)
         }
         else
         {
-            let i = (0..self.nr_bits).rev()
-                .filter(|&i| self.get(i, bit) == PauliOp::Z)
-                .next().unwrap();
-            MeasurementInfo::Deterministic(self.get_sign(i))
+            // The measured operator commutes with all stabilizers, so it is
+            // (up to sign) the product of the stabilizers whose destabilizer
+            // anticommutes with it.
+            let rows = (0..self.nr_bits).filter(|&i| self.get_destabilizer_x(i, bit));
+            MeasurementInfo::Deterministic(self.product_sign(rows))
         }
     }
 
@@ -271,34 +359,112 @@ impl StabilizerTableau
     /// Reset the qubit with index `bit` to zero.
     pub fn reset(&mut self, bit: usize)
     {
-        if let Some(i) = (0..self.nr_bits).rev().filter(|&i| self.get_x(i, bit)).next()
+        match self.measure(bit)
         {
-            // Bit is in superposition. Collapse it to zero.
-            self.collapse(i, bit, false);
+            MeasurementInfo::Random(i) => {
+                // Bit is in superposition. Collapse it to zero.
+                self.collapse(i, bit, false);
+            },
+            MeasurementInfo::Deterministic(true) => {
+                // Bit is one. Flip it by applying X, which changes the sign
+                // of every stabilizer with a Z or Y operator on the bit.
+                for i in 0..self.nr_bits
+                {
+                    if self.get_z(i, bit)
+                    {
+                        self.xor_sign(i, true);
+                    }
+                }
+            },
+            MeasurementInfo::Deterministic(false) => {}
         }
-        else
+    }
+
+    /// Return the Pauli string for generator `i`, along with its sign.
+    fn generator(&self, i: usize) -> (bool, crate::stabilizer::PauliString)
+    {
+        let ops: Vec<PauliOp> = (0..self.nr_bits).map(|j| self.get(i, j)).collect();
+        (self.get_sign(i), crate::stabilizer::PauliString::new(&ops))
+    }
+
+    /// Apply the projector `(1+S)/2` for generator `i` to vector `v`.
+    fn project(&self, i: usize, v: &crate::cmatrix::CVector) -> crate::cmatrix::CVector
+    {
+        let (sign, ps) = self.generator(i);
+        let mut res = v * crate::cmatrix::CNumber::new(0.5, 0.0);
+        for (idx, &c) in v.iter().enumerate()
+        {
+            let (dst, phase) = ps.apply_to_basis_state(idx);
+            let factor = if sign { -0.5 } else { 0.5 };
+            res[dst] += c * phase * factor;
+        }
+        res
+    }
+
+    /// The coefficient vector.
+    ///
+    /// Compute the coefficient vector of the state described by this tableau,
+    /// by applying the projector `(1+S`<sub>`i`</sub>`)/2` for every
+    /// generator `S`<sub>`i`</sub> to |00...0⟩. If this state is orthogonal
+    /// to |00...0⟩, the next basis state is tried, until a non-zero result is
+    /// found. The global phase of the result is chosen such that the first
+    /// non-zero coefficient is real and positive.
+    pub fn to_statevector(&self) -> crate::cmatrix::CVector
+    {
+        let size = 1 << self.nr_bits;
+        for idx in 0..size
         {
-            // Bit is either zero or one. Force it to zero.
-            let i = (0..self.nr_bits).rev()
-                .filter(|&i| self.get(i, bit) == PauliOp::Z)
-                .next().unwrap();
-            self.set_sign(i, false);
+            let mut v = crate::cmatrix::CVector::zeros(size);
+            v[idx] = crate::cmatrix::COMPLEX_ONE;
+            for i in 0..self.nr_bits
+            {
+                v = self.project(i, &v);
+            }
+
+            let norm = v.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+            // Non-zero overlaps of a stabilizer state with a basis state are
+            // at least 2^(-n/2), so this threshold is safe.
+            if norm > 1.0e-8
+            {
+                let first = v.iter().find(|c| c.norm() > 1.0e-8).cloned().unwrap();
+                let phase = first.conj() / (first.norm() * norm);
+                return v.mapv(|c| c * phase);
+            }
         }
+
+        // The generators of a valid tableau always stabilize a pure state, so
+        // one of the basis states above must have a non-zero overlap with it.
+        unreachable!()
+    }
+
+    /// The density matrix.
+    ///
+    /// Compute the density matrix `ρ = |ψ⟩⟨ψ|` of the pure state `|ψ⟩`
+    /// described by this tableau.
+    pub fn to_density_matrix(&self) -> crate::cmatrix::CMatrix
+    {
+        let psi = self.to_statevector();
+        let n = psi.len();
+        crate::cmatrix::CMatrix::from_shape_fn((n, n), |(i, j)| psi[i] * psi[j].conj())
     }
 }
 
 
 impl ::std::fmt::Display for StabilizerTableau
 {
+    /// Display the stabilizer generators of this tableau, in canonical form.
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
     {
-        let n = self.nr_bits;
+        let mut t = self.clone();
+        t.normalize();
+
+        let n = t.nr_bits;
         for i in 0..n
         {
-            write!(f, "{}", if self.get_sign(i) { '-' } else { '+' })?;
+            write!(f, "{}", if t.get_sign(i) { '-' } else { '+' })?;
             for j in 0..n
             {
-                write!(f, "{}", self.get(i, j))?;
+                write!(f, "{}", t.get(i, j))?;
             }
             if i < n-1
             {
@@ -318,6 +484,59 @@ mod tests
 
     use ::std::fmt::Write;
 
+    #[test]
+    fn test_phase_factors()
+    {
+        let i = crate::cmatrix::COMPLEX_I;
+        for a in 0..4u64
+        {
+            for b in 0..4u64
+            {
+                let pa = crate::stabilizer::PauliString::new(&[PauliOp::from_bits(a)]).matrix();
+                let pb = crate::stabilizer::PauliString::new(&[PauliOp::from_bits(b)]).matrix();
+                let pab = crate::stabilizer::PauliString::new(&[PauliOp::from_bits(a ^ b)]).matrix();
+                let phase = i.powi(super::PHASE_FACTORS[(a << 2 | b) as usize] as i32);
+                let product = pa.dot(&pb);
+                let expected = pab * phase;
+                assert_complex_matrix_eq!(&product, &expected, 1.0e-15);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiply_row()
+    {
+        // (XZ)(ZX) = (-iY)(iY) = +YY
+        let mut m = StabilizerTableau::new(2);
+        m.set(0, 0, PauliOp::X);
+        m.set(0, 1, PauliOp::Z);
+        m.set(1, 0, PauliOp::Z);
+        m.set(1, 1, PauliOp::X);
+        m.multiply_row(0, 1);
+        assert_eq!((m.get(0, 0), m.get(0, 1)), (PauliOp::Y, PauliOp::Y));
+        assert!(!m.get_sign(0));
+
+        // (XY)(YX) = (iZ)(-iZ) = +ZZ
+        let mut m = StabilizerTableau::new(2);
+        m.set(0, 0, PauliOp::X);
+        m.set(0, 1, PauliOp::Y);
+        m.set(1, 0, PauliOp::Y);
+        m.set(1, 1, PauliOp::X);
+        m.multiply_row(0, 1);
+        assert_eq!((m.get(0, 0), m.get(0, 1)), (PauliOp::Z, PauliOp::Z));
+        assert!(!m.get_sign(0));
+
+        // (XX)(YY) = (iZ)(iZ) = -ZZ
+        let mut m = StabilizerTableau::new(2);
+        m.set(0, 0, PauliOp::X);
+        m.set(0, 1, PauliOp::X);
+        m.set(1, 0, PauliOp::Y);
+        m.set(1, 1, PauliOp::Y);
+        m.multiply_row(0, 1);
+        assert_eq!((m.get(0, 0), m.get(0, 1)), (PauliOp::Z, PauliOp::Z));
+        assert!(m.get_sign(0));
+    }
+
     #[test]
     fn test_new()
     {
@@ -418,15 +637,16 @@ r"+ZIIIIIIIIIIII
 +IIIIIIIIIIIZI
 +IIIIIIIIIIIIZ"));
 
-        m.set(1, 3, PauliOp::X);
-        m.set(12, 11, PauliOp::Y);
+        assert_eq!(m.apply_gate(&H::new(), &[3]), Ok(()));
+        assert_eq!(m.apply_gate(&CX::new(), &[3, 1]), Ok(()));
+        assert_eq!(m.apply_gate(&X::new(), &[12]), Ok(()));
         s.clear();
         assert_eq!(write!(s, "{}", m), Ok(()));
         assert_eq!(s, String::from(
-r"+ZIIIIIIIIIIII
-+IZIXIIIIIIIII
+r"+IXIXIIIIIIIII
++ZIIIIIIIIIIII
++IZIZIIIIIIIII
 +IIZIIIIIIIIII
-+IIIZIIIIIIIII
 +IIIIZIIIIIIII
 +IIIIIZIIIIIII
 +IIIIIIZIIIIII
@@ -435,7 +655,7 @@ r"+ZIIIIIIIIIIII
 +IIIIIIIIIZIII
 +IIIIIIIIIIZII
 +IIIIIIIIIIIZI
-+IIIIIIIIIIIYZ"));
+-IIIIIIIIIIIIZ"));
     }
 
     #[test]
@@ -574,11 +794,126 @@ r"+IXZ
     {
         let mut m = StabilizerTableau::new(3);
         assert_eq!(m.apply_gate(&H::new(), &[1]), Ok(()));
-        assert_eq!(m.measure(1), MeasurementInfo::Random(0));
+        assert_eq!(m.measure(1), MeasurementInfo::Random(1));
 
         let mut m = StabilizerTableau::new(2);
         assert_eq!(m.apply_gate(&H::new(), &[0]), Ok(()));
         assert_eq!(m.apply_gate(&CX::new(), &[0, 1]), Ok(()));
         assert_eq!(m.measure(0), MeasurementInfo::Random(0));
     }
+
+    #[test]
+    fn test_measure_large()
+    {
+        // GHZ state on many qubits: after measuring the first qubit, all
+        // other qubits should be determined, and equal to the first.
+        let n = 100;
+        for &value in [false, true].iter()
+        {
+            let mut m = StabilizerTableau::new(n);
+            assert_eq!(m.apply_gate(&H::new(), &[0]), Ok(()));
+            for i in 1..n
+            {
+                assert_eq!(m.apply_gate(&CX::new(), &[i-1, i]), Ok(()));
+            }
+
+            match m.measure(0)
+            {
+                MeasurementInfo::Random(i) => m.collapse(i, 0, value),
+                // LCOV_EXCL_START
+                info => panic!("Unexpected measurement result {:?}", info)
+                // LCOV_EXCL_STOP
+            }
+            for i in 0..n
+            {
+                assert_eq!(m.measure(i), MeasurementInfo::Deterministic(value));
+            }
+
+            m.reset(n-1);
+            assert_eq!(m.measure(n-1), MeasurementInfo::Deterministic(false));
+            assert_eq!(m.measure(n-2), MeasurementInfo::Deterministic(value));
+        }
+    }
+
+    #[test]
+    fn test_to_statevector()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        let m = StabilizerTableau::new(2);
+        assert_complex_vector_eq!(&m.to_statevector(), &array![o, z, z, z]);
+
+        let mut m = StabilizerTableau::new(2);
+        assert_eq!(m.apply_gate(&X::new(), &[0]), Ok(()));
+        assert_complex_vector_eq!(&m.to_statevector(), &array![z, z, o, z]);
+
+        let mut m = StabilizerTableau::new(2);
+        assert_eq!(m.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(m.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_complex_vector_eq!(&m.to_statevector(), &array![x, z, z, x], 1.0e-15);
+
+        let mut m = StabilizerTableau::new(1);
+        assert_eq!(m.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(m.apply_gate(&S::new(), &[0]), Ok(()));
+        assert_complex_vector_eq!(&m.to_statevector(), &array![x, x*i], 1.0e-15);
+
+        // Compare with full state vector simulation
+        let ops: Vec<(Box<dyn crate::gates::Gate>, Vec<usize>)> = vec![
+            (Box::new(H::new()), vec![0]),
+            (Box::new(X::new()), vec![4]),
+            (Box::new(CX::new()), vec![0, 2]),
+            (Box::new(H::new()), vec![3]),
+            (Box::new(S::new()), vec![3]),
+            (Box::new(CY::new()), vec![3, 1]),
+            (Box::new(CZ::new()), vec![2, 4]),
+            (Box::new(Sdg::new()), vec![0]),
+            (Box::new(V::new()), vec![4]),
+            (Box::new(Y::new()), vec![2])
+        ];
+        let mut m = StabilizerTableau::new(5);
+        let mut dense = crate::cmatrix::CVector::zeros(32);
+        dense[0] = o;
+        for (gate, bits) in ops.iter()
+        {
+            assert_eq!(m.apply_gate(gate.as_ref(), bits), Ok(()));
+            crate::gates::apply_gate_slice(dense.view_mut(), gate.as_ref(), bits, 5);
+        }
+        let psi = m.to_statevector();
+        let overlap = psi.iter().zip(dense.iter())
+            .map(|(a, b)| a.conj() * b)
+            .sum::<crate::cmatrix::CNumber>();
+        assert!((overlap.norm() - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_to_density_matrix()
+    {
+        for n in 1..6
+        {
+            let mut m = StabilizerTableau::new(n);
+            for i in 0..n
+            {
+                assert_eq!(m.apply_gate(&H::new(), &[i]), Ok(()));
+                if i > 0
+                {
+                    assert_eq!(m.apply_gate(&CZ::new(), &[i-1, i]), Ok(()));
+                }
+            }
+            assert_eq!(m.apply_gate(&S::new(), &[0]), Ok(()));
+            assert_eq!(m.apply_gate(&Vdg::new(), &[n-1]), Ok(()));
+
+            let rho = m.to_density_matrix();
+            assert_eq!(rho.dim(), (1 << n, 1 << n));
+
+            // Hermitian, unit trace, and pure
+            let rho_dag = rho.t().mapv(|c| c.conj());
+            assert_complex_matrix_eq!(&rho, &rho_dag);
+            let trace = rho.diag().iter().sum::<crate::cmatrix::CNumber>();
+            assert!((trace - crate::cmatrix::COMPLEX_ONE).norm() < 1.0e-12);
+            assert_complex_matrix_eq!(&rho.dot(&rho), &rho, 1.0e-12);
+        }
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Add the preparation of the Bell state (|00⟩ + |11⟩)/√2 on qubits 0 and 1
+/// to `circuit`.
+fn add_bell_pair(circuit: &mut crate::circuit::Circuit) -> crate::error::Result<()>
+{
+    circuit.h(0)?;
+    circuit.cx(0, 1)
+}
+
+/// Add the encoding of `message` on Alice's qubit 0 to `circuit`.
+fn add_send(circuit: &mut crate::circuit::Circuit, message: (bool, bool))
+    -> crate::error::Result<()>
+{
+    if message.1
+    {
+        circuit.x(0)?;
+    }
+    if message.0
+    {
+        circuit.z(0)?;
+    }
+    Ok(())
+}
+
+/// Add the decoding of the message in qubits 0 and 1 to `circuit`.
+fn add_receive(circuit: &mut crate::circuit::Circuit) -> crate::error::Result<()>
+{
+    circuit.cx(0, 1)?;
+    circuit.h(0)?;
+    circuit.measure(0, 0)?;
+    circuit.measure(1, 1)
+}
+
+/// Superdense coding encoding step.
+///
+/// Create a circuit on two qubits and two classical bits, that encodes the two
+/// bit message `message` in Alice's half (qubit 0) of a shared Bell pair
+/// (|00⟩ + |11⟩)/√2. The first bit of the message is encoded by a `Z` gate,
+/// the second bit by an `X` gate. Bob's qubit (qubit 1) is not touched.
+pub fn superdense_coding_send(message: (bool, bool)) -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(2, 2);
+    // All qubit indices are valid, so this cannot fail
+    add_send(&mut circuit, message).unwrap();
+    circuit
+}
+
+/// Superdense coding decoding step.
+///
+/// Create a circuit on two qubits and two classical bits, that decodes the
+/// message encoded by `superdense_coding_send()`. The two qubits are
+/// disentangled with a `CX` and `H` gate, after which qubit 0 is measured into
+/// classical bit 0, holding the first bit of the message, and qubit 1 into
+/// classical bit 1, holding the second bit.
+pub fn superdense_coding_receive() -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(2, 2);
+    // All bit indices are valid, so this cannot fail
+    add_receive(&mut circuit).unwrap();
+    circuit
+}
+
+/// Superdense coding.
+///
+/// Create the full superdense coding protocol for message `message`: the
+/// preparation of a Bell pair on qubits 0 and 1, the encoding of the message
+/// on qubit 0 as in `superdense_coding_send()`, and the decoding as in
+/// `superdense_coding_receive()`. In the absence of noise, the classical
+/// register holds `message.0` in bit 0 and `message.1` in bit 1 after
+/// execution.
+pub fn superdense_coding_circuit(message: (bool, bool)) -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(2, 2);
+    // All bit indices are valid, so this cannot fail
+    add_bell_pair(&mut circuit).unwrap();
+    add_send(&mut circuit, message).unwrap();
+    add_receive(&mut circuit).unwrap();
+    circuit
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{add_bell_pair, add_send, superdense_coding_circuit,
+        superdense_coding_receive, superdense_coding_send};
+
+    const MESSAGES: [(bool, bool); 4] = [(false, false), (false, true), (true, false), (true, true)];
+
+    #[test]
+    fn test_send()
+    {
+        let circuit = superdense_coding_send((false, false));
+        assert_eq!(circuit.nr_qbits(), 2);
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+"#)));
+
+        let circuit = superdense_coding_send((true, true));
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+x q[0];
+z q[0];
+"#)));
+
+        // Each message maps the Bell pair to a different Bell state
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let h = 0.5 * crate::cmatrix::COMPLEX_ONE;
+        let expected = [
+            array![[h, z, z, h], [z, z, z, z], [z, z, z, z], [h, z, z, h]],
+            array![[z, z, z, z], [z, h, h, z], [z, h, h, z], [z, z, z, z]],
+            array![[h, z, z, -h], [z, z, z, z], [z, z, z, z], [-h, z, z, h]],
+            array![[z, z, z, z], [z, h, -h, z], [z, -h, h, z], [z, z, z, z]]
+        ];
+        for (&message, rho) in MESSAGES.iter().zip(expected.iter())
+        {
+            let mut circuit = crate::circuit::Circuit::new(2, 0);
+            assert_eq!(add_bell_pair(&mut circuit), Ok(()));
+            assert_eq!(add_send(&mut circuit, message), Ok(()));
+            assert_complex_matrix_eq!(circuit.density_matrix().unwrap().matrix(), rho);
+        }
+    }
+
+    #[test]
+    fn test_receive()
+    {
+        let circuit = superdense_coding_receive();
+        assert_eq!(circuit.open_qasm(), Ok(String::from(
+r#"OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg b[2];
+cx q[0], q[1];
+h q[0];
+measure q[0] -> b[0];
+measure q[1] -> b[1];
+"#)));
+    }
+
+    #[test]
+    fn test_superdense_coding()
+    {
+        let nr_shots = 1024;
+        for &message in MESSAGES.iter()
+        {
+            let mut circuit = superdense_coding_circuit(message);
+            assert_eq!(circuit.execute(nr_shots), Ok(()));
+            let hist = circuit.histogram_vec().unwrap();
+            let key = message.0 as usize | (message.1 as usize) << 1;
+            for (value, &count) in hist.iter().enumerate()
+            {
+                assert_eq!(count, if value == key { nr_shots } else { 0 });
+            }
+        }
+    }
+}
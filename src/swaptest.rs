@@ -0,0 +1,172 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The SWAP test
+//!
+//! The SWAP test estimates the overlap `|⟨φ|ψ⟩|²` between two states `|φ⟩`
+//! and `|ψ⟩` using a single ancilla qubit. After a Hadamard gate on the
+//! ancilla, the two states are swapped conditioned on the ancilla, and a second
+//! Hadamard gate is applied. The ancilla is then measured to be 0 with
+//! probability `(1 + |⟨φ|ψ⟩|²)/2`.
+
+/// Check that the qubits for a SWAP test are valid, i.e. that both states are
+/// held in the same number of qubits, and that no qubit is used twice.
+fn check_bits(state_a_bits: &[usize], state_b_bits: &[usize], ancilla: usize)
+    -> crate::error::Result<()>
+{
+    if state_a_bits.len() != state_b_bits.len()
+    {
+        return Err(crate::error::Error::InvalidNrBits(state_b_bits.len(),
+            state_a_bits.len(), String::from("SWAP test")));
+    }
+
+    let mut seen = vec![ancilla];
+    for &bit in state_a_bits.iter().chain(state_b_bits.iter())
+    {
+        if seen.contains(&bit)
+        {
+            return Err(crate::error::Error::InvalidQBit(bit));
+        }
+        seen.push(bit);
+    }
+
+    Ok(())
+}
+
+/// Add a SWAP test.
+///
+/// Add a SWAP test between the states in qubits `state_a_bits` and
+/// `state_b_bits` to `circuit`, using qubit `ancilla` as control, and
+/// measuring it into classical bit `cbit`. The ancilla should be in state
+/// |0⟩ before the test. An error is returned when the two states are held in
+/// a different number of qubits, when a qubit is used more than once, or when
+/// a bit index is not valid for `circuit`.
+pub fn add_swap_test(circuit: &mut crate::circuit::Circuit, state_a_bits: &[usize],
+    state_b_bits: &[usize], ancilla: usize, cbit: usize) -> crate::error::Result<()>
+{
+    check_bits(state_a_bits, state_b_bits, ancilla)?;
+
+    circuit.h(ancilla)?;
+    for (&a, &b) in state_a_bits.iter().zip(state_b_bits)
+    {
+        circuit.add_gate(crate::gates::CSwap::new(), &[ancilla, a, b])?;
+    }
+    circuit.h(ancilla)?;
+    circuit.measure(ancilla, cbit)
+}
+
+/// SWAP test circuit.
+///
+/// Create a circuit performing the SWAP test between the states in qubits
+/// `state_a_bits` and `state_b_bits`, using qubit `ancilla` as control. The
+/// circuit holds as many qubits as needed for the highest qubit index used,
+/// and a single classical bit, into which the ancilla is measured. See
+/// `add_swap_test()`.
+pub fn swap_test_circuit(state_a_bits: &[usize], state_b_bits: &[usize], ancilla: usize)
+    -> crate::error::Result<crate::circuit::Circuit>
+{
+    let nr_qbits = state_a_bits.iter().chain(state_b_bits.iter())
+        .fold(ancilla, |m, &b| m.max(b)) + 1;
+    let mut circuit = crate::circuit::Circuit::new(nr_qbits, 1);
+    add_swap_test(&mut circuit, state_a_bits, state_b_bits, ancilla, 0)?;
+    Ok(circuit)
+}
+
+/// Overlap from a SWAP test.
+///
+/// Estimate the overlap `|⟨φ|ψ⟩|² = 2P(0) - 1` from the measurement results
+/// `histogram` of `nr_shots` runs of a SWAP test, where the ancilla was
+/// measured into classical bit `ancilla_cbit`. Due to statistical noise, the
+/// estimate may fall slightly outside the range `[0, 1]`.
+pub fn overlap_from_swap_test(histogram: &crate::idhash::U64HashMap<usize>,
+    ancilla_cbit: usize, nr_shots: usize) -> f64
+{
+    let nr_zeros: usize = histogram.iter()
+        .filter(|&(&key, _)| (key >> ancilla_cbit) & 1 == 0)
+        .map(|(_, &count)| count)
+        .sum();
+    2.0 * nr_zeros as f64 / nr_shots as f64 - 1.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{add_swap_test, overlap_from_swap_test, swap_test_circuit};
+    use crate::circuit::Circuit;
+    use crate::instruction::Instruction;
+    use rand::SeedableRng;
+
+    fn estimate_overlap(circuit: &mut Circuit, seed: u64) -> f64
+    {
+        let nr_shots = 4096;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        assert_eq!(circuit.execute_with_rng(nr_shots, &mut rng), Ok(()));
+        overlap_from_swap_test(&circuit.histogram().unwrap(), 0, nr_shots)
+    }
+
+    #[test]
+    fn test_swap_test_circuit()
+    {
+        let circuit = swap_test_circuit(&[1, 2], &[3, 4], 0).unwrap();
+        assert_eq!(circuit.nr_qbits(), 5);
+        assert_eq!(circuit.nr_cbits(), 1);
+        assert_eq!(circuit.to_instructions(), vec![
+            Instruction::H(0),
+            Instruction::CSwap(0, 1, 3),
+            Instruction::CSwap(0, 2, 4),
+            Instruction::H(0),
+            Instruction::Measure(0, 0, crate::circuit::Basis::Z)
+        ]);
+
+        assert_eq!(swap_test_circuit(&[1, 2], &[3], 0).err(),
+            Some(crate::error::Error::InvalidNrBits(1, 2, String::from("SWAP test"))));
+        assert_eq!(swap_test_circuit(&[1], &[0], 0).err(),
+            Some(crate::error::Error::InvalidQBit(0)));
+    }
+
+    #[test]
+    fn test_overlap()
+    {
+        // Identical states
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        assert_eq!(add_swap_test(&mut circuit, &[1], &[2], 0, 0), Ok(()));
+        assert!((estimate_overlap(&mut circuit, 1234) - 1.0).abs() < 1.0e-12);
+
+        // Orthogonal states
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.x(2), Ok(()));
+        assert_eq!(add_swap_test(&mut circuit, &[1], &[2], 0, 0), Ok(()));
+        assert!(estimate_overlap(&mut circuit, 2345).abs() < 0.05);
+
+        // H|0⟩ and |0⟩
+        let mut circuit = Circuit::new(3, 1);
+        assert_eq!(circuit.h(1), Ok(()));
+        assert_eq!(add_swap_test(&mut circuit, &[1], &[2], 0, 0), Ok(()));
+        assert!((estimate_overlap(&mut circuit, 3456) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_overlap_from_swap_test()
+    {
+        let mut hist = crate::idhash::new_u64_hash_map();
+        hist.insert(0b00, 60);
+        hist.insert(0b01, 10);
+        hist.insert(0b10, 20);
+        hist.insert(0b11, 10);
+        assert!((overlap_from_swap_test(&hist, 1, 100) - 0.4).abs() < 1.0e-12);
+        assert!((overlap_from_swap_test(&hist, 0, 100) - 0.6).abs() < 1.0e-12);
+    }
+}
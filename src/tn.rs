@@ -0,0 +1,532 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tensor networks
+//!
+//! This module provides a tensor network representation of a quantum circuit,
+//! in which the initial state of every qubit and every gate is a tensor, and
+//! the qubit wires connecting them are the legs of the tensors. The final
+//! state of the circuit is obtained by contracting the network. The order in
+//! which tensors are contracted largely determines the cost of the
+//! contraction, so functions are provided to find a contraction path that
+//! keeps the intermediate tensors small.
+//!
+//! A contraction path is a list of pairs `(i, j)` of positions in the list of
+//! tensors. In each step, the two tensors at positions `i` and `j` are
+//! removed from the list, and the result of their contraction is appended to
+//! the end of the list.
+
+/// The maximum number of tensors in a network for which the optimal
+/// contraction path is computed exactly. For larger networks, a greedy
+/// algorithm is used.
+const MAX_EXACT_NR_TENSORS: usize = 12;
+
+/// A tensor in a tensor network
+///
+/// Struct `Tensor` holds the elements of a tensor, with each of its legs of
+/// dimension 2. The legs are identified by a number unique within the network,
+/// legs with the same number in two different tensors are connected. The
+/// elements are stored such that the first leg corresponds to the most
+/// significant bit of the element index.
+#[derive(Clone, Debug)]
+pub struct Tensor
+{
+    /// The identifiers of the legs of this tensor
+    legs: Vec<usize>,
+    /// The elements of the tensor
+    data: crate::cmatrix::CVector
+}
+
+impl Tensor
+{
+    /// Create a new tensor with legs `legs` and elements `data`. The number of
+    /// elements must be 2<sup>`n`</sup>, where `n` is the number of legs.
+    pub fn new(legs: Vec<usize>, data: crate::cmatrix::CVector) -> Self
+    {
+        assert!(data.len() == 1 << legs.len(),
+            "The number of elements does not match the number of legs of the tensor");
+        Tensor { legs: legs, data: data }
+    }
+
+    /// The identifiers of the legs of this tensor
+    pub fn legs(&self) -> &[usize]
+    {
+        &self.legs
+    }
+
+    /// Return the elements of this tensor, with its legs reordered to `legs`,
+    /// which should be a permutation of the legs of this tensor.
+    fn permuted_data(&self, legs: &[usize]) -> crate::cmatrix::CVector
+    {
+        let rank = legs.len();
+        let shifts: Vec<usize> = legs.iter().map(|l| {
+            let pos = self.legs.iter().position(|m| m == l).unwrap();
+            rank - 1 - pos
+        }).collect();
+
+        (0..self.data.len()).map(|idx| {
+            let src = shifts.iter().enumerate().fold(0, |src, (p, &shift)| {
+                src | (((idx >> (rank - 1 - p)) & 1) << shift)
+            });
+            self.data[src]
+        }).collect()
+    }
+
+    /// Contract this tensor with `other` over all legs they have in common.
+    /// The legs of the result are the remaining legs of this tensor, followed
+    /// by those of `other`.
+    fn contract(&self, other: &Tensor) -> Tensor
+    {
+        let shared: Vec<usize> = self.legs.iter().cloned()
+            .filter(|l| other.legs.contains(l))
+            .collect();
+        let free0: Vec<usize> = self.legs.iter().cloned()
+            .filter(|l| !shared.contains(l))
+            .collect();
+        let free1: Vec<usize> = other.legs.iter().cloned()
+            .filter(|l| !shared.contains(l))
+            .collect();
+
+        let legs0: Vec<usize> = free0.iter().chain(shared.iter()).cloned().collect();
+        let legs1: Vec<usize> = shared.iter().chain(free1.iter()).cloned().collect();
+        let (n0, ns, n1) = (1 << free0.len(), 1 << shared.len(), 1 << free1.len());
+        let m0 = self.permuted_data(&legs0).into_shape((n0, ns)).unwrap();
+        let m1 = other.permuted_data(&legs1).into_shape((ns, n1)).unwrap();
+        let data = m0.dot(&m1).into_shape(n0 * n1).unwrap();
+
+        Tensor::new(free0.into_iter().chain(free1).collect(), data)
+    }
+}
+
+/// A tensor network
+///
+/// Struct `TensorNetwork` describes a quantum circuit as a network of tensors,
+/// together with the open legs corresponding to the final state of each qubit.
+#[derive(Clone, Debug)]
+pub struct TensorNetwork
+{
+    /// The tensors in the network
+    tensors: Vec<Tensor>,
+    /// The open legs of the network, one for each qubit
+    output_legs: Vec<usize>
+}
+
+impl TensorNetwork
+{
+    /// Create a tensor network from a circuit.
+    ///
+    /// Create the tensor network for `circuit`. The first `n` tensors in the
+    /// network describe the initial |0⟩ state of the `n` qubits in the circuit,
+    /// the remaining tensors are the gates in the circuit, in order. The
+    /// circuit should be purely unitary, if it contains measurements, resets,
+    /// conditional gates or noise, `Error::NotAGate` is returned.
+    pub fn from_circuit(circuit: &crate::circuit::Circuit) -> crate::error::Result<Self>
+    {
+        let nr_qbits = circuit.nr_qbits();
+        let mut tensors: Vec<Tensor> = (0..nr_qbits).map(|bit| {
+            Tensor::new(vec![bit], array![crate::cmatrix::COMPLEX_ONE, crate::cmatrix::COMPLEX_ZERO])
+        }).collect();
+        let mut output_legs: Vec<usize> = (0..nr_qbits).collect();
+        let mut next_leg = nr_qbits;
+
+        for (gate, bits) in circuit.gate_ops()?
+        {
+            // The matrix row index corresponds to the outgoing legs, the
+            // column index to the incoming legs
+            let mut legs: Vec<usize> = (next_leg..next_leg+bits.len()).collect();
+            for (i, &bit) in bits.iter().enumerate()
+            {
+                legs.push(output_legs[bit]);
+                output_legs[bit] = next_leg + i;
+            }
+            next_leg += bits.len();
+            tensors.push(Tensor::new(legs, gate.matrix().into_shape(1 << (2 * bits.len())).unwrap()));
+        }
+
+        Ok(TensorNetwork { tensors: tensors, output_legs: output_legs })
+    }
+
+    /// The tensors in this network
+    pub fn tensors(&self) -> &[Tensor]
+    {
+        &self.tensors
+    }
+
+    /// The number of tensors in this network
+    pub fn nr_tensors(&self) -> usize
+    {
+        self.tensors.len()
+    }
+
+    /// Return the legs of the tensor resulting from contracting the tensors
+    /// with legs `legs0` and `legs1`
+    fn contracted_legs(legs0: &[usize], legs1: &[usize]) -> Vec<usize>
+    {
+        legs0.iter().filter(|l| !legs1.contains(l))
+            .chain(legs1.iter().filter(|l| !legs0.contains(l)))
+            .cloned()
+            .collect()
+    }
+
+    /// Find a contraction path with a greedy algorithm.
+    ///
+    /// Find a contraction path for this network by repeatedly contracting
+    /// the pair of tensors that results in the smallest tensor. Only pairs
+    /// of tensors that share a leg are considered, unless no such pair
+    /// exists. Ties are broken in favour of contractions that reduce the
+    /// total size of the network most.
+    pub fn greedy_path(&self) -> Vec<(usize, usize)>
+    {
+        let mut legs: Vec<Vec<usize>> = self.tensors.iter().map(|t| t.legs.clone()).collect();
+        let mut path = vec![];
+        while legs.len() > 1
+        {
+            let mut best: Option<((bool, usize, isize), usize, usize)> = None;
+            for i in 0..legs.len()
+            {
+                for j in i+1..legs.len()
+                {
+                    let connected = legs[i].iter().any(|l| legs[j].contains(l));
+                    let rank = Self::contracted_legs(&legs[i], &legs[j]).len();
+                    let gain = (1isize << rank) - (1isize << legs[i].len()) - (1isize << legs[j].len());
+                    let key = (!connected, rank, gain);
+                    if best.as_ref().map(|&(k, _, _)| key < k).unwrap_or(true)
+                    {
+                        best = Some((key, i, j));
+                    }
+                }
+            }
+
+            let (_, i, j) = best.unwrap();
+            let legs1 = legs.remove(j);
+            let legs0 = legs.remove(i);
+            legs.push(Self::contracted_legs(&legs0, &legs1));
+            path.push((i, j));
+        }
+
+        path
+    }
+
+    /// Find the optimal contraction path.
+    ///
+    /// Find the contraction path for this network that minimizes the size of
+    /// the largest intermediate tensor, and, among those, the total size of
+    /// all intermediate tensors, by dynamic programming over all subsets of
+    /// tensors. Since the cost of this search grows exponentially with the
+    /// number of tensors, it is only feasible for small networks.
+    pub fn optimal_path(&self) -> Vec<(usize, usize)>
+    {
+        let n = self.tensors.len();
+        if n < 2
+        {
+            return vec![];
+        }
+
+        // For every leg, the set of tensors it is attached to
+        let mut leg_tensors = ::std::collections::HashMap::new();
+        for (i, tensor) in self.tensors.iter().enumerate()
+        {
+            for &leg in tensor.legs.iter()
+            {
+                *leg_tensors.entry(leg).or_insert(0usize) |= 1 << i;
+            }
+        }
+        let leg_masks: Vec<usize> = leg_tensors.values().cloned().collect();
+
+        // The rank of the tensor resulting from contracting all tensors in a subset
+        let nr_subsets = 1usize << n;
+        let ranks: Vec<usize> = (0..nr_subsets).map(|s| {
+            leg_masks.iter()
+                .filter(|&&m| m & s != 0 && (m & !s != 0 || m.count_ones() == 1))
+                .count()
+        }).collect();
+
+        // Cost (largest tensor size, total size) and best split for every subset
+        let mut costs = vec![(0usize, 0usize); nr_subsets];
+        let mut splits = vec![0usize; nr_subsets];
+        for s in 1..nr_subsets
+        {
+            let size = 1usize << ranks[s];
+            if s.count_ones() == 1
+            {
+                costs[s] = (size, 0);
+                continue;
+            }
+
+            let low = s & s.wrapping_neg();
+            let mut best: Option<(usize, usize)> = None;
+            let mut a = (s - 1) & s;
+            while a > 0
+            {
+                if a & low != 0
+                {
+                    let b = s & !a;
+                    let (max_a, total_a) = costs[a];
+                    let (max_b, total_b) = costs[b];
+                    let cost = (max_a.max(max_b).max(size), total_a + total_b + size);
+                    if best.map(|c| cost < c).unwrap_or(true)
+                    {
+                        best = Some(cost);
+                        splits[s] = a;
+                    }
+                }
+                a = (a - 1) & s;
+            }
+            costs[s] = best.unwrap();
+        }
+
+        // Convert the contraction tree into a path
+        let mut merges = vec![];
+        let mut stack = vec![nr_subsets - 1];
+        while let Some(s) = stack.pop()
+        {
+            if s.count_ones() > 1
+            {
+                let a = splits[s];
+                merges.push((a, s & !a));
+                stack.push(a);
+                stack.push(s & !a);
+            }
+        }
+
+        let mut current: Vec<usize> = (0..n).map(|i| 1 << i).collect();
+        let mut path = vec![];
+        for &(a, b) in merges.iter().rev()
+        {
+            let ia = current.iter().position(|&s| s == a).unwrap();
+            let ib = current.iter().position(|&s| s == b).unwrap();
+            let (i, j) = if ia < ib { (ia, ib) } else { (ib, ia) };
+            current.remove(j);
+            current.remove(i);
+            current.push(a | b);
+            path.push((i, j));
+        }
+
+        path
+    }
+}
+
+/// Find a contraction path for a circuit.
+///
+/// Build the tensor network for `circuit`, and find a contraction path for it
+/// that keeps the intermediate tensors small. For small networks, the optimal
+/// path is computed exactly, for larger networks a greedy algorithm is used.
+/// The circuit should be purely unitary, if it contains measurements, resets,
+/// conditional gates or noise, `Error::NotAGate` is returned.
+pub fn optimal_contraction_path(circuit: &crate::circuit::Circuit)
+    -> crate::error::Result<Vec<(usize, usize)>>
+{
+    let network = TensorNetwork::from_circuit(circuit)?;
+    if network.nr_tensors() <= MAX_EXACT_NR_TENSORS
+    {
+        Ok(network.optimal_path())
+    }
+    else
+    {
+        Ok(network.greedy_path())
+    }
+}
+
+/// Contract a tensor network.
+///
+/// Contract the tensors in `network` in the order given by `path`, and return
+/// the coefficient vector of the resulting state, with the first qubit
+/// corresponding to the most significant bit of the index. Any tensors
+/// that remain after following the path are combined by outer products. This
+/// function panics if `path` refers to tensors that do not exist.
+pub fn contract_with_path(network: &TensorNetwork, path: &[(usize, usize)])
+    -> crate::cmatrix::CVector
+{
+    let mut tensors = network.tensors.clone();
+    for &(i, j) in path
+    {
+        assert!(i != j && i < tensors.len() && j < tensors.len(),
+            "Invalid contraction ({}, {}) for {} tensors", i, j, tensors.len());
+        let (t0, t1) = if i < j
+            {
+                let t1 = tensors.remove(j);
+                (tensors.remove(i), t1)
+            }
+            else
+            {
+                let t0 = tensors.remove(i);
+                (t0, tensors.remove(j))
+            };
+        tensors.push(t0.contract(&t1));
+    }
+
+    let mut result = Tensor::new(vec![], array![crate::cmatrix::COMPLEX_ONE]);
+    for tensor in tensors
+    {
+        result = result.contract(&tensor);
+    }
+
+    result.permuted_data(&network.output_legs)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{contract_with_path, optimal_contraction_path, Tensor, TensorNetwork};
+    use crate::circuit::Circuit;
+
+    fn circuit_state(circuit: &Circuit) -> crate::cmatrix::CVector
+    {
+        let mut state = crate::cmatrix::CVector::zeros(1 << circuit.nr_qbits());
+        state[0] = crate::cmatrix::COMPLEX_ONE;
+        for (gate, bits) in circuit.gate_ops().unwrap()
+        {
+            crate::gates::apply_gate_slice(state.view_mut(), gate, bits, circuit.nr_qbits());
+        }
+        state
+    }
+
+    fn test_circuit() -> Circuit
+    {
+        let mut circuit = Circuit::new(4, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.ry(0.7, 1), Ok(()));
+        assert_eq!(circuit.cx(0, 2), Ok(()));
+        assert_eq!(circuit.u3(0.4, -1.1, 0.2, 3), Ok(()));
+        assert_eq!(circuit.cx(1, 3), Ok(()));
+        assert_eq!(circuit.barrier(&[0, 1, 2, 3]), Ok(()));
+        assert_eq!(circuit.rz(1.3, 2), Ok(()));
+        assert_eq!(circuit.cx(3, 0), Ok(()));
+        assert_eq!(circuit.add_gate(crate::gates::Swap::new(), &[2, 1]), Ok(()));
+        circuit
+    }
+
+    #[test]
+    fn test_contract()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+
+        // Matrix-vector product
+        let m = Tensor::new(vec![1, 0], array![o, 2.0*o, i, z]);
+        let v = Tensor::new(vec![0], array![o, -o]);
+        let r = m.contract(&v);
+        assert_eq!(r.legs(), &[1]);
+        assert_complex_vector_eq!(&r.data, &array![-o, i]);
+
+        // Vector-matrix product
+        let r = v.contract(&m);
+        assert_eq!(r.legs(), &[1]);
+        assert_complex_vector_eq!(&r.data, &array![-o, i]);
+
+        // Outer product
+        let w = Tensor::new(vec![2], array![o, i]);
+        let r = v.contract(&w);
+        assert_eq!(r.legs(), &[0, 2]);
+        assert_complex_vector_eq!(&r.data, &array![o, i, -o, -i]);
+
+        // Permutation
+        assert_complex_vector_eq!(&r.permuted_data(&[2, 0]), &array![o, -o, i, -i]);
+    }
+
+    #[test]
+    fn test_from_circuit()
+    {
+        let mut circuit = Circuit::new(2, 0);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.cx(0, 1), Ok(()));
+        let network = TensorNetwork::from_circuit(&circuit).unwrap();
+        assert_eq!(network.nr_tensors(), 4);
+        assert_eq!(network.tensors()[2].legs(), &[2, 0]);
+        assert_eq!(network.tensors()[3].legs(), &[3, 4, 2, 1]);
+        assert_eq!(network.output_legs, vec![3, 4]);
+
+        let mut circuit = Circuit::new(2, 2);
+        assert_eq!(circuit.h(0), Ok(()));
+        assert_eq!(circuit.measure(0, 0), Ok(()));
+        assert!(matches!(TensorNetwork::from_circuit(&circuit),
+            Err(crate::error::Error::NotAGate(_))));
+        assert!(optimal_contraction_path(&circuit).is_err());
+    }
+
+    #[test]
+    fn test_paths()
+    {
+        let circuit = test_circuit();
+        let network = TensorNetwork::from_circuit(&circuit).unwrap();
+        let expected = circuit_state(&circuit);
+
+        let greedy = network.greedy_path();
+        assert_eq!(greedy.len(), network.nr_tensors() - 1);
+        assert_complex_vector_eq!(&contract_with_path(&network, &greedy), &expected);
+
+        let optimal = network.optimal_path();
+        assert_eq!(optimal.len(), network.nr_tensors() - 1);
+        assert_complex_vector_eq!(&contract_with_path(&network, &optimal), &expected);
+
+        let path = optimal_contraction_path(&circuit).unwrap();
+        assert_eq!(path, optimal);
+
+        // Contracting in the order of the circuit, and without a path
+        let sequential: Vec<(usize, usize)> = (0..network.nr_tensors()-1).map(|_| (0, 1)).collect();
+        assert_complex_vector_eq!(&contract_with_path(&network, &sequential), &expected);
+        assert_complex_vector_eq!(&contract_with_path(&network, &[]), &expected);
+    }
+
+    #[test]
+    fn test_optimal_path_size()
+    {
+        // Two independent chains of gates: the optimal path never needs a
+        // tensor with more than four legs.
+        let mut circuit = Circuit::new(4, 0);
+        for _ in 0..2
+        {
+            assert_eq!(circuit.h(0), Ok(()));
+            assert_eq!(circuit.cx(0, 1), Ok(()));
+            assert_eq!(circuit.h(2), Ok(()));
+            assert_eq!(circuit.cx(2, 3), Ok(()));
+        }
+        let network = TensorNetwork::from_circuit(&circuit).unwrap();
+        let path = network.optimal_path();
+        let mut legs: Vec<Vec<usize>> = network.tensors().iter().map(|t| t.legs().to_vec()).collect();
+        let mut max_rank = 0;
+        for &(i, j) in path.iter()
+        {
+            let l1 = legs.remove(j);
+            let l0 = legs.remove(i);
+            let l = TensorNetwork::contracted_legs(&l0, &l1);
+            max_rank = max_rank.max(l.len());
+            legs.push(l);
+        }
+        assert_eq!(max_rank, 4);
+        assert_complex_vector_eq!(&contract_with_path(&network, &path), &circuit_state(&circuit));
+    }
+
+    #[test]
+    fn test_large_circuit()
+    {
+        // Too large for the exact algorithm, falls back to the greedy path
+        let mut circuit = Circuit::new(6, 0);
+        for bit in 0..6
+        {
+            assert_eq!(circuit.h(bit), Ok(()));
+        }
+        for bit in 0..5
+        {
+            assert_eq!(circuit.cx(bit, bit+1), Ok(()));
+            assert_eq!(circuit.rz(0.3 * bit as f64, bit+1), Ok(()));
+        }
+        let network = TensorNetwork::from_circuit(&circuit).unwrap();
+        assert!(network.nr_tensors() > super::MAX_EXACT_NR_TENSORS);
+        let path = optimal_contraction_path(&circuit).unwrap();
+        assert_eq!(path, network.greedy_path());
+        assert_complex_vector_eq!(&contract_with_path(&network, &path), &circuit_state(&circuit));
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hardware topologies
+//!
+//! On most quantum devices, two-qubit gates can only be applied to certain
+//! pairs of qubits. This module describes these connectivity constraints.
+
+/// Qubit connectivity of a device
+///
+/// Struct `Topology` describes which pairs of qubits on a device can be
+/// operated on by a two-qubit gate. Connections are undirected, and are
+/// stored as pairs `(a, b)` with `a < b`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Topology
+{
+    /// The number of qubits on the device
+    nr_qbits: usize,
+    /// The pairs of connected qubits
+    edges: ::std::collections::HashSet<(usize, usize)>
+}
+
+impl Topology
+{
+    /// Create a new topology.
+    ///
+    /// Create a new topology for a device with `nr_qbits` qubits, where the
+    /// pairs of qubits in `edges` are connected. An `InvalidQBit` error is
+    /// returned when a qubit index in `edges` is not smaller than `nr_qbits`,
+    /// or when a qubit is connected to itself.
+    pub fn new(nr_qbits: usize, edges: &[(usize, usize)]) -> crate::error::Result<Self>
+    {
+        let mut res = Topology { nr_qbits: nr_qbits, edges: ::std::collections::HashSet::new() };
+        for &(a, b) in edges
+        {
+            if a >= nr_qbits || a == b
+            {
+                return Err(crate::error::Error::InvalidQBit(a));
+            }
+            if b >= nr_qbits
+            {
+                return Err(crate::error::Error::InvalidQBit(b));
+            }
+            res.edges.insert((a.min(b), a.max(b)));
+        }
+        Ok(res)
+    }
+
+    /// Linear topology.
+    ///
+    /// Create the topology of `n` qubits on a line, where qubit `i` is
+    /// connected to qubit `i+1`.
+    pub fn linear(n: usize) -> Self
+    {
+        let edges: Vec<(usize, usize)> = (1..n).map(|i| (i - 1, i)).collect();
+        // All qubit indices are valid
+        Topology::new(n, &edges).unwrap()
+    }
+
+    /// Grid topology.
+    ///
+    /// Create the topology of a rectangular grid of `rows×cols` qubits, where
+    /// each qubit is connected to its horizontal and vertical neighbours. The
+    /// qubit at row `r` and column `c` has index `r·cols + c`.
+    pub fn grid(rows: usize, cols: usize) -> Self
+    {
+        let mut edges = vec![];
+        for r in 0..rows
+        {
+            for c in 0..cols
+            {
+                let idx = r * cols + c;
+                if c + 1 < cols
+                {
+                    edges.push((idx, idx + 1));
+                }
+                if r + 1 < rows
+                {
+                    edges.push((idx, idx + cols));
+                }
+            }
+        }
+        // All qubit indices are valid
+        Topology::new(rows * cols, &edges).unwrap()
+    }
+
+    /// Heavy hexagon topology.
+    ///
+    /// Create a heavy hexagon lattice, as used on IBM devices, of `n` rows of
+    /// `n` hexagons. The lattice is a hexagonal lattice in brick wall layout,
+    /// with an extra qubit on every edge, so that no qubit has more than
+    /// three neighbours. It consists of `n+1` horizontal chains of `4n+3`
+    /// qubits, where subsequent chains are connected through `n+1` bridge
+    /// qubits, alternating between even and odd positions in the chains.
+    /// Qubits are numbered chain by chain, where the bridge qubits between
+    /// two chains are numbered after the first of these chains.
+    pub fn heavy_hex(n: usize) -> Self
+    {
+        if n == 0
+        {
+            return Topology::linear(0);
+        }
+
+        let chain_len = 4 * n + 3;
+        let mut edges = vec![];
+        let mut start = 0;
+        for r in 0..=n
+        {
+            edges.extend((1..chain_len).map(|i| (start + i - 1, start + i)));
+            if r == n
+            {
+                start += chain_len;
+                break;
+            }
+
+            // Hexagon corners of row r are at even columns of the brick wall,
+            // which become positions 4k (for even r) or 4k+2 (for odd r)
+            // after adding the qubits on the horizontal edges.
+            let bridge_start = start + chain_len;
+            let next_start = bridge_start + n + 1;
+            let offset = if r % 2 == 0 { 0 } else { 2 };
+            for k in 0..=n
+            {
+                let pos = 4 * k + offset;
+                edges.push((start + pos, bridge_start + k));
+                edges.push((bridge_start + k, next_start + pos));
+            }
+            start = next_start;
+        }
+
+        // All qubit indices are valid
+        Topology::new(start, &edges).unwrap()
+    }
+
+    /// Fully connected topology.
+    ///
+    /// Create the topology of `n` qubits, where every pair of qubits is
+    /// connected.
+    pub fn complete(n: usize) -> Self
+    {
+        let edges: Vec<(usize, usize)> = (0..n)
+            .flat_map(|a| (a+1..n).map(move |b| (a, b)))
+            .collect();
+        // All qubit indices are valid
+        Topology::new(n, &edges).unwrap()
+    }
+
+    /// The number of qubits on the device
+    pub fn nr_qbits(&self) -> usize
+    {
+        self.nr_qbits
+    }
+
+    /// The pairs `(a, b)` of connected qubits, with `a < b`
+    pub fn edges(&self) -> &::std::collections::HashSet<(usize, usize)>
+    {
+        &self.edges
+    }
+
+    /// Return whether qubits `a` and `b` are connected
+    pub fn is_connected(&self, a: usize, b: usize) -> bool
+    {
+        self.edges.contains(&(a.min(b), a.max(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Topology;
+
+    /// The number of neighbours of every qubit in `topology`
+    fn degrees(topology: &Topology) -> Vec<usize>
+    {
+        let mut res = vec![0; topology.nr_qbits()];
+        for &(a, b) in topology.edges()
+        {
+            res[a] += 1;
+            res[b] += 1;
+        }
+        res
+    }
+
+    #[test]
+    fn test_new()
+    {
+        let topology = Topology::new(3, &[(1, 0), (1, 2), (0, 1)]).unwrap();
+        assert_eq!(topology.nr_qbits(), 3);
+        assert_eq!(topology.edges().len(), 2);
+        assert!(topology.is_connected(0, 1));
+        assert!(topology.is_connected(2, 1));
+        assert!(!topology.is_connected(0, 2));
+
+        assert_eq!(Topology::new(3, &[(0, 3)]), Err(crate::error::Error::InvalidQBit(3)));
+        assert_eq!(Topology::new(3, &[(1, 1)]), Err(crate::error::Error::InvalidQBit(1)));
+    }
+
+    #[test]
+    fn test_predefined()
+    {
+        let topology = Topology::linear(4);
+        assert_eq!(topology.edges().len(), 3);
+        assert!(topology.is_connected(2, 3) && !topology.is_connected(1, 3));
+
+        let topology = Topology::grid(2, 3);
+        assert_eq!(topology.nr_qbits(), 6);
+        assert_eq!(topology.edges().len(), 7);
+        assert!(topology.is_connected(1, 4) && topology.is_connected(4, 5));
+        assert!(!topology.is_connected(2, 3));
+
+        let topology = Topology::complete(4);
+        assert_eq!(topology.edges().len(), 6);
+        assert_eq!(degrees(&topology), vec![3; 4]);
+
+        let topology = Topology::heavy_hex(2);
+        assert_eq!(topology.nr_qbits(), 3 * 11 + 2 * 3);
+        assert!(degrees(&topology).iter().all(|d| (1..=3).contains(d)));
+        // Three chains of 11 qubits, and two rows of three bridge qubits
+        // with two connections each
+        assert_eq!(topology.edges().len(), 3 * 10 + 2 * 3 * 2);
+        assert!(topology.is_connected(0, 11) && topology.is_connected(11, 14));
+        assert!(topology.is_connected(16, 25) && topology.is_connected(25, 30));
+
+        assert_eq!(Topology::heavy_hex(0).nr_qbits(), 0);
+    }
+}
@@ -32,6 +32,8 @@ pub struct VectorState
     counts: Vec<usize>,
     /// The quantum states themselves
     states: crate::cmatrix::CMatrix,
+    /// Whether the states have been collapsed by a measurement
+    measured: bool
 }
 
 impl VectorState
@@ -48,7 +50,8 @@ impl VectorState
             nr_bits: nr_bits,
             nr_shots: nr_shots,
             counts: vec![nr_shots],
-            states: states
+            states: states,
+            measured: false
         }
     }
 
@@ -78,7 +81,31 @@ impl VectorState
             nr_bits: nr_bits,
             nr_shots: nr_shots,
             counts: vec![nr_shots],
-            states: states
+            states: states,
+            measured: false
+        }
+    }
+
+    /// Create a new quantum state from its coefficients.
+    ///
+    /// Create a new quantum state with coefficient vector `coefs`, which will
+    /// be evaluated in `nr_shots` separate runs. The length of `coefs` must be
+    /// a power of two, and the vector should be normalized.
+    pub fn from_coefficients(coefs: crate::cmatrix::CVector, nr_shots: usize) -> Self
+    {
+        assert!(coefs.len().is_power_of_two(), "Length of coefficient vector is not a power of two");
+
+        let nr_bits = coefs.len().trailing_zeros() as usize;
+        let len = coefs.len();
+        let states = coefs.into_shape((len, 1)).unwrap();
+
+        VectorState
+        {
+            nr_bits: nr_bits,
+            nr_shots: nr_shots,
+            counts: vec![nr_shots],
+            states: states,
+            measured: false
         }
     }
 
@@ -155,6 +182,7 @@ impl VectorState
                 self.states[(idx, col_idx)] = crate::cmatrix::COMPLEX_ONE;
             }
             self.counts = state_counts.iter().map(|t| t.1).collect();
+            self.measured = true;
         }
 
         Ok(())
@@ -173,7 +201,7 @@ impl crate::qustate::QuState for VectorState
                 String::from(gate.description())));
         }
 
-        crate::gates::apply_gate_mat_slice(self.states.view_mut(), gate, bits, self.nr_bits);
+        gate.apply_columns(&mut self.states, bits, self.nr_bits);
         Ok(())
     }
 
@@ -324,6 +352,7 @@ impl crate::qustate::QuState for VectorState
 
         self.counts = new_counts;
         self.states = new_states;
+        self.measured = true;
 
         Ok(())
     }
@@ -399,6 +428,107 @@ impl crate::qustate::QuState for VectorState
         self.measure_all_into_helper(cbits, res, false, rng)
     }
 
+    fn partial_measure_peek<R: rand::Rng>(&self, qbits: &[usize], cbits: &[usize],
+        res: &mut ndarray::Array1<u64>, rng: &mut R) -> crate::error::Result<()>
+    {
+        if cbits.len() != qbits.len()
+        {
+            return Err(crate::error::Error::InvalidNrMeasurementBits(cbits.len(), qbits.len()));
+        }
+        if let Some(&qbit) = qbits.iter().find(|&&b| b >= self.nr_bits)
+        {
+            return Err(crate::error::Error::InvalidQBit(qbit));
+        }
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let mask = !cbits.iter().fold(0u64, |m, b| m | (1u64 << b));
+        let mut res_start = 0;
+        for (state, &count) in self.states.gencolumns().into_iter().zip(self.counts.iter())
+        {
+            // Compute the marginal distribution over the measured qubits, by
+            // summing over the other qubits
+            let mut probs = vec![0.0; 1 << qbits.len()];
+            for (idx, c) in state.iter().enumerate()
+            {
+                let outcome = qbits.iter().enumerate()
+                    .fold(0, |o, (j, &q)| o | (((idx >> (self.nr_bits - 1 - q)) & 1) << j));
+                probs[outcome] += c.norm_sqr();
+            }
+
+            let distr = rand::distributions::WeightedIndex::new(&probs).unwrap();
+            let mut outcome_counts = vec![0; probs.len()];
+            for outcome in distr.sample_iter(&mut *rng).take(count)
+            {
+                outcome_counts[outcome] += 1;
+            }
+
+            for (outcome, &n) in outcome_counts.iter().enumerate().filter(|&(_, &n)| n > 0)
+            {
+                let value = cbits.iter().enumerate()
+                    .fold(0u64, |v, (j, &cbit)| v | ((((outcome >> j) & 1) as u64) << cbit));
+                res.slice_mut(s![res_start..res_start+n]).map_inplace(
+                    |b| *b = (*b & mask) | value
+                );
+                res_start += n;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expectation_into(&self, obs: &crate::stabilizer::PauliString, cbit: usize,
+        res: &mut ndarray::Array1<u64>) -> crate::error::Result<()>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+        if res.len() < self.nr_shots
+        {
+            return Err(crate::error::Error::NotEnoughSpace(res.len(), self.nr_shots));
+        }
+
+        let one_mask = 1 << cbit;
+        let zero_mask = !one_mask;
+
+        let mut res_start = 0;
+        for (state, &count) in self.states.gencolumns().into_iter().zip(self.counts.iter())
+        {
+            let value = obs.expectation_value(state);
+            let mut slice = res.slice_mut(s![res_start..res_start+count]);
+            if value > 0.0
+            {
+                slice.map_inplace(|b| *b |= one_mask);
+            }
+            else
+            {
+                slice.map_inplace(|b| *b &= zero_mask);
+            }
+            res_start += count;
+        }
+
+        Ok(())
+    }
+
+    fn expectation_value(&self, obs: &crate::stabilizer::PauliString)
+        -> crate::error::Result<f64>
+    {
+        if obs.nr_bits() != self.nr_bits
+        {
+            return Err(crate::error::Error::InvalidNrBits(obs.nr_bits(),
+                self.nr_bits, format!("{}", obs)));
+        }
+
+        let sum: f64 = self.states.gencolumns().into_iter().zip(self.counts.iter())
+            .map(|(state, &count)| count as f64 * obs.expectation_value(state))
+            .sum();
+        Ok(sum / self.nr_shots as f64)
+    }
+
     fn reset<R: rand::Rng>(&mut self, bit: usize, rng: &mut R)
         -> crate::error::Result<()>
     {
@@ -412,6 +542,102 @@ impl crate::qustate::QuState for VectorState
         self.states = crate::cmatrix::CMatrix::zeros((1 << self.nr_bits, 1));
         self.states[[0, 0]] = crate::cmatrix::COMPLEX_ONE;
         self.counts = vec![self.nr_shots];
+        self.measured = false;
+    }
+
+    fn are_shots_entangled(&self) -> bool
+    {
+        self.counts.len() > 1
+    }
+
+    fn shot_statevector(&self, shot: usize)
+        -> Option<ndarray::ArrayView1<'_, crate::cmatrix::CNumber>>
+    {
+        crate::qustate::shot_state_index(&self.counts, shot).map(|idx| self.states.column(idx))
+    }
+
+    fn statevector(&self) -> Option<crate::cmatrix::CVector>
+    {
+        if self.nr_shots == 1
+        {
+            Some(self.states.column(0).to_owned())
+        }
+        else
+        {
+            None
+        }
+    }
+
+    fn fidelity_with_pure_state(&self, target: &crate::cmatrix::CVector)
+        -> crate::error::Result<f64>
+    {
+        if self.measured
+        {
+            return Err(crate::error::Error::NoPureState);
+        }
+
+        let psi = self.statevector().ok_or(crate::error::Error::NoPureState)?;
+        crate::measures::statevector_fidelity(&psi, target)
+    }
+}
+
+impl ::std::fmt::Display for VectorState
+{
+    /// Print the state in Dirac notation, e.g. `|ψ⟩ = 0.707|00⟩ + 0.707|11⟩`
+    /// for a Bell state. Amplitudes are rounded to the precision of the
+    /// formatter, or to three decimals by default, and basis states whose
+    /// amplitude would be rounded to zero are left out. When the runs in this
+    /// state have collapsed to different states, each state is printed on a
+    /// separate line, followed by the number of runs in that state.
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result
+    {
+        let precision = f.precision().unwrap_or(3);
+        let threshold = 0.5 * 10.0f64.powi(-(precision as i32));
+        for (col_idx, col) in self.states.gencolumns().into_iter().enumerate()
+        {
+            if col_idx > 0
+            {
+                writeln!(f)?;
+            }
+
+            write!(f, "|ψ⟩ =")?;
+            let mut first = true;
+            for (idx, &amp) in col.iter().enumerate()
+            {
+                if amp.re.abs() < threshold && amp.im.abs() < threshold
+                {
+                    continue;
+                }
+
+                let mut coef = crate::cmatrix::format_amplitude(amp, precision);
+                if first
+                {
+                    write!(f, " ")?;
+                }
+                else if coef.starts_with('-')
+                {
+                    coef.remove(0);
+                    write!(f, " - ")?;
+                }
+                else
+                {
+                    write!(f, " + ")?;
+                }
+                write!(f, "{}|{:0width$b}⟩", coef, idx, width=self.nr_bits)?;
+                first = false;
+            }
+            if first
+            {
+                write!(f, " 0")?;
+            }
+
+            if self.states.cols() > 1
+            {
+                write!(f, " ({} runs)", self.counts[col_idx])?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -421,6 +647,7 @@ mod tests
     use super::VectorState;
     use crate::gates::{CCX, CX, H, Kron, X, Y};
     use crate::qustate::QuState;
+    use crate::stabilizer::{PauliOp, PauliString};
 
     #[test]
     fn test_new()
@@ -469,6 +696,24 @@ mod tests
         assert_complex_matrix_eq!(&s.states, &array![[-x], [z], [-x], [z]]);
     }
 
+    #[test]
+    fn test_from_coefficients()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let s = VectorState::from_coefficients(array![x, z, z, -x], 7);
+        assert_eq!(s.nr_bits, 2);
+        assert_eq!(s.nr_shots, 7);
+        assert_eq!(s.counts, vec![7]);
+        assert_complex_matrix_eq!(&s.states, &array![[x], [z], [z], [-x]]);
+
+        let s = VectorState::from_coefficients(array![o], 3);
+        assert_eq!(s.nr_bits, 0);
+        assert_complex_matrix_eq!(&s.states, &array![[o]]);
+    }
+
     #[test]
     fn test_apply_conditional_gate()
     {
@@ -589,6 +834,42 @@ mod tests
         }
     }
 
+    #[test]
+    fn test_partial_measure_peek()
+    {
+        let nr_shots = 1024;
+        let mut measurements = ndarray::Array1::zeros(nr_shots);
+
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut rng = rand::thread_rng();
+
+        // (|010⟩ + |111⟩)/√2
+        let mut s = VectorState::new(3, nr_shots);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 2]), Ok(()));
+        assert_eq!(s.apply_gate(&X::new(), &[1]), Ok(()));
+        assert_eq!(s.partial_measure_peek(&[2, 0], &[0, 2], &mut measurements, &mut rng), Ok(()));
+        assert!(measurements.iter().all(|&bits| bits == 0 || bits == 5));
+        let n5 = measurements.iter().filter(|&&bits| bits == 5).count();
+        assert!(crate::stats::measurement_ok(n5, nr_shots, 0.5, 1.0e-5));
+        assert_complex_matrix_eq!(&s.states, &array![[z], [z], [x], [z], [z], [z], [z], [x]]);
+
+        // Other bits in the result are not touched
+        measurements.fill(2);
+        assert_eq!(s.partial_measure_peek(&[1], &[3], &mut measurements, &mut rng), Ok(()));
+        assert!(measurements.iter().all(|&bits| bits == 10));
+
+        assert_eq!(s.partial_measure_peek(&[0, 1], &[0], &mut measurements, &mut rng),
+            Err(crate::error::Error::InvalidNrMeasurementBits(1, 2)));
+        assert_eq!(s.partial_measure_peek(&[3], &[0], &mut measurements, &mut rng),
+            Err(crate::error::Error::InvalidQBit(3)));
+        let mut measurements = ndarray::Array1::zeros(nr_shots - 1);
+        assert_eq!(s.partial_measure_peek(&[0], &[0], &mut measurements, &mut rng),
+            Err(crate::error::Error::NotEnoughSpace(nr_shots - 1, nr_shots)));
+    }
+
     #[test]
     fn test_peek_into()
     {
@@ -706,6 +987,31 @@ mod tests
             &array![[hx], [-hx], [-hx], [hx], [-hx], [-hx], [hx], [hx]]);
     }
 
+    #[test]
+    fn test_expectation_into()
+    {
+        let nr_shots = 16;
+        let mut res = ndarray::Array1::zeros(nr_shots);
+
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let i = crate::cmatrix::COMPLEX_I;
+        let x = PauliString::new(&[PauliOp::X]);
+        let y = PauliString::new(&[PauliOp::Y]);
+
+        let s = VectorState::from_qubit_coefs(&[o, i], nr_shots);
+        assert_eq!(s.expectation_into(&y, 1, &mut res), Ok(()));
+        assert!(res.iter().all(|&bits| bits == 2));
+        assert_eq!(s.expectation_into(&x, 1, &mut res), Ok(()));
+        assert!(res.iter().all(|&bits| bits == 0));
+
+        let xx = PauliString::new(&[PauliOp::X, PauliOp::X]);
+        assert!(matches!(s.expectation_into(&xx, 0, &mut res),
+            Err(crate::error::Error::InvalidNrBits(2, 1, _))));
+        let mut res = ndarray::Array1::zeros(nr_shots - 1);
+        assert_eq!(s.expectation_into(&x, 0, &mut res),
+            Err(crate::error::Error::NotEnoughSpace(nr_shots - 1, nr_shots)));
+    }
+
     #[test]
     fn test_measure_all()
     {
@@ -827,4 +1133,107 @@ mod tests
         coefs[[0, 0]] = crate::cmatrix::COMPLEX_ONE;
         assert_complex_matrix_eq!(&s.states, &coefs);
     }
+
+    #[test]
+    fn test_shot_statevector()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0x3af1);
+        let mut s = VectorState::new(2, 10);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert!(!s.are_shots_entangled());
+        for shot in 0..10
+        {
+            assert_complex_vector_eq!(&s.shot_statevector(shot).unwrap(), &array![x, z, z, x]);
+        }
+        assert!(s.shot_statevector(10).is_none());
+
+        let res = s.measure(0, &mut rng).unwrap();
+        assert!(s.are_shots_entangled());
+        for shot in 0..10
+        {
+            let expected = if res[shot] == 0 { array![o, z, z, z] } else { array![z, z, z, o] };
+            assert_complex_vector_eq!(&s.shot_statevector(shot).unwrap(), &expected);
+        }
+
+        s.reset_all();
+        assert!(!s.are_shots_entangled());
+    }
+
+    #[test]
+    fn test_statevector()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut s = VectorState::new(2, 1);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_complex_vector_eq!(&s.statevector().unwrap(), &array![x, z, z, x]);
+
+        let s = VectorState::new(2, 2);
+        assert!(s.statevector().is_none());
+    }
+
+    #[test]
+    fn test_fidelity_with_pure_state()
+    {
+        let z = crate::cmatrix::COMPLEX_ZERO;
+        let o = crate::cmatrix::COMPLEX_ONE;
+        let x = crate::cmatrix::COMPLEX_HSQRT2;
+
+        let mut bell = VectorState::new(2, 1);
+        assert_eq!(bell.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(bell.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        let fidelity = bell.fidelity_with_pure_state(&array![x, z, z, x]).unwrap();
+        assert!((fidelity - 1.0).abs() < 1.0e-12);
+        let fidelity = bell.fidelity_with_pure_state(&array![o, z, z, z]).unwrap();
+        assert!((fidelity - 0.5).abs() < 1.0e-12);
+
+        let mut plus = VectorState::new(2, 1);
+        assert_eq!(plus.apply_gate(&H::new(), &[1]), Ok(()));
+        let f0 = bell.fidelity_with_pure_state(&plus.statevector().unwrap()).unwrap();
+        let f1 = plus.fidelity_with_pure_state(&bell.statevector().unwrap()).unwrap();
+        assert!((f0 - 0.25).abs() < 1.0e-12);
+        assert!((f0 - f1).abs() < 1.0e-12);
+
+        assert_eq!(bell.fidelity_with_pure_state(&array![x, x]),
+            Err(crate::error::Error::DimensionMismatch(4, 2)));
+        let s = VectorState::new(2, 2);
+        assert_eq!(s.fidelity_with_pure_state(&array![x, z, z, x]),
+            Err(crate::error::Error::NoPureState));
+
+        let mut rng = rand::thread_rng();
+        assert!(bell.measure(0, &mut rng).is_ok());
+        assert_eq!(bell.fidelity_with_pure_state(&array![o, z, z, z]),
+            Err(crate::error::Error::NoPureState));
+        bell.reset_all();
+        let fidelity = bell.fidelity_with_pure_state(&array![o, z, z, z]).unwrap();
+        assert!((fidelity - 1.0).abs() < 1.0e-12);
+        assert!(bell.measure_all(&mut rng).is_ok());
+        assert_eq!(bell.fidelity_with_pure_state(&array![o, z, z, z]),
+            Err(crate::error::Error::NoPureState));
+    }
+
+    #[test]
+    fn test_display()
+    {
+        let mut s = VectorState::new(2, 5);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&CX::new(), &[0, 1]), Ok(()));
+        assert_eq!(format!("{}", s), "|ψ⟩ = 0.707|00⟩ + 0.707|11⟩");
+        assert_eq!(format!("{:.1}", s), "|ψ⟩ = 0.7|00⟩ + 0.7|11⟩");
+
+        let mut s = VectorState::new(1, 5);
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&Y::new(), &[0]), Ok(()));
+        assert_eq!(format!("{}", s), "|ψ⟩ = -0.707i|0⟩ + 0.707i|1⟩");
+        assert_eq!(s.apply_gate(&Y::new(), &[0]), Ok(()));
+        assert_eq!(s.apply_gate(&H::new(), &[0]), Ok(()));
+        assert_eq!(format!("{}", s), "|ψ⟩ = 1|0⟩");
+    }
 }
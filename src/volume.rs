@@ -0,0 +1,211 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use rand::seq::SliceRandom;
+
+/// Heavy output probability.
+///
+/// Compute the fraction of the shots in histogram `measured_hist`, obtained by
+/// executing circuit `circuit`, that fall in the set of heavy outputs. The
+/// heavy outputs are the measurement results whose ideal probability is
+/// larger than the median of the ideal output distribution `ideal_probs`.
+/// The ideal distribution is indexed by the measurement result, using the
+/// same key convention as `Circuit::histogram()`; its length should be
+/// `2`<sup>`n`</sup>, where `n` is the number of classical bits in the
+/// circuit, otherwise this function panics. If the histogram is empty, the
+/// result is 0.
+pub fn heavy_output_probability<S>(circuit: &crate::circuit::Circuit, ideal_probs: &[f64],
+    measured_hist: &::std::collections::HashMap<u64, usize, S>) -> f64
+where S: ::std::hash::BuildHasher
+{
+    assert!(ideal_probs.len() == 1 << circuit.nr_cbits(),
+        "The number of ideal probabilities does not match the number of classical bits");
+
+    let mut sorted = ideal_probs.to_vec();
+    sorted.sort_by(|p0, p1| p0.partial_cmp(p1).unwrap());
+    let n = sorted.len();
+    let median = if n % 2 == 0 { 0.5 * (sorted[n/2 - 1] + sorted[n/2]) } else { sorted[n/2] };
+
+    let mut nr_heavy = 0;
+    let mut nr_shots = 0;
+    for (&key, &count) in measured_hist.iter()
+    {
+        if ideal_probs.get(key as usize).map_or(false, |&p| p > median)
+        {
+            nr_heavy += count;
+        }
+        nr_shots += count;
+    }
+
+    if nr_shots == 0
+    {
+        0.0
+    }
+    else
+    {
+        nr_heavy as f64 / nr_shots as f64
+    }
+}
+
+/// Create a quantum volume model circuit.
+///
+/// Create a model circuit on `width` qubits, consisting of `width` layers.
+/// Each layer applies Haar random two-qubit unitaries to `⌊width/2⌋` random,
+/// disjoint pairs of qubits. No measurements are added.
+fn model_circuit<R: rand::Rng>(width: usize, rng: &mut R) -> crate::circuit::Circuit
+{
+    let mut circuit = crate::circuit::Circuit::new(width, width);
+    let mut bits: Vec<usize> = (0..width).collect();
+    // All qubit indices are valid, so adding gates cannot fail
+    for _ in 0..width
+    {
+        bits.shuffle(rng);
+        for pair in bits.chunks_exact(2)
+        {
+            circuit.add_gate(crate::random::haar_random_unitary(2, rng), pair).unwrap();
+        }
+    }
+
+    circuit
+}
+
+/// Ideal output distribution of a circuit.
+///
+/// Compute the probabilities of the outcomes of measuring every qubit `i` of
+/// the state produced by circuit `circuit` into classical bit `i`, indexed
+/// by the measurement result as in `Circuit::histogram()`.
+fn ideal_probabilities(circuit: &crate::circuit::Circuit) -> crate::error::Result<Vec<f64>>
+{
+    let nr_bits = circuit.nr_qbits();
+    let rho = circuit.density_matrix()?;
+    let diag = rho.matrix().diag();
+    let mut probs = vec![0.0; diag.len()];
+    for (idx, p) in diag.iter().enumerate()
+    {
+        // Qubit 0 is the most significant bit in the state, but the least
+        // significant bit in the measurement result
+        let key = (0..nr_bits).fold(0, |key, i| key | (((idx >> (nr_bits - 1 - i)) & 1) << i));
+        probs[key] = p.re;
+    }
+
+    Ok(probs)
+}
+
+/// Quantum volume certification.
+///
+/// Run the quantum volume protocol for width and depth `depth`: generate
+/// `nr_trials` random model circuits, execute each of them `nr_shots` times,
+/// and compute the heavy output probability of each run. Quantum volume
+/// 2<sup>`depth`</sup> is certified when the mean heavy output probability
+/// exceeds 2/3 by at least two standard deviations, where the standard
+/// deviation is estimated as `√(h(1-h)/nr_trials)` for mean heavy output
+/// probability `h`. The circuits are executed on the noiseless simulator, so
+/// that this mainly serves as a reference for the protocol.
+pub fn certify_quantum_volume(depth: usize, nr_trials: usize, nr_shots: usize,
+    rng: &mut impl rand::Rng) -> bool
+{
+    if nr_trials == 0
+    {
+        return false;
+    }
+
+    let mut total = 0.0;
+    for _ in 0..nr_trials
+    {
+        let mut circuit = model_circuit(depth, rng);
+        // The model circuit contains only gates, so computing its density
+        // matrix, measuring, and executing cannot fail.
+        let ideal_probs = ideal_probabilities(&circuit).unwrap();
+        let cbits: Vec<usize> = (0..depth).collect();
+        circuit.measure_all(&cbits).unwrap();
+        circuit.execute_with_rng(nr_shots, rng).unwrap();
+        let hist = circuit.histogram().unwrap();
+        total += heavy_output_probability(&circuit, &ideal_probs, &hist);
+    }
+
+    let mean = total / nr_trials as f64;
+    let sigma = (mean * (1.0 - mean) / nr_trials as f64).sqrt();
+    mean - 2.0 * sigma > 2.0 / 3.0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{certify_quantum_volume, heavy_output_probability, ideal_probabilities,
+        model_circuit};
+
+    #[test]
+    fn test_heavy_output_probability()
+    {
+        let circuit = crate::circuit::Circuit::new(2, 2);
+        let ideal_probs = [0.1, 0.4, 0.3, 0.2];
+        let mut hist = ::std::collections::HashMap::new();
+        assert_eq!(heavy_output_probability(&circuit, &ideal_probs, &hist), 0.0);
+
+        hist.insert(0, 10);
+        hist.insert(1, 50);
+        hist.insert(2, 30);
+        hist.insert(3, 10);
+        assert!((heavy_output_probability(&circuit, &ideal_probs, &hist) - 0.8).abs() < 1.0e-12);
+
+        let mut hist = crate::idhash::new_u64_hash_map();
+        hist.insert(0, 3);
+        hist.insert(3, 1);
+        assert_eq!(heavy_output_probability(&circuit, &ideal_probs, &hist), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_heavy_output_probability_invalid()
+    {
+        let circuit = crate::circuit::Circuit::new(2, 2);
+        let hist = crate::idhash::new_u64_hash_map();
+        heavy_output_probability(&circuit, &[0.5, 0.5], &hist);
+    }
+
+    #[test]
+    fn test_ideal_probabilities()
+    {
+        let mut circuit = crate::circuit::Circuit::new(3, 3);
+        assert_eq!(circuit.x(0), Ok(()));
+        assert_eq!(circuit.h(2), Ok(()));
+        let probs = ideal_probabilities(&circuit).unwrap();
+        let expected = [0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0];
+        for (&p, &e) in probs.iter().zip(expected.iter())
+        {
+            assert!((p - e).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_model_circuit()
+    {
+        let mut rng = rand::thread_rng();
+        let circuit = model_circuit(4, &mut rng);
+        assert_eq!(circuit.nr_qbits(), 4);
+        assert!(circuit.cost_breakdown().contains_key("Haar"));
+        let probs = ideal_probabilities(&circuit).unwrap();
+        assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_certify_quantum_volume()
+    {
+        let mut rng = rand::thread_rng();
+        // The ideal heavy output probability of random circuits is well above
+        // 2/3, so the noiseless simulator should always pass
+        assert!(certify_quantum_volume(3, 100, 100, &mut rng));
+        assert!(!certify_quantum_volume(3, 0, 100, &mut rng));
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2019 Q1t BV
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::stabilizer::{PauliOp, PauliString};
+
+/// Compute the number of qubits needed to encode `n` values
+fn nr_bits_for(n: usize) -> usize
+{
+    n.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Decompose a real symmetric matrix into Pauli strings.
+///
+/// Write the `2`<sup>`k`</sup>`×2`<sup>`k`</sup> matrix `a`, padded with zeros when
+/// necessary, as a linear combination `Σ c`<sub>`P`</sub>` P` of Pauli strings
+/// `P` on `k` qubits, and return the terms with a non-zero coefficient. The
+/// identity term is not included.
+fn pauli_decomposition(a: &ndarray::Array2<f64>, nr_bits: usize) -> Vec<(f64, PauliString)>
+{
+    const OPS: [PauliOp; 4] = [PauliOp::I, PauliOp::X, PauliOp::Y, PauliOp::Z];
+
+    let size = 1 << nr_bits;
+    let mut terms = vec![];
+    for idx in 1..1 << (2 * nr_bits)
+    {
+        let ops: Vec<PauliOp> = (0..nr_bits)
+            .map(|i| OPS[(idx >> (2 * (nr_bits - i - 1))) & 3])
+            .collect();
+        let ps = PauliString::new(&ops);
+
+        // c_P = Tr(PA) / 2^k
+        let mut trace = crate::cmatrix::COMPLEX_ZERO;
+        for col in 0..size
+        {
+            let (row, phase) = ps.apply_to_basis_state(col);
+            if col < a.rows() && row < a.cols()
+            {
+                trace += phase * a[[col, row]];
+            }
+        }
+        let coef = trace.re / size as f64;
+        if coef.abs() > 1.0e-12
+        {
+            terms.push((coef, ps));
+        }
+    }
+
+    terms
+}
+
+/// Add the rotation `exp(-iθP)` for Pauli string `ps` on the first qubits
+/// of `circuit`.
+pub(crate) fn add_pauli_rotation(circuit: &mut crate::circuit::Circuit, ps: &PauliString, theta: f64)
+    -> crate::error::Result<()>
+{
+    let bits: Vec<usize> = (0..ps.nr_bits()).filter(|&i| ps.ops()[i] != PauliOp::I).collect();
+    let last = match bits.last()
+        {
+            Some(&last) => last,
+            None        => return Ok(())
+        };
+
+    // Rotate every operator to Z
+    for &bit in bits.iter()
+    {
+        match ps.ops()[bit]
+        {
+            PauliOp::X => { circuit.h(bit)?; },
+            PauliOp::Y => { circuit.sdg(bit)?; circuit.h(bit)?; },
+            _          => { }
+        }
+    }
+    // Collect the parity in the last qubit, and rotate
+    for pair in bits.windows(2)
+    {
+        circuit.cx(pair[0], pair[1])?;
+    }
+    circuit.rz(2.0 * theta, last)?;
+    for pair in bits.windows(2).rev()
+    {
+        circuit.cx(pair[0], pair[1])?;
+    }
+    for &bit in bits.iter()
+    {
+        match ps.ops()[bit]
+        {
+            PauliOp::X => { circuit.h(bit)?; },
+            PauliOp::Y => { circuit.h(bit)?; circuit.s(bit)?; },
+            _          => { }
+        }
+    }
+
+    Ok(())
+}
+
+/// Continuous time quantum walk.
+///
+/// Create a circuit implementing the continuous time quantum walk
+/// `e`<sup>`-iAt`</sup> on a graph with `n` vertices and (symmetric)
+/// adjacency matrix `adjacency`. Vertex `j` is encoded in basis state |j⟩ of
+/// `⌈log₂(n)⌉` qubits, where qubit 0 holds the most significant bit. The
+/// adjacency matrix is decomposed in Pauli strings, and the evolution is
+/// approximated using `nr_steps` first order Trotter steps. The result is
+/// exact up to a global phase when all Pauli terms commute.
+///
+/// This function panics if `adjacency` is not a square matrix.
+pub fn continuous_time_quantum_walk(adjacency: &ndarray::Array2<f64>, t: f64,
+    nr_steps: usize) -> crate::circuit::Circuit
+{
+    assert!(adjacency.rows() == adjacency.cols(), "The adjacency matrix should be square");
+
+    let nr_bits = nr_bits_for(adjacency.rows());
+    let terms = pauli_decomposition(adjacency, nr_bits);
+    let dt = t / nr_steps.max(1) as f64;
+
+    let mut circuit = crate::circuit::Circuit::new(nr_bits, 0);
+    for _ in 0..nr_steps
+    {
+        for (coef, ps) in terms.iter()
+        {
+            // All qubits are valid, so this cannot fail
+            add_pauli_rotation(&mut circuit, ps, coef * dt).unwrap();
+        }
+    }
+
+    circuit
+}
+
+/// Discrete time coined quantum walk.
+///
+/// Create a circuit implementing `nr_steps` steps of a Hadamard walk on a
+/// line of `nr_positions` positions. Qubit 0 holds the coin, the position is
+/// stored in the following `⌈log₂(nr_positions)⌉` qubits, most significant
+/// bit first. The walker starts in the middle of the line, with the coin in
+/// the symmetric state (|0⟩ + i|1⟩)/√2. Every step consists of a Hadamard
+/// coin flip, followed by a shift of the position by one place to the right
+/// if the coin is |1⟩, and to the left if it is |0⟩. The shift is implemented
+/// with (controlled) phase rotations in the Fourier basis, and wraps around
+/// at the edges of the position register, so the line is effectively closed
+/// into a cycle of `2`<sup>`⌈log₂(nr_positions)⌉`</sup> positions.
+pub fn coined_quantum_walk(nr_positions: usize, nr_steps: usize) -> crate::circuit::Circuit
+{
+    let nr_pos_bits = nr_bits_for(nr_positions);
+    let size = 1 << nr_pos_bits;
+    let bits: Vec<usize> = (1..=nr_pos_bits).collect();
+
+    let mut circuit = crate::circuit::Circuit::new(nr_pos_bits + 1, 0);
+    // All qubits are valid, so adding gates cannot fail
+    circuit.h(0).unwrap();
+    circuit.s(0).unwrap();
+    if nr_pos_bits > 0
+    {
+        circuit.x(1).unwrap();
+    }
+
+    for _ in 0..nr_steps
+    {
+        circuit.h(0).unwrap();
+
+        // Without the qubit reversal, qubit j in the Fourier basis
+        // corresponds to weight 2^j. Shift left unconditionally, and shift
+        // right twice when the coin is |1⟩.
+        crate::algorithms::qft_general(&mut circuit, &bits, false, false, 0.0).unwrap();
+        for (j, &bit) in bits.iter().enumerate()
+        {
+            let lambda = 2.0 * ::std::f64::consts::PI * 2.0f64.powi(j as i32) / size as f64;
+            circuit.u1(-lambda, bit).unwrap();
+            circuit.add_gate(crate::gates::CU1::new(2.0 * lambda), &[0, bit]).unwrap();
+        }
+        crate::algorithms::qft_general(&mut circuit, &bits, true, false, 0.0).unwrap();
+    }
+
+    circuit
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{coined_quantum_walk, continuous_time_quantum_walk, pauli_decomposition};
+    use crate::stabilizer::{PauliOp, PauliString};
+
+    /// Compute e^{-iAt}|0⟩ using a Taylor series
+    fn evolve_first_basis_state(a: &ndarray::Array2<f64>, t: f64) -> crate::cmatrix::CVector
+    {
+        let ca = a.mapv(|x| crate::cmatrix::CNumber::new(0.0, -t * x));
+        let mut term = crate::cmatrix::CVector::zeros(a.rows());
+        term[0] = crate::cmatrix::COMPLEX_ONE;
+        let mut res = term.clone();
+        for k in 1..60
+        {
+            term = ca.dot(&term) / k as f64;
+            res += &term;
+        }
+        res
+    }
+
+    /// Compute the position distribution of the walker in state `rho`
+    fn position_distribution(rho: &crate::cmatrix::CMatrix, nr_pos_bits: usize) -> Vec<f64>
+    {
+        let size = 1 << nr_pos_bits;
+        let mut probs = vec![0.0; size];
+        for idx in 0..rho.rows()
+        {
+            probs[idx % size] += rho[[idx, idx]].re;
+        }
+        probs
+    }
+
+    #[test]
+    fn test_pauli_decomposition()
+    {
+        // Cycle of four vertices is the same as the square (0-1-3-2)
+        let a = array![
+            [0.0, 1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0, 0.0]
+        ];
+        let terms = pauli_decomposition(&a, 2);
+        assert_eq!(terms.len(), 2);
+        assert!((terms[0].0 - 1.0).abs() < 1.0e-12);
+        assert_eq!(terms[0].1, PauliString::new(&[PauliOp::I, PauliOp::X]));
+        assert!((terms[1].0 - 1.0).abs() < 1.0e-12);
+        assert_eq!(terms[1].1, PauliString::new(&[PauliOp::X, PauliOp::I]));
+
+        // Padded triangle
+        let a = array![[0.0, 1.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 0.0]];
+        let terms = pauli_decomposition(&a, 2);
+        let mut m = crate::cmatrix::CMatrix::zeros((4, 4));
+        for (c, ps) in terms.iter()
+        {
+            m += &(ps.matrix() * *c);
+        }
+        for ((i, j), x) in m.indexed_iter()
+        {
+            let expected = if i < 3 && j < 3 { a[[i, j]] } else { 0.0 };
+            assert!((x.re - expected).abs() < 1.0e-12 && x.im.abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_continuous_time_quantum_walk()
+    {
+        // Commuting terms, exact in a single step
+        let a = array![
+            [0.0, 1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0, 0.0]
+        ];
+        let circuit = continuous_time_quantum_walk(&a, 0.7, 1);
+        assert_eq!(circuit.nr_qbits(), 2);
+        let rho = circuit.density_matrix().unwrap();
+        let psi = evolve_first_basis_state(&a, 0.7);
+        for ((i, j), &x) in rho.matrix().indexed_iter()
+        {
+            assert!((x - psi[i] * psi[j].conj()).norm() < 1.0e-12);
+        }
+
+        // Path graph 0-1-2-3-4, non-commuting terms
+        let a = array![
+            [0.0, 1.0, 0.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0, 0.0]
+        ];
+        let circuit = continuous_time_quantum_walk(&a, 1.2, 200);
+        assert_eq!(circuit.nr_qbits(), 3);
+        let rho = circuit.density_matrix().unwrap();
+        let psi = evolve_first_basis_state(&a, 1.2);
+        for i in 0..8
+        {
+            let p = if i < 5 { psi[i].norm_sqr() } else { 0.0 };
+            assert!((rho.matrix()[[i, i]].re - p).abs() < 1.0e-2);
+        }
+    }
+
+    #[test]
+    fn test_coined_quantum_walk_single_step()
+    {
+        // After a single step, the walker is equally likely one place left
+        // or right of the center
+        let circuit = coined_quantum_walk(8, 1);
+        assert_eq!(circuit.nr_qbits(), 4);
+        let rho = circuit.density_matrix().unwrap();
+        let probs = position_distribution(rho.matrix(), 3);
+        for (pos, &p) in probs.iter().enumerate()
+        {
+            let expected = if pos == 3 || pos == 5 { 0.5 } else { 0.0 };
+            assert!((p - expected).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_coined_quantum_walk_spread()
+    {
+        let nr_positions = 32;
+        let nr_steps = nr_positions / 2;
+        let circuit = coined_quantum_walk(nr_positions, nr_steps);
+        let rho = circuit.density_matrix().unwrap();
+        let probs = position_distribution(rho.matrix(), 5);
+
+        assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1.0e-10);
+        // Symmetric around the center
+        let center = nr_positions / 2;
+        for d in 1..center
+        {
+            assert!((probs[center - d] - probs[center + d]).abs() < 1.0e-10);
+        }
+
+        // The quantum walk spreads faster than a classical random walk,
+        // whose standard deviation is √t.
+        let var: f64 = probs.iter().enumerate()
+            .map(|(pos, &p)| p * (pos as f64 - center as f64).powi(2))
+            .sum();
+        assert!(var.sqrt() > (nr_steps as f64).sqrt());
+        // The position is spread over many positions
+        let nr_occupied = probs.iter().filter(|&&p| p > 1.0e-3).count();
+        assert!(nr_occupied as f64 >= (nr_positions as f64).sqrt());
+    }
+}